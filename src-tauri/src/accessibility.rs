@@ -0,0 +1,106 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How often the OS settings are checked again. Reading them can mean spawning a process.
+const POLL_EVERY: Duration = Duration::from_secs(15);
+
+/// The OS accessibility preferences the app adapts to.
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessibilityPrefs {
+    /// Animations and transitions should be kept to a minimum.
+    pub reduced_motion: bool,
+    pub high_contrast: bool,
+    /// A screen reader is running, so spoken output and notifications shouldn't talk over it.
+    pub screen_reader: bool,
+}
+
+/// The last preferences read from the OS, shared between the watcher thread and the command.
+#[derive(Default)]
+pub struct Accessibility(Mutex<AccessibilityPrefs>);
+
+impl Accessibility {
+    pub fn current(&self) -> AccessibilityPrefs {
+        self.0.lock().map(|p| *p).unwrap_or_default()
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run(program: &str, args: &[&str]) -> String {
+    std::process::Command::new(program).args(args).output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> AccessibilityPrefs {
+    // GNOME settings; other desktops leave these unset and everything reads as off.
+    let gsetting = |schema, key| run("gsettings", &["get", schema, key]);
+    AccessibilityPrefs {
+        reduced_motion: gsetting("org.gnome.desktop.interface", "enable-animations") == "false",
+        high_contrast: gsetting("org.gnome.desktop.a11y.interface", "high-contrast") == "true"
+            || gsetting("org.gnome.desktop.interface", "gtk-theme").contains("HighContrast"),
+        screen_reader: gsetting("org.gnome.desktop.a11y.applications", "screen-reader-enabled") == "true",
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn detect() -> AccessibilityPrefs {
+    let universal_access = |key| run("defaults", &["read", "com.apple.universalaccess", key]) == "1";
+    AccessibilityPrefs {
+        reduced_motion: universal_access("reduceMotion"),
+        high_contrast: universal_access("increaseContrast"),
+        screen_reader: universal_access("voiceOverOnOffKey"),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn detect() -> AccessibilityPrefs {
+    // One line each: high contrast, UI effects (animations) enabled, screen reader flag.
+    let script = "Add-Type -AssemblyName System.Windows.Forms; \
+        [System.Windows.Forms.SystemInformation]::HighContrast; \
+        [System.Windows.Forms.SystemInformation]::UIEffectsEnabled; \
+        (Get-ItemProperty 'HKCU:\\Control Panel\\Accessibility\\Blind Access' -ErrorAction SilentlyContinue).On";
+    let output = run("powershell", &["-NoProfile", "-Command", script]);
+    let mut lines = output.lines().map(str::trim);
+    let mut flag = |on: &str| lines.next().is_some_and(|l| l.eq_ignore_ascii_case(on));
+    let high_contrast = flag("True");
+    let effects_enabled = flag("True");
+    AccessibilityPrefs { reduced_motion: !output.is_empty() && !effects_enabled, high_contrast, screen_reader: flag("1") }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn detect() -> AccessibilityPrefs {
+    AccessibilityPrefs::default()
+}
+
+/// Starts a thread that reads the preferences and keeps re-reading them, emitting
+/// `accessibility-changed` with the new preferences whenever any of them flips, including
+/// once the first reading is in.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("accessibility-watcher".into())
+        .spawn(move || loop {
+            let detected = detect();
+            let changed = match app.state::<Accessibility>().0.lock() {
+                Ok(mut prefs) if *prefs != detected => {
+                    *prefs = detected;
+                    true
+                }
+                _ => false,
+            };
+            if changed {
+                let _ = app.emit("accessibility-changed", detected);
+            }
+            std::thread::sleep(POLL_EVERY);
+        })
+        .expect("Failed to start the accessibility watcher");
+}
+
+// ─── Accessibility Commands ───
+
+#[tauri::command]
+pub fn get_accessibility(accessibility: State<Accessibility>) -> AccessibilityPrefs {
+    accessibility.current()
+}
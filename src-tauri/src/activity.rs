@@ -0,0 +1,281 @@
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+
+use crate::bulk;
+use crate::executor::{self, RunMode};
+use crate::power::Power;
+use crate::windows;
+use crate::{DbState, read_setting, update_agent_config, write_setting};
+
+/// `"true"` to record which app is in front. Off unless the user turns it on.
+const TRACKING_SETTING: &str = "activity_tracking";
+const RETENTION_SETTING: &str = "activity_retention_days";
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+const MAX_RETENTION_DAYS: i64 = 90;
+const POLL_EVERY: std::time::Duration = std::time::Duration::from_secs(5);
+const PURGE_EVERY: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// An agent is offered for the same app at most this often.
+const TRIGGER_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// The app in front and its window title.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ActiveWindow {
+    pub app: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivitySettings {
+    pub enabled: bool,
+    pub retention_days: i64,
+}
+
+/// Time spent in one app over the period asked for.
+#[derive(Debug, Serialize, Clone)]
+pub struct AppUsage {
+    pub app: String,
+    pub seconds: i64,
+    pub sessions: i64,
+    pub last_used_at: String,
+}
+
+/// Apps that bring an agent up when they come to the front, e.g. `["excel"]` to offer
+/// importing today's report. With `auto_run` the agent runs instead of being offered.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AppTrigger {
+    #[serde(default)]
+    pub apps: Vec<String>,
+    #[serde(default)]
+    pub auto_run: bool,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS app_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app TEXT NOT NULL,
+            title TEXT DEFAULT '',
+            started_at TEXT NOT NULL,
+            ended_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_app_usage_started ON app_usage(started_at);
+    ").expect("Failed to initialize activity tables");
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    std::process::Command::new(program).args(args).output().ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+pub fn active_window() -> Option<ActiveWindow> {
+    // X11 only; Wayland compositors don't tell other apps what is in front.
+    let title = run("xdotool", &["getactivewindow", "getwindowname"])?;
+    let app = run("xdotool", &["getactivewindow", "getwindowpid"])
+        .and_then(|pid| std::fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+        .map(|comm| comm.trim().to_string())
+        .unwrap_or_default();
+    Some(ActiveWindow { app, title })
+}
+
+#[cfg(target_os = "macos")]
+pub fn active_window() -> Option<ActiveWindow> {
+    let out = run("osascript", &["-e", "tell application \"System Events\" to set p to first process whose frontmost is true\n\
+        set t to \"\"\n\
+        try\n\
+        tell p to set t to name of front window\n\
+        end try\n\
+        return (name of p) & linefeed & t"])?;
+    let (app, title) = out.split_once('\n').unwrap_or((&out, ""));
+    Some(ActiveWindow { app: app.to_string(), title: title.to_string() })
+}
+
+#[cfg(target_os = "windows")]
+pub fn active_window() -> Option<ActiveWindow> {
+    let out = run("powershell", &["-NoProfile", "-Command",
+        "Add-Type 'using System; using System.Text; using System.Runtime.InteropServices; \
+         public class W { [DllImport(\"user32.dll\")] public static extern IntPtr GetForegroundWindow(); \
+         [DllImport(\"user32.dll\")] public static extern int GetWindowText(IntPtr h, StringBuilder s, int n); \
+         [DllImport(\"user32.dll\")] public static extern uint GetWindowThreadProcessId(IntPtr h, out uint p); }'; \
+         $h = [W]::GetForegroundWindow(); $s = New-Object System.Text.StringBuilder 512; [void][W]::GetWindowText($h, $s, 512); \
+         $p = 0; [void][W]::GetWindowThreadProcessId($h, [ref]$p); (Get-Process -Id $p).ProcessName; $s.ToString()"])?;
+    let (app, title) = out.split_once('\n').unwrap_or((&out, ""));
+    Some(ActiveWindow { app: app.trim().to_string(), title: title.trim().to_string() })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn active_window() -> Option<ActiveWindow> {
+    None
+}
+
+fn settings(conn: &Connection) -> Result<ActivitySettings, String> {
+    Ok(ActivitySettings {
+        enabled: read_setting(conn, TRACKING_SETTING)?.as_deref() == Some("true"),
+        retention_days: read_setting(conn, RETENTION_SETTING)?
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(DEFAULT_RETENTION_DAYS)
+            .clamp(1, MAX_RETENTION_DAYS),
+    })
+}
+
+fn purge(conn: &Connection) -> Result<(), String> {
+    let cutoff = Utc::now() - Duration::days(settings(conn)?.retention_days);
+    conn.execute("DELETE FROM app_usage WHERE ended_at < ?1", params![cutoff.to_rfc3339()]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Agents with an app trigger matching `app`, as (id, name, auto_run).
+fn triggered(conn: &Connection, app: &str) -> Result<Vec<(String, String, bool)>, String> {
+    let mut stmt = conn.prepare("SELECT id, name, config_json FROM agents").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| e.to_string())?;
+    let app = app.to_lowercase();
+    Ok(rows.filter_map(|row| row.ok()).filter(|(_, _, config)| bulk::enabled(config)).filter_map(|(id, name, config)| {
+        let trigger: AppTrigger = serde_json::from_str::<Value>(&config).ok()?
+            .get("app_trigger")
+            .and_then(|t| serde_json::from_value(t.clone()).ok())?;
+        trigger.apps.iter().any(|a| !a.trim().is_empty() && app.contains(&a.trim().to_lowercase()))
+            .then_some((id, name, trigger.auto_run))
+    }).collect())
+}
+
+/// Offers or runs the agents that trigger on `window`'s app, each at most once per cooldown.
+fn fire(app: &AppHandle, window: &ActiveWindow, fired: &mut HashMap<(String, String), Instant>) {
+    let agents = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+        triggered(&conn, &window.app).unwrap_or_default()
+    };
+    for (agent_id, name, auto_run) in agents {
+        let key = (agent_id.clone(), window.app.to_lowercase());
+        if fired.get(&key).is_some_and(|at| at.elapsed() < TRIGGER_COOLDOWN) {
+            continue;
+        }
+        fired.insert(key, Instant::now());
+        if auto_run {
+            if let Err(e) = executor::execute_agent(app, &agent_id, RunMode::Live, "app") {
+                eprintln!("App-triggered run of {agent_id} didn't start: {e}");
+            }
+        } else {
+            windows::broadcast(app, "app-trigger-offer", &json!({ "agent_id": agent_id, "agent_name": name, "app": window.app }));
+        }
+    }
+}
+
+/// Starts the thread that, while tracking is on, records the app in front and brings up
+/// agents that trigger on it. Nothing is read while tracking is off; old records are purged
+/// past the retention period.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("activity-watcher".into())
+        .spawn(move || {
+            let mut current: Option<(ActiveWindow, i64)> = None;
+            let mut fired = HashMap::new();
+            let mut purged = Instant::now() - PURGE_EVERY;
+            let mut resumes = app.state::<Power>().resumes();
+            loop {
+                std::thread::sleep(POLL_EVERY);
+                // Time asleep isn't time in the app that was in front.
+                if app.state::<Power>().resumes() != resumes {
+                    resumes = app.state::<Power>().resumes();
+                    current = None;
+                }
+                let enabled = {
+                    let db = app.state::<DbState>();
+                    let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                    if purged.elapsed() >= PURGE_EVERY {
+                        let _ = purge(&conn);
+                        purged = Instant::now();
+                    }
+                    settings(&conn).is_ok_and(|s| s.enabled)
+                };
+                if !enabled {
+                    current = None;
+                    continue;
+                }
+                let Some(window) = active_window().filter(|w| !w.app.is_empty()) else { continue };
+                let now = Utc::now().to_rfc3339();
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                match &current {
+                    Some((last, id)) if last.app == window.app => {
+                        let _ = conn.execute("UPDATE app_usage SET ended_at = ?1, title = ?2 WHERE id = ?3", params![now, window.title, id]);
+                    }
+                    _ => {
+                        let inserted = conn.execute(
+                            "INSERT INTO app_usage (app, title, started_at, ended_at) VALUES (?1, ?2, ?3, ?3)",
+                            params![window.app, window.title, now],
+                        );
+                        let id = conn.last_insert_rowid();
+                        drop(conn);
+                        if inserted.is_ok() {
+                            fire(&app, &window, &mut fired);
+                            current = Some((window, id));
+                        }
+                    }
+                }
+            }
+        })
+        .expect("Failed to start the activity watcher");
+}
+
+// ─── Activity Commands ───
+
+#[tauri::command]
+pub fn get_activity_settings(db: State<DbState>) -> Result<ActivitySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings(&conn)
+}
+
+#[tauri::command]
+pub fn set_activity_settings(db: State<DbState>, settings: ActivitySettings) -> Result<ActivitySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, TRACKING_SETTING, if settings.enabled { "true" } else { "false" })?;
+    write_setting(&conn, RETENTION_SETTING, &settings.retention_days.clamp(1, MAX_RETENTION_DAYS).to_string())?;
+    purge(&conn)?;
+    self::settings(&conn)
+}
+
+/// Time per app over the last `days` (a week by default), most used first.
+#[tauri::command]
+pub fn get_app_usage(db: State<DbState>, days: Option<i64>) -> Result<Vec<AppUsage>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let since = Utc::now() - Duration::days(days.unwrap_or(7).clamp(1, MAX_RETENTION_DAYS));
+    let mut stmt = conn.prepare(
+        "SELECT app, CAST(SUM((julianday(ended_at) - julianday(started_at)) * 86400) AS INTEGER), COUNT(*), MAX(ended_at)
+         FROM app_usage WHERE started_at >= ?1 GROUP BY app ORDER BY 2 DESC"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![since.to_rfc3339()], |row| Ok(AppUsage {
+        app: row.get(0)?,
+        seconds: row.get(1)?,
+        sessions: row.get(2)?,
+        last_used_at: row.get(3)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_app_usage(db: State<DbState>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM app_usage", []).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The apps that bring up an agent; an empty list removes the trigger. Needs activity
+/// tracking on to have any effect.
+#[tauri::command]
+pub fn set_agent_app_trigger(db: State<DbState>, agent_id: String, trigger: AppTrigger) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let apps: Vec<String> = trigger.apps.iter().map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+    update_agent_config(&conn, &agent_id, |config| match apps.is_empty() {
+        true => { config.remove("app_trigger"); }
+        false => { config.insert("app_trigger".into(), json!({ "apps": apps, "auto_run": trigger.auto_run })); }
+    })
+}
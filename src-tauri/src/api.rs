@@ -0,0 +1,297 @@
+use rand::RngCore;
+use rand::rngs::OsRng;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+use tiny_http::{Header, Method, Request, Response, Server};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::executor::{self, RunMode, RUN_COLUMNS};
+use crate::routing;
+use crate::{AGENT_COLUMNS, DbState, query_logs, read_setting, row_to_agent};
+
+/// The API only starts when this setting is `"true"`; changes apply on the next launch.
+const ENABLED_SETTING: &str = "local_api_enabled";
+const PORT_SETTING: &str = "local_api_port";
+/// One above the OpenClaw gateway's default port.
+const DEFAULT_PORT: u16 = 18790;
+const TOKEN_PREFIX: &str = "ocl_";
+
+/// `Admin` tokens may trigger runs; `Observer` tokens can only read status, runs and logs.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Admin,
+    Observer,
+}
+
+impl TokenScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Admin => "admin",
+            TokenScope::Observer => "observer",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scope: String,
+    /// First characters of the token, so the user can tell tokens apart.
+    pub hint: String,
+    pub created_at: String,
+    /// Empty for tokens that never expire.
+    pub expires_at: String,
+    pub last_used_at: String,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MintedToken {
+    /// Shown once; only its hash is stored.
+    pub token: String,
+    pub info: ApiToken,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS api_tokens (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            hint TEXT DEFAULT '',
+            created_at TEXT NOT NULL,
+            expires_at TEXT DEFAULT '',
+            last_used_at TEXT DEFAULT '',
+            revoked INTEGER DEFAULT 0
+        );
+    ").expect("Failed to initialize API token tables");
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+const TOKEN_COLUMNS: &str = "id, name, scope, hint, created_at, expires_at, last_used_at, revoked";
+
+fn row_to_token(row: &rusqlite::Row) -> rusqlite::Result<ApiToken> {
+    Ok(ApiToken {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        scope: row.get(2)?,
+        hint: row.get(3)?,
+        created_at: row.get(4)?,
+        expires_at: row.get(5)?,
+        last_used_at: row.get(6)?,
+        revoked: row.get::<_, i32>(7)? != 0,
+    })
+}
+
+/// Scope of a valid, unexpired, unrevoked token; also records that it was used.
+fn authenticate(conn: &Connection, token: &str) -> Result<Option<TokenScope>, String> {
+    let found: Option<(String, String, String)> = conn.query_row(
+        "SELECT id, scope, expires_at FROM api_tokens WHERE token_hash = ?1 AND revoked = 0",
+        params![hash_token(token)],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).optional().map_err(|e| e.to_string())?;
+    let Some((id, scope, expires_at)) = found else {
+        return Ok(None);
+    };
+    let now = Utc::now().to_rfc3339();
+    if !expires_at.is_empty() && expires_at <= now {
+        return Ok(None);
+    }
+    conn.execute("UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2", params![now, id])
+        .map_err(|e| e.to_string())?;
+    Ok(Some(if scope == "admin" { TokenScope::Admin } else { TokenScope::Observer }))
+}
+
+fn port(conn: &Connection) -> u16 {
+    read_setting(conn, PORT_SETTING).ok().flatten()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT)
+}
+
+/// Where the API listens, or `None` when it is turned off.
+pub fn base_url(conn: &Connection) -> Result<Option<String>, String> {
+    if read_setting(conn, ENABLED_SETTING)?.as_deref() != Some("true") {
+        return Ok(None);
+    }
+    Ok(Some(format!("http://127.0.0.1:{}", port(conn))))
+}
+
+// ─── HTTP Server ───
+
+/// Starts the API on 127.0.0.1 in a background thread, if the user turned it on.
+pub fn start(app: AppHandle) {
+    let port = {
+        let db = app.state::<DbState>();
+        let Ok(conn) = db.0.lock() else { return };
+        if read_setting(&conn, ENABLED_SETTING).ok().flatten().as_deref() != Some("true") {
+            return;
+        }
+        port(&conn)
+    };
+    std::thread::spawn(move || {
+        let server = match Server::http(("127.0.0.1", port)) {
+            Ok(server) => server,
+            Err(e) => {
+                eprintln!("Local API could not listen on port {port}: {e}");
+                return;
+            }
+        };
+        for request in server.incoming_requests() {
+            // Decision links are signed, so they need no token; they answer with a page.
+            let (status, body, content_type) = match routing::handle_link(&app, &request) {
+                Some((status, html)) => (status, html, "text/html; charset=utf-8"),
+                None => {
+                    let (status, body) = handle(&app, &request);
+                    (status, body.to_string(), "application/json")
+                }
+            };
+            let response = Response::from_string(body)
+                .with_status_code(status)
+                .with_header(Header::from_bytes("Content-Type", content_type).expect("static header"));
+            let _ = request.respond(response);
+        }
+    });
+}
+
+fn handle(app: &AppHandle, request: &Request) -> (u16, Value) {
+    let token = request.headers().iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or_default()
+        .to_string();
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let db = app.state::<DbState>();
+    let scope = {
+        let Ok(conn) = db.0.lock() else { return (500, json!({ "error": "Database unavailable" })) };
+        match authenticate(&conn, &token) {
+            Ok(Some(scope)) => scope,
+            Ok(None) => return (401, json!({ "error": "Missing, expired or revoked token" })),
+            Err(e) => return (500, json!({ "error": e })),
+        }
+    };
+    let param = |name: &str| query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.to_string());
+
+    let result = match (request.method(), segments.as_slice()) {
+        (Method::Get, ["api", "status"]) => read(app, |conn| {
+            let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).map_err(|e| e.to_string());
+            Ok(json!({
+                "agents": count("SELECT COUNT(*) FROM agents")?,
+                "running": count("SELECT COUNT(*) FROM runs WHERE status = 'running'")?,
+                "awaiting_approval": count("SELECT COUNT(*) FROM approval_queue WHERE status = 'pending'")?,
+            }))
+        }),
+        (Method::Get, ["api", "agents"]) => read(app, |conn| {
+            let mut stmt = conn.prepare(&format!("SELECT {AGENT_COLUMNS} FROM agents ORDER BY created_at DESC"))
+                .map_err(|e| e.to_string())?;
+            let agents = stmt.query_map([], row_to_agent).map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+            Ok(json!(agents))
+        }),
+        (Method::Get, ["api", "logs"]) => read(app, |conn| {
+            let limit = param("limit").and_then(|l| l.parse().ok()).unwrap_or(100);
+            Ok(json!(query_logs(conn, limit)?))
+        }),
+        (Method::Get, ["api", "runs"]) => read(app, |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {RUN_COLUMNS} FROM runs WHERE (?1 IS NULL OR agent_id = ?1) ORDER BY started_at DESC LIMIT 50"
+            )).map_err(|e| e.to_string())?;
+            let runs = stmt.query_map(params![param("agent_id")], executor::row_to_run).map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+            Ok(json!(runs))
+        }),
+        (Method::Post, ["api", "agents", id, "run"]) => {
+            if scope != TokenScope::Admin {
+                return (403, json!({ "error": "Observer tokens can't start runs" }));
+            }
+            let mode = if param("mode").as_deref() == Some("simulate") { RunMode::Simulate } else { RunMode::Live };
+            let (app, id) = (app.clone(), id.to_string());
+            // Runs can take minutes; the caller polls /api/runs for the result.
+            std::thread::spawn(move || {
+                if let Err(e) = executor::execute_agent(&app, &id, mode, "api") {
+                    eprintln!("API-triggered run of {id} failed: {e}");
+                }
+            });
+            return (202, json!({ "status": "started" }));
+        }
+        _ => return (404, json!({ "error": "Not found" })),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(e) => (500, json!({ "error": e })),
+    }
+}
+
+fn read(app: &AppHandle, f: impl FnOnce(&Connection) -> Result<Value, String>) -> Result<Value, String> {
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    f(&conn)
+}
+
+// ─── API Token Commands ───
+
+#[tauri::command]
+pub fn mint_api_token(
+    db: State<DbState>,
+    name: String,
+    scope: TokenScope,
+    expires_in_days: Option<i64>,
+) -> Result<MintedToken, String> {
+    let mut secret = [0u8; 24];
+    OsRng.fill_bytes(&mut secret);
+    let token = format!("{TOKEN_PREFIX}{}", secret.iter().map(|b| format!("{b:02x}")).collect::<String>());
+    let now = Utc::now();
+    let info = ApiToken {
+        id: Uuid::new_v4().to_string(),
+        name,
+        scope: scope.as_str().into(),
+        hint: token[..TOKEN_PREFIX.len() + 6].to_string(),
+        created_at: now.to_rfc3339(),
+        expires_at: expires_in_days.filter(|d| *d > 0).map(|d| (now + Duration::days(d)).to_rfc3339()).unwrap_or_default(),
+        last_used_at: String::new(),
+        revoked: false,
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO api_tokens (id, name, scope, token_hash, hint, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![info.id, info.name, info.scope, hash_token(&token), info.hint, info.created_at, info.expires_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(MintedToken { token, info })
+}
+
+#[tauri::command]
+pub fn list_api_tokens(db: State<DbState>) -> Result<Vec<ApiToken>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!("SELECT {TOKEN_COLUMNS} FROM api_tokens ORDER BY created_at DESC"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_token).map_err(|e| e.to_string())?;
+
+    let mut tokens = Vec::new();
+    for row in rows {
+        tokens.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(tokens)
+}
+
+#[tauri::command]
+pub fn revoke_api_token(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("UPDATE api_tokens SET revoked = 1 WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
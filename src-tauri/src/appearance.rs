@@ -0,0 +1,120 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Theme};
+
+use crate::{DbState, read_setting, write_setting};
+
+const SETTING: &str = "appearance";
+const MAIN_WINDOW: &str = "main";
+/// Font scale limits, so text never becomes unreadable or breaks the layout.
+const MIN_FONT_SCALE: f64 = 0.8;
+const MAX_FONT_SCALE: f64 = 1.6;
+
+/// Appearance preferences, stored as JSON under `appearance`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    /// `light`, `dark` or `system`.
+    pub theme: String,
+    /// `#rrggbb`; empty for the default accent.
+    pub accent_color: String,
+    /// Multiplies the base font size, 0.8 to 1.6.
+    pub font_scale: f64,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        AppearanceSettings { theme: "system".into(), accent_color: String::new(), font_scale: 1.0 }
+    }
+}
+
+/// The settings plus the theme actually in use, with `system` resolved from the OS.
+#[derive(Debug, Serialize, Clone)]
+pub struct Appearance {
+    #[serde(flatten)]
+    pub settings: AppearanceSettings,
+    /// `light` or `dark`.
+    pub effective_theme: String,
+}
+
+fn load(conn: &Connection) -> Result<AppearanceSettings, String> {
+    Ok(read_setting(conn, SETTING)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+/// The OS theme as the main window sees it; light when it can't tell.
+fn os_theme(app: &AppHandle) -> &'static str {
+    match app.get_webview_window(MAIN_WINDOW).and_then(|w| w.theme().ok()) {
+        Some(Theme::Dark) => "dark",
+        _ => "light",
+    }
+}
+
+fn resolve(app: &AppHandle, settings: AppearanceSettings) -> Appearance {
+    let effective_theme = match settings.theme.as_str() {
+        "light" | "dark" => settings.theme.clone(),
+        _ => os_theme(app).to_string(),
+    };
+    Appearance { settings, effective_theme }
+}
+
+fn valid_accent(color: &str) -> bool {
+    color.is_empty() || (color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn current(app: &AppHandle) -> Result<Appearance, String> {
+    let settings = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load(&conn)?
+    };
+    Ok(resolve(app, settings))
+}
+
+/// Tells every window the appearance changed, after a settings change or when the OS theme
+/// flips while the theme follows the system.
+pub fn announce(app: &AppHandle) {
+    if let Ok(appearance) = current(app) {
+        let _ = app.emit("appearance-changed", appearance);
+    }
+}
+
+/// Called when a window reports an OS theme change.
+pub fn os_theme_changed(app: &AppHandle) {
+    let follows_system = {
+        let db = app.state::<DbState>();
+        let Ok(conn) = db.0.lock() else { return };
+        load(&conn).map(|s| s.theme == "system").unwrap_or(true)
+    };
+    if follows_system {
+        announce(app);
+    }
+}
+
+// ─── Appearance Commands ───
+
+#[tauri::command]
+pub fn get_appearance(app: AppHandle) -> Result<Appearance, String> {
+    current(&app)
+}
+
+/// Saves appearance preferences; open windows get an `appearance-changed` event.
+#[tauri::command]
+pub fn set_appearance(app: AppHandle, db: State<DbState>, settings: AppearanceSettings) -> Result<Appearance, String> {
+    if !matches!(settings.theme.as_str(), "light" | "dark" | "system") {
+        return Err(format!("Unknown theme: {}", settings.theme));
+    }
+    if !valid_accent(&settings.accent_color) {
+        return Err("Give the accent color as #rrggbb".into());
+    }
+    if !(MIN_FONT_SCALE..=MAX_FONT_SCALE).contains(&settings.font_scale) {
+        return Err(format!("Font scale must be between {MIN_FONT_SCALE} and {MAX_FONT_SCALE}"));
+    }
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, SETTING, &serde_json::to_string(&settings).map_err(|e| e.to_string())?)?;
+    }
+    announce(&app);
+    current(&app)
+}
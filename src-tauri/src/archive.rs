@@ -0,0 +1,303 @@
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::crypto::{self, Sealed};
+use crate::datadir::DATABASE_FILE;
+use crate::windows;
+use crate::{DbState, Setting, app_data_dir, init_db};
+
+const MAGIC: &[u8] = b"OPENCLAW-ARCHIVE\n";
+const ARCHIVE_FORMAT: &str = "openclaw-everything";
+/// The database's name inside the archive; it is restored through SQLite, not as a file.
+const DATABASE_ENTRY: &str = "database.sqlite";
+const MIN_PASSPHRASE: usize = 8;
+/// Tied to a key in this machine's keychain, so useless anywhere else.
+const MACHINE_FILES: &[&str] = &["companion-cert.der"];
+
+/// Unencrypted first line after the magic: what is needed to decrypt the rest.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    kdf_iterations: u32,
+    salt: String,
+    nonce: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedFile {
+    /// Relative to the data folder, with `/` separators.
+    pub path: String,
+    pub size: u64,
+}
+
+/// Encrypted inside the archive, followed by each file's bytes in order.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Manifest {
+    pub created_at: String,
+    pub app_version: String,
+    /// A copy for reading without restoring; the database holds the same values.
+    pub settings: Vec<Setting>,
+    /// OpenClaw plugins that were installed, to reinstall on the new machine.
+    pub plugins: Vec<String>,
+    pub files: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveSummary {
+    pub path: String,
+    pub created_at: String,
+    pub app_version: String,
+    pub settings: usize,
+    pub plugins: Vec<String>,
+    /// Files besides the database.
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RestoreResult {
+    pub summary: ArchiveSummary,
+    /// Where the database from before the import was kept, to go back to by hand.
+    pub previous_database: String,
+    pub files_restored: usize,
+    /// Plugins in the archive that aren't installed here.
+    pub missing_plugins: Vec<String>,
+}
+
+fn installed_plugins() -> Vec<String> {
+    std::process::Command::new("openclaw").args(["plugins", "list", "--json"]).output().ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| serde_json::from_slice::<serde_json::Value>(&out.stdout).ok())
+        .and_then(|list| {
+            let items = list.get("plugins").unwrap_or(&list).as_array()?.clone();
+            Some(items.iter().filter_map(|p| p.as_str().or_else(|| p.get("id").and_then(|id| id.as_str())).map(String::from)).collect())
+        })
+        .unwrap_or_default()
+}
+
+/// Every file in the data folder except the live database, its journals and `MACHINE_FILES`.
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<(String, PathBuf)>) -> Result<(), String> {
+    for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if dir == root && (name.starts_with(DATABASE_FILE) || MACHINE_FILES.contains(&name.as_str())) {
+            continue;
+        }
+        if entry.file_type().map_err(|e| e.to_string())?.is_dir() {
+            collect_files(root, &path, out)?;
+        } else {
+            let relative = path.strip_prefix(root).map_err(|e| e.to_string())?;
+            let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            out.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+/// Where an archived path goes under `root`, refusing anything that would land outside it.
+fn restore_path(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative);
+    if relative.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("The archive has an unsafe file path: {relative}"));
+    }
+    Ok(root.join(path))
+}
+
+fn summary(path: &str, manifest: &Manifest) -> ArchiveSummary {
+    ArchiveSummary {
+        path: path.into(),
+        created_at: manifest.created_at.clone(),
+        app_version: manifest.app_version.clone(),
+        settings: manifest.settings.len(),
+        plugins: manifest.plugins.clone(),
+        files: manifest.files.iter().filter(|f| f.path != DATABASE_ENTRY).count(),
+        bytes: manifest.files.iter().map(|f| f.size).sum(),
+    }
+}
+
+/// Decrypts an archive into its manifest and the bytes of each file.
+fn read(path: &str, passphrase: &str) -> Result<(Manifest, Vec<Vec<u8>>), String> {
+    let raw = std::fs::read(path).map_err(|e| format!("Couldn't read {path}: {e}"))?;
+    let rest = raw.strip_prefix(MAGIC).ok_or("This isn't an OpenClaw archive")?;
+    let newline = rest.iter().position(|b| *b == b'\n').ok_or("The archive is damaged")?;
+    let header: Header = serde_json::from_slice(&rest[..newline]).map_err(|_| "The archive is damaged")?;
+    if header.format != ARCHIVE_FORMAT || header.version != 1 {
+        return Err("This archive was made by a newer version of the app".into());
+    }
+    let field = |s: &str| B64.decode(s).map_err(|_| "The archive is damaged".to_string());
+    let plaintext = crypto::open(passphrase, &Sealed {
+        kdf_iterations: header.kdf_iterations,
+        salt: field(&header.salt)?,
+        nonce: field(&header.nonce)?,
+        ciphertext: rest[newline + 1..].to_vec(),
+    })?;
+
+    let damaged = || "The archive is damaged".to_string();
+    let manifest_len = u32::from_be_bytes(plaintext.get(..4).ok_or_else(damaged)?.try_into().map_err(|_| damaged())?) as usize;
+    let manifest: Manifest = serde_json::from_slice(plaintext.get(4..4 + manifest_len).ok_or_else(damaged)?).map_err(|_| damaged())?;
+    let mut offset = 4 + manifest_len;
+    let mut contents = Vec::new();
+    for file in &manifest.files {
+        let end = offset + file.size as usize;
+        contents.push(plaintext.get(offset..end).ok_or_else(damaged)?.to_vec());
+        offset = end;
+    }
+    Ok((manifest, contents))
+}
+
+fn export(app: &AppHandle, path: &str, passphrase: &str) -> Result<ArchiveSummary, String> {
+    let root = app_data_dir();
+    let snapshot = std::env::temp_dir().join(format!("openclaw-export-{}.db", uuid::Uuid::new_v4()));
+    let settings = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute("VACUUM INTO ?1", [snapshot.display().to_string()]).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| Ok(Setting { key: row.get(0)?, value: row.get(1)? })).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?
+    };
+    let database = std::fs::read(&snapshot).map_err(|e| e.to_string());
+    let _ = std::fs::remove_file(&snapshot);
+    let database = database?;
+
+    let mut files = Vec::new();
+    collect_files(&root, &root, &mut files)?;
+    let mut entries = vec![(DATABASE_ENTRY.to_string(), database)];
+    for (name, file) in files {
+        let bytes = std::fs::read(&file).map_err(|e| format!("Couldn't read {}: {e}", file.display()))?;
+        entries.push((name, bytes));
+    }
+    let manifest = Manifest {
+        created_at: Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        settings,
+        plugins: installed_plugins(),
+        files: entries.iter().map(|(path, bytes)| ArchivedFile { path: path.clone(), size: bytes.len() as u64 }).collect(),
+    };
+    let manifest_json = serde_json::to_vec(&manifest).map_err(|e| e.to_string())?;
+    let mut plaintext = (manifest_json.len() as u32).to_be_bytes().to_vec();
+    plaintext.extend_from_slice(&manifest_json);
+    for (_, bytes) in &entries {
+        plaintext.extend_from_slice(bytes);
+    }
+    let sealed = crypto::seal(passphrase, &plaintext)?;
+    drop(plaintext);
+
+    let header = serde_json::to_vec(&Header {
+        format: ARCHIVE_FORMAT.into(),
+        version: 1,
+        kdf_iterations: sealed.kdf_iterations,
+        salt: B64.encode(&sealed.salt),
+        nonce: B64.encode(&sealed.nonce),
+    }).map_err(|e| e.to_string())?;
+    // Written beside and renamed over, so a failed export never leaves a partial archive.
+    let target = PathBuf::from(path);
+    let partial = target.with_extension("partial");
+    let mut out = std::fs::File::create(&partial).map_err(|e| format!("Couldn't write {path}: {e}"))?;
+    [MAGIC, &header, b"\n", &sealed.ciphertext].iter()
+        .try_for_each(|part| out.write_all(part))
+        .and_then(|_| out.sync_all())
+        .map_err(|e| format!("Couldn't write {path}: {e}"))?;
+    std::fs::rename(&partial, &target).map_err(|e| format!("Couldn't write {path}: {e}"))?;
+    Ok(summary(path, &manifest))
+}
+
+fn import(app: &AppHandle, path: &str, passphrase: &str) -> Result<RestoreResult, String> {
+    let (manifest, contents) = read(path, passphrase)?;
+    let root = app_data_dir();
+    let database = manifest.files.iter().position(|f| f.path == DATABASE_ENTRY).ok_or("The archive has no database")?;
+    // Check every path before touching anything.
+    for file in manifest.files.iter().filter(|f| f.path != DATABASE_ENTRY) {
+        restore_path(&root, &file.path)?;
+    }
+    let incoming = root.join(format!("{DATABASE_FILE}.incoming"));
+    std::fs::write(&incoming, &contents[database]).map_err(|e| e.to_string())?;
+    if let Err(e) = Connection::open(&incoming).and_then(|c| c.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0)))
+        .map_err(|e| e.to_string())
+        .and_then(|result| if result == "ok" { Ok(()) } else { Err(result) }) {
+        let _ = std::fs::remove_file(&incoming);
+        return Err(format!("The database in the archive is damaged: {e}"));
+    }
+
+    let live = root.join(DATABASE_FILE);
+    let previous = root.join(format!("{DATABASE_FILE}.before-import-{}", Utc::now().format("%Y%m%d-%H%M%S")));
+    {
+        let db = app.state::<DbState>();
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute("VACUUM INTO ?1", [previous.display().to_string()]).map_err(|e| e.to_string())?;
+        // Close the live database so its file can be replaced on every platform.
+        *conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+        for suffix in ["-wal", "-shm", "-journal"] {
+            let _ = std::fs::remove_file(root.join(format!("{DATABASE_FILE}{suffix}")));
+        }
+        let swapped = std::fs::rename(&incoming, &live).map_err(|e| e.to_string());
+        let restored = Connection::open(&live).map_err(|e| e.to_string());
+        match (swapped, restored) {
+            (Ok(()), Ok(restored)) => {
+                // Brings an archive from an older version up to this one's tables.
+                init_db(&restored);
+                *conn = restored;
+            }
+            (swapped, _) => {
+                *conn = Connection::open(&live).map_err(|e| e.to_string())?;
+                return Err(format!("Couldn't restore the database: {}", swapped.err().unwrap_or_else(|| "it wouldn't open".into())));
+            }
+        }
+    }
+
+    let mut files_restored = 0;
+    for (file, bytes) in manifest.files.iter().zip(&contents).filter(|(f, _)| f.path != DATABASE_ENTRY) {
+        let target = restore_path(&root, &file.path)?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&target, bytes).map_err(|e| format!("Couldn't restore {}: {e}", file.path))?;
+        files_restored += 1;
+    }
+    let installed = installed_plugins();
+    let missing_plugins = manifest.plugins.iter().filter(|p| !installed.contains(p)).cloned().collect();
+    windows::broadcast(app, "everything-imported", &path);
+    Ok(RestoreResult {
+        summary: summary(path, &manifest),
+        previous_database: previous.display().to_string(),
+        files_restored,
+        missing_plugins,
+    })
+}
+
+// ─── Archive Commands ───
+
+/// Writes one encrypted archive of everything the app keeps: a consistent copy of the
+/// database (agents, runs, artifacts, settings), the files in the data folder and the
+/// installed plugins. Passwords kept in the system keychain aren't included.
+#[tauri::command]
+pub async fn export_everything(app: AppHandle, path: String, passphrase: String) -> Result<ArchiveSummary, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE {
+        return Err(format!("Use a passphrase of at least {MIN_PASSPHRASE} characters"));
+    }
+    tauri::async_runtime::spawn_blocking(move || export(&app, &path, &passphrase)).await.map_err(|e| e.to_string())?
+}
+
+/// What an archive holds, without restoring it.
+#[tauri::command]
+pub async fn inspect_archive(path: String, passphrase: String) -> Result<ArchiveSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || read(&path, &passphrase).map(|(manifest, _)| summary(&path, &manifest)))
+        .await.map_err(|e| e.to_string())?
+}
+
+/// Replaces this machine's data with the archive's. The current database is kept beside
+/// the restored one first; restart the app afterwards so everything picks up the restored
+/// state.
+#[tauri::command]
+pub async fn import_everything(app: AppHandle, path: String, passphrase: String) -> Result<RestoreResult, String> {
+    tauri::async_runtime::spawn_blocking(move || import(&app, &path, &passphrase)).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,142 @@
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::executor::QUICK_TASK_AGENT;
+use crate::sanitize;
+use crate::DbState;
+
+/// Intermediate output a run produced along the way, kept so the user can see how a result
+/// was reached.
+#[derive(Debug, Serialize, Clone)]
+pub struct Artifact {
+    pub id: String,
+    pub run_id: String,
+    pub agent_id: String,
+    pub step_index: i64,
+    /// e.g. `chunk_summary`, `merged_summary`, `critique`, `revision`, `transcript`.
+    pub kind: String,
+    pub name: String,
+    /// Empty in listings; fetch one artifact to read it.
+    pub content: String,
+    pub size: i64,
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS run_artifacts (
+            id TEXT PRIMARY KEY,
+            run_id TEXT NOT NULL,
+            agent_id TEXT DEFAULT '',
+            step_index INTEGER DEFAULT 0,
+            kind TEXT NOT NULL,
+            name TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_run_artifacts_run ON run_artifacts(run_id);
+    ").expect("Failed to initialize artifact tables");
+}
+
+pub fn record(
+    conn: &Connection,
+    run_id: &str,
+    agent_id: &str,
+    step_index: usize,
+    kind: &str,
+    name: &str,
+    content: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO run_artifacts (id, run_id, agent_id, step_index, kind, name, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![Uuid::new_v4().to_string(), run_id, agent_id, step_index as i64, kind, name, content, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Artifacts nothing refers to any more: their run is gone, or the agent that made them was
+/// deleted. Quick-task artifacts belong to no agent row and stay with their run; artifacts
+/// made outside any run, like transcripts of dropped recordings, have no run to lose.
+const ORPHANED: &str = "(run_id != '' AND run_id NOT IN (SELECT id FROM runs))
+    OR (agent_id NOT IN ('', ?1) AND agent_id NOT IN (SELECT id FROM agents))";
+
+/// Deletes orphaned artifacts; called wherever agents or runs are removed.
+pub fn remove_orphans(conn: &Connection) -> Result<usize, String> {
+    conn.execute(&format!("DELETE FROM run_artifacts WHERE {ORPHANED}"), params![QUICK_TASK_AGENT])
+        .map_err(|e| e.to_string())
+}
+
+/// Orphaned artifacts, found and, unless this is a dry run, deleted.
+#[derive(Debug, Serialize, Clone)]
+pub struct OrphanReport {
+    /// Listing form, without content.
+    pub artifacts: Vec<Artifact>,
+    pub bytes: i64,
+    pub deleted: bool,
+}
+
+fn row_to_artifact(row: &rusqlite::Row) -> rusqlite::Result<Artifact> {
+    Ok(Artifact {
+        id: row.get(0)?,
+        run_id: row.get(1)?,
+        agent_id: row.get(2)?,
+        step_index: row.get(3)?,
+        kind: row.get(4)?,
+        name: row.get(5)?,
+        content: row.get(6)?,
+        size: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+#[tauri::command]
+pub fn list_run_artifacts(db: State<DbState>, run_id: String) -> Result<Vec<Artifact>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, run_id, agent_id, step_index, kind, name, '', LENGTH(content), created_at
+         FROM run_artifacts WHERE run_id = ?1 ORDER BY step_index, created_at"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![run_id], row_to_artifact).map_err(|e| e.to_string())?;
+
+    let mut artifacts = Vec::new();
+    for row in rows {
+        artifacts.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(artifacts)
+}
+
+#[tauri::command]
+pub fn get_artifact(db: State<DbState>, id: String) -> Result<Artifact, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut artifact = conn.query_row(
+        "SELECT id, run_id, agent_id, step_index, kind, name, content, LENGTH(content), created_at
+         FROM run_artifacts WHERE id = ?1",
+        params![id],
+        row_to_artifact,
+    ).map_err(|_| "Artifact not found".to_string())?;
+    sanitize::artifact(&mut artifact, sanitize::mode(&conn));
+    Ok(artifact)
+}
+
+/// Lists artifacts left behind by deleted runs or agents. With `dry_run` false they are
+/// deleted too; by default nothing is.
+#[tauri::command]
+pub fn find_orphaned_artifacts(db: State<DbState>, dry_run: Option<bool>) -> Result<OrphanReport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let artifacts = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, run_id, agent_id, step_index, kind, name, '', LENGTH(content), created_at
+             FROM run_artifacts WHERE {ORPHANED} ORDER BY created_at"
+        )).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![QUICK_TASK_AGENT], row_to_artifact).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?
+    };
+    let deleted = !dry_run.unwrap_or(true);
+    if deleted {
+        remove_orphans(&conn)?;
+    }
+    Ok(OrphanReport { bytes: artifacts.iter().map(|a| a.size).sum(), artifacts, deleted })
+}
@@ -0,0 +1,255 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::sharing;
+use crate::DbState;
+
+const EXPORT_FORMAT: &str = "openclaw-audit-log";
+/// Appended to the export's path for its detached signature.
+const SIGNATURE_EXTENSION: &str = "sig";
+
+/// A security-relevant event, kept separately from the execution logs users clear.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEvent {
+    pub id: String,
+    /// e.g. `pii_detected`.
+    pub kind: String,
+    pub agent_id: String,
+    pub run_id: String,
+    pub summary: String,
+    pub detail: Value,
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS audit_events (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            agent_id TEXT DEFAULT '',
+            run_id TEXT DEFAULT '',
+            summary TEXT DEFAULT '',
+            detail_json TEXT DEFAULT '{}',
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_audit_created ON audit_events(created_at);
+    ").expect("Failed to initialize audit tables");
+}
+
+pub fn record(conn: &Connection, kind: &str, agent_id: &str, run_id: &str, summary: &str, detail: &Value) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO audit_events (id, kind, agent_id, run_id, summary, detail_json, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![Uuid::new_v4().to_string(), kind, agent_id, run_id, summary, detail.to_string(), Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Time window for an export; either end may be left open.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuditRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// One line of an export after the header. `hash` covers the previous line's hash and this
+/// event, so removing, reordering or editing any line breaks every hash after it.
+#[derive(Debug, Serialize, Deserialize)]
+struct ChainedEvent {
+    seq: u64,
+    event: AuditEvent,
+    prev_hash: String,
+    hash: String,
+}
+
+/// The detached signature written beside an export: Ed25519 over the export file's bytes.
+#[derive(Debug, Serialize, Deserialize)]
+struct DetachedSignature {
+    format: String,
+    signer: String,
+    fingerprint: String,
+    signature: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AuditExport {
+    pub path: String,
+    pub signature_path: String,
+    pub events: u64,
+    /// Hash of the last line, worth noting down elsewhere as extra proof.
+    pub head_hash: String,
+    pub signer: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AuditVerification {
+    pub valid: bool,
+    /// What failed; empty when valid.
+    pub problem: String,
+    pub events: u64,
+    pub signer: String,
+    /// Signed with this install's key.
+    pub signed_by_you: bool,
+}
+
+fn sha256_hex(data: &str) -> String {
+    Sha256::digest(data.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn chain_hash(prev_hash: &str, event: &AuditEvent) -> Result<String, String> {
+    Ok(sha256_hex(&format!("{prev_hash}\n{}", serde_json::to_string(event).map_err(|e| e.to_string())?)))
+}
+
+fn signature_path(path: &str) -> String {
+    format!("{path}.{SIGNATURE_EXTENSION}")
+}
+
+fn row_to_event(row: &rusqlite::Row) -> rusqlite::Result<AuditEvent> {
+    Ok(AuditEvent {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        agent_id: row.get(2)?,
+        run_id: row.get(3)?,
+        summary: row.get(4)?,
+        detail: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or(Value::Null),
+        created_at: row.get(6)?,
+    })
+}
+
+/// Checks an export line by line against its hash chain, then the detached signature.
+fn verify(raw: &[u8], signature: &DetachedSignature) -> Result<u64, String> {
+    let text = std::str::from_utf8(raw).map_err(|_| "The export isn't a text file")?;
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or_default();
+    let header: Value = serde_json::from_str(first).map_err(|_| "The export has no header")?;
+    if header["format"] != EXPORT_FORMAT {
+        return Err("This isn't an audit log export".into());
+    }
+    let mut prev_hash = sha256_hex(first);
+    let mut events = 0;
+    for (i, line) in lines.enumerate() {
+        let chained: ChainedEvent = serde_json::from_str(line).map_err(|_| format!("Line {} is damaged", i + 2))?;
+        if chained.seq != events + 1 || chained.prev_hash != prev_hash || chained.hash != chain_hash(&prev_hash, &chained.event)? {
+            return Err(format!("The chain breaks at event {}: it was removed, reordered or edited", events + 1));
+        }
+        prev_hash = chained.hash;
+        events += 1;
+    }
+    if header["events"].as_u64() != Some(events) {
+        return Err("Events are missing from the end of the export".into());
+    }
+    let signer_bytes: [u8; 32] = B64.decode(&signature.signer).ok().and_then(|b| b.try_into().ok()).ok_or("The signature file is damaged")?;
+    let signature_bytes: [u8; 64] = B64.decode(&signature.signature).ok().and_then(|b| b.try_into().ok()).ok_or("The signature file is damaged")?;
+    let signer = VerifyingKey::from_bytes(&signer_bytes).map_err(|_| "The signature file is damaged")?;
+    signer.verify(raw, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "The signature doesn't match: the export was changed after it was signed")?;
+    Ok(events)
+}
+
+#[tauri::command]
+pub fn list_audit_events(
+    db: State<DbState>,
+    kind: Option<String>,
+    agent_id: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<AuditEvent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, agent_id, run_id, summary, detail_json, created_at FROM audit_events
+         WHERE (?1 IS NULL OR kind = ?1) AND (?2 IS NULL OR agent_id = ?2)
+         ORDER BY created_at DESC LIMIT ?3"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![kind, agent_id, limit.unwrap_or(200)], row_to_event).map_err(|e| e.to_string())?;
+
+    let mut events = Vec::new();
+    for row in rows {
+        events.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(events)
+}
+
+/// Writes the audit events in `range`, oldest first, to `path` as JSON lines: a header, then
+/// one hash-chained line per event. A detached Ed25519 signature over the file goes to
+/// `<path>.sig`, signed with this install's key.
+#[tauri::command]
+pub fn export_audit_log(db: State<DbState>, range: Option<AuditRange>, path: String) -> Result<AuditExport, String> {
+    let range = range.unwrap_or_default();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let events: Vec<AuditEvent> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, agent_id, run_id, summary, detail_json, created_at FROM audit_events
+             WHERE (?1 IS NULL OR created_at >= ?1) AND (?2 IS NULL OR created_at <= ?2)
+             ORDER BY created_at, id"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![range.from, range.to], row_to_event).map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let key = sharing::signing_key(&conn)?;
+    let signer = B64.encode(key.verifying_key().as_bytes());
+
+    let header = json!({
+        "format": EXPORT_FORMAT,
+        "version": 1,
+        "from": range.from,
+        "to": range.to,
+        "events": events.len(),
+        "exported_at": Utc::now().to_rfc3339(),
+        "signer": signer,
+    }).to_string();
+    let count = events.len() as u64;
+    let mut prev_hash = sha256_hex(&header);
+    let mut out = header;
+    out.push('\n');
+    for (i, event) in events.into_iter().enumerate() {
+        let hash = chain_hash(&prev_hash, &event)?;
+        let line = ChainedEvent { seq: i as u64 + 1, event, prev_hash, hash: hash.clone() };
+        out.push_str(&serde_json::to_string(&line).map_err(|e| e.to_string())?);
+        out.push('\n');
+        prev_hash = hash;
+    }
+    let signature = DetachedSignature {
+        format: EXPORT_FORMAT.into(),
+        fingerprint: sharing::fingerprint(&key.verifying_key()),
+        signature: B64.encode(key.sign(out.as_bytes()).to_bytes()),
+        signer,
+    };
+    std::fs::write(&path, &out).map_err(|e| format!("Couldn't write {path}: {e}"))?;
+    let signature_path = signature_path(&path);
+    std::fs::write(&signature_path, serde_json::to_vec_pretty(&signature).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("Couldn't write {signature_path}: {e}"))?;
+    Ok(AuditExport {
+        path,
+        signature_path,
+        events: count,
+        head_hash: prev_hash,
+        signer: signature.fingerprint,
+    })
+}
+
+/// Checks an export and its `.sig` file: every event is present, in order and unchanged, and
+/// the file is exactly as signed.
+#[tauri::command]
+pub fn verify_audit_export(db: State<DbState>, path: String) -> Result<AuditVerification, String> {
+    let raw = std::fs::read(&path).map_err(|e| format!("Couldn't read {path}: {e}"))?;
+    let signature_path = signature_path(&path);
+    let signature: DetachedSignature = std::fs::read(&signature_path).ok()
+        .and_then(|s| serde_json::from_slice(&s).ok())
+        .ok_or_else(|| format!("The signature file {signature_path} is missing or damaged"))?;
+    let signed_by_you = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        sharing::stored_signing_key(&conn)?.is_some_and(|key| B64.encode(key.verifying_key().as_bytes()) == signature.signer)
+    };
+    let (valid, problem, events) = match verify(&raw, &signature) {
+        Ok(events) => (true, String::new(), events),
+        Err(problem) => (false, problem, 0),
+    };
+    Ok(AuditVerification { valid, problem, events, signer: signature.fingerprint, signed_by_you })
+}
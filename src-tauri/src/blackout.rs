@@ -0,0 +1,346 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::audit;
+use crate::calendar;
+use crate::executor::{self, RunMode};
+use crate::shutdown;
+use crate::{DbState, update_agent_config};
+
+const DAYS: &[&str] = &["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+/// How often deferred runs are checked for being due.
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(30);
+/// Back-to-back windows are followed this far to find when an agent may run again.
+const MAX_CHAINED: usize = 16;
+
+/// A time when agents must not run: every week on `days` (every day when empty), or only
+/// on `date`. A window whose end is before its start runs past midnight.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlackoutWindow {
+    #[serde(default)]
+    pub id: String,
+    /// The agent it applies to; empty for every agent.
+    #[serde(default)]
+    pub agent_id: String,
+    #[serde(default)]
+    pub label: String,
+    /// `mon` … `sun`.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// `YYYY-MM-DD` for a one-off window, e.g. a presentation.
+    #[serde(default)]
+    pub date: Option<String>,
+    /// Local time, `HH:MM`.
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BlackoutStatus {
+    pub blocked: bool,
+    /// The window in force now, if any.
+    pub window: Option<BlackoutWindow>,
+    /// The calendar event the agent is sitting out, if any.
+    pub event: Option<String>,
+    /// When the agent may run again, after any back-to-back windows.
+    pub until: Option<String>,
+    /// `defer` or `skip`.
+    pub catch_up: String,
+}
+
+/// A run held back by a blackout window, started once the window is over.
+#[derive(Debug, Serialize, Clone)]
+pub struct DeferredRun {
+    pub id: String,
+    pub agent_id: String,
+    pub agent_name: String,
+    pub trigger: String,
+    pub mode: String,
+    pub due_at: String,
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS blackout_windows (
+            id TEXT PRIMARY KEY,
+            agent_id TEXT DEFAULT '',
+            label TEXT DEFAULT '',
+            days TEXT DEFAULT '',
+            date TEXT,
+            start_time TEXT NOT NULL,
+            end_time TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS deferred_runs (
+            id TEXT PRIMARY KEY,
+            agent_id TEXT NOT NULL,
+            trigger_kind TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            due_at TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize blackout tables");
+}
+
+fn row_to_window(row: &rusqlite::Row) -> rusqlite::Result<BlackoutWindow> {
+    let days: String = row.get(3)?;
+    Ok(BlackoutWindow {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        label: row.get(2)?,
+        days: days.split(',').filter(|d| !d.is_empty()).map(str::to_string).collect(),
+        date: row.get(4)?,
+        start: row.get(5)?,
+        end: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+/// Windows for `agent_id` and the global ones; every window when `agent_id` is `None`.
+fn windows_for(conn: &Connection, agent_id: Option<&str>) -> Result<Vec<BlackoutWindow>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, label, days, date, start_time, end_time, created_at FROM blackout_windows
+         WHERE ?1 IS NULL OR agent_id IN ('', ?1) ORDER BY agent_id, start_time"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id], row_to_window).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| format!("\"{value}\" isn't a time like 09:30"))
+}
+
+fn applies_on(window: &BlackoutWindow, day: NaiveDate) -> bool {
+    match &window.date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok_and(|d| d == day),
+        None => window.days.is_empty() || window.days.iter().any(|d| d == DAYS[day.weekday().num_days_from_monday() as usize]),
+    }
+}
+
+/// When `window` ends, if it is in force at `now`.
+fn active_until(window: &BlackoutWindow, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let (start, end) = (time(&window.start).ok()?, time(&window.end).ok()?);
+    let today = now.date_naive();
+    // A window past midnight may have started yesterday.
+    [today, today.pred_opt()?].into_iter().filter(|day| applies_on(window, *day)).find_map(|day| {
+        let end_day = if end > start { day } else { day.succ_opt()? };
+        let from = day.and_time(start).and_local_timezone(Local).earliest()?;
+        let to = end_day.and_time(end).and_local_timezone(Local).earliest()?;
+        (from <= now && now < to).then_some(to)
+    })
+}
+
+/// The window keeping `agent_id` from running at `now`, and when it may run again.
+pub fn blocked(conn: &Connection, agent_id: &str, now: DateTime<Local>) -> Result<Option<(BlackoutWindow, DateTime<Local>)>, String> {
+    let windows = windows_for(conn, Some(agent_id))?;
+    let Some((first, mut until)) = windows.iter().find_map(|w| active_until(w, now).map(|to| (w.clone(), to))) else {
+        return Ok(None);
+    };
+    for _ in 0..MAX_CHAINED {
+        match windows.iter().filter_map(|w| active_until(w, until)).max() {
+            Some(later) if later > until => until = later,
+            _ => break,
+        }
+    }
+    Ok(Some((first, until)))
+}
+
+/// Whether an agent's missed runs are made up once the window ends (`defer`, the default)
+/// or dropped (`skip`).
+pub fn catch_up(conn: &Connection, agent_id: &str) -> String {
+    conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        .and_then(|config| config.get("catch_up").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| "defer".into())
+}
+
+/// Holds back an automatic run that falls in a blackout window, or in a calendar event the
+/// agent avoids, deferring it to the end of the window or meeting or skipping it as the
+/// agent's catch-up policy says. A deferred run replaces
+/// any already waiting, so a long window doesn't pile up runs. Returns why the run didn't
+/// start, or `None` when it may.
+pub fn hold(conn: &Connection, agent_id: &str, trigger: &str, mode: RunMode) -> Result<Option<String>, String> {
+    let now = Local::now();
+    let (label, until, cause) = if let Some((window, until)) = blocked(conn, agent_id, now)? {
+        let label = if window.label.is_empty() { "a blackout window".to_string() } else { format!("\"{}\"", window.label) };
+        (label, until, json!({ "window": window.id }))
+    } else if let Some((event, until)) = calendar::busy(conn, agent_id, now)? {
+        (format!("the meeting \"{event}\""), until, json!({ "event": event }))
+    } else {
+        return Ok(None);
+    };
+    let policy = catch_up(conn, agent_id);
+    let summary = match policy.as_str() {
+        "skip" => format!("Skipped a {trigger} run during {label}"),
+        _ => {
+            conn.execute("DELETE FROM deferred_runs WHERE agent_id = ?1", params![agent_id]).map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT INTO deferred_runs (id, agent_id, trigger_kind, mode, due_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![Uuid::new_v4().to_string(), agent_id, trigger, mode.as_str(), until.with_timezone(&Utc).to_rfc3339(), Utc::now().to_rfc3339()],
+            ).map_err(|e| e.to_string())?;
+            format!("Deferred a {trigger} run during {label} until {}", until.format("%H:%M"))
+        }
+    };
+    audit::record(conn, "blackout_held", agent_id, "", &summary, &json!({ "cause": cause, "until": until.to_rfc3339(), "catch_up": policy }))?;
+    Ok(Some(summary))
+}
+
+/// Drops a deleted agent's own windows and waiting runs.
+pub fn remove_agent(conn: &Connection, agent_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM blackout_windows WHERE agent_id = ?1", params![agent_id]).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM deferred_runs WHERE agent_id = ?1", params![agent_id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn due_runs(conn: &Connection) -> Result<Vec<(String, String, String, String)>, String> {
+    let mut stmt = conn.prepare("SELECT id, agent_id, trigger_kind, mode FROM deferred_runs WHERE due_at <= ?1 ORDER BY due_at")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![Utc::now().to_rfc3339()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Starts the watcher that runs deferred runs once their window is over. They go through
+/// the same check again, so a window added in the meantime defers them further.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("deferred-runs".into())
+        .spawn(move || loop {
+            std::thread::sleep(CHECK_EVERY);
+            if shutdown::stopping(&app) {
+                continue;
+            }
+            let due = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                let due = due_runs(&conn).unwrap_or_default();
+                for (id, ..) in &due {
+                    let _ = conn.execute("DELETE FROM deferred_runs WHERE id = ?1", params![id]);
+                }
+                due
+            };
+            for (_, agent_id, trigger, mode) in due {
+                let mode = if mode == "simulate" { RunMode::Simulate } else { RunMode::Live };
+                if let Err(e) = executor::execute_agent(&app, &agent_id, mode, &trigger) {
+                    eprintln!("Deferred run of {agent_id} didn't start: {e}");
+                }
+            }
+        })
+        .expect("Failed to start the deferred run watcher");
+}
+
+// ─── Blackout Commands ───
+
+/// Windows for one agent, including the global ones, or every window.
+#[tauri::command]
+pub fn list_blackout_windows(db: State<DbState>, agent_id: Option<String>) -> Result<Vec<BlackoutWindow>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    windows_for(&conn, agent_id.as_deref())
+}
+
+#[tauri::command]
+pub fn add_blackout_window(db: State<DbState>, window: BlackoutWindow) -> Result<BlackoutWindow, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (start, end) = (time(&window.start)?, time(&window.end)?);
+    if start == end {
+        return Err("A blackout window needs different start and end times".into());
+    }
+    let days: Vec<String> = window.days.iter().map(|d| d.trim().to_lowercase().chars().take(3).collect()).collect();
+    if let Some(day) = days.iter().find(|d| !DAYS.contains(&d.as_str())) {
+        return Err(format!("\"{day}\" isn't a day of the week"));
+    }
+    let date = window.date.filter(|d| !d.trim().is_empty());
+    if let Some(date) = &date {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("\"{date}\" isn't a date like 2024-05-31"))?;
+    }
+    if !window.agent_id.is_empty() {
+        conn.query_row("SELECT 1 FROM agents WHERE id = ?1", params![window.agent_id], |_| Ok(()))
+            .optional().map_err(|e| e.to_string())?.ok_or("Agent not found")?;
+    }
+    let window = BlackoutWindow {
+        id: Uuid::new_v4().to_string(),
+        days,
+        date,
+        start: start.format("%H:%M").to_string(),
+        end: end.format("%H:%M").to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        ..window
+    };
+    conn.execute(
+        "INSERT INTO blackout_windows (id, agent_id, label, days, date, start_time, end_time, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![window.id, window.agent_id, window.label.trim(), window.days.join(","), window.date, window.start, window.end, window.created_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(window)
+}
+
+#[tauri::command]
+pub fn delete_blackout_window(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM blackout_windows WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// What happens to an automatic run that falls in a blackout window: `defer` runs it once
+/// the window ends, `skip` drops it.
+#[tauri::command]
+pub fn set_agent_catch_up(db: State<DbState>, agent_id: String, policy: String) -> Result<(), String> {
+    if !matches!(policy.as_str(), "defer" | "skip") {
+        return Err(format!("Unknown catch-up policy: {policy}"));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| {
+        config.insert("catch_up".into(), json!(policy));
+    })
+}
+
+#[tauri::command]
+pub fn get_blackout_status(db: State<DbState>, agent_id: String) -> Result<BlackoutStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let now = Local::now();
+    let blocked = blocked(&conn, &agent_id, now)?;
+    let busy = match blocked {
+        Some(_) => None,
+        None => calendar::busy(&conn, &agent_id, now)?,
+    };
+    Ok(BlackoutStatus {
+        blocked: blocked.is_some() || busy.is_some(),
+        until: blocked.as_ref().map(|(_, until)| *until).or(busy.as_ref().map(|(_, until)| *until)).map(|t| t.to_rfc3339()),
+        window: blocked.map(|(window, _)| window),
+        event: busy.map(|(event, _)| event),
+        catch_up: catch_up(&conn, &agent_id),
+    })
+}
+
+#[tauri::command]
+pub fn list_deferred_runs(db: State<DbState>) -> Result<Vec<DeferredRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.agent_id, COALESCE(a.name, ''), d.trigger_kind, d.mode, d.due_at, d.created_at
+         FROM deferred_runs d LEFT JOIN agents a ON a.id = d.agent_id ORDER BY d.due_at"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok(DeferredRun {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        agent_name: row.get(2)?,
+        trigger: row.get(3)?,
+        mode: row.get(4)?,
+        due_at: row.get(5)?,
+        created_at: row.get(6)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cancel_deferred_run(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM deferred_runs WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
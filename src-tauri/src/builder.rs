@@ -0,0 +1,746 @@
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::executor::{self, RunMode, StepResult};
+use crate::llm::{ChatMessage, LlmRequest};
+use crate::sanitize;
+use crate::structured;
+use crate::tool_calling;
+use crate::tools;
+use crate::workflows::{self, WorkflowStep};
+use crate::{Agent, DbState, insert_agent};
+
+const BUILDER_PROMPT: &str = "You design automation agents for non-technical users.
+Turn the user's description into an agent. Reply with ONE JSON object and nothing else, using these keys:
+  name      short friendly name
+  role      e.g. \"Inbox Assistant\"
+  goal      one or two plain sentences
+  schedule  5-field cron expression, or \"\" if it should only run on demand
+  tools     array of tool names the agent needs
+  workflow  array of steps: {\"type\":\"tool\",\"tool\":<name>,\"params\":{...},\"label\":<what it does>}
+  notes     array of short remarks for the user (assumptions, things to check)
+Use only the tools listed below, and fill params according to each tool's schema.
+Available tools:
+";
+
+const ASK_POLICY: &str = "If details you truly need are missing (which folder, which address, how often), \
+reply instead with {\"questions\": [\"...\"]} — at most 3 short, friendly questions. \
+Otherwise reply with the agent.";
+
+const NO_QUESTIONS_POLICY: &str = "Do not ask questions; make sensible assumptions and list them in notes.";
+
+/// Questions asked per turn, and turns before the builder stops asking and just drafts.
+const MAX_QUESTIONS: usize = 3;
+const MAX_CLARIFY_ROUNDS: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolPermission {
+    pub tool: String,
+    pub permission: String,
+    pub side_effect: bool,
+    pub requires_approval: bool,
+}
+
+/// A proposed agent the user reviews before it is saved.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentDraft {
+    pub name: String,
+    pub role: String,
+    pub goal: String,
+    pub schedule: String,
+    pub tools: Vec<String>,
+    pub workflow: Vec<WorkflowStep>,
+    pub permissions: Vec<ToolPermission>,
+    pub sandbox: bool,
+    pub notes: Vec<String>,
+    /// `"llm"`, `"heuristic"`, `"template"` or `"run"` — which path produced the draft.
+    pub source: String,
+}
+
+pub(crate) fn looks_like_cron(expr: &str) -> bool {
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    fields.len() == 5
+        && fields.iter().all(|f| f.chars().all(|c| c.is_ascii_digit() || "*/,-".contains(c)))
+}
+
+fn permissions_for(tools: &[String]) -> Vec<ToolPermission> {
+    tools.iter()
+        .filter_map(|name| tools::find(name))
+        .map(|spec| ToolPermission {
+            tool: spec.name.to_string(),
+            permission: spec.permission.to_string(),
+            side_effect: spec.side_effect,
+            requires_approval: spec.irreversible,
+        })
+        .collect()
+}
+
+/// Drops anything the registry doesn't know about and recomputes permissions from the registry,
+/// so a draft never claims fewer permissions than its steps actually need.
+pub(crate) fn normalize_draft(mut draft: AgentDraft) -> AgentDraft {
+    let known = |name: &str| tools::find(name).is_some();
+    let mut notes = std::mem::take(&mut draft.notes);
+
+    draft.workflow.retain(|step| match step {
+        WorkflowStep::Tool { tool, .. } if !known(tool) => {
+            notes.push(format!("Removed a step that used an unknown tool \"{tool}\"."));
+            false
+        }
+        _ => true,
+    });
+    let mut tools: Vec<String> = draft.tools.into_iter().filter(|t| known(t)).collect();
+    for step in &draft.workflow {
+        if let WorkflowStep::Tool { tool, .. } = step {
+            if !tools.contains(tool) {
+                tools.push(tool.clone());
+            }
+        }
+    }
+    if !draft.schedule.is_empty() && !looks_like_cron(&draft.schedule) {
+        notes.push(format!("Couldn't understand the schedule \"{}\"; it will run on demand.", draft.schedule));
+        draft.schedule.clear();
+    }
+    if draft.name.trim().is_empty() {
+        draft.name = "New Agent".into();
+    }
+
+    draft.permissions = permissions_for(&tools);
+    draft.tools = tools;
+    draft.notes = notes;
+    draft
+}
+
+fn draft_from_llm_json(value: &Value, description: &str) -> Result<AgentDraft, String> {
+    let text = |key: &str| value.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    let list = |key: &str| -> Vec<String> {
+        value.get(key)
+            .and_then(Value::as_array)
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    let workflow: Vec<WorkflowStep> = match value.get("workflow") {
+        Some(steps) => serde_json::from_value(steps.clone()).map_err(|e| format!("Invalid workflow: {e}"))?,
+        None => Vec::new(),
+    };
+    let goal = text("goal");
+    Ok(AgentDraft {
+        name: text("name"),
+        role: text("role"),
+        goal: if goal.is_empty() { description.to_string() } else { goal },
+        schedule: text("schedule"),
+        tools: list("tools"),
+        workflow,
+        permissions: Vec::new(),
+        sandbox: true,
+        notes: list("notes"),
+        source: "llm".into(),
+    })
+}
+
+/// Maps common phrases ("every morning", "hourly", "weekdays at 8am") to cron.
+pub(crate) fn schedule_from_text(text: &str) -> String {
+    let t = text.to_lowercase();
+    let hour = t.split_whitespace()
+        .skip_while(|w| *w != "at")
+        .nth(1)
+        .and_then(|w| {
+            let pm = w.ends_with("pm");
+            let digits: String = w.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<u32>().ok().map(|h| if pm && h < 12 { h + 12 } else { h })
+        })
+        .filter(|h| *h < 24);
+
+    if let Some(n) = t.split_whitespace()
+        .collect::<Vec<_>>()
+        .windows(3)
+        .find(|w| w[0] == "every" && w[2].starts_with("minute"))
+        .and_then(|w| w[1].parse::<u32>().ok())
+    {
+        return format!("*/{n} * * * *");
+    }
+    if t.contains("every hour") || t.contains("hourly") {
+        return "0 * * * *".into();
+    }
+    let at = hour.unwrap_or(if t.contains("evening") { 18 } else { 9 });
+    const DAYS: [(&str, u32); 7] = [
+        ("sunday", 0), ("monday", 1), ("tuesday", 2), ("wednesday", 3),
+        ("thursday", 4), ("friday", 5), ("saturday", 6),
+    ];
+    if let Some((_, d)) = DAYS.iter().find(|(name, _)| t.contains(name)) {
+        return format!("0 {at} * * {d}");
+    }
+    if t.contains("weekday") {
+        return format!("0 {at} * * 1-5");
+    }
+    if t.contains("weekly") || t.contains("every week") {
+        return format!("0 {at} * * 1");
+    }
+    if t.contains("daily") || t.contains("every day") || t.contains("every morning")
+        || t.contains("every evening") || t.contains("each day") || hour.is_some()
+    {
+        return format!("0 {at} * * *");
+    }
+    String::new()
+}
+
+/// Offline fallback used when no model is reachable: keyword matching against the registry.
+pub(crate) fn heuristic_draft(description: &str) -> AgentDraft {
+    let t = description.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| t.contains(w));
+    let mut steps = Vec::new();
+    let mut notes = vec!["Drafted without an AI model — please check each step.".to_string()];
+
+    if has(&["email", "inbox", "mail"]) {
+        steps.push(("read_inbox", json!({ "limit": 20 }), "Read new emails"));
+    }
+    if has(&["folder", "file", "document", "download", "pdf"]) {
+        steps.push(("list_directory", json!({ "path": "" }), "Look at the folder"));
+        notes.push("Fill in which folder the agent should look at.".into());
+    }
+    if has(&["website", "news", "fetch", "check the site"]) {
+        steps.push(("http_get", json!({ "url": "" }), "Fetch the page"));
+        notes.push("Fill in the web address to check.".into());
+    }
+    if has(&["summar", "write", "draft", "reply", "post", "translate", "decide"]) || steps.is_empty() {
+        steps.push(("llm_prompt", json!({ "prompt": description }), "Work out what to do"));
+    }
+    if has(&["move", "organi", "sort", "file it", "archive"]) {
+        steps.push(("move_file", json!({ "from": "", "to": "" }), "Move the file"));
+    }
+    if has(&["delete", "clean up", "remove"]) {
+        steps.push(("delete_file", json!({ "path": "" }), "Delete the file"));
+    }
+    if has(&["send", "reply", "forward"]) && has(&["email", "mail"]) {
+        steps.push(("send_email", json!({ "to": "", "subject": "", "body": "{{previous_output}}" }), "Send the email"));
+    }
+    if has(&["linkedin", "twitter", "browser", "comment on"]) {
+        steps.push(("browser", json!({ "instruction": description }), "Do it in the browser"));
+    }
+    if has(&["notify", "remind", "let me know", "alert me"]) {
+        steps.push(("notify", json!({ "title": "Agent update", "message": "{{previous_output}}" }), "Let you know"));
+    }
+
+    let name: String = description.split_whitespace()
+        .take(4)
+        .map(|w| {
+            let mut c = w.chars();
+            c.next().map(|f| f.to_uppercase().chain(c).collect::<String>()).unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    AgentDraft {
+        name: if name.is_empty() { "New Agent".into() } else { format!("{name} Agent") },
+        role: "Assistant".into(),
+        goal: description.trim().to_string(),
+        schedule: schedule_from_text(description),
+        tools: Vec::new(),
+        workflow: steps.into_iter()
+            .map(|(tool, params, label)| WorkflowStep::Tool { tool: tool.into(), params, label: label.into() })
+            .collect(),
+        permissions: Vec::new(),
+        sandbox: true,
+        notes,
+        source: "heuristic".into(),
+    }
+}
+
+fn tool_catalog() -> String {
+    let catalog: Vec<Value> = tools::registry().iter()
+        .map(|t| json!({ "name": t.name, "description": t.description, "params": t.params }))
+        .collect();
+    serde_json::to_string_pretty(&catalog).unwrap_or_default()
+}
+
+/// What the model answered for one builder turn.
+pub enum BuilderReply {
+    Questions(Vec<String>),
+    Draft(AgentDraft),
+}
+
+/// Shape of a builder reply: either `questions`, or the agent's fields.
+fn reply_schema() -> Value {
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+    json!({
+        "type": "object",
+        "properties": {
+            "questions": strings,
+            "name": { "type": "string" },
+            "role": { "type": "string" },
+            "goal": { "type": "string" },
+            "schedule": { "type": "string" },
+            "tools": strings,
+            "workflow": { "type": "array", "items": { "type": "object", "required": ["type"] } },
+            "notes": strings,
+        },
+    })
+}
+
+fn builder_request(history: &[ChatMessage], allow_questions: bool) -> LlmRequest {
+    let policy = if allow_questions { ASK_POLICY } else { NO_QUESTIONS_POLICY };
+    LlmRequest {
+        system: format!("{BUILDER_PROMPT}{}\n{policy}", tool_catalog()),
+        messages: history.to_vec(),
+        max_tokens: 1500,
+        json: true,
+        ..Default::default()
+    }
+}
+
+/// Runs one builder turn over the conversation so far, falling back to keyword matching when
+/// no model is reachable.
+pub fn builder_turn(db: &DbState, description: &str, history: &[ChatMessage], allow_questions: bool) -> BuilderReply {
+    let reply = structured::complete(db, &builder_request(history, allow_questions), &reply_schema())
+        .and_then(|value| {
+            let questions: Vec<String> = value.get("questions")
+                .and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(|q| q.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            if allow_questions && !questions.is_empty() {
+                return Ok(BuilderReply::Questions(questions.into_iter().take(MAX_QUESTIONS).collect()));
+            }
+            draft_from_llm_json(&value, description).map(|d| BuilderReply::Draft(normalize_draft(d)))
+        });
+    match reply {
+        Ok(reply) => reply,
+        Err(e) => {
+            let mut draft = heuristic_draft(description);
+            draft.notes.push(format!("AI drafting unavailable: {e}"));
+            BuilderReply::Draft(normalize_draft(draft))
+        }
+    }
+}
+
+/// Produces a draft for `description` in a single turn, without asking questions.
+pub fn draft_from_description(db: &DbState, description: &str) -> AgentDraft {
+    match builder_turn(db, description, &[ChatMessage::user(description)], false) {
+        BuilderReply::Draft(draft) => draft,
+        BuilderReply::Questions(_) => heuristic_draft(description),
+    }
+}
+
+// ─── Drafts From Runs ───
+
+/// Outputs shorter than this aren't turned into placeholders; short strings like "ok" turn
+/// up in params by coincidence.
+const MIN_LINKED_OUTPUT: usize = 12;
+
+/// Replaces text in `value` that a run filled in from the goal or an earlier step's output
+/// with the placeholder that produces it, so the saved agent works on fresh data each time.
+fn generalize(value: &Value, goal: &str, earlier: &[StepResult]) -> Value {
+    match value {
+        Value::String(s) => {
+            let mut s = s.clone();
+            for (i, step) in earlier.iter().enumerate().rev() {
+                let output = step.output.trim();
+                if output.chars().count() < MIN_LINKED_OUTPUT || !s.contains(output) {
+                    continue;
+                }
+                let placeholder = if i + 1 == earlier.len() { "{{previous_output}}".to_string() } else { format!("{{{{step_{}}}}}", i + 1) };
+                s = s.replace(output, &placeholder);
+            }
+            if goal.chars().count() >= MIN_LINKED_OUTPUT {
+                s = s.replace(goal, "{{goal}}");
+            }
+            Value::String(s)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| generalize(v, goal, earlier)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), generalize(v, goal, earlier))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Builds a draft agent that repeats what a successful run did: its steps in order, with
+/// values that came from earlier steps turned back into placeholders.
+fn draft_from_run(conn: &Connection, run_id: &str) -> Result<AgentDraft, String> {
+    let run = executor::load_run(conn, run_id)?;
+    if run.status != "success" {
+        return Err("Only a run that finished successfully can be saved as an agent".into());
+    }
+    let task: String = conn.query_row("SELECT task_json FROM runs WHERE id = ?1", params![run_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let instruction = serde_json::from_str::<Value>(&task).ok()
+        .and_then(|t| t.get("instruction").and_then(Value::as_str).map(String::from));
+    let (name, role, goal) = match instruction {
+        Some(instruction) => (String::new(), "Assistant".to_string(), instruction),
+        None => conn.query_row("SELECT name, role, goal FROM agents WHERE id = ?1", params![run.agent_id], |row| {
+            Ok((format!("{} (from run)", row.get::<_, String>(0)?), row.get(1)?, row.get(2)?))
+        }).map_err(|_| "The agent behind this run no longer exists".to_string())?,
+    };
+
+    let mut workflow = Vec::new();
+    let mut kept: Vec<StepResult> = Vec::new();
+    let mut notes = vec!["Built from a run; check that each step should happen every time.".to_string()];
+    for step in run.steps.iter().filter(|s| s.status == "ok" || s.status == "simulated") {
+        if step.tool == "answer" {
+            workflow.push(WorkflowStep::Tool {
+                tool: "llm_prompt".into(),
+                params: json!({ "prompt": "{{goal}}\n\nWhat was found:\n{{previous_output}}" }),
+                label: "Write up the result".into(),
+            });
+            continue;
+        }
+        let label = if step.label == tool_calling::CHOSEN_LABEL { step.tool.replace('_', " ") } else { step.label.clone() };
+        workflow.push(WorkflowStep::Tool { tool: step.tool.clone(), params: generalize(&step.params, &goal, &kept), label });
+        kept.push(step.clone());
+    }
+    if workflow.is_empty() {
+        return Err("This run has no steps to repeat".into());
+    }
+    if run.mode == RunMode::Simulate {
+        notes.push("The run was simulated, so its changes were never actually made.".into());
+    }
+
+    let schedule = schedule_from_text(&goal);
+    if !schedule.is_empty() {
+        notes.push("The schedule was guessed from the instruction.".into());
+    }
+    let name = if name.is_empty() { heuristic_draft(&goal).name } else { name };
+    Ok(normalize_draft(AgentDraft {
+        name,
+        role,
+        goal,
+        schedule,
+        tools: Vec::new(),
+        workflow,
+        permissions: Vec::new(),
+        sandbox: true,
+        notes,
+        source: "run".into(),
+    }))
+}
+
+// ─── Draft Sessions ───
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClarifyQuestion {
+    /// `q<n>` for questions from the model, `step:<index>:<param>` for blanks in the draft.
+    pub id: String,
+    pub question: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DraftAnswer {
+    pub id: String,
+    pub answer: String,
+}
+
+/// A multi-turn agent-building conversation, persisted so it survives restarts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DraftSession {
+    pub id: String,
+    pub description: String,
+    /// `needs_answers`, `ready` or `saved`.
+    pub status: String,
+    pub questions: Vec<ClarifyQuestion>,
+    pub draft: Option<AgentDraft>,
+    pub rounds: i64,
+    #[serde(skip)]
+    history: Vec<ChatMessage>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS agent_draft_sessions (
+            id TEXT PRIMARY KEY,
+            description TEXT NOT NULL,
+            status TEXT NOT NULL,
+            history_json TEXT DEFAULT '[]',
+            questions_json TEXT DEFAULT '[]',
+            draft_json TEXT DEFAULT '',
+            rounds INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize builder tables");
+}
+
+fn load_session(conn: &Connection, id: &str) -> Result<DraftSession, String> {
+    let mut sessions = query_sessions(conn, "WHERE id = ?1", params![id])?;
+    sessions.pop().ok_or_else(|| "Draft session not found".to_string())
+}
+
+fn query_sessions(conn: &Connection, filter: &str, args: impl rusqlite::Params) -> Result<Vec<DraftSession>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, description, status, history_json, questions_json, draft_json, rounds, created_at, updated_at
+         FROM agent_draft_sessions {filter} ORDER BY updated_at DESC"
+    )).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(args, |row| {
+        Ok((
+            row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?, row.get::<_, String>(4)?, row.get::<_, String>(5)?,
+            row.get::<_, i64>(6)?, row.get::<_, String>(7)?, row.get::<_, String>(8)?,
+        ))
+    }).map_err(|e| e.to_string())?;
+
+    let mut sessions = Vec::new();
+    for row in rows {
+        let (id, description, status, history, questions, draft, rounds, created_at, updated_at) =
+            row.map_err(|e| e.to_string())?;
+        sessions.push(DraftSession {
+            id,
+            description,
+            status,
+            questions: serde_json::from_str(&questions).unwrap_or_default(),
+            draft: serde_json::from_str(&draft).ok(),
+            rounds,
+            history: serde_json::from_str(&history).unwrap_or_default(),
+            created_at,
+            updated_at,
+        });
+    }
+    Ok(sessions)
+}
+
+fn store_session(conn: &Connection, session: &DraftSession) -> Result<(), String> {
+    let draft = match &session.draft {
+        Some(d) => serde_json::to_string(d).map_err(|e| e.to_string())?,
+        None => String::new(),
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_draft_sessions (id, description, status, history_json, questions_json, draft_json, rounds, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            session.id, session.description, session.status,
+            serde_json::to_string(&session.history).map_err(|e| e.to_string())?,
+            serde_json::to_string(&session.questions).map_err(|e| e.to_string())?,
+            draft, session.rounds, session.created_at, session.updated_at
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn blank_question(param: &str, label: &str) -> String {
+    match param {
+        "path" => format!("Which folder or file should \"{label}\" use?"),
+        "url" => format!("Which web address should \"{label}\" use?"),
+        "to" => "Who should the email go to?".into(),
+        "subject" => "What subject line should the email have?".into(),
+        "from" => "Which file should be moved?".into(),
+        other => format!("What should \"{other}\" be for \"{label}\"?"),
+    }
+}
+
+/// Asks about required parameters the draft left empty.
+fn questions_for_blanks(draft: &AgentDraft) -> Vec<ClarifyQuestion> {
+    let mut questions = Vec::new();
+    for (index, step) in draft.workflow.iter().enumerate() {
+        let WorkflowStep::Tool { tool, params, label } = step else { continue };
+        let Some(spec) = tools::find(tool) else { continue };
+        let required = spec.params["required"].as_array().cloned().unwrap_or_default();
+        for param in required.iter().filter_map(Value::as_str) {
+            let blank = params.get(param).map(|v| v.as_str() == Some("")).unwrap_or(true);
+            if blank {
+                let label = if label.is_empty() { tool.as_str() } else { label.as_str() };
+                questions.push(ClarifyQuestion {
+                    id: format!("step:{index}:{param}"),
+                    question: blank_question(param, label),
+                });
+            }
+        }
+    }
+    questions
+}
+
+fn fill_blanks(draft: &mut AgentDraft, answers: &[DraftAnswer]) {
+    for answer in answers {
+        let mut parts = answer.id.splitn(3, ':');
+        let (Some("step"), Some(index), Some(param)) = (parts.next(), parts.next(), parts.next()) else { continue };
+        let Ok(index) = index.parse::<usize>() else { continue };
+        if let Some(WorkflowStep::Tool { params, .. }) = draft.workflow.get_mut(index) {
+            if !params.is_object() {
+                *params = json!({});
+            }
+            params[param] = Value::String(answer.answer.trim().to_string());
+        }
+    }
+}
+
+/// Applies a builder reply to the session, turning blanks in a finished draft into questions.
+fn apply_reply(session: &mut DraftSession, reply: BuilderReply) {
+    match reply {
+        BuilderReply::Questions(questions) => {
+            session.history.push(ChatMessage::assistant(json!({ "questions": questions }).to_string()));
+            session.questions = questions.into_iter()
+                .enumerate()
+                .map(|(i, question)| ClarifyQuestion { id: format!("q{}", i + 1), question })
+                .collect();
+            session.status = "needs_answers".into();
+        }
+        BuilderReply::Draft(draft) => {
+            session.history.push(ChatMessage::assistant(serde_json::to_string(&draft).unwrap_or_default()));
+            session.questions = questions_for_blanks(&draft);
+            session.status = if session.questions.is_empty() { "ready" } else { "needs_answers" }.into();
+            session.draft = Some(draft);
+        }
+    }
+    session.updated_at = Utc::now().to_rfc3339();
+}
+
+fn answer_session(db: &DbState, session_id: &str, answers: &[DraftAnswer]) -> Result<DraftSession, String> {
+    let mut session = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_session(&conn, session_id)?
+    };
+    if session.status != "needs_answers" {
+        return Err("This draft has no open questions".into());
+    }
+
+    let blanks_only = session.questions.iter().all(|q| q.id.starts_with("step:"));
+    if blanks_only {
+        // Filling in blanks is purely local; no need to go back to the model.
+        if let Some(draft) = session.draft.as_mut() {
+            fill_blanks(draft, answers);
+        }
+        let draft = session.draft.clone().ok_or("Draft session has no draft")?;
+        apply_reply(&mut session, BuilderReply::Draft(draft));
+    } else {
+        let transcript: Vec<String> = session.questions.iter()
+            .map(|q| {
+                let answer = answers.iter()
+                    .find(|a| a.id == q.id)
+                    .map(|a| a.answer.trim())
+                    .filter(|a| !a.is_empty())
+                    .unwrap_or("(no answer — use your best judgement)");
+                format!("Q: {}\nA: {answer}", q.question)
+            })
+            .collect();
+        session.history.push(ChatMessage::user(format!("Here are my answers:\n{}", transcript.join("\n"))));
+        session.rounds += 1;
+        let allow_questions = (session.rounds as usize) < MAX_CLARIFY_ROUNDS;
+        let reply = builder_turn(db, &session.description, &session.history, allow_questions);
+        apply_reply(&mut session, reply);
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store_session(&conn, &session)?;
+    Ok(session)
+}
+
+// ─── Builder Commands ───
+
+#[tauri::command]
+pub async fn draft_agent_from_description(app: AppHandle, text: String) -> Result<AgentDraft, String> {
+    if text.trim().is_empty() {
+        return Err("Describe what the agent should do".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let mut draft = draft_from_description(&db, &text);
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        sanitize::draft(&mut draft, sanitize::mode(&conn));
+        Ok(draft)
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// Starts a multi-turn draft; the reply either holds questions or a draft ready for review.
+#[tauri::command]
+pub async fn start_agent_draft(app: AppHandle, text: String) -> Result<DraftSession, String> {
+    if text.trim().is_empty() {
+        return Err("Describe what the agent should do".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let now = Utc::now().to_rfc3339();
+        let mut session = DraftSession {
+            id: Uuid::new_v4().to_string(),
+            description: text.trim().to_string(),
+            status: "needs_answers".into(),
+            questions: Vec::new(),
+            draft: None,
+            rounds: 0,
+            history: vec![ChatMessage::user(text.trim())],
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        let reply = builder_turn(&db, &session.description, &session.history, true);
+        apply_reply(&mut session, reply);
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        store_session(&conn, &session)?;
+        sanitize::session(&mut session, sanitize::mode(&conn));
+        Ok(session)
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn answer_draft_questions(app: AppHandle, session_id: String, answers: Vec<DraftAnswer>) -> Result<DraftSession, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let mut session = answer_session(&db, &session_id, &answers)?;
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        sanitize::session(&mut session, sanitize::mode(&conn));
+        Ok(session)
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_draft_session(db: State<DbState>, id: String) -> Result<DraftSession, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut session = load_session(&conn, &id)?;
+    sanitize::session(&mut session, sanitize::mode(&conn));
+    Ok(session)
+}
+
+/// Lists drafts that haven't been saved yet, most recently touched first.
+#[tauri::command]
+pub fn list_draft_sessions(db: State<DbState>) -> Result<Vec<DraftSession>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut sessions = query_sessions(&conn, "WHERE status != 'saved'", [])?;
+    let mode = sanitize::mode(&conn);
+    sessions.iter_mut().for_each(|s| sanitize::session(s, mode));
+    Ok(sessions)
+}
+
+#[tauri::command]
+pub fn discard_draft_session(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM agent_draft_sessions WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Saves a reviewed draft as a real agent, including its workflow.
+#[tauri::command]
+pub fn save_agent_draft(db: State<DbState>, draft: AgentDraft, session_id: Option<String>) -> Result<Agent, String> {
+    let draft = normalize_draft(draft);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    workflows::expand_workflow(&conn, &draft.workflow)?;
+    let agent = insert_agent(
+        &conn,
+        draft.name,
+        draft.role,
+        draft.goal,
+        draft.tools.join(","),
+        draft.schedule,
+        draft.sandbox,
+    )?;
+    workflows::store_agent_workflow(&conn, &agent.id, &draft.workflow)?;
+    if let Some(session_id) = session_id {
+        conn.execute(
+            "UPDATE agent_draft_sessions SET status = 'saved', updated_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), session_id],
+        ).map_err(|e| e.to_string())?;
+    }
+    let config_json = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent.id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    Ok(Agent { config_json, ..agent })
+}
+
+/// Turns a successful run, such as a quick task worth keeping, into a draft agent with the
+/// same steps and a schedule guessed from the instruction. Save it with `save_agent_draft`.
+#[tauri::command]
+pub fn promote_run_to_agent(db: State<DbState>, run_id: String) -> Result<AgentDraft, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut draft = draft_from_run(&conn, &run_id)?;
+    sanitize::draft(&mut draft, sanitize::mode(&conn));
+    Ok(draft)
+}
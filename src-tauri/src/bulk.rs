@@ -0,0 +1,117 @@
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::folders;
+use crate::{DbState, delete_agent_rows, update_agent_config};
+
+/// A change applied to every agent in a `bulk_agent_operation`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOp {
+    Enable,
+    /// Disabled agents don't start from automatic triggers; they can still be run by hand.
+    Disable,
+    Delete,
+    Tag {
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// `None` takes the agents out of any folder.
+    MoveToFolder {
+        #[serde(default)]
+        folder_id: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BulkItem {
+    pub id: String,
+    pub ok: bool,
+    pub error: String,
+}
+
+/// `applied` is false when any agent failed; nothing was changed then, and `items` says which
+/// agents were the problem.
+#[derive(Debug, Serialize, Clone)]
+pub struct BulkResult {
+    pub applied: bool,
+    pub items: Vec<BulkItem>,
+}
+
+/// Whether the agent may start from automatic triggers (`"enabled": false` in its config
+/// turns that off).
+pub fn enabled(config_json: &str) -> bool {
+    serde_json::from_str::<Value>(config_json).ok()
+        .and_then(|c| c.get("enabled").and_then(Value::as_bool))
+        .unwrap_or(true)
+}
+
+fn retag(config: &mut serde_json::Map<String, Value>, add: &[String], remove: &[String]) {
+    let mut tags: Vec<String> = config.get("tags")
+        .and_then(Value::as_array)
+        .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    tags.retain(|t| !remove.iter().any(|r| r.trim().eq_ignore_ascii_case(t)));
+    for tag in add.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    if tags.is_empty() {
+        config.remove("tags");
+    } else {
+        config.insert("tags".into(), json!(tags));
+    }
+}
+
+fn apply(conn: &Connection, id: &str, op: &BulkOp) -> Result<(), String> {
+    let exists: bool = conn.query_row("SELECT COUNT(*) FROM agents WHERE id = ?1", params![id], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())? > 0;
+    if !exists {
+        return Err("Agent not found".into());
+    }
+    match op {
+        BulkOp::Enable => update_agent_config(conn, id, |config| { config.remove("enabled"); }),
+        BulkOp::Disable => update_agent_config(conn, id, |config| { config.insert("enabled".into(), json!(false)); }),
+        BulkOp::Tag { add, remove } => update_agent_config(conn, id, |config| retag(config, add, remove)),
+        BulkOp::Delete => delete_agent_rows(conn, id),
+        BulkOp::MoveToFolder { folder_id } => {
+            conn.execute("UPDATE agents SET folder_id = ?1 WHERE id = ?2", params![folder_id, id])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        }
+    }
+}
+
+// ─── Bulk Commands ───
+
+/// Applies `op` to every agent in `ids` in one transaction: either all of them change or,
+/// if any fails, none do.
+#[tauri::command]
+pub fn bulk_agent_operation(db: State<DbState>, ids: Vec<String>, op: BulkOp) -> Result<BulkResult, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let BulkOp::MoveToFolder { folder_id: Some(folder_id) } = &op {
+        folders::load_folder(&conn, folder_id)?;
+    }
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut items: Vec<BulkItem> = ids.iter().map(|id| match apply(&tx, id, &op) {
+        Ok(()) => BulkItem { id: id.clone(), ok: true, error: String::new() },
+        Err(error) => BulkItem { id: id.clone(), ok: false, error },
+    }).collect();
+
+    let applied = items.iter().all(|item| item.ok);
+    if applied {
+        tx.commit().map_err(|e| e.to_string())?;
+    } else {
+        // Dropping the transaction rolls back the agents that did succeed.
+        for item in items.iter_mut().filter(|item| item.ok) {
+            item.ok = false;
+            item.error = "Not changed because another agent failed".into();
+        }
+    }
+    Ok(BulkResult { applied, items })
+}
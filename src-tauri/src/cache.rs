@@ -0,0 +1,166 @@
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+use crate::{DbState, read_setting, update_agent_config};
+
+/// `"false"` turns the cache off for every agent.
+const ENABLED_SETTING: &str = "response_cache_enabled";
+const TTL_SETTING: &str = "response_cache_ttl_hours";
+const MAX_SIZE_SETTING: &str = "response_cache_max_mb";
+const DEFAULT_TTL_HOURS: i64 = 24;
+const DEFAULT_MAX_MB: i64 = 50;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CacheStats {
+    pub entries: i64,
+    pub bytes: i64,
+    pub ttl_hours: i64,
+    pub max_bytes: i64,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS response_cache (
+            key TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            response TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            last_used_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_response_cache_used ON response_cache(last_used_at);
+    ").expect("Failed to initialize cache tables");
+}
+
+fn number_setting(conn: &Connection, key: &str, default: i64) -> i64 {
+    read_setting(conn, key).ok().flatten()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n: &i64| *n > 0)
+        .unwrap_or(default)
+}
+
+fn max_bytes(conn: &Connection) -> i64 {
+    number_setting(conn, MAX_SIZE_SETTING, DEFAULT_MAX_MB) * 1024 * 1024
+}
+
+/// Content address of a request: the SHA-256 of its JSON, whose object keys are always sorted.
+pub fn key(kind: &str, request: &Value) -> String {
+    Sha256::digest(format!("{kind}\n{request}").as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Whether `agent_id` may be answered from the cache. Agents opt out with `"cache": false`
+/// in their config; calls outside an agent (the builder) follow the global setting.
+pub fn enabled_for(conn: &Connection, agent_id: &str) -> bool {
+    if read_setting(conn, ENABLED_SETTING).ok().flatten().as_deref() == Some("false") {
+        return false;
+    }
+    let config: Option<String> = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).optional().ok().flatten();
+    config.and_then(|c| serde_json::from_str::<Value>(&c).ok())
+        .and_then(|c| c.get("cache").and_then(Value::as_bool))
+        .unwrap_or(true)
+}
+
+/// A stored response younger than the TTL, if any.
+pub fn get(conn: &Connection, key: &str) -> Option<String> {
+    get_within(conn, key, None)
+}
+
+/// [`get`] for answers that go stale sooner than the TTL, like a weather forecast.
+pub fn get_within(conn: &Connection, key: &str, max_age: Option<Duration>) -> Option<String> {
+    let ttl = Duration::hours(number_setting(conn, TTL_SETTING, DEFAULT_TTL_HOURS));
+    let cutoff = (Utc::now() - max_age.map_or(ttl, |age| age.min(ttl))).to_rfc3339();
+    let response: Option<String> = conn.query_row(
+        "SELECT response FROM response_cache WHERE key = ?1 AND created_at >= ?2",
+        params![key, cutoff],
+        |row| row.get(0),
+    ).optional().ok().flatten();
+    if response.is_some() {
+        let _ = conn.execute(
+            "UPDATE response_cache SET last_used_at = ?1 WHERE key = ?2",
+            params![Utc::now().to_rfc3339(), key],
+        );
+    }
+    response
+}
+
+/// Stores a response, then evicts expired and least recently used entries until the cache
+/// fits its size limit. Best effort: a failure here never fails the call being cached.
+pub fn put(conn: &Connection, kind: &str, key: &str, response: &str) {
+    let limit = max_bytes(conn);
+    let size = response.len() as i64;
+    if size > limit {
+        return;
+    }
+    let now = Utc::now();
+    let cutoff = (now - Duration::hours(number_setting(conn, TTL_SETTING, DEFAULT_TTL_HOURS))).to_rfc3339();
+    let _ = conn.execute(
+        "INSERT OR REPLACE INTO response_cache (key, kind, response, size, created_at, last_used_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+        params![key, kind, response, size, now.to_rfc3339()],
+    );
+    let _ = conn.execute("DELETE FROM response_cache WHERE created_at < ?1", params![cutoff]);
+
+    let Ok(mut stmt) = conn.prepare("SELECT key, size FROM response_cache ORDER BY last_used_at DESC") else {
+        return;
+    };
+    let Ok(rows) = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))) else {
+        return;
+    };
+    let mut total = 0;
+    let evict: Vec<String> = rows.filter_map(Result::ok)
+        .filter_map(|(key, size)| {
+            total += size;
+            (total > limit).then_some(key)
+        })
+        .collect();
+    for key in evict {
+        let _ = conn.execute("DELETE FROM response_cache WHERE key = ?1", params![key]);
+    }
+}
+
+// ─── Cache Commands ───
+
+#[tauri::command]
+pub fn get_cache_stats(db: State<DbState>) -> Result<CacheStats, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (entries, bytes) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM response_cache",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| e.to_string())?;
+    Ok(CacheStats {
+        entries,
+        bytes,
+        ttl_hours: number_setting(&conn, TTL_SETTING, DEFAULT_TTL_HOURS),
+        max_bytes: max_bytes(&conn),
+    })
+}
+
+/// Empties the cache, or only `llm` or `http` entries; returns how many were removed.
+#[tauri::command]
+pub fn clear_cache(db: State<DbState>, kind: Option<String>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM response_cache WHERE ?1 IS NULL OR kind = ?1", params![kind])
+        .map_err(|e| e.to_string())
+}
+
+/// Opts one agent out of (or back into) cached responses, e.g. for agents that should
+/// always see fresh data.
+#[tauri::command]
+pub fn set_agent_cache_enabled(db: State<DbState>, agent_id: String, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| {
+        if enabled {
+            config.remove("cache");
+        } else {
+            config.insert("cache".into(), json!(false));
+        }
+    })
+}
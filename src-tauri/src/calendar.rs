@@ -0,0 +1,408 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use tauri::{AppHandle, Manager, State};
+
+use crate::net;
+use crate::{DbState, read_setting, update_agent_config, write_setting};
+
+/// JSON list of calendar feeds: `https://` or `webcal://` addresses of ICS feeds, or paths
+/// to `.ics` files exported from a calendar app.
+pub(crate) const SOURCES_SETTING: &str = "calendar_sources";
+const FETCHED_SETTING: &str = "calendar_fetched_at";
+const ERROR_SETTING: &str = "calendar_error";
+/// How this device's last fetch went; another device fetches for itself.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[FETCHED_SETTING, ERROR_SETTING];
+const REFRESH_EVERY: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+const MAX_FEED_BYTES: u64 = 10 * 1024 * 1024;
+/// Occurrences are kept from a day back to this many days ahead.
+const HORIZON_DAYS: i64 = 14;
+/// Recurring events are followed at most this many days from their first occurrence.
+const MAX_RECURRENCE_DAYS: i64 = 3660;
+const WEEKDAYS: &[&str] = &["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+/// One occurrence of a calendar event.
+#[derive(Debug, Serialize, Clone)]
+pub struct CalendarEvent {
+    pub source: String,
+    pub summary: String,
+    pub starts_at: String,
+    pub ends_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CalendarStatus {
+    pub sources: Vec<String>,
+    pub events: i64,
+    pub fetched_at: Option<String>,
+    /// What went wrong with the last refresh, per feed.
+    pub error: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS calendar_events (
+            source TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            starts_at TEXT NOT NULL,
+            ends_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_calendar_events_time ON calendar_events(starts_at, ends_at);
+    ").expect("Failed to initialize calendar tables");
+}
+
+pub(crate) fn sources(conn: &Connection) -> Result<Vec<String>, String> {
+    Ok(read_setting(conn, SOURCES_SETTING)?
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default())
+}
+
+pub(crate) fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://") || source.starts_with("webcal://")
+}
+
+pub(crate) fn fetch(proxy: &net::Proxy, source: &str) -> Result<String, String> {
+    if !is_url(source) {
+        return std::fs::read_to_string(source).map_err(|e| format!("Couldn't read {source}: {e}"));
+    }
+    let url = match source.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{rest}"),
+        None => source.to_string(),
+    };
+    let resp = match proxy.agent(&url, FETCH_TIMEOUT)?.get(&url).call() {
+        Ok(resp) => resp,
+        Err(ureq::Error::Status(code, _)) => return Err(format!("{source} answered with status {code}")),
+        Err(e) => return Err(format!("Couldn't reach {source}: {e}")),
+    };
+    let mut body = String::new();
+    resp.into_reader().take(MAX_FEED_BYTES).read_to_string(&mut body)
+        .map_err(|e| format!("Couldn't read {source}: {e}"))?;
+    Ok(body)
+}
+
+/// A VEVENT with just what scheduling needs.
+#[derive(Default)]
+struct RawEvent {
+    summary: String,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    duration: Option<Duration>,
+    rrule: Option<String>,
+    exdates: Vec<DateTime<Utc>>,
+    all_day: bool,
+    free: bool,
+    cancelled: bool,
+}
+
+/// `20240531T093000Z` as UTC; a time without `Z`, floating or with a `TZID`, is taken as
+/// local time. A bare date marks an all-day event.
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d").map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default()))
+        .ok()?;
+    Some(naive.and_local_timezone(Local).earliest()?.with_timezone(&Utc))
+}
+
+/// `PT1H30M`, `P1D` and the like.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.trim().trim_start_matches(['+', 'P']).chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: i64 = std::mem::take(&mut number).parse().ok()?;
+                total += match unit {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(total)
+}
+
+/// Events in an ICS document, with folded lines joined back up.
+fn parse_ics(text: &str) -> Vec<RawEvent> {
+    let unfolded = text.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    let mut events = Vec::new();
+    let mut current: Option<RawEvent> = None;
+    for line in unfolded.lines() {
+        let Some((head, value)) = line.split_once(':') else { continue };
+        let name = head.split_once(';').map_or(head, |(name, _)| name);
+        match (name.to_ascii_uppercase().as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => current = Some(RawEvent::default()),
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => events.extend(current.take()),
+            ("SUMMARY", Some(event)) => event.summary = value.replace("\\,", ",").replace("\\;", ";").replace("\\n", " "),
+            ("DTSTART", Some(event)) => {
+                event.all_day = !value.contains('T');
+                event.start = parse_time(value);
+            }
+            ("DTEND", Some(event)) => event.end = parse_time(value),
+            ("DURATION", Some(event)) => event.duration = parse_duration(value),
+            ("RRULE", Some(event)) => event.rrule = Some(value.to_ascii_uppercase()),
+            ("EXDATE", Some(event)) => event.exdates.extend(value.split(',').filter_map(parse_time)),
+            ("TRANSP", Some(event)) => event.free = value.eq_ignore_ascii_case("TRANSPARENT"),
+            ("STATUS", Some(event)) => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Start times of `event` overlapping `from..to`. Daily and weekly rules with `INTERVAL`,
+/// `BYDAY`, `COUNT` and `UNTIL` are followed; other rules count only the first occurrence.
+fn occurrences(event: &RawEvent, length: Duration, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let Some(start) = event.start else { return Vec::new() };
+    let overlaps = |at: &DateTime<Utc>| *at < to && *at + length > from && !event.exdates.contains(at);
+    let Some(rule) = &event.rrule else { return [start].into_iter().filter(overlaps).collect() };
+    let part = |key: &str| rule.split(';').find_map(|p| p.strip_prefix(key).and_then(|v| v.strip_prefix('=')));
+    let weekly = match part("FREQ") {
+        Some("DAILY") => false,
+        Some("WEEKLY") => true,
+        _ => return [start].into_iter().filter(overlaps).collect(),
+    };
+    let interval = part("INTERVAL").and_then(|v| v.parse::<i64>().ok()).unwrap_or(1).max(1);
+    let count = part("COUNT").and_then(|v| v.parse::<usize>().ok());
+    let until = part("UNTIL").and_then(parse_time);
+    let local_start = start.with_timezone(&Local);
+    let by_day: Vec<usize> = match part("BYDAY") {
+        Some(days) => days.split(',').filter_map(|d| WEEKDAYS.iter().position(|w| d.ends_with(w))).collect(),
+        None => vec![local_start.weekday().num_days_from_monday() as usize],
+    };
+    let first_day = local_start.date_naive();
+    let first_monday = first_day - Duration::days(first_day.weekday().num_days_from_monday() as i64);
+
+    let mut found = Vec::new();
+    let mut seen = 0;
+    for offset in 0..MAX_RECURRENCE_DAYS {
+        let day = first_day + Duration::days(offset);
+        let included = if weekly {
+            (day - first_monday).num_days() / 7 % interval == 0 && by_day.contains(&(day.weekday().num_days_from_monday() as usize))
+        } else {
+            offset % interval == 0
+        };
+        if !included {
+            continue;
+        }
+        let Some(at) = day.and_time(local_start.time()).and_local_timezone(Local).earliest().map(|t| t.with_timezone(&Utc)) else { continue };
+        if at >= to || until.is_some_and(|u| at > u) || count.is_some_and(|c| seen >= c) {
+            break;
+        }
+        seen += 1;
+        if overlaps(&at) {
+            found.push(at);
+        }
+    }
+    found
+}
+
+/// Occurrences in `text` within the cache horizon, as (summary, start, end).
+fn expand(text: &str, now: DateTime<Utc>) -> Vec<(String, DateTime<Utc>, DateTime<Utc>)> {
+    let (from, to) = (now - Duration::days(1), now + Duration::days(HORIZON_DAYS));
+    parse_ics(text).into_iter()
+        .filter(|e| !e.all_day && !e.free && !e.cancelled)
+        .flat_map(|event| {
+            let length = match (event.start, event.end, event.duration) {
+                (Some(start), Some(end), _) if end > start => end - start,
+                (_, _, Some(duration)) => duration,
+                _ => Duration::zero(),
+            };
+            occurrences(&event, length, from, to).into_iter()
+                .map(|at| (event.summary.clone(), at, at + length))
+                .collect::<Vec<_>>()
+        })
+        .filter(|(_, start, end)| end > start)
+        .collect()
+}
+
+/// Re-reads every feed and replaces the cached events. A feed that can't be read keeps its
+/// previous events, so a flaky connection doesn't clear the calendar. The network calls run
+/// without holding the database.
+pub fn refresh(app: &AppHandle) -> Result<CalendarStatus, String> {
+    let db = app.state::<DbState>();
+    let (sources, proxy, local_only) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (sources(&conn)?, net::proxy(&conn)?, net::local_only(&conn))
+    };
+    let now = Utc::now();
+    let results: Vec<(String, Result<String, String>)> = sources.iter().map(|source| {
+        let text = if local_only && is_url(source) && !net::is_loopback(source) {
+            Err(format!("Skipped {source}: local-only mode is on"))
+        } else {
+            fetch(&proxy, source)
+        };
+        (source.clone(), text)
+    }).collect();
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM calendar_events WHERE source NOT IN (SELECT value FROM json_each(?1))", params![json!(sources).to_string()])
+        .map_err(|e| e.to_string())?;
+    let mut errors = Vec::new();
+    for (source, text) in results {
+        match text {
+            Ok(text) => {
+                tx.execute("DELETE FROM calendar_events WHERE source = ?1", params![source]).map_err(|e| e.to_string())?;
+                for (summary, start, end) in expand(&text, now) {
+                    tx.execute(
+                        "INSERT INTO calendar_events (source, summary, starts_at, ends_at) VALUES (?1, ?2, ?3, ?4)",
+                        params![source, summary, start.to_rfc3339(), end.to_rfc3339()],
+                    ).map_err(|e| e.to_string())?;
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    write_setting(&conn, FETCHED_SETTING, &now.to_rfc3339())?;
+    write_setting(&conn, ERROR_SETTING, &errors.join("\n"))?;
+    status(&conn)
+}
+
+fn status(conn: &Connection) -> Result<CalendarStatus, String> {
+    Ok(CalendarStatus {
+        sources: sources(conn)?,
+        events: conn.query_row("SELECT COUNT(*) FROM calendar_events", [], |row| row.get(0)).map_err(|e| e.to_string())?,
+        fetched_at: read_setting(conn, FETCHED_SETTING)?,
+        error: read_setting(conn, ERROR_SETTING)?.filter(|e| !e.is_empty()),
+    })
+}
+
+/// The agent's event filter: the text an event's title must contain to keep the agent
+/// from running, or `*` for every event. Several alternatives are separated by `|`.
+fn event_filter(conn: &Connection, agent_id: &str) -> Option<String> {
+    conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        .and_then(|config| config.get("avoid_events").and_then(Value::as_str).map(str::to_string))
+        .filter(|f| !f.trim().is_empty())
+}
+
+fn matches(filter: &str, summary: &str) -> bool {
+    let summary = summary.to_lowercase();
+    filter.split('|').map(str::trim).filter(|p| !p.is_empty())
+        .any(|p| p == "*" || summary.contains(&p.to_lowercase()))
+}
+
+/// The matching event in progress at `at` for an agent with an event filter, and when it
+/// ends. Overlapping and back-to-back matches are followed to the end of the last one.
+pub fn busy(conn: &Connection, agent_id: &str, at: DateTime<Local>) -> Result<Option<(String, DateTime<Local>)>, String> {
+    let Some(filter) = event_filter(conn, agent_id) else { return Ok(None) };
+    let at = at.with_timezone(&Utc);
+    let mut stmt = conn.prepare("SELECT summary, starts_at, ends_at FROM calendar_events WHERE ends_at > ?1 ORDER BY starts_at")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![at.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| e.to_string())?;
+    let parse = |t: &str| DateTime::parse_from_rfc3339(t).map(|t| t.with_timezone(&Utc)).ok();
+    let events: Vec<(String, DateTime<Utc>, DateTime<Utc>)> = rows
+        .filter_map(|row| row.ok())
+        .filter(|(summary, ..)| matches(&filter, summary))
+        .filter_map(|(summary, start, end)| Some((summary, parse(&start)?, parse(&end)?)))
+        .collect();
+    let Some((summary, _, mut until)) = events.iter().find(|(_, start, end)| *start <= at && at < *end).cloned() else {
+        return Ok(None);
+    };
+    for (_, start, end) in &events {
+        if *start <= until && *end > until {
+            until = *end;
+        }
+    }
+    Ok(Some((summary, until.with_timezone(&Local))))
+}
+
+/// Refreshes the feeds, if any are set up.
+pub fn refresh_if_configured(app: &AppHandle) {
+    let configured = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+        sources(&conn).is_ok_and(|s| !s.is_empty())
+    };
+    if configured {
+        if let Err(e) = refresh(app) {
+            eprintln!("Calendar refresh failed: {e}");
+        }
+    }
+}
+
+/// Starts the thread that keeps the cached events current.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("calendar-refresh".into())
+        .spawn(move || loop {
+            refresh_if_configured(&app);
+            std::thread::sleep(REFRESH_EVERY);
+        })
+        .expect("Failed to start the calendar refresher");
+}
+
+// ─── Calendar Commands ───
+
+#[tauri::command]
+pub fn get_calendar_status(db: State<DbState>) -> Result<CalendarStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    status(&conn)
+}
+
+/// Replaces the calendar feeds and reads them right away.
+#[tauri::command]
+pub async fn set_calendar_sources(app: AppHandle, sources: Vec<String>) -> Result<CalendarStatus, String> {
+    let sources: Vec<String> = sources.iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+    if let Some(source) = sources.iter().find(|s| !is_url(s) && !std::path::Path::new(s).is_file()) {
+        return Err(format!("{source} is neither a calendar address nor an .ics file"));
+    }
+    {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, SOURCES_SETTING, &json!(sources).to_string())?;
+    }
+    tauri::async_runtime::spawn_blocking(move || refresh(&app)).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn refresh_calendar(app: AppHandle) -> Result<CalendarStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || refresh(&app)).await.map_err(|e| e.to_string())?
+}
+
+/// Events from now until `hours` ahead (a day by default), soonest first.
+#[tauri::command]
+pub fn list_calendar_events(db: State<DbState>, hours: Option<i64>) -> Result<Vec<CalendarEvent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let now = Utc::now();
+    let to = now + Duration::hours(hours.unwrap_or(24).clamp(1, HORIZON_DAYS * 24));
+    let mut stmt = conn.prepare(
+        "SELECT source, summary, starts_at, ends_at FROM calendar_events WHERE ends_at > ?1 AND starts_at < ?2 ORDER BY starts_at"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![now.to_rfc3339(), to.to_rfc3339()], |row| Ok(CalendarEvent {
+        source: row.get(0)?,
+        summary: row.get(1)?,
+        starts_at: row.get(2)?,
+        ends_at: row.get(3)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Keeps an agent's automatic runs out of calendar events whose title contains `filter`
+/// (`*` for any event, `|` between alternatives); `None` lets it run through meetings.
+#[tauri::command]
+pub fn set_agent_event_filter(db: State<DbState>, agent_id: String, filter: Option<String>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let filter = filter.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+    update_agent_config(&conn, &agent_id, |config| match filter {
+        Some(filter) => { config.insert("avoid_events".into(), json!(filter)); }
+        None => { config.remove("avoid_events"); }
+    })
+}
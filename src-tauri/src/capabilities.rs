@@ -0,0 +1,272 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::State;
+
+use crate::llm::{self, ChatMessage, LlmRequest, LlmResponse, ProviderConfig, ToolCall};
+use crate::tokens;
+use crate::{DbState, read_setting, write_setting};
+
+/// Corrections to the built-in table, as JSON keyed by `provider/model`. Filled in by the user
+/// for gateways and local models the table doesn't know, and by the app when a provider
+/// rejects a feature the table said it had.
+const OVERRIDES_SETTING: &str = "llm_capabilities";
+/// A reply shorter than this isn't worth sending; the prompt is too long instead.
+const MIN_REPLY_TOKENS: usize = 256;
+
+/// Ollama models that can call tools, by name prefix.
+const LOCAL_TOOL_MODELS: &[&str] = &[
+    "llama3.1", "llama3.2", "llama3.3", "qwen2.5", "qwen3", "mistral", "mixtral", "mistral-nemo",
+    "command-r", "firefunction", "hermes3", "granite3", "smollm2", "nemotron",
+];
+/// Ollama models that can read images, by name prefix.
+const LOCAL_VISION_MODELS: &[&str] = &["llava", "bakllava", "llama3.2-vision", "moondream", "minicpm-v", "gemma3"];
+
+/// What a provider and model can do.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelCapabilities {
+    pub provider: String,
+    pub model: String,
+    pub streaming: bool,
+    pub tools: bool,
+    pub vision: bool,
+    pub json_mode: bool,
+    /// Tokens per request, prompt and reply together.
+    pub context_window: usize,
+    /// Fields that come from an override rather than the built-in table.
+    pub overridden: Vec<String>,
+}
+
+/// A partial correction; unset fields keep the built-in value.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CapabilityOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<usize>,
+}
+
+fn key(provider: &str, model: &str) -> String {
+    format!("{provider}/{model}")
+}
+
+fn overrides(conn: &Connection) -> Result<Map<String, Value>, String> {
+    Ok(read_setting(conn, OVERRIDES_SETTING)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+fn override_for(conn: &Connection, provider: &str, model: &str) -> Result<CapabilityOverride, String> {
+    Ok(overrides(conn)?.get(&key(provider, model))
+        .and_then(|o| serde_json::from_value(o.clone()).ok())
+        .unwrap_or_default())
+}
+
+fn save_override(conn: &Connection, provider: &str, model: &str, value: Option<&CapabilityOverride>) -> Result<(), String> {
+    let mut all = overrides(conn)?;
+    match value {
+        Some(value) => all.insert(key(provider, model), serde_json::to_value(value).map_err(|e| e.to_string())?),
+        None => all.remove(&key(provider, model)),
+    };
+    write_setting(conn, OVERRIDES_SETTING, &Value::Object(all).to_string())
+}
+
+/// A context window the user set for this model, which `llm::context_window` prefers.
+pub fn context_override(conn: &Connection, cfg: &ProviderConfig) -> Result<Option<usize>, String> {
+    Ok(override_for(conn, &cfg.provider, &cfg.model)?.context_window)
+}
+
+/// What the built-in table knows, before overrides.
+fn builtin(provider: &str, model: &str) -> (bool, bool, bool, bool) {
+    let starts = |prefixes: &[&str]| prefixes.iter().any(|p| model.starts_with(p));
+    // (streaming, tools, vision, json_mode)
+    match provider {
+        "openai" if starts(&["o1-mini", "o1-preview"]) => (false, false, false, false),
+        "openai" if starts(&["gpt-3.5-turbo-instruct", "davinci", "babbage"]) => (true, false, false, false),
+        "openai" if starts(&["gpt-4o", "gpt-4.1", "gpt-4-turbo", "gpt-5", "o1", "o3", "o4"]) => (true, true, true, true),
+        "openai" if starts(&["gpt-4-"]) || model == "gpt-4" => (true, true, false, false),
+        "openai" => (true, true, false, true),
+        "anthropic" if starts(&["claude-2", "claude-instant"]) => (true, false, false, false),
+        "anthropic" => (true, true, true, false),
+        _ => (true, starts(LOCAL_TOOL_MODELS), starts(LOCAL_VISION_MODELS), true),
+    }
+}
+
+pub fn lookup(conn: &Connection, cfg: &ProviderConfig) -> Result<ModelCapabilities, String> {
+    let (streaming, tools, vision, json_mode) = builtin(&cfg.provider, &cfg.model);
+    let o = override_for(conn, &cfg.provider, &cfg.model)?;
+    let overridden = [
+        ("streaming", o.streaming.is_some()),
+        ("tools", o.tools.is_some()),
+        ("vision", o.vision.is_some()),
+        ("json_mode", o.json_mode.is_some()),
+        ("context_window", o.context_window.is_some()),
+    ].iter().filter(|(_, set)| *set).map(|(name, _)| name.to_string()).collect();
+    Ok(ModelCapabilities {
+        provider: cfg.provider.clone(),
+        model: cfg.model.clone(),
+        streaming: o.streaming.unwrap_or(streaming),
+        tools: o.tools.unwrap_or(tools),
+        vision: o.vision.unwrap_or(vision),
+        json_mode: o.json_mode.unwrap_or(json_mode),
+        context_window: llm::context_window(conn, cfg)?,
+        overridden,
+    })
+}
+
+/// Tools described in the prompt, for models that can't be offered them natively.
+fn tool_prompt(req: &LlmRequest) -> String {
+    let listed = req.tools.iter()
+        .map(|t| format!("- {}: {}\n  Arguments (JSON schema): {}", t.name, t.description, t.parameters))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "\n\nYou can use these tools:\n{listed}\n\nTo use one, reply with only a JSON object \
+        {{\"tool\": \"<name>\", \"arguments\": {{...}}}}. To answer without a tool, reply with only \
+        {{\"answer\": \"<your answer>\"}}."
+    )
+}
+
+/// Earlier tool calls and results as plain conversation, since a model without tool support
+/// rejects or ignores the structured form.
+fn flatten_tool_messages(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    messages.iter().map(|m| match (m.role.as_str(), m.tool_calls.first()) {
+        ("tool", _) => ChatMessage::user(format!("Tool result:\n{}", m.content)),
+        (_, Some(call)) => ChatMessage::assistant(json!({ "tool": call.name, "arguments": call.arguments }).to_string()),
+        _ => m.clone(),
+    }).collect()
+}
+
+/// Reshapes `req` for what the model supports: JSON mode and tool calling become prompt
+/// instructions, and the reply is shortened to fit the context window. Returns whether tools
+/// are emulated, so the reply can be read back as a tool call.
+fn adapt(req: &LlmRequest, caps: &ModelCapabilities) -> Result<(LlmRequest, bool), String> {
+    let images: Vec<_> = req.messages.iter().flat_map(|m| &m.images).collect();
+    if !images.is_empty() && !caps.vision {
+        return Err(format!(
+            "{} can't look at images. Choose a model that can, such as gpt-4o, a Claude model, or llava for local use.",
+            caps.model,
+        ));
+    }
+    let mut adapted = req.clone();
+    let emulate_tools = !req.tools.is_empty() && !caps.tools;
+    if emulate_tools {
+        adapted.system.push_str(&tool_prompt(req));
+        adapted.messages = flatten_tool_messages(&req.messages);
+        adapted.tools.clear();
+        adapted.json = caps.json_mode;
+    }
+    if adapted.json && !caps.json_mode {
+        adapted.json = false;
+        adapted.system.push_str("\n\nReply with a single JSON object and nothing else.");
+    }
+
+    let text: String = std::iter::once(adapted.system.as_str())
+        .chain(adapted.messages.iter().map(|m| m.content.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = tokens::count(&caps.model, &text) + images.iter().map(|i| i.tokens(&caps.provider)).sum::<usize>();
+    let room = caps.context_window.saturating_sub(prompt);
+    if room < MIN_REPLY_TOKENS.min(adapted.max_tokens as usize) {
+        return Err(format!(
+            "This request is too long for {}: about {prompt} tokens, and the model takes {} in total. \
+            Shorten the input or choose a model with a larger context window.",
+            caps.model, caps.context_window,
+        ));
+    }
+    adapted.max_tokens = adapted.max_tokens.min(room as u32);
+    Ok((adapted, emulate_tools))
+}
+
+/// Reads an emulated tool call back out of a plain reply.
+fn read_emulated(req: &LlmRequest, mut resp: LlmResponse) -> LlmResponse {
+    let Ok(reply) = llm::extract_json(&resp.text) else { return resp };
+    if let Some(tool) = reply["tool"].as_str().filter(|t| req.tools.iter().any(|d| d.name == *t)) {
+        let arguments = if reply["arguments"].is_object() { reply["arguments"].clone() } else { json!({}) };
+        resp.tool_calls = vec![ToolCall { id: "call_0".into(), name: tool.to_string(), arguments }];
+        resp.text = String::new();
+    } else if let Some(answer) = reply["answer"].as_str() {
+        resp.text = answer.to_string();
+    }
+    resp
+}
+
+/// A provider error saying the model lacks a feature the table claimed.
+fn unsupported(error: &str) -> Option<&'static str> {
+    let e = error.to_lowercase();
+    if e.contains("does not support tools") || (e.contains("tool") && e.contains("not supported")) {
+        Some("tools")
+    } else if e.contains("response_format") || e.contains("json mode") || e.contains("does not support json") {
+        Some("json_mode")
+    } else {
+        None
+    }
+}
+
+/// Calls the provider in a way the model supports. When the provider rejects a feature anyway,
+/// the call is retried without it; the returned field name lets the caller remember that.
+pub fn call(cfg: &ProviderConfig, caps: &ModelCapabilities, req: &LlmRequest) -> Result<(LlmResponse, Option<&'static str>), String> {
+    let (adapted, emulated) = adapt(req, caps)?;
+    let (resp, emulated, learned) = match llm::call_provider(cfg, &adapted) {
+        Err(e) => {
+            let Some(field) = unsupported(&e) else { return Err(e) };
+            let mut reduced = caps.clone();
+            match field {
+                "tools" => reduced.tools = false,
+                _ => reduced.json_mode = false,
+            }
+            if reduced.tools == caps.tools && reduced.json_mode == caps.json_mode {
+                return Err(e);
+            }
+            let (adapted, emulated) = adapt(req, &reduced)?;
+            (llm::call_provider(cfg, &adapted)?, emulated, Some(field))
+        }
+        Ok(resp) => (resp, emulated, None),
+    };
+    Ok((if emulated { read_emulated(req, resp) } else { resp }, learned))
+}
+
+/// Records that the model turned out not to support `field`.
+pub fn learn(conn: &Connection, cfg: &ProviderConfig, field: &str) -> Result<(), String> {
+    let mut o = override_for(conn, &cfg.provider, &cfg.model)?;
+    match field {
+        "tools" => o.tools = Some(false),
+        "json_mode" => o.json_mode = Some(false),
+        _ => return Ok(()),
+    }
+    save_override(conn, &cfg.provider, &cfg.model, Some(&o))
+}
+
+// ─── Capability Commands ───
+
+/// What a model supports; the active provider and model unless both are given.
+#[tauri::command]
+pub fn get_model_capabilities(db: State<DbState>, provider: Option<String>, model: Option<String>) -> Result<ModelCapabilities, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut cfg = llm::provider_config(&conn)?;
+    if let (Some(provider), Some(model)) = (provider, model) {
+        cfg.provider = provider;
+        cfg.model = model;
+    }
+    lookup(&conn, &cfg)
+}
+
+/// Corrects what the app assumes about a model, e.g. a local model that can call tools.
+/// `None` goes back to the built-in table.
+#[tauri::command]
+pub fn set_model_capabilities(
+    db: State<DbState>,
+    provider: String,
+    model: String,
+    overrides: Option<CapabilityOverride>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_override(&conn, &provider, &model, overrides.as_ref())
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+
+/// Folders with more entries than this are only partly compared.
+const MAX_ENTRIES: usize = 5000;
+
+/// Something a run changed in a folder it wrote to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileChange {
+    /// `created`, `modified`, `moved` or `deleted`.
+    pub kind: String,
+    pub path: String,
+    /// Where a moved file ended up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// e.g. "Moved invoice.pdf from ~/Downloads to ~/Documents".
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Entry {
+    size: u64,
+    modified_ms: i64,
+    dir: bool,
+}
+
+type Manifest = BTreeMap<String, Entry>;
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS run_snapshots (
+            run_id TEXT NOT NULL,
+            dir TEXT NOT NULL,
+            manifest_json TEXT NOT NULL,
+            PRIMARY KEY (run_id, dir)
+        );
+    ").expect("Failed to initialize run snapshot tables");
+}
+
+fn manifest(dir: &Path) -> Manifest {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Manifest::new() };
+    entries.flatten().take(MAX_ENTRIES).filter_map(|entry| {
+        let meta = entry.metadata().ok()?;
+        let modified_ms = meta.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as i64);
+        Some((entry.file_name().to_string_lossy().to_string(), Entry { size: meta.len(), modified_ms, dir: meta.is_dir() }))
+    }).collect()
+}
+
+/// Records how `dirs` look before the run first writes in them; later steps in the same
+/// folder keep the first snapshot.
+pub fn before(conn: &Connection, run_id: &str, dirs: &[PathBuf]) -> Result<(), String> {
+    for dir in dirs {
+        let manifest = serde_json::to_string(&manifest(dir)).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR IGNORE INTO run_snapshots (run_id, dir, manifest_json) VALUES (?1, ?2, ?3)",
+            params![run_id, dir.to_string_lossy(), manifest],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// `~/Documents` rather than the full home path.
+fn display(dir: &str) -> String {
+    let home = dirs_next::home_dir().map(|h| h.to_string_lossy().to_string()).unwrap_or_default();
+    match dir.strip_prefix(&home) {
+        Some(rest) if !home.is_empty() => format!("~{rest}"),
+        _ => dir.to_string(),
+    }
+}
+
+/// Compares the snapshots with the folders now, and forgets the snapshots. A file that
+/// disappeared and one that appeared with the same size and time count as a move.
+pub fn after(conn: &Connection, run_id: &str) -> Result<Vec<FileChange>, String> {
+    let mut stmt = conn.prepare("SELECT dir, manifest_json FROM run_snapshots WHERE run_id = ?1 ORDER BY dir")
+        .map_err(|e| e.to_string())?;
+    let snapshots = stmt.query_map(params![run_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    if snapshots.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut created = Vec::new();
+    let mut deleted = Vec::new();
+    let mut changes = Vec::new();
+    for (dir, before) in snapshots {
+        let before: Manifest = serde_json::from_str(&before).unwrap_or_default();
+        let now = manifest(Path::new(&dir));
+        for (name, entry) in &before {
+            match now.get(name) {
+                None => deleted.push((dir.clone(), name.clone(), entry.clone())),
+                Some(current) if !entry.dir && current != entry => changes.push(FileChange {
+                    kind: "modified".into(),
+                    path: Path::new(&dir).join(name).to_string_lossy().to_string(),
+                    to: None,
+                    description: format!("Changed {name} in {}", display(&dir)),
+                }),
+                Some(_) => {}
+            }
+        }
+        created.extend(now.into_iter().filter(|(name, _)| !before.contains_key(name)).map(|(name, entry)| (dir.clone(), name, entry)));
+    }
+
+    for (dir, name, entry) in deleted {
+        let from = Path::new(&dir).join(&name).to_string_lossy().to_string();
+        // Prefer the same name when several files could be the one that moved.
+        let found = created.iter().position(|(_, n, e)| *e == entry && *n == name)
+            .or_else(|| created.iter().position(|(_, _, e)| *e == entry));
+        match found.map(|i| created.remove(i)) {
+            Some((to_dir, to_name, _)) => {
+                let description = if to_dir == dir {
+                    format!("Renamed {name} to {to_name} in {}", display(&dir))
+                } else if to_name == name {
+                    format!("Moved {name} from {} to {}", display(&dir), display(&to_dir))
+                } else {
+                    format!("Moved {name} from {} to {} as {to_name}", display(&dir), display(&to_dir))
+                };
+                changes.push(FileChange {
+                    kind: "moved".into(),
+                    path: from,
+                    to: Some(Path::new(&to_dir).join(&to_name).to_string_lossy().to_string()),
+                    description,
+                });
+            }
+            None => changes.push(FileChange {
+                kind: "deleted".into(),
+                path: from,
+                to: None,
+                description: format!("Deleted {name} from {}", display(&dir)),
+            }),
+        }
+    }
+    changes.extend(created.into_iter().map(|(dir, name, _)| FileChange {
+        kind: "created".into(),
+        path: Path::new(&dir).join(&name).to_string_lossy().to_string(),
+        to: None,
+        description: format!("Created {name} in {}", display(&dir)),
+    }));
+    conn.execute("DELETE FROM run_snapshots WHERE run_id = ?1", params![run_id]).map_err(|e| e.to_string())?;
+    Ok(changes)
+}
+
+/// One line for the run log, e.g. "Files: 2 created, 1 moved".
+pub fn summary(changes: &[FileChange]) -> Option<String> {
+    if changes.is_empty() {
+        return None;
+    }
+    let counts: Vec<String> = ["created", "modified", "moved", "deleted"].iter()
+        .map(|kind| (kind, changes.iter().filter(|c| c.kind == *kind).count()))
+        .filter(|(_, n)| *n > 0)
+        .map(|(kind, n)| format!("{n} {kind}"))
+        .collect();
+    Some(format!("Files: {}", counts.join(", ")))
+}
@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Datelike, Duration, Utc};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use rusqlite::{Connection, OptionalExtension, params};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::net;
+use crate::priority;
+use crate::qr;
+use crate::routing;
+use crate::sanitize::{self, Mode};
+use crate::{DbState, app_data_dir, query_approvals, read_setting, write_setting};
+
+/// The phone view only runs while this is `"true"`; pairing turns it on.
+const ENABLED_SETTING: &str = "companion_enabled";
+const PORT_SETTING: &str = "companion_port";
+/// Two above the OpenClaw gateway's default port, next to the local API.
+const DEFAULT_PORT: u16 = 18791;
+const KEYCHAIN_KEY_ENTRY: &str = "companion-tls-key";
+const CERTIFICATE_FILE: &str = "companion-cert.der";
+const COOKIE: &str = "ocl_companion";
+const PAIRING_MINUTES: i64 = 10;
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+/// Connections handled at once; more are closed straight away rather than each getting a thread.
+const MAX_CONNECTIONS: usize = 16;
+
+/// A phone paired by scanning the code; it can see approvals and runs and answer approvals.
+#[derive(Debug, Serialize, Clone)]
+pub struct CompanionDevice {
+    pub id: String,
+    /// From the phone's browser, e.g. "iPhone".
+    pub name: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CompanionPairing {
+    pub url: String,
+    /// The URL as a QR code, in SVG.
+    pub qr_svg: String,
+    /// SHA-256 of the certificate, to compare with what the phone's browser shows.
+    pub fingerprint: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CompanionStatus {
+    pub running: bool,
+    /// `https://<LAN address>:<port>` while running.
+    pub address: Option<String>,
+    pub fingerprint: Option<String>,
+    pub devices: Vec<CompanionDevice>,
+}
+
+struct Pairing {
+    code: String,
+    expires_at: DateTime<Utc>,
+}
+
+struct Running {
+    stop: Arc<AtomicBool>,
+    port: u16,
+    fingerprint: String,
+}
+
+/// The running server, if any, and the pairing code waiting to be scanned.
+#[derive(Default)]
+pub struct Companion {
+    running: Mutex<Option<Running>>,
+    pairing: Mutex<Option<Pairing>>,
+}
+
+impl Companion {
+    /// The port and certificate fingerprint while the phone view is running.
+    pub fn serving(&self) -> Option<(u16, String)> {
+        self.running.lock().ok()?.as_ref().map(|r| (r.port, r.fingerprint.clone()))
+    }
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS companion_devices (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            fingerprint TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            last_seen_at TEXT DEFAULT '',
+            revoked INTEGER DEFAULT 0
+        );
+    ").expect("Failed to initialize companion tables");
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn port(conn: &Connection) -> u16 {
+    read_setting(conn, PORT_SETTING).ok().flatten()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT)
+}
+
+/// The address phones on the same network reach this computer at.
+pub fn lan_address() -> Option<String> {
+    // Connecting a UDP socket sends nothing; it only picks the outgoing interface.
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_loopback() && !ip.is_unspecified()).then(|| ip.to_string())
+}
+
+// ─── Certificate ───
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn sequence(parts: &[Vec<u8>]) -> Vec<u8> {
+    der(0x30, &parts.concat())
+}
+
+fn utc_time(at: DateTime<Utc>) -> Vec<u8> {
+    let text = if at.year() < 2050 { at.format("%y%m%d%H%M%SZ") } else { at.format("%Y%m%d%H%M%SZ") }.to_string();
+    der(if at.year() < 2050 { 0x17 } else { 0x18 }, text.as_bytes())
+}
+
+/// A self-signed ECDSA P-256 certificate for `key`, made by hand since only the phone's
+/// browser ever checks it.
+fn self_signed(key: &EcdsaKeyPair, rng: &SystemRandom) -> Result<Vec<u8>, String> {
+    let ecdsa_with_sha256 = sequence(&[der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02])]);
+    let name = sequence(&[der(0x31, &sequence(&[der(0x06, &[0x55, 0x04, 0x03]), der(0x0c, b"OpenClaw Desktop")]))]);
+    let mut serial = [0u8; 16];
+    OsRng.fill_bytes(&mut serial);
+    serial[0] = (serial[0] & 0x7f) | 0x01;
+    let now = Utc::now();
+    let mut public_key = vec![0u8];
+    public_key.extend_from_slice(key.public_key().as_ref());
+    let spki = sequence(&[
+        sequence(&[
+            der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01]),
+            der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07]),
+        ]),
+        der(0x03, &public_key),
+    ]);
+    let tbs = sequence(&[
+        der(0xa0, &der(0x02, &[2])),
+        der(0x02, &serial),
+        ecdsa_with_sha256.clone(),
+        name.clone(),
+        sequence(&[utc_time(now - Duration::days(1)), utc_time(now + Duration::days(3650))]),
+        name,
+        spki,
+    ]);
+    let signature = key.sign(rng, &tbs).map_err(|_| "Couldn't sign the certificate")?;
+    let mut signature_bits = vec![0u8];
+    signature_bits.extend_from_slice(signature.as_ref());
+    Ok(sequence(&[tbs, ecdsa_with_sha256, der(0x03, &signature_bits)]))
+}
+
+/// The key (kept in the system keychain) and certificate the phone view serves, made on
+/// first use and then kept, so a phone's browser only has to trust it once.
+fn identity() -> Result<(Vec<u8>, Vec<u8>), String> {
+    let entry = keyring::Entry::new(net::KEYCHAIN_SERVICE, KEYCHAIN_KEY_ENTRY)
+        .map_err(|e| format!("The system keychain is unavailable: {e}"))?;
+    let rng = SystemRandom::new();
+    let cert_path = app_data_dir().join(CERTIFICATE_FILE);
+    let stored = match entry.get_password() {
+        Ok(hex) => (0..hex.len()).step_by(2)
+            .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect::<Option<Vec<u8>>>(),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => return Err(format!("Couldn't read the phone view key from the system keychain: {e}")),
+    };
+    if let Some(pkcs8) = stored.filter(|k| EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, k, &rng).is_ok()) {
+        if let Ok(cert) = std::fs::read(&cert_path) {
+            return Ok((pkcs8, cert));
+        }
+    }
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+        .map_err(|_| "Couldn't make a key for the phone view")?.as_ref().to_vec();
+    let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &pkcs8, &rng).map_err(|e| e.to_string())?;
+    let cert = self_signed(&key, &rng)?;
+    entry.set_password(&hex(&pkcs8)).map_err(|e| format!("Couldn't save the phone view key to the system keychain: {e}"))?;
+    std::fs::write(&cert_path, &cert).map_err(|e| format!("Couldn't save the phone view certificate: {e}"))?;
+    Ok((pkcs8, cert))
+}
+
+/// `AB:CD:…`, as browsers show it.
+fn fingerprint(cert: &[u8]) -> String {
+    Sha256::digest(cert).iter().map(|b| format!("{b:02X}")).collect::<Vec<_>>().join(":")
+}
+
+// ─── Server ───
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: String,
+    headers: HashMap<String, String>,
+}
+
+struct HttpResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+fn html(status: u16, body: String) -> HttpResponse {
+    HttpResponse { status, headers: Vec::new(), body }
+}
+
+fn redirect(to: &str) -> HttpResponse {
+    HttpResponse { status: 303, headers: vec![("Location", to.into())], body: String::new() }
+}
+
+fn read_request(stream: &mut impl Read) -> Option<HttpRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 2048];
+    let end = loop {
+        let n = stream.read(&mut chunk).ok().filter(|n| *n > 0)?;
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return None;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut first = lines.next()?.split_whitespace();
+    let (method, target) = (first.next()?.to_string(), first.next()?);
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    // Forms post no fields; whatever body arrives is left unread.
+    Some(HttpRequest { method, path: path.to_string(), query: query.to_string(), headers })
+}
+
+fn write_response(stream: &mut impl Write, response: HttpResponse) {
+    let reason = match response.status {
+        200 => "OK",
+        303 => "See Other",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Error",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {reason}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+        response.status, response.body.len(),
+    );
+    for (name, value) in response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(response.body.as_bytes());
+    let _ = stream.flush();
+}
+
+fn device_name(user_agent: &str) -> String {
+    ["iPhone", "iPad", "Android"].iter()
+        .find(|d| user_agent.contains(*d))
+        .map_or("Phone".into(), |d| if *d == "Android" { "Android phone".into() } else { d.to_string() })
+}
+
+/// The paired device a request's cookie belongs to, if it was paired with the current
+/// certificate.
+fn device(conn: &Connection, request: &HttpRequest, fingerprint: &str) -> Option<(String, String)> {
+    let token = request.headers.get("cookie")?
+        .split(';')
+        .filter_map(|c| c.trim().split_once('='))
+        .find(|(k, _)| *k == COOKIE)
+        .map(|(_, v)| v.to_string())?;
+    let found: Option<(String, String)> = conn.query_row(
+        "SELECT id, name FROM companion_devices WHERE token_hash = ?1 AND fingerprint = ?2 AND revoked = 0",
+        params![hash_token(&token), fingerprint],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().ok().flatten();
+    if let Some((id, _)) = &found {
+        let _ = conn.execute("UPDATE companion_devices SET last_seen_at = ?1 WHERE id = ?2", params![Utc::now().to_rfc3339(), id]);
+    }
+    found
+}
+
+fn pair(app: &AppHandle, request: &HttpRequest, fingerprint: &str) -> HttpResponse {
+    let code = request.query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == "code")
+        .map(|(_, v)| v.to_string())
+        .unwrap_or_default();
+    let valid = {
+        let companion = app.state::<Companion>();
+        let mut pairing = companion.pairing.lock().unwrap_or_else(|e| e.into_inner());
+        let valid = pairing.as_ref().is_some_and(|p| p.code == code && p.expires_at > Utc::now());
+        if valid {
+            *pairing = None;
+        }
+        valid
+    };
+    if !valid {
+        return html(401, routing::page("Code not valid", "<p>This code was already used or has expired. Show a new one in OpenClaw Desktop.</p>"));
+    }
+    let token = random_hex(32);
+    let name = device_name(request.headers.get("user-agent").map_or("", String::as_str));
+    let db = app.state::<DbState>();
+    let Ok(conn) = db.0.lock() else {
+        return html(500, routing::page("Something went wrong", "<p>Try again in a moment.</p>"));
+    };
+    if conn.execute(
+        "INSERT INTO companion_devices (id, name, token_hash, fingerprint, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![Uuid::new_v4().to_string(), name, hash_token(&token), fingerprint, Utc::now().to_rfc3339()],
+    ).is_err() {
+        return html(500, routing::page("Something went wrong", "<p>Try again in a moment.</p>"));
+    }
+    let mut response = redirect("/");
+    response.headers.push(("Set-Cookie", format!("{COOKIE}={token}; Path=/; Max-Age=31536000; Secure; HttpOnly; SameSite=Strict")));
+    response
+}
+
+fn dashboard(conn: &Connection, fingerprint: &str) -> Result<String, String> {
+    let mut approvals: Vec<_> = query_approvals(conn)?.into_iter().filter(|a| a.status == "pending").collect();
+    priority::sort(&mut approvals, None, None)?;
+    let mut body = String::from("<h2>Waiting for you</h2>");
+    if approvals.is_empty() {
+        body.push_str("<p>Nothing to approve.</p>");
+    }
+    for item in &approvals {
+        let badge = if priority::rank(&item.priority) >= 2 { format!(" <strong>{}</strong>", item.priority) } else { String::new() };
+        body.push_str(&format!(
+            "<div style=\"border:1px solid #ccc;border-radius:8px;padding:.75rem;margin:.5rem 0\"><p>{}{badge}</p>\
+             <form method=\"post\" action=\"/approvals/{id}/approved\" style=\"display:inline\"><button>Approve</button></form> \
+             <form method=\"post\" action=\"/approvals/{id}/rejected\" style=\"display:inline\"><button>Deny</button></form></div>",
+            sanitize::clean(&item.content_preview, Mode::Escape),
+            id = sanitize::clean(&item.id, Mode::Escape),
+        ));
+    }
+
+    body.push_str("<h2>Recent runs</h2><ul>");
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(a.name, r.agent_id), r.status, r.started_at FROM runs r LEFT JOIN agents a ON a.id = r.agent_id
+         WHERE r.mode = 'live' ORDER BY r.started_at DESC LIMIT 15"
+    ).map_err(|e| e.to_string())?;
+    let runs = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| e.to_string())?;
+    for run in runs {
+        let (agent, status, started_at) = run.map_err(|e| e.to_string())?;
+        let started = DateTime::parse_from_rfc3339(&started_at)
+            .map(|t| t.with_timezone(&chrono::Local).format("%a %H:%M").to_string())
+            .unwrap_or(started_at);
+        body.push_str(&format!(
+            "<li>{} — {} <small>({started})</small></li>",
+            sanitize::clean(&agent, Mode::Escape),
+            status.replace('_', " "),
+        ));
+    }
+    body.push_str(&format!("</ul><p><small>Certificate {fingerprint}</small></p>"));
+    Ok(routing::page("OpenClaw", &format!("<meta http-equiv=\"refresh\" content=\"30\">{body}")))
+}
+
+fn respond(app: &AppHandle, request: &HttpRequest, fingerprint: &str) -> HttpResponse {
+    if request.method == "GET" && request.path == "/pair" {
+        return pair(app, request, fingerprint);
+    }
+    let db = app.state::<DbState>();
+    let Ok(conn) = db.0.lock() else {
+        return html(500, routing::page("Something went wrong", "<p>Try again in a moment.</p>"));
+    };
+    let Some((_, name)) = device(&conn, request, fingerprint) else {
+        return html(401, routing::page("Not paired", "<p>Scan the pairing code in OpenClaw Desktop to use this phone.</p>"));
+    };
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", [""]) => match dashboard(&conn, fingerprint) {
+            Ok(page) => html(200, page),
+            Err(e) => html(500, routing::page("Something went wrong", &format!("<p>{}</p>", sanitize::clean(&e, Mode::Escape)))),
+        },
+        // The cookie is SameSite=Strict, so other sites can't post these for the phone.
+        ("POST", ["approvals", id, decision @ ("approved" | "rejected")]) => {
+            routing::decide(app, conn, id, decision, &name, "a paired phone");
+            redirect("/")
+        }
+        _ => html(404, routing::page("Not found", "<p><a href=\"/\">Back</a></p>")),
+    }
+}
+
+/// Held while a connection is handled, so the server knows how many are open.
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle_connection(app: &AppHandle, config: Arc<ServerConfig>, stream: TcpStream, fingerprint: &str) {
+    let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(10)));
+    let _ = stream.set_write_timeout(Some(std::time::Duration::from_secs(10)));
+    let Ok(connection) = ServerConnection::new(config) else { return };
+    let mut tls = StreamOwned::new(connection, stream);
+    let Some(request) = read_request(&mut tls) else { return };
+    let response = respond(app, &request, fingerprint);
+    write_response(&mut tls, response);
+    tls.conn.send_close_notify();
+    let _ = tls.flush();
+}
+
+/// Starts serving the phone view on the local network, if it isn't already.
+fn serve(app: &AppHandle) -> Result<(), String> {
+    let companion = app.state::<Companion>();
+    let mut running = companion.running.lock().map_err(|e| e.to_string())?;
+    if running.is_some() {
+        return Ok(());
+    }
+    let (key, cert) = identity()?;
+    let fingerprint = fingerprint(&cert);
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(vec![CertificateDer::from(cert)], PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key)))
+        .map_err(|e| format!("The phone view certificate is unusable: {e}"))?;
+    let config = Arc::new(config);
+    let port = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        port(&conn)
+    };
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| format!("The phone view couldn't listen on port {port}: {e}"))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+    let flag = Arc::new(AtomicBool::new(false));
+    *running = Some(Running { stop: flag.clone(), port, fingerprint: fingerprint.clone() });
+    let app = app.clone();
+    let open = Arc::new(AtomicUsize::new(0));
+    std::thread::Builder::new()
+        .name("companion-server".into())
+        .spawn(move || {
+            while !flag.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            open.fetch_sub(1, Ordering::SeqCst);
+                            continue;
+                        }
+                        let guard = ConnectionGuard(open.clone());
+                        let _ = stream.set_nonblocking(false);
+                        let (app, config, fingerprint) = (app.clone(), config.clone(), fingerprint.clone());
+                        std::thread::spawn(move || {
+                            let _guard = guard;
+                            handle_connection(&app, config, stream, &fingerprint);
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(std::time::Duration::from_millis(200)),
+                    Err(e) => {
+                        eprintln!("Phone view connection failed: {e}");
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                    }
+                }
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Serves the phone view at launch when it was left on.
+pub fn start(app: AppHandle) {
+    let enabled = {
+        let db = app.state::<DbState>();
+        let Ok(conn) = db.0.lock() else { return };
+        read_setting(&conn, ENABLED_SETTING).ok().flatten().as_deref() == Some("true")
+    };
+    if enabled {
+        if let Err(e) = serve(&app) {
+            eprintln!("Phone view didn't start: {e}");
+        }
+    }
+}
+
+// ─── Companion Commands ───
+
+/// Starts the phone view if needed and makes a one-time pairing code, good for ten minutes,
+/// for a phone on the same network to scan.
+#[tauri::command]
+pub fn start_companion_pairing(app: AppHandle) -> Result<CompanionPairing, String> {
+    let address = lan_address().ok_or("This computer doesn't seem to be on a local network")?;
+    let port = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, ENABLED_SETTING, "true")?;
+        port(&conn)
+    };
+    serve(&app)?;
+    let (_, fingerprint) = app.state::<Companion>().serving().ok_or("The phone view isn't running")?;
+    let code = random_hex(16);
+    let expires_at = Utc::now() + Duration::minutes(PAIRING_MINUTES);
+    *app.state::<Companion>().pairing.lock().map_err(|e| e.to_string())? = Some(Pairing { code: code.clone(), expires_at });
+    let url = format!("https://{address}:{port}/pair?code={code}");
+    Ok(CompanionPairing { qr_svg: qr::svg(&url)?, url, fingerprint, expires_at: expires_at.to_rfc3339() })
+}
+
+#[tauri::command]
+pub fn get_companion_status(app: AppHandle) -> Result<CompanionStatus, String> {
+    let serving = app.state::<Companion>().serving();
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT id, name, created_at, last_seen_at FROM companion_devices WHERE revoked = 0 ORDER BY created_at DESC")
+        .map_err(|e| e.to_string())?;
+    let devices = stmt.query_map([], |row| Ok(CompanionDevice {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        last_seen_at: row.get(3)?,
+    })).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let address = serving.as_ref().and_then(|(port, _)| lan_address().map(|ip| format!("https://{ip}:{port}")));
+    Ok(CompanionStatus { running: serving.is_some(), address, fingerprint: serving.map(|(_, f)| f), devices })
+}
+
+#[tauri::command]
+pub fn revoke_companion_device(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("UPDATE companion_devices SET revoked = 1 WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Stops the phone view. Paired phones stay paired and work again once it is back on.
+#[tauri::command]
+pub fn stop_companion(app: AppHandle) -> Result<(), String> {
+    {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, ENABLED_SETTING, "false")?;
+    }
+    let companion = app.state::<Companion>();
+    *companion.pairing.lock().map_err(|e| e.to_string())? = None;
+    if let Some(running) = companion.running.lock().map_err(|e| e.to_string())?.take() {
+        running.stop.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+
+    /// The tag, content and what follows of the DER value at the start of `bytes`.
+    fn read(bytes: &[u8]) -> (u8, &[u8], &[u8]) {
+        let (tag, first) = (bytes[0], bytes[1] as usize);
+        let (len, start) = match first {
+            0..0x80 => (first, 2),
+            _ => {
+                let count = first & 0x7f;
+                (bytes[2..2 + count].iter().fold(0, |len, b| len << 8 | *b as usize), 2 + count)
+            }
+        };
+        (tag, &bytes[start..start + len], &bytes[start + len..])
+    }
+
+    /// The values inside a constructed DER value, with their tags.
+    fn children(mut content: &[u8]) -> Vec<(u8, &[u8])> {
+        let mut out = Vec::new();
+        while !content.is_empty() {
+            let (tag, inner, rest) = read(content);
+            out.push((tag, inner));
+            content = rest;
+        }
+        out
+    }
+
+    #[test]
+    fn encodes_der_lengths() {
+        assert_eq!(der(0x04, b"hi"), [0x04, 0x02, b'h', b'i']);
+        assert_eq!(der(0x05, &[]), [0x05, 0x00]);
+        assert_eq!(der(0x04, &[7; 127])[..2], [0x04, 0x7f]);
+        assert_eq!(der(0x04, &[7; 128])[..3], [0x04, 0x81, 0x80]);
+        assert_eq!(der(0x04, &[7; 300])[..4], [0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(der(0x04, &[7; 300]).len(), 304);
+        assert_eq!(sequence(&[der(0x02, &[1]), der(0x05, &[])]), [0x30, 0x05, 0x02, 0x01, 0x01, 0x05, 0x00]);
+    }
+
+    #[test]
+    fn encodes_times_by_year() {
+        let at = DateTime::parse_from_rfc3339("2026-01-02T03:04:05Z").unwrap().with_timezone(&Utc);
+        assert_eq!(utc_time(at), der(0x17, b"260102030405Z"));
+        let later = DateTime::parse_from_rfc3339("2050-12-31T23:59:59Z").unwrap().with_timezone(&Utc);
+        assert_eq!(utc_time(later), der(0x18, b"20501231235959Z"));
+    }
+
+    #[test]
+    fn fingerprints_like_browsers() {
+        assert_eq!(
+            fingerprint(b""),
+            "E3:B0:C4:42:98:FC:1C:14:9A:FB:F4:C8:99:6F:B9:24:27:AE:41:E4:64:9B:93:4C:A4:95:99:1B:78:52:B8:55",
+        );
+    }
+
+    #[test]
+    fn signs_a_well_formed_certificate() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let cert = self_signed(&key, &rng).unwrap();
+
+        let (tag, content, rest) = read(&cert);
+        assert_eq!((tag, rest.len()), (0x30, 0));
+        let [(0x30, tbs), (0x30, algorithm), (0x03, signature)] = children(content)[..] else { panic!("certificate layout") };
+        let ecdsa_with_sha256 = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+        assert_eq!(algorithm, ecdsa_with_sha256);
+
+        let fields = children(tbs);
+        assert_eq!(fields.len(), 7);
+        assert_eq!(fields[0], (0xa0, &[0x02, 0x01, 0x02][..]), "version 3");
+        let (serial_tag, serial) = fields[1];
+        assert_eq!((serial_tag, serial.len()), (0x02, 16));
+        assert!(serial[0] & 0x80 == 0 && serial[0] != 0, "a positive serial with no leading zero");
+        assert_eq!(fields[2], (0x30, &ecdsa_with_sha256[..]));
+        assert_eq!(fields[3], fields[5], "issued by itself");
+        assert!(fields[3].1.windows(16).any(|w| w == b"OpenClaw Desktop"));
+        let validity = children(fields[4].1);
+        assert!(validity.iter().all(|(tag, _)| *tag == 0x17));
+        assert!(validity[0].1 < validity[1].1);
+        let (_, public_key) = children(fields[6].1)[1];
+        assert_eq!(public_key[0], 0, "no unused bits");
+        assert_eq!(&public_key[1..], key.public_key().as_ref());
+
+        // The signature covers the whole encoded TBS, header included.
+        let (_, _, after_tbs) = read(content);
+        let encoded_tbs = &content[..content.len() - after_tbs.len()];
+        assert_eq!(signature[0], 0);
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &public_key[1..])
+            .verify(encoded_tbs, &signature[1..])
+            .expect("the signature checks out");
+    }
+}
@@ -0,0 +1,244 @@
+//! Every outside service the app is connected to, in one list: the AI provider and its
+//! fallback, calendar feeds and the sync target. Each can be checked and disconnected from
+//! here. Telegram and Slack are connected in OpenClaw itself, which sends for the app, so
+//! they are managed there.
+//!
+//! None of these hold tokens that expire and refresh; credentials a service starts refusing
+//! mark the connection as needing to be signed in again, rather than as merely failing.
+
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager, State};
+
+use crate::llm::{self, ChatMessage, LlmRequest};
+use crate::{audit, calendar, net, sync};
+use crate::{DbState, write_setting};
+
+const CALENDAR_PREFIX: &str = "calendar:";
+/// Answers containing these status codes mean the saved key or password was refused.
+const REFUSED: &[&str] = &[" 401", " 403"];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Integration {
+    /// `ai_provider`, `ai_fallback`, `sync`, or `calendar:` followed by the feed's address.
+    pub id: String,
+    /// `ai`, `calendar` or `sync`.
+    pub kind: String,
+    pub name: String,
+    /// What it connects to: the model, the feed's site, or the sync folder or server.
+    pub detail: String,
+    /// `ok`, `error`, `needs_sign_in` when the service refused the saved credentials, or
+    /// `untested`.
+    pub status: String,
+    pub error: String,
+    pub checked_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS connection_checks (
+            id TEXT PRIMARY KEY,
+            status TEXT NOT NULL,
+            error TEXT NOT NULL DEFAULT '',
+            checked_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize connection tables");
+}
+
+fn provider_name(provider: &str) -> String {
+    match provider {
+        "openai" => "OpenAI".into(),
+        "anthropic" => "Anthropic".into(),
+        other => other.to_string(),
+    }
+}
+
+/// A feed's site without its path, which for private calendar links is the secret.
+fn feed_site(source: &str) -> String {
+    tauri::Url::parse(source).ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "calendar feed".into())
+}
+
+/// The connections that are set up, without their last check.
+fn configured(conn: &Connection) -> Result<Vec<Integration>, String> {
+    let integration = |id: String, kind: &str, name: String, detail: String| Integration {
+        id,
+        kind: kind.into(),
+        name,
+        detail,
+        status: "untested".into(),
+        error: String::new(),
+        checked_at: String::new(),
+    };
+    let mut found = Vec::new();
+    let primary = llm::provider_config(conn).ok();
+    if let Some(cfg) = primary.as_ref().filter(|cfg| cfg.provider != "local") {
+        found.push(integration("ai_provider".into(), "ai", provider_name(&cfg.provider), cfg.model.clone()));
+    }
+    let fallback = primary.as_ref().and_then(|primary| llm::configured_fallback(conn, primary).ok().flatten());
+    if let Some(cfg) = fallback.filter(|cfg| cfg.provider != "local") {
+        found.push(integration("ai_fallback".into(), "ai", format!("{} (fallback)", provider_name(&cfg.provider)), cfg.model));
+    }
+    for source in calendar::sources(conn)?.into_iter().filter(|s| calendar::is_url(s)) {
+        found.push(integration(format!("{CALENDAR_PREFIX}{source}"), "calendar", "Calendar feed".into(), feed_site(&source)));
+    }
+    let sync = sync::load_config(conn)?;
+    match sync.target.as_str() {
+        "folder" => found.push(integration("sync".into(), "sync", "Sync folder".into(), sync.folder)),
+        "webdav" => found.push(integration("sync".into(), "sync", "Sync server (WebDAV)".into(), feed_site(&sync.webdav_url))),
+        _ => {}
+    }
+    Ok(found)
+}
+
+fn with_last_check(conn: &Connection, mut integration: Integration) -> Result<Integration, String> {
+    let check: Option<(String, String, String)> = conn.query_row(
+        "SELECT status, error, checked_at FROM connection_checks WHERE id = ?1",
+        params![integration.id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).optional().map_err(|e| e.to_string())?;
+    if let Some((status, error, checked_at)) = check {
+        integration.status = status;
+        integration.error = error;
+        integration.checked_at = checked_at;
+    }
+    Ok(integration)
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Integration>, String> {
+    configured(conn)?.into_iter().map(|i| with_last_check(conn, i)).collect()
+}
+
+fn find(conn: &Connection, id: &str) -> Result<Integration, String> {
+    configured(conn)?.into_iter().find(|i| i.id == id).ok_or_else(|| format!("Nothing is connected as {id}"))
+}
+
+/// What a check is about to do, gathered while the database is held so the call itself
+/// runs without it.
+enum Check {
+    Provider(llm::ProviderConfig),
+    Calendar(net::Proxy, String),
+    Sync(sync::SyncConfig, net::Proxy),
+}
+
+fn prepare(conn: &Connection, id: &str) -> Result<Check, String> {
+    let check = match id {
+        "ai_provider" => Check::Provider(llm::provider_config(conn)?),
+        "ai_fallback" => {
+            let primary = llm::provider_config(conn)?;
+            Check::Provider(llm::configured_fallback(conn, &primary)?.ok_or("No fallback provider is set up")?)
+        }
+        "sync" => Check::Sync(sync::load_config(conn)?, net::proxy(conn)?),
+        id => match id.strip_prefix(CALENDAR_PREFIX) {
+            Some(source) => Check::Calendar(net::proxy(conn)?, source.to_string()),
+            None => return Err(format!("Nothing is connected as {id}")),
+        },
+    };
+    let url = match &check {
+        Check::Provider(cfg) => Some(cfg.base_url.clone()),
+        Check::Calendar(_, source) => Some(source.replacen("webcal://", "https://", 1)),
+        Check::Sync(config, _) => (config.target == "webdav").then(|| config.webdav_url.clone()),
+    };
+    net::check_egress(conn, "a connection check", url.as_deref(), "", "")?;
+    Ok(check)
+}
+
+fn run(check: Check) -> Result<(), String> {
+    match check {
+        // The smallest request the provider will answer, to prove the key and model work.
+        Check::Provider(cfg) => llm::call_provider(&cfg, &LlmRequest {
+            system: "Reply with OK.".into(),
+            messages: vec![ChatMessage::user("Ping")],
+            max_tokens: 5,
+            ..Default::default()
+        }).map(|_| ()),
+        Check::Calendar(proxy, source) => calendar::fetch(&proxy, &source).map(|_| ()),
+        Check::Sync(config, proxy) => sync::check(&config, proxy),
+    }
+}
+
+/// Checks one connection with its saved settings and remembers the outcome.
+pub fn test(app: &AppHandle, id: &str) -> Result<Integration, String> {
+    let db = app.state::<DbState>();
+    let check = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        find(&conn, id)?;
+        prepare(&conn, id)?
+    };
+    let outcome = run(check);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (status, error) = match outcome {
+        Ok(()) => ("ok", String::new()),
+        Err(e) if REFUSED.iter().any(|code| e.contains(code)) => ("needs_sign_in", e),
+        Err(e) => ("error", e),
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO connection_checks (id, status, error, checked_at) VALUES (?1, ?2, ?3, ?4)",
+        params![id, status, error, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    with_last_check(&conn, find(&conn, id)?)
+}
+
+/// Disconnects a service and deletes the key or password saved for it. An API key stays
+/// valid at the provider until it is deleted there too, so the audit entry says so.
+pub fn disconnect(conn: &Connection, id: &str) -> Result<(), String> {
+    let integration = find(conn, id)?;
+    match id {
+        "ai_provider" => write_setting(conn, "llm_api_key", "")?,
+        "ai_fallback" => {
+            write_setting(conn, "llm_fallback_provider", "")?;
+            write_setting(conn, "llm_fallback_api_key", "")?;
+        }
+        "sync" => {
+            write_setting(conn, "sync_target", "")?;
+            write_setting(conn, "sync_webdav_password", "")?;
+            write_setting(conn, "sync_passphrase", "")?;
+        }
+        id => {
+            let source = id.strip_prefix(CALENDAR_PREFIX).ok_or_else(|| format!("Nothing is connected as {id}"))?;
+            let sources: Vec<String> = calendar::sources(conn)?.into_iter().filter(|s| s != source).collect();
+            write_setting(conn, calendar::SOURCES_SETTING, &json!(sources).to_string())?;
+            conn.execute("DELETE FROM calendar_events WHERE source = ?1", params![source]).map_err(|e| e.to_string())?;
+        }
+    }
+    conn.execute("DELETE FROM connection_checks WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    let revoke = match integration.kind.as_str() {
+        "ai" => "; delete the API key in the provider's settings to revoke it",
+        _ => "",
+    };
+    audit::record(conn, "connection_removed", "", "", &format!("Disconnected {}{revoke}", integration.name), &json!({ "connection": integration.kind }))
+}
+
+/// Forgets the last check of the connection a changed setting belongs to, so a fixed key
+/// doesn't still show as refused.
+pub(crate) fn setting_changed(conn: &Connection, key: &str) {
+    let id = match key {
+        "llm_provider" | "llm_api_key" | "llm_model" => "ai_provider",
+        k if k.starts_with("llm_fallback_") => "ai_fallback",
+        k if k.starts_with("sync_") => "sync",
+        _ => return,
+    };
+    let _ = conn.execute("DELETE FROM connection_checks WHERE id = ?1", params![id]);
+}
+
+// ─── Connection Commands ───
+
+#[tauri::command]
+pub fn list_connections(db: State<DbState>) -> Result<Vec<Integration>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list(&conn)
+}
+
+#[tauri::command]
+pub async fn test_connection(app: AppHandle, id: String) -> Result<Integration, String> {
+    tauri::async_runtime::spawn_blocking(move || test(&app, &id)).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn disconnect_connection(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    disconnect(&conn, &id)
+}
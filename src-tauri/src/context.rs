@@ -0,0 +1,154 @@
+use serde_json::Value;
+use tauri::Manager;
+
+use crate::artifacts;
+use crate::injection;
+use crate::llm::{self, ChatMessage, LlmRequest};
+use crate::persona;
+use crate::structured;
+use crate::tokens;
+use crate::tools::ToolContext;
+use crate::vision::{self, Image};
+use crate::DbState;
+
+pub(crate) const STEP_SYSTEM: &str = "You are a careful assistant carrying out one step of an automation. Reply with the result only.";
+const CONDENSE_SYSTEM: &str = "You condense one part of a longer input so a later step can work from it. \
+Keep instructions, names, numbers, dates and anything the overall request may need; drop repetition. \
+Reply with the condensed text only.";
+const REPLY_TOKENS: u32 = 1024;
+/// Kept free for the system prompt and chat formatting on top of the reply.
+const OVERHEAD_TOKENS: usize = 256;
+/// How much of the original request each condense call sees, so it knows what matters.
+const TASK_PREVIEW_CHARS: usize = 500;
+/// Rounds of condensing before giving up; each one shrinks the input several times over.
+const MAX_ROUNDS: usize = 4;
+
+fn request(ctx: &ToolContext, system: &str, prompt: &str) -> LlmRequest {
+    LlmRequest {
+        system: system.into(),
+        messages: vec![ChatMessage::user(prompt)],
+        max_tokens: REPLY_TOKENS,
+        agent_id: ctx.agent_id.to_string(),
+        run_id: ctx.run_id.to_string(),
+        ..Default::default()
+    }
+}
+
+fn ask(ctx: &ToolContext, system: &str, prompt: &str) -> Result<String, String> {
+    llm::complete(&ctx.app.state::<DbState>(), &request(ctx, system, prompt)).map(|r| r.text)
+}
+
+/// The step's own model call, in the agent's persona, with any attached `images`. With a
+/// `schema` the reply is validated JSON, returned as text.
+fn answer(ctx: &ToolContext, prompt: &str, schema: Option<&Value>, images: &[Image]) -> Result<String, String> {
+    let db = ctx.app.state::<DbState>();
+    let system = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let base = match prompt.contains("<untrusted") {
+            true => format!("{STEP_SYSTEM} {}", injection::SYSTEM_NOTE),
+            false => STEP_SYSTEM.to_string(),
+        };
+        persona::system_prompt(&conn, ctx.agent_id, &base)?
+    };
+    let mut req = request(ctx, &system, prompt);
+    req.messages[0].images = images.to_vec();
+    match schema {
+        Some(schema) => structured::complete(&db, &req, schema).map(|value| value.to_string()),
+        None => llm::complete(&db, &req).map(|r| r.text),
+    }
+}
+
+/// Whether a provider error means the request was too long for the model.
+fn is_context_error(error: &str) -> bool {
+    let error = error.to_ascii_lowercase();
+    ["context length", "context_length", "context window", "maximum context", "too many tokens", "prompt is too long"]
+        .iter()
+        .any(|needle| error.contains(needle))
+}
+
+/// Splits `text` into pieces of at most `max_tokens`, breaking between lines where possible.
+fn split(model: &str, text: &str, max_tokens: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+    for line in text.split_inclusive('\n') {
+        let line_tokens = tokens::count(model, line);
+        if !current.is_empty() && current_tokens + line_tokens > max_tokens {
+            pieces.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if line_tokens <= max_tokens {
+            current.push_str(line);
+            current_tokens += line_tokens;
+            continue;
+        }
+        // A single line longer than a whole piece is cut by characters.
+        let chars: Vec<char> = line.chars().collect();
+        let per_piece = (chars.len() * max_tokens / line_tokens).max(1);
+        pieces.extend(chars.chunks(per_piece).map(|c| c.iter().collect::<String>()));
+    }
+    if !current.trim().is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Map-reduce: condenses each piece of `text` separately, joins the results and repeats until
+/// they fit in `budget` tokens. Every intermediate summary is kept as a run artifact.
+fn condense(ctx: &ToolContext, model: &str, text: &str, budget: usize) -> Result<String, String> {
+    let task: String = text.chars().take(TASK_PREVIEW_CHARS).collect();
+    let piece_budget = budget.saturating_sub(tokens::count(model, &task) + 64).max(budget / 2);
+    let mut text = text.to_string();
+    for round in 1..=MAX_ROUNDS {
+        let pieces = split(model, &text, piece_budget);
+        let mut summaries = Vec::with_capacity(pieces.len());
+        for (i, piece) in pieces.iter().enumerate() {
+            let prompt = format!(
+                "The overall request begins:\n{task}\n\nThis is part {} of {} of the input. Condense it:\n\n{piece}",
+                i + 1,
+                pieces.len(),
+            );
+            let summary = ask(ctx, CONDENSE_SYSTEM, &prompt)?;
+            record(ctx, "chunk_summary", &format!("Round {round}, part {} of {}", i + 1, pieces.len()), &summary)?;
+            summaries.push(summary);
+        }
+        text = summaries.join("\n\n");
+        if tokens::count(model, &text) <= budget {
+            record(ctx, "merged_summary", &format!("Merged input after {round} round(s)"), &text)?;
+            return Ok(text);
+        }
+    }
+    Err("The input is too long for the model even after summarizing it".into())
+}
+
+pub(crate) fn record(ctx: &ToolContext, kind: &str, name: &str, content: &str) -> Result<(), String> {
+    let db = ctx.app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    artifacts::record(&conn, ctx.run_id, ctx.agent_id, ctx.step_index, kind, name, content)
+}
+
+/// Runs an `llm_prompt` step, showing the model the attachments in `images`. Input too long
+/// for the model's context window is condensed first, and a provider rejecting the length
+/// triggers the same fallback rather than failing.
+pub fn run_prompt(ctx: &ToolContext, prompt: &str, schema: Option<&Value>, images: &[String]) -> Result<String, String> {
+    let (model, budget, images) = {
+        let db = ctx.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let cfg = llm::provider_config(&conn)?;
+        let images = vision::load(&conn, images)?;
+        let image_tokens: usize = images.iter().map(|i| i.tokens(&cfg.provider)).sum();
+        let window = llm::context_window(&conn, &cfg)?;
+        (cfg.model, window.saturating_sub(REPLY_TOKENS as usize + OVERHEAD_TOKENS + image_tokens), images)
+    };
+    let model = model.as_str();
+    let prompt = if tokens::count(model, prompt) > budget {
+        condense(ctx, model, prompt, budget)?
+    } else {
+        prompt.to_string()
+    };
+    match answer(ctx, &prompt, schema, &images) {
+        // The estimate was too generous for this model's tokenizer; try again with room to spare.
+        Err(e) if is_context_error(&e) => answer(ctx, &condense(ctx, model, &prompt, budget / 2)?, schema, &images),
+        other => other,
+    }
+}
@@ -0,0 +1,251 @@
+use std::backtrace::Backtrace;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::diagnostics::Anonymizer;
+use crate::{DbState, app_data_dir, audit, net, read_setting, write_setting};
+
+const CRASH_DIR: &str = "crashes";
+/// Present while the app runs; finding it at startup means the last session ended abruptly.
+const SESSION_FILE: &str = "session-running";
+const KEEP_REPORTS: usize = 50;
+const UPLOAD_ENABLED_SETTING: &str = "crash_upload_enabled";
+const UPLOAD_ENDPOINT_SETTING: &str = "crash_upload_endpoint";
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrashReport {
+    pub id: String,
+    /// `panic`, or `unclean_exit` when the app was killed or crashed outside Rust code.
+    pub kind: String,
+    pub created_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: String,
+    pub message: String,
+    /// `file:line` of the panic.
+    pub location: String,
+    pub backtrace: String,
+    #[serde(default)]
+    pub uploaded_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CrashUploadSettings {
+    pub enabled: bool,
+    pub endpoint: String,
+}
+
+/// Exactly what an upload would send, for the user to read first. Uploading needs `digest`
+/// back, so only a payload the user has seen can go out.
+#[derive(Debug, Serialize, Clone)]
+pub struct CrashUploadPreview {
+    pub endpoint: String,
+    pub payload: Value,
+    pub digest: String,
+}
+
+fn crash_dir() -> PathBuf {
+    app_data_dir().join(CRASH_DIR)
+}
+
+fn report_path(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err("Crash report not found".into());
+    }
+    Ok(crash_dir().join(format!("{id}.json")))
+}
+
+fn new_report(kind: &str, thread: String, message: String, location: String, backtrace: String) -> CrashReport {
+    let now = Utc::now();
+    CrashReport {
+        id: format!("{}-{}", now.format("%Y%m%d-%H%M%S"), &Uuid::new_v4().simple().to_string()[..8]),
+        kind: kind.into(),
+        created_at: now.to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        os: std::env::consts::OS.into(),
+        arch: std::env::consts::ARCH.into(),
+        thread,
+        message,
+        location,
+        backtrace,
+        uploaded_at: None,
+    }
+}
+
+fn save(report: &CrashReport) -> Result<(), String> {
+    std::fs::create_dir_all(crash_dir()).map_err(|e| e.to_string())?;
+    let text = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(report_path(&report.id)?, text).map_err(|e| e.to_string())
+}
+
+fn load_all() -> Vec<CrashReport> {
+    let mut reports: Vec<CrashReport> = std::fs::read_dir(crash_dir()).into_iter().flatten().flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|text| serde_json::from_str(&text).ok())
+        .collect();
+    reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    reports
+}
+
+fn prune() {
+    for old in load_all().iter().skip(KEEP_REPORTS) {
+        if let Ok(path) = report_path(&old.id) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Records panics on any thread and notes whether the previous session ended without
+/// shutting down. Called first thing at startup, before anything can panic.
+pub fn install() {
+    let dir = crash_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let marker = dir.join(SESSION_FILE);
+    if let Ok(started_at) = std::fs::read_to_string(&marker) {
+        let panicked = load_all().iter().any(|r| r.kind == "panic" && r.created_at >= started_at);
+        if !panicked {
+            let message = format!("OpenClaw closed without shutting down during the session started at {}", started_at.trim());
+            let _ = save(&new_report("unclean_exit", String::new(), message, String::new(), String::new()));
+        }
+    }
+    let _ = std::fs::write(&marker, Utc::now().to_rfc3339());
+    prune();
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic with no message".into());
+        let location = info.location().map(|l| format!("{}:{}", l.file(), l.line())).unwrap_or_default();
+        let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+        let backtrace = Backtrace::force_capture().to_string();
+        let _ = save(&new_report("panic", thread, message, location, backtrace));
+        previous(info);
+    }));
+}
+
+/// Called when the app shuts down normally.
+pub fn clean_exit() {
+    let _ = std::fs::remove_file(crash_dir().join(SESSION_FILE));
+}
+
+fn upload_settings(conn: &rusqlite::Connection) -> Result<CrashUploadSettings, String> {
+    Ok(CrashUploadSettings {
+        enabled: read_setting(conn, UPLOAD_ENABLED_SETTING)?.as_deref() == Some("true"),
+        endpoint: read_setting(conn, UPLOAD_ENDPOINT_SETTING)?.unwrap_or_default(),
+    })
+}
+
+/// The report with paths, addresses and quoted text in the message and backtrace replaced.
+fn payload(report: &CrashReport) -> Value {
+    let mut anon = Anonymizer::default();
+    json!({
+        "id": report.id,
+        "kind": report.kind,
+        "created_at": report.created_at,
+        "app_version": report.app_version,
+        "os": report.os,
+        "arch": report.arch,
+        "thread": report.thread,
+        "message": anon.scrub(&report.message),
+        "location": report.location,
+        "backtrace": report.backtrace.lines().map(|line| anon.scrub(line)).collect::<Vec<_>>().join("\n"),
+    })
+}
+
+fn digest(body: &str) -> String {
+    Sha256::digest(body.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn load(id: &str) -> Result<CrashReport, String> {
+    let text = std::fs::read_to_string(report_path(id)?).map_err(|_| "Crash report not found".to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+// ─── Crash Report Commands ───
+
+/// Newest first. Reports stay on this computer unless the user uploads one.
+#[tauri::command]
+pub fn list_crash_reports() -> Vec<CrashReport> {
+    load_all()
+}
+
+#[tauri::command]
+pub fn delete_crash_report(id: String) -> Result<(), String> {
+    std::fs::remove_file(report_path(&id)?).map_err(|_| "Crash report not found".to_string())
+}
+
+#[tauri::command]
+pub fn get_crash_upload_settings(db: State<DbState>) -> Result<CrashUploadSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    upload_settings(&conn)
+}
+
+/// Uploading is off until the user turns it on and gives an `https://` endpoint.
+#[tauri::command]
+pub fn set_crash_upload_settings(db: State<DbState>, enabled: bool, endpoint: String) -> Result<CrashUploadSettings, String> {
+    let endpoint = endpoint.trim().to_string();
+    if enabled && !endpoint.starts_with("https://") {
+        return Err("Crash reports can only be sent to an https:// address".into());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, UPLOAD_ENABLED_SETTING, if enabled { "true" } else { "false" })?;
+    write_setting(&conn, UPLOAD_ENDPOINT_SETTING, &endpoint)?;
+    upload_settings(&conn)
+}
+
+#[tauri::command]
+pub fn preview_crash_upload(db: State<DbState>, id: String) -> Result<CrashUploadPreview, String> {
+    let settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        upload_settings(&conn)?
+    };
+    let payload = payload(&load(&id)?);
+    let body = serde_json::to_string(&payload).map_err(|e| e.to_string())?;
+    Ok(CrashUploadPreview { endpoint: settings.endpoint, digest: digest(&body), payload })
+}
+
+/// Sends one report the user has reviewed with `preview_crash_upload`; `digest` must match
+/// what was shown.
+#[tauri::command]
+pub async fn upload_crash_report(db: State<'_, DbState>, id: String, digest: String) -> Result<CrashReport, String> {
+    let mut report = load(&id)?;
+    let body = serde_json::to_string(&payload(&report)).map_err(|e| e.to_string())?;
+    if self::digest(&body) != digest {
+        return Err("The report changed since it was reviewed; look it over again before sending".into());
+    }
+    let (settings, proxy) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let settings = upload_settings(&conn)?;
+        if !settings.enabled || settings.endpoint.is_empty() {
+            return Err("Turn on crash report uploads and set where to send them first".into());
+        }
+        net::check_egress(&conn, "crash report upload", Some(&settings.endpoint), "", "")?;
+        (settings, net::proxy(&conn)?)
+    };
+    let endpoint = settings.endpoint.clone();
+    tauri::async_runtime::spawn_blocking(move || -> Result<(), String> {
+        let request = proxy.agent(&endpoint, UPLOAD_TIMEOUT)?.post(&endpoint).set("Content-Type", "application/json");
+        match request.send_string(&body) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(code, _)) => Err(format!("{endpoint} answered with status {code}")),
+            Err(e) => Err(format!("Couldn't reach {endpoint}: {e}")),
+        }
+    }).await.map_err(|e| e.to_string())??;
+
+    report.uploaded_at = Some(Utc::now().to_rfc3339());
+    save(&report)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    audit::record(&conn, "crash_report_uploaded", "", "", &format!("Sent crash report {id}"), &json!({ "endpoint": settings.endpoint }))?;
+    Ok(report)
+}
@@ -0,0 +1,83 @@
+use serde_json::{json, Value};
+use tauri::Manager;
+
+use crate::context;
+use crate::llm::{self, ChatMessage, LlmRequest};
+use crate::persona;
+use crate::structured;
+use crate::tools::ToolContext;
+use crate::{DbState, read_setting};
+
+/// Model that reviews drafts, from the active provider. Unset, the main model reviews its own
+/// work in a separate, independent call.
+const REVIEWER_MODEL_SETTING: &str = "llm_reviewer_model";
+const REVIEW_SYSTEM: &str = "You review a draft another assistant wrote before it is used. \
+Check it against the goal: is it correct, complete, and right in tone for whoever will read it? \
+Approve it unless something actually needs changing; when it does, say specifically what.";
+const REVISE_SYSTEM: &str = "You revise a draft to address a reviewer's feedback, keeping what was already fine. \
+Reply with the revised draft only.";
+const REPLY_TOKENS: u32 = 1024;
+const DEFAULT_REVISIONS: u64 = 2;
+const MAX_REVISIONS: u64 = 5;
+
+fn review_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "approved": { "type": "boolean" },
+            "feedback": { "type": "string" },
+        },
+        "required": ["approved", "feedback"],
+    })
+}
+
+fn request(ctx: &ToolContext, system: &str, prompt: String, model: Option<String>) -> LlmRequest {
+    LlmRequest {
+        system: system.into(),
+        messages: vec![ChatMessage::user(prompt)],
+        max_tokens: REPLY_TOKENS,
+        model,
+        agent_id: ctx.agent_id.to_string(),
+        run_id: ctx.run_id.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Runs a `critique` step: the reviewer approves `draft` or says what to fix, the main model
+/// revises, and so on for up to `max_revisions` rounds. Returns the approved draft; a draft
+/// still not approved at the end fails the step so it never reaches the next one.
+/// Every review and revision is kept as a run artifact.
+pub fn run(ctx: &ToolContext, draft: &str, goal: &str, model: Option<&str>, max_revisions: Option<u64>) -> Result<String, String> {
+    let db = ctx.app.state::<DbState>();
+    // The reviewer stays neutral; revisions are written in the agent's own persona.
+    let (reviewer, revise_system) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let reviewer = match model.filter(|m| !m.trim().is_empty()) {
+            Some(model) => Some(model.to_string()),
+            None => read_setting(&conn, REVIEWER_MODEL_SETTING)?,
+        };
+        (reviewer, persona::system_prompt(&conn, ctx.agent_id, REVISE_SYSTEM)?)
+    };
+    let revisions = max_revisions.unwrap_or(DEFAULT_REVISIONS).min(MAX_REVISIONS);
+
+    let mut draft = draft.to_string();
+    let mut round = 0;
+    loop {
+        let prompt = format!("Goal:\n{goal}\n\nDraft:\n{draft}");
+        let review = structured::complete(&db, &request(ctx, REVIEW_SYSTEM, prompt, reviewer.clone()), &review_schema())?;
+        let approved = review["approved"].as_bool().unwrap_or(false);
+        let feedback = review["feedback"].as_str().unwrap_or_default().to_string();
+        let verdict = if approved { "Approved" } else { "Changes requested" };
+        context::record(ctx, "critique", &format!("Review {}: {verdict}", round + 1), &feedback)?;
+        if approved {
+            return Ok(draft);
+        }
+        if round == revisions {
+            return Err(format!("The reviewer still wasn't satisfied after {revisions} revision(s): {feedback}"));
+        }
+        round += 1;
+        let prompt = format!("Goal:\n{goal}\n\nDraft:\n{draft}\n\nReviewer feedback:\n{feedback}");
+        draft = llm::complete(&db, &request(ctx, &revise_system, prompt, None))?.text;
+        context::record(ctx, "revision", &format!("Revision {round}"), &draft)?;
+    }
+}
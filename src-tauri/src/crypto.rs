@@ -0,0 +1,98 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+
+const KDF_ITERATIONS: u32 = 210_000;
+/// Anything outside this range is a damaged or hostile file, not a real setting.
+const KDF_ITERATION_RANGE: std::ops::RangeInclusive<u32> = 10_000..=10_000_000;
+
+/// Passphrase-encrypted data (PBKDF2-SHA256 key, AES-256-GCM).
+pub struct Sealed {
+    pub kdf_iterations: u32,
+    pub salt: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+pub fn seal(passphrase: &str, plaintext: &[u8]) -> Result<Sealed, String> {
+    let mut salt = vec![0u8; 16];
+    let mut nonce = vec![0u8; 12];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    let key = derive_key(passphrase, &salt, KDF_ITERATIONS);
+    let ciphertext = Aes256Gcm::new(&key.into())
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "Couldn't encrypt the data")?;
+    Ok(Sealed { kdf_iterations: KDF_ITERATIONS, salt, nonce, ciphertext })
+}
+
+/// Fails with "Wrong passphrase" when the key doesn't match or the data was altered.
+pub fn open(passphrase: &str, sealed: &Sealed) -> Result<Vec<u8>, String> {
+    if !KDF_ITERATION_RANGE.contains(&sealed.kdf_iterations) || sealed.nonce.len() != 12 {
+        return Err("The encrypted data is damaged".into());
+    }
+    let key = derive_key(passphrase, &sealed.salt, sealed.kdf_iterations);
+    Aes256Gcm::new(&key.into())
+        .decrypt(Nonce::from_slice(&sealed.nonce), sealed.ciphertext.as_slice())
+        .map_err(|_| "Wrong passphrase".to_string())
+}
+
+/// Encrypts with a random 256-bit key kept elsewhere, such as the system keychain. The
+/// nonce is stored in front of the ciphertext.
+pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = Aes256Gcm::new(key.into())
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "Couldn't encrypt the data")?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+/// Reverses [`encrypt`]; fails when the key is a different one or the data was altered.
+pub fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 12 {
+        return Err("The encrypted data is damaged".into());
+    }
+    let (nonce, ciphertext) = data.split_at(12);
+    Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Couldn't decrypt the data with this key".to_string())
+}
+
+/// Salted PBKDF2 hash of a PIN or password, as `pbkdf2-sha256$<iterations>$<salt hex>$<hash hex>`.
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let hash = derive_key(password, &salt, KDF_ITERATIONS);
+    format!("pbkdf2-sha256${KDF_ITERATIONS}${}${}", hex(&salt), hex(&hash))
+}
+
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    let [_, iterations, salt, hash] = parts.as_slice() else {
+        return false;
+    };
+    let (Ok(iterations), Some(salt)) = (iterations.parse::<u32>(), unhex(salt)) else {
+        return false;
+    };
+    if !KDF_ITERATION_RANGE.contains(&iterations) {
+        return false;
+    }
+    hex(&derive_key(password, &salt, iterations)) == *hash
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    (0..s.len()).step_by(2).map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok())).collect()
+}
@@ -0,0 +1,182 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use tauri::{AppHandle, Manager};
+
+use crate::DbState;
+
+pub const DATABASE_FILE: &str = "openclaw.db";
+const APP_FOLDER: &str = "openclaw-desktop";
+/// Kept outside the data directory, since it says where that is.
+const BOOTSTRAP_FILE: &str = "bootstrap.json";
+/// A file of this name beside the executable, or this launch argument, turns on portable mode.
+const PORTABLE_SENTINEL: &str = "portable";
+const PORTABLE_ARG: &str = "--portable";
+/// In portable mode the data lives in this folder beside the executable.
+const PORTABLE_FOLDER: &str = "data";
+
+/// The little configuration read before the database can be opened.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct Bootstrap {
+    /// Where the data lives; empty for the OS default.
+    data_dir: String,
+}
+
+static CURRENT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+/// Whether the app keeps its data beside the executable, e.g. when run from a USB stick.
+/// Decided once at launch.
+pub fn is_portable() -> bool {
+    static PORTABLE: OnceLock<bool> = OnceLock::new();
+    *PORTABLE.get_or_init(|| {
+        std::env::args().skip(1).any(|arg| arg == PORTABLE_ARG)
+            || exe_dir().is_some_and(|dir| dir.join(PORTABLE_SENTINEL).is_file())
+    })
+}
+
+fn default_dir() -> PathBuf {
+    dirs_next::data_dir().unwrap_or_else(|| PathBuf::from(".")).join(APP_FOLDER)
+}
+
+fn bootstrap_path() -> PathBuf {
+    dirs_next::config_dir().unwrap_or_else(|| PathBuf::from(".")).join(APP_FOLDER).join(BOOTSTRAP_FILE)
+}
+
+fn read_bootstrap() -> Bootstrap {
+    std::fs::read_to_string(bootstrap_path()).ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_bootstrap(bootstrap: &Bootstrap) -> Result<(), String> {
+    let path = bootstrap_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    // Written beside and renamed over, so a crash never leaves a half-written file.
+    let partial = path.with_extension("json.partial");
+    std::fs::write(&partial, serde_json::to_string_pretty(bootstrap).map_err(|e| e.to_string())?)
+        .map_err(|e| e.to_string())?;
+    std::fs::rename(&partial, &path).map_err(|e| e.to_string())
+}
+
+/// The data directory: `data/` beside the executable in portable mode, otherwise the one
+/// chosen with `set_data_directory`, else the OS default.
+pub fn current() -> PathBuf {
+    if let Some(dir) = CURRENT.read().ok().and_then(|c| c.clone()) {
+        return dir;
+    }
+    let dir = match (is_portable(), exe_dir()) {
+        (true, Some(exe_dir)) => exe_dir.join(PORTABLE_FOLDER),
+        _ => match read_bootstrap().data_dir.trim() {
+            "" => default_dir(),
+            dir => PathBuf::from(dir),
+        },
+    };
+    if let Ok(mut current) = CURRENT.write() {
+        *current = Some(dir.clone());
+    }
+    dir
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<u64, String> {
+    std::fs::create_dir_all(to).map_err(|e| e.to_string())?;
+    let mut bytes = 0;
+    for entry in std::fs::read_dir(from).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name();
+        // The database itself is copied through SQLite so the copy is consistent.
+        if name.to_string_lossy().starts_with(DATABASE_FILE) {
+            continue;
+        }
+        let target = to.join(&name);
+        bytes += match entry.file_type().map_err(|e| e.to_string())?.is_dir() {
+            true => copy_dir(&entry.path(), &target)?,
+            false => std::fs::copy(entry.path(), &target).map_err(|e| e.to_string())?,
+        };
+    }
+    Ok(bytes)
+}
+
+/// Checks that `target` can take the data: an absolute folder outside the current one,
+/// writable, and not already holding another copy of the app's data.
+fn validate(target: &Path, current: &Path) -> Result<(), String> {
+    if !target.is_absolute() {
+        return Err("Choose a full folder path".into());
+    }
+    if target.starts_with(current) || current.starts_with(target) {
+        return Err("The new folder can't be inside the current data folder, or contain it".into());
+    }
+    if target.join(DATABASE_FILE).exists() {
+        return Err("That folder already holds OpenClaw data; choose an empty folder".into());
+    }
+    std::fs::create_dir_all(target).map_err(|e| format!("Can't create {}: {e}", target.display()))?;
+    let probe = target.join(".openclaw-write-test");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("Can't write to {}: {e}", target.display()))?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DataDirectoryMove {
+    pub old_path: String,
+    pub new_path: String,
+    pub bytes_copied: u64,
+}
+
+// ─── Data Directory Commands ───
+
+#[tauri::command]
+pub fn get_data_directory() -> String {
+    current().display().to_string()
+}
+
+#[tauri::command]
+pub fn get_portable_mode() -> bool {
+    is_portable()
+}
+
+/// Moves the app's data to `path`. The database is copied while no one else can use it, the
+/// app switches to the copy, and the location is recorded so the next start opens it there.
+/// The old folder is left untouched as a fallback; it can be deleted once the move is
+/// confirmed.
+#[tauri::command]
+pub async fn set_data_directory(app: AppHandle, path: String) -> Result<DataDirectoryMove, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        if is_portable() {
+            return Err("In portable mode the data stays in the data folder beside the app".into());
+        }
+        let old = current();
+        let target = PathBuf::from(path.trim());
+        validate(&target, &old)?;
+
+        let db = app.state::<DbState>();
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let new_database = target.join(DATABASE_FILE);
+        conn.execute("VACUUM INTO ?1", [new_database.display().to_string()]).map_err(|e| e.to_string())?;
+        let copied = copy_dir(&old, &target).and_then(|bytes| {
+            let moved = Connection::open(&new_database).map_err(|e| e.to_string())?;
+            write_bootstrap(&Bootstrap { data_dir: target.display().to_string() })?;
+            Ok((bytes, moved))
+        });
+        let (bytes, moved) = match copied {
+            Ok(done) => done,
+            Err(e) => {
+                let _ = std::fs::remove_file(&new_database);
+                return Err(e);
+            }
+        };
+        *conn = moved;
+        if let Ok(mut current) = CURRENT.write() {
+            *current = Some(target.clone());
+        }
+        let bytes_copied = bytes + std::fs::metadata(&new_database).map_or(0, |m| m.len());
+        Ok(DataDirectoryMove { old_path: old.display().to_string(), new_path: target.display().to_string(), bytes_copied })
+    }).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,450 @@
+//! Data sources the user registers for agents to ask questions of: SQLite files, and CSV
+//! files or folders loaded into an in-memory database. Agents get read-only SQL over them;
+//! SQLite's authorizer refuses anything but reading, and masked columns read as NULL
+//! however a query reaches them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params, params_from_iter};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::tools::{files, transform};
+use crate::DbState;
+
+const KINDS: &[&str] = &["sqlite", "csv"];
+const DEFAULT_ROW_LIMIT: i64 = 100;
+const MAX_ROW_LIMIT: i64 = 1000;
+/// A query still running after this is stopped.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// CSV data loaded for one query, across all of a folder's files.
+const MAX_CSV_BYTES: u64 = 50 * 1024 * 1024;
+/// Shown in place of a masked column in a source's description.
+const MASKED_NOTE: &str = "masked, reads as null";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DataSource {
+    #[serde(default)]
+    pub id: String,
+    /// What agents call the source in `query_data` steps.
+    pub name: String,
+    /// `sqlite` for a database file, `csv` for a CSV file or a folder of them.
+    pub kind: String,
+    pub path: String,
+    /// Columns agents never see, as `column` in any table or `table.column`.
+    #[serde(default)]
+    pub masked_columns: Vec<String>,
+    /// Rows a query returns at most.
+    #[serde(default = "default_row_limit")]
+    pub row_limit: i64,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+fn default_row_limit() -> i64 {
+    DEFAULT_ROW_LIMIT
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TableInfo {
+    pub name: String,
+    pub columns: Vec<String>,
+    /// Those of `columns` that are masked.
+    pub masked: Vec<String>,
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Map<String, Value>>,
+    /// More rows matched than the limit let through.
+    pub truncated: bool,
+    /// The source's masked columns, so nulls in them aren't taken for missing data.
+    pub masked_columns: Vec<String>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS data_sources (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            path TEXT NOT NULL,
+            masked_columns TEXT NOT NULL DEFAULT '[]',
+            row_limit INTEGER NOT NULL DEFAULT 100,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize data source tables");
+}
+
+const COLUMNS: &str = "id, name, kind, path, masked_columns, row_limit, created_at, updated_at";
+
+fn row_to_source(row: &rusqlite::Row) -> rusqlite::Result<DataSource> {
+    let masked: String = row.get(4)?;
+    Ok(DataSource {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: row.get(2)?,
+        path: row.get(3)?,
+        masked_columns: serde_json::from_str(&masked).unwrap_or_default(),
+        row_limit: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<DataSource>, String> {
+    let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM data_sources ORDER BY name COLLATE NOCASE"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_source).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// The source with this id, or else with this name, ignoring case.
+pub fn find(conn: &Connection, id_or_name: &str) -> Result<DataSource, String> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM data_sources WHERE id = ?1 OR name = ?1 COLLATE NOCASE ORDER BY id = ?1 DESC LIMIT 1"),
+        params![id_or_name.trim()],
+        row_to_source,
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No data source called \"{id_or_name}\""))
+}
+
+fn validate(source: &DataSource) -> Result<(), String> {
+    if source.name.trim().is_empty() {
+        return Err("Give the data source a name".into());
+    }
+    if !KINDS.contains(&source.kind.as_str()) {
+        return Err(format!("Kind must be one of {}", KINDS.join(", ")));
+    }
+    if !(1..=MAX_ROW_LIMIT).contains(&source.row_limit) {
+        return Err(format!("The row limit must be between 1 and {MAX_ROW_LIMIT}"));
+    }
+    let path = files::expand_path(&source.path);
+    if !path.exists() {
+        return Err(format!("{} doesn't exist", path.display()));
+    }
+    if source.kind == "sqlite" && !path.is_file() {
+        return Err("A SQLite source has to be a database file".into());
+    }
+    Ok(())
+}
+
+// ─── Opening ───
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// The CSV files a source reads: the file itself, or those directly in the folder.
+fn csv_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut found: Vec<PathBuf> = fs::read_dir(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv")))
+        .collect();
+    found.sort();
+    if found.is_empty() {
+        return Err(format!("There are no CSV files in {}", path.display()));
+    }
+    Ok(found)
+}
+
+/// A table name for a CSV file: its name in lower case with anything but letters, digits
+/// and underscores turned into underscores, e.g. `Sales 2024.csv` → `sales_2024`.
+fn table_name(path: &Path, taken: &[String]) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let mut name: String = stem.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+    name = name.trim_matches('_').to_string();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name = format!("t_{name}");
+    }
+    let mut unique = name.clone();
+    let mut n = 2;
+    while taken.contains(&unique) {
+        unique = format!("{name}_{n}");
+        n += 1;
+    }
+    unique
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => n.as_i64().map(SqlValue::Integer).unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or_default())),
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+/// Loads each CSV file into its own table of an in-memory database.
+fn load_csv(path: &Path) -> Result<Connection, String> {
+    let paths = csv_files(path)?;
+    let size: u64 = paths.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+    if size > MAX_CSV_BYTES {
+        return Err(format!("The CSV files come to more than {} MB; use a SQLite file for data this large", MAX_CSV_BYTES / 1024 / 1024));
+    }
+    let mut conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut taken = Vec::new();
+    for path in paths {
+        let bytes = fs::read(&path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        let (records, header) = transform::csv_records(&String::from_utf8_lossy(&bytes), None)?;
+        if header.is_empty() {
+            continue;
+        }
+        let table = table_name(&path, &taken);
+        let records = records.as_array().cloned().unwrap_or_default();
+        // Rows longer than the header add columns of their own.
+        let mut columns = header;
+        for record in &records {
+            for key in record.as_object().into_iter().flat_map(Map::keys) {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+        let names: Vec<String> = columns.iter().map(|c| quote(c)).collect();
+        tx.execute_batch(&format!("CREATE TABLE {} ({});", quote(&table), names.join(", ")))
+            .map_err(|e| format!("Can't load {}: {e}", path.display()))?;
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = tx.prepare(&format!("INSERT INTO {} VALUES ({placeholders})", quote(&table)))
+            .map_err(|e| e.to_string())?;
+        for record in &records {
+            let values = columns.iter().map(|c| record.get(c).map(sql_value).unwrap_or(SqlValue::Null));
+            insert.execute(params_from_iter(values)).map_err(|e| format!("Can't load {}: {e}", path.display()))?;
+        }
+        taken.push(table);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn open(source: &DataSource) -> Result<Connection, String> {
+    let path = files::expand_path(&source.path);
+    match source.kind.as_str() {
+        "sqlite" => Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .map_err(|e| format!("Can't open {}: {e}", path.display())),
+        _ => load_csv(&path),
+    }
+}
+
+/// The source's masked columns as the authorizer compares them.
+fn masked(source: &DataSource) -> Vec<String> {
+    source.masked_columns.iter().map(|m| m.trim().to_lowercase()).filter(|m| !m.is_empty()).collect()
+}
+
+fn is_masked(masked: &[String], table: &str, column: &str) -> bool {
+    let (table, column) = (table.to_lowercase(), column.to_lowercase());
+    masked.iter().any(|m| match m.split_once('.') {
+        Some((t, c)) => t == table && c == column,
+        None => *m == column,
+    })
+}
+
+/// Lets the connection only read, with masked columns reading as NULL, and stops queries
+/// that run too long.
+fn guard(conn: &Connection, source: &DataSource) {
+    let masked = masked(source);
+    conn.authorizer(Some(move |ctx: AuthContext<'_>| match ctx.action {
+        AuthAction::Select | AuthAction::Recursive => Authorization::Allow,
+        AuthAction::Read { table_name, column_name } if is_masked(&masked, table_name, column_name) => Authorization::Ignore,
+        AuthAction::Read { .. } => Authorization::Allow,
+        AuthAction::Function { function_name } if !function_name.eq_ignore_ascii_case("load_extension") => Authorization::Allow,
+        _ => Authorization::Deny,
+    }));
+    let started = Instant::now();
+    conn.progress_handler(1000, Some(move || started.elapsed() > QUERY_TIMEOUT));
+}
+
+// ─── Querying ───
+
+/// The source's tables and views with their columns.
+pub fn describe(source: &DataSource) -> Result<Vec<TableInfo>, String> {
+    let conn = open(source)?;
+    let mut stmt = conn.prepare("SELECT name, type FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name")
+        .map_err(|e| e.to_string())?;
+    let tables: Vec<(String, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let masked_columns = masked(source);
+    let mut infos = Vec::new();
+    for (name, kind) in tables {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote(&name))).map_err(|e| e.to_string())?;
+        let columns: Vec<String> = stmt.query_map([], |row| row.get(1))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        let rows = match kind.as_str() {
+            "table" => conn.query_row(&format!("SELECT COUNT(*) FROM {}", quote(&name)), [], |row| row.get(0)).map_err(|e| e.to_string())?,
+            _ => -1,
+        };
+        let masked = columns.iter().filter(|c| is_masked(&masked_columns, &name, c)).cloned().collect();
+        infos.push(TableInfo { name, columns, masked, rows });
+    }
+    Ok(infos)
+}
+
+fn json_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(n) => json!(n),
+        ValueRef::Real(n) => json!(n),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(bytes) => Value::String(format!("[{} bytes of binary data]", bytes.len())),
+    }
+}
+
+/// Runs a read-only `sql` query on the source, returning at most `limit` rows (and never
+/// more than the source allows).
+pub fn query(source: &DataSource, sql: &str, limit: Option<i64>) -> Result<QueryResult, String> {
+    let sql = sql.trim().trim_end_matches(';').trim();
+    let first_word = sql.split_whitespace().next().unwrap_or_default().to_lowercase();
+    if !["select", "with", "values"].contains(&first_word.as_str()) {
+        return Err("Only SELECT queries can be run on a data source".into());
+    }
+    let conn = open(source)?;
+    guard(&conn, source);
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("The query can't be run: {e}"))?;
+    if !stmt.readonly() {
+        return Err("Only queries that read can be run on a data source".into());
+    }
+    let mut columns: Vec<String> = Vec::new();
+    for name in stmt.column_names() {
+        let mut unique = name.to_string();
+        let mut n = 2;
+        while columns.contains(&unique) {
+            unique = format!("{name}_{n}");
+            n += 1;
+        }
+        columns.push(unique);
+    }
+    let limit = limit.unwrap_or(source.row_limit).clamp(1, source.row_limit) as usize;
+    let mut rows = stmt.query([]).map_err(|e| format!("The query failed: {e}"))?;
+    let mut out = Vec::new();
+    let mut truncated = false;
+    while let Some(row) = rows.next().map_err(|e| format!("The query failed: {e}"))? {
+        if out.len() == limit {
+            truncated = true;
+            break;
+        }
+        let mut record = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            record.insert(column.clone(), row.get_ref(i).map(json_value).map_err(|e| e.to_string())?);
+        }
+        out.push(record);
+    }
+    Ok(QueryResult { columns, rows: out, truncated, masked_columns: source.masked_columns.clone() })
+}
+
+/// The sources with their tables, for the model to write queries against.
+pub fn overview(sources: &[DataSource]) -> String {
+    if sources.is_empty() {
+        return "No data sources are set up. The user can add SQLite files or CSV folders in Settings.".into();
+    }
+    let mut lines = Vec::new();
+    for source in sources {
+        lines.push(format!("{} ({}, at most {} rows per query):", source.name, source.kind, source.row_limit));
+        match describe(source) {
+            Ok(tables) => {
+                for table in tables {
+                    let columns: Vec<String> = table.columns.iter()
+                        .map(|c| if table.masked.contains(c) { format!("{c} ({MASKED_NOTE})") } else { c.clone() })
+                        .collect();
+                    let rows = if table.rows >= 0 { format!(", {} rows", table.rows) } else { String::new() };
+                    lines.push(format!("  {}{rows}: {}", table.name, columns.join(", ")));
+                }
+            }
+            Err(e) => lines.push(format!("  unavailable: {e}")),
+        }
+    }
+    lines.join("\n")
+}
+
+// ─── Data Source Commands ───
+
+#[tauri::command]
+pub fn list_data_sources(db: State<DbState>) -> Result<Vec<DataSource>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list(&conn)
+}
+
+/// Adds a source, or replaces the one with the same id. Names are unique, since steps refer
+/// to sources by name.
+#[tauri::command]
+pub fn save_data_source(db: State<DbState>, source: DataSource) -> Result<DataSource, String> {
+    let mut source = DataSource {
+        name: source.name.trim().to_string(),
+        kind: source.kind.trim().to_lowercase(),
+        path: source.path.trim().to_string(),
+        masked_columns: source.masked_columns.iter().map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect(),
+        ..source
+    };
+    validate(&source)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let taken: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM data_sources WHERE name = ?1 COLLATE NOCASE AND id != ?2",
+        params![source.name, source.id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if taken {
+        return Err(format!("There is already a data source called {}", source.name));
+    }
+    let now = Utc::now().to_rfc3339();
+    if source.id.is_empty() {
+        source.id = Uuid::new_v4().to_string();
+    }
+    if source.created_at.is_empty() {
+        source.created_at = now.clone();
+    }
+    source.updated_at = now;
+    conn.execute(
+        &format!("INSERT OR REPLACE INTO data_sources ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"),
+        params![
+            source.id, source.name, source.kind, source.path, serde_json::to_string(&source.masked_columns).map_err(|e| e.to_string())?,
+            source.row_limit, source.created_at, source.updated_at,
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(source)
+}
+
+#[tauri::command]
+pub fn delete_data_source(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM data_sources WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn describe_data_source(db: State<'_, DbState>, id: String) -> Result<Vec<TableInfo>, String> {
+    let source = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        find(&conn, &id)?
+    };
+    tauri::async_runtime::spawn_blocking(move || describe(&source)).await.map_err(|e| e.to_string())?
+}
+
+/// Runs a query the way an agent's step would, masking and row limit included.
+#[tauri::command]
+pub async fn query_data_source(db: State<'_, DbState>, id: String, sql: String) -> Result<QueryResult, String> {
+    let source = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        find(&conn, &id)?
+    };
+    tauri::async_runtime::spawn_blocking(move || query(&source, &sql, None)).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+use chrono::Utc;
+use regex::Regex;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::executor::{RUN_COLUMNS, row_to_run};
+use crate::{DbState, bulk, pii};
+
+const FORMAT: &str = "openclaw-diagnostics";
+const LOG_LIMIT: i64 = 500;
+const RUN_LIMIT: i64 = 100;
+/// Error messages are kept, scrubbed, up to this many characters.
+const ERROR_CHARS: usize = 500;
+
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"[A-Za-z][A-Za-z0-9+.-]*://[^\s"'<>]+"#).unwrap());
+/// Windows drive and network paths, and Unix paths of at least two parts or under `~`.
+static PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"[A-Za-z]:\\[^\s"'<>|]*|\\\\[^\s"'<>|]+|~/[^\s"'<>]*|(?:/[^\s/"'<>:]+){2,}/?"#).unwrap()
+});
+/// Quoted text inside error messages, which is usually the user's own.
+static QUOTED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""[^"]+"|'[^']{2,}'|“[^”]+”"#).unwrap());
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiagnosticsSummary {
+    pub path: String,
+    pub tables: usize,
+    pub logs: usize,
+    pub runs: usize,
+    /// How many distinct values of each kind were replaced, e.g. `email: 3`.
+    pub replaced: BTreeMap<String, usize>,
+}
+
+/// Replaces values with placeholders like `<email-2>`: the same value always gets the same
+/// placeholder, so the report still shows which entries share one without saying what it is.
+#[derive(Default)]
+pub(crate) struct Anonymizer {
+    seen: HashMap<(&'static str, String), String>,
+    counts: BTreeMap<String, usize>,
+}
+
+impl Anonymizer {
+    fn placeholder(&mut self, kind: &'static str, value: &str) -> String {
+        if let Some(existing) = self.seen.get(&(kind, value.to_string())) {
+            return existing.clone();
+        }
+        let count = self.counts.entry(kind.to_string()).or_default();
+        *count += 1;
+        let token = format!("<{}-{count}>", kind.replace('_', "-"));
+        self.seen.insert((kind, value.to_string()), token.clone());
+        token
+    }
+
+    /// Free text is replaced whole.
+    fn text(&mut self, value: &str) -> String {
+        if value.trim().is_empty() { String::new() } else { self.placeholder("text", value) }
+    }
+
+    /// Keeps the wording of app-generated messages, replacing addresses, paths, personal
+    /// data and quoted text inside them.
+    pub(crate) fn scrub(&mut self, value: &str) -> String {
+        let mut spans: Vec<(usize, usize, &'static str)> = Vec::new();
+        let mut add = |start: usize, end: usize, kind: &'static str| {
+            if !spans.iter().any(|(s, e, _)| start < *e && end > *s) {
+                spans.push((start, end, kind));
+            }
+        };
+        URL.find_iter(value).for_each(|m| add(m.start(), m.end(), "url"));
+        pii::find(value).into_iter().for_each(|(start, end, kind)| add(start, end, kind));
+        PATH.find_iter(value).for_each(|m| add(m.start(), m.end(), "path"));
+        QUOTED.find_iter(value).for_each(|m| add(m.start(), m.end(), "text"));
+        spans.sort_by_key(|(start, _, _)| *start);
+
+        let mut out = String::with_capacity(value.len());
+        let mut last = 0;
+        for (start, end, kind) in spans {
+            out.push_str(&value[last..start]);
+            out.push_str(&self.placeholder(kind, &value[start..end]));
+            last = end;
+        }
+        out.push_str(&value[last..]);
+        out.chars().take(ERROR_CHARS).collect()
+    }
+
+    /// Log actions read like `Run: <agent name>`; the part after the colon is the user's.
+    fn action(&mut self, value: &str) -> String {
+        match value.split_once(": ") {
+            Some((kind, rest)) => format!("{kind}: {}", self.text(rest)),
+            None => self.scrub(value),
+        }
+    }
+
+    /// Settings that look like options (`on`, `30`, `priority`) are kept; anything else is
+    /// replaced.
+    fn setting(&mut self, value: &str) -> String {
+        let option = value.len() <= 24 && value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-.".contains(c));
+        if option { value.to_string() } else { self.text(value) }
+    }
+}
+
+fn schema(conn: &Connection) -> Result<Vec<Value>, String> {
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+        .map_err(|e| e.to_string())?;
+    let tables = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let mut out = Vec::new();
+    for table in tables {
+        let mut columns = conn.prepare("SELECT name, type FROM pragma_table_info(?1)").map_err(|e| e.to_string())?;
+        let columns = columns.query_map(params![table], |row| Ok(format!("{} {}", row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+        let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{table}\""), [], |row| row.get(0)).map_err(|e| e.to_string())?;
+        out.push(json!({ "table": table, "columns": columns, "rows": rows }));
+    }
+    Ok(out)
+}
+
+fn build(conn: &Connection, anon: &mut Anonymizer) -> Result<Value, String> {
+    let schema = schema(conn)?;
+    let schema_version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key").map_err(|e| e.to_string())?;
+    let settings = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let settings: BTreeMap<String, String> = settings.into_iter().map(|(key, value)| { let value = anon.setting(&value); (key, value) }).collect();
+
+    let mut stmt = conn.prepare("SELECT id, tools, schedule, config_json, sandbox, created_at FROM agents ORDER BY created_at")
+        .map_err(|e| e.to_string())?;
+    let agents = stmt.query_map([], |row| Ok((
+        row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?,
+        row.get::<_, String>(3)?, row.get::<_, i64>(4)?, row.get::<_, String>(5)?,
+    ))).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let agents: Vec<Value> = agents.into_iter().map(|(id, tools, schedule, config, sandbox, created_at)| json!({
+        "agent": anon.placeholder("agent", &id),
+        "tools": serde_json::from_str::<Vec<String>>(&tools).unwrap_or_default(),
+        "scheduled": !schedule.is_empty(),
+        "enabled": bulk::enabled(&config),
+        "sandbox": sandbox != 0,
+        "created_at": created_at,
+    })).collect();
+
+    let mut stmt = conn.prepare("SELECT id, agent_id, action, status, output, error, created_at FROM execution_logs ORDER BY id DESC LIMIT ?1")
+        .map_err(|e| e.to_string())?;
+    let logs = stmt.query_map(params![LOG_LIMIT], |row| Ok((
+        row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?,
+        row.get::<_, String>(4)?, row.get::<_, String>(5)?, row.get::<_, String>(6)?,
+    ))).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let logs: Vec<Value> = logs.into_iter().map(|(id, agent_id, action, status, output, error, created_at)| json!({
+        "id": id,
+        "agent": if agent_id.is_empty() { String::new() } else { anon.placeholder("agent", &agent_id) },
+        "action": anon.action(&action),
+        "status": status,
+        "output": anon.text(&output),
+        "error": anon.scrub(&error),
+        "created_at": created_at,
+    })).collect();
+
+    let mut stmt = conn.prepare(&format!("SELECT {RUN_COLUMNS} FROM runs ORDER BY started_at DESC LIMIT ?1")).map_err(|e| e.to_string())?;
+    let runs = stmt.query_map(params![RUN_LIMIT], row_to_run).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let runs: Vec<Value> = runs.into_iter().map(|run| json!({
+        "run": anon.placeholder("run", &run.id),
+        "agent": anon.placeholder("agent", &run.agent_id),
+        "mode": run.mode.as_str(),
+        "status": run.status,
+        "trigger": run.trigger,
+        "steps": run.steps.iter().map(|step| json!({
+            "tool": step.tool,
+            "status": step.status,
+            "duration_ms": step.duration_ms,
+            "output_chars": step.output.chars().count(),
+            "error": anon.scrub(&step.error),
+        })).collect::<Vec<_>>(),
+        "side_effects": run.side_effects.len(),
+        "error": anon.scrub(&run.error),
+        "started_at": run.started_at,
+        "finished_at": run.finished_at,
+    })).collect();
+
+    Ok(json!({
+        "format": FORMAT,
+        "version": 1,
+        "created_at": Utc::now().to_rfc3339(),
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "schema_version": schema_version,
+        "schema": schema,
+        "settings": settings,
+        "agents": agents,
+        "logs": logs,
+        "runs": runs,
+    }))
+}
+
+// ─── Diagnostics Commands ───
+
+/// Writes a report for bug reports: logs, the database layout and recent runs, with email
+/// addresses, file paths, links, personal data and the user's own text replaced by stable
+/// placeholders. Nothing is sent anywhere; the user attaches the file themselves.
+#[tauri::command]
+pub fn export_anonymized_diagnostics(db: State<DbState>, path: String) -> Result<DiagnosticsSummary, String> {
+    let mut anon = Anonymizer::default();
+    let report = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        build(&conn, &mut anon)?
+    };
+    let count = |key: &str| report[key].as_array().map(Vec::len).unwrap_or(0);
+    let summary = DiagnosticsSummary {
+        path: path.clone(),
+        tables: count("schema"),
+        logs: count("logs"),
+        runs: count("runs"),
+        replaced: anon.counts,
+    };
+    let text = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, text).map_err(|e| format!("Couldn't write {path}: {e}"))?;
+    Ok(summary)
+}
@@ -0,0 +1,438 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use tauri::{AppHandle, Manager, State};
+
+use crate::companion::{self, Companion};
+use crate::{DbState, read_setting, write_setting};
+
+/// Discovery is on unless this is `"false"`.
+const ENABLED_SETTING: &str = "discovery_enabled";
+/// The name phones and the CLI list this computer under; "OpenClaw on <computer>" when unset.
+const NAME_SETTING: &str = "discovery_name";
+const SERVICE: &str = "_openclaw._tcp.local";
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+const CHECK_EVERY: Duration = Duration::from_secs(5);
+const HOST_TTL: u32 = 120;
+const SERVICE_TTL: u32 = 4500;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+/// Class IN with the cache-flush bit, for records only this computer answers for.
+const CLASS_UNIQUE: u16 = 0x8001;
+const CLASS_IN: u16 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiscoverySettings {
+    pub enabled: bool,
+    pub name: String,
+    /// Whether the phone view is being advertised right now.
+    #[serde(default)]
+    pub advertising: bool,
+}
+
+fn computer_name() -> String {
+    ["HOSTNAME", "COMPUTERNAME"].iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| std::process::Command::new("hostname").output().ok()
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "this computer".into())
+}
+
+fn settings(app: &AppHandle) -> Result<DiscoverySettings, String> {
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let name = read_setting(&conn, NAME_SETTING)?
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| format!("OpenClaw on {}", computer_name()));
+    Ok(DiscoverySettings {
+        enabled: read_setting(&conn, ENABLED_SETTING)?.as_deref() != Some("false"),
+        name,
+        advertising: false,
+    })
+}
+
+/// What is advertised: the phone view's port, address and certificate fingerprint, under
+/// the instance name.
+struct Advert {
+    instance: String,
+    host: String,
+    ip: Ipv4Addr,
+    port: u16,
+    fingerprint: String,
+}
+
+fn advert(app: &AppHandle) -> Option<Advert> {
+    let settings = settings(app).ok().filter(|s| s.enabled)?;
+    let (port, fingerprint) = app.state::<Companion>().serving()?;
+    let ip = companion::lan_address()?.parse().ok()?;
+    let host: String = computer_name().chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    // A DNS label is at most 63 bytes.
+    let mut instance = settings.name.replace('.', " ");
+    while instance.len() > 63 {
+        instance.pop();
+    }
+    Some(Advert { instance, host: format!("openclaw-{}.local", host.trim_matches('-')), ip, port, fingerprint })
+}
+
+// ─── DNS Messages ───
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+}
+
+/// Reads a possibly compressed name starting at `pos`; returns it and the position after it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    for _ in 0..64 {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let pointer = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = pointer;
+            continue;
+        }
+        labels.push(String::from_utf8_lossy(packet.get(pos + 1..pos + 1 + len)?).to_string());
+        pos += 1 + len;
+    }
+    None
+}
+
+fn record(out: &mut Vec<u8>, name: &str, kind: u16, class: u16, ttl: u32, data: &[u8]) {
+    write_name(out, name);
+    out.extend_from_slice(&kind.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn instance_name(advert: &Advert) -> String {
+    format!("{}.{SERVICE}", advert.instance)
+}
+
+/// A response carrying every record for the service. `ttl_scale` is 0 for a goodbye.
+fn response(advert: &Advert, id: u16, question: Option<&[u8]>, ttl_scale: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&0x8400u16.to_be_bytes());
+    out.extend_from_slice(&(question.is_some() as u16).to_be_bytes());
+    out.extend_from_slice(&4u16.to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    if let Some(question) = question {
+        out.extend_from_slice(question);
+    }
+    let instance = instance_name(advert);
+
+    let mut ptr = Vec::new();
+    write_name(&mut ptr, &instance);
+    record(&mut out, SERVICE, TYPE_PTR, CLASS_IN, SERVICE_TTL * ttl_scale, &ptr);
+
+    let mut srv = Vec::new();
+    srv.extend_from_slice(&[0, 0, 0, 0]);
+    srv.extend_from_slice(&advert.port.to_be_bytes());
+    write_name(&mut srv, &advert.host);
+    record(&mut out, &instance, TYPE_SRV, CLASS_UNIQUE, HOST_TTL * ttl_scale, &srv);
+
+    let mut txt = Vec::new();
+    let fingerprint = format!("fp={}", advert.fingerprint.replace(':', "").to_lowercase());
+    for entry in ["txtvers=1", "path=/", "tls=1", &fingerprint] {
+        txt.push(entry.len() as u8);
+        txt.extend_from_slice(entry.as_bytes());
+    }
+    record(&mut out, &instance, TYPE_TXT, CLASS_UNIQUE, SERVICE_TTL * ttl_scale, &txt);
+
+    record(&mut out, &advert.host, TYPE_A, CLASS_UNIQUE, HOST_TTL * ttl_scale, &advert.ip.octets());
+    out
+}
+
+/// Whether `packet` is a query asking about the service, and the question to echo for
+/// one-shot queries from ports other than 5353.
+fn asks_for_us(packet: &[u8], advert: &Advert) -> Option<Vec<u8>> {
+    let flags = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]);
+    if flags & 0x8000 != 0 {
+        return None;
+    }
+    let questions = u16::from_be_bytes([*packet.get(4)?, *packet.get(5)?]);
+    let instance = instance_name(advert);
+    let mut pos = 12;
+    for _ in 0..questions {
+        let (name, after) = read_name(packet, pos)?;
+        let kind = u16::from_be_bytes([*packet.get(after)?, *packet.get(after + 1)?]);
+        pos = after + 4;
+        let name = name.to_ascii_lowercase();
+        let matches = match kind {
+            TYPE_PTR => name == SERVICE,
+            TYPE_SRV | TYPE_TXT => name == instance.to_ascii_lowercase(),
+            TYPE_A => name == advert.host,
+            TYPE_ANY => name == SERVICE || name == instance.to_ascii_lowercase() || name == advert.host,
+            _ => false,
+        };
+        if matches {
+            // Echo the question uncompressed so the reply stands on its own.
+            let mut question = Vec::new();
+            write_name(&mut question, &name);
+            question.extend_from_slice(packet.get(after..after + 4)?);
+            return Some(question);
+        }
+    }
+    None
+}
+
+fn open_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // The system's own responder (Bonjour, Avahi) usually holds the port too.
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_ttl_v4(255)?;
+    socket.set_read_timeout(Some(Duration::from_secs(1)))?;
+    Ok(socket.into())
+}
+
+/// Starts the thread that advertises the phone view as `_openclaw._tcp` while it is running
+/// and discovery is on, so phones and the CLI find it without typing an address. The TXT
+/// record carries the certificate fingerprint for them to pin.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("mdns-responder".into())
+        .spawn(move || {
+            let group = SocketAddr::V4(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT));
+            let mut active: Option<(UdpSocket, Advert)> = None;
+            let mut checked = Instant::now() - CHECK_EVERY;
+            let mut buffer = [0u8; 9000];
+            loop {
+                if checked.elapsed() >= CHECK_EVERY {
+                    checked = Instant::now();
+                    let wanted = advert(&app);
+                    let changed = match (&active, &wanted) {
+                        (Some((_, a)), Some(w)) => a.instance != w.instance || a.ip != w.ip || a.port != w.port || a.fingerprint != w.fingerprint,
+                        (None, None) => false,
+                        _ => true,
+                    };
+                    if changed {
+                        if let Some((socket, old)) = active.take() {
+                            let _ = socket.send_to(&response(&old, 0, None, 0), group);
+                        }
+                        if let Some(new) = wanted {
+                            match open_socket() {
+                                Ok(socket) => {
+                                    // Announce twice, a second apart, as mDNS asks.
+                                    let _ = socket.send_to(&response(&new, 0, None, 1), group);
+                                    std::thread::sleep(Duration::from_secs(1));
+                                    let _ = socket.send_to(&response(&new, 0, None, 1), group);
+                                    active = Some((socket, new));
+                                }
+                                Err(e) => eprintln!("Discovery couldn't open the mDNS port: {e}"),
+                            }
+                        }
+                    }
+                }
+                let Some((socket, advert)) = &active else {
+                    std::thread::sleep(Duration::from_secs(1));
+                    continue;
+                };
+                let Ok((len, from)) = socket.recv_from(&mut buffer) else { continue };
+                let packet = &buffer[..len];
+                if let Some(question) = asks_for_us(packet, advert) {
+                    let reply = if from.port() == MDNS_PORT {
+                        socket.send_to(&response(advert, 0, None, 1), group)
+                    } else {
+                        let id = u16::from_be_bytes([packet[0], packet[1]]);
+                        socket.send_to(&response(advert, id, Some(&question), 1), from)
+                    };
+                    if let Err(e) = reply {
+                        eprintln!("Discovery reply failed: {e}");
+                    }
+                }
+            }
+        })
+        .expect("Failed to start the discovery responder");
+}
+
+// ─── Discovery Commands ───
+
+#[tauri::command]
+pub fn get_discovery_settings(app: AppHandle) -> Result<DiscoverySettings, String> {
+    let mut settings = settings(&app)?;
+    settings.advertising = settings.enabled && app.state::<Companion>().serving().is_some();
+    Ok(settings)
+}
+
+/// Turning discovery off stops answering within a few seconds and tells listeners the
+/// service is gone; an empty name goes back to the default.
+#[tauri::command]
+pub fn set_discovery_settings(app: AppHandle, db: State<DbState>, enabled: bool, name: Option<String>) -> Result<DiscoverySettings, String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, ENABLED_SETTING, if enabled { "true" } else { "false" })?;
+        if let Some(name) = name {
+            write_setting(&conn, NAME_SETTING, name.trim())?;
+        }
+    }
+    get_discovery_settings(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Advert {
+        Advert {
+            instance: "OpenClaw on Desk".into(),
+            host: "openclaw-desk.local".into(),
+            ip: Ipv4Addr::new(192, 168, 1, 20),
+            port: 18791,
+            fingerprint: "AB:CD:EF".into(),
+        }
+    }
+
+    /// A query from a one-shot resolver with the given questions.
+    fn query(questions: &[(&str, u16)]) -> Vec<u8> {
+        let mut out = vec![0x12, 0x34, 0, 0];
+        out.extend_from_slice(&(questions.len() as u16).to_be_bytes());
+        out.extend_from_slice(&[0; 6]);
+        for (name, kind) in questions {
+            write_name(&mut out, name);
+            out.extend_from_slice(&kind.to_be_bytes());
+            out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn reads_plain_and_compressed_names() {
+        let mut packet = vec![0; 12];
+        write_name(&mut packet, "_openclaw._tcp.local");
+        assert_eq!(read_name(&packet, 12), Some(("_openclaw._tcp.local".into(), packet.len())));
+        // "desk" then a pointer back to "_tcp.local".
+        let start = packet.len();
+        packet.extend_from_slice(&[4, b'd', b'e', b's', b'k', 0xC0, 22]);
+        assert_eq!(read_name(&packet, start), Some(("desk._tcp.local".into(), start + 7)));
+        assert_eq!(read_name(&[0], 0), Some((String::new(), 1)));
+    }
+
+    #[test]
+    fn refuses_broken_names() {
+        // Running off the end, a label longer than what's left, and a pointer past the end.
+        assert_eq!(read_name(&[], 0), None);
+        assert_eq!(read_name(&[3, b'a', b'b', b'c'], 0), None);
+        assert_eq!(read_name(&[5, b'a', b'b', 0], 0), None);
+        assert_eq!(read_name(&[0xC0], 0), None);
+        assert_eq!(read_name(&[0xC0, 0xFF], 0), None);
+        assert_eq!(read_name(&[1, b'a', 0], 9), None);
+        // Pointers to themselves or in a cycle stop rather than looping.
+        assert_eq!(read_name(&[0xC0, 0], 0), None);
+        assert_eq!(read_name(&[0xC0, 2, 0xC0, 0], 0), None);
+        assert_eq!(read_name(&[1, b'a', 0xC0, 0], 0), None);
+    }
+
+    #[test]
+    fn answers_questions_about_the_service() {
+        let advert = sample();
+        for (name, kind) in [
+            (SERVICE, TYPE_PTR),
+            ("OPENCLAW on desk._openclaw._tcp.local", TYPE_SRV),
+            ("OpenClaw on Desk._openclaw._tcp.local", TYPE_TXT),
+            ("openclaw-desk.local", TYPE_A),
+            ("openclaw-desk.local", TYPE_ANY),
+        ] {
+            let question = asks_for_us(&query(&[("other.local", TYPE_A), (name, kind)]), &advert).unwrap_or_else(|| panic!("{name}"));
+            let mut expected = Vec::new();
+            write_name(&mut expected, &name.to_ascii_lowercase());
+            expected.extend_from_slice(&kind.to_be_bytes());
+            expected.extend_from_slice(&CLASS_IN.to_be_bytes());
+            assert_eq!(question, expected);
+        }
+        assert_eq!(asks_for_us(&query(&[(SERVICE, TYPE_A), ("_http._tcp.local", TYPE_PTR)]), &advert), None);
+        let mut answer = query(&[(SERVICE, TYPE_PTR)]);
+        answer[2] = 0x84;
+        assert_eq!(asks_for_us(&answer, &advert), None, "responses are ignored");
+    }
+
+    #[test]
+    fn survives_truncated_and_hostile_queries() {
+        let advert = sample();
+        let whole = query(&[("nothing.local", TYPE_A), (SERVICE, TYPE_PTR)]);
+        assert!(asks_for_us(&whole, &advert).is_some());
+        for len in 0..whole.len() {
+            assert_eq!(asks_for_us(&whole[..len], &advert), None, "cut to {len} bytes");
+        }
+        // More questions claimed than present, and a question that points at itself.
+        let mut lying = query(&[]);
+        lying[4..6].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert_eq!(asks_for_us(&lying, &advert), None);
+        let mut looping = query(&[]);
+        looping[5] = 1;
+        looping.extend_from_slice(&[0xC0, 12, 0, 12, 0, 1]);
+        assert_eq!(asks_for_us(&looping, &advert), None);
+
+        // Noise shaped like queries: whatever it holds, reading it must not panic.
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..2000 {
+            let mut packet = whole.clone();
+            for _ in 0..4 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let at = (seed as usize) % packet.len();
+                packet[at] = (seed >> 32) as u8;
+            }
+            packet.truncate(12 + (seed >> 40) as usize % (packet.len() - 11));
+            let _ = asks_for_us(&packet, &advert);
+        }
+    }
+
+    #[test]
+    fn builds_a_readable_response() {
+        let advert = sample();
+        let question = asks_for_us(&query(&[(SERVICE, TYPE_PTR)]), &advert).unwrap();
+        let packet = response(&advert, 0x1234, Some(&question), 1);
+        assert_eq!(packet[..12], [0x12, 0x34, 0x84, 0, 0, 1, 0, 4, 0, 0, 0, 0]);
+        let mut pos = 12 + question.len();
+        let mut records = Vec::new();
+        for _ in 0..4 {
+            let (name, after) = read_name(&packet, pos).unwrap();
+            let field = |at: usize| u16::from_be_bytes([packet[at], packet[at + 1]]);
+            let ttl = u32::from_be_bytes(packet[after + 4..after + 8].try_into().unwrap());
+            let len = field(after + 8) as usize;
+            records.push((name, field(after), field(after + 2), ttl, packet[after + 10..after + 10 + len].to_vec()));
+            pos = after + 10 + len;
+        }
+        assert_eq!(pos, packet.len());
+        let instance = "OpenClaw on Desk._openclaw._tcp.local";
+        assert_eq!(records[0].0, SERVICE);
+        assert_eq!((records[0].1, records[0].3), (TYPE_PTR, SERVICE_TTL));
+        assert_eq!(read_name(&records[0].4, 0).unwrap().0, instance);
+        assert_eq!((records[1].0.as_str(), records[1].1, records[1].2), (instance, TYPE_SRV, CLASS_UNIQUE));
+        assert_eq!(records[1].4[4..6], 18791u16.to_be_bytes());
+        assert_eq!(read_name(&records[1].4, 6).unwrap().0, "openclaw-desk.local");
+        assert_eq!(records[2].1, TYPE_TXT);
+        assert_eq!(records[2].4, b"\x09txtvers=1\x06path=/\x05tls=1\x09fp=abcdef");
+        assert_eq!((records[3].1, records[3].4.as_slice()), (TYPE_A, &[192, 168, 1, 20][..]));
+
+        let goodbye = response(&advert, 0, None, 0);
+        assert_eq!(goodbye[4..6], [0, 0]);
+        let (_, after) = read_name(&goodbye, 12).unwrap();
+        assert_eq!(goodbye[after + 4..after + 8], [0, 0, 0, 0], "a goodbye has no time to live");
+    }
+}
@@ -0,0 +1,807 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Manager, State};
+use uuid::Uuid;
+
+use crate::context;
+use crate::format;
+use crate::tools::{ToolContext, compress, files, pdf};
+use crate::{DbState, read_setting, write_setting};
+
+const FORMATS: &[&str] = &["pdf", "docx"];
+/// Where documents go when a step doesn't say.
+const DEFAULT_FOLDER: &str = "~/Documents";
+/// Set once the starter templates are added, so deleting them doesn't bring them back.
+const SEEDED_SETTING: &str = "document_templates_seeded";
+/// Lines of a rendered document shown in an approval preview.
+const PREVIEW_LINES: usize = 40;
+
+const MARGIN: f64 = 56.0;
+const BODY_SIZE: f64 = 10.5;
+/// Helvetica-Bold runs a little wider than the regular widths `pdf::text_width` knows.
+const BOLD_WIDTH: f64 = 1.07;
+
+/// A document template: text with `{{placeholders}}` filled from the data a step passes.
+///
+/// Lines starting `# ` and `## ` are a title and a heading, `---` is a rule, and lines like
+/// `| Item | Price |` make a table whose first row is its header. `{{client.name}}` reaches
+/// into nested data, and `{{#items}} … {{/items}}` repeats its lines for each entry of a
+/// list (or shows them once when the value is set and not false).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentTemplate {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// `pdf` or `docx`, unless the step names a file with the other extension.
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Name for generated files without the extension, with placeholders like the body,
+    /// e.g. `Invoice {{number}}`.
+    #[serde(default)]
+    pub file_name: String,
+    pub body: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+fn default_format() -> String {
+    "pdf".into()
+}
+
+/// A template filled in with sample or real data, before anything is written.
+#[derive(Debug, Serialize, Clone)]
+pub struct RenderedDocument {
+    pub text: String,
+    pub file_name: String,
+    /// Placeholders the data had no value for; they are left blank in `text`.
+    pub missing: Vec<String>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS document_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            format TEXT NOT NULL DEFAULT 'pdf',
+            file_name TEXT NOT NULL DEFAULT '',
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize document template tables");
+    if read_setting(conn, SEEDED_SETTING).ok().flatten().is_none() {
+        seed(conn).expect("Failed to add the starter document templates");
+    }
+}
+
+const INVOICE: &str = "# Invoice {{number}}
+
+{{from.name}}
+{{from.address}}
+
+Billed to: {{to.name}}
+{{to.address}}
+
+Date: {{date}}
+Due: {{due}}
+
+| Description | Quantity | Price | Amount |
+{{#items}}
+| {{description}} | {{quantity}} | {{price}} | {{amount}} |
+{{/items}}
+
+---
+## Total due: {{total}}
+
+{{notes}}
+";
+
+const LETTER: &str = "{{from.name}}
+{{from.address}}
+
+{{today}}
+
+{{to.name}}
+{{to.address}}
+
+## {{subject}}
+
+Dear {{to.name}},
+
+{{body}}
+
+{{closing}}
+{{from.name}}
+";
+
+fn seed(conn: &Connection) -> Result<(), String> {
+    let now = Utc::now().to_rfc3339();
+    let starter = |name: &str, description: &str, file_name: &str, body: &str| DocumentTemplate {
+        id: Uuid::new_v4().to_string(),
+        name: name.into(),
+        description: description.into(),
+        format: "pdf".into(),
+        file_name: file_name.into(),
+        body: body.into(),
+        created_at: now.clone(),
+        updated_at: now.clone(),
+    };
+    insert(conn, &starter(
+        "Invoice",
+        "Numbered invoice with line items. Data: number, date, due, from and to (name, address), items \
+            (description, quantity, price, amount), total, notes.",
+        "Invoice {{number}}",
+        INVOICE,
+    ))?;
+    insert(conn, &starter(
+        "Letter",
+        "Plain letter dated today. Data: from and to (name, address), subject, body, closing.",
+        "Letter to {{to.name}}",
+        LETTER,
+    ))?;
+    write_setting(conn, SEEDED_SETTING, "true")
+}
+
+fn insert(conn: &Connection, template: &DocumentTemplate) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO document_templates (id, name, description, format, file_name, body, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            template.id, template.name, template.description, template.format, template.file_name, template.body,
+            template.created_at, template.updated_at,
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn row_to_template(row: &rusqlite::Row) -> rusqlite::Result<DocumentTemplate> {
+    Ok(DocumentTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        format: row.get(3)?,
+        file_name: row.get(4)?,
+        body: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+const COLUMNS: &str = "id, name, description, format, file_name, body, created_at, updated_at";
+
+/// The template with this id, or else with this name, ignoring case.
+fn find(conn: &Connection, id_or_name: &str) -> Result<DocumentTemplate, String> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM document_templates WHERE id = ?1 OR name = ?1 COLLATE NOCASE ORDER BY id = ?1 DESC LIMIT 1"),
+        params![id_or_name.trim()],
+        row_to_template,
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No document template called \"{id_or_name}\""))
+}
+
+// ─── Rendering ───
+
+/// The value a placeholder names: `.` for the current list entry, otherwise a dotted path
+/// looked up in the innermost scope that has it.
+fn lookup<'a>(scopes: &[&'a Value], path: &str) -> Option<&'a Value> {
+    if path == "." {
+        return scopes.last().copied();
+    }
+    scopes.iter().rev().find_map(|scope| {
+        path.split('.').try_fold(*scope, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+    })
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+/// Where the `{{/name}}` closing the section that starts at `from` is, allowing sections of
+/// the same name inside it.
+fn section_end(body: &str, name: &str, from: usize) -> Option<(usize, usize)> {
+    let (open, close) = (format!("{{{{#{name}}}}}"), format!("{{{{/{name}}}}}"));
+    let mut depth = 1;
+    let mut at = from;
+    loop {
+        let next_close = body[at..].find(&close)? + at;
+        match body[at..next_close].find(&open) {
+            Some(nested) => {
+                depth += 1;
+                at += nested + open.len();
+            }
+            None => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((next_close, next_close + close.len()));
+                }
+                at = next_close + close.len();
+            }
+        }
+    }
+}
+
+/// A section tag alone on its line takes its line break with it, so it leaves no blank line.
+fn skip_newline(body: &str, at: usize) -> usize {
+    match &body.as_bytes()[at..] {
+        [b'\r', b'\n', ..] => at + 2,
+        [b'\n', ..] => at + 1,
+        _ => at,
+    }
+}
+
+fn render_into(body: &str, scopes: &mut Vec<&Value>, out: &mut String, missing: &mut Vec<String>) -> Result<(), String> {
+    let mut rest = 0;
+    while let Some(start) = body[rest..].find("{{").map(|i| i + rest) {
+        out.push_str(&body[rest..start]);
+        let end = body[start..].find("}}").map(|i| i + start).ok_or("A {{ placeholder is never closed with }}")?;
+        let tag = body[start + 2..end].trim();
+        rest = end + 2;
+        if let Some(name) = tag.strip_prefix('#').map(str::trim) {
+            let (inner_end, after) = section_end(body, name, rest).ok_or_else(|| format!("{{{{#{name}}}}} has no {{{{/{name}}}}}"))?;
+            let inner = &body[skip_newline(body, rest)..inner_end];
+            let value = lookup(scopes, name);
+            match value {
+                Some(Value::Array(items)) => {
+                    for item in items {
+                        scopes.push(item);
+                        render_into(inner, scopes, out, missing)?;
+                        scopes.pop();
+                    }
+                }
+                Some(value) if truthy(Some(value)) => {
+                    scopes.push(value);
+                    render_into(inner, scopes, out, missing)?;
+                    scopes.pop();
+                }
+                _ => {}
+            }
+            rest = skip_newline(body, after);
+        } else if let Some(name) = tag.strip_prefix('/') {
+            return Err(format!("{{{{/{}}}}} closes a section that was never opened", name.trim()));
+        } else {
+            match lookup(scopes, tag) {
+                Some(value) => out.push_str(&display(value)),
+                None => {
+                    if !missing.iter().any(|m| m == tag) {
+                        missing.push(tag.to_string());
+                    }
+                }
+            }
+        }
+    }
+    out.push_str(&body[rest..]);
+    Ok(())
+}
+
+/// Fills in a template body that isn't saved, e.g. one spelled out in a workflow step.
+/// Placeholders without a value are left blank and listed.
+pub fn fill(body: &str, data: &Value) -> Result<(String, Vec<String>), String> {
+    let mut missing = Vec::new();
+    let mut text = String::new();
+    render_into(body, &mut vec![data], &mut text, &mut missing)?;
+    Ok((text, missing))
+}
+
+/// Fills `template` in with `data`. Placeholders without a value are left blank and listed.
+pub fn render(template: &DocumentTemplate, data: &Value) -> Result<RenderedDocument, String> {
+    let mut missing = Vec::new();
+    let mut text = String::new();
+    render_into(&template.body, &mut vec![data], &mut text, &mut missing)?;
+    let mut file_name = String::new();
+    let pattern = if template.file_name.trim().is_empty() { &template.name } else { &template.file_name };
+    render_into(pattern, &mut vec![data], &mut file_name, &mut Vec::new())?;
+    // Whatever the data says, the name stays a single file name.
+    let file_name: String = file_name.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '-' } else { c })
+        .collect();
+    let file_name = file_name.trim().trim_matches('.').to_string();
+    Ok(RenderedDocument {
+        text: text.trim_end().to_string(),
+        file_name: if file_name.is_empty() { template.name.clone() } else { file_name },
+        missing,
+    })
+}
+
+// ─── Layout ───
+
+#[derive(Debug, PartialEq)]
+enum Block {
+    Title(String),
+    Heading(String),
+    Text(String),
+    Table(Vec<Vec<String>>),
+    Rule,
+    Space,
+}
+
+fn blocks(text: &str) -> Vec<Block> {
+    let mut out: Vec<Block> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        let trimmed = line.trim();
+        if let Some(row) = trimmed.strip_prefix('|') {
+            let cells: Vec<String> = row.trim_end_matches('|').split('|').map(|c| c.trim().to_string()).collect();
+            // Markdown's `|---|---|` under a header only draws a line, which the header gets anyway.
+            if cells.iter().all(|c| !c.is_empty() && c.chars().all(|ch| matches!(ch, '-' | ':'))) {
+                continue;
+            }
+            match out.last_mut() {
+                Some(Block::Table(rows)) => rows.push(cells),
+                _ => out.push(Block::Table(vec![cells])),
+            }
+        } else if let Some(title) = trimmed.strip_prefix("# ") {
+            out.push(Block::Title(title.trim().to_string()));
+        } else if let Some(heading) = trimmed.strip_prefix("## ") {
+            out.push(Block::Heading(heading.trim().to_string()));
+        } else if !trimmed.is_empty() && trimmed.chars().all(|c| c == '-') && trimmed.len() >= 3 {
+            out.push(Block::Rule);
+        } else if trimmed.is_empty() {
+            if !matches!(out.last(), Some(Block::Space) | None) {
+                out.push(Block::Space);
+            }
+        } else {
+            out.push(Block::Text(line.to_string()));
+        }
+    }
+    while matches!(out.last(), Some(Block::Space)) {
+        out.pop();
+    }
+    out
+}
+
+/// Amounts and counts line up on the right in tables.
+fn numeric(cell: &str) -> bool {
+    let core = cell.trim_start_matches(|c: char| !c.is_ascii_digit() && c != '-').trim_end_matches(|c: char| c.is_alphabetic() || c == '%' || c.is_whitespace());
+    !core.is_empty() && core.chars().any(|c| c.is_ascii_digit()) && core.chars().all(|c| c.is_ascii_digit() || ".,- '".contains(c))
+}
+
+/// Columns after the first whose cells below the header are all numbers, header included.
+fn numeric_columns(rows: &[Vec<String>], columns: usize) -> Vec<bool> {
+    (0..columns).map(|c| {
+        c > 0 && rows.len() > 1 && rows[1..].iter().all(|row| row.get(c).is_none_or(|cell| cell.is_empty() || numeric(cell)))
+    }).collect()
+}
+
+fn width(text: &str, size: f64, bold: bool) -> f64 {
+    pdf::text_width(text, size) * if bold { BOLD_WIDTH } else { 1.0 }
+}
+
+/// Breaks `text` into lines no wider than `max`, splitting words only when one alone is
+/// too wide.
+fn wrap(text: &str, size: f64, bold: bool, max: f64) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{line} {word}") };
+        if width(&candidate, size, bold) <= max || line.is_empty() && width(word, size, bold) <= max {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            if !line.is_empty() && width(&format!("{line}{c}"), size, bold) > max {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Lays the blocks out on A4 pages, one content stream per page.
+struct PdfLayout {
+    pages: Vec<String>,
+    current: String,
+    y: f64,
+}
+
+impl PdfLayout {
+    fn new() -> Self {
+        Self { pages: Vec::new(), current: String::new(), y: pdf::PAGE_SIZE.1 - MARGIN }
+    }
+
+    /// Moves down by `height`, starting a new page first when it wouldn't fit.
+    fn advance(&mut self, height: f64) -> f64 {
+        if self.y - height < MARGIN && !self.current.is_empty() {
+            self.pages.push(std::mem::take(&mut self.current));
+            self.y = pdf::PAGE_SIZE.1 - MARGIN;
+        }
+        self.y -= height;
+        self.y
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, size: f64, bold: bool) {
+        let font = if bold { "F2" } else { "F1" };
+        self.current.push_str(&format!("BT /{font} {size} Tf {x:.2} {y:.2} Td {} Tj ET\n", pdf::pdf_string(text)));
+    }
+
+    fn line(&mut self, y: f64) {
+        self.current.push_str(&format!("0.6 w {MARGIN:.2} {y:.2} m {:.2} {y:.2} l S\n", pdf::PAGE_SIZE.0 - MARGIN));
+    }
+
+    fn lines(&mut self, text: &str, size: f64, bold: bool) {
+        let max = pdf::PAGE_SIZE.0 - 2.0 * MARGIN;
+        for line in wrap(text, size, bold, max) {
+            let y = self.advance(size * 1.4);
+            self.text(MARGIN, y + size * 0.3, &line, size, bold);
+        }
+    }
+
+    fn table(&mut self, rows: &[Vec<String>]) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let available = pdf::PAGE_SIZE.0 - 2.0 * MARGIN;
+        let gap = 8.0;
+        let natural: Vec<f64> = (0..columns).map(|c| {
+            rows.iter().enumerate().filter_map(|(r, row)| row.get(c).map(|cell| width(cell, BODY_SIZE, r == 0))).fold(0.0, f64::max) + gap
+        }).collect();
+        // Narrow columns such as quantities keep their width; the wide ones share the rest
+        // and wrap.
+        let total: f64 = natural.iter().sum();
+        let fair = available / columns as f64;
+        let narrow: f64 = natural.iter().filter(|w| **w <= fair).sum();
+        let wide: f64 = natural.iter().filter(|w| **w > fair).sum();
+        let widths: Vec<f64> = natural.iter().map(|w| match () {
+            _ if total <= available => w + (available - total) / columns as f64,
+            _ if *w <= fair => *w,
+            _ => w * (available - narrow) / wide,
+        }).collect();
+        let right = numeric_columns(rows, columns);
+        let line_height = BODY_SIZE * 1.3;
+        for (r, row) in rows.iter().enumerate() {
+            let header = r == 0 && rows.len() > 1;
+            let cells: Vec<Vec<String>> = row.iter().enumerate().map(|(c, cell)| wrap(cell, BODY_SIZE, header, widths[c] - gap)).collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(1) as f64 * line_height + BODY_SIZE * 0.3;
+            let bottom = self.advance(height);
+            let mut x = MARGIN;
+            for (c, lines) in cells.iter().enumerate() {
+                for (i, line) in lines.iter().enumerate() {
+                    let y = bottom + height - (i + 1) as f64 * line_height + BODY_SIZE * 0.3;
+                    let left = if right[c] { x + widths[c] - gap - width(line, BODY_SIZE, header) } else { x };
+                    self.text(left, y, line, BODY_SIZE, header);
+                }
+                x += widths[c];
+            }
+            if header {
+                self.line(bottom);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        if !self.current.is_empty() || self.pages.is_empty() {
+            self.pages.push(self.current);
+        }
+        self.pages
+    }
+}
+
+fn write_pdf(blocks: &[Block], to: &Path) -> Result<(), String> {
+    let mut layout = PdfLayout::new();
+    for block in blocks {
+        match block {
+            Block::Title(text) => {
+                layout.lines(text, 20.0, true);
+                layout.advance(6.0);
+            }
+            Block::Heading(text) => {
+                layout.advance(4.0);
+                layout.lines(text, 13.0, true);
+            }
+            Block::Text(text) => layout.lines(text, BODY_SIZE, false),
+            Block::Table(rows) => layout.table(rows),
+            Block::Rule => {
+                let y = layout.advance(10.0);
+                layout.line(y + 5.0);
+            }
+            Block::Space => {
+                layout.advance(BODY_SIZE * 0.8);
+            }
+        }
+    }
+    pdf::write_new(&layout.finish(), to)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\t')
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                c => out.push(c),
+            }
+            out
+        })
+}
+
+fn docx_paragraph(text: &str, style: Option<&str>, bold: bool, right: bool) -> String {
+    let mut properties = String::new();
+    if let Some(style) = style {
+        properties.push_str(&format!("<w:pStyle w:val=\"{style}\"/>"));
+    }
+    if right {
+        properties.push_str("<w:jc w:val=\"right\"/>");
+    }
+    let run_properties = if bold { "<w:rPr><w:b/></w:rPr>" } else { "" };
+    format!(
+        "<w:p><w:pPr>{properties}</w:pPr><w:r>{run_properties}<w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>",
+        xml_escape(text),
+    )
+}
+
+const DOCX_CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/><Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/></Types>"#;
+
+const DOCX_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+const DOCX_DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+const DOCX_STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Calibri" w:hAnsi="Calibri" w:cs="Calibri"/><w:sz w:val="22"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="60"/></w:pPr></w:pPrDefault></w:docDefaults><w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/></w:style><w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="200"/></w:pPr><w:rPr><w:b/><w:sz w:val="40"/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:before="160" w:after="80"/></w:pPr><w:rPr><w:b/><w:sz w:val="28"/></w:rPr></w:style><w:style w:type="table" w:styleId="TableGrid"><w:name w:val="Table Grid"/><w:tblPr><w:tblBorders><w:bottom w:val="single" w:sz="4" w:color="999999"/><w:insideH w:val="single" w:sz="4" w:color="DDDDDD"/></w:tblBorders></w:tblPr></w:style></w:styles>"#;
+
+fn write_docx(blocks: &[Block], to: &Path) -> Result<(), String> {
+    let mut body = String::new();
+    for block in blocks {
+        match block {
+            Block::Title(text) => body.push_str(&docx_paragraph(text, Some("Title"), false, false)),
+            Block::Heading(text) => body.push_str(&docx_paragraph(text, Some("Heading1"), false, false)),
+            Block::Text(text) => body.push_str(&docx_paragraph(text, None, false, false)),
+            Block::Space => body.push_str("<w:p/>"),
+            Block::Rule => body.push_str(
+                "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr></w:pPr></w:p>",
+            ),
+            Block::Table(rows) => {
+                let columns = rows.iter().map(Vec::len).max().unwrap_or(1).max(1);
+                let right = numeric_columns(rows, columns);
+                body.push_str("<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"5000\" w:type=\"pct\"/></w:tblPr><w:tblGrid>");
+                body.push_str(&format!("<w:gridCol w:w=\"{}\"/>", 9000 / columns).repeat(columns));
+                body.push_str("</w:tblGrid>");
+                for (r, row) in rows.iter().enumerate() {
+                    let header = r == 0 && rows.len() > 1;
+                    body.push_str("<w:tr>");
+                    for (c, right) in right.iter().enumerate() {
+                        let cell = row.get(c).map(String::as_str).unwrap_or_default();
+                        body.push_str("<w:tc>");
+                        body.push_str(&docx_paragraph(cell, None, header, *right));
+                        body.push_str("</w:tc>");
+                    }
+                    body.push_str("</w:tr>");
+                }
+                body.push_str("</w:tbl>");
+            }
+        }
+    }
+    let document = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\"><w:body>{body}<w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/><w:pgMar w:top=\"1134\" w:right=\"1134\" w:bottom=\"1134\" w:left=\"1134\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/></w:sectPr></w:body></w:document>",
+    );
+    let bytes = compress::zip_bytes(&[
+        ("[Content_Types].xml", DOCX_CONTENT_TYPES.as_bytes()),
+        ("_rels/.rels", DOCX_RELS.as_bytes()),
+        ("word/_rels/document.xml.rels", DOCX_DOCUMENT_RELS.as_bytes()),
+        ("word/styles.xml", DOCX_STYLES.as_bytes()),
+        ("word/document.xml", document.as_bytes()),
+    ])?;
+    files::create_file(to, &bytes)
+}
+
+/// Writes `text` as a PDF or DOCX, by the extension of `to`.
+pub fn write(text: &str, to: &Path) -> Result<(), String> {
+    let blocks = blocks(text);
+    match output_format(to).as_deref() {
+        Some("pdf") => write_pdf(&blocks, to),
+        Some("docx") => write_docx(&blocks, to),
+        _ => Err(format!("{} should end in .pdf or .docx", to.display())),
+    }
+}
+
+fn output_format(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).filter(|e| FORMATS.contains(&e.as_str()))
+}
+
+// ─── The generate_document tool ───
+
+/// The data a step passes, as an object or as JSON text, e.g. from a prompt step with a schema.
+fn data_param(params: &Value) -> Result<Value, String> {
+    match params.get("data") {
+        Some(Value::String(text)) => serde_json::from_str(text).map_err(|e| format!("The document data isn't valid JSON: {e}")),
+        Some(value @ Value::Object(_)) => Ok(value.clone()),
+        _ => Err("Missing parameter 'data'".into()),
+    }
+}
+
+/// `{{today}}` is always available, written the way the user's locale writes dates.
+fn with_today(mut data: Value, locale: &str) -> Value {
+    if let Value::Object(map) = &mut data {
+        map.entry("today").or_insert_with(|| Value::String(format::date(locale, &Local::now())));
+    }
+    data
+}
+
+fn template_param(params: &Value) -> Result<&str, String> {
+    params.get("template").and_then(Value::as_str).filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| "Missing parameter 'template'".to_string())
+}
+
+/// `to` as given, or `file_name` in Documents with the template's format; `(2)`, `(3)` and
+/// so on are added when a generated name is taken. A named file is never replaced.
+fn output_path(to: Option<PathBuf>, rendered: &RenderedDocument, template: &DocumentTemplate) -> Result<PathBuf, String> {
+    if let Some(to) = to {
+        if output_format(&to).is_none() {
+            return Err(format!("{} should end in .pdf or .docx", to.display()));
+        }
+        return Ok(to);
+    }
+    let folder = files::expand_path(DEFAULT_FOLDER);
+    let extension = if FORMATS.contains(&template.format.as_str()) { template.format.as_str() } else { "pdf" };
+    let mut path = folder.join(format!("{}.{extension}", rendered.file_name));
+    let mut n = 2;
+    while path.exists() {
+        path = folder.join(format!("{} ({n}).{extension}", rendered.file_name));
+        n += 1;
+    }
+    Ok(path)
+}
+
+fn to_param(params: &Value) -> Option<&str> {
+    params.get("to").and_then(Value::as_str).filter(|t| !t.trim().is_empty())
+}
+
+/// The document as it would be written, shown when approving or simulating the step.
+pub fn preview(conn: &Connection, params: &Value) -> String {
+    let name = params.get("template").and_then(Value::as_str).unwrap_or("?");
+    let prepared = template_param(params)
+        .and_then(|t| find(conn, t))
+        .and_then(|template| {
+            let rendered = render(&template, &with_today(data_param(params)?, &format::locale_of(conn)?))?;
+            let to = output_path(to_param(params).map(files::expand_path), &rendered, &template)?;
+            Ok((rendered, to))
+        });
+    match prepared {
+        Ok((rendered, to)) => {
+            let lines: Vec<&str> = rendered.text.lines().collect();
+            let mut text = lines.iter().take(PREVIEW_LINES).copied().collect::<Vec<_>>().join("\n");
+            if lines.len() > PREVIEW_LINES {
+                text.push_str(&format!("\n…and {} more line(s)", lines.len() - PREVIEW_LINES));
+            }
+            let missing = if rendered.missing.is_empty() {
+                String::new()
+            } else {
+                format!(" ({} left blank)", rendered.missing.join(", "))
+            };
+            format!("Would create {} from the {name} template{missing}:\n\n{text}", to.display())
+        }
+        Err(e) => format!("Would create a document from the {name} template, but: {e}"),
+    }
+}
+
+/// The folder `generate_document` writes to, for the run's change report.
+pub fn touched_dirs(ctx: &ToolContext, params: &Value) -> Vec<PathBuf> {
+    match to_param(params) {
+        Some(to) => ctx.path(to).parent().map(PathBuf::from).into_iter().collect(),
+        None => vec![files::expand_path(DEFAULT_FOLDER)],
+    }
+}
+
+/// Renders the template with the step's data and writes the file. The filled-in text is kept
+/// as the step's artifact. Data missing a placeholder's value is refused rather than
+/// producing a document with gaps.
+pub fn run_tool(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let (template, locale) = {
+        let db = ctx.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (find(&conn, template_param(params)?)?, format::locale_of(&conn)?)
+    };
+    let rendered = render(&template, &with_today(data_param(params)?, &locale))?;
+    if !rendered.missing.is_empty() {
+        return Err(format!("The data has no value for {}", rendered.missing.join(", ")));
+    }
+    let to = output_path(to_param(params).map(|t| ctx.path(t)), &rendered, &template)?;
+    write(&rendered.text, &to)?;
+    context::record(ctx, "document", &to.display().to_string(), &rendered.text)?;
+    Ok(format!("Created {} from the {} template", to.display(), template.name))
+}
+
+fn validate(template: &DocumentTemplate) -> Result<(), String> {
+    if template.name.trim().is_empty() {
+        return Err("Give the template a name".into());
+    }
+    if !FORMATS.contains(&template.format.as_str()) {
+        return Err(format!("Format must be one of {}", FORMATS.join(", ")));
+    }
+    if template.body.trim().is_empty() {
+        return Err("The template is empty".into());
+    }
+    render(template, &Value::Object(Default::default())).map(|_| ())
+}
+
+// ─── Document Template Commands ───
+
+#[tauri::command]
+pub fn list_document_templates(db: State<DbState>) -> Result<Vec<DocumentTemplate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM document_templates ORDER BY name COLLATE NOCASE"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_template).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Adds a template, or replaces the one with the same id. Names are unique, since steps
+/// refer to templates by name.
+#[tauri::command]
+pub fn save_document_template(db: State<DbState>, template: DocumentTemplate) -> Result<DocumentTemplate, String> {
+    let mut template = DocumentTemplate {
+        name: template.name.trim().to_string(),
+        format: template.format.trim().to_lowercase(),
+        file_name: template.file_name.trim().to_string(),
+        ..template
+    };
+    validate(&template)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let taken: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM document_templates WHERE name = ?1 COLLATE NOCASE AND id != ?2",
+        params![template.name, template.id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if taken {
+        return Err(format!("There is already a template called {}", template.name));
+    }
+    let now = Utc::now().to_rfc3339();
+    if template.id.is_empty() {
+        template.id = Uuid::new_v4().to_string();
+    }
+    if template.created_at.is_empty() {
+        template.created_at = now.clone();
+    }
+    template.updated_at = now;
+    insert(&conn, &template)?;
+    Ok(template)
+}
+
+#[tauri::command]
+pub fn delete_document_template(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM document_templates WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Fills a template, saved or not, with sample data so it can be checked before an agent
+/// uses it. Nothing is written.
+#[tauri::command]
+pub fn preview_document(template: DocumentTemplate, data: Value) -> Result<RenderedDocument, String> {
+    render(&template, &data)
+}
@@ -0,0 +1,437 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{Manager, State};
+use uuid::Uuid;
+
+use crate::templates::CatalogTemplate;
+use crate::tools::fileops;
+use crate::tools::{ToolContext, files};
+use crate::workflows::WorkflowStep;
+use crate::{DbState, read_setting, write_setting};
+
+const ACTIONS: &[&str] = &["move", "delete", "keep"];
+const DEFAULT_FOLDER: &str = "~/Downloads";
+/// Set once the starter rules are added, so deleting them all doesn't bring them back.
+const SEEDED_SETTING: &str = "download_rules_seeded";
+/// Browsers write to these while a download is still arriving.
+const PARTIAL_EXTENSIONS: &[&str] = &["crdownload", "part", "partial", "download", "opdownload", "tmp"];
+
+/// One rule for tidying the downloads folder. Every condition that is set must hold; the
+/// first enabled rule in order that matches a file decides what happens to it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadRule {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Extensions without the dot; empty matches any.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Site the file came from, e.g. `bank.com`, which also matches its subdomains.
+    #[serde(default)]
+    pub domain: String,
+    /// A name pattern with `*` and `?`; empty matches any.
+    #[serde(default)]
+    pub pattern: String,
+    /// Only files last changed at least this many days ago.
+    #[serde(default)]
+    pub min_age_days: Option<i64>,
+    /// `move` to `destination`, `delete`, or `keep` to leave the file where it is.
+    pub action: String,
+    /// Relative to the downloads folder or absolute; may use `{year}`, `{month}`, `{day}`
+    /// and `{ext}`.
+    #[serde(default)]
+    pub destination: String,
+    #[serde(default)]
+    pub position: i64,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What the rules would do with one file.
+#[derive(Debug, Serialize, Clone)]
+pub struct RuleMatch {
+    pub path: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub action: String,
+    /// Where a `move` would put the file.
+    pub to: Option<String>,
+    /// The address the file was downloaded from, when a rule needed to look.
+    pub source: Option<String>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS download_rules (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            extensions TEXT NOT NULL DEFAULT '[]',
+            domain TEXT NOT NULL DEFAULT '',
+            pattern TEXT NOT NULL DEFAULT '',
+            min_age_days INTEGER,
+            action TEXT NOT NULL,
+            destination TEXT NOT NULL DEFAULT '',
+            position INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize download rule tables");
+    if read_setting(conn, SEEDED_SETTING).ok().flatten().is_none() {
+        seed(conn).expect("Failed to add the starter download rules");
+    }
+}
+
+/// Starter rules, sorting by type. Old installers are cleared out only once the user turns
+/// that rule on.
+fn seed(conn: &Connection) -> Result<(), String> {
+    let now = Utc::now().to_rfc3339();
+    let starter = |position: i64, name: &str, extensions: &[&str], action: &str, destination: &str| DownloadRule {
+        id: Uuid::new_v4().to_string(),
+        name: name.into(),
+        enabled: true,
+        extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        domain: String::new(),
+        pattern: String::new(),
+        min_age_days: None,
+        action: action.into(),
+        destination: destination.into(),
+        position,
+        created_at: now.clone(),
+    };
+    let starters = [
+        starter(0, "Images", &["jpg", "jpeg", "png", "gif", "heic", "webp", "svg"], "move", "~/Pictures/Downloads"),
+        starter(1, "Documents", &["pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "rtf", "txt", "csv"], "move",
+            "~/Documents/Downloads/{year}-{month}"),
+        starter(2, "Archives", &["zip", "rar", "7z", "tar", "gz"], "move", "Archives"),
+        starter(3, "Audio and video", &["mp3", "m4a", "wav", "flac", "mp4", "mov", "mkv", "avi"], "move", "Media"),
+        DownloadRule {
+            enabled: false,
+            min_age_days: Some(30),
+            ..starter(4, "Old installers", &["dmg", "pkg", "exe", "msi", "deb", "rpm", "appimage"], "delete", "")
+        },
+    ];
+    for rule in &starters {
+        insert(conn, rule)?;
+    }
+    write_setting(conn, SEEDED_SETTING, "true")
+}
+
+fn insert(conn: &Connection, rule: &DownloadRule) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO download_rules (id, name, enabled, extensions, domain, pattern, min_age_days, action, destination, position, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            rule.id, rule.name, rule.enabled, json!(rule.extensions).to_string(), rule.domain, rule.pattern,
+            rule.min_age_days, rule.action, rule.destination, rule.position, rule.created_at,
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn row_to_rule(row: &rusqlite::Row) -> rusqlite::Result<DownloadRule> {
+    Ok(DownloadRule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        enabled: row.get::<_, i64>(2)? != 0,
+        extensions: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+        domain: row.get(4)?,
+        pattern: row.get(5)?,
+        min_age_days: row.get(6)?,
+        action: row.get(7)?,
+        destination: row.get(8)?,
+        position: row.get(9)?,
+        created_at: row.get(10)?,
+    })
+}
+
+pub fn rules(conn: &Connection) -> Result<Vec<DownloadRule>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, enabled, extensions, domain, pattern, min_age_days, action, destination, position, created_at
+         FROM download_rules ORDER BY position, created_at",
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_rule).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    std::process::Command::new(program).args(args).output().ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Where a file was downloaded from, as the browser or the system recorded it.
+#[cfg(target_os = "macos")]
+fn source_url(path: &Path) -> Option<String> {
+    // A list like ("https://example.com/file.pdf", "https://example.com/"), or (null).
+    let out = run("mdls", &["-raw", "-name", "kMDItemWhereFroms", &path.to_string_lossy()])?;
+    out.split('"').skip(1).step_by(2).find(|s| s.contains("://")).map(str::to_string)
+}
+
+#[cfg(target_os = "linux")]
+fn source_url(path: &Path) -> Option<String> {
+    // Chromium and Firefox record this extended attribute on downloads.
+    run("getfattr", &["--only-values", "--absolute-names", "-n", "user.xdg.origin.url", &path.to_string_lossy()])
+        .filter(|url| url.contains("://"))
+}
+
+#[cfg(target_os = "windows")]
+fn source_url(path: &Path) -> Option<String> {
+    // The Mark of the Web, a stream beside the file with HostUrl= and ReferrerUrl= lines.
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":Zone.Identifier");
+    let text = std::fs::read_to_string(PathBuf::from(stream)).ok()?;
+    let field = |key: &str| text.lines().find_map(|line| line.strip_prefix(key)).map(str::trim).filter(|url| url.contains("://"));
+    field("HostUrl=").or_else(|| field("ReferrerUrl=")).map(str::to_string)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn source_url(_path: &Path) -> Option<String> {
+    None
+}
+
+fn from_domain(url: &str, domain: &str) -> bool {
+    let Some(host) = tauri::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase)) else {
+        return false;
+    };
+    let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+    host == domain || host.ends_with(&format!(".{domain}"))
+}
+
+fn age_days(path: &Path) -> Option<i64> {
+    let modified: DateTime<Utc> = std::fs::metadata(path).and_then(|m| m.modified()).ok()?.into();
+    Some((Utc::now() - modified).num_days())
+}
+
+/// Whether `rule` applies to the file. The download source is looked up at most once per
+/// file, and only when a rule asks for it.
+fn matches(rule: &DownloadRule, path: &Path, source: &mut Option<Option<String>>) -> bool {
+    let ext = fileops::extension(path);
+    if !rule.extensions.is_empty() && !rule.extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext)) {
+        return false;
+    }
+    if !rule.pattern.is_empty() && !fileops::glob_match(&rule.pattern, &fileops::name(path)) {
+        return false;
+    }
+    if rule.min_age_days.is_some_and(|days| age_days(path).is_none_or(|age| age < days)) {
+        return false;
+    }
+    if !rule.domain.trim().is_empty() {
+        let url = source.get_or_insert_with(|| source_url(path));
+        return url.as_deref().is_some_and(|url| from_domain(url, &rule.domain));
+    }
+    true
+}
+
+/// What the enabled rules would do with each file directly in `folder`. Files still being
+/// downloaded, hidden files and files no rule matches are left out.
+pub fn plan(folder: &Path, rules: &[DownloadRule]) -> Result<Vec<RuleMatch>, String> {
+    let mut out = Vec::new();
+    for path in fileops::files_in(folder)? {
+        let name = fileops::name(&path);
+        if name.starts_with('.') || PARTIAL_EXTENSIONS.contains(&fileops::extension(&path).as_str()) {
+            continue;
+        }
+        let mut source = None;
+        let Some(rule) = rules.iter().filter(|r| r.enabled).find(|r| matches(r, &path, &mut source)) else {
+            continue;
+        };
+        let to = (rule.action == "move").then(|| fileops::destination(folder, &rule.destination, &path));
+        if to.as_deref() == Some(folder) {
+            continue;
+        }
+        out.push(RuleMatch {
+            path: path.display().to_string(),
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            action: rule.action.clone(),
+            to: to.map(|dir| dir.join(&name).display().to_string()),
+            source: source.flatten(),
+        });
+    }
+    Ok(out)
+}
+
+fn moves(plan: &[RuleMatch]) -> Vec<(PathBuf, PathBuf)> {
+    plan.iter().filter_map(|m| m.to.as_ref().map(|to| (PathBuf::from(&m.path), PathBuf::from(to)))).collect()
+}
+
+fn deletes(plan: &[RuleMatch]) -> Vec<&RuleMatch> {
+    plan.iter().filter(|m| m.action == "delete").collect()
+}
+
+fn describe(verbs: (&str, &str), plan: &[RuleMatch]) -> String {
+    let moves = moves(plan);
+    let deletes = deletes(plan);
+    if moves.is_empty() && deletes.is_empty() {
+        return "No downloads match a rule, so nothing would change".into();
+    }
+    let mut parts = Vec::new();
+    if !moves.is_empty() {
+        parts.push(fileops::describe_plan(verbs.0, &moves));
+    }
+    if !deletes.is_empty() {
+        let mut lines = vec![format!("{} {} file(s):", verbs.1, deletes.len())];
+        lines.extend(deletes.iter().map(|m| format!("  {} ({})", m.path, m.rule_name)));
+        parts.push(lines.join("\n"));
+    }
+    parts.join("\n")
+}
+
+fn folder_param(params: &Value) -> &str {
+    params.get("folder").and_then(Value::as_str).filter(|f| !f.trim().is_empty()).unwrap_or(DEFAULT_FOLDER)
+}
+
+/// The dry run shown before `organize_downloads` is approved.
+pub fn preview(ctx: &ToolContext, conn: &Connection, params: &Value) -> String {
+    let folder = ctx.path(folder_param(params));
+    rules(conn).and_then(|rules| plan(&folder, &rules))
+        .map(|plan| describe(("Would move", "Would delete"), &plan))
+        .unwrap_or_else(|e| format!("Would tidy {} by your download rules, but: {e}", folder.display()))
+}
+
+/// Folders `organize_downloads` would change, for the run's change report.
+pub fn touched_dirs(ctx: &ToolContext, params: &Value) -> Vec<PathBuf> {
+    let folder = ctx.path(folder_param(params));
+    let db = ctx.app.state::<DbState>();
+    let rules = db.0.lock().map_err(|e| e.to_string()).and_then(|conn| rules(&conn)).unwrap_or_default();
+    let mut dirs: Vec<PathBuf> = plan(&folder, &rules).map(|plan| moves(&plan)).unwrap_or_default()
+        .into_iter()
+        .filter_map(|(_, to)| to.parent().map(PathBuf::from))
+        .collect();
+    dirs.push(folder);
+    dirs
+}
+
+pub fn run_tool(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let rules = {
+        let db = ctx.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        rules(&conn)?
+    };
+    let plan = plan(&ctx.path(folder_param(params)), &rules)?;
+    let moves = moves(&plan);
+    if !moves.is_empty() {
+        fileops::apply_plan("Moved", &moves)?;
+    }
+    for m in deletes(&plan) {
+        files::delete_file(Path::new(&m.path))?;
+    }
+    Ok(describe(("Moved", "Deleted"), &plan))
+}
+
+/// Built-in template: tidy the downloads folder every evening by the stored rules.
+pub fn template() -> CatalogTemplate {
+    CatalogTemplate {
+        id: "builtin:downloads-organizer".into(),
+        name: "Organize my downloads".into(),
+        description: "Sorts your Downloads folder every evening: moves files by type, name, age or the site they \
+            came from, and clears out what you no longer need. Change the rules under Download rules.".into(),
+        author: "OpenClaw".into(),
+        tags: vec!["files".into(), "downloads".into(), "cleanup".into()],
+        role: "File organizer".into(),
+        goal: "Keep the Downloads folder tidy".into(),
+        schedule: "0 18 * * *".into(),
+        tools: vec!["organize_downloads".into()],
+        workflow: vec![WorkflowStep::Tool {
+            tool: "organize_downloads".into(),
+            params: json!({ "folder": DEFAULT_FOLDER }),
+            label: "Tidy Downloads".into(),
+        }],
+    }
+}
+
+fn validate(rule: &DownloadRule) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("Give the rule a name".into());
+    }
+    if !ACTIONS.contains(&rule.action.as_str()) {
+        return Err(format!("Action must be one of {}", ACTIONS.join(", ")));
+    }
+    if rule.action == "move" && rule.destination.trim().is_empty() {
+        return Err("Say which folder to move the files to".into());
+    }
+    if rule.min_age_days.is_some_and(|days| days < 0) {
+        return Err("Age can't be negative".into());
+    }
+    Ok(())
+}
+
+// ─── Download Rule Commands ───
+
+#[tauri::command]
+pub fn list_download_rules(db: State<DbState>) -> Result<Vec<DownloadRule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    rules(&conn)
+}
+
+/// Adds a rule at the end, or replaces the one with the same id.
+#[tauri::command]
+pub fn save_download_rule(db: State<DbState>, rule: DownloadRule) -> Result<DownloadRule, String> {
+    validate(&rule)?;
+    let mut rule = rule;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if rule.id.is_empty() {
+        rule.id = Uuid::new_v4().to_string();
+        rule.position = conn.query_row("SELECT COALESCE(MAX(position) + 1, 0) FROM download_rules", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+    }
+    if rule.created_at.is_empty() {
+        rule.created_at = Utc::now().to_rfc3339();
+    }
+    rule.name = rule.name.trim().to_string();
+    rule.domain = rule.domain.trim().to_string();
+    rule.pattern = rule.pattern.trim().to_string();
+    rule.destination = rule.destination.trim().to_string();
+    rule.extensions = rule.extensions.iter().map(|e| e.trim().trim_start_matches('.').to_lowercase()).filter(|e| !e.is_empty()).collect();
+    insert(&conn, &rule)?;
+    Ok(rule)
+}
+
+#[tauri::command]
+pub fn delete_download_rule(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM download_rules WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Puts the rules in the order given; rules not listed keep their place after them.
+#[tauri::command]
+pub fn reorder_download_rules(db: State<DbState>, ids: Vec<String>) -> Result<Vec<DownloadRule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let offset = ids.len() as i64;
+    conn.execute("UPDATE download_rules SET position = position + ?1", params![offset]).map_err(|e| e.to_string())?;
+    for (position, id) in ids.iter().enumerate() {
+        conn.execute("UPDATE download_rules SET position = ?1 WHERE id = ?2", params![position as i64, id])
+            .map_err(|e| e.to_string())?;
+    }
+    rules(&conn)
+}
+
+/// What the rules would do with the files in `folder` (Downloads by default), without
+/// touching anything. With `rule`, tries just that rule, saved or not.
+#[tauri::command]
+pub async fn test_download_rules(db: State<'_, DbState>, folder: Option<String>, rule: Option<DownloadRule>) -> Result<Vec<RuleMatch>, String> {
+    let rules = match rule {
+        Some(rule) => {
+            validate(&rule)?;
+            vec![DownloadRule { enabled: true, ..rule }]
+        }
+        None => {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            rules(&conn)?
+        }
+    };
+    let folder = files::expand_path(folder.as_deref().filter(|f| !f.trim().is_empty()).unwrap_or(DEFAULT_FOLDER));
+    tauri::async_runtime::spawn_blocking(move || plan(&folder, &rules)).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,973 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::audit;
+use crate::blackout;
+use crate::changes::{self, FileChange};
+use crate::bulk;
+use crate::fixtures::{self, ActiveFixture};
+use crate::grace::{self, Grace};
+use crate::injection;
+use crate::llm;
+use crate::net;
+use crate::notifications::{self, Event};
+use crate::pii::{self, PiiPolicy};
+use crate::policy;
+use crate::priority;
+use crate::profiles;
+use crate::routing;
+use crate::sanitize;
+use crate::sensitivity;
+use crate::shutdown;
+use crate::stats;
+use crate::supervision;
+use crate::tool_calling::{self, Next};
+use crate::tools::{self, ToolContext};
+use crate::transcribe;
+use crate::variables;
+use crate::windows;
+use crate::workflows::{self, WorkflowStep};
+use crate::{DbState, ensure_column, insert_approval, insert_log};
+
+/// `Simulate` runs read-only tools for real but only records what side-effecting tools would do.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    Live,
+    Simulate,
+}
+
+impl RunMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunMode::Live => "live",
+            RunMode::Simulate => "simulate",
+        }
+    }
+
+    fn parse(s: &str) -> RunMode {
+        if s == "live" { RunMode::Live } else { RunMode::Simulate }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StepResult {
+    pub index: usize,
+    pub tool: String,
+    pub label: String,
+    /// Params after placeholders were filled in.
+    pub params: Value,
+    /// `ok`, `simulated`, `error`, `awaiting_approval`, `pending_action`, `rejected`, `cancelled`
+    /// or `interrupted`.
+    pub status: String,
+    pub output: String,
+    pub error: String,
+    pub duration_ms: i64,
+    /// Parsed output of a step that was asked for JSON matching a schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    /// Why the step's output looks like a prompt injection, for steps reading untrusted content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub injection: Option<String>,
+    /// Provider and model that answered the step's model calls, e.g. `openai/gpt-4o-mini`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub served_by: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Run {
+    pub id: String,
+    pub agent_id: String,
+    pub mode: RunMode,
+    /// `running`, `success`, `error`, `awaiting_approval`, `pending_action`, `cancelled` or
+    /// `interrupted` (stopped between steps when the app closed; resumable).
+    pub status: String,
+    pub trigger: String,
+    pub steps: Vec<StepResult>,
+    /// What the run changed (or, when simulated, would have changed) outside the app.
+    pub side_effects: Vec<String>,
+    pub error: String,
+    pub started_at: String,
+    pub finished_at: String,
+    /// Marked by the user as the expected result for regression tests.
+    pub golden: bool,
+    /// The user's note on this run, if any.
+    #[serde(default)]
+    pub note: String,
+    /// What the run created, changed, moved or deleted in the folders its file steps wrote in.
+    #[serde(default)]
+    pub changes: Vec<FileChange>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS runs (
+            id TEXT PRIMARY KEY,
+            agent_id TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            status TEXT NOT NULL,
+            trigger_kind TEXT DEFAULT 'manual',
+            plan_json TEXT DEFAULT '[]',
+            steps_json TEXT DEFAULT '[]',
+            side_effects_json TEXT DEFAULT '[]',
+            error TEXT DEFAULT '',
+            started_at TEXT NOT NULL,
+            finished_at TEXT DEFAULT ''
+        );
+        CREATE INDEX IF NOT EXISTS idx_runs_agent ON runs(agent_id, started_at);
+    ").expect("Failed to initialize run tables");
+    ensure_column(conn, "runs", "golden", "INTEGER DEFAULT 0").expect("Failed to migrate run tables");
+    ensure_column(conn, "runs", "task_json", "TEXT DEFAULT ''").expect("Failed to migrate run tables");
+    ensure_column(conn, "runs", "changes_json", "TEXT DEFAULT '[]'").expect("Failed to migrate run tables");
+}
+
+pub(crate) const RUN_COLUMNS: &str = "id, agent_id, mode, status, trigger_kind, steps_json, side_effects_json, error, started_at, finished_at, golden,
+    COALESCE((SELECT note FROM run_notes WHERE kind = 'run' AND target = runs.id), ''), changes_json";
+
+pub(crate) fn row_to_run(row: &rusqlite::Row) -> rusqlite::Result<Run> {
+    let steps: String = row.get(5)?;
+    let side_effects: String = row.get(6)?;
+    Ok(Run {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        mode: RunMode::parse(&row.get::<_, String>(2)?),
+        status: row.get(3)?,
+        trigger: row.get(4)?,
+        steps: serde_json::from_str(&steps).unwrap_or_default(),
+        side_effects: serde_json::from_str(&side_effects).unwrap_or_default(),
+        error: row.get(7)?,
+        started_at: row.get(8)?,
+        finished_at: row.get(9)?,
+        golden: row.get::<_, i32>(10)? != 0,
+        note: row.get(11)?,
+        changes: serde_json::from_str(&row.get::<_, String>(12)?).unwrap_or_default(),
+    })
+}
+
+pub fn load_run(conn: &Connection, id: &str) -> Result<Run, String> {
+    conn.query_row(&format!("SELECT {RUN_COLUMNS} FROM runs WHERE id = ?1"), params![id], row_to_run)
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Run not found".to_string())
+}
+
+fn save_run(conn: &Connection, run: &Run) -> Result<(), String> {
+    conn.execute(
+        "UPDATE runs SET status = ?1, steps_json = ?2, side_effects_json = ?3, error = ?4, finished_at = ?5, changes_json = ?6 WHERE id = ?7",
+        params![
+            run.status,
+            serde_json::to_string(&run.steps).map_err(|e| e.to_string())?,
+            serde_json::to_string(&run.side_effects).map_err(|e| e.to_string())?,
+            run.error,
+            run.finished_at,
+            serde_json::to_string(&run.changes).map_err(|e| e.to_string())?,
+            run.id
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Agent id that quick task runs and logs are filed under. No agent row has it.
+pub const QUICK_TASK_AGENT: &str = "quick-task";
+
+/// A one-off instruction run without an agent, stored with its run.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct QuickTask {
+    instruction: String,
+    allowed_tools: Vec<String>,
+    /// Attached images the model sees with the instruction.
+    #[serde(default)]
+    images: Vec<String>,
+    /// The stored plan is all there is to run; the model adds no steps of its own.
+    #[serde(default)]
+    pipeline: bool,
+}
+
+struct AgentInfo {
+    name: String,
+    goal: String,
+    sandbox: bool,
+    /// The model picks the steps itself from the agent's tools.
+    tool_calling: bool,
+    enabled: bool,
+    /// Tools the model may pick from, instead of the ones listed on the agent.
+    tools: Option<Vec<String>>,
+    /// Attached images shown to the model with the goal.
+    images: Vec<String>,
+}
+
+impl AgentInfo {
+    fn quick_task(task: QuickTask) -> AgentInfo {
+        AgentInfo {
+            name: if task.pipeline { task.instruction.clone() } else { "Quick task".into() },
+            goal: task.instruction,
+            sandbox: false,
+            tool_calling: !task.pipeline,
+            enabled: true,
+            tools: Some(task.allowed_tools),
+            images: task.images,
+        }
+    }
+}
+
+fn load_agent_info(conn: &Connection, agent_id: &str) -> Result<AgentInfo, String> {
+    conn.query_row(
+        "SELECT name, goal, sandbox, config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| Ok(AgentInfo {
+            name: row.get(0)?,
+            goal: row.get(1)?,
+            sandbox: row.get::<_, i32>(2)? != 0,
+            tool_calling: tool_calling::enabled(&row.get::<_, String>(3)?),
+            enabled: bulk::enabled(&row.get::<_, String>(3)?),
+            tools: None,
+            images: Vec::new(),
+        }),
+    ).optional().map_err(|e| e.to_string())?.ok_or_else(|| "Agent not found".to_string())
+}
+
+/// The steps a run will execute: the agent's expanded workflow, or for agents that were
+/// created without one, a single model step working on the goal. Tool-calling agents start
+/// with an empty plan that the model fills in as the run goes.
+fn build_plan(conn: &Connection, agent_id: &str, agent: &AgentInfo) -> Result<Vec<WorkflowStep>, String> {
+    let steps = workflows::expand_workflow(conn, &workflows::agent_workflow(conn, agent_id)?)?;
+    if !steps.is_empty() || agent.tool_calling {
+        return Ok(steps);
+    }
+    Ok(vec![WorkflowStep::Tool {
+        tool: "llm_prompt".into(),
+        params: json!({ "prompt": agent.goal }),
+        label: "Work on the goal".into(),
+    }])
+}
+
+/// The plan a run of the agent would start with, and whether the model adds steps of its own.
+pub(crate) fn preview_plan(conn: &Connection, agent_id: &str) -> Result<(Vec<WorkflowStep>, bool), String> {
+    let agent = load_agent_info(conn, agent_id)?;
+    Ok((build_plan(conn, agent_id, &agent)?, agent.tool_calling))
+}
+
+/// Placeholders available to step params: `{{goal}}`, `{{agent_name}}`,
+/// `{{previous_output}}` and `{{step_N}}` (1-based output of an earlier step). Steps with
+/// structured output also provide `{{step_N.field}}` for each top-level field. Live values
+/// such as `{{today}}` or `{{clipboard}}` come from `variables::provide`.
+/// For model steps, output read from files, the web or email is marked as untrusted.
+fn step_args(agent: &AgentInfo, done: &[StepResult], for_model: bool) -> Map<String, Value> {
+    let output = |step: &StepResult| match for_model && injection::is_untrusted(&step.tool) {
+        true => injection::wrap(&step.tool, &step.output, step.injection.is_some()),
+        false => step.output.clone(),
+    };
+    let mut args = Map::new();
+    args.insert("goal".into(), Value::String(agent.goal.clone()));
+    args.insert("agent_name".into(), Value::String(agent.name.clone()));
+    args.insert(
+        "previous_output".into(),
+        Value::String(done.last().map(output).unwrap_or_default()),
+    );
+    for step in done {
+        args.insert(format!("step_{}", step.index + 1), Value::String(output(step)));
+        if let Some(Value::Object(fields)) = &step.data {
+            for (field, value) in fields {
+                args.insert(format!("step_{}.{field}", step.index + 1), value.clone());
+            }
+        }
+    }
+    args
+}
+
+/// Asks the model for the step after `index - 1` and appends it to the run's plan, which is
+/// saved so a run paused for approval resumes with the same step. Returns `false` once the
+/// model has answered instead, after recording the answer as the final step.
+fn extend_plan(
+    db: &DbState,
+    run: &mut Run,
+    plan: &mut Vec<WorkflowStep>,
+    agent: &AgentInfo,
+    index: usize,
+) -> Result<bool, String> {
+    if index >= tool_calling::MAX_STEPS {
+        run.error = format!("Stopped after {} steps without finishing the goal", tool_calling::MAX_STEPS);
+        run.status = "error".into();
+        return Ok(false);
+    }
+    let mut result = StepResult {
+        index,
+        tool: "answer".into(),
+        label: "Final answer".into(),
+        params: json!({}),
+        status: "ok".into(),
+        output: String::new(),
+        error: String::new(),
+        duration_ms: 0,
+        data: None,
+        injection: None,
+        served_by: Vec::new(),
+    };
+    let mark = llm::usage_mark(db)?;
+    let started = Instant::now();
+    let next = tool_calling::next(db, &run.agent_id, &run.id, &agent.goal, &agent.images, agent.tools.as_deref(), &run.steps);
+    result.duration_ms = started.elapsed().as_millis() as i64;
+    result.served_by = llm::served_since(db, &run.id, mark)?;
+    match next {
+        Ok(Next::Call { tool, params }) => {
+            plan.push(WorkflowStep::Tool { tool, params, label: tool_calling::CHOSEN_LABEL.into() });
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.execute(
+                "UPDATE runs SET plan_json = ?1 WHERE id = ?2",
+                params![serde_json::to_string(plan).map_err(|e| e.to_string())?, run.id],
+            ).map_err(|e| e.to_string())?;
+            return Ok(true);
+        }
+        Ok(Next::Answer(text)) => result.output = text,
+        Err(e) => {
+            result.status = "error".into();
+            result.error = e.clone();
+            run.error = format!("Choosing step {} failed: {e}", index + 1);
+            run.status = "error".into();
+        }
+    }
+    run.steps.push(result);
+    Ok(false)
+}
+
+fn approval_decision(conn: &Connection, run_id: &str, index: usize) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT status FROM approval_queue WHERE run_id = ?1 AND step_index = ?2 ORDER BY created_at DESC LIMIT 1",
+        params![run_id, index as i64],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())
+}
+
+/// Steps that only talk to the configured model (`llm_prompt`, `critique`).
+fn uses_model(tool: &str) -> bool {
+    tools::find(tool).is_some_and(|t| t.permission == "llm")
+}
+
+/// Applies local-only mode and the agent's sensitivity label to a network step. Only a model
+/// served from this machine passes local-only mode; confidential agents may also reach other
+/// services on this machine.
+fn check_step_egress(conn: &Connection, run: &Run, tool: &str, params: &Value) -> Result<(), String> {
+    let url = match tool {
+        "transcribe_audio" => Some(transcribe::config(conn, &run.agent_id)?.base_url),
+        _ if uses_model(tool) => Some(llm::agent_provider_config(conn, &run.agent_id)?.base_url),
+        _ => None,
+    };
+    net::check_egress(conn, tool, url.as_deref(), &run.agent_id, &run.id)?;
+    let target = match tool {
+        "search_knowledge" => Some(llm::embedding_config(conn)?.base_url),
+        _ => url.or_else(|| params.get("url").and_then(Value::as_str).map(String::from)),
+    };
+    sensitivity::check_egress(conn, tool, target.as_deref(), &run.agent_id, &run.id)
+}
+
+/// Scans what a network step is about to send for personal data and applies the agent's
+/// policy. Returns the params to use and, when the policy blocks a live step, the reason it
+/// needs approval. Simulated runs can't pause, so there `block` masks instead.
+fn apply_pii_policy(conn: &Connection, run: &Run, tool: &str, resolved: Value) -> Result<(Value, Option<String>), String> {
+    let local_model = uses_model(tool) && llm::provider_config(conn)?.provider == "local";
+    let Some(policy) = pii::policy_for(conn, &run.agent_id)?.filter(|_| !local_model) else {
+        return Ok((resolved, None));
+    };
+    let detections = pii::scan_value(&resolved);
+    if detections.is_empty() {
+        return Ok((resolved, None));
+    }
+    let summary = format!("{tool} would send {}", pii::describe(&detections));
+    audit::record(conn, "pii_detected", &run.agent_id, &run.id, &summary, &pii::audit_detail(tool, policy, &detections))?;
+    Ok(match (policy, run.mode) {
+        (PiiPolicy::Allow, _) => (resolved, None),
+        (PiiPolicy::Block, RunMode::Live) => (resolved, Some(summary)),
+        (PiiPolicy::Mask | PiiPolicy::Block, _) => (pii::mask_value(&resolved), None),
+    })
+}
+
+/// Executes `plan` from step `start` onwards, persisting progress after every step.
+/// Stops early on an error or when a live irreversible step needs the user's approval.
+/// With a fixture, simulated file changes are applied to its scratch folder instead of mocked.
+/// For tool-calling agents the model is asked for another step whenever the plan runs out.
+fn continue_run(
+    app: &AppHandle,
+    run: &mut Run,
+    plan: &mut Vec<WorkflowStep>,
+    agent: &AgentInfo,
+    start: usize,
+    fixture: Option<&ActiveFixture>,
+) -> Result<(), String> {
+    let db = app.state::<DbState>();
+    let mut index = start;
+    loop {
+        if index == plan.len() && !(agent.tool_calling && extend_plan(&db, run, plan, agent, index)?) {
+            break;
+        }
+        let WorkflowStep::Tool { tool, params, label } = &plan[index] else {
+            return Err("Workflow was not expanded before running".into());
+        };
+        let mut args = step_args(agent, &run.steps, uses_model(tool));
+        let provided = variables::provide(&db, &run.agent_id, &run.id, params, &mut args);
+        let resolved = workflows::bind_args(params, &args);
+        let mut result = StepResult {
+            index,
+            tool: tool.clone(),
+            label: label.clone(),
+            params: tools::loggable_params(tool, &resolved),
+            status: "ok".into(),
+            output: String::new(),
+            error: String::new(),
+            duration_ms: 0,
+            data: None,
+            injection: None,
+            served_by: Vec::new(),
+        };
+        // Stops between steps when the app is closing. Live runs keep the step to resume from;
+        // simulated ones are simply cancelled.
+        if shutdown::stopping(app) {
+            if run.mode == RunMode::Live {
+                result.status = "interrupted".into();
+                run.status = "interrupted".into();
+                run.steps.push(result);
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                save_run(&conn, run)?;
+                return Ok(());
+            }
+            run.error = "Stopped when OpenClaw closed".into();
+            run.status = "cancelled".into();
+            break;
+        }
+        if let Err(e) = provided {
+            result.status = "error".into();
+            result.error = e.clone();
+            run.error = format!("Step {} ({tool}) failed: {e}", index + 1);
+            run.status = "error".into();
+            run.steps.push(result);
+            break;
+        }
+        let Some(spec) = tools::find(tool) else {
+            result.status = "error".into();
+            result.error = format!("Unknown tool: {tool}");
+            run.error = result.error.clone();
+            run.status = "error".into();
+            run.steps.push(result);
+            break;
+        };
+
+        let supervised = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            policy::check_tool(&conn, &run.agent_id, &run.id, tool)
+                .and_then(|_| supervision::check_step(&conn, &run.agent_id, &run.id, tool, &resolved))
+                .err()
+        };
+        if let Some(e) = supervised {
+            result.status = "error".into();
+            result.error = e.clone();
+            run.error = format!("Step {} ({tool}) blocked: {e}", index + 1);
+            run.status = "error".into();
+            run.steps.push(result);
+            break;
+        }
+
+        // Simulated side effects and fixture inboxes never leave the machine.
+        let reaches_network = spec.network
+            && !(run.mode == RunMode::Simulate && spec.side_effect)
+            && !(tool == "read_inbox" && fixture.is_some());
+        if reaches_network {
+            let blocked = {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                check_step_egress(&conn, run, tool, &resolved).err()
+            };
+            if let Some(e) = blocked {
+                result.status = "error".into();
+                result.error = e.clone();
+                run.error = format!("Step {} ({tool}) failed: {e}", index + 1);
+                run.status = "error".into();
+                run.steps.push(result);
+                break;
+            }
+        }
+
+        let (resolved, pii_hold) = if spec.network {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            apply_pii_policy(&conn, run, tool, resolved)?
+        } else {
+            (resolved, None)
+        };
+        result.params = tools::loggable_params(tool, &resolved);
+
+        // A step the model chose after reading suspicious content may have been asked for by
+        // that content, so anything with side effects waits for the user.
+        let injection_hold = (label == tool_calling::CHOSEN_LABEL && spec.side_effect)
+            .then(|| run.steps.iter().rev().find_map(|s| s.injection.clone()))
+            .flatten();
+
+        let ctx = ToolContext { app, agent_id: &run.agent_id, run_id: &run.id, step_index: index, fixture };
+        if run.mode == RunMode::Simulate && spec.side_effect {
+            result.status = "simulated".into();
+            result.output = {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                tools::describe_effect(&ctx, &conn, tool, &resolved)
+            };
+            if fixture.is_some() && spec.permission.starts_with("files.") {
+                if let Err(e) = tools::execute(&ctx, tool, &resolved) {
+                    result.status = "error".into();
+                    result.error = e.clone();
+                    run.error = format!("Step {} ({tool}) failed: {e}", index + 1);
+                    run.status = "error".into();
+                    run.steps.push(result);
+                    break;
+                }
+                result.output.push_str(" (applied to the fixture folder)");
+            }
+            run.side_effects.push(result.output.clone());
+        } else {
+            if run.mode == RunMode::Live && (spec.irreversible || pii_hold.is_some() || injection_hold.is_some() || tools::wants_review(tool, &resolved)) {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                match approval_decision(&conn, &run.id, index)?.as_deref() {
+                    Some("approved") => {}
+                    Some("rejected") => {
+                        result.status = "rejected".into();
+                        result.error = "Rejected by user".into();
+                        run.status = "cancelled".into();
+                        run.steps.push(result);
+                        break;
+                    }
+                    Some(_) => {
+                        result.status = "awaiting_approval".into();
+                        run.status = "awaiting_approval".into();
+                        run.steps.push(result);
+                        save_run(&conn, run)?;
+                        return Ok(());
+                    }
+                    None => {
+                        let preview = match (&pii_hold, &injection_hold) {
+                            (Some(reason), _) => format!("{}: Personal data found. {reason}", agent.name),
+                            (None, Some(reason)) => format!(
+                                "{}: Wants to {tool} after reading suspicious content. {reason}",
+                                agent.name,
+                            ),
+                            (None, None) => format!("{}: {}", agent.name, tools::describe_effect(&ctx, &conn, tool, &resolved).replacen("Would ", "Wants to ", 1)),
+                        };
+                        let item = insert_approval(
+                            &conn,
+                            run.agent_id.clone(),
+                            tool.clone(),
+                            preview,
+                            run.id.clone(),
+                            index as i64,
+                            priority::classify(&conn, &run.agent_id, &spec, pii_hold.is_some() || injection_hold.is_some()),
+                        )?;
+                        routing::dispatch(app, &conn, &item);
+                        result.status = "awaiting_approval".into();
+                        run.status = "awaiting_approval".into();
+                        run.steps.push(result);
+                        save_run(&conn, run)?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            // Approved irreversible steps count down first, so the user can still stop them, and
+            // wait for their `send_at` time when they have one.
+            if run.mode == RunMode::Live && spec.irreversible {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                let preview = format!("{}: {}", agent.name, tools::describe_effect(&ctx, &conn, tool, &resolved).replacen("Would ", "About to ", 1));
+                let send_at = match grace::send_at(&resolved) {
+                    Ok(send_at) => send_at,
+                    Err(e) => {
+                        result.status = "error".into();
+                        result.error = e.clone();
+                        run.error = format!("Step {} ({tool}) failed: {e}", index + 1);
+                        run.status = "error".into();
+                        run.steps.push(result);
+                        break;
+                    }
+                };
+                match grace::hold(&conn, run, index, tool, &preview, send_at)? {
+                    Grace::Run => {}
+                    Grace::Cancelled => {
+                        result.status = "cancelled".into();
+                        result.error = "Cancelled before it ran".into();
+                        run.status = "cancelled".into();
+                        run.steps.push(result);
+                        break;
+                    }
+                    Grace::Wait => {
+                        result.status = "pending_action".into();
+                        run.status = "pending_action".into();
+                        run.steps.push(result);
+                        save_run(&conn, run)?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            // Folders a file step writes in are snapshotted first, for the run's change report.
+            if run.mode == RunMode::Live && spec.permission.starts_with("files.") && spec.permission != "files.read" {
+                let dirs = tools::touched_dirs(&ctx, tool, &resolved);
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                changes::before(&conn, &run.id, &dirs)?;
+            }
+
+            let mark = llm::usage_mark(&db)?;
+            let started = Instant::now();
+            let outcome = tools::execute(&ctx, tool, &resolved);
+            result.duration_ms = started.elapsed().as_millis() as i64;
+            result.served_by = llm::served_since(&db, &run.id, mark)?;
+            match outcome {
+                Ok(output) => {
+                    if spec.side_effect {
+                        run.side_effects.push(output.clone());
+                    }
+                    if resolved.get("schema").is_some_and(Value::is_object) {
+                        result.data = serde_json::from_str(&output).ok();
+                    }
+                    if injection::is_untrusted(tool) {
+                        result.injection = injection::screen(&db, &run.agent_id, &run.id, tool, &output)?;
+                    }
+                    result.output = output;
+                }
+                Err(e) => {
+                    result.status = "error".into();
+                    result.error = e.clone();
+                    run.error = format!("Step {} ({tool}) failed: {e}", index + 1);
+                    run.status = "error".into();
+                    run.steps.push(result);
+                    break;
+                }
+            }
+        }
+
+        run.steps.push(result);
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        save_run(&conn, run)?;
+        index += 1;
+    }
+
+    if run.status == "running" {
+        run.status = "success".into();
+    }
+    run.finished_at = Utc::now().to_rfc3339();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    run.changes = changes::after(&conn, &run.id)?;
+    save_run(&conn, run)?;
+    stats::record_run(&conn, run)?;
+    let summary = run.steps.iter()
+        .map(|s| format!("{}. {} [{}] {}", s.index + 1, s.tool, s.status, if s.error.is_empty() { &s.output } else { &s.error }))
+        .chain(changes::summary(&run.changes))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prefix = if run.mode == RunMode::Simulate { "[SIMULATED] " } else { "" };
+    insert_log(&conn, &run.agent_id, &format!("{prefix}Run: {}", agent.name), &run.status, &summary, &run.error)?;
+    Ok(())
+}
+
+/// Tells open windows that a run stopped, finished, paused for approval or started counting
+/// down before an irreversible step, and notifies the user as the agent's settings say.
+fn announce(app: &AppHandle, run: &Run) {
+    let payload = json!({ "run_id": run.id, "agent_id": run.agent_id, "status": run.status });
+    windows::broadcast(app, "run-updated", &payload);
+    windows::broadcast(app, "logs-changed", &run.agent_id);
+    if run.status == "pending_action" {
+        windows::broadcast(app, "pending-actions-changed", &run.id);
+    }
+    if run.status == "awaiting_approval" {
+        windows::broadcast(app, "approvals-changed", &run.id);
+    }
+    let event = match run.status.as_str() {
+        "success" => Event::Success,
+        "error" => Event::Failure,
+        "awaiting_approval" => Event::Approval,
+        _ => return,
+    };
+    if run.mode != RunMode::Live {
+        return;
+    }
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+    let name: String = conn.query_row("SELECT name FROM agents WHERE id = ?1", params![run.agent_id], |row| row.get(0))
+        .unwrap_or_else(|_| "Quick task".into());
+    let (title, message) = match event {
+        Event::Success => (format!("{name} finished"), String::new()),
+        Event::Failure => (format!("{name} failed"), run.error.clone()),
+        _ => (format!("{name} needs your approval"), String::new()),
+    };
+    notifications::notify(app, &conn, &run.agent_id, event, &title, &message);
+}
+
+/// Records a new run and executes `plan`. Sandbox agents always run simulated.
+fn start_run(
+    app: &AppHandle,
+    agent_id: &str,
+    agent: &AgentInfo,
+    plan: Vec<WorkflowStep>,
+    mode: RunMode,
+    trigger: &str,
+    task: Option<&QuickTask>,
+) -> Result<Run, String> {
+    let _guard = shutdown::begin_run(app)?;
+    let db = app.state::<DbState>();
+    let (run, fixture) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mode = if agent.sandbox { RunMode::Simulate } else { mode };
+        // Reseeded on every simulated run so each one starts from the same files and inbox.
+        let fixture = match mode {
+            RunMode::Simulate => fixtures::prepare_active(&conn)?,
+            RunMode::Live => None,
+        };
+        let run = Run {
+            id: Uuid::new_v4().to_string(),
+            agent_id: agent_id.to_string(),
+            mode,
+            status: "running".into(),
+            trigger: trigger.to_string(),
+            steps: Vec::new(),
+            side_effects: Vec::new(),
+            error: String::new(),
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: String::new(),
+            golden: false,
+            note: String::new(),
+            changes: Vec::new(),
+        };
+        let task = match task {
+            Some(task) => serde_json::to_string(task).map_err(|e| e.to_string())?,
+            None => String::new(),
+        };
+        conn.execute(
+            "INSERT INTO runs (id, agent_id, mode, status, trigger_kind, plan_json, task_json, started_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                run.id, run.agent_id, run.mode.as_str(), run.status, run.trigger,
+                serde_json::to_string(&plan).map_err(|e| e.to_string())?,
+                task, run.started_at
+            ],
+        ).map_err(|e| e.to_string())?;
+        (run, fixture)
+    };
+    let (mut run, mut plan) = (run, plan);
+    continue_run(app, &mut run, &mut plan, agent, 0, fixture.as_ref())?;
+    announce(app, &run);
+    Ok(run)
+}
+
+/// Starts a run of `agent_id`. Sandbox agents always run simulated; disabled agents only
+/// run when started by hand or from a test, and automatic runs wait out blackout windows
+/// and don't start in network profiles the agent is off in.
+pub fn execute_agent(app: &AppHandle, agent_id: &str, mode: RunMode, trigger: &str) -> Result<Run, String> {
+    let db = app.state::<DbState>();
+    let (agent, plan) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let agent = load_agent_info(&conn, agent_id)?;
+        if !agent.enabled && !matches!(trigger, "manual" | "test") {
+            return Err(format!("{} is disabled", agent.name));
+        }
+        if !matches!(trigger, "manual" | "test") {
+            if let Some(off) = profiles::hold(app, &conn, agent_id) {
+                return Err(off);
+            }
+            if let Some(held) = blackout::hold(&conn, agent_id, trigger, mode)? {
+                return Err(held);
+            }
+        }
+        let plan = build_plan(&conn, agent_id, &agent)?;
+        (agent, plan)
+    };
+    start_run(app, agent_id, &agent, plan, mode, trigger, None)
+}
+
+/// Runs a one-off instruction without creating an agent: the model picks steps from
+/// `allowed_tools` (none means it can only answer), with the same approvals, egress and
+/// personal-data checks as agent runs. Runs and logs are filed under `QUICK_TASK_AGENT`.
+/// `images` are attachment ids the model sees with the instruction.
+pub fn execute_quick_task(app: &AppHandle, instruction: &str, allowed_tools: Vec<String>, images: Vec<String>, mode: RunMode) -> Result<Run, String> {
+    if instruction.trim().is_empty() {
+        return Err("Say what the task should do".into());
+    }
+    if let Some(unknown) = allowed_tools.iter().find(|t| tools::find(t).is_none()) {
+        return Err(format!("Unknown tool: {unknown}"));
+    }
+    let task = QuickTask { instruction: instruction.trim().to_string(), allowed_tools, images, pipeline: false };
+    let agent = AgentInfo::quick_task(task.clone());
+    start_run(app, QUICK_TASK_AGENT, &agent, Vec::new(), mode, "quick_task", Some(&task))
+}
+
+/// Runs a fixed `plan` live without creating an agent, like a built-in pipeline started on a
+/// dropped file, with the same approvals and checks as agent runs. `name` is what
+/// notifications call it. Runs and logs are filed under `QUICK_TASK_AGENT`.
+pub fn execute_pipeline(app: &AppHandle, name: &str, plan: Vec<WorkflowStep>, trigger: &str) -> Result<Run, String> {
+    let allowed_tools = plan.iter()
+        .filter_map(|step| match step {
+            WorkflowStep::Tool { tool, .. } => Some(tool.clone()),
+            _ => None,
+        })
+        .collect();
+    let task = QuickTask { instruction: name.to_string(), allowed_tools, images: Vec::new(), pipeline: true };
+    let agent = AgentInfo::quick_task(task.clone());
+    start_run(app, QUICK_TASK_AGENT, &agent, plan, RunMode::Live, trigger, Some(&task))
+}
+
+/// Takes a paused run for one caller to continue. The check and the change are one
+/// statement, so when approval, the grace watcher and the user all try at once exactly one
+/// of them gets it and the paused step runs once.
+fn claim_paused(conn: &Connection, run_id: &str) -> Result<(), String> {
+    let claimed = conn.execute(
+        "UPDATE runs SET status = 'running' WHERE id = ?1 AND status IN ('awaiting_approval', 'pending_action', 'interrupted')",
+        params![run_id],
+    ).map_err(|e| e.to_string())?;
+    match claimed {
+        0 => Err("The run has already been continued".into()),
+        _ => Ok(()),
+    }
+}
+
+/// Continues a run that paused for approval, once the user has decided, that held a step
+/// for its grace period, once that is over or cancelled, or that was interrupted by the app
+/// closing.
+pub fn resume(app: &AppHandle, run_id: &str) -> Result<Run, String> {
+    let _guard = shutdown::begin_run(app)?;
+    let db = app.state::<DbState>();
+    let (mut run, mut plan, agent, paused) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut run = load_run(&conn, run_id)?;
+        if !matches!(run.status.as_str(), "awaiting_approval" | "pending_action" | "interrupted") {
+            return Err(format!("Run is {}, not waiting for approval", run.status));
+        }
+        let plan: String = conn.query_row("SELECT plan_json FROM runs WHERE id = ?1", params![run_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        let plan: Vec<WorkflowStep> = serde_json::from_str(&plan).map_err(|e| e.to_string())?;
+        let task: String = conn.query_row("SELECT task_json FROM runs WHERE id = ?1", params![run_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        let agent = match serde_json::from_str::<QuickTask>(&task) {
+            Ok(task) => AgentInfo::quick_task(task),
+            Err(_) => load_agent_info(&conn, &run.agent_id)?,
+        };
+        let paused = run.steps.pop().ok_or("Run has no paused step")?;
+        if approval_decision(&conn, run_id, paused.index)?.as_deref() == Some("pending") {
+            return Err("The step is still waiting for approval".into());
+        }
+        claim_paused(&conn, run_id)?;
+        (run, plan, agent, paused)
+    };
+    run.status = "running".into();
+    // Only live runs pause for approval, and live runs never use fixtures.
+    continue_run(app, &mut run, &mut plan, &agent, paused.index, None)?;
+    announce(app, &run);
+    Ok(run)
+}
+
+// ─── Run Commands ───
+
+/// Cleans a run's tool and model output before it is handed to the webview.
+pub(crate) fn outbound(app: &AppHandle, mut run: Run) -> Result<Run, String> {
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    sanitize::run(&mut run, sanitize::mode(&conn));
+    Ok(run)
+}
+
+#[tauri::command]
+pub async fn run_agent(app: AppHandle, agent_id: String, mode: Option<RunMode>) -> Result<Run, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let run = execute_agent(&app, &agent_id, mode.unwrap_or(RunMode::Live), "manual")?;
+        outbound(&app, run)
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// Runs a single instruction once, without creating an agent. `allowed_tools` lists the
+/// tools it may use; steps that change things still wait for approval. `images` are ids from
+/// `attach_image`, e.g. a screenshot of an error to explain.
+#[tauri::command]
+pub async fn run_quick_task(
+    app: AppHandle,
+    instruction: String,
+    allowed_tools: Vec<String>,
+    images: Option<Vec<String>>,
+    mode: Option<RunMode>,
+) -> Result<Run, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let run = execute_quick_task(&app, &instruction, allowed_tools, images.unwrap_or_default(), mode.unwrap_or(RunMode::Live))?;
+        outbound(&app, run)
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn resume_run(app: AppHandle, run_id: String) -> Result<Run, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let run = resume(&app, &run_id)?;
+        outbound(&app, run)
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_run(db: State<DbState>, id: String) -> Result<Run, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut run = load_run(&conn, &id)?;
+    sanitize::run(&mut run, sanitize::mode(&conn));
+    Ok(run)
+}
+
+#[tauri::command]
+pub fn list_runs(db: State<DbState>, agent_id: Option<String>, limit: Option<i64>) -> Result<Vec<Run>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let lim = limit.unwrap_or(50);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {RUN_COLUMNS} FROM runs WHERE (?1 IS NULL OR agent_id = ?1) ORDER BY started_at DESC LIMIT ?2"
+    )).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id, lim], row_to_run).map_err(|e| e.to_string())?;
+
+    let mode = sanitize::mode(&conn);
+    let mut runs = Vec::new();
+    for row in rows {
+        let mut run = row.map_err(|e| e.to_string())?;
+        sanitize::run(&mut run, mode);
+        runs.push(run);
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier, Mutex};
+
+    fn paused_run(status: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE runs (id TEXT PRIMARY KEY, status TEXT NOT NULL)").unwrap();
+        conn.execute("INSERT INTO runs (id, status) VALUES ('run', ?1)", params![status]).unwrap();
+        conn
+    }
+
+    #[test]
+    fn claims_each_paused_state_once() {
+        for status in ["awaiting_approval", "pending_action", "interrupted"] {
+            let conn = paused_run(status);
+            assert_eq!(claim_paused(&conn, "run"), Ok(()));
+            assert_eq!(claim_paused(&conn, "run"), Err("The run has already been continued".into()));
+            let now: String = conn.query_row("SELECT status FROM runs", [], |row| row.get(0)).unwrap();
+            assert_eq!(now, "running");
+        }
+        for status in ["running", "completed", "error", "cancelled"] {
+            assert!(claim_paused(&paused_run(status), "run").is_err(), "{status}");
+        }
+        assert!(claim_paused(&paused_run("pending_action"), "other").is_err());
+    }
+
+    #[test]
+    fn racing_resumes_continue_the_step_once() {
+        // The user releasing a held step while the grace watcher fires, and approval arriving
+        // from a phone at the same moment.
+        let conn = Arc::new(Mutex::new(paused_run("pending_action")));
+        let start = Arc::new(Barrier::new(4));
+        let runs: Vec<_> = (0..4).map(|_| {
+            let (conn, start) = (conn.clone(), start.clone());
+            std::thread::spawn(move || {
+                start.wait();
+                claim_paused(&conn.lock().unwrap(), "run").is_ok()
+            })
+        }).collect();
+        let continued = runs.into_iter().map(|run| run.join().unwrap()).filter(|claimed| *claimed).count();
+        assert_eq!(continued, 1);
+    }
+}
@@ -0,0 +1,168 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use tauri::State;
+use chrono::Utc;
+
+use crate::DbState;
+
+/// What feedback can be about: a whole run, an artifact a run produced (such as a drafted
+/// reply), or an agent draft from the builder.
+const KINDS: &[&str] = &["run", "artifact", "draft"];
+/// Comments shown with each agent's summary.
+const RECENT_COMMENTS: usize = 5;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Feedback {
+    pub kind: String,
+    pub target_id: String,
+    /// Empty for builder drafts that weren't saved as an agent.
+    pub agent_id: String,
+    /// 1 for thumbs up, -1 for thumbs down.
+    pub rating: i64,
+    pub comment: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AgentFeedback {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub up: i64,
+    pub down: i64,
+    /// Share of thumbs up, 0 to 1; `None` without any feedback yet.
+    pub satisfaction: Option<f64>,
+    /// Newest first, up to five.
+    pub recent_comments: Vec<String>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS feedback (
+            kind TEXT NOT NULL,
+            target_id TEXT NOT NULL,
+            agent_id TEXT DEFAULT '',
+            rating INTEGER NOT NULL,
+            comment TEXT DEFAULT '',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (kind, target_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_feedback_agent ON feedback(agent_id);
+    ").expect("Failed to initialize feedback tables");
+}
+
+/// The agent a run or artifact belongs to; builder drafts have none.
+fn target_agent(conn: &Connection, kind: &str, target_id: &str) -> Result<String, String> {
+    let query = match kind {
+        "run" => "SELECT agent_id FROM runs WHERE id = ?1",
+        "artifact" => "SELECT agent_id FROM run_artifacts WHERE id = ?1",
+        "draft" => "SELECT '' FROM agent_draft_sessions WHERE id = ?1",
+        other => return Err(format!("Feedback can't be given on {other}; use one of {}", KINDS.join(", "))),
+    };
+    conn.query_row(query, params![target_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("That {kind} no longer exists"))
+}
+
+fn row_to_feedback(row: &rusqlite::Row) -> rusqlite::Result<Feedback> {
+    Ok(Feedback {
+        kind: row.get(0)?,
+        target_id: row.get(1)?,
+        agent_id: row.get(2)?,
+        rating: row.get(3)?,
+        comment: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn summary(conn: &Connection, agent_id: &str, agent_name: String) -> Result<AgentFeedback, String> {
+    let (up, down): (i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(rating > 0), 0), COALESCE(SUM(rating < 0), 0) FROM feedback WHERE agent_id = ?1",
+        params![agent_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT comment FROM feedback WHERE agent_id = ?1 AND comment != '' ORDER BY updated_at DESC LIMIT ?2"
+    ).map_err(|e| e.to_string())?;
+    let comments = stmt.query_map(params![agent_id, RECENT_COMMENTS as i64], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    Ok(AgentFeedback {
+        agent_id: agent_id.to_string(),
+        agent_name,
+        up,
+        down,
+        satisfaction: (up + down > 0).then(|| up as f64 / (up + down) as f64),
+        recent_comments: comments.collect::<Result<_, _>>().map_err(|e| e.to_string())?,
+    })
+}
+
+// ─── Feedback Commands ───
+
+/// Records a thumbs up (`positive`) or down on a run, artifact or builder draft, replacing any
+/// earlier rating of the same thing.
+#[tauri::command]
+pub fn submit_feedback(db: State<DbState>, kind: String, target_id: String, positive: bool, comment: Option<String>) -> Result<Feedback, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let agent_id = target_agent(&conn, &kind, &target_id)?;
+    let now = Utc::now().to_rfc3339();
+    let feedback = Feedback {
+        kind,
+        target_id,
+        agent_id,
+        rating: if positive { 1 } else { -1 },
+        comment: comment.unwrap_or_default().trim().to_string(),
+        updated_at: now.clone(),
+    };
+    conn.execute(
+        "INSERT INTO feedback (kind, target_id, agent_id, rating, comment, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+         ON CONFLICT(kind, target_id) DO UPDATE SET rating = excluded.rating, comment = excluded.comment, updated_at = excluded.updated_at",
+        params![feedback.kind, feedback.target_id, feedback.agent_id, feedback.rating, feedback.comment, now],
+    ).map_err(|e| e.to_string())?;
+    Ok(feedback)
+}
+
+#[tauri::command]
+pub fn clear_feedback(db: State<DbState>, kind: String, target_id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM feedback WHERE kind = ?1 AND target_id = ?2", params![kind, target_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_feedback(db: State<DbState>, kind: String, target_id: String) -> Result<Option<Feedback>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.query_row(
+        "SELECT kind, target_id, agent_id, rating, comment, updated_at FROM feedback WHERE kind = ?1 AND target_id = ?2",
+        params![kind, target_id],
+        row_to_feedback,
+    ).optional().map_err(|e| e.to_string())
+}
+
+/// How satisfied the user is with each agent that has feedback, least satisfied first.
+#[tauri::command]
+pub fn list_agent_feedback(db: State<DbState>) -> Result<Vec<AgentFeedback>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let agents: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT f.agent_id, a.name FROM feedback f JOIN agents a ON a.id = f.agent_id"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let mut summaries = agents.into_iter()
+        .map(|(id, name)| summary(&conn, &id, name))
+        .collect::<Result<Vec<_>, _>>()?;
+    summaries.sort_by(|a, b| a.satisfaction.unwrap_or(1.0).total_cmp(&b.satisfaction.unwrap_or(1.0)));
+    Ok(summaries)
+}
+
+/// One agent's feedback summary.
+#[tauri::command]
+pub fn get_agent_feedback(db: State<DbState>, agent_id: String) -> Result<AgentFeedback, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let name: String = conn.query_row("SELECT name FROM agents WHERE id = ?1", params![agent_id], |row| row.get(0))
+        .map_err(|_| "Agent not found".to_string())?;
+    summary(&conn, &agent_id, name)
+}
@@ -0,0 +1,226 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::{DbState, app_data_dir, read_setting, write_setting};
+
+/// Setting holding the id of the fixture set simulated runs operate on.
+const ACTIVE_FIXTURE_KEY: &str = "active_fixture_set";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FixtureFile {
+    /// Path as an agent would refer to it, e.g. `~/Downloads/invoice.txt`.
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FixtureEmail {
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    #[serde(default)]
+    pub date: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FixtureSet {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub files: Vec<FixtureFile>,
+    pub emails: Vec<FixtureEmail>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+/// The fixture a simulated run is using: a freshly seeded scratch folder plus a fake inbox.
+#[derive(Debug, Clone)]
+pub struct ActiveFixture {
+    pub root: PathBuf,
+    pub emails: Vec<FixtureEmail>,
+}
+
+impl ActiveFixture {
+    /// Maps a path an agent uses onto the scratch folder, so `~/Downloads/a.txt` becomes
+    /// `<root>/home/Downloads/a.txt`. `..` components are dropped to keep everything inside.
+    pub fn remap(&self, path: &str) -> PathBuf {
+        let path = path.trim();
+        let (base, rest) = match path.strip_prefix('~') {
+            Some(rest) => (self.root.join("home"), rest),
+            None => (self.root.clone(), path),
+        };
+        let mut out = base;
+        for component in Path::new(rest).components() {
+            match component {
+                Component::Normal(part) => out.push(part),
+                Component::Prefix(prefix) => {
+                    let drive: String = prefix.as_os_str().to_string_lossy().chars().filter(|c| c.is_alphanumeric()).collect();
+                    out.push(drive);
+                }
+                Component::RootDir | Component::CurDir | Component::ParentDir => {}
+            }
+        }
+        out
+    }
+
+    /// The fake inbox rendered the way `read_inbox` reports real mail.
+    pub fn inbox(&self, limit: usize) -> String {
+        self.emails.iter()
+            .take(limit)
+            .map(|m| format!("From: {}\nTo: {}\nDate: {}\nSubject: {}\n\n{}", m.from, m.to, m.date, m.subject, m.body))
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n")
+    }
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS fixture_sets (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT DEFAULT '',
+            files_json TEXT DEFAULT '[]',
+            emails_json TEXT DEFAULT '[]',
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize fixture tables");
+}
+
+fn fixture_root(id: &str) -> PathBuf {
+    app_data_dir().join("fixtures").join(id)
+}
+
+fn load_set(conn: &Connection, id: &str) -> Result<FixtureSet, String> {
+    let active = read_setting(conn, ACTIVE_FIXTURE_KEY)?.unwrap_or_default();
+    conn.query_row(
+        "SELECT id, name, description, files_json, emails_json, created_at FROM fixture_sets WHERE id = ?1",
+        params![id],
+        |row| row_to_set(row, &active),
+    ).optional().map_err(|e| e.to_string())?.ok_or_else(|| "Fixture set not found".to_string())
+}
+
+fn row_to_set(row: &rusqlite::Row, active: &str) -> rusqlite::Result<FixtureSet> {
+    let id: String = row.get(0)?;
+    Ok(FixtureSet {
+        active: id == active,
+        id,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        files: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+        emails: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+        created_at: row.get(5)?,
+    })
+}
+
+/// Wipes the scratch folder and writes the fixture's files back, so every simulated run
+/// starts from the same state.
+pub fn seed(set: &FixtureSet) -> Result<ActiveFixture, String> {
+    let fixture = ActiveFixture { root: fixture_root(&set.id), emails: set.emails.clone() };
+    if fixture.root.exists() {
+        fs::remove_dir_all(&fixture.root).map_err(|e| format!("Can't reset fixture folder: {e}"))?;
+    }
+    fs::create_dir_all(&fixture.root).map_err(|e| format!("Can't create fixture folder: {e}"))?;
+    for file in &set.files {
+        let path = fixture.remap(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, &file.content).map_err(|e| format!("Can't write fixture file {}: {e}", file.path))?;
+    }
+    Ok(fixture)
+}
+
+/// Seeds and returns the active fixture set, if the user has loaded one.
+pub fn prepare_active(conn: &Connection) -> Result<Option<ActiveFixture>, String> {
+    let Some(id) = read_setting(conn, ACTIVE_FIXTURE_KEY)?.filter(|id| !id.is_empty()) else {
+        return Ok(None);
+    };
+    match load_set(conn, &id) {
+        Ok(set) => seed(&set).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+// ─── Fixture Commands ───
+
+#[tauri::command]
+pub fn create_fixture_set(
+    db: State<DbState>,
+    name: String,
+    description: String,
+    files: Vec<FixtureFile>,
+    emails: Vec<FixtureEmail>,
+) -> Result<FixtureSet, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO fixture_sets (id, name, description, files_json, emails_json, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id, name, description,
+            serde_json::to_string(&files).map_err(|e| e.to_string())?,
+            serde_json::to_string(&emails).map_err(|e| e.to_string())?,
+            now
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(FixtureSet { id, name, description, files, emails, active: false, created_at: now })
+}
+
+#[tauri::command]
+pub fn list_fixture_sets(db: State<DbState>) -> Result<Vec<FixtureSet>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let active = read_setting(&conn, ACTIVE_FIXTURE_KEY)?.unwrap_or_default();
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, files_json, emails_json, created_at FROM fixture_sets ORDER BY created_at DESC"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| row_to_set(row, &active)).map_err(|e| e.to_string())?;
+
+    let mut sets = Vec::new();
+    for row in rows {
+        sets.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(sets)
+}
+
+/// Seeds the scratch folder and makes this the fixture set simulated runs operate on.
+#[tauri::command]
+pub fn load_fixture_set(db: State<DbState>, id: String) -> Result<FixtureSet, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut set = load_set(&conn, &id)?;
+    seed(&set)?;
+    write_setting(&conn, ACTIVE_FIXTURE_KEY, &id)?;
+    set.active = true;
+    Ok(set)
+}
+
+/// Stops using fixtures; simulated runs go back to reading the real disk.
+#[tauri::command]
+pub fn unload_fixture_set(db: State<DbState>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM settings WHERE key = ?1", params![ACTIVE_FIXTURE_KEY])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_fixture_set(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM fixture_sets WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM settings WHERE key = ?1 AND value = ?2",
+        params![ACTIVE_FIXTURE_KEY, id],
+    ).map_err(|e| e.to_string())?;
+    let root = fixture_root(&id);
+    if root.exists() {
+        fs::remove_dir_all(root).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,381 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::audit;
+use crate::windows;
+use crate::{DbState, app_data_dir, read_setting, write_setting};
+
+const BLOCKLIST_SETTING: &str = "focus_blocklist";
+/// The session in progress, kept as a setting so a restart still ends it on time.
+const SESSION_SETTING: &str = "focus_session";
+const MAX_MINUTES: i64 = 8 * 60;
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(5);
+const HOSTS_BEGIN: &str = "# BEGIN OpenClaw focus session";
+const HOSTS_END: &str = "# END OpenClaw focus session";
+
+/// Sites and apps a focus session blocks. Sites are blocked in the hosts file, with their
+/// `www.` form; apps matching a name are closed while the session lasts.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Blocklist {
+    #[serde(default)]
+    pub domains: Vec<String>,
+    #[serde(default)]
+    pub apps: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Session {
+    started_at: String,
+    ends_at: String,
+    /// A strict session can only be ended early with the emergency unlock.
+    strict: bool,
+    blocklist: Blocklist,
+    /// Whether the hosts file was changed; it needs administrator rights.
+    hosts_applied: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct FocusStatus {
+    pub active: bool,
+    pub started_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub remaining_seconds: i64,
+    pub strict: bool,
+    pub blocklist: Blocklist,
+    pub hosts_applied: bool,
+}
+
+fn blocklist(conn: &Connection) -> Result<Blocklist, String> {
+    Ok(read_setting(conn, BLOCKLIST_SETTING)?
+        .and_then(|b| serde_json::from_str(&b).ok())
+        .unwrap_or_default())
+}
+
+fn session(conn: &Connection) -> Result<Option<Session>, String> {
+    Ok(read_setting(conn, SESSION_SETTING)?.and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+fn status_of(session: Option<&Session>) -> FocusStatus {
+    let Some(session) = session else { return FocusStatus::default() };
+    let remaining = DateTime::parse_from_rfc3339(&session.ends_at)
+        .map(|end| (end.with_timezone(&Utc) - Utc::now()).num_seconds().max(0))
+        .unwrap_or(0);
+    FocusStatus {
+        active: true,
+        started_at: Some(session.started_at.clone()),
+        ends_at: Some(session.ends_at.clone()),
+        remaining_seconds: remaining,
+        strict: session.strict,
+        blocklist: session.blocklist.clone(),
+        hosts_applied: session.hosts_applied,
+    }
+}
+
+fn hosts_path() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        PathBuf::from(std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".into())).join("System32\\drivers\\etc\\hosts")
+    } else {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+/// A plain host name such as `example.com`: dot-separated labels of letters, digits and
+/// hyphens, with an alphabetic top level so IP addresses don't pass. Anything else could
+/// add mappings of its own to the hosts file. Internationalized names go in `xn--` form.
+fn is_host_name(name: &str) -> bool {
+    let labels: Vec<&str> = name.split('.').collect();
+    name.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+        && labels.last().is_some_and(|tld| tld.bytes().any(|b| b.is_ascii_alphabetic()))
+}
+
+/// `hosts` without the session's block.
+fn strip_block(hosts: &str) -> String {
+    let mut out = String::new();
+    let mut inside = false;
+    for line in hosts.lines() {
+        match line.trim() {
+            HOSTS_BEGIN => inside = true,
+            HOSTS_END => inside = false,
+            _ if !inside => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Copies `contents` over the hosts file, asking for administrator rights when a plain
+/// write isn't allowed. The copy is staged in the app's own data folder under a fresh name
+/// that only the user can read, and its path is never written into script text in Rust.
+fn write_hosts(contents: &str) -> Result<(), String> {
+    let path = hosts_path();
+    let Err(e) = std::fs::write(&path, contents) else { return Ok(()) };
+    if e.kind() != std::io::ErrorKind::PermissionDenied {
+        return Err(format!("Couldn't update {}: {e}", path.display()));
+    }
+    let staged = app_data_dir().join(format!("focus-hosts-{}", Uuid::new_v4()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&staged).and_then(|mut file| file.write_all(contents.as_bytes()));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&staged);
+        return Err(format!("Couldn't prepare the hosts file: {e}"));
+    }
+    let status = if cfg!(target_os = "windows") {
+        // The elevated PowerShell gets both paths as single-quoted literals, quoted here by
+        // PowerShell itself from the environment rather than from text built in Rust.
+        std::process::Command::new("powershell")
+            .env("OPENCLAW_HOSTS_STAGED", &staged)
+            .env("OPENCLAW_HOSTS_TARGET", &path)
+            .args(["-NoProfile", "-Command", "$s = [string][char]39; $q = { $s + ($args[0] -replace $s, ($s + $s)) + $s }; \
+                Start-Process powershell -Verb RunAs -Wait -WindowStyle Hidden -ArgumentList ('-NoProfile -Command Copy-Item -Force -LiteralPath ' + \
+                (& $q $env:OPENCLAW_HOSTS_STAGED) + ' -Destination ' + (& $q $env:OPENCLAW_HOSTS_TARGET))"])
+            .status()
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("osascript")
+            .args(["-e", "on run argv",
+                "-e", "do shell script \"cp \" & quoted form of item 1 of argv & \" \" & quoted form of item 2 of argv with administrator privileges",
+                "-e", "end run"])
+            .arg(&staged)
+            .arg(&path)
+            .status()
+    } else {
+        std::process::Command::new("pkexec").arg("cp").arg(&staged).arg(&path).status()
+    };
+    let _ = std::fs::remove_file(&staged);
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        _ => Err("Blocking sites needs administrator permission, which wasn't given".into()),
+    }
+}
+
+fn apply_hosts(domains: &[String]) -> Result<(), String> {
+    if let Some(bad) = domains.iter().find(|d| !is_host_name(d)) {
+        return Err(format!("\"{bad}\" isn't a site address like example.com"));
+    }
+    let current = std::fs::read_to_string(hosts_path()).map_err(|e| e.to_string())?;
+    let mut hosts = strip_block(&current);
+    hosts.push_str(HOSTS_BEGIN);
+    hosts.push('\n');
+    for domain in domains {
+        let domain = domain.trim_start_matches("www.");
+        for name in [domain.to_string(), format!("www.{domain}")] {
+            hosts.push_str(&format!("0.0.0.0 {name}\n:: {name}\n"));
+        }
+    }
+    hosts.push_str(HOSTS_END);
+    hosts.push('\n');
+    write_hosts(&hosts)
+}
+
+fn remove_hosts() -> Result<(), String> {
+    let current = std::fs::read_to_string(hosts_path()).map_err(|e| e.to_string())?;
+    if !current.contains(HOSTS_BEGIN) {
+        return Ok(());
+    }
+    write_hosts(&strip_block(&current))
+}
+
+/// A program name as the process list shows it, like `Slack` or `steam_osx`: letters,
+/// digits, spaces, `-`, `_` and `.`. pkill reads its argument as a pattern and taskkill
+/// takes wildcards, so anything more could close every program the user has open.
+fn is_app_name(name: &str) -> bool {
+    (1..=64).contains(&name.chars().count())
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+}
+
+fn close_apps(apps: &[String]) {
+    for app in apps.iter().filter(|app| is_app_name(app)) {
+        let _ = if cfg!(target_os = "windows") {
+            let image = if app.to_lowercase().ends_with(".exe") { app.clone() } else { format!("{app}.exe") };
+            std::process::Command::new("taskkill").args(["/IM", &image]).output()
+        } else {
+            std::process::Command::new("pkill").args(["-i", "-x", &app.replace('.', "\\.")]).output()
+        };
+    }
+}
+
+/// Starts a focus session of `minutes` with the saved blocklist. Sites stay reachable when
+/// the user declines administrator rights; the status says so.
+pub fn start_session(app: &AppHandle, minutes: i64, strict: bool, agent_id: &str, run_id: &str) -> Result<FocusStatus, String> {
+    if !(1..=MAX_MINUTES).contains(&minutes) {
+        return Err(format!("A focus session lasts between 1 and {MAX_MINUTES} minutes"));
+    }
+    let db = app.state::<DbState>();
+    let blocklist = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if session(&conn)?.is_some() {
+            return Err("A focus session is already running".into());
+        }
+        blocklist(&conn)?
+    };
+    if blocklist.domains.is_empty() && blocklist.apps.is_empty() {
+        return Err("Add the sites or apps to block before starting a focus session".into());
+    }
+    let hosts_applied = !blocklist.domains.is_empty() && apply_hosts(&blocklist.domains).is_ok();
+    close_apps(&blocklist.apps);
+    let now = Utc::now();
+    let session = Session {
+        started_at: now.to_rfc3339(),
+        ends_at: (now + Duration::minutes(minutes)).to_rfc3339(),
+        strict,
+        blocklist,
+        hosts_applied,
+    };
+    let status = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, SESSION_SETTING, &serde_json::to_string(&session).map_err(|e| e.to_string())?)?;
+        audit::record(&conn, "focus_started", agent_id, run_id, &format!("Started a {minutes}-minute focus session"), &json!({ "strict": strict, "hosts_applied": hosts_applied }))?;
+        status_of(Some(&session))
+    };
+    windows::broadcast(app, "focus-changed", &status);
+    Ok(status)
+}
+
+/// Ends the session and undoes the blocking, or clears blocking left over from one.
+fn end_session(app: &AppHandle, reason: &str) -> Result<FocusStatus, String> {
+    let db = app.state::<DbState>();
+    let session = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        session(&conn)?
+    };
+    // The session ends even when the hosts file can't be put back, so the user isn't asked
+    // for administrator rights over and over; the emergency unlock tries again.
+    let unblocked = match session.as_ref().is_none_or(|s| s.hosts_applied) {
+        true => remove_hosts(),
+        false => Ok(()),
+    };
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM settings WHERE key = ?1", [SESSION_SETTING]).map_err(|e| e.to_string())?;
+        if session.is_some() {
+            audit::record(&conn, "focus_ended", "", "", reason, &json!({ "sites_unblocked": unblocked.is_ok() }))?;
+        }
+    }
+    let status = FocusStatus::default();
+    windows::broadcast(app, "focus-changed", &status);
+    unblocked.map_err(|e| format!("The focus session ended, but the blocked sites couldn't be restored: {e}"))?;
+    Ok(status)
+}
+
+/// Runs the `start_focus` tool.
+pub fn run_tool(app: &AppHandle, params: &Value, agent_id: &str, run_id: &str) -> Result<String, String> {
+    let minutes = params.get("minutes").and_then(Value::as_i64).ok_or("Missing parameter 'minutes'")?;
+    let status = start_session(app, minutes, false, agent_id, run_id)?;
+    let mut blocked: Vec<String> = status.blocklist.apps.clone();
+    if status.hosts_applied {
+        blocked.extend(status.blocklist.domains.iter().cloned());
+    }
+    Ok(format!("Focus session on for {minutes} minutes, blocking {}", if blocked.is_empty() { "nothing".into() } else { blocked.join(", ") }))
+}
+
+/// Starts the thread that keeps blocked apps closed during a session and ends it on time,
+/// including one left running when the app last closed.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("focus-watcher".into())
+        .spawn(move || loop {
+            std::thread::sleep(CHECK_EVERY);
+            let session = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                session(&conn).ok().flatten()
+            };
+            let Some(session) = session else { continue };
+            if status_of(Some(&session)).remaining_seconds == 0 {
+                if let Err(e) = end_session(&app, "The focus session ended") {
+                    eprintln!("Couldn't end the focus session: {e}");
+                }
+            } else {
+                close_apps(&session.blocklist.apps);
+            }
+        })
+        .expect("Failed to start the focus watcher");
+}
+
+// ─── Focus Commands ───
+
+#[tauri::command]
+pub fn get_focus_blocklist(db: State<DbState>) -> Result<Blocklist, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    blocklist(&conn)
+}
+
+/// Saves the sites and apps to block; used from the next session on.
+#[tauri::command]
+pub fn set_focus_blocklist(db: State<DbState>, blocklist: Blocklist) -> Result<Blocklist, String> {
+    let clean = |list: Vec<String>, domain: bool| -> Vec<String> {
+        list.into_iter()
+            .map(|s| {
+                let s = s.trim().to_lowercase();
+                match domain {
+                    true => s.trim_start_matches("https://").trim_start_matches("http://").split('/').next().unwrap_or_default().to_string(),
+                    false => s,
+                }
+            })
+            .filter(|s| !s.is_empty())
+            .collect()
+    };
+    let blocklist = Blocklist { domains: clean(blocklist.domains, true), apps: clean(blocklist.apps, false) };
+    if let Some(bad) = blocklist.domains.iter().find(|d| !is_host_name(d)) {
+        return Err(format!("\"{bad}\" isn't a site address like example.com"));
+    }
+    if let Some(bad) = blocklist.apps.iter().find(|a| !is_app_name(a)) {
+        return Err(format!("\"{bad}\" isn't an app name like Slack; use only letters, digits, spaces, -, _ and ."));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, BLOCKLIST_SETTING, &serde_json::to_string(&blocklist).map_err(|e| e.to_string())?)?;
+    Ok(blocklist)
+}
+
+#[tauri::command]
+pub fn get_focus_status(db: State<DbState>) -> Result<FocusStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(status_of(session(&conn)?.as_ref()))
+}
+
+#[tauri::command]
+pub async fn start_focus_session(app: AppHandle, minutes: i64, strict: Option<bool>) -> Result<FocusStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || start_session(&app, minutes, strict.unwrap_or(false), "", ""))
+        .await.map_err(|e| e.to_string())?
+}
+
+/// Ends a session early. Strict sessions refuse; the emergency unlock still ends them.
+#[tauri::command]
+pub async fn stop_focus_session(app: AppHandle) -> Result<FocusStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let strict = {
+            let db = app.state::<DbState>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            session(&conn)?.is_some_and(|s| s.strict)
+        };
+        if strict {
+            return Err("This is a strict focus session; use the emergency unlock to end it early".into());
+        }
+        end_session(&app, "The focus session was stopped early")
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// Ends any session at once, strict or not, and clears leftover site blocking.
+#[tauri::command]
+pub async fn emergency_unlock_focus(app: AppHandle) -> Result<FocusStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || end_session(&app, "Emergency unlock ended the focus session"))
+        .await.map_err(|e| e.to_string())?
+}
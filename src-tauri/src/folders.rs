@@ -0,0 +1,201 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::{Agent, DbState, query_agents};
+
+/// Folders nest at most this deep, so the sidebar stays usable.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    /// `None` for a top-level folder.
+    pub parent_id: Option<String>,
+    pub created_at: String,
+}
+
+/// A folder with its agents and subfolders. The root node has no folder and holds the
+/// agents that aren't filed anywhere.
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderNode {
+    pub folder: Option<Folder>,
+    pub agents: Vec<Agent>,
+    pub children: Vec<FolderNode>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS folders (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            parent_id TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_folders_parent ON folders(parent_id);
+    ").expect("Failed to initialize folder tables");
+}
+
+fn row_to_folder(row: &rusqlite::Row) -> rusqlite::Result<Folder> {
+    Ok(Folder { id: row.get(0)?, name: row.get(1)?, parent_id: row.get(2)?, created_at: row.get(3)? })
+}
+
+pub(crate) fn load_folder(conn: &Connection, id: &str) -> Result<Folder, String> {
+    conn.query_row("SELECT id, name, parent_id, created_at FROM folders WHERE id = ?1", params![id], row_to_folder)
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Folder not found".to_string())
+}
+
+fn all_folders(conn: &Connection) -> Result<Vec<Folder>, String> {
+    let mut stmt = conn.prepare("SELECT id, name, parent_id, created_at FROM folders ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_folder).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Ids from `id` up to its top-level folder.
+fn ancestry(conn: &Connection, id: &str) -> Result<Vec<String>, String> {
+    let mut chain = vec![id.to_string()];
+    let mut current = load_folder(conn, id)?;
+    while let Some(parent) = current.parent_id {
+        if chain.contains(&parent) || chain.len() > MAX_DEPTH * 2 {
+            break;
+        }
+        chain.push(parent.clone());
+        current = load_folder(conn, &parent)?;
+    }
+    Ok(chain)
+}
+
+/// Levels below `id`, counting `id` itself as one.
+fn subtree_depth(folders: &[Folder], id: &str) -> usize {
+    1 + folders.iter()
+        .filter(|f| f.parent_id.as_deref() == Some(id))
+        .map(|f| subtree_depth(folders, &f.id))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Checks that `folder_id` can go under `parent_id`: the parent exists, isn't the folder or
+/// one of its own subfolders, and the result isn't nested too deep.
+fn check_parent(conn: &Connection, folder_id: Option<&str>, parent_id: Option<&str>) -> Result<(), String> {
+    let Some(parent_id) = parent_id else { return Ok(()) };
+    let chain = ancestry(conn, parent_id)?;
+    let height = match folder_id {
+        Some(id) if chain.iter().any(|c| c == id) => return Err("A folder can't be moved into itself".into()),
+        Some(id) => subtree_depth(&all_folders(conn)?, id),
+        None => 1,
+    };
+    if chain.len() + height > MAX_DEPTH {
+        return Err(format!("Folders can be nested at most {MAX_DEPTH} levels deep"));
+    }
+    Ok(())
+}
+
+fn build_node(folder: Option<Folder>, folders: &[Folder], agents: &mut Vec<Agent>) -> FolderNode {
+    let id = folder.as_ref().map(|f| f.id.clone());
+    let (mine, rest): (Vec<Agent>, Vec<Agent>) = std::mem::take(agents).into_iter().partition(|a| a.folder_id == id);
+    *agents = rest;
+    let children = folders.iter()
+        .filter(|f| f.parent_id == id)
+        .map(|f| build_node(Some(f.clone()), folders, agents))
+        .collect();
+    FolderNode { folder, agents: mine, children }
+}
+
+// ─── Folder Commands ───
+
+#[tauri::command]
+pub fn list_folders(db: State<DbState>) -> Result<Vec<Folder>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    all_folders(&conn)
+}
+
+#[tauri::command]
+pub fn create_folder(db: State<DbState>, name: String, parent_id: Option<String>) -> Result<Folder, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("A folder needs a name".into());
+    }
+    check_parent(&conn, None, parent_id.as_deref())?;
+    let folder = Folder { id: Uuid::new_v4().to_string(), name, parent_id, created_at: Utc::now().to_rfc3339() };
+    conn.execute(
+        "INSERT INTO folders (id, name, parent_id, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![folder.id, folder.name, folder.parent_id, folder.created_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(folder)
+}
+
+#[tauri::command]
+pub fn rename_folder(db: State<DbState>, id: String, name: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if name.trim().is_empty() {
+        return Err("A folder needs a name".into());
+    }
+    let updated = conn.execute("UPDATE folders SET name = ?1 WHERE id = ?2", params![name.trim(), id])
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("Folder not found".into());
+    }
+    Ok(())
+}
+
+/// Moves a folder, with everything in it, under `parent_id` (top level when `None`).
+#[tauri::command]
+pub fn move_folder(db: State<DbState>, id: String, parent_id: Option<String>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_folder(&conn, &id)?;
+    check_parent(&conn, Some(&id), parent_id.as_deref())?;
+    conn.execute("UPDATE folders SET parent_id = ?1 WHERE id = ?2", params![parent_id, id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Deletes a folder. Its agents and subfolders move up to its parent rather than being lost.
+#[tauri::command]
+pub fn delete_folder(db: State<DbState>, id: String) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let folder = load_folder(&conn, &id)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("UPDATE agents SET folder_id = ?1 WHERE folder_id = ?2", params![folder.parent_id, id])
+        .map_err(|e| e.to_string())?;
+    tx.execute("UPDATE folders SET parent_id = ?1 WHERE parent_id = ?2", params![folder.parent_id, id])
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM folders WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Files an agent in a folder, or takes it out of any folder with `None`.
+#[tauri::command]
+pub fn set_agent_folder(db: State<DbState>, agent_id: String, folder_id: Option<String>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(folder_id) = &folder_id {
+        load_folder(&conn, folder_id)?;
+    }
+    let updated = conn.execute("UPDATE agents SET folder_id = ?1 WHERE id = ?2", params![folder_id, agent_id])
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("Agent not found".into());
+    }
+    Ok(())
+}
+
+/// Agents grouped by folder, each group in `list_agents` order for `sort`. Agents filed in a
+/// folder that no longer exists appear at the root.
+#[tauri::command]
+pub fn list_agents_by_folder(db: State<DbState>, sort: Option<String>) -> Result<FolderNode, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let folders = all_folders(&conn)?;
+    let mut agents = query_agents(&conn, sort.as_deref())?;
+    for agent in &mut agents {
+        if agent.folder_id.as_ref().is_some_and(|id| !folders.iter().any(|f| &f.id == id)) {
+            agent.folder_id = None;
+        }
+    }
+    Ok(build_node(None, &folders, &mut agents))
+}
@@ -0,0 +1,228 @@
+use chrono::{DateTime, Datelike, Local, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::State;
+
+use crate::{DbState, read_setting};
+
+/// BCP 47 tag such as `de-DE`. Unset, the OS locale is used.
+const LOCALE_SETTING: &str = "locale";
+const FALLBACK_LOCALE: &str = "en-US";
+
+#[derive(Clone, Copy)]
+enum DateOrder {
+    MonthDayYear,
+    DayMonthYear,
+    YearMonthDay,
+}
+
+/// How a locale writes numbers, dates and times.
+struct Conventions {
+    decimal: char,
+    group: &'static str,
+    order: DateOrder,
+    date_separator: char,
+    twelve_hour: bool,
+}
+
+/// Conventions for `locale`, by language and then region. Anything unknown is written the
+/// US way.
+fn conventions(locale: &str) -> Conventions {
+    let mut parts = locale.split(['-', '_']);
+    let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+    let region = parts.next().unwrap_or_default().to_ascii_uppercase();
+    let c = |decimal, group, order, date_separator, twelve_hour| Conventions { decimal, group, order, date_separator, twelve_hour };
+    match (language.as_str(), region.as_str()) {
+        ("en", "US" | "") => c('.', ",", DateOrder::MonthDayYear, '/', true),
+        ("en", "CA" | "PH") => c('.', ",", DateOrder::YearMonthDay, '-', true),
+        ("en", "AU" | "IN") => c('.', ",", DateOrder::DayMonthYear, '/', true),
+        ("en", _) => c('.', ",", DateOrder::DayMonthYear, '/', false),
+        ("de" | "da" | "nb" | "no" | "fi" | "tr" | "ru" | "pl" | "cs", _) => c(',', ".", DateOrder::DayMonthYear, '.', false),
+        ("fr", _) => c(',', "\u{202f}", DateOrder::DayMonthYear, '/', false),
+        ("sv", _) => c(',', "\u{a0}", DateOrder::YearMonthDay, '-', false),
+        ("nl", _) => c(',', ".", DateOrder::DayMonthYear, '-', false),
+        ("es" | "it" | "pt" | "el" | "id", _) => c(',', ".", DateOrder::DayMonthYear, '/', false),
+        ("ja" | "zh" | "ko", _) => c('.', ",", DateOrder::YearMonthDay, '/', false),
+        ("hi", _) => c('.', ",", DateOrder::DayMonthYear, '/', true),
+        _ => c('.', ",", DateOrder::MonthDayYear, '/', true),
+    }
+}
+
+/// Turns a POSIX locale such as `de_DE.UTF-8` into `de-DE`.
+fn normalize(locale: &str) -> Option<String> {
+    let tag = locale.split(['.', '@']).next().unwrap_or_default().trim().replace('_', "-");
+    (!tag.is_empty() && tag != "C" && tag != "POSIX").then_some(tag)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_locale() -> Option<String> {
+    let out = std::process::Command::new("defaults").args(["read", "-g", "AppleLocale"]).output().ok()?;
+    normalize(&String::from_utf8_lossy(&out.stdout))
+}
+
+#[cfg(target_os = "windows")]
+fn platform_locale() -> Option<String> {
+    let out = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-Command", "(Get-Culture).Name"])
+        .output()
+        .ok()?;
+    normalize(&String::from_utf8_lossy(&out.stdout))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn platform_locale() -> Option<String> {
+    None
+}
+
+/// The OS locale, read once: the usual environment variables, then the platform setting.
+fn os_locale() -> &'static str {
+    static LOCALE: OnceLock<String> = OnceLock::new();
+    LOCALE.get_or_init(|| {
+        ["LC_ALL", "LC_TIME", "LANG"].iter()
+            .find_map(|var| std::env::var(var).ok().and_then(|v| normalize(&v)))
+            .or_else(platform_locale)
+            .unwrap_or_else(|| FALLBACK_LOCALE.into())
+    })
+}
+
+/// The locale to format for: the `locale` setting if set, otherwise the OS locale.
+pub fn locale(db: &DbState) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    locale_of(&conn)
+}
+
+/// [`locale`] for callers already holding the database.
+pub fn locale_of(conn: &Connection) -> Result<String, String> {
+    Ok(read_setting(conn, LOCALE_SETTING)?
+        .and_then(|l| normalize(&l))
+        .unwrap_or_else(|| os_locale().to_string()))
+}
+
+/// `value` with `decimals` places and the locale's separators, e.g. `1,234.5` or `1.234,5`.
+pub fn number(locale: &str, value: f64, decimals: usize) -> String {
+    let c = conventions(locale);
+    let fixed = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push_str(c.group);
+        }
+        grouped.push(digit);
+    }
+    let sign = if value < 0.0 && fixed.chars().any(|d| d.is_ascii_digit() && d != '0') { "-" } else { "" };
+    match fraction {
+        "" => format!("{sign}{grouped}"),
+        fraction => format!("{sign}{grouped}{}{fraction}", c.decimal),
+    }
+}
+
+/// Whether the locale writes the day before the month in a numeric date, as in `15.10.2026`.
+pub fn day_before_month(locale: &str) -> bool {
+    matches!(conventions(locale).order, DateOrder::DayMonthYear)
+}
+
+/// The date alone, in the locale's order, e.g. `10/15/2026` or `15.10.2026`.
+pub fn date(locale: &str, time: &DateTime<Local>) -> String {
+    let c = conventions(locale);
+    let s = c.date_separator;
+    let (y, m, d) = (time.year(), time.month(), time.day());
+    match c.order {
+        DateOrder::MonthDayYear => format!("{m}{s}{d}{s}{y}"),
+        DateOrder::DayMonthYear => format!("{d:02}{s}{m:02}{s}{y}"),
+        DateOrder::YearMonthDay => format!("{y}{s}{m:02}{s}{d:02}"),
+    }
+}
+
+/// Date and time of day, e.g. `10/15/2026, 3:04 PM` or `15.10.2026, 15:04`.
+pub fn date_time(locale: &str, time: &DateTime<Local>) -> String {
+    let clock = match conventions(locale).twelve_hour {
+        true => time.format("%-I:%M %p").to_string(),
+        false => time.format("%H:%M").to_string(),
+    };
+    format!("{}, {clock}", date(locale, time))
+}
+
+fn count(n: i64, unit: &str) -> String {
+    match n {
+        1 => format!("1 {unit}"),
+        n => format!("{n} {unit}s"),
+    }
+}
+
+/// How long ago (or how far ahead) `time` is from `now`, e.g. "just now", "3 minutes ago",
+/// "yesterday", "in 2 hours".
+pub fn relative(time: &DateTime<Local>, now: &DateTime<Local>) -> String {
+    let seconds = (*now - *time).num_seconds();
+    let ahead = seconds < 0;
+    let seconds = seconds.abs();
+    let days = (now.date_naive() - time.date_naive()).num_days();
+    let span = match seconds {
+        s if s < 45 => return "just now".into(),
+        s if s < 90 => count(1, "minute"),
+        s if s < 3600 => count((s + 30) / 60, "minute"),
+        s if s < 86_400 && days.abs() < 1 => count((s + 1800) / 3600, "hour"),
+        _ if days == 1 => return "yesterday".into(),
+        _ if days == -1 => return "tomorrow".into(),
+        _ if days.abs() < 7 => count(days.abs().max(1), "day"),
+        _ if days.abs() < 30 => count(days.abs() / 7, "week"),
+        _ if days.abs() < 365 => count(days.abs() / 30, "month"),
+        _ => count(days.abs() / 365, "year"),
+    };
+    match ahead {
+        true => format!("in {span}"),
+        false => format!("{span} ago"),
+    }
+}
+
+/// A stored timestamp, ready to show.
+#[derive(Debug, Serialize, Clone)]
+pub struct FormattedTime {
+    pub timestamp: String,
+    /// "3 minutes ago", "yesterday"; empty when the timestamp couldn't be read.
+    pub relative: String,
+    pub date: String,
+    pub date_time: String,
+}
+
+fn format_time(locale: &str, timestamp: &str, now: &DateTime<Local>) -> FormattedTime {
+    let parsed = DateTime::parse_from_rfc3339(timestamp).map(|t| t.with_timezone(&Local));
+    match parsed {
+        Ok(time) => FormattedTime {
+            timestamp: timestamp.to_string(),
+            relative: relative(&time, now),
+            date: date(locale, &time),
+            date_time: date_time(locale, &time),
+        },
+        Err(_) => FormattedTime {
+            timestamp: timestamp.to_string(),
+            relative: String::new(),
+            date: String::new(),
+            date_time: String::new(),
+        },
+    }
+}
+
+// ─── Formatting Commands ───
+
+/// The locale dates and numbers are formatted for.
+#[tauri::command]
+pub fn get_locale(db: State<DbState>) -> Result<String, String> {
+    locale(&db)
+}
+
+/// Formats stored RFC 3339 timestamps for display, in local time, in the order given.
+#[tauri::command]
+pub fn format_times(db: State<DbState>, timestamps: Vec<String>) -> Result<Vec<FormattedTime>, String> {
+    let locale = locale(&db)?;
+    let now = Utc::now().with_timezone(&Local);
+    Ok(timestamps.iter().map(|t| format_time(&locale, t, &now)).collect())
+}
+
+/// Formats numbers with the locale's separators and `decimals` places (default 0).
+#[tauri::command]
+pub fn format_numbers(db: State<DbState>, values: Vec<f64>, decimals: Option<usize>) -> Result<Vec<String>, String> {
+    let locale = locale(&db)?;
+    Ok(values.iter().map(|v| number(&locale, *v, decimals.unwrap_or(0).min(10))).collect())
+}
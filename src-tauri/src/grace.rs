@@ -0,0 +1,305 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::audit;
+use crate::executor::{self, Run};
+use crate::power;
+use crate::shutdown;
+use crate::windows;
+use crate::{DbState, ensure_column, read_setting};
+
+/// Seconds an approved irreversible step waits, cancellable, before it runs; `0` runs it
+/// straight away.
+const GRACE_SETTING: &str = "action_grace_seconds";
+const DEFAULT_GRACE: i64 = 10;
+const MAX_GRACE: i64 = 300;
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// An approved step counting down before it runs, like an email's "undo send", or waiting
+/// for the time it was scheduled to be sent.
+#[derive(Debug, Serialize, Clone)]
+pub struct PendingAction {
+    pub id: String,
+    pub run_id: String,
+    pub step_index: i64,
+    pub agent_id: String,
+    pub agent_name: String,
+    pub tool: String,
+    pub preview: String,
+    /// When it runs unless cancelled.
+    pub execute_at: String,
+    /// Set when the step asked to be sent at a later time rather than right away.
+    pub send_at: Option<String>,
+    pub created_at: String,
+}
+
+/// What a step held for its grace period should do now.
+pub enum Grace {
+    Run,
+    Wait,
+    Cancelled,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS pending_actions (
+            id TEXT PRIMARY KEY,
+            run_id TEXT NOT NULL,
+            step_index INTEGER NOT NULL,
+            agent_id TEXT NOT NULL,
+            tool TEXT NOT NULL,
+            preview TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            execute_at TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_pending_actions_run ON pending_actions(run_id, step_index);
+    ").expect("Failed to initialize pending action tables");
+    ensure_column(conn, "pending_actions", "send_at", "TEXT").expect("Failed to migrate pending action tables");
+}
+
+fn grace_seconds(conn: &Connection) -> Result<i64, String> {
+    Ok(read_setting(conn, GRACE_SETTING)?
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(DEFAULT_GRACE)
+        .clamp(0, MAX_GRACE))
+}
+
+/// Reads a delivery time in local time: `2024-06-01 08:00`, `tomorrow 08:00`, `08:00` (the
+/// next 8am) or an RFC 3339 timestamp.
+pub fn parse_send_at(value: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    let invalid = || format!("\"{value}\" isn't a time to send like 2024-06-01 08:00 or tomorrow 08:00");
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Local));
+    }
+    let local = |naive: NaiveDateTime| naive.and_local_timezone(Local).earliest().ok_or_else(invalid);
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M") {
+        return local(naive);
+    }
+    let (day, time) = match value.split_once(' ') {
+        Some((day, time)) if day.eq_ignore_ascii_case("tomorrow") => (Some(now.date_naive() + Duration::days(1)), time),
+        Some((day, time)) if day.eq_ignore_ascii_case("today") => (Some(now.date_naive()), time),
+        Some((day, time)) => (Some(NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| invalid())?), time),
+        None => (None, value),
+    };
+    let time = NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| invalid())?;
+    match day {
+        Some(day) => local(day.and_time(time)),
+        None => {
+            let today = local(now.date_naive().and_time(time))?;
+            if today > now { Ok(today) } else { local((now.date_naive() + Duration::days(1)).and_time(time)) }
+        }
+    }
+}
+
+/// When a step asked to be sent, from its `send_at` param; `None` to send right away.
+pub fn send_at(params: &serde_json::Value) -> Result<Option<DateTime<Local>>, String> {
+    match params.get("send_at").and_then(serde_json::Value::as_str).map(str::trim) {
+        Some(value) if !value.is_empty() => parse_send_at(value, Local::now()).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Queues an approved irreversible step the first time it comes up, for the grace period or
+/// until `send_at` when that is later, and says whether it may run once it comes up again.
+pub fn hold(conn: &Connection, run: &Run, index: usize, tool: &str, preview: &str, send_at: Option<DateTime<Local>>) -> Result<Grace, String> {
+    let status: Option<String> = conn.query_row(
+        "SELECT status FROM pending_actions WHERE run_id = ?1 AND step_index = ?2 ORDER BY created_at DESC LIMIT 1",
+        params![run.id, index as i64],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    match status.as_deref() {
+        Some("released") => Ok(Grace::Run),
+        Some("cancelled") => Ok(Grace::Cancelled),
+        Some(_) => Ok(Grace::Wait),
+        None => {
+            let now = Utc::now();
+            let countdown = now + Duration::seconds(grace_seconds(conn)?);
+            let send_at = send_at.map(|t| t.with_timezone(&Utc)).filter(|t| *t > countdown);
+            if countdown == now && send_at.is_none() {
+                return Ok(Grace::Run);
+            }
+            conn.execute(
+                "INSERT INTO pending_actions (id, run_id, step_index, agent_id, tool, preview, execute_at, send_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![Uuid::new_v4().to_string(), run.id, index as i64, run.agent_id, tool, preview,
+                    send_at.unwrap_or(countdown).to_rfc3339(), send_at.map(|t| t.to_rfc3339()), now.to_rfc3339()],
+            ).map_err(|e| e.to_string())?;
+            Ok(Grace::Wait)
+        }
+    }
+}
+
+/// Moves countdowns that were running when the computer went to sleep on by the time it
+/// slept. Steps scheduled for a set time keep it.
+pub fn postpone(conn: &Connection, asleep_since: DateTime<Utc>, by: Duration) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT id, execute_at FROM pending_actions WHERE status = 'pending' AND send_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    for (id, execute_at) in rows {
+        let Ok(execute_at) = DateTime::parse_from_rfc3339(&execute_at) else { continue };
+        let execute_at = execute_at.with_timezone(&Utc);
+        if execute_at > asleep_since {
+            conn.execute("UPDATE pending_actions SET execute_at = ?1 WHERE id = ?2", params![(execute_at + by).to_rfc3339(), id])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn due(conn: &Connection) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn.prepare("SELECT id, run_id FROM pending_actions WHERE status = 'pending' AND execute_at <= ?1 ORDER BY execute_at")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![Utc::now().to_rfc3339()], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Marks a held step `released` or `cancelled` if nothing else has yet, so the watcher and
+/// the user can't both act on it. Returns whether this call settled it.
+fn settle(conn: &Connection, id: &str, status: &str) -> Result<bool, String> {
+    conn.execute("UPDATE pending_actions SET status = ?2 WHERE id = ?1 AND status = 'pending'", params![id, status])
+        .map(|changed| changed > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Starts the thread that runs held steps once their grace period is over, including those
+/// left waiting when the app last closed.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("pending-actions".into())
+        .spawn(move || loop {
+            std::thread::sleep(CHECK_EVERY);
+            if shutdown::stopping(&app) {
+                continue;
+            }
+            // Catches a wake-up first, so countdowns that were asleep get their time back.
+            power::check(&app);
+            let due = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                due(&conn).unwrap_or_default().into_iter()
+                    .filter(|(id, _)| settle(&conn, id, "released").unwrap_or(false))
+                    .collect::<Vec<_>>()
+            };
+            for (_, run_id) in due {
+                windows::broadcast(&app, "pending-actions-changed", &run_id);
+                if let Err(e) = executor::resume(&app, &run_id) {
+                    eprintln!("Pending action in run {run_id} didn't run: {e}");
+                }
+            }
+        })
+        .expect("Failed to start the pending action watcher");
+}
+
+// ─── Pending Action Commands ───
+
+/// Steps counting down or scheduled to be sent, soonest first.
+#[tauri::command]
+pub fn list_pending_actions(db: State<DbState>) -> Result<Vec<PendingAction>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.run_id, p.step_index, p.agent_id, COALESCE(a.name, ''), p.tool, p.preview, p.execute_at, p.send_at, p.created_at
+         FROM pending_actions p LEFT JOIN agents a ON a.id = p.agent_id
+         WHERE p.status = 'pending' ORDER BY p.execute_at"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok(PendingAction {
+        id: row.get(0)?,
+        run_id: row.get(1)?,
+        step_index: row.get(2)?,
+        agent_id: row.get(3)?,
+        agent_name: row.get(4)?,
+        tool: row.get(5)?,
+        preview: row.get(6)?,
+        execute_at: row.get(7)?,
+        send_at: row.get(8)?,
+        created_at: row.get(9)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Stops a step before its grace period is over. The run ends as cancelled.
+#[tauri::command]
+pub async fn cancel_pending_action(app: AppHandle, id: String) -> Result<(), String> {
+    let run_id = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let (run_id, agent_id, preview): (String, String, String) = conn.query_row(
+            "SELECT run_id, agent_id, preview FROM pending_actions WHERE id = ?1 AND status = 'pending'",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional().map_err(|e| e.to_string())?.ok_or("Too late to cancel: the action already ran")?;
+        if !settle(&conn, &id, "cancelled")? {
+            return Err("Too late to cancel: the action already ran".into());
+        }
+        audit::record(&conn, "action_cancelled", &agent_id, &run_id, &format!("Cancelled before it ran: {preview}"), &serde_json::json!({ "id": id }))?;
+        run_id
+    };
+    windows::broadcast(&app, "pending-actions-changed", &run_id);
+    tauri::async_runtime::spawn_blocking(move || executor::resume(&app, &run_id)).await.map_err(|e| e.to_string())??;
+    Ok(())
+}
+
+/// Moves a scheduled send to another time, e.g. `tomorrow 09:00`.
+#[tauri::command]
+pub fn reschedule_pending_action(app: AppHandle, id: String, send_at: String) -> Result<PendingAction, String> {
+    let at = parse_send_at(&send_at, Local::now())?.with_timezone(&Utc);
+    if at <= Utc::now() {
+        return Err("Pick a time that hasn't passed yet".into());
+    }
+    let run_id = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let updated = conn.execute(
+            "UPDATE pending_actions SET execute_at = ?1, send_at = ?1 WHERE id = ?2 AND status = 'pending'",
+            params![at.to_rfc3339(), id],
+        ).map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("Too late to reschedule: the action already ran or was cancelled".into());
+        }
+        conn.query_row("SELECT run_id FROM pending_actions WHERE id = ?1", params![id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+    };
+    windows::broadcast(&app, "pending-actions-changed", &run_id);
+    list_pending_actions(app.state::<DbState>())?.into_iter().find(|a| a.id == id).ok_or_else(|| "Pending action not found".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier, Mutex};
+
+    #[test]
+    fn settles_a_held_step_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_tables(&conn);
+        conn.execute(
+            "INSERT INTO pending_actions (id, run_id, step_index, agent_id, tool, preview, execute_at, created_at)
+             VALUES ('held', 'run', 2, 'agent', 'send_email', 'Email to Sam', '', '')",
+            [],
+        ).unwrap();
+        // The watcher releasing the step as the user cancels it: only one of them wins.
+        let conn = Arc::new(Mutex::new(conn));
+        let start = Arc::new(Barrier::new(2));
+        let racers: Vec<_> = ["released", "cancelled"].into_iter().map(|status| {
+            let (conn, start) = (conn.clone(), start.clone());
+            std::thread::spawn(move || {
+                start.wait();
+                settle(&conn.lock().unwrap(), "held", status).unwrap()
+            })
+        }).collect();
+        let won: Vec<bool> = racers.into_iter().map(|r| r.join().unwrap()).collect();
+        assert_eq!(won.iter().filter(|w| **w).count(), 1);
+        let conn = conn.lock().unwrap();
+        let status: String = conn.query_row("SELECT status FROM pending_actions", [], |row| row.get(0)).unwrap();
+        assert_eq!(status, if won[0] { "released" } else { "cancelled" });
+        assert!(!settle(&conn, "held", "released").unwrap());
+        assert!(!settle(&conn, "missing", "cancelled").unwrap());
+    }
+}
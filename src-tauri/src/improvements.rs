@@ -0,0 +1,353 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::llm::{ChatMessage, LlmRequest};
+use crate::persona::{self, Tone};
+use crate::structured;
+use crate::workflows::{self, WorkflowStep};
+use crate::{DbState, update_agent_config};
+
+const SYSTEM: &str = "You improve automation agents for non-technical users. You are given an agent's \
+instructions and evidence of how it has been doing: the user's ratings and comments, and errors from failed runs. \
+Propose a few concrete edits to the listed fields that address what went wrong. Only change what the evidence \
+supports, keep the user's intent, and write each proposed value in full. Explain each change in one plain sentence.";
+const REPLY_TOKENS: u32 = 2048;
+/// Evidence sent to the model: this many rated items and failed runs, newest first.
+const RECENT_FEEDBACK: i64 = 20;
+const RECENT_FAILURES: i64 = 10;
+/// Error text from a failed run is cut to this many characters.
+const ERROR_CHARS: usize = 500;
+
+/// One proposed edit. `field` is `goal`, `role`, `system_prompt` (the agent's own persona
+/// instructions), `tone`, or `step:<n>:prompt` for the prompt of workflow step `n` (from 1).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProposedChange {
+    pub field: String,
+    pub current: String,
+    pub proposed: String,
+    pub reason: String,
+    /// Line diff of `current` against `proposed`: lines start with `- `, `+ ` or two spaces.
+    pub diff: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentSuggestion {
+    pub id: String,
+    pub agent_id: String,
+    pub summary: String,
+    pub changes: Vec<ProposedChange>,
+    /// `pending`, `applied` or `dismissed`.
+    pub status: String,
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS agent_suggestions (
+            id TEXT PRIMARY KEY,
+            agent_id TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            changes_json TEXT NOT NULL,
+            status TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_agent_suggestions_agent ON agent_suggestions(agent_id, created_at);
+    ").expect("Failed to initialize suggestion tables");
+}
+
+/// The fields a suggestion may change, with their current values.
+fn current_fields(conn: &Connection, agent_id: &str) -> Result<Vec<(String, String)>, String> {
+    let (goal, role): (String, String) = conn.query_row(
+        "SELECT goal, role FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| e.to_string())?.ok_or("Agent not found")?;
+    let persona = persona::agent_persona(conn, agent_id)?;
+    let tone = persona.tone.map(|t| json!(t).as_str().unwrap_or_default().to_string()).unwrap_or_default();
+    let mut fields = vec![
+        ("goal".to_string(), goal),
+        ("role".to_string(), role),
+        ("system_prompt".to_string(), persona.system_prompt),
+        ("tone".to_string(), tone),
+    ];
+    for (i, step) in workflows::agent_workflow(conn, agent_id)?.iter().enumerate() {
+        if let WorkflowStep::Tool { params, .. } = step {
+            if let Some(prompt) = params.get("prompt").and_then(Value::as_str) {
+                fields.push((format!("step:{}:prompt", i + 1), prompt.to_string()));
+            }
+        }
+    }
+    Ok(fields)
+}
+
+fn set_field(conn: &Connection, agent_id: &str, field: &str, value: &str) -> Result<(), String> {
+    match field {
+        "goal" | "role" => {
+            conn.execute(
+                &format!("UPDATE agents SET {field} = ?1, updated_at = ?2 WHERE id = ?3"),
+                params![value, Utc::now().to_rfc3339(), agent_id],
+            ).map_err(|e| e.to_string())?;
+            Ok(())
+        }
+        "system_prompt" | "tone" => {
+            let mut persona = persona::agent_persona(conn, agent_id)?;
+            if field == "tone" {
+                persona.tone = parse_tone(value)?;
+            } else {
+                persona.system_prompt = value.to_string();
+            }
+            let value = serde_json::to_value(&persona).map_err(|e| e.to_string())?;
+            update_agent_config(conn, agent_id, |config| { config.insert("persona".into(), value); })
+        }
+        step => {
+            let index: usize = step.strip_prefix("step:").and_then(|s| s.strip_suffix(":prompt"))
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Unknown field: {step}"))?;
+            let mut workflow = workflows::agent_workflow(conn, agent_id)?;
+            match workflow.get_mut(index - 1) {
+                Some(WorkflowStep::Tool { params, .. }) if params.get("prompt").is_some() => {
+                    params["prompt"] = json!(value);
+                }
+                _ => return Err(format!("Step {index} no longer has a prompt")),
+            }
+            workflows::store_agent_workflow(conn, agent_id, &workflow)
+        }
+    }
+}
+
+fn parse_tone(value: &str) -> Result<Option<Tone>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_value(json!(value.trim().to_lowercase()))
+        .map(Some)
+        .map_err(|_| format!("Unknown tone: {value}"))
+}
+
+/// Line diff by longest common subsequence; agent texts are short enough for the table.
+fn line_diff(old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push(format!("  {}", a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push(format!("+ {}", b[j]));
+            j += 1;
+        } else {
+            out.push(format!("- {}", a[i]));
+            i += 1;
+        }
+    }
+    out.join("\n")
+}
+
+/// The user's ratings and failed runs, as text for the model. Empty when there's nothing to
+/// go on.
+fn evidence(conn: &Connection, agent_id: &str) -> Result<String, String> {
+    let mut lines = Vec::new();
+    let mut stmt = conn.prepare(
+        "SELECT kind, rating, comment FROM feedback WHERE agent_id = ?1 ORDER BY updated_at DESC LIMIT ?2"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id, RECENT_FEEDBACK], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
+    }).map_err(|e| e.to_string())?;
+    for row in rows {
+        let (kind, rating, comment) = row.map_err(|e| e.to_string())?;
+        let verdict = if rating > 0 { "liked" } else { "disliked" };
+        let comment = if comment.is_empty() { String::new() } else { format!(": \"{comment}\"") };
+        lines.push(format!("- The user {verdict} a {kind}{comment}"));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT error FROM runs WHERE agent_id = ?1 AND status = 'error' ORDER BY started_at DESC LIMIT ?2"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id, RECENT_FAILURES], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let error: String = row.map_err(|e| e.to_string())?.chars().take(ERROR_CHARS).collect();
+        lines.push(format!("- A run failed: {error}"));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn suggestion_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "summary": { "type": "string" },
+            "changes": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "field": { "type": "string" },
+                        "proposed": { "type": "string" },
+                        "reason": { "type": "string" },
+                    },
+                    "required": ["field", "proposed", "reason"],
+                },
+            },
+        },
+        "required": ["summary", "changes"],
+    })
+}
+
+fn store(conn: &Connection, suggestion: &AgentSuggestion) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_suggestions (id, agent_id, summary, changes_json, status, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            suggestion.id, suggestion.agent_id, suggestion.summary,
+            serde_json::to_string(&suggestion.changes).map_err(|e| e.to_string())?,
+            suggestion.status, suggestion.created_at
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn row_to_suggestion(row: &rusqlite::Row) -> rusqlite::Result<AgentSuggestion> {
+    let changes: String = row.get(3)?;
+    Ok(AgentSuggestion {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        summary: row.get(2)?,
+        changes: serde_json::from_str(&changes).unwrap_or_default(),
+        status: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+fn load(conn: &Connection, id: &str) -> Result<AgentSuggestion, String> {
+    conn.query_row(
+        "SELECT id, agent_id, summary, changes_json, status, created_at FROM agent_suggestions WHERE id = ?1",
+        params![id],
+        row_to_suggestion,
+    ).optional().map_err(|e| e.to_string())?.ok_or_else(|| "Suggestion not found".to_string())
+}
+
+// ─── Improvement Commands ───
+
+/// Asks the model for changes to the agent's goal, role, instructions, tone or step prompts
+/// based on the user's feedback and its failed runs. The suggestion is kept until the user
+/// applies or dismisses it; nothing changes before that.
+#[tauri::command]
+pub async fn suggest_agent_improvements(app: AppHandle, agent_id: String) -> Result<AgentSuggestion, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let (name, fields, evidence) = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let name: String = conn.query_row("SELECT name FROM agents WHERE id = ?1", params![agent_id], |row| row.get(0))
+                .map_err(|_| "Agent not found".to_string())?;
+            (name, current_fields(&conn, &agent_id)?, evidence(&conn, &agent_id)?)
+        };
+        let mut suggestion = AgentSuggestion {
+            id: Uuid::new_v4().to_string(),
+            agent_id: agent_id.clone(),
+            summary: String::new(),
+            changes: Vec::new(),
+            status: "pending".into(),
+            created_at: Utc::now().to_rfc3339(),
+        };
+        if evidence.is_empty() {
+            suggestion.summary = "There's no feedback or failed run to learn from yet.".into();
+            return Ok(suggestion);
+        }
+
+        let listed = fields.iter()
+            .map(|(field, value)| format!("[{field}]\n{}", if value.is_empty() { "(empty)" } else { value }))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let prompt = format!(
+            "Agent: {name}\n\nFields you may change (tone is one of neutral, formal, friendly, concise):\n\n{listed}\n\nEvidence:\n{evidence}"
+        );
+        let reply = structured::complete(&db, &LlmRequest {
+            system: SYSTEM.into(),
+            messages: vec![ChatMessage::user(prompt)],
+            max_tokens: REPLY_TOKENS,
+            agent_id: agent_id.clone(),
+            ..Default::default()
+        }, &suggestion_schema())?;
+
+        suggestion.summary = reply["summary"].as_str().unwrap_or_default().to_string();
+        for change in reply["changes"].as_array().into_iter().flatten() {
+            let field = change["field"].as_str().unwrap_or_default().trim();
+            let proposed = change["proposed"].as_str().unwrap_or_default().trim().to_string();
+            // Fields the agent doesn't have and edits that change nothing are dropped.
+            let Some((_, current)) = fields.iter().find(|(f, _)| f == field) else { continue };
+            if proposed == current.trim() || (field == "tone" && parse_tone(&proposed).is_err()) {
+                continue;
+            }
+            suggestion.changes.push(ProposedChange {
+                field: field.to_string(),
+                diff: line_diff(current, &proposed),
+                current: current.clone(),
+                proposed,
+                reason: change["reason"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        store(&conn, &suggestion)?;
+        Ok(suggestion)
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn list_agent_suggestions(db: State<DbState>, agent_id: String) -> Result<Vec<AgentSuggestion>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, summary, changes_json, status, created_at FROM agent_suggestions
+         WHERE agent_id = ?1 ORDER BY created_at DESC"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id], row_to_suggestion).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Applies the suggestion's changes, or only those for `fields` when given, in one go.
+/// Refuses when a field was edited since the suggestion was made.
+#[tauri::command]
+pub fn apply_agent_suggestion(db: State<DbState>, id: String, fields: Option<Vec<String>>) -> Result<AgentSuggestion, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut suggestion = load(&conn, &id)?;
+    if suggestion.status != "pending" {
+        return Err(format!("This suggestion was already {}", suggestion.status));
+    }
+    let current = current_fields(&conn, &suggestion.agent_id)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for change in &suggestion.changes {
+        if fields.as_ref().is_some_and(|f| !f.contains(&change.field)) {
+            continue;
+        }
+        let now = current.iter().find(|(f, _)| *f == change.field).map(|(_, v)| v.as_str());
+        if now != Some(change.current.as_str()) {
+            return Err(format!("The agent's {} changed since this suggestion was made; ask for a new one", change.field));
+        }
+        set_field(&tx, &suggestion.agent_id, &change.field, &change.proposed)?;
+    }
+    suggestion.status = "applied".into();
+    store(&tx, &suggestion)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(suggestion)
+}
+
+#[tauri::command]
+pub fn dismiss_agent_suggestion(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("UPDATE agent_suggestions SET status = 'dismissed' WHERE id = ?1 AND status = 'pending'", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
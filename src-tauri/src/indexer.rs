@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::rag::{self, IndexReport};
+use crate::{DbState, read_setting};
+
+/// Minutes between scheduled passes; `index_knowledge` starts one straight away.
+const INTERVAL_SETTING: &str = "knowledge_index_interval_minutes";
+const DEFAULT_INTERVAL_MINUTES: u64 = 60;
+/// Pause between files, so indexing never competes with what the user is doing.
+const IDLE_DELAY: Duration = Duration::from_millis(100);
+const ACTIVE_DELAY: Duration = Duration::from_millis(1500);
+const BATTERY_DELAY: Duration = Duration::from_secs(5);
+/// Checking the power source can mean spawning a process, so the answer is reused for a while.
+const POWER_CHECK_EVERY: Duration = Duration::from_secs(60);
+
+/// Shared between the worker thread, the window-focus handler and the commands.
+#[derive(Default)]
+pub struct Indexer {
+    paused: AtomicBool,
+    running: AtomicBool,
+    /// True while an app window has focus, i.e. the user is probably working in it.
+    user_active: AtomicBool,
+    /// Set by `index_knowledge` to start a pass before the next scheduled one.
+    wake: Mutex<bool>,
+    signal: Condvar,
+    on_battery: Mutex<Option<(Instant, bool)>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexProgress {
+    /// `indexing`, `paused` or `idle`.
+    pub state: &'static str,
+    pub done: usize,
+    pub total: usize,
+    pub file: String,
+}
+
+impl Indexer {
+    pub fn set_user_active(&self, active: bool) {
+        self.user_active.store(active, Ordering::SeqCst);
+    }
+
+    pub fn is_user_active(&self) -> bool {
+        self.user_active.load(Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn wake(&self) {
+        *self.wake.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.signal.notify_all();
+    }
+
+    /// Blocks until a pass is requested or `interval` has passed.
+    fn wait(&self, interval: Duration) {
+        let woken = self.wake.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut woken, _) = self.signal.wait_timeout_while(woken, interval, |w| !*w)
+            .unwrap_or_else(|e| e.into_inner());
+        *woken = false;
+    }
+
+    fn on_battery(&self) -> bool {
+        let mut cached = self.on_battery.lock().unwrap_or_else(|e| e.into_inner());
+        match *cached {
+            Some((checked, value)) if checked.elapsed() < POWER_CHECK_EVERY => value,
+            _ => {
+                let value = on_battery();
+                *cached = Some((Instant::now(), value));
+                value
+            }
+        }
+    }
+
+    /// Runs before each file: waits out a pause, reports progress, then backs off according
+    /// to what the machine is doing.
+    fn pace(&self, app: &AppHandle, done: usize, total: usize, file: &Path) {
+        let file = file.display().to_string();
+        while self.is_paused() {
+            let _ = app.emit("knowledge-index-progress", IndexProgress { state: "paused", done, total, file: file.clone() });
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        let _ = app.emit("knowledge-index-progress", IndexProgress { state: "indexing", done, total, file });
+        std::thread::sleep(if self.on_battery() {
+            BATTERY_DELAY
+        } else if self.user_active.load(Ordering::SeqCst) {
+            ACTIVE_DELAY
+        } else {
+            IDLE_DELAY
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else { return false };
+    supplies.filter_map(|e| e.ok())
+        .filter(|e| std::fs::read_to_string(e.path().join("type")).is_ok_and(|t| t.trim() == "Battery"))
+        .any(|e| std::fs::read_to_string(e.path().join("status")).is_ok_and(|s| s.trim() == "Discharging"))
+}
+
+#[cfg(target_os = "macos")]
+fn on_battery() -> bool {
+    std::process::Command::new("pmset").args(["-g", "batt"]).output()
+        .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).contains("'Battery Power'"))
+}
+
+#[cfg(target_os = "windows")]
+fn on_battery() -> bool {
+    // Win32_Battery.BatteryStatus 1 means "discharging".
+    std::process::Command::new("powershell")
+        .args(["-NoProfile", "-Command", "(Get-CimInstance Win32_Battery).BatteryStatus"])
+        .output()
+        .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).lines().any(|l| l.trim() == "1"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn on_battery() -> bool {
+    false
+}
+
+/// Starts the worker thread. It re-indexes every interval, or sooner when asked, and emits
+/// `knowledge-index-progress` while working and `knowledge-index-finished` with the report.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("knowledge-indexer".into())
+        .spawn(move || loop {
+            let interval = {
+                let db = app.state::<DbState>();
+                let minutes = db.0.lock().ok()
+                    .and_then(|conn| read_setting(&conn, INTERVAL_SETTING).ok().flatten())
+                    .and_then(|m| m.trim().parse().ok())
+                    .unwrap_or(DEFAULT_INTERVAL_MINUTES);
+                Duration::from_secs(minutes.max(1) * 60)
+            };
+            let indexer = app.state::<Indexer>();
+            indexer.wait(interval);
+
+            indexer.running.store(true, Ordering::SeqCst);
+            let result = rag::run_index(&app.state::<DbState>(), |done, total, file| indexer.pace(&app, done, total, file));
+            indexer.running.store(false, Ordering::SeqCst);
+            let _ = app.emit("knowledge-index-progress", IndexProgress { state: "idle", done: 0, total: 0, file: String::new() });
+            let report = result.unwrap_or_else(|e| IndexReport { failed: vec![e], ..Default::default() });
+            let _ = app.emit("knowledge-index-finished", report);
+        })
+        .expect("Failed to start the knowledge indexer");
+}
+
+// ─── Indexer Commands ───
+
+/// Starts an index pass in the background; progress arrives as events.
+#[tauri::command]
+pub fn index_knowledge(indexer: State<Indexer>) {
+    indexer.wake();
+}
+
+#[tauri::command]
+pub fn pause_indexing(indexer: State<Indexer>) {
+    indexer.paused.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+pub fn resume_indexing(indexer: State<Indexer>) {
+    indexer.paused.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,139 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::datadir::{self, DATABASE_FILE};
+use crate::{read_setting, write_setting};
+
+/// The OS account that owns this data, recorded on first start. Device-local.
+const OWNER_SETTING: &str = "data_owner";
+
+/// One check of the startup audit.
+#[derive(Debug, Serialize, Clone)]
+pub struct IsolationFinding {
+    pub check: String,
+    /// `ok`, `repaired` or `warning`.
+    pub status: String,
+    pub detail: String,
+}
+
+/// What the startup audit found, kept for the diagnostics view.
+#[derive(Default)]
+pub struct IsolationAudit(Mutex<Vec<IsolationFinding>>);
+
+fn finding(check: &str, status: &str, detail: impl Into<String>) -> IsolationFinding {
+    IsolationFinding { check: check.into(), status: status.into(), detail: detail.into() }
+}
+
+/// The signed-in OS account, as the environment names it.
+fn os_user() -> String {
+    ["USER", "USERNAME", "LOGNAME"].iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty()))
+        .unwrap_or_default()
+}
+
+/// The data folder should belong to this account: inside its home folder, unless the user
+/// chose portable mode, where sharing the stick is the point.
+fn check_location(dir: &Path) -> IsolationFinding {
+    if datadir::is_portable() {
+        return finding("location", "warning", format!(
+            "Portable mode: the data in {} is available to anyone who can open that folder", dir.display(),
+        ));
+    }
+    match dirs_next::home_dir() {
+        Some(home) if !dir.starts_with(&home) => finding("location", "warning", format!(
+            "The data folder {} is outside your home folder; other accounts on this computer may be able to reach it",
+            dir.display(),
+        )),
+        _ => finding("location", "ok", format!("Data is kept in {}", dir.display())),
+    }
+}
+
+/// On Unix, tightens the data folder and database files to this account only.
+#[cfg(unix)]
+fn check_permissions(dir: &Path) -> IsolationFinding {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    // Whoever created the newest file here is this process's account.
+    let probe = dir.join(".openclaw-owner-check");
+    let me = std::fs::write(&probe, b"").and_then(|_| std::fs::metadata(&probe)).map(|m| m.uid());
+    let _ = std::fs::remove_file(&probe);
+    let Ok(me) = me else {
+        return finding("permissions", "warning", format!("Couldn't write to {}", dir.display()));
+    };
+
+    let mut targets = vec![(dir.to_path_buf(), 0o700)];
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let file = dir.join(format!("{DATABASE_FILE}{suffix}"));
+        if file.exists() {
+            targets.push((file, 0o600));
+        }
+    }
+    let mut repaired = Vec::new();
+    for (path, mode) in targets {
+        let Ok(meta) = std::fs::metadata(&path) else { continue };
+        if meta.uid() != me {
+            return finding("permissions", "warning", format!(
+                "{} belongs to another account; its data may be shared between accounts", path.display(),
+            ));
+        }
+        if meta.permissions().mode() & 0o077 != 0 {
+            if std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).is_err() {
+                return finding("permissions", "warning", format!("Other accounts can read {}", path.display()));
+            }
+            repaired.push(path.display().to_string());
+        }
+    }
+    match repaired.is_empty() {
+        true => finding("permissions", "ok", "Only your account can read the data"),
+        false => finding("permissions", "repaired", format!("Made private to your account: {}", repaired.join(", "))),
+    }
+}
+
+/// Windows keeps the default data folder private to the account; other folders inherit
+/// whatever access their parent grants, which the location check already reports.
+#[cfg(not(unix))]
+fn check_permissions(_dir: &Path) -> IsolationFinding {
+    finding("permissions", "ok", "Access follows the folder's Windows permissions")
+}
+
+/// Data created under one account and opened from another, e.g. a copied profile or a data
+/// folder on a shared drive.
+fn check_owner(conn: &Connection) -> Result<IsolationFinding, String> {
+    let me = os_user();
+    if me.is_empty() {
+        return Ok(finding("owner", "ok", "The OS account name isn't available"));
+    }
+    Ok(match read_setting(conn, OWNER_SETTING)? {
+        Some(owner) if owner != me => finding("owner", "warning", format!(
+            "This data was created by the account \"{owner}\" and is now opened by \"{me}\"",
+        )),
+        Some(_) => finding("owner", "ok", format!("Data belongs to \"{me}\"")),
+        None => {
+            write_setting(conn, OWNER_SETTING, &me)?;
+            finding("owner", "ok", format!("Data belongs to \"{me}\""))
+        }
+    })
+}
+
+/// Runs the audit at startup, repairing what it safely can, and keeps the findings.
+/// Secrets need no check: the OS keychain is already separate for each account.
+pub fn audit(conn: &Connection, state: &IsolationAudit) {
+    let dir = datadir::current();
+    let mut findings = vec![check_location(&dir), check_permissions(&dir)];
+    findings.push(check_owner(conn).unwrap_or_else(|e| finding("owner", "warning", e)));
+    findings.push(finding("secrets", "ok", "Passwords are kept in your account's own system keychain"));
+    if let Ok(mut stored) = state.0.lock() {
+        *stored = findings;
+    }
+}
+
+// ─── Isolation Commands ───
+
+/// Findings of the startup audit of how well this account's data is kept from other accounts.
+#[tauri::command]
+pub fn get_isolation_report(state: State<IsolationAudit>) -> Vec<IsolationFinding> {
+    state.0.lock().map(|f| f.clone()).unwrap_or_default()
+}
@@ -1,10 +1,95 @@
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 use uuid::Uuid;
 use chrono::Utc;
 
+mod accessibility;
+mod activity;
+mod api;
+mod appearance;
+mod archive;
+mod artifacts;
+mod audit;
+mod blackout;
+mod builder;
+mod bulk;
+mod calendar;
+mod cache;
+mod capabilities;
+mod changes;
+mod companion;
+mod connections;
+mod context;
+mod crashes;
+mod critique;
+mod crypto;
+mod datadir;
+mod datasources;
+mod diagnostics;
+mod discovery;
+mod documents;
+mod downloads;
+mod executor;
+mod feedback;
+mod fixtures;
+mod focus;
+mod folders;
+mod format;
+mod grace;
+mod improvements;
+mod indexer;
+mod injection;
+mod isolation;
+mod llm;
+mod maintenance;
+mod meetings;
+mod net;
+mod notifications;
+mod notes;
+mod palette;
+mod persona;
+mod pii;
+mod policy;
+mod power;
+mod priority;
+mod profiles;
+mod qr;
+mod rag;
+mod rates;
+mod receipts;
+mod recorder;
+mod regression;
+mod review;
+mod routing;
+mod sanitize;
+mod schedules;
+mod secure_notes;
+mod security;
+mod sensitivity;
+mod storage;
+mod supervision;
+mod structured;
+mod sharing;
+mod shutdown;
+mod stats;
+mod sync;
+mod templates;
+mod tokens;
+mod tool_calling;
+mod tools;
+mod totp;
+mod transcribe;
+mod transcript;
+mod translate;
+mod usage;
+mod variables;
+mod vision;
+mod windows;
+mod transfer;
+mod workflows;
+
 pub struct DbState(pub Mutex<Connection>);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,6 +103,16 @@ pub struct Agent {
     pub config_json: String,
     pub sandbox: bool,
     pub created_at: String,
+    /// Kept at the top of the list on this device.
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub run_count: i64,
+    /// Empty when the agent has never run.
+    #[serde(default)]
+    pub last_run_at: String,
+    #[serde(default)]
+    pub folder_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,6 +124,9 @@ pub struct ExecutionLog {
     pub output: String,
     pub error: String,
     pub created_at: String,
+    /// The user's note on this entry, if any.
+    #[serde(default)]
+    pub note: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,6 +137,14 @@ pub struct ApprovalItem {
     pub content_preview: String,
     pub status: String,
     pub created_at: String,
+    pub run_id: String,
+    /// `low`, `normal`, `high` or `urgent`.
+    pub priority: String,
+    /// Times it was raised for sitting unanswered.
+    pub escalations: i64,
+    /// `you` for decisions made in the app, else who answered away from it: the approver a
+    /// routed link went to, or a paired phone.
+    pub decided_by: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -47,7 +153,19 @@ pub struct Setting {
     pub value: String,
 }
 
-fn init_db(conn: &Connection) {
+/// Adds `column` to `table` unless it is already there.
+pub(crate) fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .any(|name| name.map(|n| n == column).unwrap_or(false));
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn init_db(conn: &Connection) {
     conn.execute_batch("
         CREATE TABLE IF NOT EXISTS agents (
             id TEXT PRIMARY KEY,
@@ -91,13 +209,64 @@ fn init_db(conn: &Connection) {
             next_run TEXT DEFAULT ''
         );
     ").expect("Failed to initialize database");
+
+    // Columns added after the first release; existing databases get them on startup.
+    ensure_column(conn, "approval_queue", "run_id", "TEXT DEFAULT ''").expect("Failed to migrate database");
+    ensure_column(conn, "approval_queue", "step_index", "INTEGER DEFAULT -1").expect("Failed to migrate database");
+    ensure_column(conn, "approval_queue", "decided_by", "TEXT DEFAULT ''").expect("Failed to migrate database");
+    ensure_column(conn, "approval_queue", "decided_at", "TEXT DEFAULT ''").expect("Failed to migrate database");
+    ensure_column(conn, "agents", "updated_at", "TEXT DEFAULT ''").expect("Failed to migrate database");
+    ensure_column(conn, "agents", "pinned", "INTEGER DEFAULT 0").expect("Failed to migrate database");
+    ensure_column(conn, "agents", "folder_id", "TEXT").expect("Failed to migrate database");
+    ensure_column(conn, "settings", "updated_at", "TEXT DEFAULT ''").expect("Failed to migrate database");
+    ensure_column(conn, "schedules", "updated_at", "TEXT DEFAULT ''").expect("Failed to migrate database");
+
+    workflows::init_tables(conn);
+    llm::init_tables(conn);
+    builder::init_tables(conn);
+    executor::init_tables(conn);
+    fixtures::init_tables(conn);
+    templates::init_tables(conn);
+    sync::init_tables(conn);
+    api::init_tables(conn);
+    audit::init_tables(conn);
+    cache::init_tables(conn);
+    rag::init_tables(conn);
+    artifacts::init_tables(conn);
+    persona::init_tables(conn);
+    usage::init_tables(conn);
+    palette::init_tables(conn);
+    folders::init_tables(conn);
+    notes::init_tables(conn);
+    feedback::init_tables(conn);
+    improvements::init_tables(conn);
+    stats::init_tables(conn);
+    vision::init_tables(conn);
+    blackout::init_tables(conn);
+    calendar::init_tables(conn);
+    profiles::init_tables(conn);
+    grace::init_tables(conn);
+    activity::init_tables(conn);
+    review::init_tables(conn);
+    priority::init_tables(conn);
+    routing::init_tables(conn);
+    companion::init_tables(conn);
+    power::init_tables(conn);
+    changes::init_tables(conn);
+    downloads::init_tables(conn);
+    documents::init_tables(conn);
+    rates::init_tables(conn);
+    datasources::init_tables(conn);
+    secure_notes::init_tables(conn);
+    totp::init_tables(conn);
+    connections::init_tables(conn);
 }
 
 // ─── Agent CRUD ───
 
-#[tauri::command]
-fn create_agent(
-    db: State<DbState>,
+/// Inserts an agent row; shared by `create_agent` and the other agent-producing commands.
+pub(crate) fn insert_agent(
+    conn: &Connection,
     name: String,
     role: String,
     goal: String,
@@ -105,7 +274,6 @@ fn create_agent(
     schedule: String,
     sandbox: bool,
 ) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
     let config = serde_json::json!({
@@ -118,31 +286,125 @@ fn create_agent(
     }).to_string();
 
     conn.execute(
-        "INSERT INTO agents (id, name, role, goal, tools, schedule, config_json, sandbox, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        "INSERT INTO agents (id, name, role, goal, tools, schedule, config_json, sandbox, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
         params![id, name, role, goal, tools, schedule, config, sandbox as i32, now],
     ).map_err(|e| e.to_string())?;
 
-    Ok(Agent { id, name, role, goal, tools, schedule, config_json: config, sandbox, created_at: now })
+    Ok(Agent {
+        id, name, role, goal, tools, schedule, config_json: config, sandbox, created_at: now,
+        pinned: false, run_count: 0, last_run_at: String::new(), folder_id: None,
+    })
 }
 
 #[tauri::command]
-fn list_agents(db: State<DbState>) -> Result<Vec<Agent>, String> {
+fn create_agent(
+    db: State<DbState>,
+    name: String,
+    role: String,
+    goal: String,
+    tools: String,
+    schedule: String,
+    sandbox: bool,
+) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn.prepare("SELECT id, name, role, goal, tools, schedule, config_json, sandbox, created_at FROM agents ORDER BY created_at DESC")
+    insert_agent(&conn, name, role, goal, tools, schedule, sandbox)
+}
+
+/// Replaces an agent's fields, keeping its id, runs and logs. The same fields in
+/// `config_json` are updated to match; everything else in it is left alone.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn update_agent(
+    db: State<DbState>,
+    id: String,
+    name: String,
+    role: String,
+    goal: String,
+    tools: String,
+    schedule: String,
+    sandbox: bool,
+) -> Result<Agent, String> {
+    if name.trim().is_empty() {
+        return Err("Give the agent a name".into());
+    }
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    update_agent_config(&tx, &id, |config| {
+        config.insert("name".into(), serde_json::json!(name));
+        config.insert("role".into(), serde_json::json!(role));
+        config.insert("goal".into(), serde_json::json!(goal));
+        config.insert("tools".into(), serde_json::json!(tools));
+        config.insert("schedule".into(), serde_json::json!(schedule));
+        config.insert("sandbox".into(), serde_json::json!(sandbox));
+    })?;
+    tx.execute(
+        "UPDATE agents SET name = ?1, role = ?2, goal = ?3, tools = ?4, schedule = ?5, sandbox = ?6 WHERE id = ?7",
+        params![name, role, goal, tools, schedule, sandbox as i32, id],
+    ).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    conn.query_row(&format!("SELECT {AGENT_COLUMNS} FROM agents WHERE id = ?1"), params![id], row_to_agent)
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) const AGENT_COLUMNS: &str = "id, name, role, goal, tools, schedule, config_json, sandbox, created_at, pinned,
+    (SELECT COUNT(*) FROM runs WHERE runs.agent_id = agents.id),
+    COALESCE((SELECT MAX(started_at) FROM runs WHERE runs.agent_id = agents.id), ''), folder_id";
+
+pub(crate) fn row_to_agent(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+    Ok(Agent {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        role: row.get(2)?,
+        goal: row.get(3)?,
+        tools: row.get(4)?,
+        schedule: row.get(5)?,
+        config_json: row.get(6)?,
+        sandbox: row.get::<_, i32>(7)? != 0,
+        created_at: row.get(8)?,
+        pinned: row.get::<_, i32>(9)? != 0,
+        run_count: row.get(10)?,
+        last_run_at: row.get(11)?,
+        folder_id: row.get(12)?,
+    })
+}
+
+/// Applies `change` to an agent's `config_json` object and bumps `updated_at` so it syncs.
+pub(crate) fn update_agent_config(
+    conn: &Connection,
+    agent_id: &str,
+    change: impl FnOnce(&mut serde_json::Map<String, serde_json::Value>),
+) -> Result<(), String> {
+    let config: String = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).map_err(|_| "Agent not found".to_string())?;
+    let mut config = match serde_json::from_str(&config) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    change(&mut config);
+    conn.execute(
+        "UPDATE agents SET config_json = ?1, updated_at = ?2 WHERE id = ?3",
+        params![serde_json::Value::Object(config).to_string(), Utc::now().to_rfc3339(), agent_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Agents newest first, or by `sort`: `recent` (last run), `most_used` (run count) or
+/// `pinned` (pinned ones first).
+pub(crate) fn query_agents(conn: &Connection, sort: Option<&str>) -> Result<Vec<Agent>, String> {
+    // Columns 10-12 of AGENT_COLUMNS: pinned, run count, last run.
+    let order = match sort.unwrap_or("created") {
+        "created" => "created_at DESC",
+        "recent" => "12 DESC, created_at DESC",
+        "most_used" => "11 DESC, 12 DESC",
+        "pinned" => "pinned DESC, 12 DESC, created_at DESC",
+        other => return Err(format!("Unknown sort: {other}")),
+    };
+    let mut stmt = conn.prepare(&format!("SELECT {AGENT_COLUMNS} FROM agents ORDER BY {order}"))
         .map_err(|e| e.to_string())?;
-    let rows = stmt.query_map([], |row| {
-        Ok(Agent {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            role: row.get(2)?,
-            goal: row.get(3)?,
-            tools: row.get(4)?,
-            schedule: row.get(5)?,
-            config_json: row.get(6)?,
-            sandbox: row.get::<_, i32>(7)? != 0,
-            created_at: row.get(8)?,
-        })
-    }).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_agent).map_err(|e| e.to_string())?;
 
     let mut agents = Vec::new();
     for row in rows {
@@ -152,17 +414,72 @@ fn list_agents(db: State<DbState>) -> Result<Vec<Agent>, String> {
 }
 
 #[tauri::command]
-fn delete_agent(db: State<DbState>, id: String) -> Result<(), String> {
+fn list_agents(db: State<DbState>, sort: Option<String>) -> Result<Vec<Agent>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
+    query_agents(&conn, sort.as_deref())
+}
+
+fn set_pinned(conn: &Connection, id: &str, pinned: bool) -> Result<(), String> {
+    let updated = conn.execute("UPDATE agents SET pinned = ?1 WHERE id = ?2", params![pinned as i32, id])
         .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("Agent not found".into());
+    }
     Ok(())
 }
 
+#[tauri::command]
+fn pin_agent(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_pinned(&conn, &id, true)
+}
+
+#[tauri::command]
+fn unpin_agent(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_pinned(&conn, &id, false)
+}
+
+#[tauri::command]
+fn delete_agent(db: State<DbState>, id: String) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    delete_agent_rows(&tx, &id)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Deletes an agent and everything kept for it alone. Callers run it in a transaction so a
+/// failed step leaves the agent whole.
+pub(crate) fn delete_agent_rows(conn: &Connection, id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    sync::record_deletion(conn, "agent", id)?;
+    artifacts::remove_orphans(conn)?;
+    blackout::remove_agent(conn, id)?;
+    schedules::remove_agent(conn, id)
+}
+
 // ─── Execution Logs ───
 
+pub(crate) fn insert_log(
+    conn: &Connection,
+    agent_id: &str,
+    action: &str,
+    status: &str,
+    output: &str,
+    error: &str,
+) -> Result<(), String> {
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO execution_logs (agent_id, action, status, output, error, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![agent_id, action, status, output, error, now],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 #[tauri::command]
 fn add_log(
+    app: AppHandle,
     db: State<DbState>,
     agent_id: String,
     action: String,
@@ -171,19 +488,24 @@ fn add_log(
     error: String,
 ) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let now = Utc::now().to_rfc3339();
-    conn.execute(
-        "INSERT INTO execution_logs (agent_id, action, status, output, error, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![agent_id, action, status, output, error, now],
-    ).map_err(|e| e.to_string())?;
+    insert_log(&conn, &agent_id, &action, &status, &output, &error)?;
+    windows::broadcast(&app, "logs-changed", &agent_id);
     Ok(())
 }
 
 #[tauri::command]
 fn get_logs(db: State<DbState>, limit: Option<i64>) -> Result<Vec<ExecutionLog>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let lim = limit.unwrap_or(100);
-    let mut stmt = conn.prepare("SELECT id, agent_id, action, status, output, error, created_at FROM execution_logs ORDER BY created_at DESC LIMIT ?1")
+    let mut logs = query_logs(&conn, limit.unwrap_or(100))?;
+    let mode = sanitize::mode(&conn);
+    logs.iter_mut().for_each(|log| sanitize::log(log, mode));
+    Ok(logs)
+}
+
+pub(crate) fn query_logs(conn: &Connection, lim: i64) -> Result<Vec<ExecutionLog>, String> {
+    let mut stmt = conn.prepare("SELECT id, agent_id, action, status, output, error, created_at,
+            COALESCE((SELECT note FROM run_notes WHERE kind = 'log' AND target = CAST(execution_logs.id AS TEXT)), '')
+         FROM execution_logs ORDER BY created_at DESC LIMIT ?1")
         .map_err(|e| e.to_string())?;
     let rows = stmt.query_map(params![lim], |row| {
         Ok(ExecutionLog {
@@ -194,6 +516,7 @@ fn get_logs(db: State<DbState>, limit: Option<i64>) -> Result<Vec<ExecutionLog>,
             output: row.get(4)?,
             error: row.get(5)?,
             created_at: row.get(6)?,
+            note: row.get(7)?,
         })
     }).map_err(|e| e.to_string())?;
 
@@ -206,9 +529,21 @@ fn get_logs(db: State<DbState>, limit: Option<i64>) -> Result<Vec<ExecutionLog>,
 
 // ─── Settings ───
 
-#[tauri::command]
-fn get_setting(db: State<DbState>, key: String) -> Result<Option<String>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+/// Stores a setting and stamps it so sync can tell which device changed it last. Settings
+/// pinned by a managed policy can't be changed.
+pub(crate) fn write_setting(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+    policy::check_setting_unlocked(key)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+        params![key, value, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub(crate) fn read_setting(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    if let Some(pinned) = policy::pinned_setting(key) {
+        return Ok(Some(pinned));
+    }
     let result = conn.query_row(
         "SELECT value FROM settings WHERE key = ?1",
         params![key],
@@ -221,58 +556,89 @@ fn get_setting(db: State<DbState>, key: String) -> Result<Option<String>, String
     }
 }
 
+#[tauri::command]
+fn get_setting(db: State<DbState>, key: String) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    read_setting(&conn, &key)
+}
+
 #[tauri::command]
 fn set_setting(db: State<DbState>, key: String, value: String) -> Result<(), String> {
+    if security::is_protected_setting(&key) {
+        return Err("This setting can only be changed from the lock settings".into());
+    }
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    conn.execute(
-        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
-        params![key, value],
-    ).map_err(|e| e.to_string())?;
+    write_setting(&conn, &key, &value)?;
+    connections::setting_changed(&conn, &key);
     Ok(())
 }
 
 #[tauri::command]
 fn delete_setting(db: State<DbState>, key: String) -> Result<(), String> {
+    if security::is_protected_setting(&key) {
+        return Err("This setting can only be changed from the lock settings".into());
+    }
+    policy::check_setting_unlocked(&key)?;
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     conn.execute("DELETE FROM settings WHERE key = ?1", params![key])
         .map_err(|e| e.to_string())?;
+    sync::record_deletion(&conn, "setting", &key)?;
+    connections::setting_changed(&conn, &key);
     Ok(())
 }
 
 // ─── Approval Queue ───
 
-#[tauri::command]
-fn add_approval(
-    db: State<DbState>,
+/// Queues an approval; `run_id`/`step_index` link it to a paused run step, if any.
+pub(crate) fn insert_approval(
+    conn: &Connection,
     agent_id: String,
     action_type: String,
     content_preview: String,
+    run_id: String,
+    step_index: i64,
+    priority: String,
 ) -> Result<ApprovalItem, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
     conn.execute(
-        "INSERT INTO approval_queue (id, agent_id, action_type, content_preview, status, created_at) VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
-        params![id, agent_id, action_type, content_preview, now],
+        "INSERT INTO approval_queue (id, agent_id, action_type, content_preview, status, created_at, run_id, step_index, priority) VALUES (?1, ?2, ?3, ?4, 'pending', ?5, ?6, ?7, ?8)",
+        params![id, agent_id, action_type, content_preview, now, run_id, step_index, priority],
     ).map_err(|e| e.to_string())?;
-    Ok(ApprovalItem { id, agent_id, action_type, content_preview, status: "pending".into(), created_at: now })
+    Ok(ApprovalItem { id, agent_id, action_type, content_preview, status: "pending".into(), created_at: now, run_id, priority, escalations: 0, decided_by: String::new() })
 }
 
 #[tauri::command]
-fn update_approval(db: State<DbState>, id: String, status: String) -> Result<(), String> {
+fn add_approval(
+    app: AppHandle,
+    db: State<DbState>,
+    agent_id: String,
+    action_type: String,
+    content_preview: String,
+) -> Result<ApprovalItem, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let item = insert_approval(&conn, agent_id, action_type, content_preview, String::new(), -1, "normal".into())?;
+    routing::dispatch(&app, &conn, &item);
+    windows::broadcast(&app, "approvals-changed", &item.id);
+    Ok(item)
+}
+
+#[tauri::command]
+fn update_approval(app: AppHandle, db: State<DbState>, id: String, status: String) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     conn.execute(
-        "UPDATE approval_queue SET status = ?1 WHERE id = ?2",
-        params![status, id],
+        "UPDATE approval_queue SET status = ?1, decided_by = 'you', decided_at = ?2 WHERE id = ?3",
+        params![status, Utc::now().to_rfc3339(), id],
     ).map_err(|e| e.to_string())?;
+    windows::broadcast(&app, "approvals-changed", &id);
     Ok(())
 }
 
-#[tauri::command]
-fn get_approvals(db: State<DbState>) -> Result<Vec<ApprovalItem>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn.prepare("SELECT id, agent_id, action_type, content_preview, status, created_at FROM approval_queue ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
+pub(crate) fn query_approvals(conn: &Connection) -> Result<Vec<ApprovalItem>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, action_type, content_preview, status, created_at, run_id, COALESCE(priority, 'normal'), COALESCE(escalations, 0), COALESCE(decided_by, '')
+         FROM approval_queue ORDER BY created_at DESC"
+    ).map_err(|e| e.to_string())?;
     let rows = stmt.query_map([], |row| {
         Ok(ApprovalItem {
             id: row.get(0)?,
@@ -281,36 +647,103 @@ fn get_approvals(db: State<DbState>) -> Result<Vec<ApprovalItem>, String> {
             content_preview: row.get(3)?,
             status: row.get(4)?,
             created_at: row.get(5)?,
+            run_id: row.get(6)?,
+            priority: row.get(7)?,
+            escalations: row.get(8)?,
+            decided_by: row.get(9)?,
         })
     }).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
 
-    let mut items = Vec::new();
-    for row in rows {
-        items.push(row.map_err(|e| e.to_string())?);
-    }
+/// The queue, pending items first by priority unless `order` says otherwise; see
+/// `priority::sort` for `order` and `group_by`.
+#[tauri::command]
+fn get_approvals(db: State<DbState>, order: Option<String>, group_by: Option<String>) -> Result<Vec<ApprovalItem>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut items = query_approvals(&conn)?;
+    let mode = sanitize::mode(&conn);
+    items.iter_mut().for_each(|item| sanitize::approval(item, mode));
+    priority::sort(&mut items, order.as_deref(), group_by.as_deref())?;
     Ok(items)
 }
 
 // ─── App Entry ───
 
+/// Folder holding the database and everything else the app stores on disk.
+pub(crate) fn app_data_dir() -> std::path::PathBuf {
+    datadir::current()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let app_dir = dirs_next::data_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("openclaw-desktop");
+    crashes::install();
+    let app_dir = app_data_dir();
     std::fs::create_dir_all(&app_dir).ok();
-    let db_path = app_dir.join("openclaw.db");
+    let db_path = app_dir.join(datadir::DATABASE_FILE);
 
     let conn = Connection::open(&db_path).expect("Failed to open database");
     init_db(&conn);
+    shutdown::recover(&conn);
+    let _ = policy::enforce_retention(&conn);
+    let lock = security::initial_lock(&conn);
+    let isolation = isolation::IsolationAudit::default();
+    isolation::audit(&conn, &isolation);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .manage(DbState(Mutex::new(conn)))
-        .invoke_handler(tauri::generate_handler![
+        .manage(lock)
+        .manage(indexer::Indexer::default())
+        .manage(windows::Subscriptions::default())
+        .manage(accessibility::Accessibility::default())
+        .manage(recorder::Recorder::default())
+        .manage(profiles::NetworkState::default())
+        .manage(companion::Companion::default())
+        .manage(shutdown::Shutdown::default())
+        .manage(power::Power::default())
+        .manage(isolation)
+        .setup(|app| {
+            // The main window starts hidden so it appears where it was left rather than jumping there.
+            if let Some(main) = app.get_webview_window("main") {
+                windows::restore_state(&main);
+                main.show()?;
+            }
+            api::start(app.handle().clone());
+            indexer::start(app.handle().clone());
+            accessibility::start(app.handle().clone());
+            maintenance::start(app.handle().clone());
+            usage::start(app.handle().clone());
+            blackout::start(app.handle().clone());
+            schedules::start(app.handle().clone());
+            calendar::start(app.handle().clone());
+            profiles::start(app.handle().clone());
+            grace::start(app.handle().clone());
+            activity::start(app.handle().clone());
+            focus::start(app.handle().clone());
+            review::start(app.handle().clone());
+            priority::start(app.handle().clone());
+            companion::start(app.handle().clone());
+            discovery::start(app.handle().clone());
+            power::start(app.handle().clone());
+            rates::start(app.handle().clone());
+            Ok(())
+        })
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Focused(focused) => window.state::<indexer::Indexer>().set_user_active(*focused),
+            tauri::WindowEvent::CloseRequested { .. } => windows::save_state(window),
+            tauri::WindowEvent::Destroyed => windows::forget(window),
+            tauri::WindowEvent::ThemeChanged(_) if window.label() == "main" => appearance::os_theme_changed(window.app_handle()),
+            _ => {}
+        })
+        // Origin, window and lock checks run before any command sees its arguments.
+        .invoke_handler(security::guard(tauri::generate_handler![
             create_agent,
+            update_agent,
             list_agents,
+            pin_agent,
+            unpin_agent,
             delete_agent,
             add_log,
             get_logs,
@@ -320,7 +753,258 @@ pub fn run() {
             add_approval,
             update_approval,
             get_approvals,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            workflows::create_fragment,
+            workflows::update_fragment,
+            workflows::list_fragments,
+            workflows::get_fragment,
+            workflows::list_fragment_versions,
+            workflows::get_fragment_usages,
+            workflows::delete_fragment,
+            workflows::set_agent_workflow,
+            workflows::get_agent_workflow,
+            tools::list_tools,
+            builder::draft_agent_from_description,
+            builder::save_agent_draft,
+            builder::promote_run_to_agent,
+            builder::start_agent_draft,
+            builder::answer_draft_questions,
+            builder::get_draft_session,
+            builder::list_draft_sessions,
+            builder::discard_draft_session,
+            executor::run_agent,
+            executor::run_quick_task,
+            executor::resume_run,
+            executor::get_run,
+            executor::list_runs,
+            regression::set_golden_run,
+            regression::test_agent,
+            fixtures::create_fixture_set,
+            fixtures::list_fixture_sets,
+            fixtures::load_fixture_set,
+            fixtures::unload_fixture_set,
+            fixtures::delete_fixture_set,
+            sharing::share_agent,
+            sharing::import_shared_agent,
+            templates::get_template_catalog_status,
+            templates::sync_template_catalog,
+            templates::search_templates,
+            templates::install_template,
+            sync::get_sync_config,
+            sync::set_sync_config,
+            sync::sync_now,
+            transfer::export_agents,
+            transfer::import_agents,
+            api::mint_api_token,
+            api::list_api_tokens,
+            api::revoke_api_token,
+            security::get_lock_status,
+            security::lock_app,
+            security::unlock_app,
+            security::set_app_lock_pin,
+            security::factory_reset,
+            audit::list_audit_events,
+            audit::export_audit_log,
+            audit::verify_audit_export,
+            pii::set_agent_pii_policy,
+            pii::scan_pii,
+            net::get_network_mode,
+            net::set_local_only_mode,
+            net::get_proxy_settings,
+            net::set_proxy_settings,
+            cache::get_cache_stats,
+            cache::clear_cache,
+            cache::set_agent_cache_enabled,
+            rag::list_knowledge_sources,
+            rag::add_knowledge_source,
+            rag::remove_knowledge_source,
+            indexer::index_knowledge,
+            indexer::pause_indexing,
+            indexer::resume_indexing,
+            rag::get_index_status,
+            rag::search_knowledge,
+            artifacts::list_run_artifacts,
+            artifacts::get_artifact,
+            artifacts::find_orphaned_artifacts,
+            tokens::count_tokens,
+            tool_calling::set_agent_tool_calling,
+            persona::list_personas,
+            persona::save_persona,
+            persona::delete_persona,
+            persona::get_agent_persona,
+            persona::set_agent_persona,
+            persona::preview_agent_prompt,
+            usage::estimate_run_cost,
+            usage::list_budgets,
+            usage::set_budget,
+            usage::get_monthly_usage,
+            usage::export_usage_report,
+            palette::search_actions,
+            palette::record_palette_action,
+            folders::list_folders,
+            folders::create_folder,
+            folders::rename_folder,
+            folders::move_folder,
+            folders::delete_folder,
+            folders::set_agent_folder,
+            folders::list_agents_by_folder,
+            bulk::bulk_agent_operation,
+            notes::annotate_run,
+            notes::annotate_log,
+            notes::search_notes,
+            transcript::export_run_transcript,
+            feedback::submit_feedback,
+            feedback::clear_feedback,
+            feedback::get_feedback,
+            feedback::list_agent_feedback,
+            feedback::get_agent_feedback,
+            improvements::suggest_agent_improvements,
+            improvements::list_agent_suggestions,
+            improvements::apply_agent_suggestion,
+            improvements::dismiss_agent_suggestion,
+            stats::set_agent_time_saved,
+            stats::get_dashboard_stats,
+            windows::open_window,
+            windows::subscribe_window_events,
+            windows::set_last_view,
+            windows::get_main_window_state,
+            appearance::get_appearance,
+            appearance::set_appearance,
+            accessibility::get_accessibility,
+            format::get_locale,
+            format::format_times,
+            format::format_numbers,
+            maintenance::run_db_maintenance,
+            maintenance::get_db_maintenance_status,
+            storage::get_storage_report,
+            storage::clean_storage,
+            datadir::get_data_directory,
+            datadir::set_data_directory,
+            datadir::get_portable_mode,
+            isolation::get_isolation_report,
+            supervision::get_supervisor_status,
+            supervision::set_supervisor_pin,
+            supervision::set_supervisor_policy,
+            supervision::suspend_supervision,
+            policy::get_effective_policy,
+            sensitivity::set_agent_sensitivity,
+            sensitivity::list_agent_sensitivity,
+            capabilities::get_model_capabilities,
+            capabilities::set_model_capabilities,
+            vision::attach_image,
+            vision::get_image_attachment,
+            vision::delete_image_attachment,
+            transcribe::transcribe_audio,
+            meetings::summarize_meeting,
+            recorder::start_recording,
+            recorder::stop_recording,
+            recorder::get_recording_status,
+            blackout::list_blackout_windows,
+            blackout::add_blackout_window,
+            blackout::delete_blackout_window,
+            blackout::set_agent_catch_up,
+            blackout::get_blackout_status,
+            blackout::list_deferred_runs,
+            blackout::cancel_deferred_run,
+            calendar::get_calendar_status,
+            calendar::set_calendar_sources,
+            calendar::refresh_calendar,
+            calendar::list_calendar_events,
+            calendar::set_agent_event_filter,
+            profiles::get_current_profile,
+            profiles::list_network_profiles,
+            profiles::save_network_profile,
+            profiles::delete_network_profile,
+            profiles::set_agent_profiles,
+            grace::list_pending_actions,
+            grace::cancel_pending_action,
+            grace::reschedule_pending_action,
+            variables::list_context_providers,
+            variables::set_agent_context_providers,
+            activity::get_activity_settings,
+            activity::set_activity_settings,
+            activity::get_app_usage,
+            activity::clear_app_usage,
+            activity::set_agent_app_trigger,
+            focus::get_focus_blocklist,
+            focus::set_focus_blocklist,
+            focus::get_focus_status,
+            focus::start_focus_session,
+            focus::stop_focus_session,
+            focus::emergency_unlock_focus,
+            review::start_week_review,
+            review::get_week_review,
+            review::list_week_reviews,
+            review::set_week_review_step,
+            review::decide_review_suggestion,
+            priority::get_approval_escalation,
+            priority::set_approval_escalation,
+            priority::set_agent_approval_priority,
+            priority::set_approval_priority,
+            routing::list_approval_routes,
+            routing::save_approval_route,
+            routing::delete_approval_route,
+            companion::start_companion_pairing,
+            companion::get_companion_status,
+            companion::revoke_companion_device,
+            companion::stop_companion,
+            discovery::get_discovery_settings,
+            discovery::set_discovery_settings,
+            archive::export_everything,
+            archive::inspect_archive,
+            archive::import_everything,
+            diagnostics::export_anonymized_diagnostics,
+            crashes::list_crash_reports,
+            crashes::delete_crash_report,
+            crashes::get_crash_upload_settings,
+            crashes::set_crash_upload_settings,
+            crashes::preview_crash_upload,
+            crashes::upload_crash_report,
+            power::list_suspensions,
+            notifications::get_notification_settings,
+            notifications::set_notification_settings,
+            notifications::get_agent_notifications,
+            notifications::set_agent_notifications,
+            downloads::list_download_rules,
+            downloads::save_download_rule,
+            downloads::delete_download_rule,
+            downloads::reorder_download_rules,
+            downloads::test_download_rules,
+            documents::list_document_templates,
+            documents::save_document_template,
+            documents::delete_document_template,
+            documents::preview_document,
+            receipts::process_receipt,
+            rates::get_currency_rates,
+            rates::refresh_currency_rates,
+            translate::translate_text,
+            datasources::list_data_sources,
+            datasources::save_data_source,
+            datasources::delete_data_source,
+            datasources::describe_data_source,
+            datasources::query_data_source,
+            secure_notes::list_secure_notes,
+            secure_notes::save_secure_note,
+            secure_notes::reveal_secure_note,
+            secure_notes::delete_secure_note,
+            secure_notes::generate_password,
+            totp::list_totp_accounts,
+            totp::add_totp_account,
+            totp::delete_totp_account,
+            totp::get_totp_code,
+            connections::list_connections,
+            connections::test_connection,
+            connections::disconnect_connection,
+            schedules::list_schedules,
+            schedules::create_schedule,
+            schedules::toggle_schedule,
+            schedules::delete_schedule,
+        ]))
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown::run(app);
+                crashes::clean_exit();
+            }
+        });
 }
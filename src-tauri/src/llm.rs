@@ -0,0 +1,623 @@
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+
+use crate::audit;
+use crate::cache;
+use crate::capabilities;
+use crate::net;
+use crate::policy;
+use crate::sensitivity;
+use crate::tokens;
+use crate::vision::Image;
+use crate::{DbState, ensure_column, read_setting};
+
+const DEFAULT_LOCAL_URL: &str = "http://127.0.0.1:11434";
+const LOCAL_URL_SETTING: &str = "llm_local_url";
+/// The local model server runs on this computer, or one only it can reach.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[LOCAL_URL_SETTING];
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    /// `user`, `assistant`, or `tool` for the result of a tool call.
+    pub role: String,
+    pub content: String,
+    /// Tools an assistant message asked to call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// The call a `tool` message answers.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tool_call_id: String,
+    /// Images shown to the model with a user message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<Image>,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        ChatMessage { role: role.into(), content, tool_calls: Vec::new(), tool_call_id: String::new(), images: Vec::new() }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content.into())
+    }
+
+    pub fn user_with_images(content: impl Into<String>, images: Vec<Image>) -> Self {
+        ChatMessage { images, ..Self::user(content) }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content.into())
+    }
+
+    pub fn tool_call(content: impl Into<String>, call: ToolCall) -> Self {
+        ChatMessage { tool_calls: vec![call], ..Self::new("assistant", content.into()) }
+    }
+
+    pub fn tool_result(call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        ChatMessage { tool_call_id: call_id.into(), ..Self::new("tool", content.into()) }
+    }
+}
+
+/// A tool offered to the model, described the way the tool registry describes it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolDef {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments.
+    pub parameters: Value,
+}
+
+/// A tool the model chose to call.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LlmRequest {
+    pub system: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+    /// Ask the provider for a JSON object response where it supports it.
+    pub json: bool,
+    /// Tools the model may call instead of answering.
+    pub tools: Vec<ToolDef>,
+    /// Another model from the active provider, e.g. a reviewer; `None` uses the configured one.
+    pub model: Option<String>,
+    pub agent_id: String,
+    pub run_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LlmResponse {
+    pub text: String,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// Served by the fallback provider because the primary one failed.
+    #[serde(default)]
+    pub fallback: bool,
+}
+
+/// Provider settings as saved by the Settings panel (`llm_provider`, `llm_api_key`, `llm_model`).
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub provider: String,
+    pub api_key: String,
+    pub model: String,
+    pub base_url: String,
+    pub proxy: net::Proxy,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS llm_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id TEXT DEFAULT '',
+            run_id TEXT DEFAULT '',
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER DEFAULT 0,
+            output_tokens INTEGER DEFAULT 0,
+            duration_ms INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize llm tables");
+    ensure_column(conn, "llm_usage", "fallback", "INTEGER DEFAULT 0").expect("Failed to migrate llm tables");
+}
+
+fn default_model(provider: &str) -> &'static str {
+    match provider {
+        "openai" => "gpt-4o-mini",
+        "anthropic" => "claude-3-haiku-20240307",
+        _ => "phi3",
+    }
+}
+
+/// How many tokens `cfg.model` accepts in one request, prompt and reply together.
+/// Local models are configured with `llm_local_context_tokens`, since it depends on how
+/// the model server was started; any model's can be overridden in its capabilities.
+pub fn context_window(conn: &Connection, cfg: &ProviderConfig) -> Result<usize, String> {
+    if let Some(window) = capabilities::context_override(conn, cfg)? {
+        return Ok(window);
+    }
+    let model = cfg.model.as_str();
+    Ok(match cfg.provider.as_str() {
+        "anthropic" => 200_000,
+        "openai" if model.starts_with("gpt-3.5") => 16_385,
+        "openai" if model.starts_with("gpt-4-") && !model.starts_with("gpt-4-turbo") => 8_192,
+        "openai" => 128_000,
+        _ => read_setting(conn, "llm_local_context_tokens")?
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(4_096),
+    })
+}
+
+fn local_config(conn: &Connection) -> Result<ProviderConfig, String> {
+    policy::check_provider("local")?;
+    let model = read_setting(conn, "llm_local_model")?.unwrap_or_else(|| default_model("local").into());
+    let base_url = read_setting(conn, LOCAL_URL_SETTING)?.unwrap_or_else(|| DEFAULT_LOCAL_URL.into());
+    Ok(ProviderConfig { provider: "local".into(), api_key: String::new(), model, base_url, proxy: net::proxy(conn)? })
+}
+
+/// The provider for calls made for `agent_id`: confidential agents always get the local model.
+pub fn agent_provider_config(conn: &Connection, agent_id: &str) -> Result<ProviderConfig, String> {
+    match sensitivity::is_confidential(conn, agent_id) {
+        true => local_config(conn),
+        false => provider_config(conn),
+    }
+}
+
+/// Reads the active provider; without a saved API key this is the local (Ollama) model.
+pub fn provider_config(conn: &Connection) -> Result<ProviderConfig, String> {
+    let provider = read_setting(conn, "llm_provider")?.unwrap_or_default();
+    let api_key = read_setting(conn, "llm_api_key")?.unwrap_or_default();
+    if provider.is_empty() || provider == "local" || api_key.is_empty() {
+        return local_config(conn);
+    }
+    remote_config(conn, provider, api_key, read_setting(conn, "llm_model")?)
+}
+
+fn remote_config(conn: &Connection, provider: String, api_key: String, model: Option<String>) -> Result<ProviderConfig, String> {
+    policy::check_provider(&provider)?;
+    let model = model
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| default_model(&provider).into());
+    let default_url = match provider.as_str() {
+        "openai" => "https://api.openai.com/v1",
+        "anthropic" => "https://api.anthropic.com/v1",
+        other => return Err(format!("Unknown LLM provider: {other}")),
+    };
+    // Self-hosted gateways speaking the same API, e.g. `llm_openai_base_url`.
+    let base_url = read_setting(conn, &format!("llm_{provider}_base_url"))?
+        .map(|u| u.trim().trim_end_matches('/').to_string())
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| default_url.into());
+    Ok(ProviderConfig { provider, api_key, model, base_url, proxy: net::proxy(conn)? })
+}
+
+fn post_json(req: ureq::Request, body: Value, provider: &str) -> Result<Value, String> {
+    match req.send_json(body) {
+        Ok(resp) => resp.into_json().map_err(|e| e.to_string()),
+        Err(ureq::Error::Status(code, resp)) => {
+            let detail = resp.into_string().unwrap_or_default();
+            Err(format!("{provider} API error: {code} {detail}"))
+        }
+        Err(e) => Err(format!("{provider} request failed: {e}")),
+    }
+}
+
+/// OpenAI-style messages, also understood by Ollama. OpenAI wants call arguments as a JSON
+/// string and images as content parts, Ollama arguments as an object and images alongside.
+fn chat_messages(req: &LlmRequest, arguments_as_string: bool) -> Vec<Value> {
+    let mut messages = vec![json!({ "role": "system", "content": req.system })];
+    messages.extend(req.messages.iter().map(|m| {
+        if m.role == "tool" {
+            return json!({ "role": "tool", "tool_call_id": m.tool_call_id, "content": m.content });
+        }
+        let mut message = json!({ "role": m.role, "content": m.content });
+        if !m.images.is_empty() && arguments_as_string {
+            let mut parts = vec![json!({ "type": "text", "text": m.content })];
+            parts.extend(m.images.iter().map(|image| json!({ "type": "image_url", "image_url": { "url": image.data_url() } })));
+            message["content"] = Value::Array(parts);
+        } else if !m.images.is_empty() {
+            message["images"] = m.images.iter().map(|image| json!(image.data)).collect();
+        }
+        if !m.tool_calls.is_empty() {
+            message["tool_calls"] = m.tool_calls.iter().map(|call| json!({
+                "id": call.id,
+                "type": "function",
+                "function": {
+                    "name": call.name,
+                    "arguments": if arguments_as_string { json!(call.arguments.to_string()) } else { call.arguments.clone() },
+                },
+            })).collect();
+        }
+        message
+    }));
+    messages
+}
+
+fn function_tools(tools: &[ToolDef]) -> Value {
+    tools.iter().map(|t| json!({
+        "type": "function",
+        "function": { "name": t.name, "description": t.description, "parameters": t.parameters },
+    })).collect()
+}
+
+fn call_openai(cfg: &ProviderConfig, req: &LlmRequest) -> Result<LlmResponse, String> {
+    let mut body = json!({
+        "model": cfg.model,
+        "messages": chat_messages(req, true),
+        "max_tokens": req.max_tokens,
+    });
+    if req.json {
+        body["response_format"] = json!({ "type": "json_object" });
+    }
+    if !req.tools.is_empty() {
+        body["tools"] = function_tools(&req.tools);
+        // One call at a time, so each one gets its own permission check and approval.
+        body["parallel_tool_calls"] = json!(false);
+    }
+    let url = format!("{}/chat/completions", cfg.base_url);
+    let request = cfg.proxy.agent(&url, REQUEST_TIMEOUT)?
+        .post(&url)
+        .set("Authorization", &format!("Bearer {}", cfg.api_key));
+    let data = post_json(request, body, "OpenAI")?;
+    let message = &data["choices"][0]["message"];
+    let tool_calls = message["tool_calls"].as_array().into_iter().flatten().map(|call| ToolCall {
+        id: call["id"].as_str().unwrap_or_default().to_string(),
+        name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+        arguments: call["function"]["arguments"].as_str()
+            .and_then(|args| serde_json::from_str(args).ok())
+            .unwrap_or_else(|| json!({})),
+    }).collect();
+    Ok(LlmResponse {
+        text: message["content"].as_str().unwrap_or_default().to_string(),
+        provider: cfg.provider.clone(),
+        model: cfg.model.clone(),
+        input_tokens: data["usage"]["prompt_tokens"].as_i64().unwrap_or(0),
+        output_tokens: data["usage"]["completion_tokens"].as_i64().unwrap_or(0),
+        tool_calls,
+        fallback: false,
+    })
+}
+
+/// Anthropic carries tool calls and results as content blocks rather than separate fields.
+fn anthropic_messages(req: &LlmRequest) -> Vec<Value> {
+    req.messages.iter().map(|m| {
+        if m.role == "tool" {
+            return json!({
+                "role": "user",
+                "content": [{ "type": "tool_result", "tool_use_id": m.tool_call_id, "content": m.content }],
+            });
+        }
+        if !m.images.is_empty() {
+            let mut blocks: Vec<Value> = m.images.iter().map(|image| json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": image.media_type, "data": image.data },
+            })).collect();
+            blocks.push(json!({ "type": "text", "text": m.content }));
+            return json!({ "role": m.role, "content": blocks });
+        }
+        if m.tool_calls.is_empty() {
+            return json!({ "role": m.role, "content": m.content });
+        }
+        let mut blocks = Vec::new();
+        if !m.content.is_empty() {
+            blocks.push(json!({ "type": "text", "text": m.content }));
+        }
+        blocks.extend(m.tool_calls.iter().map(|call| json!({
+            "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments,
+        })));
+        json!({ "role": m.role, "content": blocks })
+    }).collect()
+}
+
+fn call_anthropic(cfg: &ProviderConfig, req: &LlmRequest) -> Result<LlmResponse, String> {
+    let mut body = json!({
+        "model": cfg.model,
+        "system": req.system,
+        "messages": anthropic_messages(req),
+        "max_tokens": req.max_tokens,
+    });
+    if !req.tools.is_empty() {
+        body["tools"] = req.tools.iter()
+            .map(|t| json!({ "name": t.name, "description": t.description, "input_schema": t.parameters }))
+            .collect();
+        body["tool_choice"] = json!({ "type": "auto", "disable_parallel_tool_use": true });
+    }
+    let url = format!("{}/messages", cfg.base_url);
+    let request = cfg.proxy.agent(&url, REQUEST_TIMEOUT)?
+        .post(&url)
+        .set("x-api-key", &cfg.api_key)
+        .set("anthropic-version", "2023-06-01");
+    let data = post_json(request, body, "Anthropic")?;
+    let blocks = data["content"].as_array().cloned().unwrap_or_default();
+    let text = blocks.iter()
+        .filter(|b| b["type"] == "text")
+        .filter_map(|b| b["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    let tool_calls = blocks.iter().filter(|b| b["type"] == "tool_use").map(|b| ToolCall {
+        id: b["id"].as_str().unwrap_or_default().to_string(),
+        name: b["name"].as_str().unwrap_or_default().to_string(),
+        arguments: b["input"].clone(),
+    }).collect();
+    Ok(LlmResponse {
+        text,
+        provider: cfg.provider.clone(),
+        model: cfg.model.clone(),
+        input_tokens: data["usage"]["input_tokens"].as_i64().unwrap_or(0),
+        output_tokens: data["usage"]["output_tokens"].as_i64().unwrap_or(0),
+        tool_calls,
+        fallback: false,
+    })
+}
+
+fn call_local(cfg: &ProviderConfig, req: &LlmRequest) -> Result<LlmResponse, String> {
+    let mut body = json!({
+        "model": cfg.model,
+        "messages": chat_messages(req, false),
+        "stream": false,
+        "options": { "num_predict": req.max_tokens },
+    });
+    if req.json {
+        body["format"] = json!("json");
+    }
+    if !req.tools.is_empty() {
+        body["tools"] = function_tools(&req.tools);
+    }
+    let url = format!("{}/api/chat", cfg.base_url.trim_end_matches('/'));
+    let request = cfg.proxy.agent(&url, REQUEST_TIMEOUT)?.post(&url);
+    let data = post_json(request, body, "Local model")?;
+    // Ollama doesn't number its calls, so ids are made up here.
+    let tool_calls = data["message"]["tool_calls"].as_array().into_iter().flatten().enumerate().map(|(i, call)| ToolCall {
+        id: format!("call_{i}"),
+        name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+        arguments: call["function"]["arguments"].clone(),
+    }).collect();
+    Ok(LlmResponse {
+        text: data["message"]["content"].as_str().unwrap_or_default().to_string(),
+        provider: cfg.provider.clone(),
+        model: cfg.model.clone(),
+        input_tokens: data["prompt_eval_count"].as_i64().unwrap_or(0),
+        output_tokens: data["eval_count"].as_i64().unwrap_or(0),
+        tool_calls,
+        fallback: false,
+    })
+}
+
+/// Provider used for embeddings: OpenAI when it is the active provider, otherwise the local
+/// model server, since Anthropic has no embeddings API. `llm_embedding_model` overrides the model.
+pub fn embedding_config(conn: &Connection) -> Result<ProviderConfig, String> {
+    let active = provider_config(conn)?;
+    let mut cfg = if active.provider == "openai" { active } else { local_config(conn)? };
+    cfg.model = read_setting(conn, "llm_embedding_model")?
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| if cfg.provider == "openai" { "text-embedding-3-small" } else { "nomic-embed-text" }.into());
+    Ok(cfg)
+}
+
+fn to_vector(value: &Value) -> Vec<f32> {
+    value.as_array().map(|v| v.iter().filter_map(Value::as_f64).map(|x| x as f32).collect()).unwrap_or_default()
+}
+
+/// One vector per input text, in order. Callers check local-only mode first.
+pub fn embed(cfg: &ProviderConfig, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let body = json!({ "model": cfg.model, "input": texts });
+    let embeddings: Vec<Vec<f32>> = if cfg.provider == "openai" {
+        let url = format!("{}/embeddings", cfg.base_url);
+        let request = cfg.proxy.agent(&url, REQUEST_TIMEOUT)?
+            .post(&url)
+            .set("Authorization", &format!("Bearer {}", cfg.api_key));
+        let data = post_json(request, body, "OpenAI")?;
+        data["data"].as_array().map(|items| items.iter().map(|item| to_vector(&item["embedding"])).collect()).unwrap_or_default()
+    } else {
+        let url = format!("{}/api/embed", cfg.base_url.trim_end_matches('/'));
+        let data = post_json(cfg.proxy.agent(&url, REQUEST_TIMEOUT)?.post(&url), body, "Local model")?;
+        data["embeddings"].as_array().map(|rows| rows.iter().map(to_vector).collect()).unwrap_or_default()
+    };
+    if embeddings.len() != texts.len() || embeddings.iter().any(Vec::is_empty) {
+        return Err(format!("{} returned no embeddings; is it an embedding model?", cfg.model));
+    }
+    Ok(embeddings)
+}
+
+pub fn call_provider(cfg: &ProviderConfig, req: &LlmRequest) -> Result<LlmResponse, String> {
+    match cfg.provider.as_str() {
+        "openai" => call_openai(cfg, req),
+        "anthropic" => call_anthropic(cfg, req),
+        _ => call_local(cfg, req),
+    }
+}
+
+fn record_usage(conn: &Connection, req: &LlmRequest, resp: &LlmResponse, duration_ms: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO llm_usage (agent_id, run_id, provider, model, input_tokens, output_tokens, duration_ms, fallback, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            req.agent_id, req.run_id, resp.provider, resp.model, resp.input_tokens, resp.output_tokens,
+            duration_ms, resp.fallback as i32, Utc::now().to_rfc3339()
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The newest usage row, to find the calls a step made with `served_since`.
+pub fn usage_mark(db: &DbState) -> Result<i64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.query_row("SELECT COALESCE(MAX(id), 0) FROM llm_usage", [], |row| row.get(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Which provider and model answered the run's calls after `mark`, e.g. `openai/gpt-4o-mini`,
+/// with ` (fallback)` added where the primary provider had failed.
+pub fn served_since(db: &DbState, run_id: &str, mark: i64) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT provider, model, fallback FROM llm_usage WHERE run_id = ?1 AND id > ?2 ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![run_id, mark], |row| {
+        let (provider, model, fallback): (String, String, i32) = (row.get(0)?, row.get(1)?, row.get(2)?);
+        Ok(format!("{provider}/{model}{}", if fallback != 0 { " (fallback)" } else { "" }))
+    }).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Errors another provider might not have: outages, rate limits, and a rejected or exhausted
+/// API key. A request the provider found invalid, e.g. too long, would fail anywhere.
+fn is_provider_failure(error: &str) -> bool {
+    if error.contains("request failed") {
+        return true;
+    }
+    let status = error.split_once("API error: ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .and_then(|code| code.parse::<u16>().ok());
+    matches!(status, Some(401 | 402 | 403 | 408 | 409 | 429 | 500..=599))
+}
+
+/// The fallback provider as set up, before checking it suits any particular request.
+pub(crate) fn configured_fallback(conn: &Connection, primary: &ProviderConfig) -> Result<Option<ProviderConfig>, String> {
+    let Some(provider) = read_setting(conn, "llm_fallback_provider")?.filter(|p| !p.is_empty() && policy::provider_allowed(p)) else {
+        return Ok(None);
+    };
+    let model = read_setting(conn, "llm_fallback_model")?;
+    if provider == "local" {
+        let local = local_config(conn)?;
+        return Ok(Some(ProviderConfig { model: model.filter(|m| !m.is_empty()).unwrap_or(local.model), ..local }));
+    }
+    let api_key = read_setting(conn, "llm_fallback_api_key")?
+        .filter(|k| !k.is_empty())
+        .or_else(|| (provider == primary.provider).then(|| primary.api_key.clone()))
+        .ok_or("The fallback provider has no API key")?;
+    remote_config(conn, provider, api_key, model).map(Some)
+}
+
+/// The provider to retry with when the primary fails (`llm_fallback_provider`,
+/// `llm_fallback_model`, and `llm_fallback_api_key` unless it shares the primary's provider).
+/// `None` when none is set or it can't take the request: the same model as the primary, a
+/// context window too small for it, or a cloud provider while in local-only mode.
+fn fallback_config(conn: &Connection, primary: &ProviderConfig, req: &LlmRequest) -> Result<Option<ProviderConfig>, String> {
+    let Some(cfg) = configured_fallback(conn, primary)? else {
+        return Ok(None);
+    };
+    if cfg.provider == primary.provider && cfg.model == primary.model {
+        return Ok(None);
+    }
+    let text: String = std::iter::once(req.system.as_str())
+        .chain(req.messages.iter().map(|m| m.content.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if tokens::count(&cfg.model, &text) + req.max_tokens as usize > context_window(conn, &cfg)? {
+        return Ok(None);
+    }
+    if (net::local_only(conn) || sensitivity::is_confidential(conn, &req.agent_id)) && !net::is_loopback(&cfg.base_url) {
+        return Ok(None);
+    }
+    Ok(Some(cfg))
+}
+
+/// Sends a request to the configured provider and records its token usage. An identical
+/// earlier request is answered from the response cache, spending no tokens. When the provider
+/// is down or rate-limited the request goes to the fallback provider, if one is set up.
+/// The database lock is only held while reading settings and writing usage, never during the HTTP call.
+pub fn complete(db: &DbState, req: &LlmRequest) -> Result<LlmResponse, String> {
+    let (cfg, caps, cache_key) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut cfg = agent_provider_config(&conn, &req.agent_id)?;
+        // A model picked for a cloud provider means nothing to a confidential agent's local one.
+        let model = req.model.as_ref().filter(|m| !m.trim().is_empty() && !sensitivity::is_confidential(&conn, &req.agent_id));
+        if let Some(model) = model {
+            cfg.model = model.trim().to_string();
+        }
+        let cache_key = cache::enabled_for(&conn, &req.agent_id).then(|| cache::key("llm", &json!({
+            "provider": cfg.provider,
+            "base_url": cfg.base_url,
+            "model": cfg.model,
+            "system": req.system,
+            "messages": req.messages,
+            "max_tokens": req.max_tokens,
+            "json": req.json,
+            "tools": req.tools,
+        })));
+        let hit = cache_key.as_deref()
+            .and_then(|key| cache::get(&conn, key))
+            .and_then(|cached| serde_json::from_str::<LlmResponse>(&cached).ok());
+        if let Some(resp) = hit {
+            return Ok(LlmResponse { input_tokens: 0, output_tokens: 0, ..resp });
+        }
+        net::check_egress(&conn, &format!("the {} model", cfg.provider), Some(&cfg.base_url), &req.agent_id, &req.run_id)?;
+        let caps = capabilities::lookup(&conn, &cfg)?;
+        (cfg, caps, cache_key)
+    };
+    let started = Instant::now();
+    let (resp, learned) = match capabilities::call(&cfg, &caps, req) {
+        Err(e) if is_provider_failure(&e) => {
+            let fallback = {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                match fallback_config(&conn, &cfg, req)? {
+                    Some(fallback) => {
+                        let summary = format!("{} failed, switching to {}/{}", cfg.provider, fallback.provider, fallback.model);
+                        let _ = audit::record(&conn, "provider_failover", &req.agent_id, &req.run_id, &summary, &json!({ "error": e }));
+                        let caps = capabilities::lookup(&conn, &fallback)?;
+                        Some((fallback, caps))
+                    }
+                    None => None,
+                }
+            };
+            let Some((fallback, fallback_caps)) = fallback else { return Err(e) };
+            let (resp, _) = capabilities::call(&fallback, &fallback_caps, req)
+                .map_err(|e2| format!("{e} (the fallback provider also failed: {e2})"))?;
+            (LlmResponse { fallback: true, ..resp }, None)
+        }
+        other => other?,
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(field) = learned {
+        capabilities::learn(&conn, &cfg, field)?;
+    }
+    record_usage(&conn, req, &resp, started.elapsed().as_millis() as i64)?;
+    // Fallback answers aren't cached under the primary's key.
+    if let Some(key) = cache_key.filter(|_| !resp.fallback) {
+        if let Ok(body) = serde_json::to_string(&resp) {
+            cache::put(&conn, "llm", &key, &body);
+        }
+    }
+    Ok(resp)
+}
+
+/// Sends a request straight to the local model, for work that can still be done on this
+/// computer when the configured provider can't be reached. It isn't cached, and its usage is
+/// recorded as a fallback call.
+pub fn complete_locally(db: &DbState, req: &LlmRequest) -> Result<LlmResponse, String> {
+    let cfg = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let cfg = local_config(&conn)?;
+        net::check_egress(&conn, "the local model", Some(&cfg.base_url), &req.agent_id, &req.run_id)?;
+        cfg
+    };
+    let started = Instant::now();
+    let resp = LlmResponse { fallback: true, ..call_provider(&cfg, req)? };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_usage(&conn, req, &resp, started.elapsed().as_millis() as i64)?;
+    Ok(resp)
+}
+
+/// Pulls the first JSON object out of a model reply, tolerating code fences and surrounding prose.
+pub fn extract_json(text: &str) -> Result<Value, String> {
+    let start = text.find('{').ok_or("Model reply did not contain JSON")?;
+    let end = text.rfind('}').ok_or("Model reply did not contain JSON")?;
+    if end < start {
+        return Err("Model reply did not contain JSON".into());
+    }
+    serde_json::from_str(&text[start..=end]).map_err(|e| format!("Model returned invalid JSON: {e}"))
+}
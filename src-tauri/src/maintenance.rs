@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::indexer::Indexer;
+use crate::policy;
+use crate::{DbState, read_setting, write_setting};
+
+/// The last maintenance report, as JSON. Device-local, like the database file it describes.
+const LAST_RUN_SETTING: &str = "db_maintenance_last";
+/// Days between automatic passes; `0` turns them off.
+const INTERVAL_SETTING: &str = "db_maintenance_interval_days";
+const DEFAULT_INTERVAL_DAYS: i64 = 7;
+/// How often the watcher looks for a quiet moment once a pass is due.
+const CHECK_EVERY: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceReport {
+    pub ran_at: String,
+    /// True when started by the idle-time scheduler rather than the user.
+    pub automatic: bool,
+    /// Database size in bytes, including its write-ahead log.
+    pub size_before: u64,
+    pub size_after: u64,
+    pub duration_ms: u64,
+    /// Full-text indexes that were merged.
+    pub fts_tables: Vec<String>,
+}
+
+pub(crate) fn database_size(conn: &Connection) -> Result<u64, String> {
+    let pages: u64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let wal = conn.path()
+        .and_then(|path| std::fs::metadata(format!("{path}-wal")).ok())
+        .map_or(0, |m| m.len());
+    Ok(pages * page_size + wal)
+}
+
+fn fts_tables(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%USING fts5%'"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| row.get(0)).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Checkpoints the WAL, merges full-text index segments, refreshes the planner statistics
+/// and rebuilds the file to hand free pages back to the disk. Records the report.
+pub fn run(conn: &Connection, automatic: bool) -> Result<MaintenanceReport, String> {
+    let started = Instant::now();
+    let size_before = database_size(conn)?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(())).map_err(|e| e.to_string())?;
+    let fts_tables = fts_tables(conn)?;
+    for table in &fts_tables {
+        conn.execute(&format!("INSERT INTO \"{table}\"(\"{table}\") VALUES ('optimize')"), [])
+            .map_err(|e| e.to_string())?;
+    }
+    conn.execute_batch("ANALYZE; VACUUM; PRAGMA optimize;").map_err(|e| e.to_string())?;
+    let report = MaintenanceReport {
+        ran_at: Utc::now().to_rfc3339(),
+        automatic,
+        size_before,
+        size_after: database_size(conn)?,
+        duration_ms: started.elapsed().as_millis() as u64,
+        fts_tables,
+    };
+    write_setting(conn, LAST_RUN_SETTING, &serde_json::to_string(&report).map_err(|e| e.to_string())?)?;
+    Ok(report)
+}
+
+fn last_report(conn: &Connection) -> Result<Option<MaintenanceReport>, String> {
+    Ok(read_setting(conn, LAST_RUN_SETTING)?.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// An automatic pass is due and nothing would be held up by it: no window has focus, the
+/// indexer is idle and no run is in progress.
+fn due(app: &AppHandle, conn: &Connection) -> Result<bool, String> {
+    let days = read_setting(conn, INTERVAL_SETTING)?
+        .and_then(|d| d.trim().parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_DAYS);
+    if days <= 0 {
+        return Ok(false);
+    }
+    let last = last_report(conn)?
+        .and_then(|r| DateTime::parse_from_rfc3339(&r.ran_at).ok())
+        .map(|t| t.with_timezone(&Utc));
+    if last.is_some_and(|t| Utc::now() - t < chrono::Duration::days(days)) {
+        return Ok(false);
+    }
+    let indexer = app.state::<Indexer>();
+    if indexer.is_user_active() || indexer.is_running() {
+        return Ok(false);
+    }
+    let running: i64 = conn.query_row("SELECT COUNT(*) FROM runs WHERE status = 'running'", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    Ok(running == 0)
+}
+
+/// Starts the watcher that runs maintenance in idle time once it is due, emitting
+/// `db-maintenance-finished` with the report.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("db-maintenance".into())
+        .spawn(move || loop {
+            std::thread::sleep(CHECK_EVERY);
+            let report = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                let _ = policy::enforce_retention(&conn);
+                match due(&app, &conn) {
+                    Ok(true) => run(&conn, true).ok(),
+                    _ => None,
+                }
+            };
+            if let Some(report) = report {
+                let _ = app.emit("db-maintenance-finished", report);
+            }
+        })
+        .expect("Failed to start the maintenance watcher");
+}
+
+// ─── Maintenance Commands ───
+
+/// Runs maintenance now. The database is busy until it finishes, which can take a while on
+/// a large one.
+#[tauri::command]
+pub async fn run_db_maintenance(app: AppHandle) -> Result<MaintenanceReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        run(&conn, false)
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// The last maintenance pass, manual or automatic, if there has been one.
+#[tauri::command]
+pub fn get_db_maintenance_status(db: State<DbState>) -> Result<Option<MaintenanceReport>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    last_report(&conn)
+}
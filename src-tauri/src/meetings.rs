@@ -0,0 +1,262 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::injection;
+use crate::llm::{ChatMessage, LlmRequest};
+use crate::structured;
+use crate::templates::CatalogTemplate;
+use crate::tools::{files, ToolContext};
+use crate::transcribe;
+use crate::workflows::WorkflowStep;
+use crate::{DbState, read_setting};
+
+/// Folder of markdown notes the pipeline files into; `~/Documents/OpenClaw Notes` by default.
+const VAULT_SETTING: &str = "notes_vault_dir";
+/// The folder is on this computer; another device has its own.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[VAULT_SETTING];
+const MEETINGS_FOLDER: &str = "Meetings";
+/// Open action items from every meeting, as markdown checkboxes.
+const TASKS_FILE: &str = "Tasks.md";
+const SUMMARY_PROMPT: &str = "Summarize this meeting transcript for someone who missed it: a short title, \
+a summary of a few paragraphs, the decisions made, and the action items with who owns each and when it is \
+due, where the transcript says.";
+const REPLY_TOKENS: u32 = 2048;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActionItem {
+    pub task: String,
+    #[serde(default)]
+    pub owner: String,
+    #[serde(default)]
+    pub due: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MeetingNotes {
+    pub title: String,
+    pub summary: String,
+    pub decisions: Vec<String>,
+    pub action_items: Vec<ActionItem>,
+    /// The meeting's note in the vault.
+    pub note_path: String,
+    /// Where the action items were added as tasks.
+    pub tasks_path: String,
+    pub duration_seconds: f64,
+}
+
+/// What the summarizing model call returns.
+pub fn summary_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "title": { "type": "string" },
+            "summary": { "type": "string" },
+            "decisions": { "type": "array", "items": { "type": "string" } },
+            "action_items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": { "task": { "type": "string" }, "owner": { "type": "string" }, "due": { "type": "string" } },
+                    "required": ["task"],
+                },
+            },
+        },
+        "required": ["title", "summary", "decisions", "action_items"],
+    })
+}
+
+pub fn vault_dir(conn: &rusqlite::Connection) -> Result<String, String> {
+    Ok(read_setting(conn, VAULT_SETTING)?.filter(|d| !d.trim().is_empty()).unwrap_or_else(|| {
+        dirs_next::document_dir().unwrap_or_default().join("OpenClaw Notes").display().to_string()
+    }))
+}
+
+/// `title` made safe as a file name on every platform.
+fn file_stem(title: &str) -> String {
+    let cleaned: String = title.chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned: String = cleaned.chars().take(80).collect();
+    format!("{} {}", Local::now().format("%Y-%m-%d"), if cleaned.is_empty() { "Meeting" } else { cleaned.trim_end_matches('.') })
+}
+
+fn task_line(item: &ActionItem, link: &str) -> String {
+    let details: Vec<&str> = [item.owner.trim(), item.due.trim()].into_iter().filter(|d| !d.is_empty()).collect();
+    let details = match details.len() {
+        0 => String::new(),
+        _ => format!(" ({})", details.join(", due ")),
+    };
+    format!("- [ ] {}{details}{link}", item.task.trim())
+}
+
+/// Writes the meeting's note under `Meetings/` in `vault` and adds its action items to
+/// `Tasks.md`, each linking back to the note. Returns both paths.
+pub fn file_notes(
+    vault: &Path,
+    title: &str,
+    summary: &str,
+    decisions: &[String],
+    action_items: &[ActionItem],
+    transcript: &str,
+) -> Result<(PathBuf, PathBuf), String> {
+    let folder = vault.join(MEETINGS_FOLDER);
+    fs::create_dir_all(&folder).map_err(|e| format!("Can't create {}: {e}", folder.display()))?;
+    let base = file_stem(title);
+    let stem = (1..).map(|n| if n == 1 { base.clone() } else { format!("{base} ({n})") })
+        .find(|stem| !folder.join(format!("{stem}.md")).exists())
+        .unwrap_or(base);
+    let note_path = folder.join(format!("{stem}.md"));
+
+    let mut note = format!("# {}\n\n_{}_\n\n## Summary\n\n{}\n", title.trim(), Local::now().format("%Y-%m-%d %H:%M"), summary.trim());
+    if !decisions.is_empty() {
+        note.push_str("\n## Decisions\n\n");
+        note.extend(decisions.iter().map(|d| format!("- {}\n", d.trim())));
+    }
+    if !action_items.is_empty() {
+        note.push_str("\n## Action items\n\n");
+        note.extend(action_items.iter().map(|item| task_line(item, "") + "\n"));
+    }
+    if !transcript.trim().is_empty() {
+        note.push_str(&format!("\n## Transcript\n\n{}\n", transcript.trim()));
+    }
+    fs::write(&note_path, note).map_err(|e| format!("Can't write {}: {e}", note_path.display()))?;
+
+    let tasks_path = vault.join(TASKS_FILE);
+    if !action_items.is_empty() {
+        let is_new = !tasks_path.exists();
+        let mut tasks = OpenOptions::new().create(true).append(true).open(&tasks_path)
+            .map_err(|e| format!("Can't open {}: {e}", tasks_path.display()))?;
+        let link = format!(" — [[{MEETINGS_FOLDER}/{stem}]]");
+        let mut lines = if is_new { "# Tasks\n\n".to_string() } else { String::new() };
+        lines.extend(action_items.iter().map(|item| task_line(item, &link) + "\n"));
+        tasks.write_all(lines.as_bytes()).map_err(|e| format!("Can't write {}: {e}", tasks_path.display()))?;
+    }
+    Ok((note_path, tasks_path))
+}
+
+/// Accepts a list, or text with one entry per line, as a step's params may give either.
+fn text_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(items)) => items.iter()
+            .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+            .collect(),
+        Some(Value::String(text)) => text.lines()
+            .map(|l| l.trim().trim_start_matches(['-', '*']).trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn action_items(value: Option<&Value>) -> Vec<ActionItem> {
+    match value {
+        Some(Value::Array(items)) => items.iter().filter_map(|item| match item {
+            Value::String(task) => Some(ActionItem { task: task.clone(), owner: String::new(), due: String::new() }),
+            other => serde_json::from_value(other.clone()).ok(),
+        }).filter(|item: &ActionItem| !item.task.trim().is_empty()).collect(),
+        other => text_list(other).into_iter()
+            .map(|task| ActionItem { task, owner: String::new(), due: String::new() })
+            .collect(),
+    }
+}
+
+/// Runs a `file_meeting_notes` step.
+pub fn run_tool(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let vault = {
+        let db = ctx.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        vault_dir(&conn)?
+    };
+    let text = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    let items = action_items(params.get("action_items"));
+    let (note, tasks) = file_notes(
+        &ctx.path(&vault),
+        &text("title"),
+        &text("summary"),
+        &text_list(params.get("decisions")),
+        &items,
+        &text("transcript"),
+    )?;
+    Ok(match items.len() {
+        0 => format!("Filed the notes in {}", note.display()),
+        n => format!("Filed the notes in {} and added {n} task(s) to {}", note.display(), tasks.display()),
+    })
+}
+
+/// Built-in template: transcribe a recording, summarize it, file the notes and tasks.
+pub fn template() -> CatalogTemplate {
+    let step = |tool: &str, params: Value, label: &str| WorkflowStep::Tool { tool: tool.into(), params, label: label.into() };
+    CatalogTemplate {
+        id: "builtin:meeting-summarizer".into(),
+        name: "Meeting summarizer".into(),
+        description: "Transcribes a meeting recording, summarizes it with decisions and action items, \
+            and files the notes and tasks into your notes folder.".into(),
+        author: "OpenClaw".into(),
+        tags: vec!["meetings".into(), "audio".into(), "notes".into(), "tasks".into()],
+        role: "Meeting note taker".into(),
+        goal: "Turn the meeting recording into notes with action items".into(),
+        schedule: String::new(),
+        tools: vec!["transcribe_audio".into(), "llm_prompt".into(), "file_meeting_notes".into()],
+        workflow: vec![
+            step("transcribe_audio", json!({ "path": "~/Documents/Meeting recording.m4a" }), "Transcribe the recording"),
+            step("llm_prompt", json!({ "prompt": format!("{SUMMARY_PROMPT}\n\n{{{{step_1}}}}"), "schema": summary_schema() }), "Summarize"),
+            step("file_meeting_notes", json!({
+                "title": "{{step_2.title}}",
+                "summary": "{{step_2.summary}}",
+                "decisions": "{{step_2.decisions}}",
+                "action_items": "{{step_2.action_items}}",
+                "transcript": "{{step_1}}",
+            }), "File notes and tasks"),
+        ],
+    }
+}
+
+/// The whole pipeline outside any run, for a recording imported into the app.
+fn summarize(app: &AppHandle, path: &Path, title: Option<&str>) -> Result<MeetingNotes, String> {
+    let transcript = transcribe::standalone(app, path)?;
+    if transcript.segments.is_empty() {
+        return Err("The recording has no speech in it".into());
+    }
+    let db = app.state::<DbState>();
+    let summary = structured::complete(&db, &LlmRequest {
+        system: format!("{SUMMARY_PROMPT} {}", injection::SYSTEM_NOTE),
+        messages: vec![ChatMessage::user(injection::wrap("transcript", &transcript.text, false))],
+        max_tokens: REPLY_TOKENS,
+        ..Default::default()
+    }, &summary_schema())?;
+    let title = title.filter(|t| !t.trim().is_empty()).map(str::to_string)
+        .unwrap_or_else(|| summary["title"].as_str().unwrap_or("Meeting").to_string());
+    let decisions = text_list(summary.get("decisions"));
+    let items = action_items(summary.get("action_items"));
+    let summary_text = summary["summary"].as_str().unwrap_or_default().to_string();
+    let vault = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        vault_dir(&conn)?
+    };
+    let (note, tasks) = file_notes(&files::expand_path(&vault), &title, &summary_text, &decisions, &items, &transcript.text)?;
+    Ok(MeetingNotes {
+        title,
+        summary: summary_text,
+        decisions,
+        action_items: items,
+        note_path: note.display().to_string(),
+        tasks_path: tasks.display().to_string(),
+        duration_seconds: transcript.duration_seconds,
+    })
+}
+
+// ─── Meeting Commands ───
+
+/// Transcribes and summarizes a meeting recording, then files the notes under `Meetings/`
+/// in the notes folder and the action items in its `Tasks.md`.
+#[tauri::command]
+pub async fn summarize_meeting(app: AppHandle, path: String, title: Option<String>) -> Result<MeetingNotes, String> {
+    tauri::async_runtime::spawn_blocking(move || summarize(&app, &files::expand_path(&path), title.as_deref()))
+        .await.map_err(|e| e.to_string())?
+}
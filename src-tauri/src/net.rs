@@ -0,0 +1,171 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tauri::State;
+
+use crate::audit;
+use crate::{DbState, read_setting, write_setting};
+
+/// When `"true"`, nothing but a model on this machine may be reached over the network.
+const LOCAL_ONLY_SETTING: &str = "local_only_mode";
+/// Proxy for `http://` requests, e.g. `http://proxy.corp:3128`.
+const HTTP_PROXY_SETTING: &str = "proxy_http";
+/// Proxy for `https://` requests, reached with CONNECT.
+const HTTPS_PROXY_SETTING: &str = "proxy_https";
+const PROXY_USER_SETTING: &str = "proxy_username";
+/// The proxy password is kept in the OS keychain under this service, never in the database.
+pub(crate) const KEYCHAIN_SERVICE: &str = "openclaw-desktop";
+const KEYCHAIN_PROXY_ENTRY: &str = "proxy";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct NetworkMode {
+    pub local_only: bool,
+}
+
+pub fn local_only(conn: &Connection) -> bool {
+    read_setting(conn, LOCAL_ONLY_SETTING).ok().flatten().as_deref() == Some("true")
+}
+
+/// Proxy settings as edited in the Settings panel.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub http_proxy: String,
+    #[serde(default)]
+    pub https_proxy: String,
+    #[serde(default)]
+    pub username: String,
+    /// Only ever sent to the backend: `None` keeps the saved password, `""` removes it.
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub has_password: bool,
+}
+
+/// Resolved proxy for outbound requests, loaded while the database is locked and then used
+/// without it.
+#[derive(Debug, Clone, Default)]
+pub struct Proxy {
+    http: String,
+    https: String,
+    username: String,
+    password: String,
+}
+
+impl Proxy {
+    /// An HTTP client for `url` that goes through the matching proxy. Loopback addresses never do.
+    pub fn agent(&self, url: &str, timeout: Duration) -> Result<ureq::Agent, String> {
+        let builder = ureq::AgentBuilder::new().timeout(timeout);
+        let server = if url.starts_with("https://") { &self.https } else { &self.http };
+        if server.is_empty() || is_loopback(url) {
+            return Ok(builder.build());
+        }
+        let address = server.trim_start_matches("http://");
+        let spec = if self.username.is_empty() {
+            format!("http://{address}")
+        } else {
+            format!("http://{}:{}@{address}", self.username, self.password)
+        };
+        let proxy = ureq::Proxy::new(&spec).map_err(|e| format!("The proxy address {server} is invalid: {e}"))?;
+        Ok(builder.proxy(proxy).build())
+    }
+}
+
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_PROXY_ENTRY)
+        .map_err(|e| format!("The system keychain is unavailable: {e}"))
+}
+
+fn keychain_password() -> Result<Option<String>, String> {
+    match keychain_entry()?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Couldn't read the proxy password from the system keychain: {e}")),
+    }
+}
+
+/// Removes the proxy password from the system keychain, for a factory reset.
+pub(crate) fn forget_proxy_password() -> Result<(), String> {
+    match keychain_entry()?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Couldn't remove the proxy password from the system keychain: {e}")),
+    }
+}
+
+pub fn proxy(conn: &Connection) -> Result<Proxy, String> {
+    let setting = |key| read_setting(conn, key).map(Option::unwrap_or_default);
+    let username = setting(PROXY_USER_SETTING)?;
+    let password = if username.is_empty() { String::new() } else { keychain_password()?.unwrap_or_default() };
+    Ok(Proxy { http: setting(HTTP_PROXY_SETTING)?, https: setting(HTTPS_PROXY_SETTING)?, username, password })
+}
+
+pub fn is_loopback(url: &str) -> bool {
+    tauri::Url::parse(url).ok()
+        .and_then(|u| u.host_str().map(|h| matches!(h, "localhost" | "127.0.0.1" | "[::1]")))
+        .unwrap_or(false)
+}
+
+/// Refuses a network call while local-only mode is on and records the attempt in the audit
+/// trail. `url` is `None` for tools that always leave the machine (email, browser).
+pub fn check_egress(conn: &Connection, what: &str, url: Option<&str>, agent_id: &str, run_id: &str) -> Result<(), String> {
+    if !local_only(conn) || url.is_some_and(is_loopback) {
+        return Ok(());
+    }
+    let summary = format!("Blocked {what}: local-only mode is on");
+    audit::record(conn, "egress_blocked", agent_id, run_id, &summary, &json!({ "what": what, "url": url }))?;
+    Err(summary)
+}
+
+// ─── Network Commands ───
+
+#[tauri::command]
+pub fn get_network_mode(db: State<DbState>) -> Result<NetworkMode, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(NetworkMode { local_only: local_only(&conn) })
+}
+
+#[tauri::command]
+pub fn get_proxy_settings(db: State<DbState>) -> Result<ProxySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let setting = |key| read_setting(&conn, key).map(Option::unwrap_or_default);
+    Ok(ProxySettings {
+        http_proxy: setting(HTTP_PROXY_SETTING)?,
+        https_proxy: setting(HTTPS_PROXY_SETTING)?,
+        username: setting(PROXY_USER_SETTING)?,
+        password: None,
+        has_password: keychain_password().ok().flatten().is_some_and(|p| !p.is_empty()),
+    })
+}
+
+#[tauri::command]
+pub fn set_proxy_settings(db: State<DbState>, settings: ProxySettings) -> Result<ProxySettings, String> {
+    let http_proxy = settings.http_proxy.trim();
+    let https_proxy = settings.https_proxy.trim();
+    for server in [http_proxy, https_proxy].into_iter().filter(|s| !s.is_empty()) {
+        if !server.starts_with("http://") {
+            return Err(format!("Proxy addresses look like http://host:port, not {server}"));
+        }
+        ureq::Proxy::new(server).map_err(|e| format!("The proxy address {server} is invalid: {e}"))?;
+    }
+    match settings.password.as_deref() {
+        None => {}
+        Some("") => forget_proxy_password()?,
+        Some(password) => keychain_entry()?.set_password(password)
+            .map_err(|e| format!("Couldn't save the proxy password to the system keychain: {e}"))?,
+    }
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_setting(&conn, HTTP_PROXY_SETTING, http_proxy)?;
+        write_setting(&conn, HTTPS_PROXY_SETTING, https_proxy)?;
+        write_setting(&conn, PROXY_USER_SETTING, settings.username.trim())?;
+    }
+    get_proxy_settings(db)
+}
+
+#[tauri::command]
+pub fn set_local_only_mode(db: State<DbState>, enabled: bool) -> Result<NetworkMode, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, LOCAL_ONLY_SETTING, if enabled { "true" } else { "false" })?;
+    Ok(NetworkMode { local_only: enabled })
+}
@@ -0,0 +1,152 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use tauri::State;
+use chrono::Utc;
+
+use crate::DbState;
+use crate::executor;
+
+const DEFAULT_LIMIT: i64 = 20;
+
+/// A note the user attached to a run or a log entry.
+#[derive(Debug, Serialize, Clone)]
+pub struct RunNote {
+    pub id: i64,
+    /// `run` or `log`.
+    pub kind: String,
+    /// Run id, or log entry id as text.
+    pub target: String,
+    pub agent_id: String,
+    pub agent_name: String,
+    pub note: String,
+    /// The matching part of the note with the search terms between `[` and `]`; empty when
+    /// listing without a query.
+    pub snippet: String,
+    pub updated_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS run_notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            target TEXT NOT NULL,
+            agent_id TEXT DEFAULT '',
+            note TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            UNIQUE(kind, target)
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS run_notes_fts USING fts5(note);
+    ").expect("Failed to initialize note tables");
+}
+
+/// Sets, replaces or (with a blank note) removes the note on one run or log entry, keeping
+/// the full-text index in step.
+fn annotate(conn: &mut Connection, kind: &str, target: &str, agent_id: &str, note: &str) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let existing: Option<i64> = tx.query_row(
+        "SELECT id FROM run_notes WHERE kind = ?1 AND target = ?2",
+        params![kind, target],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    if let Some(id) = existing {
+        tx.execute("DELETE FROM run_notes_fts WHERE rowid = ?1", params![id]).map_err(|e| e.to_string())?;
+    }
+
+    let note = note.trim();
+    if note.is_empty() {
+        tx.execute("DELETE FROM run_notes WHERE kind = ?1 AND target = ?2", params![kind, target])
+            .map_err(|e| e.to_string())?;
+    } else {
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO run_notes (kind, target, agent_id, note, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+             ON CONFLICT(kind, target) DO UPDATE SET note = excluded.note, updated_at = excluded.updated_at",
+            params![kind, target, agent_id, note, now],
+        ).map_err(|e| e.to_string())?;
+        let id: i64 = tx.query_row(
+            "SELECT id FROM run_notes WHERE kind = ?1 AND target = ?2",
+            params![kind, target],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+        tx.execute("INSERT INTO run_notes_fts (rowid, note) VALUES (?1, ?2)", params![id, note])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Turns what the user typed into an FTS5 query: every word must appear, as a prefix, so
+/// punctuation and FTS operators in the input can't make the query invalid.
+fn match_query(query: &str) -> String {
+    query.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<RunNote> {
+    Ok(RunNote {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        target: row.get(2)?,
+        agent_id: row.get(3)?,
+        agent_name: row.get(4)?,
+        note: row.get(5)?,
+        updated_at: row.get(6)?,
+        snippet: row.get(7)?,
+    })
+}
+
+// ─── Note Commands ───
+
+/// Attaches a note to a run, e.g. "this is the run where it misfiled the invoice". A blank
+/// note removes it.
+#[tauri::command]
+pub fn annotate_run(db: State<DbState>, run_id: String, note: String) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let run = executor::load_run(&conn, &run_id)?;
+    annotate(&mut conn, "run", &run_id, &run.agent_id, &note)
+}
+
+/// Attaches a note to an execution log entry. A blank note removes it.
+#[tauri::command]
+pub fn annotate_log(db: State<DbState>, log_id: i64, note: String) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let agent_id: String = conn.query_row(
+        "SELECT agent_id FROM execution_logs WHERE id = ?1",
+        params![log_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?.ok_or_else(|| "Log entry not found".to_string())?;
+    annotate(&mut conn, "log", &log_id.to_string(), &agent_id, &note)
+}
+
+/// Notes matching `query`, best match first; every word has to appear. An empty query lists
+/// the most recently edited notes.
+#[tauri::command]
+pub fn search_notes(db: State<DbState>, query: String, limit: Option<i64>) -> Result<Vec<RunNote>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    let query = match_query(&query);
+    let rows = if query.is_empty() {
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.kind, n.target, n.agent_id, COALESCE(a.name, ''), n.note, n.updated_at, ''
+             FROM run_notes n LEFT JOIN agents a ON a.id = n.agent_id
+             ORDER BY n.updated_at DESC LIMIT ?1"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![limit], row_to_note).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+    } else {
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.kind, n.target, n.agent_id, COALESCE(a.name, ''), n.note, n.updated_at,
+                    snippet(run_notes_fts, 0, '[', ']', '…', 16)
+             FROM run_notes_fts JOIN run_notes n ON n.id = run_notes_fts.rowid
+             LEFT JOIN agents a ON a.id = n.agent_id
+             WHERE run_notes_fts MATCH ?1 ORDER BY bm25(run_notes_fts) LIMIT ?2"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![query, limit], row_to_note).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+    };
+    rows.map_err(|e| e.to_string())
+}
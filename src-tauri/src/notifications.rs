@@ -0,0 +1,195 @@
+use chrono::{Local, NaiveTime};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::net;
+use crate::routing;
+use crate::{DbState, read_setting, update_agent_config, write_setting};
+
+const SETTINGS_KEY: &str = "notification_settings";
+/// Where an agent's overrides live in its config.
+const AGENT_KEY: &str = "notifications";
+const CHANNELS: &[&str] = &["desktop", "telegram", "slack"];
+
+/// What the user hears about, and where, unless an agent says otherwise.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationSettings {
+    #[serde(default)]
+    pub on_success: bool,
+    #[serde(default = "yes")]
+    pub on_failure: bool,
+    #[serde(default = "yes")]
+    pub on_approval: bool,
+    /// `desktop`, `telegram` or `slack`.
+    #[serde(default = "desktop")]
+    pub channel: String,
+    /// The chat or user id for `telegram` and `slack`.
+    #[serde(default)]
+    pub target: String,
+    /// `HH:MM` local time; empty for no quiet hours. The window may cross midnight.
+    #[serde(default)]
+    pub quiet_start: String,
+    #[serde(default)]
+    pub quiet_end: String,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn desktop() -> String {
+    "desktop".into()
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            on_success: false,
+            on_failure: true,
+            on_approval: true,
+            channel: desktop(),
+            target: String::new(),
+            quiet_start: String::new(),
+            quiet_end: String::new(),
+        }
+    }
+}
+
+/// One agent's overrides; anything unset follows the global settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AgentNotifications {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_success: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_approval: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Notify even during quiet hours, for agents that can't wait until morning.
+    #[serde(default)]
+    pub ignore_quiet_hours: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Success,
+    Failure,
+    Approval,
+    /// Sent by the agent itself with the `notify` tool.
+    Message,
+}
+
+fn settings(conn: &Connection) -> Result<NotificationSettings, String> {
+    Ok(read_setting(conn, SETTINGS_KEY)?.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default())
+}
+
+fn agent_overrides(conn: &Connection, agent_id: &str) -> AgentNotifications {
+    conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        .and_then(|config| config.get(AGENT_KEY).cloned())
+        .and_then(|prefs| serde_json::from_value(prefs).ok())
+        .unwrap_or_default()
+}
+
+fn quiet_time(value: &str) -> Result<Option<NaiveTime>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map(Some).map_err(|_| format!("\"{value}\" isn't a time like 22:00"))
+}
+
+fn in_quiet_hours(settings: &NotificationSettings, now: NaiveTime) -> bool {
+    match (quiet_time(&settings.quiet_start), quiet_time(&settings.quiet_end)) {
+        (Ok(Some(start)), Ok(Some(end))) if start <= end => now >= start && now < end,
+        (Ok(Some(start)), Ok(Some(end))) => now >= start || now < end,
+        _ => false,
+    }
+}
+
+fn check_channel(channel: &str, target: &str) -> Result<(), String> {
+    if !CHANNELS.contains(&channel) {
+        return Err(format!("Unknown notification channel: {channel}"));
+    }
+    if channel != "desktop" && target.trim().is_empty() {
+        return Err(format!("Say who to notify on {channel}"));
+    }
+    Ok(())
+}
+
+/// Tells the user about `event` the way the agent's settings say. Returns whether it went
+/// out; a chat message that can't be sent falls back to a desktop notification.
+pub fn notify(app: &AppHandle, conn: &Connection, agent_id: &str, event: Event, title: &str, message: &str) -> bool {
+    let global = settings(conn).unwrap_or_default();
+    let agent = agent_overrides(conn, agent_id);
+    let wanted = match event {
+        Event::Success => agent.on_success.unwrap_or(global.on_success),
+        Event::Failure => agent.on_failure.unwrap_or(global.on_failure),
+        Event::Approval => agent.on_approval.unwrap_or(global.on_approval),
+        Event::Message => true,
+    };
+    if !wanted || (!agent.ignore_quiet_hours && in_quiet_hours(&global, Local::now().time())) {
+        return false;
+    }
+    let (channel, target) = match agent.channel {
+        Some(channel) => (channel, agent.target.unwrap_or_default()),
+        None => (global.channel, global.target),
+    };
+    let payload = json!({ "agent_id": agent_id, "title": title, "message": message });
+    if channel == "desktop" || net::check_egress(conn, "notification", None, agent_id, "").is_err() {
+        return app.emit("agent-notification", payload).is_ok();
+    }
+    // Sending can take a while; the caller holds the database.
+    let (app, text) = (app.clone(), if message.is_empty() { title.to_string() } else { format!("{title}\n{message}") });
+    std::thread::spawn(move || {
+        if let Err(e) = routing::deliver(&channel, &target, &text) {
+            eprintln!("Couldn't send the notification on {channel}: {e}");
+            let _ = app.emit("agent-notification", payload);
+        }
+    });
+    true
+}
+
+// ─── Notification Commands ───
+
+#[tauri::command]
+pub fn get_notification_settings(db: State<DbState>) -> Result<NotificationSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings(&conn)
+}
+
+#[tauri::command]
+pub fn set_notification_settings(db: State<DbState>, settings: NotificationSettings) -> Result<NotificationSettings, String> {
+    check_channel(&settings.channel, &settings.target)?;
+    if quiet_time(&settings.quiet_start)?.is_some() != quiet_time(&settings.quiet_end)?.is_some() {
+        return Err("Quiet hours need both a start and an end".into());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, SETTINGS_KEY, &serde_json::to_string(&settings).map_err(|e| e.to_string())?)?;
+    self::settings(&conn)
+}
+
+#[tauri::command]
+pub fn get_agent_notifications(db: State<DbState>, agent_id: String) -> Result<AgentNotifications, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(agent_overrides(&conn, &agent_id))
+}
+
+/// `None` goes back to the global settings for everything.
+#[tauri::command]
+pub fn set_agent_notifications(db: State<DbState>, agent_id: String, prefs: Option<AgentNotifications>) -> Result<(), String> {
+    if let Some(AgentNotifications { channel: Some(channel), target, .. }) = &prefs {
+        check_channel(channel, target.as_deref().unwrap_or_default())?;
+    }
+    let value = prefs.map(|p| serde_json::to_value(p).map_err(|e| e.to_string())).transpose()?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| match value {
+        Some(v) => { config.insert(AGENT_KEY.into(), v); }
+        None => { config.remove(AGENT_KEY); }
+    })
+}
@@ -0,0 +1,176 @@
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::State;
+use chrono::{DateTime, Utc};
+
+use crate::DbState;
+
+const RECENT_ARTIFACTS: i64 = 20;
+const DEFAULT_LIMIT: usize = 20;
+
+/// Pages and settings sections the palette can open, as (target, title, keywords).
+const PLACES: &[(&str, &str, &str)] = &[
+    ("chat", "Go to Chat", "conversation talk"),
+    ("agents", "Go to Agents", "automations bots"),
+    ("schedules", "Go to Schedules", "calendar cron timer"),
+    ("logs", "Go to Logs", "history runs activity"),
+    ("settings", "Open Settings", "preferences options"),
+    ("settings/model", "Settings: Model provider", "llm api key openai anthropic ollama local"),
+    ("settings/network", "Settings: Network and proxy", "offline local-only proxy internet"),
+    ("settings/privacy", "Settings: Privacy and audit log", "pii personal data audit"),
+    ("settings/budgets", "Settings: Budgets and usage", "cost spend money tokens report"),
+    ("settings/knowledge", "Settings: Knowledge folders", "documents index search rag"),
+    ("settings/personas", "Settings: Personas", "tone system prompt"),
+    ("settings/sync", "Settings: Sync", "backup devices webdav"),
+];
+
+/// Something the user can do from the command palette.
+#[derive(Debug, Serialize, Clone)]
+pub struct PaletteAction {
+    /// Stable id, passed back to `record_palette_action`, e.g. `run_agent:<agent id>`.
+    pub id: String,
+    /// `run_agent`, `simulate_agent`, `open` or `artifact`.
+    pub kind: String,
+    pub title: String,
+    pub subtitle: String,
+    /// Agent id, page or settings section, or artifact id, depending on `kind`.
+    pub target: String,
+    pub score: f64,
+    #[serde(skip)]
+    keywords: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS palette_usage (
+            action_id TEXT PRIMARY KEY,
+            uses INTEGER NOT NULL DEFAULT 0,
+            last_used_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize palette tables");
+}
+
+fn action(kind: &str, target: &str, title: String, subtitle: String, keywords: &str) -> PaletteAction {
+    PaletteAction {
+        id: format!("{kind}:{target}"),
+        kind: kind.into(),
+        title,
+        subtitle,
+        target: target.into(),
+        score: 0.0,
+        keywords: keywords.into(),
+    }
+}
+
+fn actions(conn: &Connection) -> Result<Vec<PaletteAction>, String> {
+    let mut actions = Vec::new();
+
+    let mut stmt = conn.prepare("SELECT id, name, goal FROM agents ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let agents = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| e.to_string())?;
+    for agent in agents {
+        let (id, name, goal) = agent.map_err(|e| e.to_string())?;
+        actions.push(action("run_agent", &id, format!("Run {name}"), goal.clone(), "start execute"));
+        actions.push(action("simulate_agent", &id, format!("Simulate {name}"), goal, "dry run test preview"));
+    }
+
+    actions.extend(PLACES.iter().map(|(target, title, keywords)| action("open", target, title.to_string(), String::new(), keywords)));
+
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.name, r.kind, COALESCE(a.name, '')
+         FROM run_artifacts r LEFT JOIN agents a ON a.id = r.agent_id
+         ORDER BY r.created_at DESC LIMIT ?1"
+    ).map_err(|e| e.to_string())?;
+    let artifacts = stmt.query_map(params![RECENT_ARTIFACTS], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+    }).map_err(|e| e.to_string())?;
+    for artifact in artifacts {
+        let (id, name, kind, agent) = artifact.map_err(|e| e.to_string())?;
+        let subtitle = if agent.is_empty() { kind.replace('_', " ") } else { format!("{} · {agent}", kind.replace('_', " ")) };
+        actions.push(action("artifact", &id, name, subtitle, "result output summary"));
+    }
+    Ok(actions)
+}
+
+/// Scores `query` as a subsequence of `text`, ignoring case. Matches at the start of a word
+/// and runs of consecutive matches score higher, gaps lower. `None` unless every query
+/// character appears in order.
+fn fuzzy_score(query: &str, text: &str) -> Option<f64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0.0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (pos..text.len()).find(|&i| text[i] == q)?;
+        score += 1.0;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 2.0;
+        }
+        match previous {
+            Some(p) if p + 1 == found => score += 1.5,
+            Some(p) => score -= ((found - p - 1) as f64 * 0.1).min(1.0),
+            None => score -= (found as f64 * 0.05).min(1.0),
+        }
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// How often and how lately each action was picked, as a ranking bonus.
+fn usage_bonus(conn: &Connection) -> Result<HashMap<String, f64>, String> {
+    let mut stmt = conn.prepare("SELECT action_id, uses, last_used_at FROM palette_usage")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| e.to_string())?;
+    let now = Utc::now();
+    let mut bonus = HashMap::new();
+    for row in rows {
+        let (id, uses, last_used) = row.map_err(|e| e.to_string())?;
+        let days = DateTime::parse_from_rfc3339(&last_used)
+            .map(|t| (now - t.with_timezone(&Utc)).num_hours() as f64 / 24.0)
+            .unwrap_or(30.0);
+        bonus.insert(id, 2.0 * (1.0 + uses as f64).ln() + 2.0 / (1.0 + days));
+    }
+    Ok(bonus)
+}
+
+// ─── Palette Commands ───
+
+/// Actions matching `query`, best first. The title counts most, then keywords and subtitle;
+/// actions picked often or recently rank higher. An empty query lists the most used.
+#[tauri::command]
+pub fn search_actions(db: State<DbState>, query: String, limit: Option<usize>) -> Result<Vec<PaletteAction>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let bonus = usage_bonus(&conn)?;
+    let query = query.trim();
+
+    let mut ranked: Vec<PaletteAction> = actions(&conn)?.into_iter().filter_map(|mut action| {
+        let matched = if query.is_empty() {
+            0.0
+        } else {
+            let title = fuzzy_score(query, &action.title).map(|s| s * 1.5);
+            let rest = fuzzy_score(query, &format!("{} {}", action.keywords, action.subtitle));
+            title.into_iter().chain(rest).reduce(f64::max)?
+        };
+        action.score = matched + bonus.get(&action.id).copied().unwrap_or(0.0);
+        Some(action)
+    }).collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+    ranked.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(ranked)
+}
+
+/// Records that the user picked an action, so it ranks higher next time.
+#[tauri::command]
+pub fn record_palette_action(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO palette_usage (action_id, uses, last_used_at) VALUES (?1, 1, ?2)
+         ON CONFLICT(action_id) DO UPDATE SET uses = uses + 1, last_used_at = excluded.last_used_at",
+        params![id, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::context;
+use crate::{DbState, update_agent_config};
+
+/// How an agent's writing should sound, added to its prompt as a short instruction.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Tone {
+    #[default]
+    Neutral,
+    Formal,
+    Friendly,
+    Concise,
+}
+
+impl Tone {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Tone::Neutral => "neutral",
+            Tone::Formal => "formal",
+            Tone::Friendly => "friendly",
+            Tone::Concise => "concise",
+        }
+    }
+
+    fn parse(s: &str) -> Tone {
+        serde_json::from_value(Value::String(s.to_string())).unwrap_or_default()
+    }
+
+    fn instruction(&self) -> Option<&'static str> {
+        match self {
+            Tone::Neutral => None,
+            Tone::Formal => Some("Write in a formal, professional tone suitable for a manager or client."),
+            Tone::Friendly => Some("Write in a warm, friendly tone, as to a colleague you get on well with."),
+            Tone::Concise => Some("Keep writing short and to the point; no pleasantries or filler."),
+        }
+    }
+}
+
+/// A reusable system prompt from the persona library. Built-in personas have ids starting
+/// with `builtin:` and can't be changed or deleted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Persona {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub system_prompt: String,
+    pub tone: Tone,
+    pub builtin: bool,
+    pub updated_at: String,
+}
+
+/// An agent's persona settings, stored under `persona` in its `config_json`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AgentPersona {
+    /// Library persona the agent builds on.
+    #[serde(default)]
+    pub persona_id: Option<String>,
+    /// The agent's own instructions, added after the library persona's.
+    #[serde(default)]
+    pub system_prompt: String,
+    /// Overrides the library persona's tone.
+    #[serde(default)]
+    pub tone: Option<Tone>,
+}
+
+fn builtin_personas() -> Vec<Persona> {
+    let persona = |id: &str, name: &str, description: &str, system_prompt: &str, tone| Persona {
+        id: format!("builtin:{id}"),
+        name: name.into(),
+        description: description.into(),
+        system_prompt: system_prompt.into(),
+        tone,
+        builtin: true,
+        updated_at: String::new(),
+    };
+    vec![
+        persona(
+            "assistant",
+            "Personal assistant",
+            "Helpful all-rounder for everyday tasks",
+            "You are a dependable personal assistant. You work carefully, never invent facts, and say plainly when something is missing.",
+            Tone::Friendly,
+        ),
+        persona(
+            "secretary",
+            "Executive secretary",
+            "Polished emails and summaries for work",
+            "You are an experienced executive secretary. You write clear, polite, well-structured messages and summaries that a busy manager can act on at a glance.",
+            Tone::Formal,
+        ),
+        persona(
+            "analyst",
+            "Analyst",
+            "Sticks to the numbers and the sources",
+            "You are a careful analyst. You base every statement on the material you were given, point out uncertainty, and prefer lists and figures to prose.",
+            Tone::Concise,
+        ),
+    ]
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS personas (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT DEFAULT '',
+            system_prompt TEXT NOT NULL,
+            tone TEXT DEFAULT 'neutral',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize persona tables");
+}
+
+fn row_to_persona(row: &rusqlite::Row) -> rusqlite::Result<Persona> {
+    Ok(Persona {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        system_prompt: row.get(3)?,
+        tone: Tone::parse(&row.get::<_, String>(4)?),
+        builtin: false,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn load_persona(conn: &Connection, id: &str) -> Result<Option<Persona>, String> {
+    if let Some(persona) = builtin_personas().into_iter().find(|p| p.id == id) {
+        return Ok(Some(persona));
+    }
+    conn.query_row(
+        "SELECT id, name, description, system_prompt, tone, updated_at FROM personas WHERE id = ?1",
+        params![id],
+        row_to_persona,
+    ).optional().map_err(|e| e.to_string())
+}
+
+pub fn agent_persona(conn: &Connection, agent_id: &str) -> Result<AgentPersona, String> {
+    let config: Option<String> = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    Ok(config.and_then(|c| serde_json::from_str::<Value>(&c).ok())
+        .and_then(|c| c.get("persona").cloned())
+        .and_then(|p| serde_json::from_value(p).ok())
+        .unwrap_or_default())
+}
+
+/// The system prompt for one of the agent's own model calls: the library persona, the
+/// agent's instructions, `base` (what this particular call is for) and the tone.
+/// A library persona missing on this device, e.g. after sync, is skipped.
+pub fn system_prompt(conn: &Connection, agent_id: &str, base: &str) -> Result<String, String> {
+    let settings = agent_persona(conn, agent_id)?;
+    let persona = match &settings.persona_id {
+        Some(id) => load_persona(conn, id)?,
+        None => None,
+    };
+    let tone = settings.tone.or(persona.as_ref().map(|p| p.tone)).unwrap_or_default();
+    let parts = [
+        persona.map(|p| p.system_prompt).unwrap_or_default(),
+        settings.system_prompt,
+        base.to_string(),
+        tone.instruction().unwrap_or_default().to_string(),
+    ];
+    Ok(parts.iter().map(|p| p.trim()).filter(|p| !p.is_empty()).collect::<Vec<_>>().join("\n\n"))
+}
+
+fn persona_users(conn: &Connection, persona_id: &str) -> Result<usize, String> {
+    let mut stmt = conn.prepare("SELECT id FROM agents").map_err(|e| e.to_string())?;
+    let ids = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+    let mut users = 0;
+    for id in ids {
+        if agent_persona(conn, &id.map_err(|e| e.to_string())?)?.persona_id.as_deref() == Some(persona_id) {
+            users += 1;
+        }
+    }
+    Ok(users)
+}
+
+// ─── Persona Commands ───
+
+/// Built-in personas first, then the user's own by name.
+#[tauri::command]
+pub fn list_personas(db: State<DbState>) -> Result<Vec<Persona>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, system_prompt, tone, updated_at FROM personas ORDER BY name COLLATE NOCASE"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_persona).map_err(|e| e.to_string())?;
+
+    let mut personas = builtin_personas();
+    for row in rows {
+        personas.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(personas)
+}
+
+/// Creates a persona, or updates it when `id` is given.
+#[tauri::command]
+pub fn save_persona(
+    db: State<DbState>,
+    id: Option<String>,
+    name: String,
+    description: String,
+    system_prompt: String,
+    tone: Option<Tone>,
+) -> Result<Persona, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if name.trim().is_empty() || system_prompt.trim().is_empty() {
+        return Err("A persona needs a name and a system prompt".into());
+    }
+    if id.as_deref().is_some_and(|id| id.starts_with("builtin:")) {
+        return Err("Built-in personas can't be changed; save a copy instead".into());
+    }
+    let tone = tone.unwrap_or_default();
+    let now = Utc::now().to_rfc3339();
+    let id = match id {
+        Some(id) => {
+            let updated = conn.execute(
+                "UPDATE personas SET name = ?1, description = ?2, system_prompt = ?3, tone = ?4, updated_at = ?5 WHERE id = ?6",
+                params![name, description, system_prompt, tone.as_str(), now, id],
+            ).map_err(|e| e.to_string())?;
+            if updated == 0 {
+                return Err("Persona not found".into());
+            }
+            id
+        }
+        None => {
+            let id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO personas (id, name, description, system_prompt, tone, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                params![id, name, description, system_prompt, tone.as_str(), now],
+            ).map_err(|e| e.to_string())?;
+            id
+        }
+    };
+    Ok(Persona { id, name, description, system_prompt, tone, builtin: false, updated_at: now })
+}
+
+/// Deletes a persona; refuses while an agent still uses it.
+#[tauri::command]
+pub fn delete_persona(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if id.starts_with("builtin:") {
+        return Err("Built-in personas can't be deleted".into());
+    }
+    let users = persona_users(&conn, &id)?;
+    if users > 0 {
+        return Err(format!("Persona is still used by {users} agent(s)"));
+    }
+    conn.execute("DELETE FROM personas WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_agent_persona(db: State<DbState>, agent_id: String) -> Result<AgentPersona, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    agent_persona(&conn, &agent_id)
+}
+
+#[tauri::command]
+pub fn set_agent_persona(db: State<DbState>, agent_id: String, persona: AgentPersona) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Some(id) = &persona.persona_id {
+        load_persona(&conn, id)?.ok_or("Persona not found")?;
+    }
+    let value = serde_json::to_value(&persona).map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| {
+        config.insert("persona".into(), value);
+    })
+}
+
+/// The system prompt the agent's model steps will be sent, with its persona and tone applied.
+#[tauri::command]
+pub fn preview_agent_prompt(db: State<DbState>, agent_id: String) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    system_prompt(&conn, &agent_id, context::STEP_SYSTEM)
+}
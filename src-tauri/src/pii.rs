@@ -0,0 +1,210 @@
+use regex::Regex;
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::LazyLock;
+use tauri::State;
+
+use crate::{DbState, read_setting, update_agent_config};
+
+/// Used for agents without their own `pii_policy`.
+const DEFAULT_POLICY_SETTING: &str = "pii_default_policy";
+
+/// What happens when personal data is about to leave the machine.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiPolicy {
+    /// Send it, but record the detection.
+    Allow,
+    /// Replace each match with a placeholder before sending.
+    Mask,
+    /// Hold the step for the user's approval.
+    Block,
+}
+
+impl PiiPolicy {
+    fn parse(s: &str) -> Option<PiiPolicy> {
+        match s {
+            "allow" => Some(PiiPolicy::Allow),
+            "mask" => Some(PiiPolicy::Mask),
+            "block" => Some(PiiPolicy::Block),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Detection {
+    /// `email`, `phone`, `card_number` or `national_id`.
+    pub kind: &'static str,
+    /// The match with most characters hidden, safe to keep in the audit trail.
+    pub preview: String,
+}
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+static CARD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap());
+/// US SSN, UK National Insurance number and Indian Aadhaar.
+static NATIONAL_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b\d{3}-\d{2}-\d{4}\b|\b[A-CEGHJ-PR-TW-Z]{2}\d{6}[A-D]\b|\b\d{4} \d{4} \d{4}\b").unwrap()
+});
+static PHONE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)[ .-]?)?\d{3,4}[ .-]?\d{3,4}(?:[ .-]?\d{2,4})?").unwrap());
+
+fn luhn(digits: &str) -> bool {
+    let digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits.iter().rev().enumerate()
+        .map(|(i, d)| if i % 2 == 1 { let x = d * 2; if x > 9 { x - 9 } else { x } } else { *d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn preview(kind: &str, text: &str) -> String {
+    match kind {
+        "email" => {
+            let (user, domain) = text.split_once('@').unwrap_or((text, ""));
+            format!("{}***@{domain}", user.chars().next().unwrap_or('*'))
+        }
+        _ => {
+            let digits: String = text.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+            let tail: String = digits.chars().skip(digits.chars().count().saturating_sub(4)).collect();
+            format!("***{tail}")
+        }
+    }
+}
+
+fn placeholder(kind: &str) -> &'static str {
+    match kind {
+        "email" => "[email hidden]",
+        "phone" => "[phone hidden]",
+        "card_number" => "[card number hidden]",
+        _ => "[ID hidden]",
+    }
+}
+
+/// Matches in `text` as `(start, end, kind)`, earlier detectors taking precedence on overlap.
+pub(crate) fn find(text: &str) -> Vec<(usize, usize, &'static str)> {
+    let mut found: Vec<(usize, usize, &'static str)> = Vec::new();
+    let mut add = |start: usize, end: usize, kind: &'static str| {
+        if !found.iter().any(|(s, e, _)| start < *e && end > *s) {
+            found.push((start, end, kind));
+        }
+    };
+    for m in EMAIL.find_iter(text) {
+        add(m.start(), m.end(), "email");
+    }
+    for m in NATIONAL_ID.find_iter(text) {
+        add(m.start(), m.end(), "national_id");
+    }
+    for m in CARD.find_iter(text).filter(|m| luhn(m.as_str())) {
+        add(m.start(), m.end(), "card_number");
+    }
+    for m in PHONE.find_iter(text) {
+        let digits = m.as_str().chars().filter(char::is_ascii_digit).count();
+        if (10..=15).contains(&digits) {
+            add(m.start(), m.end(), "phone");
+        }
+    }
+    found.sort_by_key(|(start, _, _)| *start);
+    found
+}
+
+pub fn scan(text: &str) -> Vec<Detection> {
+    find(text).into_iter()
+        .map(|(start, end, kind)| Detection { kind, preview: preview(kind, &text[start..end]) })
+        .collect()
+}
+
+pub fn mask(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, kind) in find(text) {
+        out.push_str(&text[last..start]);
+        out.push_str(placeholder(kind));
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Scans every string inside a tool's params.
+pub fn scan_value(value: &Value) -> Vec<Detection> {
+    match value {
+        Value::String(s) => scan(s),
+        Value::Array(items) => items.iter().flat_map(scan_value).collect(),
+        Value::Object(map) => map.values().flat_map(scan_value).collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn mask_value(value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(mask(s)),
+        Value::Array(items) => Value::Array(items.iter().map(mask_value).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), mask_value(v))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// "2 email addresses, 1 phone number" style summary.
+pub fn describe(detections: &[Detection]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for d in detections {
+        match counts.iter_mut().find(|(k, _)| *k == d.kind) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((d.kind, 1)),
+        }
+    }
+    counts.iter()
+        .map(|(kind, n)| {
+            let noun = match *kind {
+                "email" => "email address",
+                "phone" => "phone number",
+                "card_number" => "card number",
+                _ => "national ID",
+            };
+            format!("{n} {noun}{}", if *n == 1 { "" } else if noun.ends_with('s') { "es" } else { "s" })
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The agent's own policy, else the default setting; `None` when scanning is off.
+pub fn policy_for(conn: &Connection, agent_id: &str) -> Result<Option<PiiPolicy>, String> {
+    let config: String = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).unwrap_or_default();
+    let own = serde_json::from_str::<Value>(&config).ok()
+        .and_then(|c| c.get("pii_policy").and_then(Value::as_str).and_then(PiiPolicy::parse));
+    if own.is_some() {
+        return Ok(own);
+    }
+    Ok(read_setting(conn, DEFAULT_POLICY_SETTING)?.as_deref().and_then(PiiPolicy::parse))
+}
+
+/// Detail stored with a `pii_detected` audit event.
+pub fn audit_detail(tool: &str, policy: PiiPolicy, detections: &[Detection]) -> Value {
+    json!({ "tool": tool, "policy": policy, "detections": detections })
+}
+
+// ─── PII Commands ───
+
+/// Sets how one agent handles personal data; `None` falls back to `pii_default_policy`.
+#[tauri::command]
+pub fn set_agent_pii_policy(db: State<DbState>, agent_id: String, policy: Option<PiiPolicy>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| match policy {
+        Some(policy) => {
+            config.insert("pii_policy".into(), json!(policy));
+        }
+        None => {
+            config.remove("pii_policy");
+        }
+    })
+}
+
+/// Lets the UI preview what the scanner finds in a piece of text.
+#[tauri::command]
+pub fn scan_pii(text: String) -> Vec<Detection> {
+    scan(&text)
+}
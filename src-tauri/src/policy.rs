@@ -0,0 +1,162 @@
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::audit;
+
+const POLICY_FILE: &str = "policy.json";
+const LOCAL_ONLY_SETTING: &str = "local_only_mode";
+
+/// Settings an administrator provisions for every user of the machine. Only an administrator
+/// can write the file; the app reads it once at launch and never changes it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ManagedPolicy {
+    /// Model providers that may be used (`openai`, `anthropic`, `local`); empty allows all.
+    pub allowed_providers: Vec<String>,
+    /// Tools no agent may use.
+    pub disabled_tools: Vec<String>,
+    /// Forces local-only mode on or off.
+    pub local_only: Option<bool>,
+    /// Activity log entries older than this many days are deleted.
+    pub log_retention_days: Option<u32>,
+    /// Any other settings, pinned to these values.
+    pub settings: BTreeMap<String, String>,
+}
+
+struct Loaded {
+    path: PathBuf,
+    policy: Option<ManagedPolicy>,
+    error: String,
+}
+
+/// Where administrators put the policy file on each platform.
+fn policy_path() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        std::env::var_os("ProgramData")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("C:\\ProgramData"))
+            .join("OpenClaw Desktop")
+            .join(POLICY_FILE)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/OpenClaw Desktop").join(POLICY_FILE)
+    } else {
+        PathBuf::from("/etc/openclaw-desktop").join(POLICY_FILE)
+    }
+}
+
+/// The policy file, read on first use. A file that can't be read applies nothing; the error
+/// shows in `get_effective_policy` so the administrator can fix it.
+fn loaded() -> &'static Loaded {
+    static LOADED: OnceLock<Loaded> = OnceLock::new();
+    LOADED.get_or_init(|| {
+        let path = policy_path();
+        let (policy, error) = match std::fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<ManagedPolicy>(&json) {
+                Ok(policy) => (Some(policy), String::new()),
+                Err(e) => (None, format!("The policy file couldn't be read: {e}")),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (None, String::new()),
+            Err(e) => (None, format!("The policy file couldn't be opened: {e}")),
+        };
+        Loaded { path, policy, error }
+    })
+}
+
+fn policy() -> Option<&'static ManagedPolicy> {
+    loaded().policy.as_ref()
+}
+
+/// The value a setting is pinned to by the policy, if it is.
+pub fn pinned_setting(key: &str) -> Option<String> {
+    let policy = policy()?;
+    if key == LOCAL_ONLY_SETTING {
+        if let Some(local_only) = policy.local_only {
+            return Some(local_only.to_string());
+        }
+    }
+    policy.settings.get(key).cloned()
+}
+
+/// Refuses a change to a setting the policy pins.
+pub fn check_setting_unlocked(key: &str) -> Result<(), String> {
+    match pinned_setting(key) {
+        Some(_) => Err("This setting is managed by your organization".into()),
+        None => Ok(()),
+    }
+}
+
+pub fn provider_allowed(provider: &str) -> bool {
+    policy().is_none_or(|p| p.allowed_providers.is_empty() || p.allowed_providers.iter().any(|a| a == provider))
+}
+
+pub fn check_provider(provider: &str) -> Result<(), String> {
+    match provider_allowed(provider) {
+        true => Ok(()),
+        false => Err(format!("Your organization's policy doesn't allow the {provider} model provider")),
+    }
+}
+
+/// Whether the model may be offered `tool` at all.
+pub fn tool_allowed(tool: &str) -> bool {
+    !policy().is_some_and(|p| p.disabled_tools.iter().any(|t| t == tool))
+}
+
+/// Refuses a step using a tool the policy disables and records the attempt in the audit trail.
+pub fn check_tool(conn: &Connection, agent_id: &str, run_id: &str, tool: &str) -> Result<(), String> {
+    if tool_allowed(tool) {
+        return Ok(());
+    }
+    let summary = format!("Your organization's policy doesn't allow {tool}");
+    audit::record(conn, "policy_blocked", agent_id, run_id, &summary, &json!({ "tool": tool }))?;
+    Err(summary)
+}
+
+/// Deletes activity log entries past the policy's retention period.
+pub fn enforce_retention(conn: &Connection) -> Result<usize, String> {
+    let Some(days) = policy().and_then(|p| p.log_retention_days) else { return Ok(0) };
+    let cutoff = (Utc::now() - Duration::days(days as i64)).to_rfc3339();
+    conn.execute("DELETE FROM execution_logs WHERE created_at < ?1", params![cutoff]).map_err(|e| e.to_string())
+}
+
+/// The policy in force and where it comes from.
+#[derive(Debug, Serialize, Clone)]
+pub struct EffectivePolicy {
+    pub path: String,
+    /// A readable policy file is in place.
+    pub managed: bool,
+    /// Why the file at `path` couldn't be applied; empty when it could or there is none.
+    pub error: String,
+    pub allowed_providers: Vec<String>,
+    pub disabled_tools: Vec<String>,
+    pub local_only: Option<bool>,
+    pub log_retention_days: Option<u32>,
+    /// Settings that can't be changed in the app.
+    pub locked_settings: Vec<String>,
+}
+
+// ─── Policy Commands ───
+
+#[tauri::command]
+pub fn get_effective_policy() -> EffectivePolicy {
+    let loaded = loaded();
+    let policy = loaded.policy.clone().unwrap_or_default();
+    let mut locked_settings: Vec<String> = policy.settings.keys().cloned().collect();
+    if policy.local_only.is_some() {
+        locked_settings.push(LOCAL_ONLY_SETTING.into());
+    }
+    EffectivePolicy {
+        path: loaded.path.display().to_string(),
+        managed: loaded.policy.is_some(),
+        error: loaded.error.clone(),
+        allowed_providers: policy.allowed_providers,
+        disabled_tools: policy.disabled_tools,
+        local_only: policy.local_only,
+        log_retention_days: policy.log_retention_days,
+        locked_settings,
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager, State};
+
+use crate::audit;
+use crate::calendar;
+use crate::grace;
+use crate::profiles;
+use crate::windows;
+use crate::DbState;
+
+const TICK_EVERY: std::time::Duration = std::time::Duration::from_secs(2);
+/// The monitor ticks every two seconds while the computer is awake, so a gap this long
+/// means it was asleep.
+const SLEEP_GAP_SECONDS: i64 = 20;
+
+/// When the monitor last ticked, and how many times the computer has woken since startup.
+#[derive(Default)]
+pub struct Power {
+    last_tick: Mutex<Option<DateTime<Utc>>>,
+    resumes: AtomicU64,
+}
+
+impl Power {
+    /// Watchers keep the last value they saw and start over when it changes.
+    pub fn resumes(&self) -> u64 {
+        self.resumes.load(Ordering::SeqCst)
+    }
+}
+
+/// A time the computer was asleep.
+#[derive(Debug, Serialize, Clone)]
+pub struct Suspension {
+    pub id: i64,
+    pub suspended_at: String,
+    pub resumed_at: String,
+    pub seconds: i64,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS suspensions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            suspended_at TEXT NOT NULL,
+            resumed_at TEXT NOT NULL,
+            seconds INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_suspensions_resumed ON suspensions(resumed_at);
+    ").expect("Failed to initialize power tables");
+}
+
+/// Milliseconds of `[from, to]` the computer spent asleep, to leave out of durations.
+pub fn asleep_ms(conn: &Connection, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<i64, String> {
+    let mut stmt = conn.prepare("SELECT suspended_at, resumed_at FROM suspensions WHERE resumed_at > ?1 AND suspended_at < ?2")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| e.to_string())?;
+    let mut total = 0;
+    for row in rows {
+        let (suspended, resumed) = row.map_err(|e| e.to_string())?;
+        let (Ok(suspended), Ok(resumed)) = (DateTime::parse_from_rfc3339(&suspended), DateTime::parse_from_rfc3339(&resumed)) else {
+            continue;
+        };
+        let overlap = resumed.with_timezone(&Utc).min(to) - suspended.with_timezone(&Utc).max(from);
+        total += overlap.num_milliseconds().max(0);
+    }
+    Ok(total)
+}
+
+/// Notices the computer having been asleep since the last tick and catches up. Called by the
+/// monitor, and by watchers that shouldn't act on stale timers before it has had a chance to.
+pub fn check(app: &AppHandle) {
+    let power = app.state::<Power>();
+    let now = Utc::now();
+    let asleep_since = {
+        let mut last = power.last_tick.lock().unwrap_or_else(|e| e.into_inner());
+        last.replace(now).filter(|tick| now - *tick > Duration::seconds(SLEEP_GAP_SECONDS))
+    };
+    if let Some(since) = asleep_since {
+        power.resumes.fetch_add(1, Ordering::SeqCst);
+        resumed(app, since, now);
+    }
+}
+
+/// Records how long the computer slept, moves countdowns on by that much so the user still
+/// gets their full chance to cancel, and refreshes what may have changed meanwhile.
+fn resumed(app: &AppHandle, since: DateTime<Utc>, now: DateTime<Utc>) {
+    let seconds = (now - since).num_seconds();
+    {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = conn.execute(
+            "INSERT INTO suspensions (suspended_at, resumed_at, seconds) VALUES (?1, ?2, ?3)",
+            params![since.to_rfc3339(), now.to_rfc3339(), seconds],
+        );
+        if let Err(e) = grace::postpone(&conn, since, now - since) {
+            eprintln!("Couldn't move pending countdowns after sleep: {e}");
+        }
+        let _ = audit::record(&conn, "system_resumed", "", "", &format!("Woke after {} minutes asleep", seconds / 60),
+            &json!({ "suspended_at": since.to_rfc3339(), "resumed_at": now.to_rfc3339(), "seconds": seconds }));
+    }
+    windows::broadcast(app, "system-resumed", &json!({ "suspended_at": since.to_rfc3339(), "seconds": seconds }));
+
+    // The network may be a different one now and feeds may have changed; neither should hold
+    // up the caller.
+    let app = app.clone();
+    let _ = std::thread::Builder::new()
+        .name("resume-refresh".into())
+        .spawn(move || {
+            if let Err(e) = profiles::check(&app) {
+                eprintln!("Network profile check after sleep failed: {e}");
+            }
+            calendar::refresh_if_configured(&app);
+        });
+}
+
+/// Starts the thread that notices the computer going to sleep and waking up.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("power-monitor".into())
+        .spawn(move || loop {
+            check(&app);
+            std::thread::sleep(TICK_EVERY);
+        })
+        .expect("Failed to start the power monitor");
+}
+
+// ─── Power Commands ───
+
+/// Recent times the computer was asleep, newest first.
+#[tauri::command]
+pub fn list_suspensions(db: State<DbState>, limit: Option<i64>) -> Result<Vec<Suspension>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT id, suspended_at, resumed_at, seconds FROM suspensions ORDER BY id DESC LIMIT ?1")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![limit.unwrap_or(50)], |row| Ok(Suspension {
+        id: row.get(0)?,
+        suspended_at: row.get(1)?,
+        resumed_at: row.get(2)?,
+        seconds: row.get(3)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
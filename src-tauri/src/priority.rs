@@ -0,0 +1,205 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+
+use crate::audit;
+use crate::tools::ToolSpec;
+use crate::windows;
+use crate::{ApprovalItem, DbState, ensure_column, read_setting, update_agent_config, write_setting};
+
+/// Lowest to highest.
+pub const LEVELS: &[&str] = &["low", "normal", "high", "urgent"];
+/// Minutes a high or urgent approval may wait before it is raised and shown again; `0`
+/// turns escalation off.
+const ESCALATE_SETTING: &str = "approval_escalate_minutes";
+const DEFAULT_ESCALATE_MINUTES: i64 = 30;
+/// An unanswered item is shown again at most this many times.
+const MAX_ESCALATIONS: i64 = 3;
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EscalationSettings {
+    /// `0` when escalation is off.
+    pub minutes: i64,
+    pub max_escalations: i64,
+}
+
+pub fn init_tables(conn: &Connection) {
+    ensure_column(conn, "approval_queue", "priority", "TEXT DEFAULT 'normal'").expect("Failed to migrate approval tables");
+    ensure_column(conn, "approval_queue", "escalations", "INTEGER DEFAULT 0").expect("Failed to migrate approval tables");
+    ensure_column(conn, "approval_queue", "escalated_at", "TEXT DEFAULT ''").expect("Failed to migrate approval tables");
+}
+
+/// Position in `LEVELS`; anything unknown counts as `normal`.
+pub fn rank(priority: &str) -> usize {
+    LEVELS.iter().position(|l| *l == priority).unwrap_or(1)
+}
+
+fn check_level(priority: &str) -> Result<(), String> {
+    if LEVELS.contains(&priority) {
+        Ok(())
+    } else {
+        Err(format!("Priority must be one of {}", LEVELS.join(", ")))
+    }
+}
+
+/// Priority for a step waiting on approval. Irreversible steps are `high`, other changes
+/// `normal` and the rest `low`; the agent's `approval_priority` replaces that, except that
+/// a step held for personal data or suspicious content never drops below `high`.
+pub fn classify(conn: &Connection, agent_id: &str, spec: &ToolSpec, flagged: bool) -> String {
+    let risk = if flagged || spec.irreversible { 2 } else if spec.side_effect { 1 } else { 0 };
+    let chosen = conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        .and_then(|config| config.get("approval_priority").and_then(Value::as_str).map(rank))
+        .unwrap_or(risk);
+    let level = if flagged { chosen.max(2) } else { chosen };
+    LEVELS[level].to_string()
+}
+
+/// Orders the queue in place. `order` is `priority` (the default: pending first, most
+/// urgent then longest waiting first), `newest` or `oldest`; `group_by` (`agent`, `action`
+/// or `priority`) keeps each group's items together.
+pub fn sort(items: &mut [ApprovalItem], order: Option<&str>, group_by: Option<&str>) -> Result<(), String> {
+    if !matches!(group_by, None | Some("agent" | "action" | "priority" | "none")) {
+        return Err("Group by agent, action or priority".into());
+    }
+    let order = order.unwrap_or("priority");
+    if !matches!(order, "priority" | "newest" | "oldest") {
+        return Err("Order by priority, newest or oldest".into());
+    }
+    items.sort_by(|a, b| {
+        let group = match group_by {
+            Some("agent") => a.agent_id.cmp(&b.agent_id),
+            Some("action") => a.action_type.cmp(&b.action_type),
+            Some("priority") => rank(&b.priority).cmp(&rank(&a.priority)),
+            _ => std::cmp::Ordering::Equal,
+        };
+        group.then_with(|| match order {
+            "newest" => b.created_at.cmp(&a.created_at),
+            "oldest" => a.created_at.cmp(&b.created_at),
+            _ => {
+                let (a_pending, b_pending) = (a.status == "pending", b.status == "pending");
+                b_pending.cmp(&a_pending).then_with(|| if a_pending && b_pending {
+                    rank(&b.priority).cmp(&rank(&a.priority)).then_with(|| a.created_at.cmp(&b.created_at))
+                } else {
+                    b.created_at.cmp(&a.created_at)
+                })
+            }
+        })
+    });
+    Ok(())
+}
+
+fn escalate_minutes(conn: &Connection) -> Result<i64, String> {
+    Ok(read_setting(conn, ESCALATE_SETTING)?
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(DEFAULT_ESCALATE_MINUTES)
+        .max(0))
+}
+
+/// Raises pending high and urgent items that have waited past the threshold since they
+/// were queued or last raised, and returns them so the user can be told again.
+fn escalate(conn: &Connection) -> Result<Vec<(String, String, String)>, String> {
+    let minutes = escalate_minutes(conn)?;
+    if minutes == 0 {
+        return Ok(Vec::new());
+    }
+    let cutoff = Utc::now() - Duration::minutes(minutes);
+    let mut stmt = conn.prepare(
+        "SELECT id, agent_id, priority, created_at, escalated_at, escalations FROM approval_queue
+         WHERE status = 'pending' AND priority IN ('high', 'urgent') AND escalations < ?1"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![MAX_ESCALATIONS], |row| Ok((
+        row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?,
+        row.get::<_, String>(3)?, row.get::<_, String>(4)?, row.get::<_, i64>(5)?,
+    ))).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+
+    let mut raised = Vec::new();
+    for (id, agent_id, priority, created_at, escalated_at, escalations) in rows {
+        let since = if escalated_at.is_empty() { &created_at } else { &escalated_at };
+        let waiting_since = DateTime::parse_from_rfc3339(since).map(|t| t.with_timezone(&Utc)).unwrap_or_else(|_| Utc::now());
+        if waiting_since > cutoff {
+            continue;
+        }
+        let bumped = LEVELS[(rank(&priority) + 1).min(LEVELS.len() - 1)];
+        conn.execute(
+            "UPDATE approval_queue SET priority = ?1, escalations = ?2, escalated_at = ?3 WHERE id = ?4",
+            params![bumped, escalations + 1, Utc::now().to_rfc3339(), id],
+        ).map_err(|e| e.to_string())?;
+        audit::record(conn, "approval_escalated", &agent_id, "", &format!("Still waiting for approval after {minutes} minutes"),
+            &json!({ "id": id, "from": priority, "to": bumped }))?;
+        raised.push((id, agent_id, bumped.to_string()));
+    }
+    Ok(raised)
+}
+
+/// Starts the thread that raises and re-announces approvals left unanswered too long.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("approval-escalation".into())
+        .spawn(move || loop {
+            std::thread::sleep(CHECK_EVERY);
+            let raised = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                escalate(&conn).unwrap_or_else(|e| {
+                    eprintln!("Approval escalation failed: {e}");
+                    Vec::new()
+                })
+            };
+            for (id, agent_id, priority) in raised {
+                windows::broadcast(&app, "approval-escalated", &json!({ "id": id, "agent_id": agent_id, "priority": priority }));
+                windows::broadcast(&app, "approvals-changed", &id);
+            }
+        })
+        .expect("Failed to start the approval escalation watcher");
+}
+
+// ─── Approval Priority Commands ───
+
+#[tauri::command]
+pub fn get_approval_escalation(db: State<DbState>) -> Result<EscalationSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(EscalationSettings { minutes: escalate_minutes(&conn)?, max_escalations: MAX_ESCALATIONS })
+}
+
+#[tauri::command]
+pub fn set_approval_escalation(db: State<DbState>, minutes: i64) -> Result<EscalationSettings, String> {
+    if minutes < 0 {
+        return Err("Minutes can't be negative".into());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, ESCALATE_SETTING, &minutes.to_string())?;
+    Ok(EscalationSettings { minutes, max_escalations: MAX_ESCALATIONS })
+}
+
+/// Fixes the priority of an agent's approvals, e.g. `urgent` for one that pays bills; `None`
+/// goes back to judging each step by its risk.
+#[tauri::command]
+pub fn set_agent_approval_priority(db: State<DbState>, agent_id: String, priority: Option<String>) -> Result<(), String> {
+    if let Some(p) = &priority {
+        check_level(p)?;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| match priority {
+        Some(p) => { config.insert("approval_priority".into(), json!(p)); }
+        None => { config.remove("approval_priority"); }
+    })
+}
+
+/// Changes one waiting item's priority by hand.
+#[tauri::command]
+pub fn set_approval_priority(app: AppHandle, db: State<DbState>, id: String, priority: String) -> Result<(), String> {
+    check_level(&priority)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn.execute("UPDATE approval_queue SET priority = ?1 WHERE id = ?2 AND status = 'pending'", params![priority, id])
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err("Approval not found or already answered".into());
+    }
+    windows::broadcast(&app, "approvals-changed", &id);
+    Ok(())
+}
@@ -0,0 +1,361 @@
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::audit;
+use crate::policy;
+use crate::security;
+use crate::windows;
+use crate::{DbState, read_setting, update_agent_config, write_setting};
+
+const POLL_EVERY: Duration = Duration::from_secs(60);
+/// Values the active profile's settings replaced, restored when it stops being active.
+const BASELINE_SETTING: &str = "network_profile_baseline";
+/// What this device's network replaced stays with it, as does the profile's bookkeeping.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &["network_profile_"];
+
+/// Where the computer is, told apart by the Wi-Fi network or the network's DNS domain,
+/// e.g. "Office" for the `Corp-WiFi` network or the `corp.example.com` domain.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkProfile {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Wi-Fi network names, matched exactly.
+    #[serde(default)]
+    pub ssids: Vec<String>,
+    /// DNS domains; a subdomain matches too.
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Settings that take these values while the profile is active, e.g. local-only mode on
+    /// public Wi-Fi.
+    #[serde(default)]
+    pub settings: Map<String, Value>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+/// The network the computer is on and the profile it matches.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct CurrentProfile {
+    pub ssid: Option<String>,
+    pub domains: Vec<String>,
+    /// Id and name of the matching profile; `None` when no profile matches.
+    pub profile_id: Option<String>,
+    pub profile_name: Option<String>,
+}
+
+#[derive(Default)]
+pub struct NetworkState(Mutex<CurrentProfile>);
+
+impl NetworkState {
+    pub fn current(&self) -> CurrentProfile {
+        self.0.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS network_profiles (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            ssids TEXT DEFAULT '[]',
+            domains TEXT DEFAULT '[]',
+            settings TEXT DEFAULT '{}',
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize network profile tables");
+}
+
+fn row_to_profile(row: &rusqlite::Row) -> rusqlite::Result<NetworkProfile> {
+    let json = |i: usize| -> rusqlite::Result<String> { row.get(i) };
+    Ok(NetworkProfile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        ssids: serde_json::from_str(&json(2)?).unwrap_or_default(),
+        domains: serde_json::from_str(&json(3)?).unwrap_or_default(),
+        settings: serde_json::from_str(&json(4)?).unwrap_or_default(),
+        created_at: row.get(5)?,
+    })
+}
+
+fn all_profiles(conn: &Connection) -> Result<Vec<NetworkProfile>, String> {
+    let mut stmt = conn.prepare("SELECT id, name, ssids, domains, settings, created_at FROM network_profiles ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_profile).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run(program: &str, args: &[&str]) -> String {
+    std::process::Command::new(program).args(args).output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Search domains from resolv.conf, which DHCP fills in on most networks.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn resolv_domains() -> Vec<String> {
+    std::fs::read_to_string("/etc/resolv.conf").unwrap_or_default().lines()
+        .filter_map(|l| l.strip_prefix("search ").or_else(|| l.strip_prefix("domain ")))
+        .flat_map(|l| l.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> (Option<String>, Vec<String>) {
+    // NetworkManager first, then the wireless tools.
+    let ssid = run("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"]).lines()
+        .find_map(|l| l.strip_prefix("yes:").map(str::to_string))
+        .or_else(|| Some(run("iwgetid", &["-r"])));
+    (ssid, resolv_domains())
+}
+
+#[cfg(target_os = "macos")]
+fn detect() -> (Option<String>, Vec<String>) {
+    let ssid = run("networksetup", &["-getairportnetwork", "en0"])
+        .strip_prefix("Current Wi-Fi Network: ")
+        .map(str::to_string);
+    (ssid, resolv_domains())
+}
+
+#[cfg(target_os = "windows")]
+fn detect() -> (Option<String>, Vec<String>) {
+    let value = |line: &str, key: &str| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    };
+    let ssid = run("netsh", &["wlan", "show", "interfaces"]).lines().find_map(|l| value(l, "SSID"));
+    let domains = run("ipconfig", &[]).lines()
+        .filter_map(|l| l.split_once(':').filter(|(name, _)| name.contains("DNS Suffix")).map(|(_, v)| v.trim().to_string()))
+        .chain(std::env::var("USERDNSDOMAIN").ok())
+        .filter(|d| !d.is_empty())
+        .collect();
+    (ssid, domains)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn detect() -> (Option<String>, Vec<String>) {
+    (None, Vec::new())
+}
+
+/// The first profile naming the Wi-Fi network, or else the first whose domain the network
+/// is in.
+fn matching<'a>(profiles: &'a [NetworkProfile], ssid: Option<&str>, domains: &[String]) -> Option<&'a NetworkProfile> {
+    let in_domain = |domain: &String| {
+        let domain = domain.trim_matches('.').to_lowercase();
+        domains.iter().map(|d| d.trim_matches('.').to_lowercase()).any(|d| d == domain || d.ends_with(&format!(".{domain}")))
+    };
+    profiles.iter().find(|p| ssid.is_some_and(|s| p.ssids.iter().any(|x| x == s)))
+        .or_else(|| profiles.iter().find(|p| p.domains.iter().any(in_domain)))
+}
+
+/// Settings a profile may not set: the lock and supervisor settings, which only their own
+/// commands change, and the profile bookkeeping itself.
+fn is_reserved(key: &str) -> bool {
+    security::is_protected_setting(key) || key.starts_with("network_profile_")
+}
+
+/// Writes a setting for a profile without restamping it, so sync doesn't take the
+/// profile's value for a change the user made and carry it to their other devices.
+fn write_unstamped(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+    policy::check_setting_unlocked(key)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value, updated_at)
+         VALUES (?1, ?2, COALESCE((SELECT updated_at FROM settings WHERE key = ?1), ''))",
+        params![key, value],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The settings the active profile replaced, with the values they had before (`null` for
+/// ones that weren't set). Sync shares these rather than the profile's.
+pub(crate) fn overridden(conn: &Connection) -> Map<String, Value> {
+    read_setting(conn, BASELINE_SETTING).ok().flatten()
+        .and_then(|b| serde_json::from_str(&b).ok())
+        .unwrap_or_default()
+}
+
+/// Takes a synced change to a setting the active profile replaced: it becomes the value
+/// put back when the profile stops being active, and the profile's value stays meanwhile.
+pub(crate) fn set_overridden(conn: &Connection, key: &str, value: &str, updated_at: &str) -> rusqlite::Result<()> {
+    let mut baseline = overridden(conn);
+    baseline.insert(key.to_string(), json!(value));
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, '')",
+        params![BASELINE_SETTING, Value::Object(baseline).to_string()],
+    )?;
+    conn.execute("UPDATE settings SET updated_at = ?1 WHERE key = ?2", params![updated_at, key])?;
+    Ok(())
+}
+
+/// Puts back the settings the last profile replaced, then applies `profile`'s, remembering
+/// what they replaced. Settings locked by policy, and the lock and supervisor settings, are
+/// left alone.
+fn apply_settings(conn: &Connection, profile: Option<&NetworkProfile>) -> Result<(), String> {
+    for (key, value) in &overridden(conn) {
+        let _ = match value.as_str() {
+            Some(value) => write_unstamped(conn, key, value),
+            None => conn.execute("DELETE FROM settings WHERE key = ?1", params![key]).map(|_| ()).map_err(|e| e.to_string()),
+        };
+    }
+    let mut replaced = Map::new();
+    for (key, value) in profile.map(|p| &p.settings).into_iter().flatten().filter(|(key, _)| !is_reserved(key)) {
+        let previous = read_setting(conn, key)?;
+        let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+        if write_unstamped(conn, key, &value).is_ok() {
+            replaced.insert(key.clone(), json!(previous));
+        }
+    }
+    write_setting(conn, BASELINE_SETTING, &Value::Object(replaced).to_string())
+}
+
+/// Reads the network again and switches profile when it changed: the profile's settings
+/// are applied, the change is audited and `profile-changed` is sent with the new state.
+pub fn check(app: &AppHandle) -> Result<CurrentProfile, String> {
+    let (ssid, domains) = detect();
+    let ssid = ssid.filter(|s| !s.is_empty());
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let profiles = all_profiles(&conn)?;
+    let profile = matching(&profiles, ssid.as_deref(), &domains);
+    let detected = CurrentProfile {
+        ssid,
+        domains,
+        profile_id: profile.map(|p| p.id.clone()),
+        profile_name: profile.map(|p| p.name.clone()),
+    };
+    let previous = {
+        let state = app.state::<NetworkState>();
+        let mut current = state.0.lock().map_err(|e| e.to_string())?;
+        std::mem::replace(&mut *current, detected.clone())
+    };
+    if previous == detected {
+        return Ok(detected);
+    }
+    if previous.profile_id != detected.profile_id {
+        apply_settings(&conn, profile)?;
+        let summary = format!("Switched to the {} network profile", detected.profile_name.as_deref().unwrap_or("default"));
+        audit::record(&conn, "profile_changed", "", "", &summary, &json!({ "from": previous.profile_id, "to": detected.profile_id, "ssid": detected.ssid }))?;
+    }
+    drop(conn);
+    windows::broadcast(app, "profile-changed", &detected);
+    Ok(detected)
+}
+
+/// Why an agent mustn't run automatically in the current profile, if it mustn't. Agents
+/// list the profiles they are off in as `off_in_profiles` in their config.
+pub fn hold(app: &AppHandle, conn: &Connection, agent_id: &str) -> Option<String> {
+    let current = app.state::<NetworkState>().current();
+    let profile = current.profile_id?;
+    let config: String = conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get(0)).ok()?;
+    let off: Vec<String> = serde_json::from_str::<Value>(&config).ok()?
+        .get("off_in_profiles")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    off.contains(&profile).then(|| format!("Turned off on the {} network", current.profile_name.unwrap_or_default()))
+}
+
+/// Starts the thread that follows network changes, emitting `profile-changed` once the
+/// first reading is in and whenever the network or matching profile changes.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("network-profiles".into())
+        .spawn(move || loop {
+            if let Err(e) = check(&app) {
+                eprintln!("Network profile check failed: {e}");
+            }
+            std::thread::sleep(POLL_EVERY);
+        })
+        .expect("Failed to start the network profile watcher");
+}
+
+// ─── Network Profile Commands ───
+
+#[tauri::command]
+pub fn get_current_profile(state: State<NetworkState>) -> CurrentProfile {
+    state.current()
+}
+
+#[tauri::command]
+pub fn list_network_profiles(db: State<DbState>) -> Result<Vec<NetworkProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    all_profiles(&conn)
+}
+
+/// Creates a profile, or updates it when `id` is set, and checks the network again so the
+/// change takes effect right away.
+#[tauri::command]
+pub async fn save_network_profile(app: AppHandle, profile: NetworkProfile) -> Result<NetworkProfile, String> {
+    let clean = |list: Vec<String>| list.into_iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    let profile = NetworkProfile {
+        name: profile.name.trim().to_string(),
+        ssids: clean(profile.ssids),
+        domains: clean(profile.domains),
+        ..profile
+    };
+    if profile.name.is_empty() {
+        return Err("A profile needs a name".into());
+    }
+    if profile.ssids.is_empty() && profile.domains.is_empty() {
+        return Err("Name a Wi-Fi network or domain the profile is for".into());
+    }
+    if let Some(key) = profile.settings.keys().find(|key| is_reserved(key)) {
+        return Err(format!("A network profile can't change {key}"));
+    }
+    let saved = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let existing = match profile.id.is_empty() {
+            true => None,
+            false => conn.query_row("SELECT created_at FROM network_profiles WHERE id = ?1", params![profile.id], |row| row.get::<_, String>(0))
+                .optional().map_err(|e| e.to_string())?,
+        };
+        let saved = NetworkProfile {
+            id: if existing.is_some() { profile.id.clone() } else { Uuid::new_v4().to_string() },
+            created_at: existing.unwrap_or_else(|| Utc::now().to_rfc3339()),
+            ..profile
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO network_profiles (id, name, ssids, domains, settings, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![saved.id, saved.name, json!(saved.ssids).to_string(), json!(saved.domains).to_string(), Value::Object(saved.settings.clone()).to_string(), saved.created_at],
+        ).map_err(|e| e.to_string())?;
+        saved
+    };
+    // Forget the active profile so an edit to it is applied, not taken as no change.
+    if let Ok(mut current) = app.state::<NetworkState>().0.lock() {
+        current.profile_id = None;
+    }
+    tauri::async_runtime::spawn_blocking(move || check(&app)).await.map_err(|e| e.to_string())??;
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn delete_network_profile(app: AppHandle, id: String) -> Result<(), String> {
+    {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM network_profiles WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    }
+    tauri::async_runtime::spawn_blocking(move || check(&app)).await.map_err(|e| e.to_string())??;
+    Ok(())
+}
+
+/// The profiles in which an agent doesn't run automatically, e.g. a personal email agent
+/// on the office network. Runs started by hand still go ahead.
+#[tauri::command]
+pub fn set_agent_profiles(db: State<DbState>, agent_id: String, off_in: Vec<String>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let known: Vec<String> = all_profiles(&conn)?.into_iter().map(|p| p.id).collect();
+    if let Some(unknown) = off_in.iter().find(|id| !known.contains(id)) {
+        return Err(format!("Unknown network profile: {unknown}"));
+    }
+    update_agent_config(&conn, &agent_id, |config| {
+        config.insert("off_in_profiles".into(), json!(off_in));
+    })
+}
@@ -0,0 +1,449 @@
+//! A small QR code encoder for pairing links: byte mode, error correction level M,
+//! versions 1 to 10 (up to 213 bytes), drawn as SVG.
+
+/// (block count, data codewords per block) groups.
+type Blocks = &'static [(usize, usize)];
+
+/// Per version: total codewords, error correction codewords per block, and the blocks, at
+/// level M.
+const VERSIONS: &[(usize, usize, Blocks)] = &[
+    (26, 10, &[(1, 16)]),
+    (44, 16, &[(1, 28)]),
+    (70, 26, &[(1, 44)]),
+    (100, 18, &[(2, 32)]),
+    (134, 24, &[(2, 43)]),
+    (172, 16, &[(4, 27)]),
+    (196, 18, &[(4, 31)]),
+    (242, 22, &[(2, 38), (2, 39)]),
+    (292, 22, &[(3, 36), (2, 37)]),
+    (346, 26, &[(4, 43), (1, 44)]),
+];
+
+const ALIGNMENT: &[&[usize]] = &[
+    &[], &[6, 18], &[6, 22], &[6, 26], &[6, 30], &[6, 34],
+    &[6, 22, 38], &[6, 24, 42], &[6, 26, 46], &[6, 28, 50],
+];
+
+struct Grid {
+    size: usize,
+    dark: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Grid {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.size + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.dark[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+}
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let mut product: u16 = 0;
+    for i in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * 0x11D);
+        product ^= ((b >> i) & 1) as u16 * a as u16;
+    }
+    product as u8
+}
+
+/// Reed-Solomon error correction codewords for one block.
+fn error_correction(data: &[u8], degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0u8; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_mul(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_mul(root, 2);
+    }
+    let mut result = vec![0u8; degree];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, d) in result.iter_mut().zip(&divisor) {
+            *r ^= gf_mul(*d, factor);
+        }
+    }
+    result
+}
+
+/// The data codewords: mode, length, bytes, terminator and padding.
+fn data_codewords(text: &[u8], version: usize, capacity: usize) -> Vec<u8> {
+    fn push(bits: &mut Vec<bool>, value: usize, count: usize) {
+        (0..count).rev().for_each(|i| bits.push((value >> i) & 1 == 1));
+    }
+    let mut bits = Vec::new();
+    push(&mut bits, 0b0100, 4);
+    push(&mut bits, text.len(), if version < 10 { 8 } else { 16 });
+    text.iter().for_each(|&b| push(&mut bits, b as usize, 8));
+    let terminator = (capacity * 8 - bits.len()).min(4);
+    push(&mut bits, 0, terminator);
+    let padding = (8 - bits.len() % 8) % 8;
+    push(&mut bits, 0, padding);
+    let mut bytes: Vec<u8> = bits.chunks(8).map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8)).collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if bytes.len() >= capacity {
+            break;
+        }
+        bytes.push(pad);
+    }
+    bytes
+}
+
+/// Splits data into blocks, adds error correction and interleaves them.
+fn interleave(data: &[u8], ec_len: usize, groups: &[(usize, usize)]) -> Vec<u8> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    for &(count, len) in groups {
+        for _ in 0..count {
+            blocks.push(&data[offset..offset + len]);
+            offset += len;
+        }
+    }
+    let ec: Vec<Vec<u8>> = blocks.iter().map(|b| error_correction(b, ec_len)).collect();
+    let longest = blocks.iter().map(|b| b.len()).max().unwrap_or(0);
+    let mut out = Vec::new();
+    for i in 0..longest {
+        out.extend(blocks.iter().filter_map(|b| b.get(i)));
+    }
+    for i in 0..ec_len {
+        out.extend(ec.iter().map(|e| e[i]));
+    }
+    out
+}
+
+fn draw_function_patterns(grid: &mut Grid, version: usize) {
+    let size = grid.size;
+    for i in 0..size {
+        grid.set(6, i, i % 2 == 0);
+        grid.set(i, 6, i % 2 == 0);
+    }
+    for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                    let dist = dx.abs().max(dy.abs());
+                    grid.set(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+    let positions = ALIGNMENT[version - 1];
+    let last = positions.len().saturating_sub(1);
+    for (i, &ax) in positions.iter().enumerate() {
+        for (j, &ay) in positions.iter().enumerate() {
+            if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                continue;
+            }
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    grid.set((ax as i32 + dx) as usize, (ay as i32 + dy) as usize, dx.abs().max(dy.abs()) != 1);
+                }
+            }
+        }
+    }
+    draw_format(grid, 0);
+    if version >= 7 {
+        let mut rem = version;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = (version << 12) | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 == 1;
+            let (a, b) = (size - 11 + i % 3, i / 3);
+            grid.set(a, b, dark);
+            grid.set(b, a, dark);
+        }
+    }
+}
+
+/// Format bits for level M and `mask`, plus the always-dark module.
+fn draw_format(grid: &mut Grid, mask: usize) {
+    let size = grid.size;
+    let data = mask; // level M is 0b00
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    let bits = ((data << 10) | rem) ^ 0x5412;
+    let bit = |i: usize| (bits >> i) & 1 == 1;
+    for i in 0..=5 {
+        grid.set(8, i, bit(i));
+    }
+    grid.set(8, 7, bit(6));
+    grid.set(8, 8, bit(7));
+    grid.set(7, 8, bit(8));
+    for i in 9..15 {
+        grid.set(14 - i, 8, bit(i));
+    }
+    for i in 0..8 {
+        grid.set(size - 1 - i, 8, bit(i));
+    }
+    for i in 8..15 {
+        grid.set(8, size - 15 + i, bit(i));
+    }
+    grid.set(8, size - 8, true);
+}
+
+fn draw_codewords(grid: &mut Grid, codewords: &[u8]) {
+    let size = grid.size;
+    let mut i = 0;
+    let mut right = size as i32 - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+        for vert in 0..size {
+            for j in 0..2 {
+                let x = (right - j) as usize;
+                let upward = (right + 1) & 2 == 0;
+                let y = if upward { size - 1 - vert } else { vert };
+                if !grid.function[y * size + x] && i < codewords.len() * 8 {
+                    grid.dark[y * size + x] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 == 1;
+                    i += 1;
+                }
+            }
+        }
+        right -= 2;
+    }
+}
+
+fn apply_mask(grid: &mut Grid, mask: usize) {
+    let size = grid.size;
+    for y in 0..size {
+        for x in 0..size {
+            let invert = match mask {
+                0 => (x + y) % 2 == 0,
+                1 => y % 2 == 0,
+                2 => x % 3 == 0,
+                3 => (x + y) % 3 == 0,
+                4 => (x / 3 + y / 2) % 2 == 0,
+                5 => x * y % 2 + x * y % 3 == 0,
+                6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+            };
+            if invert && !grid.function[y * size + x] {
+                grid.dark[y * size + x] ^= true;
+            }
+        }
+    }
+}
+
+/// The standard penalty score; the mask with the lowest one is used.
+fn penalty(grid: &Grid) -> usize {
+    let size = grid.size;
+    let mut score = 0;
+    let finder = [true, false, true, true, true, false, true];
+    for horizontal in [true, false] {
+        for a in 0..size {
+            let line: Vec<bool> = (0..size).map(|b| if horizontal { grid.get(b, a) } else { grid.get(a, b) }).collect();
+            let mut run = 1;
+            for b in 1..=size {
+                if b < size && line[b] == line[b - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        score += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            for start in 0..=size - 7 {
+                if line[start..start + 7] == finder {
+                    let light_before = start >= 4 && line[start - 4..start].iter().all(|d| !d);
+                    let light_after = start + 11 <= size && line[start + 7..start + 11].iter().all(|d| !d);
+                    if light_before || light_after {
+                        score += 40;
+                    }
+                }
+            }
+        }
+    }
+    for y in 0..size - 1 {
+        for x in 0..size - 1 {
+            let c = grid.get(x, y);
+            if c == grid.get(x + 1, y) && c == grid.get(x, y + 1) && c == grid.get(x + 1, y + 1) {
+                score += 3;
+            }
+        }
+    }
+    let dark = grid.dark.iter().filter(|d| **d).count();
+    let total = size * size;
+    let deviation = (dark * 20).abs_diff(total * 10);
+    score + deviation.div_ceil(total).saturating_sub(1) * 10
+}
+
+/// The modules of `text`'s QR code, with the mask that scores best.
+fn matrix(text: &str) -> Result<Grid, String> {
+    let bytes = text.as_bytes();
+    let (version, &(total, ec_len, groups)) = VERSIONS.iter().enumerate()
+        .map(|(i, v)| (i + 1, v))
+        .find(|(version, (_, _, groups))| {
+            let capacity: usize = groups.iter().map(|(count, len)| count * len).sum();
+            4 + if *version < 10 { 8 } else { 16 } + bytes.len() * 8 <= capacity * 8
+        })
+        .ok_or("That's too long for a QR code")?;
+    let capacity = total - ec_len * groups.iter().map(|(count, _)| count).sum::<usize>();
+    let codewords = interleave(&data_codewords(bytes, version, capacity), ec_len, groups);
+
+    let size = version * 4 + 17;
+    let mut grid = Grid { size, dark: vec![false; size * size], function: vec![false; size * size] };
+    draw_function_patterns(&mut grid, version);
+    draw_codewords(&mut grid, &codewords);
+    let mut best: Option<(usize, Vec<bool>)> = None;
+    for mask in 0..8 {
+        apply_mask(&mut grid, mask);
+        draw_format(&mut grid, mask);
+        let score = penalty(&grid);
+        if best.as_ref().is_none_or(|(s, _)| score < *s) {
+            best = Some((score, grid.dark.clone()));
+        }
+        apply_mask(&mut grid, mask);
+    }
+    grid.dark = best.map(|(_, dark)| dark).unwrap_or(grid.dark);
+    Ok(grid)
+}
+
+/// `text` as a QR code in SVG, black on white with a quiet zone.
+pub fn svg(text: &str) -> Result<String, String> {
+    let grid = matrix(text)?;
+    let size = grid.size;
+    let quiet = 4;
+    let mut path = String::new();
+    for y in 0..size {
+        for x in 0..size {
+            if grid.get(x, y) {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + quiet, y + quiet));
+            }
+        }
+    }
+    let side = size + quiet * 2;
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {side} {side}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"{side}\" height=\"{side}\" fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The 15 format bits, read from around the top-left finder.
+    fn format_bits(grid: &Grid) -> usize {
+        let mut cells: Vec<(usize, usize)> = (0..=5).map(|i| (8, i)).collect();
+        cells.extend([(8, 7), (8, 8), (7, 8)]);
+        cells.extend((9..15).map(|i| (14 - i, 8)));
+        cells.iter().enumerate().fold(0, |bits, (i, &(x, y))| bits | (grid.get(x, y) as usize) << i)
+    }
+
+    /// The codewords in the data area, read back in placement order.
+    fn read_codewords(grid: &Grid) -> Vec<u8> {
+        let size = grid.size;
+        let mut bits = Vec::new();
+        let mut right = size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let y = if (right + 1) & 2 == 0 { size - 1 - vert } else { vert };
+                    if !grid.function[y * size + x] {
+                        bits.push(grid.get(x, y));
+                    }
+                }
+            }
+            right -= 2;
+        }
+        bits.chunks_exact(8).map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8)).collect()
+    }
+
+    #[test]
+    fn multiplies_in_the_qr_field() {
+        assert_eq!(gf_mul(0, 0x53), 0);
+        assert_eq!(gf_mul(1, 0x53), 0x53);
+        assert_eq!(gf_mul(2, 0x80), 0x1D);
+        assert_eq!(gf_mul(0x53, 0xCA), gf_mul(0xCA, 0x53));
+    }
+
+    #[test]
+    fn matches_the_reed_solomon_example() {
+        // "HELLO WORLD" at 1-M, from the worked example most QR guides use.
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        assert_eq!(error_correction(&data, 10), [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+    }
+
+    #[test]
+    fn encodes_byte_mode_data() {
+        assert_eq!(data_codewords(b"hello", 1, 16), [
+            0x40, 0x56, 0x86, 0x56, 0xC6, 0xC6, 0xF0, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC,
+        ]);
+        // Versions from 10 up count bytes in 16 bits.
+        assert_eq!(&data_codewords(b"A", 10, 216)[..4], [0x40, 0x00, 0x14, 0x10]);
+    }
+
+    #[test]
+    fn interleaves_blocks_of_different_lengths() {
+        let data: Vec<u8> = (0..5).collect();
+        let out = interleave(&data, 2, &[(1, 2), (1, 3)]);
+        assert_eq!(out[..5], [0, 2, 1, 3, 4]);
+        let (a, b) = (error_correction(&[0, 1], 2), error_correction(&[2, 3, 4], 2));
+        assert_eq!(out[5..], [a[0], b[0], a[1], b[1]]);
+    }
+
+    #[test]
+    fn draws_known_format_and_version_bits() {
+        let mut grid = Grid { size: 21, dark: vec![false; 441], function: vec![false; 441] };
+        draw_format(&mut grid, 0);
+        assert_eq!(format_bits(&grid), 0b101010000010010);
+        assert!(grid.get(8, 13), "the dark module is set");
+        draw_format(&mut grid, 5);
+        assert_eq!(format_bits(&grid), 0b100000011001110);
+
+        let size = 7 * 4 + 17;
+        let mut grid = Grid { size, dark: vec![false; size * size], function: vec![false; size * size] };
+        draw_function_patterns(&mut grid, 7);
+        let bits = (0..18).fold(0, |bits, i| bits | (grid.get(size - 11 + i % 3, i / 3) as usize) << i);
+        assert_eq!(bits, 0b000111110010010100);
+    }
+
+    #[test]
+    fn reads_back_what_it_encodes() {
+        for text in ["", "hello", "https://192.168.1.20:18791/pair?code=0123456789abcdef", &"x".repeat(213)] {
+            let grid = matrix(text).unwrap();
+            let version = (grid.size - 17) / 4;
+            let (total, ec_len, groups) = VERSIONS[version - 1];
+            let capacity = total - ec_len * groups.iter().map(|(count, _)| count).sum::<usize>();
+
+            let format = format_bits(&grid) ^ 0x5412;
+            assert_eq!(format >> 13, 0, "level M");
+            let mask = (format >> 10) & 7;
+            let mut unmasked = Grid { size: grid.size, dark: grid.dark.clone(), function: grid.function.clone() };
+            apply_mask(&mut unmasked, mask);
+            let codewords = read_codewords(&unmasked);
+            assert_eq!(codewords[..total], interleave(&data_codewords(text.as_bytes(), version, capacity), ec_len, groups));
+        }
+    }
+
+    #[test]
+    fn draws_svg_and_refuses_long_text() {
+        let drawn = svg("hello").unwrap();
+        assert!(drawn.contains("viewBox=\"0 0 29 29\""));
+        assert!(drawn.contains("M4,4h1v1h-1z"), "finder corner is dark");
+        assert!(!drawn.contains("M5,5h1v1h-1z"), "finder ring is light");
+        assert_eq!(matrix(&"x".repeat(14)).unwrap().size, 21);
+        assert_eq!(matrix(&"x".repeat(15)).unwrap().size, 25);
+        assert_eq!(svg(&"x".repeat(214)).unwrap_err(), "That's too long for a QR code");
+    }
+}
@@ -0,0 +1,388 @@
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::indexer::Indexer;
+use crate::llm::{self, ProviderConfig};
+use crate::net;
+use crate::sanitize;
+use crate::tools::files;
+use crate::{DbState, read_setting, write_setting};
+
+const LAST_INDEXED_SETTING: &str = "knowledge_last_indexed_at";
+/// Larger files are skipped; they are rarely notes and would dominate the index.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "csv", "json", "html", "htm", "xml", "yaml", "yml", "log", "rst", "org", "tex",
+];
+const CHUNK_CHARS: usize = 1200;
+const CHUNK_OVERLAP: usize = 200;
+const EMBED_BATCH: usize = 16;
+
+/// A folder whose documents agents can search.
+#[derive(Debug, Serialize, Clone)]
+pub struct KnowledgeSource {
+    pub id: String,
+    pub path: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct IndexStatus {
+    pub sources: usize,
+    pub indexed_files: usize,
+    /// New or changed files, plus ones embedded with a different model.
+    pub pending_files: usize,
+    /// Indexed files that no longer exist on disk.
+    pub removed_files: usize,
+    pub chunk_count: i64,
+    pub embedding_model: String,
+    pub last_indexed_at: String,
+    /// The background indexer is working through files right now.
+    pub indexing: bool,
+    pub paused: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct IndexReport {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub chunks: usize,
+    /// `path: reason` for files that couldn't be indexed this time.
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct KnowledgeHit {
+    pub path: String,
+    pub text: String,
+    pub score: f32,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS knowledge_sources (
+            id TEXT PRIMARY KEY,
+            path TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS knowledge_files (
+            path TEXT PRIMARY KEY,
+            source_id TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            embedding_model TEXT NOT NULL,
+            chunk_count INTEGER DEFAULT 0,
+            indexed_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS knowledge_chunks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            file_path TEXT NOT NULL,
+            ordinal INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            embedding BLOB NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_knowledge_chunks_file ON knowledge_chunks(file_path);
+    ").expect("Failed to initialize knowledge tables");
+}
+
+// ─── Files ───
+
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if meta.is_dir() {
+            walk(&path, out);
+        } else if meta.len() <= MAX_FILE_BYTES
+            && path.extension().and_then(|e| e.to_str()).is_some_and(|e| TEXT_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        {
+            out.push(path);
+        }
+    }
+}
+
+fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Splits text into overlapping windows, preferring to break at a paragraph or sentence end.
+fn chunk(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + CHUNK_CHARS).min(chars.len());
+        if end < chars.len() {
+            let window: String = chars[start..end].iter().collect();
+            let floor = CHUNK_CHARS / 2;
+            let cut = window.rfind("\n\n").filter(|i| *i > floor)
+                .or_else(|| window.rfind(". ").filter(|i| *i > floor).map(|i| i + 1));
+            if let Some(cut) = cut {
+                end = start + window[..cut].chars().count();
+            }
+        }
+        let piece: String = chars[start..end].iter().collect();
+        if !piece.trim().is_empty() {
+            chunks.push(piece.trim().to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(CHUNK_OVERLAP).max(start + 1);
+    }
+    chunks
+}
+
+/// Embeddings are stored int8-quantized against their largest component: a quarter of the
+/// size of raw f32s, with no measurable effect on ranking.
+fn compress(vector: &[f32]) -> Vec<u8> {
+    let scale = vector.iter().fold(0f32, |m, x| m.max(x.abs())).max(f32::MIN_POSITIVE);
+    let mut out = scale.to_le_bytes().to_vec();
+    out.extend(vector.iter().map(|x| ((x / scale) * 127.0).round() as i8 as u8));
+    out
+}
+
+fn decompress(bytes: &[u8]) -> Vec<f32> {
+    let Some((scale, values)) = bytes.split_first_chunk::<4>() else { return Vec::new() };
+    let scale = f32::from_le_bytes(*scale);
+    values.iter().map(|b| (*b as i8) as f32 / 127.0 * scale).collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 { 0.0 } else { dot / denom }
+}
+
+// ─── Index Planning ───
+
+struct PendingFile {
+    path: PathBuf,
+    source_id: String,
+}
+
+/// What an index run would do: files to (re-)embed, indexed files that are gone, and how
+/// many are already up to date.
+struct Plan {
+    pending: Vec<PendingFile>,
+    removed: Vec<String>,
+    unchanged: usize,
+}
+
+fn list_sources(conn: &Connection) -> Result<Vec<KnowledgeSource>, String> {
+    let mut stmt = conn.prepare("SELECT id, path, created_at FROM knowledge_sources ORDER BY created_at")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok(KnowledgeSource { id: row.get(0)?, path: row.get(1)?, created_at: row.get(2)? }))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Indexed files as `path -> (hash, embedding model)`.
+fn indexed_files(conn: &Connection) -> Result<HashMap<String, (String, String)>, String> {
+    let mut stmt = conn.prepare("SELECT path, hash, embedding_model FROM knowledge_files").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Walks every source and hashes its files. Runs without the database lock.
+fn plan(sources: &[KnowledgeSource], known: &HashMap<String, (String, String)>, model: &str) -> Plan {
+    let mut plan = Plan { pending: Vec::new(), removed: Vec::new(), unchanged: 0 };
+    let mut seen = std::collections::HashSet::new();
+    for source in sources {
+        let mut paths = Vec::new();
+        walk(&files::expand_path(&source.path), &mut paths);
+        for path in paths {
+            let key = path.to_string_lossy().to_string();
+            let Ok(bytes) = std::fs::read(&path) else { continue };
+            seen.insert(key.clone());
+            match known.get(&key) {
+                Some((h, m)) if *h == hash(&bytes) && m == model => plan.unchanged += 1,
+                _ => plan.pending.push(PendingFile { path, source_id: source.id.clone() }),
+            }
+        }
+    }
+    plan.removed = known.keys().filter(|k| !seen.contains(*k)).cloned().collect();
+    plan
+}
+
+/// Re-chunks and re-embeds one file, replacing whatever was indexed for it before.
+fn index_file(db: &DbState, cfg: &ProviderConfig, file: &PendingFile) -> Result<usize, String> {
+    let bytes = std::fs::read(&file.path).map_err(|e| e.to_string())?;
+    let chunks = chunk(&String::from_utf8_lossy(&bytes));
+    let mut embeddings = Vec::with_capacity(chunks.len());
+    for batch in chunks.chunks(EMBED_BATCH) {
+        embeddings.extend(llm::embed(cfg, batch)?);
+    }
+
+    let key = file.path.to_string_lossy().to_string();
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM knowledge_chunks WHERE file_path = ?1", params![key]).map_err(|e| e.to_string())?;
+    for (ordinal, (text, vector)) in chunks.iter().zip(&embeddings).enumerate() {
+        tx.execute(
+            "INSERT INTO knowledge_chunks (file_path, ordinal, text, embedding) VALUES (?1, ?2, ?3, ?4)",
+            params![key, ordinal as i64, text, compress(vector)],
+        ).map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO knowledge_files (path, source_id, hash, embedding_model, chunk_count, indexed_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![key, file.source_id, hash(&bytes), cfg.model, chunks.len() as i64, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(chunks.len())
+}
+
+fn forget_files(conn: &Connection, paths: &[String]) -> Result<(), String> {
+    for path in paths {
+        conn.execute("DELETE FROM knowledge_chunks WHERE file_path = ?1", params![path]).map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM knowledge_files WHERE path = ?1", params![path]).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Brings the index up to date. Only new or changed files are re-embedded.
+/// `before_file(done, total, path)` runs ahead of each one and may block to throttle the run.
+pub fn run_index(db: &DbState, mut before_file: impl FnMut(usize, usize, &Path)) -> Result<IndexReport, String> {
+    let (sources, known, cfg) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (list_sources(&conn)?, indexed_files(&conn)?, llm::embedding_config(&conn)?)
+    };
+    let plan = plan(&sources, &known, &cfg.model);
+    let mut report = IndexReport { unchanged: plan.unchanged, removed: plan.removed.len(), ..Default::default() };
+    if !plan.pending.is_empty() {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        net::check_egress(&conn, "document indexing", Some(&cfg.base_url), "", "")?;
+    }
+    for (done, file) in plan.pending.iter().enumerate() {
+        before_file(done, plan.pending.len(), &file.path);
+        match index_file(db, &cfg, file) {
+            Ok(chunks) => {
+                report.indexed += 1;
+                report.chunks += chunks;
+            }
+            Err(e) => report.failed.push(format!("{}: {e}", file.path.display())),
+        }
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    forget_files(&conn, &plan.removed)?;
+    write_setting(&conn, LAST_INDEXED_SETTING, &Utc::now().to_rfc3339())?;
+    Ok(report)
+}
+
+/// The indexed passages closest to `query`, best first.
+pub fn search(db: &DbState, query: &str, limit: usize) -> Result<Vec<KnowledgeHit>, String> {
+    let cfg = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let cfg = llm::embedding_config(&conn)?;
+        net::check_egress(&conn, "document search", Some(&cfg.base_url), "", "")?;
+        cfg
+    };
+    let query_vector = llm::embed(&cfg, &[query.to_string()])?.remove(0);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT c.file_path, c.text, c.embedding FROM knowledge_chunks c
+         JOIN knowledge_files f ON f.path = c.file_path WHERE f.embedding_model = ?1"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![cfg.model], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Vec<u8>>(2)?))
+    }).map_err(|e| e.to_string())?;
+    let mut hits = Vec::new();
+    for row in rows {
+        let (path, text, embedding) = row.map_err(|e| e.to_string())?;
+        hits.push(KnowledgeHit { score: cosine(&query_vector, &decompress(&embedding)), path, text });
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+// ─── Knowledge Commands ───
+
+#[tauri::command]
+pub fn list_knowledge_sources(db: State<DbState>) -> Result<Vec<KnowledgeSource>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_sources(&conn)
+}
+
+#[tauri::command]
+pub fn add_knowledge_source(db: State<DbState>, path: String) -> Result<KnowledgeSource, String> {
+    if !files::expand_path(&path).is_dir() {
+        return Err(format!("{path} is not a folder"));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let source = KnowledgeSource { id: Uuid::new_v4().to_string(), path, created_at: Utc::now().to_rfc3339() };
+    conn.execute(
+        "INSERT INTO knowledge_sources (id, path, created_at) VALUES (?1, ?2, ?3)",
+        params![source.id, source.path, source.created_at],
+    ).map_err(|_| "That folder is already indexed".to_string())?;
+    Ok(source)
+}
+
+/// Stops indexing a folder and drops everything indexed from it.
+#[tauri::command]
+pub fn remove_knowledge_source(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let paths: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT path FROM knowledge_files WHERE source_id = ?1").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![id], |row| row.get(0)).map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    forget_files(&conn, &paths)?;
+    conn.execute("DELETE FROM knowledge_sources WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Compares what is on disk with what is indexed. Hashes every file, so it reads the
+/// sources but sends nothing anywhere.
+#[tauri::command]
+pub async fn get_index_status(app: AppHandle) -> Result<IndexStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let (sources, known, model, chunk_count, last_indexed_at) = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let chunk_count: i64 = conn.query_row("SELECT COUNT(*) FROM knowledge_chunks", [], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            (
+                list_sources(&conn)?,
+                indexed_files(&conn)?,
+                llm::embedding_config(&conn)?.model,
+                chunk_count,
+                read_setting(&conn, LAST_INDEXED_SETTING)?.unwrap_or_default(),
+            )
+        };
+        let plan = plan(&sources, &known, &model);
+        Ok(IndexStatus {
+            sources: sources.len(),
+            indexed_files: known.len(),
+            pending_files: plan.pending.len(),
+            removed_files: plan.removed.len(),
+            chunk_count,
+            embedding_model: model,
+            last_indexed_at,
+            indexing: app.state::<Indexer>().is_running(),
+            paused: app.state::<Indexer>().is_paused(),
+        })
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn search_knowledge(app: AppHandle, query: String, limit: Option<usize>) -> Result<Vec<KnowledgeHit>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let mut hits = search(&db, &query, limit.unwrap_or(5))?;
+        let mode = sanitize::mode(&*db.0.lock().map_err(|e| e.to_string())?);
+        hits.iter_mut().for_each(|h| sanitize::knowledge_hit(h, mode));
+        Ok(hits)
+    }).await.map_err(|e| e.to_string())?
+}
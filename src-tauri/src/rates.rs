@@ -0,0 +1,265 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use regex::Regex;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::net;
+use crate::tools::{calc, web};
+use crate::{DbState, read_setting, write_setting};
+
+/// Where rates are fetched from: the European Central Bank's daily reference rates by
+/// default, or any feed in that format or as JSON `{ "base", "date", "rates": { … } }`.
+const URL_SETTING: &str = "currency_rates_url";
+const DEFAULT_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+const FETCHED_SETTING: &str = "currency_rates_fetched_at";
+const ERROR_SETTING: &str = "currency_rates_error";
+/// How this device's last fetch went; another device fetches for itself.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[FETCHED_SETTING, ERROR_SETTING];
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// The ECB publishes once per working day; fetching more often gains nothing.
+const REFRESH_AFTER_HOURS: i64 = 12;
+/// Conversions with rates older than this say so.
+const STALE_DAYS: i64 = 7;
+const BUILTIN_SOURCE: &str = "built-in";
+/// Approximate euro rates shipped with the app, so conversions work before the first
+/// fetch and offline. Fetched rates replace them.
+const BUILTIN_AS_OF: &str = "2025-06-30";
+const BUILTIN_RATES: &[(&str, f64)] = &[
+    ("EUR", 1.0), ("USD", 1.172), ("JPY", 169.2), ("GBP", 0.856), ("CHF", 0.935), ("CAD", 1.603),
+    ("AUD", 1.79), ("NZD", 1.93), ("CNY", 8.4), ("HKD", 9.2), ("SGD", 1.494), ("INR", 100.5),
+    ("KRW", 1590.0), ("SEK", 11.15), ("NOK", 11.84), ("DKK", 7.461), ("PLN", 4.242), ("CZK", 24.72),
+    ("HUF", 399.8), ("RON", 5.077), ("BGN", 1.956), ("ISK", 142.0), ("TRY", 46.6), ("ILS", 3.95),
+    ("ZAR", 20.8), ("MXN", 22.1), ("BRL", 6.44), ("THB", 38.2), ("IDR", 19_030.0), ("MYR", 4.95),
+    ("PHP", 66.2),
+];
+
+/// The rate of one currency against the euro.
+#[derive(Debug, Serialize, Clone)]
+pub struct CurrencyRate {
+    pub code: String,
+    /// Units of this currency one euro buys.
+    pub per_euro: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RatesStatus {
+    pub url: String,
+    /// The date the rates are for, as the source published them.
+    pub as_of: String,
+    /// `built-in` until a fetch has succeeded, then the address they came from.
+    pub source: String,
+    pub fetched_at: Option<String>,
+    /// Why the last refresh failed; empty when it worked.
+    pub error: String,
+    pub rates: Vec<CurrencyRate>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS currency_rates (
+            code TEXT PRIMARY KEY,
+            per_euro REAL NOT NULL,
+            as_of TEXT NOT NULL,
+            source TEXT NOT NULL
+        );
+    ").expect("Failed to initialize currency rate tables");
+    for (code, rate) in BUILTIN_RATES {
+        conn.execute(
+            "INSERT OR IGNORE INTO currency_rates (code, per_euro, as_of, source) VALUES (?1, ?2, ?3, ?4)",
+            params![code, rate, BUILTIN_AS_OF, BUILTIN_SOURCE],
+        ).expect("Failed to initialize currency rate tables");
+    }
+}
+
+fn url(conn: &Connection) -> Result<String, String> {
+    Ok(read_setting(conn, URL_SETTING)?.filter(|u| !u.trim().is_empty()).unwrap_or_else(|| DEFAULT_URL.into()))
+}
+
+/// Rates per euro and their date from the ECB's XML or a JSON feed with any base currency.
+fn parse(body: &str) -> Result<(String, Vec<(String, f64)>), String> {
+    let body = body.trim_start_matches('\u{feff}').trim();
+    let (date, base, mut rates) = if body.starts_with('{') {
+        let json: Value = serde_json::from_str(body).map_err(|e| format!("The rates aren't valid JSON: {e}"))?;
+        let rates: Vec<(String, f64)> = json.get("rates").and_then(Value::as_object)
+            .map(|r| r.iter().filter_map(|(code, rate)| Some((code.to_uppercase(), rate.as_f64()?))).collect())
+            .unwrap_or_default();
+        let base = json.get("base").and_then(Value::as_str).unwrap_or("EUR").to_uppercase();
+        (json.get("date").and_then(Value::as_str).unwrap_or_default().to_string(), base, rates)
+    } else {
+        let rate = Regex::new(r#"currency=['"]([A-Za-z]{3})['"]\s+rate=['"]([0-9.]+)['"]"#).map_err(|e| e.to_string())?;
+        let time = Regex::new(r#"time=['"](\d{4}-\d{2}-\d{2})['"]"#).map_err(|e| e.to_string())?;
+        let rates = rate.captures_iter(body)
+            .filter_map(|c| Some((c[1].to_uppercase(), c[2].parse().ok()?)))
+            .collect();
+        (time.captures(body).map(|c| c[1].to_string()).unwrap_or_default(), "EUR".to_string(), rates)
+    };
+    if !rates.iter().any(|(code, _)| *code == base) {
+        rates.push((base.clone(), 1.0));
+    }
+    let per_base_euro = rates.iter().find(|(code, _)| code == "EUR").map(|(_, rate)| *rate)
+        .ok_or("The rates don't include the euro or say what they are based on")?;
+    let rates: Vec<(String, f64)> = rates.into_iter()
+        .filter(|(_, rate)| rate.is_finite() && *rate > 0.0)
+        .map(|(code, rate)| (code, rate / per_base_euro))
+        .collect();
+    if rates.len() < 2 {
+        return Err("The rates feed had no rates in it".into());
+    }
+    let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map(|d| d.to_string())
+        .unwrap_or_else(|_| Utc::now().date_naive().to_string());
+    Ok((date, rates))
+}
+
+pub fn status(conn: &Connection) -> Result<RatesStatus, String> {
+    let mut stmt = conn.prepare("SELECT code, per_euro, as_of, source FROM currency_rates ORDER BY code")
+        .map_err(|e| e.to_string())?;
+    let rows: Vec<(CurrencyRate, String, String)> = stmt.query_map([], |row| Ok((
+        CurrencyRate { code: row.get(0)?, per_euro: row.get(1)? },
+        row.get(2)?,
+        row.get(3)?,
+    ))).map_err(|e| e.to_string())?.collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+    // Rows the latest feed didn't include can be older; report the newest.
+    let (as_of, source) = rows.iter().max_by(|a, b| a.1.cmp(&b.1))
+        .map(|(_, as_of, source)| (as_of.clone(), source.clone()))
+        .unwrap_or_default();
+    Ok(RatesStatus {
+        url: url(conn)?,
+        as_of,
+        source,
+        fetched_at: read_setting(conn, FETCHED_SETTING)?.filter(|f| !f.is_empty()),
+        error: read_setting(conn, ERROR_SETTING)?.unwrap_or_default(),
+        rates: rows.into_iter().map(|(rate, ..)| rate).collect(),
+    })
+}
+
+/// Fetches current rates and replaces the cached ones. The cache is left as it was when the
+/// fetch fails, so conversions keep working from the last good rates.
+pub fn refresh(app: &AppHandle) -> Result<RatesStatus, String> {
+    let db = app.state::<DbState>();
+    let (url, proxy) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let url = url(&conn)?;
+        if net::local_only(&conn) && !net::is_loopback(&url) {
+            return Err(format!("Skipped {url}: local-only mode is on"));
+        }
+        (url, net::proxy(&conn)?)
+    };
+    let fetched = web::http_get(&proxy, &url).and_then(|body| parse(&body));
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, FETCHED_SETTING, &Utc::now().to_rfc3339())?;
+    let (as_of, rates) = match fetched {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            write_setting(&conn, ERROR_SETTING, &e)?;
+            return Err(e);
+        }
+    };
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (code, rate) in &rates {
+        tx.execute(
+            "INSERT INTO currency_rates (code, per_euro, as_of, source) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(code) DO UPDATE SET per_euro = excluded.per_euro, as_of = excluded.as_of, source = excluded.source",
+            params![code, rate, as_of, url],
+        ).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    write_setting(&conn, ERROR_SETTING, "")?;
+    status(&conn)
+}
+
+/// Refreshes when the last attempt, good or not, was long enough ago. Nothing is fetched in
+/// local-only mode; the cached rates stay in use.
+fn refresh_if_due(app: &AppHandle) {
+    let due = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+        !net::local_only(&conn) && read_setting(&conn, FETCHED_SETTING).ok().flatten()
+            .and_then(|f| DateTime::parse_from_rfc3339(&f).ok())
+            .is_none_or(|f| Utc::now() - f.with_timezone(&Utc) > Duration::hours(REFRESH_AFTER_HOURS))
+    };
+    if due {
+        if let Err(e) = refresh(app) {
+            eprintln!("Currency rate refresh failed: {e}");
+        }
+    }
+}
+
+/// Starts the thread that keeps the cached rates current.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("currency-rates".into())
+        .spawn(move || loop {
+            refresh_if_due(&app);
+            std::thread::sleep(CHECK_EVERY);
+        })
+        .expect("Failed to start the currency rate refresher");
+}
+
+/// A currency code for `name`, with the common symbols read as their currency.
+fn code(name: &str) -> String {
+    match name.trim() {
+        "€" => "EUR".into(),
+        "$" | "US$" => "USD".into(),
+        "£" => "GBP".into(),
+        "¥" => "JPY".into(),
+        "₹" => "INR".into(),
+        "₩" => "KRW".into(),
+        "₺" => "TRY".into(),
+        "₪" => "ILS".into(),
+        "R$" => "BRL".into(),
+        other => other.to_uppercase(),
+    }
+}
+
+fn rate(conn: &Connection, code: &str) -> Result<Option<(f64, String, String)>, String> {
+    match conn.query_row(
+        "SELECT per_euro, as_of, source FROM currency_rates WHERE code = ?1",
+        params![code],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ) {
+        Ok(found) => Ok(Some(found)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// `amount` of currency `from` in currency `to` from the cached rates, saying how old the
+/// rates are. `None` when either isn't a currency with a cached rate.
+pub fn convert(conn: &Connection, amount: f64, from: &str, to: &str) -> Result<Option<String>, String> {
+    let (from, to) = (code(from), code(to));
+    let (Some((from_rate, from_date, from_source)), Some((to_rate, to_date, to_source))) = (rate(conn, &from)?, rate(conn, &to)?) else {
+        return Ok(None);
+    };
+    let converted = amount / from_rate * to_rate;
+    let as_of = from_date.min(to_date);
+    let mut note = if from_source == BUILTIN_SOURCE || to_source == BUILTIN_SOURCE {
+        format!("approximate built-in rates from {as_of}; current rates couldn't be fetched")
+    } else {
+        format!("rates of {as_of}")
+    };
+    let age = NaiveDate::parse_from_str(&as_of, "%Y-%m-%d").map(|d| (Utc::now().date_naive() - d).num_days()).unwrap_or(0);
+    if age > STALE_DAYS && !note.starts_with("approximate") {
+        note.push_str(&format!(", {age} days old"));
+    }
+    Ok(Some(format!(
+        "{} {from} = {} {to} ({note})",
+        calc::format_number(amount), calc::format_number((converted * 100.0).round() / 100.0),
+    )))
+}
+
+// ─── Currency Rate Commands ───
+
+#[tauri::command]
+pub fn get_currency_rates(db: State<DbState>) -> Result<RatesStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    status(&conn)
+}
+
+/// Fetches the rates now instead of waiting for the next scheduled refresh.
+#[tauri::command]
+pub async fn refresh_currency_rates(app: AppHandle) -> Result<RatesStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || refresh(&app)).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::executor::{self, Run};
+use crate::injection;
+use crate::llm::{ChatMessage, LlmRequest};
+use crate::structured;
+use crate::templates::CatalogTemplate;
+use crate::tools::{fileops, files, pdf, ToolContext};
+use crate::usage::csv_field;
+use crate::vision;
+use crate::workflows::WorkflowStep;
+use crate::{DbState, read_setting};
+
+/// The CSV file receipts are added to; `~/Documents/Receipts.csv` by default.
+const LEDGER_SETTING: &str = "receipt_ledger_path";
+/// The ledger is a file on this computer; another device has its own.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[LEDGER_SETTING];
+const DEFAULT_LEDGER: &str = "~/Documents/Receipts.csv";
+const LEDGER_HEADER: &str = "Date,Vendor,Total,Tax,Currency,File,Added";
+/// A field the model is less sure of than this holds the ledger entry for approval.
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.8;
+/// The fields read from a receipt, each with its own confidence.
+const FIELDS: &[&str] = &["vendor", "date", "total", "tax", "currency"];
+/// Scanned pages of a PDF without text that are shown to the model.
+const MAX_SCANS: usize = 3;
+/// Less text than this in a PDF means it is a scan with at most a stray page number.
+const MIN_TEXT_CHARS: usize = 20;
+const PROMPT: &str = "Read this receipt or invoice. Give who issued it, the date it was issued as YYYY-MM-DD, \
+the total paid including tax, the tax included in that total (0 when none is shown) and the currency as a \
+three-letter code. For each of these give your confidence from 0 to 1 that it is right: anything guessed, \
+hard to read or worked out rather than printed should be well below 1.";
+const REPLY_TOKENS: u32 = 512;
+
+/// What `extract_receipt` read from a receipt or invoice.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Receipt {
+    pub vendor: String,
+    /// As `YYYY-MM-DD`.
+    pub date: String,
+    pub total: f64,
+    pub tax: f64,
+    pub currency: String,
+    /// From 0 to 1 for each field, how sure the model was. Fields that fail a check are 0.
+    #[serde(default)]
+    pub confidence: BTreeMap<String, f64>,
+    /// Why fields failed their checks.
+    #[serde(default)]
+    pub problems: Vec<String>,
+    /// The file it was read from.
+    #[serde(default)]
+    pub file: String,
+}
+
+/// What the extracting model call returns.
+fn receipt_schema() -> Value {
+    let confidence: serde_json::Map<String, Value> = FIELDS.iter()
+        .map(|f| (f.to_string(), json!({ "type": "number" })))
+        .collect();
+    json!({
+        "type": "object",
+        "properties": {
+            "vendor": { "type": "string" },
+            "date": { "type": "string" },
+            "total": { "type": "number" },
+            "tax": { "type": "number" },
+            "currency": { "type": "string" },
+            "confidence": { "type": "object", "properties": confidence, "required": FIELDS },
+        },
+        "required": ["vendor", "date", "total", "tax", "currency", "confidence"],
+    })
+}
+
+/// Text read from an image by Tesseract, when it is installed. The model reads the image
+/// too; the text helps with small print.
+fn ocr(path: &Path) -> Option<String> {
+    std::process::Command::new("tesseract").arg(path).arg("stdout").output().ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// The receipt's text and the images the model should see: a PDF's own text when it has
+/// some, otherwise its scanned pages; an image itself, with what OCR made of it.
+fn source(path: &Path) -> Result<(String, Vec<vision::Image>), String> {
+    let is_pdf = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        let text = pdf::text(path)?;
+        if text.chars().filter(|c| !c.is_whitespace()).count() >= MIN_TEXT_CHARS {
+            return Ok((text, Vec::new()));
+        }
+        let scans = pdf::jpeg_images(path)?;
+        if scans.is_empty() {
+            return Err(format!("{} has no text, and its scanned pages aren't in a format that can be read", fileops::name(path)));
+        }
+        let images = scans.iter().take(MAX_SCANS)
+            .map(|scan| vision::prepare(scan).map(|(image, _)| image))
+            .collect::<Result<_, _>>()?;
+        return Ok((String::new(), images));
+    }
+    let bytes = fs::read(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+    let (image, _) = vision::prepare(&bytes).map_err(|e| format!("{}: {e}", fileops::name(path)))?;
+    Ok((ocr(path).unwrap_or_default(), vec![image]))
+}
+
+/// Checks what the model read; a field that fails gets no confidence so it is reviewed.
+fn check(receipt: &mut Receipt) {
+    for field in FIELDS {
+        let value = receipt.confidence.entry(field.to_string()).or_insert(0.0);
+        *value = value.clamp(0.0, 1.0);
+    }
+    let mut failed = Vec::new();
+    receipt.vendor = receipt.vendor.trim().to_string();
+    receipt.currency = receipt.currency.trim().to_uppercase();
+    if receipt.vendor.is_empty() {
+        failed.push(("vendor", "No vendor was found".to_string()));
+    }
+    match NaiveDate::parse_from_str(receipt.date.trim(), "%Y-%m-%d") {
+        Ok(date) if date > Local::now().date_naive() => failed.push(("date", format!("The date {date} is in the future"))),
+        Ok(date) => receipt.date = date.format("%Y-%m-%d").to_string(),
+        Err(_) => failed.push(("date", format!("\"{}\" isn't a date", receipt.date))),
+    }
+    if receipt.total <= 0.0 {
+        failed.push(("total", "The total isn't more than zero".to_string()));
+    }
+    if receipt.tax < 0.0 || receipt.tax > receipt.total {
+        failed.push(("tax", "The tax is more than the total or below zero".to_string()));
+    }
+    if receipt.currency.len() != 3 || !receipt.currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        failed.push(("currency", format!("\"{}\" isn't a currency code", receipt.currency)));
+    }
+    for (field, problem) in failed {
+        receipt.confidence.insert(field.to_string(), 0.0);
+        receipt.problems.push(problem);
+    }
+}
+
+/// Runs an `extract_receipt` step: reads the receipt at `path` and returns its fields as JSON.
+pub fn extract(ctx: &ToolContext, path: &Path) -> Result<String, String> {
+    let (text, images) = source(path)?;
+    let content = match text.is_empty() {
+        true => "The receipt is in the attached image(s).".to_string(),
+        false => injection::wrap("receipt", &text, false),
+    };
+    let db = ctx.app.state::<DbState>();
+    let value = structured::complete(&db, &LlmRequest {
+        system: format!("{PROMPT} {}", injection::SYSTEM_NOTE),
+        messages: vec![ChatMessage::user_with_images(content, images)],
+        max_tokens: REPLY_TOKENS,
+        agent_id: ctx.agent_id.to_string(),
+        run_id: ctx.run_id.to_string(),
+        ..Default::default()
+    }, &receipt_schema())?;
+    let mut receipt: Receipt = serde_json::from_value(value).map_err(|e| format!("The model's reply doesn't fit a receipt: {e}"))?;
+    receipt.problems.clear();
+    receipt.file = path.display().to_string();
+    check(&mut receipt);
+    serde_json::to_string(&receipt).map_err(|e| e.to_string())
+}
+
+fn receipt_param(params: &Value) -> Result<Receipt, String> {
+    let value = match params.get("receipt") {
+        Some(Value::String(text)) => serde_json::from_str(text).map_err(|e| format!("The receipt isn't valid JSON: {e}"))?,
+        Some(value @ Value::Object(_)) => value.clone(),
+        _ => return Err("Missing parameter 'receipt'".into()),
+    };
+    serde_json::from_value(value).map_err(|e| format!("The receipt isn't what extract_receipt gives: {e}"))
+}
+
+fn min_confidence(params: &Value) -> f64 {
+    params.get("min_confidence").and_then(Value::as_f64).unwrap_or(DEFAULT_MIN_CONFIDENCE)
+}
+
+/// Fields of the step's receipt the model was less sure of than the step allows, with how
+/// sure it was. A receipt that can't be read counts as all of them.
+pub fn unsure_fields(params: &Value) -> Vec<(String, f64)> {
+    let Ok(receipt) = receipt_param(params) else {
+        return FIELDS.iter().map(|f| (f.to_string(), 0.0)).collect();
+    };
+    let min = min_confidence(params);
+    FIELDS.iter()
+        .map(|f| (f.to_string(), receipt.confidence.get(*f).copied().unwrap_or(0.0)))
+        .filter(|(_, confidence)| *confidence < min)
+        .collect()
+}
+
+fn ledger_path(conn: &Connection, params: &Value) -> Result<String, String> {
+    if let Some(ledger) = params.get("ledger").and_then(Value::as_str).filter(|l| !l.trim().is_empty()) {
+        return Ok(ledger.to_string());
+    }
+    Ok(read_setting(conn, LEDGER_SETTING)?.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| DEFAULT_LEDGER.into()))
+}
+
+fn amount(value: f64) -> String {
+    format!("{value:.2}")
+}
+
+/// What logging the step's receipt would add, flagging the fields to check.
+pub fn preview(conn: &Connection, params: &Value) -> String {
+    let ledger = ledger_path(conn, params).unwrap_or_else(|_| DEFAULT_LEDGER.into());
+    let receipt = match receipt_param(params) {
+        Ok(receipt) => receipt,
+        Err(e) => return format!("Would add a receipt to {ledger}, but {}", e.to_lowercase()),
+    };
+    let mut text = format!(
+        "Would add {}, {}, {} {} (tax {}) to {ledger}",
+        receipt.vendor, receipt.date, amount(receipt.total), receipt.currency, amount(receipt.tax),
+    );
+    let unsure = unsure_fields(params);
+    if !unsure.is_empty() {
+        let fields: Vec<String> = unsure.iter().map(|(f, c)| format!("{f} ({:.0}% sure)", c * 100.0)).collect();
+        text.push_str(&format!(". Check {} before approving", fields.join(", ")));
+    }
+    for problem in &receipt.problems {
+        text.push_str(&format!("\n{problem}"));
+    }
+    text
+}
+
+pub fn touched_dirs(ctx: &ToolContext, params: &Value) -> Vec<PathBuf> {
+    let ledger = {
+        let db = ctx.app.state::<DbState>();
+        let Ok(conn) = db.0.lock() else { return Vec::new() };
+        ledger_path(&conn, params).unwrap_or_else(|_| DEFAULT_LEDGER.into())
+    };
+    ctx.path(&ledger).parent().map(PathBuf::from).into_iter().collect()
+}
+
+/// Adds the receipt as a row of the ledger, creating it with a header row first. A receipt
+/// already there, by date, vendor and total, isn't added twice.
+fn append(ledger: &Path, receipt: &Receipt) -> Result<String, String> {
+    let key = [receipt.date.clone(), csv_field(&receipt.vendor), amount(receipt.total)].join(",") + ",";
+    let existing = fs::read_to_string(ledger).unwrap_or_default();
+    if existing.lines().any(|line| line.starts_with(&key)) {
+        return Ok(format!("{} on {} is already in {}", receipt.vendor, receipt.date, ledger.display()));
+    }
+    if let Some(folder) = ledger.parent().filter(|f| !f.as_os_str().is_empty()) {
+        fs::create_dir_all(folder).map_err(|e| format!("Can't create {}: {e}", folder.display()))?;
+    }
+    let mut row = String::new();
+    if existing.trim().is_empty() {
+        row.push_str(LEDGER_HEADER);
+        row.push('\n');
+    } else if !existing.ends_with('\n') {
+        row.push('\n');
+    }
+    row.push_str(&[
+        receipt.date.clone(),
+        csv_field(&receipt.vendor),
+        amount(receipt.total),
+        amount(receipt.tax),
+        csv_field(&receipt.currency),
+        csv_field(&receipt.file),
+        Local::now().format("%Y-%m-%d %H:%M").to_string(),
+    ].join(","));
+    row.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(ledger)
+        .map_err(|e| format!("Can't open {}: {e}", ledger.display()))?;
+    file.write_all(row.as_bytes()).map_err(|e| format!("Can't write {}: {e}", ledger.display()))?;
+    Ok(format!(
+        "Added {}, {}, {} {} to {}",
+        receipt.vendor, receipt.date, amount(receipt.total), receipt.currency, ledger.display(),
+    ))
+}
+
+/// Runs a `log_receipt` step.
+pub fn run_tool(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let receipt = receipt_param(params)?;
+    let ledger = {
+        let db = ctx.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        ledger_path(&conn, params)?
+    };
+    append(&ctx.path(&ledger), &receipt)
+}
+
+fn steps(path: &str) -> Vec<WorkflowStep> {
+    let step = |tool: &str, params: Value, label: &str| WorkflowStep::Tool { tool: tool.into(), params, label: label.into() };
+    vec![
+        step("extract_receipt", json!({ "path": path }), "Read the receipt"),
+        step("log_receipt", json!({ "receipt": "{{step_1}}" }), "Add it to the ledger"),
+    ]
+}
+
+/// Built-in template: read a receipt or invoice and add it to the ledger.
+pub fn template() -> CatalogTemplate {
+    CatalogTemplate {
+        id: "builtin:receipt-ledger".into(),
+        name: "Receipt ledger".into(),
+        description: "Reads the vendor, date, total and tax from a receipt or invoice, photo or PDF, \
+            and adds them to a spreadsheet. Anything the model isn't sure of waits for you to check.".into(),
+        author: "OpenClaw".into(),
+        tags: vec!["receipts".into(), "invoices".into(), "expenses".into(), "spreadsheet".into()],
+        role: "Bookkeeper".into(),
+        goal: "Log the receipt in the expenses spreadsheet".into(),
+        schedule: String::new(),
+        tools: vec!["extract_receipt".into(), "log_receipt".into()],
+        workflow: steps("~/Documents/Receipt.jpg"),
+    }
+}
+
+// ─── Receipt Commands ───
+
+/// Reads a receipt or invoice dropped on the app and adds it to the ledger, as a run so a
+/// low-confidence entry can wait for approval like any other step.
+#[tauri::command]
+pub async fn process_receipt(app: AppHandle, path: String) -> Result<Run, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let file = files::expand_path(&path);
+        if !file.is_file() {
+            return Err(format!("{} isn't a file", file.display()));
+        }
+        let name = format!("Receipt {}", fileops::name(&file));
+        let run = executor::execute_pipeline(&app, &name, steps(&file.display().to_string()), "drop")?;
+        executor::outbound(&app, run)
+    }).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,342 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+use chrono::{DateTime, Local, Utc};
+
+use crate::artifacts;
+use crate::storage;
+use crate::windows;
+use crate::{DbState, app_data_dir, read_setting};
+
+/// Longest recording allowed, in minutes, unless `recording_max_minutes` asks for less.
+const MAX_MINUTES: u64 = 240;
+const DEFAULT_MINUTES: u64 = 120;
+/// Largest single recording, in MB, unless `recording_max_mb` says otherwise.
+const DEFAULT_MAX_MB: u64 = 200;
+/// All recordings together may use at most this much disk.
+const QUOTA_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// How often the level meter updates.
+const LEVEL_INTERVAL: Duration = Duration::from_millis(150);
+/// How long ffmpeg gets to finish the file after being asked to stop.
+const STOP_GRACE: Duration = Duration::from_secs(5);
+const LEVEL_KEY: &str = "lavfi.astats.Overall.RMS_level=";
+/// Where ffmpeg is installed; `ffmpeg` on the path when unset.
+const FFMPEG_SETTING: &str = "ffmpeg_path";
+/// The path is to a program on this computer.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[FFMPEG_SETTING];
+
+struct Active {
+    id: String,
+    source: String,
+    path: PathBuf,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    max_seconds: u64,
+    max_bytes: u64,
+    child: Child,
+    level: Arc<Mutex<Option<f64>>>,
+    /// ffmpeg's last words, for when it stops on its own.
+    last_error: Arc<Mutex<String>>,
+}
+
+/// The recording in progress, if any. One at a time.
+#[derive(Default)]
+pub struct Recorder(Mutex<Option<Active>>);
+
+/// Whether the app is recording, for the recording indicator.
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingStatus {
+    pub recording: bool,
+    pub id: String,
+    /// `microphone` or `system`.
+    pub source: String,
+    pub started_at: String,
+    pub elapsed_seconds: u64,
+    pub bytes: u64,
+    pub max_seconds: u64,
+    pub max_bytes: u64,
+    /// Loudness in dBFS, from about -90 (silence) to 0.
+    pub level_db: Option<f64>,
+}
+
+/// A finished recording, kept in the recordings folder and listed as a `recording` artifact.
+#[derive(Debug, Serialize, Clone)]
+pub struct Recording {
+    pub id: String,
+    pub source: String,
+    pub path: String,
+    pub started_at: String,
+    pub duration_seconds: u64,
+    pub bytes: u64,
+    /// `user`, `time_limit`, `size_limit` or `error`.
+    pub stopped_by: String,
+    /// Why ffmpeg stopped, when it wasn't asked to.
+    pub error: String,
+}
+
+pub fn recordings_dir() -> PathBuf {
+    app_data_dir().join("recordings")
+}
+
+fn ffmpeg(conn: &Connection) -> Result<String, String> {
+    Ok(read_setting(conn, FFMPEG_SETTING)?.filter(|p| !p.trim().is_empty()).unwrap_or_else(|| "ffmpeg".into()))
+}
+
+/// Audio devices ffmpeg sees through DirectShow, by name.
+fn dshow_devices(ffmpeg: &str) -> Vec<String> {
+    let Ok(out) = Command::new(ffmpeg).args(["-hide_banner", "-list_devices", "true", "-f", "dshow", "-i", "dummy"]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&out.stderr).lines()
+        .filter(|line| line.trim_end().ends_with("(audio)"))
+        .filter_map(|line| line.split('"').nth(1).map(str::to_string))
+        .collect()
+}
+
+/// ffmpeg input arguments for `source` on this platform. Recording what the computer plays
+/// needs a loopback device outside Linux, which has a monitor of every output built in.
+fn input_args(conn: &Connection, ffmpeg: &str, source: &str) -> Result<Vec<String>, String> {
+    let setting = if source == "system" { "recording_system_device" } else { "recording_microphone_device" };
+    let device = read_setting(conn, setting)?.filter(|d| !d.trim().is_empty());
+    let args = |format: &str, input: String| vec!["-f".to_string(), format.to_string(), "-i".to_string(), input];
+    if cfg!(target_os = "linux") {
+        let fallback = if source == "system" { "@DEFAULT_MONITOR@" } else { "default" };
+        return Ok(args("pulse", device.unwrap_or_else(|| fallback.into())));
+    }
+    if cfg!(target_os = "macos") {
+        return match (device, source) {
+            (Some(device), _) => Ok(args("avfoundation", format!(":{device}"))),
+            (None, "system") => Err("Recording what the computer plays needs a loopback device such as BlackHole. \
+                Install one and choose it under recording_system_device.".into()),
+            (None, _) => Ok(args("avfoundation", ":0".into())),
+        };
+    }
+    let devices = dshow_devices(ffmpeg);
+    let device = device.or_else(|| match source {
+        "system" => devices.iter().find(|d| d.to_lowercase().contains("stereo mix")).cloned(),
+        _ => devices.iter().find(|d| !d.to_lowercase().contains("stereo mix")).cloned(),
+    });
+    match device {
+        Some(device) => Ok(args("dshow", format!("audio={device}"))),
+        None if source == "system" => Err("No \"Stereo Mix\" device is turned on. Enable it in the Windows sound \
+            settings, or choose a loopback device under recording_system_device.".into()),
+        None => Err("No microphone was found".into()),
+    }
+}
+
+fn limit(conn: &Connection, key: &str, default: u64, max: u64) -> Result<u64, String> {
+    Ok(read_setting(conn, key)?.and_then(|v| v.parse().ok()).unwrap_or(default).clamp(1, max))
+}
+
+fn status_of(active: &Active) -> RecordingStatus {
+    RecordingStatus {
+        recording: true,
+        id: active.id.clone(),
+        source: active.source.clone(),
+        started_at: active.started_at.to_rfc3339(),
+        elapsed_seconds: active.started.elapsed().as_secs(),
+        bytes: std::fs::metadata(&active.path).map_or(0, |m| m.len()),
+        max_seconds: active.max_seconds,
+        max_bytes: active.max_bytes,
+        level_db: active.level.lock().ok().and_then(|l| *l),
+    }
+}
+
+fn idle() -> RecordingStatus {
+    RecordingStatus {
+        recording: false,
+        id: String::new(),
+        source: String::new(),
+        started_at: String::new(),
+        elapsed_seconds: 0,
+        bytes: 0,
+        max_seconds: 0,
+        max_bytes: 0,
+        level_db: None,
+    }
+}
+
+/// Files a stopped recording: the file stays in the recordings folder and an artifact with
+/// its path is recorded. Tells every window the indicator can go off.
+fn finish(app: &AppHandle, mut active: Active, asked: bool) -> Result<Recording, String> {
+    let exited = active.child.wait().map_err(|e| e.to_string())?;
+    let duration_seconds = active.started.elapsed().as_secs();
+    let bytes = std::fs::metadata(&active.path).map_or(0, |m| m.len());
+    let error = active.last_error.lock().map(|e| e.clone()).unwrap_or_default();
+    let stopped_by = if asked {
+        "user"
+    } else if duration_seconds + 1 >= active.max_seconds {
+        "time_limit"
+    } else if bytes * 100 >= active.max_bytes * 98 {
+        "size_limit"
+    } else if exited.success() {
+        "user"
+    } else {
+        "error"
+    };
+    let recording = Recording {
+        id: active.id.clone(),
+        source: active.source.clone(),
+        path: active.path.display().to_string(),
+        started_at: active.started_at.to_rfc3339(),
+        duration_seconds,
+        bytes,
+        stopped_by: stopped_by.into(),
+        error: if stopped_by == "error" { error } else { String::new() },
+    };
+    windows::broadcast(app, "recording-state", &json!({ "recording": false, "id": recording.id, "stopped_by": recording.stopped_by }));
+    if bytes == 0 {
+        let _ = std::fs::remove_file(&active.path);
+        return Err(match recording.error.is_empty() {
+            true => "Nothing was recorded".into(),
+            false => format!("Recording failed: {}", recording.error),
+        });
+    }
+    let name = format!(
+        "{} recording {} ({}:{:02})",
+        if recording.source == "system" { "System audio" } else { "Microphone" },
+        active.started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        duration_seconds / 60,
+        duration_seconds % 60,
+    );
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    artifacts::record(&conn, "", "", 0, "recording", &name, &recording.path)?;
+    Ok(recording)
+}
+
+/// Reads ffmpeg's log: level meter lines become `recording-level` events, the rest is kept
+/// as the last error. When the log ends ffmpeg has stopped; if nobody asked it to (a limit
+/// was reached or the device went away) the recording is filed here.
+fn watch(app: AppHandle, id: String, stderr: impl std::io::Read, level: Arc<Mutex<Option<f64>>>, last_error: Arc<Mutex<String>>) {
+    let mut last_emit = Instant::now() - LEVEL_INTERVAL;
+    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+        if let Some(value) = line.split(LEVEL_KEY).nth(1) {
+            let db = value.trim().parse::<f64>().ok().map(|v| if v.is_finite() { v.max(-90.0) } else { -90.0 });
+            if let Ok(mut current) = level.lock() {
+                *current = db;
+            }
+            if last_emit.elapsed() >= LEVEL_INTERVAL {
+                last_emit = Instant::now();
+                windows::broadcast(&app, "recording-level", &json!({ "id": id, "level_db": db }));
+            }
+        } else if !line.trim().is_empty() {
+            if let Ok(mut last) = last_error.lock() {
+                *last = line.trim().to_string();
+            }
+        }
+    }
+    let ended = {
+        let recorder = app.state::<Recorder>();
+        let Ok(mut slot) = recorder.0.lock() else { return };
+        match slot.as_ref() {
+            Some(active) if active.id == id => slot.take(),
+            _ => None,
+        }
+    };
+    if let Some(active) = ended {
+        let _ = finish(&app, active, false);
+    }
+}
+
+// ─── Recording Commands ───
+
+/// Starts recording the microphone or, with `source` `"system"`, what the computer plays.
+/// ffmpeg itself stops at the duration and size limits, so a forgotten recording can't fill
+/// the disk.
+#[tauri::command]
+pub fn start_recording(app: AppHandle, db: State<DbState>, recorder: State<Recorder>, source: Option<String>) -> Result<RecordingStatus, String> {
+    let source = source.unwrap_or_else(|| "microphone".into());
+    if !matches!(source.as_str(), "microphone" | "system") {
+        return Err(format!("Unknown recording source: {source}"));
+    }
+    let mut slot = recorder.0.lock().map_err(|e| e.to_string())?;
+    if slot.is_some() {
+        return Err("A recording is already in progress".into());
+    }
+    let (ffmpeg, input, max_seconds, max_mb) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let ffmpeg = ffmpeg(&conn)?;
+        let input = input_args(&conn, &ffmpeg, &source)?;
+        (ffmpeg, input, limit(&conn, "recording_max_minutes", DEFAULT_MINUTES, MAX_MINUTES)? * 60, limit(&conn, "recording_max_mb", DEFAULT_MAX_MB, QUOTA_BYTES / 1_048_576)?)
+    };
+    let dir = recordings_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Can't create {}: {e}", dir.display()))?;
+    let used = storage::dir_size(&dir);
+    if used >= QUOTA_BYTES {
+        return Err(format!(
+            "Recordings already use {} MB, the most allowed. Delete old ones under Storage first.",
+            used / 1_048_576,
+        ));
+    }
+    let max_bytes = (max_mb * 1_048_576).min(QUOTA_BYTES - used);
+
+    let id = Uuid::new_v4().to_string();
+    let path = dir.join(format!("{}-{}.m4a", Local::now().format("%Y%m%d-%H%M%S"), &id[..8]));
+    let mut child = Command::new(&ffmpeg)
+        .args(["-hide_banner", "-nostats", "-loglevel", "info", "-y"])
+        .args(&input)
+        .args(["-t", &max_seconds.to_string(), "-fs", &max_bytes.to_string()])
+        .args(["-af", &format!("astats=metadata=1:reset=1,ametadata=mode=print:key={}", LEVEL_KEY.trim_end_matches('='))])
+        .args(["-ac", "1", "-c:a", "aac", "-b:a", "64k"])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Couldn't start ffmpeg ({e}). Install it or set ffmpeg_path to where it is."))?;
+
+    let level = Arc::new(Mutex::new(None));
+    let last_error = Arc::new(Mutex::new(String::new()));
+    if let Some(stderr) = child.stderr.take() {
+        let (app, id, level, last_error) = (app.clone(), id.clone(), level.clone(), last_error.clone());
+        std::thread::Builder::new()
+            .name("recording-monitor".into())
+            .spawn(move || watch(app, id, stderr, level, last_error))
+            .map_err(|e| e.to_string())?;
+    }
+    let active = Active { id, source, path, started_at: Utc::now(), started: Instant::now(), max_seconds, max_bytes, child, level, last_error };
+    let status = status_of(&active);
+    *slot = Some(active);
+    windows::broadcast(&app, "recording-state", &status);
+    Ok(status)
+}
+
+/// Stops the recording and files it. ffmpeg is asked to finish the file first, so it stays
+/// playable; one that doesn't is stopped after a few seconds.
+#[tauri::command]
+pub async fn stop_recording(app: AppHandle) -> Result<Recording, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut active = {
+            let recorder = app.state::<Recorder>();
+            let mut slot = recorder.0.lock().map_err(|e| e.to_string())?;
+            slot.take().ok_or("Nothing is being recorded")?
+        };
+        if let Some(stdin) = active.child.stdin.as_mut() {
+            let _ = stdin.write_all(b"q\n");
+        }
+        let deadline = Instant::now() + STOP_GRACE;
+        while active.child.try_wait().map_err(|e| e.to_string())?.is_none() {
+            if Instant::now() >= deadline {
+                let _ = active.child.kill();
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        finish(&app, active, true)
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// For the recording indicator; `recording-state` events announce changes.
+#[tauri::command]
+pub fn get_recording_status(recorder: State<Recorder>) -> Result<RecordingStatus, String> {
+    let slot = recorder.0.lock().map_err(|e| e.to_string())?;
+    Ok(slot.as_ref().map(status_of).unwrap_or_else(idle))
+}
@@ -0,0 +1,197 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::{AppHandle, Manager, State};
+
+use crate::executor::{self, Run, RunMode, StepResult, RUN_COLUMNS};
+use crate::sanitize;
+use crate::tools;
+use crate::{DbState, insert_log};
+
+/// Word overlap below which a model-written output counts as changed, and as a regression.
+const OUTPUT_SIMILARITY_THRESHOLD: f64 = 0.5;
+const REGRESSION_SIMILARITY: f64 = 0.2;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StepDiff {
+    pub index: usize,
+    pub tool: String,
+    /// `missing_step`, `extra_step`, `tool_changed`, `status_changed`, `params_changed` or `output_changed`.
+    pub kind: String,
+    /// `regression` fails the test; `warning` is worth a look but expected to drift.
+    pub severity: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentTestReport {
+    pub agent_id: String,
+    pub golden_run_id: String,
+    pub test_run_id: String,
+    pub passed: bool,
+    pub differences: Vec<StepDiff>,
+}
+
+fn golden_run(conn: &Connection, agent_id: &str) -> Result<Option<Run>, String> {
+    conn.query_row(
+        &format!("SELECT {RUN_COLUMNS} FROM runs WHERE agent_id = ?1 AND golden = 1 ORDER BY started_at DESC LIMIT 1"),
+        params![agent_id],
+        executor::row_to_run,
+    ).optional().map_err(|e| e.to_string())
+}
+
+/// Share of distinct words the two texts have in common (Jaccard index).
+pub(crate) fn text_similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+/// A golden run may have been live while tests are simulated, so "did it" and "would do it"
+/// count as the same outcome.
+fn outcome(status: &str) -> &str {
+    match status {
+        "ok" | "simulated" => "ok",
+        other => other,
+    }
+}
+
+fn compare_step(expected: &StepResult, actual: &StepResult) -> Vec<StepDiff> {
+    let diff = |kind: &str, severity: &str, e: String, a: String| StepDiff {
+        index: expected.index,
+        tool: expected.tool.clone(),
+        kind: kind.into(),
+        severity: severity.into(),
+        expected: e,
+        actual: a,
+    };
+    if expected.tool != actual.tool {
+        return vec![diff("tool_changed", "regression", expected.tool.clone(), actual.tool.clone())];
+    }
+    if outcome(&expected.status) != outcome(&actual.status) {
+        let detail = |s: &StepResult| if s.error.is_empty() { s.status.clone() } else { format!("{}: {}", s.status, s.error) };
+        return vec![diff("status_changed", "regression", detail(expected), detail(actual))];
+    }
+
+    let mut diffs = Vec::new();
+    let side_effect = tools::find(&expected.tool).map(|t| t.side_effect).unwrap_or(false);
+    if side_effect {
+        // Side effects are compared by what they were asked to do; their outputs differ
+        // between live and simulated runs by design.
+        if expected.params != actual.params {
+            diffs.push(diff("params_changed", "regression", expected.params.to_string(), actual.params.to_string()));
+        }
+    } else if expected.output != actual.output {
+        // Read-only tools see whatever the files or pages contain today, so a change there is
+        // only a warning. Model output always drifts a little; only a large drift is flagged.
+        let similarity = text_similarity(&expected.output, &actual.output);
+        let severity = if expected.tool != "llm_prompt" {
+            Some("warning")
+        } else if similarity < REGRESSION_SIMILARITY {
+            Some("regression")
+        } else if similarity < OUTPUT_SIMILARITY_THRESHOLD {
+            Some("warning")
+        } else {
+            None
+        };
+        if let Some(severity) = severity {
+            diffs.push(diff("output_changed", severity, expected.output.clone(), actual.output.clone()));
+        }
+    }
+    diffs
+}
+
+pub fn compare_runs(golden: &Run, test: &Run) -> Vec<StepDiff> {
+    let mut diffs = Vec::new();
+    for (i, expected) in golden.steps.iter().enumerate() {
+        match test.steps.get(i) {
+            Some(actual) => diffs.extend(compare_step(expected, actual)),
+            None => diffs.push(StepDiff {
+                index: expected.index,
+                tool: expected.tool.clone(),
+                kind: "missing_step".into(),
+                severity: "regression".into(),
+                expected: expected.label.clone(),
+                actual: if test.error.is_empty() { String::new() } else { test.error.clone() },
+            }),
+        }
+    }
+    for extra in test.steps.iter().skip(golden.steps.len()) {
+        diffs.push(StepDiff {
+            index: extra.index,
+            tool: extra.tool.clone(),
+            kind: "extra_step".into(),
+            severity: "warning".into(),
+            expected: String::new(),
+            actual: extra.label.clone(),
+        });
+    }
+    diffs
+}
+
+// ─── Regression Commands ───
+
+/// Marks a successful run as the expected result for `test_agent`; an agent has at most one.
+#[tauri::command]
+pub fn set_golden_run(db: State<DbState>, run_id: String, golden: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let run = executor::load_run(&conn, &run_id)?;
+    if golden && run.status != "success" {
+        return Err("Only successful runs can be used as the expected result".into());
+    }
+    if golden {
+        conn.execute("UPDATE runs SET golden = 0 WHERE agent_id = ?1", params![run.agent_id])
+            .map_err(|e| e.to_string())?;
+    }
+    conn.execute("UPDATE runs SET golden = ?1 WHERE id = ?2", params![golden as i32, run_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Re-runs the agent in simulate mode and compares the result with its golden run.
+#[tauri::command]
+pub async fn test_agent(app: AppHandle, id: String) -> Result<AgentTestReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let golden = {
+            let db = app.state::<DbState>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            golden_run(&conn, &id)?.ok_or("Mark a successful run as expected before testing")?
+        };
+        let test = executor::execute_agent(&app, &id, RunMode::Simulate, "test")?;
+        let differences = compare_runs(&golden, &test);
+        let passed = !differences.iter().any(|d| d.severity == "regression");
+
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let summary = differences.iter()
+            .map(|d| format!("Step {} ({}) {} [{}]", d.index + 1, d.tool, d.kind, d.severity))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insert_log(
+            &conn,
+            &id,
+            "Regression test",
+            if passed { "success" } else { "error" },
+            if summary.is_empty() { "Matches the expected run" } else { &summary },
+            "",
+        )?;
+        let mut report = AgentTestReport {
+            agent_id: id,
+            golden_run_id: golden.id,
+            test_run_id: test.id,
+            passed,
+            differences,
+        };
+        sanitize::test_report(&mut report, sanitize::mode(&conn));
+        Ok(report)
+    }).await.map_err(|e| e.to_string())?
+}
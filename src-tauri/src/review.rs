@@ -0,0 +1,365 @@
+use chrono::{DateTime, Datelike, Duration, Local, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::bulk;
+use crate::llm::{self, ChatMessage, LlmRequest};
+use crate::windows;
+use crate::{DbState, read_setting, update_agent_config};
+
+/// The parts of a review, in the order they are walked through.
+const STEPS: &[&str] = &["runs", "approvals", "drafts", "suggestions"];
+/// `mon` … `sun`: the day the weekly review is offered. Unset, it isn't offered.
+const REVIEW_DAY_SETTING: &str = "week_review_day";
+const DAYS: &[&str] = &["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+/// Agent drafts untouched this long count as stale.
+const STALE_DRAFT_DAYS: i64 = 3;
+/// An agent failing at least this often, and in most of its runs, is worth a look.
+const FAILING_RUNS: i64 = 3;
+/// Enabled agents that haven't run for this long could be turned off.
+const IDLE_DAYS: i64 = 28;
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+const SUMMARY_SYSTEM: &str = "You help someone look back on a week of their automations. \
+In three or four friendly sentences, say what went well, what needs attention and what to do first.";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentWeek {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub runs: i64,
+    pub succeeded: i64,
+    pub failed: i64,
+    /// The most recent error, if any run failed.
+    pub last_error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WaitingItem {
+    pub id: String,
+    pub title: String,
+    pub detail: String,
+    pub since: String,
+}
+
+/// A change the review proposes, applied only when the user says so.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewSuggestion {
+    pub id: String,
+    /// `disable_agent`, `disable_schedule` or `improve_agent` (opens the improvement flow).
+    pub kind: String,
+    pub agent_id: String,
+    pub schedule_id: Option<String>,
+    pub title: String,
+    pub reason: String,
+    /// `pending`, `applied` or `dismissed`.
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeekReview {
+    pub id: String,
+    /// Monday of the week reviewed, `YYYY-MM-DD`.
+    pub week_start: String,
+    /// `in_progress` or `completed`.
+    pub status: String,
+    /// The part the user is on, one of `runs`, `approvals`, `drafts`, `suggestions`.
+    pub step: String,
+    pub summary: String,
+    pub agents: Vec<AgentWeek>,
+    pub approvals: Vec<WaitingItem>,
+    pub drafts: Vec<WaitingItem>,
+    pub suggestions: Vec<ReviewSuggestion>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS week_reviews (
+            id TEXT PRIMARY KEY,
+            week_start TEXT NOT NULL,
+            status TEXT NOT NULL,
+            review_json TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_week_reviews_week ON week_reviews(week_start);
+    ").expect("Failed to initialize review tables");
+}
+
+fn week_start(now: DateTime<Local>) -> String {
+    (now.date_naive() - Duration::days(now.weekday().num_days_from_monday() as i64)).format("%Y-%m-%d").to_string()
+}
+
+fn store(conn: &Connection, review: &WeekReview) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO week_reviews (id, week_start, status, review_json, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![review.id, review.week_start, review.status, serde_json::to_string(review).map_err(|e| e.to_string())?, review.created_at, review.updated_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn load(conn: &Connection, id: &str) -> Result<WeekReview, String> {
+    let json: String = conn.query_row("SELECT review_json FROM week_reviews WHERE id = ?1", params![id], |row| row.get(0))
+        .optional().map_err(|e| e.to_string())?.ok_or("Review not found")?;
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+fn agent_weeks(conn: &Connection, since: &str) -> Result<Vec<AgentWeek>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT r.agent_id, COALESCE(a.name, ''), COUNT(*),
+                SUM(r.status = 'success'), SUM(r.status = 'error'),
+                COALESCE((SELECT error FROM runs e WHERE e.agent_id = r.agent_id AND e.status = 'error' AND e.started_at >= ?1
+                          ORDER BY e.started_at DESC LIMIT 1), '')
+         FROM runs r JOIN agents a ON a.id = r.agent_id
+         WHERE r.started_at >= ?1 AND r.mode = 'live'
+         GROUP BY r.agent_id ORDER BY SUM(r.status = 'error') DESC, COUNT(*) DESC"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![since], |row| Ok(AgentWeek {
+        agent_id: row.get(0)?,
+        agent_name: row.get(1)?,
+        runs: row.get(2)?,
+        succeeded: row.get(3)?,
+        failed: row.get(4)?,
+        last_error: row.get(5)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn waiting_approvals(conn: &Connection) -> Result<Vec<WaitingItem>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT q.id, COALESCE(a.name, 'Quick task'), q.content_preview, q.created_at
+         FROM approval_queue q LEFT JOIN agents a ON a.id = q.agent_id
+         WHERE q.status = 'pending' ORDER BY q.created_at"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok(WaitingItem { id: row.get(0)?, title: row.get(1)?, detail: row.get(2)?, since: row.get(3)? }))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Agent drafts left half-built, and improvement suggestions nobody decided on.
+fn stale_drafts(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<WaitingItem>, String> {
+    let cutoff = (now - Duration::days(STALE_DRAFT_DAYS)).to_rfc3339();
+    let mut stmt = conn.prepare(
+        "SELECT id, 'Agent draft', description, updated_at FROM agent_draft_sessions WHERE status != 'saved' AND updated_at < ?1
+         UNION ALL
+         SELECT s.id, 'Suggestion for ' || COALESCE(a.name, 'an agent'), s.summary, s.created_at
+         FROM agent_suggestions s LEFT JOIN agents a ON a.id = s.agent_id WHERE s.status = 'pending' AND s.created_at < ?1
+         ORDER BY 4"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![cutoff], |row| Ok(WaitingItem { id: row.get(0)?, title: row.get(1)?, detail: row.get(2)?, since: row.get(3)? }))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn suggestion(kind: &str, agent_id: &str, schedule_id: Option<String>, title: String, reason: String) -> ReviewSuggestion {
+    ReviewSuggestion {
+        id: Uuid::new_v4().to_string(),
+        kind: kind.into(),
+        agent_id: agent_id.into(),
+        schedule_id,
+        title,
+        reason,
+        status: "pending".into(),
+    }
+}
+
+/// Schedule and agent changes worth considering: agents that mostly failed, enabled agents
+/// that haven't run in weeks, and schedules left on for disabled agents.
+fn suggestions(conn: &Connection, agents: &[AgentWeek], now: DateTime<Utc>) -> Result<Vec<ReviewSuggestion>, String> {
+    let mut found: Vec<ReviewSuggestion> = agents.iter()
+        .filter(|a| a.failed >= FAILING_RUNS && a.failed * 2 > a.runs)
+        .map(|a| suggestion(
+            "improve_agent", &a.agent_id, None,
+            format!("Look at why {} keeps failing", a.agent_name),
+            format!("{} of its {} runs failed this week", a.failed, a.runs),
+        ))
+        .collect();
+
+    let idle_since = (now - Duration::days(IDLE_DAYS)).to_rfc3339();
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.name, a.config_json FROM agents a WHERE a.created_at < ?1
+         AND NOT EXISTS (SELECT 1 FROM runs r WHERE r.agent_id = a.id AND r.started_at >= ?1)"
+    ).map_err(|e| e.to_string())?;
+    let idle = stmt.query_map(params![idle_since], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        .map_err(|e| e.to_string())?;
+    for row in idle {
+        let (id, name, config) = row.map_err(|e| e.to_string())?;
+        if !bulk::enabled(&config) {
+            continue;
+        }
+        found.push(suggestion("disable_agent", &id, None, format!("Turn off {name}"), format!("It hasn't run in {IDLE_DAYS} days")));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.agent_id, a.name, s.cron_expr, a.config_json FROM schedules s JOIN agents a ON a.id = s.agent_id
+         WHERE s.enabled = 1"
+    ).map_err(|e| e.to_string())?;
+    let scheduled = stmt.query_map([], |row| Ok((
+        row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?, row.get::<_, String>(4)?,
+    ))).map_err(|e| e.to_string())?;
+    for row in scheduled {
+        let (id, agent_id, name, cron, config) = row.map_err(|e| e.to_string())?;
+        if bulk::enabled(&config) {
+            continue;
+        }
+        found.push(suggestion(
+            "disable_schedule", &agent_id, Some(id),
+            format!("Turn off the {cron} schedule of {name}"),
+            format!("{name} is disabled, so the schedule can't start it"),
+        ));
+    }
+    Ok(found)
+}
+
+/// The model's short take on the week; a plain count when it can't be reached.
+fn summarize(db: &DbState, review: &WeekReview) -> String {
+    let runs: i64 = review.agents.iter().map(|a| a.runs).sum();
+    let failed: i64 = review.agents.iter().map(|a| a.failed).sum();
+    let fallback = format!(
+        "{runs} run(s) this week, {failed} failed. {} approval(s) waiting, {} stale draft(s), {} suggestion(s).",
+        review.approvals.len(), review.drafts.len(), review.suggestions.len(),
+    );
+    let facts = json!({ "agents": review.agents, "waiting_approvals": review.approvals.len(), "stale_drafts": review.drafts.len(),
+        "suggestions": review.suggestions.iter().map(|s| &s.title).collect::<Vec<_>>() });
+    llm::complete(db, &LlmRequest {
+        system: SUMMARY_SYSTEM.into(),
+        messages: vec![ChatMessage::user(facts.to_string())],
+        max_tokens: 300,
+        ..Default::default()
+    }).map(|r| r.text.trim().to_string()).ok().filter(|t| !t.is_empty()).unwrap_or(fallback)
+}
+
+fn compile(db: &DbState) -> Result<WeekReview, String> {
+    let now = Utc::now();
+    let mut review = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let agents = agent_weeks(&conn, &(now - Duration::days(7)).to_rfc3339())?;
+        WeekReview {
+            id: Uuid::new_v4().to_string(),
+            week_start: week_start(Local::now()),
+            status: "in_progress".into(),
+            step: STEPS[0].into(),
+            summary: String::new(),
+            approvals: waiting_approvals(&conn)?,
+            drafts: stale_drafts(&conn, now)?,
+            suggestions: suggestions(&conn, &agents, now)?,
+            agents,
+            created_at: now.to_rfc3339(),
+            updated_at: now.to_rfc3339(),
+        }
+    };
+    review.summary = summarize(db, &review);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store(&conn, &review)?;
+    Ok(review)
+}
+
+/// Starts the thread that offers the weekly review on the chosen day, once per week, with
+/// the `week-review-due` event.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("week-review".into())
+        .spawn(move || loop {
+            std::thread::sleep(CHECK_EVERY);
+            let due = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Local::now();
+                let today = DAYS[now.weekday().num_days_from_monday() as usize];
+                read_setting(&conn, REVIEW_DAY_SETTING).ok().flatten().is_some_and(|day| day == today)
+                    && conn.query_row("SELECT COUNT(*) FROM week_reviews WHERE week_start = ?1", params![week_start(now)], |row| row.get::<_, i64>(0))
+                        .is_ok_and(|n| n == 0)
+            };
+            if due {
+                windows::broadcast(&app, "week-review-due", &week_start(Local::now()));
+            }
+        })
+        .expect("Failed to start the weekly review reminder");
+}
+
+// ─── Weekly Review Commands ───
+
+/// Picks up this week's unfinished review where the user left off, or compiles a new one.
+#[tauri::command]
+pub async fn start_week_review(app: AppHandle) -> Result<WeekReview, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<WeekReview, String> {
+        let db = app.state::<DbState>();
+        let open: Option<String> = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.query_row(
+                "SELECT id FROM week_reviews WHERE week_start = ?1 AND status = 'in_progress' ORDER BY created_at DESC LIMIT 1",
+                params![week_start(Local::now())],
+                |row| row.get(0),
+            ).optional().map_err(|e| e.to_string())?
+        };
+        match open {
+            Some(id) => {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                load(&conn, &id)
+            }
+            None => compile(&db),
+        }
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_week_review(db: State<DbState>, id: String) -> Result<WeekReview, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load(&conn, &id)
+}
+
+/// Past reviews, newest first.
+#[tauri::command]
+pub fn list_week_reviews(db: State<DbState>) -> Result<Vec<WeekReview>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT review_json FROM week_reviews ORDER BY created_at DESC").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+    Ok(rows.filter_map(|r| r.ok()).filter_map(|json| serde_json::from_str(&json).ok()).collect())
+}
+
+/// Moves to another part of the review; `done` completes it.
+#[tauri::command]
+pub fn set_week_review_step(db: State<DbState>, id: String, step: String) -> Result<WeekReview, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut review = load(&conn, &id)?;
+    match step.as_str() {
+        "done" => review.status = "completed".into(),
+        step if STEPS.contains(&step) => review.step = step.into(),
+        other => return Err(format!("Unknown review step: {other}")),
+    }
+    review.updated_at = Utc::now().to_rfc3339();
+    store(&conn, &review)?;
+    Ok(review)
+}
+
+/// Applies or dismisses one of the review's suggestions. `improve_agent` only records the
+/// decision; the window opens the improvement flow for the agent.
+#[tauri::command]
+pub fn decide_review_suggestion(db: State<DbState>, id: String, suggestion_id: String, apply: bool) -> Result<WeekReview, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut review = load(&conn, &id)?;
+    let suggestion = review.suggestions.iter_mut().find(|s| s.id == suggestion_id).ok_or("Suggestion not found")?;
+    if suggestion.status != "pending" {
+        return Err(format!("That suggestion was already {}", suggestion.status));
+    }
+    if apply {
+        match (suggestion.kind.as_str(), &suggestion.schedule_id) {
+            ("disable_agent", _) => {
+                update_agent_config(&conn, &suggestion.agent_id, |config| { config.insert("enabled".into(), json!(false)); })?;
+            }
+            ("disable_schedule", Some(schedule)) => {
+                conn.execute("UPDATE schedules SET enabled = 0, updated_at = ?1 WHERE id = ?2", params![Utc::now().to_rfc3339(), schedule])
+                    .map_err(|e| e.to_string())?;
+            }
+            _ => {}
+        }
+    }
+    suggestion.status = if apply { "applied" } else { "dismissed" }.into();
+    review.updated_at = Utc::now().to_rfc3339();
+    store(&conn, &review)?;
+    Ok(review)
+}
@@ -0,0 +1,364 @@
+use chrono::{Duration, Local, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::sync::MutexGuard;
+use tauri::{AppHandle, Manager, State};
+use tiny_http::{Method, Request};
+use uuid::Uuid;
+
+use crate::api;
+use crate::audit;
+use crate::executor;
+use crate::net;
+use crate::sanitize::{self, Mode};
+use crate::windows;
+use crate::{ApprovalItem, DbState, read_setting};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const CHANNELS: &[&str] = &["email", "telegram", "slack"];
+const KEYCHAIN_LINK_ENTRY: &str = "approval-links";
+/// Address the approver's browser reaches the local API at, e.g. through a tunnel; the
+/// local API's own address when unset.
+const LINK_BASE_SETTING: &str = "approval_link_base_url";
+/// The address reaches this computer's local API, not another device's.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[LINK_BASE_SETTING];
+const DEFAULT_EXPIRES_HOURS: i64 = 24;
+const LINK_PATH: &str = "/approvals/decide";
+
+/// Sends approvals of some action types to another person, who answers with a link
+/// instead of in the app.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApprovalRoute {
+    #[serde(default)]
+    pub id: String,
+    /// Who decides, e.g. "Priya (office manager)"; recorded as `decided_by`.
+    pub approver: String,
+    /// Tools or action types it covers; `*` for all.
+    pub action_types: Vec<String>,
+    /// `email`, `telegram` or `slack`.
+    pub channel: String,
+    /// Address, chat id or channel on that channel.
+    pub target: String,
+    /// Hours the decision links work for.
+    #[serde(default)]
+    pub expires_hours: Option<i64>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS approval_routes (
+            id TEXT PRIMARY KEY,
+            approver TEXT NOT NULL,
+            action_types TEXT NOT NULL DEFAULT '[]',
+            channel TEXT NOT NULL,
+            target TEXT NOT NULL,
+            expires_hours INTEGER,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS approval_links (
+            nonce TEXT PRIMARY KEY,
+            approval_id TEXT NOT NULL,
+            route_id TEXT NOT NULL,
+            approver TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            used_at TEXT DEFAULT '',
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_approval_links_approval ON approval_links(approval_id);
+    ").expect("Failed to initialize approval routing tables");
+}
+
+fn row_to_route(row: &rusqlite::Row) -> rusqlite::Result<ApprovalRoute> {
+    Ok(ApprovalRoute {
+        id: row.get(0)?,
+        approver: row.get(1)?,
+        action_types: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+        channel: row.get(3)?,
+        target: row.get(4)?,
+        expires_hours: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+fn routes(conn: &Connection) -> Result<Vec<ApprovalRoute>, String> {
+    let mut stmt = conn.prepare("SELECT id, approver, action_types, channel, target, expires_hours, created_at FROM approval_routes ORDER BY created_at")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_route).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Key the links are signed with, made on first use and kept in the system keychain so it
+/// never syncs with the database.
+fn secret() -> Result<Vec<u8>, String> {
+    let entry = keyring::Entry::new(net::KEYCHAIN_SERVICE, KEYCHAIN_LINK_ENTRY)
+        .map_err(|e| format!("The system keychain is unavailable: {e}"))?;
+    match entry.get_password() {
+        Ok(hex) => Ok(hex.into_bytes()),
+        Err(keyring::Error::NoEntry) => {
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            let hex: String = key.iter().map(|b| format!("{b:02x}")).collect();
+            entry.set_password(&hex).map_err(|e| format!("Couldn't save the link key to the system keychain: {e}"))?;
+            Ok(hex.into_bytes())
+        }
+        Err(e) => Err(format!("Couldn't read the link key from the system keychain: {e}")),
+    }
+}
+
+fn mac(secret: &[u8], approval_id: &str, nonce: &str, decision: &str, expires: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(format!("{approval_id}:{nonce}:{decision}:{expires}").as_bytes());
+    mac
+}
+
+fn sign(secret: &[u8], approval_id: &str, nonce: &str, decision: &str, expires: i64) -> String {
+    mac(secret, approval_id, nonce, decision, expires).finalize().into_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn verify(secret: &[u8], approval_id: &str, nonce: &str, decision: &str, expires: i64, signature: &str) -> bool {
+    let bytes: Option<Vec<u8>> = (0..signature.len()).step_by(2)
+        .map(|i| signature.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect();
+    bytes.is_some_and(|bytes| mac(secret, approval_id, nonce, decision, expires).verify_slice(&bytes).is_ok())
+}
+
+fn link_base(conn: &Connection) -> Result<String, String> {
+    match read_setting(conn, LINK_BASE_SETTING)?.filter(|b| !b.trim().is_empty()) {
+        Some(base) => Ok(base.trim().trim_end_matches('/').to_string()),
+        None => api::base_url(conn)?.ok_or_else(|| "Turn on the local API so decision links have somewhere to go".into()),
+    }
+}
+
+pub(crate) fn deliver(channel: &str, target: &str, message: &str) -> Result<(), String> {
+    if channel == "email" {
+        return Err("No email account is connected yet".into());
+    }
+    let output = std::process::Command::new("openclaw")
+        .args(["message", "send", "--channel", channel, "--target", target, "--message", message])
+        .output()
+        .map_err(|e| format!("Couldn't start OpenClaw to send the message: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Sends a new approval to the person whose route covers its action type, with signed
+/// approve and reject links that work once. The item stays in the app's queue too, and
+/// whichever answer comes first counts, so a route that can't be used is only audited.
+pub fn dispatch(app: &AppHandle, conn: &Connection, item: &ApprovalItem) {
+    if let Err(e) = send_links(app, conn, item) {
+        let _ = audit::record(conn, "approval_route_failed", &item.agent_id, &item.run_id,
+            "Couldn't send the approval on; it is waiting in the app", &json!({ "approval_id": item.id, "error": e }));
+    }
+}
+
+fn send_links(app: &AppHandle, conn: &Connection, item: &ApprovalItem) -> Result<(), String> {
+    let Some(route) = routes(conn)?.into_iter()
+        .find(|r| r.action_types.iter().any(|t| t == "*" || *t == item.action_type)) else {
+        return Ok(());
+    };
+    net::check_egress(conn, "approval routing", None, &item.agent_id, &item.run_id)?;
+    let base = link_base(conn)?;
+    let secret = secret()?;
+    let mut nonce = [0u8; 16];
+    OsRng.fill_bytes(&mut nonce);
+    let nonce: String = nonce.iter().map(|b| format!("{b:02x}")).collect();
+    let expires = Utc::now() + Duration::hours(route.expires_hours.filter(|h| *h > 0).unwrap_or(DEFAULT_EXPIRES_HOURS));
+    conn.execute(
+        "INSERT INTO approval_links (nonce, approval_id, route_id, approver, expires_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![nonce, item.id, route.id, route.approver, expires.to_rfc3339(), Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+
+    let link = |decision: &str| format!(
+        "{base}{LINK_PATH}?id={}&nonce={nonce}&decision={decision}&expires={}&sig={}",
+        item.id, expires.timestamp(), sign(&secret, &item.id, &nonce, decision, expires.timestamp()),
+    );
+    let preview = sanitize::clean(&item.content_preview, sanitize::mode(conn));
+    let message = format!(
+        "Approval needed: {preview}\n\nApprove: {}\nReject: {}\n\nThe links work once, until {}.",
+        link("approved"), link("rejected"), expires.with_timezone(&Local).format("%H:%M on %a %-d %b"),
+    );
+    audit::record(conn, "approval_routed", &item.agent_id, &item.run_id, &format!("Sent to {} for approval", route.approver),
+        &json!({ "approval_id": item.id, "route_id": route.id, "channel": route.channel }))?;
+
+    // Sending can take a while; the caller holds the database.
+    let (app, item) = (app.clone(), item.clone());
+    std::thread::spawn(move || {
+        if let Err(e) = deliver(&route.channel, &route.target, &message) {
+            let db = app.state::<DbState>();
+            let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = audit::record(&conn, "approval_route_failed", &item.agent_id, &item.run_id,
+                &format!("Couldn't send the approval to {}; it is waiting in the app", route.approver),
+                &json!({ "approval_id": item.id, "error": e }));
+        }
+    });
+    Ok(())
+}
+
+/// A plain page for phones and browsers outside the app.
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width\">\
+         <title>{title}</title></head><body style=\"font-family:sans-serif;max-width:32rem;margin:3rem auto\">\
+         <h1>{title}</h1>{body}</body></html>"
+    )
+}
+
+/// Answers a decision link, or `None` for any other request so the API handles it. Opening
+/// the link only shows a confirmation, since chat apps open links to preview them; the
+/// decision is made by the form it posts.
+pub fn handle_link(app: &AppHandle, request: &Request) -> Option<(u16, String)> {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    if path != LINK_PATH {
+        return None;
+    }
+    let param = |name: &str| query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.to_string())
+        .unwrap_or_default();
+    let (id, nonce, decision, sig) = (param("id"), param("nonce"), param("decision"), param("sig"));
+    let expires: i64 = param("expires").parse().unwrap_or(0);
+
+    let invalid = |text: &str| Some((403, page("Link not valid", &format!("<p>{text}</p>"))));
+    if !matches!(decision.as_str(), "approved" | "rejected") {
+        return invalid("This link is incomplete.");
+    }
+    let Ok(secret) = secret() else {
+        return Some((500, page("Something went wrong", "<p>The link couldn't be checked. Try again later.</p>")));
+    };
+    if !verify(&secret, &id, &nonce, &decision, expires, &sig) {
+        return invalid("This link is incomplete or was changed.");
+    }
+    if Utc::now().timestamp() > expires {
+        return invalid("This link has expired. The request is still waiting in the app.");
+    }
+
+    let db = app.state::<DbState>();
+    let Ok(conn) = db.0.lock() else {
+        return Some((500, page("Something went wrong", "<p>Try again in a moment.</p>")));
+    };
+    let found: Option<(String, String, String, String)> = conn.query_row(
+        "SELECT l.approver, l.used_at, q.status, q.content_preview
+         FROM approval_links l JOIN approval_queue q ON q.id = l.approval_id
+         WHERE l.nonce = ?1 AND l.approval_id = ?2",
+        params![nonce, id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).optional().ok().flatten();
+    let Some((approver, used_at, status, preview)) = found else {
+        return invalid("This request no longer exists.");
+    };
+    if !used_at.is_empty() {
+        return invalid("This link was already used.");
+    }
+    if status != "pending" {
+        return invalid(&format!("This request was already {status}."));
+    }
+    let verb = if decision == "approved" { "Approve" } else { "Reject" };
+    let preview = sanitize::clean(&preview, Mode::Escape);
+
+    if *request.method() != Method::Post {
+        let body = format!(
+            "<p>{preview}</p><form method=\"post\"><button type=\"submit\" style=\"font-size:1.2rem;padding:.5rem 1.5rem\">{verb}</button></form>"
+        );
+        return Some((200, page(&format!("{verb} this request?"), &body)));
+    }
+
+    let _ = conn.execute("UPDATE approval_links SET used_at = ?1 WHERE approval_id = ?2", params![Utc::now().to_rfc3339(), id]);
+    if !decide(app, conn, &id, &decision, &approver, "a link") {
+        return invalid("This request was already answered.");
+    }
+    Some((200, page("Thanks", &format!("<p>{}. You can close this page.</p>", if decision == "approved" { "Approved" } else { "Rejected" }))))
+}
+
+/// Records a decision made away from the app, through a link or a paired phone, and resumes
+/// the run waiting on it. `false` when the item was already answered.
+pub fn decide(app: &AppHandle, conn: MutexGuard<'_, Connection>, id: &str, decision: &str, decided_by: &str, via: &str) -> bool {
+    let found: Option<(String, String)> = conn.query_row(
+        "SELECT agent_id, COALESCE(run_id, '') FROM approval_queue WHERE id = ?1 AND status = 'pending'",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().ok().flatten();
+    let Some((agent_id, run_id)) = found else {
+        return false;
+    };
+    let decided = conn.execute(
+        "UPDATE approval_queue SET status = ?1, decided_by = ?2, decided_at = ?3 WHERE id = ?4 AND status = 'pending'",
+        params![decision, decided_by, Utc::now().to_rfc3339(), id],
+    ).unwrap_or(0);
+    if decided == 0 {
+        return false;
+    }
+    let verb = if decision == "approved" { "Approved" } else { "Rejected" };
+    let _ = audit::record(&conn, "approval_decided", &agent_id, &run_id, &format!("{verb} by {decided_by} from {via}"),
+        &json!({ "approval_id": id, "decision": decision, "decided_by": decided_by }));
+    drop(conn);
+
+    windows::broadcast(app, "approvals-changed", &id);
+    if !run_id.is_empty() {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = executor::resume(&app, &run_id) {
+                eprintln!("Run {run_id} didn't resume after a remote decision: {e}");
+            }
+        });
+    }
+    true
+}
+
+// ─── Approval Routing Commands ───
+
+#[tauri::command]
+pub fn list_approval_routes(db: State<DbState>) -> Result<Vec<ApprovalRoute>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    routes(&conn)
+}
+
+/// Adds a route, or replaces the one with the same id. When two routes cover an action type
+/// the older one wins.
+#[tauri::command]
+pub fn save_approval_route(db: State<DbState>, route: ApprovalRoute) -> Result<ApprovalRoute, String> {
+    if route.approver.trim().is_empty() || route.target.trim().is_empty() {
+        return Err("Say who approves and where to reach them".into());
+    }
+    if !CHANNELS.contains(&route.channel.as_str()) {
+        return Err(format!("Channel must be one of {}", CHANNELS.join(", ")));
+    }
+    if route.action_types.is_empty() {
+        return Err("Pick at least one kind of action to send, or * for all".into());
+    }
+    let mut route = route;
+    if route.id.is_empty() {
+        route.id = Uuid::new_v4().to_string();
+    }
+    if route.created_at.is_empty() {
+        route.created_at = Utc::now().to_rfc3339();
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO approval_routes (id, approver, action_types, channel, target, expires_hours, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![route.id, route.approver.trim(), json!(route.action_types).to_string(), route.channel, route.target.trim(), route.expires_hours, route.created_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(route)
+}
+
+/// Links already sent for the route stop working.
+#[tauri::command]
+pub fn delete_approval_route(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM approval_routes WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    conn.execute("UPDATE approval_links SET used_at = ?1 WHERE route_id = ?2 AND used_at = ''", params![Utc::now().to_rfc3339(), id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
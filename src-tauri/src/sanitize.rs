@@ -0,0 +1,143 @@
+use rusqlite::Connection;
+
+use crate::artifacts::Artifact;
+use crate::builder::{AgentDraft, DraftSession};
+use crate::executor::{Run, StepResult};
+use crate::rag::KnowledgeHit;
+use crate::regression::AgentTestReport;
+use crate::{ApprovalItem, ExecutionLog, read_setting};
+
+/// Setting choosing how untrusted text (tool output, model replies) is cleaned before it
+/// reaches the webview. Stored data is left untouched so later steps still see raw output.
+const MODE_SETTING: &str = "output_sanitization";
+
+/// Set with `output_sanitization` = `strip`, `escape` or `markdown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Removes HTML tags entirely.
+    Strip,
+    /// Turns `<`, `>`, `&` and quotes into entities so text can't become markup.
+    Escape,
+    /// Removes HTML tags but keeps Markdown, neutralizing script links. The default.
+    Markdown,
+}
+
+pub fn mode(conn: &Connection) -> Mode {
+    match read_setting(conn, MODE_SETTING).ok().flatten().as_deref() {
+        Some("strip") => Mode::Strip,
+        Some("escape") => Mode::Escape,
+        _ => Mode::Markdown,
+    }
+}
+
+/// Drops `<tag ...>` runs. A `<` that doesn't start a tag (as in `a < b`) is kept.
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let starts_tag = after.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?');
+        match after.find('>') {
+            Some(end) if starts_tag => rest = &after[end + 1..],
+            _ => {
+                out.push('<');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Rewrites Markdown link targets using script-capable schemes to `#`.
+fn defuse_links(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("](") {
+        out.push_str(&rest[..start + 2]);
+        rest = &rest[start + 2..];
+        let target = rest.trim_start().to_ascii_lowercase();
+        if ["javascript:", "vbscript:", "data:"].iter().any(|s| target.starts_with(s)) {
+            out.push('#');
+            rest = &rest[rest.find(')').unwrap_or(rest.len())..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn clean(text: &str, mode: Mode) -> String {
+    match mode {
+        Mode::Strip => strip_tags(text),
+        Mode::Escape => escape(text),
+        Mode::Markdown => defuse_links(&strip_tags(text)),
+    }
+}
+
+fn clean_in_place(text: &mut String, mode: Mode) {
+    *text = clean(text, mode);
+}
+
+fn clean_step(step: &mut StepResult, mode: Mode) {
+    clean_in_place(&mut step.output, mode);
+    clean_in_place(&mut step.error, mode);
+}
+
+pub fn run(run: &mut Run, mode: Mode) {
+    run.steps.iter_mut().for_each(|s| clean_step(s, mode));
+    run.side_effects.iter_mut().for_each(|s| clean_in_place(s, mode));
+    clean_in_place(&mut run.error, mode);
+}
+
+pub fn log(log: &mut ExecutionLog, mode: Mode) {
+    clean_in_place(&mut log.output, mode);
+    clean_in_place(&mut log.error, mode);
+}
+
+pub fn approval(item: &mut ApprovalItem, mode: Mode) {
+    clean_in_place(&mut item.content_preview, mode);
+}
+
+/// Model-written notes; the draft's own fields are kept as-is because they get saved.
+pub fn draft(draft: &mut AgentDraft, mode: Mode) {
+    draft.notes.iter_mut().for_each(|n| clean_in_place(n, mode));
+}
+
+pub fn session(session: &mut DraftSession, mode: Mode) {
+    session.questions.iter_mut().for_each(|q| clean_in_place(&mut q.question, mode));
+    if let Some(d) = session.draft.as_mut() {
+        draft(d, mode);
+    }
+}
+
+pub fn artifact(artifact: &mut Artifact, mode: Mode) {
+    clean_in_place(&mut artifact.content, mode);
+}
+
+/// Indexed documents may contain HTML of their own.
+pub fn knowledge_hit(hit: &mut KnowledgeHit, mode: Mode) {
+    clean_in_place(&mut hit.text, mode);
+}
+
+pub fn test_report(report: &mut AgentTestReport, mode: Mode) {
+    for diff in &mut report.differences {
+        clean_in_place(&mut diff.expected, mode);
+        clean_in_place(&mut diff.actual, mode);
+    }
+}
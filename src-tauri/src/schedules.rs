@@ -0,0 +1,466 @@
+//! Agents started on a timetable. Each schedule is a five-field cron expression read in
+//! local time; a background thread starts agents as their schedules come due.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::blackout;
+use crate::executor::{self, RunMode};
+use crate::shutdown;
+use crate::sync;
+use crate::DbState;
+
+/// How often schedules are checked for being due.
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(15);
+/// A run due longer ago than this was missed while the app was closed or the computer
+/// asleep, and is made up or skipped as the agent's catch-up policy says.
+const MISSED_AFTER: Duration = Duration::minutes(2);
+/// How far ahead the next matching minute is looked for, enough for leap days.
+const SEARCH_DAYS: i64 = 366 * 8;
+const MONTHS: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Schedule {
+    pub id: String,
+    pub agent_id: String,
+    /// Minute, hour, day of month, month and day of week, e.g. `0 8 * * 1-5`.
+    pub cron_expr: String,
+    pub description: String,
+    pub enabled: bool,
+    /// When the schedule last started the agent, RFC 3339; empty if never.
+    pub last_run: String,
+    /// When it will next, RFC 3339; empty while disabled.
+    pub next_run: String,
+    pub updated_at: String,
+}
+
+const COLUMNS: &str = "id, agent_id, cron_expr, description, enabled, last_run, next_run, updated_at";
+
+fn row_to_schedule(row: &rusqlite::Row) -> rusqlite::Result<Schedule> {
+    Ok(Schedule {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        cron_expr: row.get(2)?,
+        description: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        enabled: row.get::<_, i32>(4)? != 0,
+        last_run: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+        next_run: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+        updated_at: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+    })
+}
+
+// ─── Cron ───
+
+/// A parsed cron expression, each field as a bit set of the values it allows.
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day of month and day of week were both given; a day then matches either.
+    either_day: bool,
+}
+
+fn value(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let value = match names.iter().position(|n| n.eq_ignore_ascii_case(text)) {
+        Some(i) => i as u32 + min,
+        None => text.parse().map_err(|_| format!("\"{text}\" isn't a number"))?,
+    };
+    if value < min || value > max {
+        return Err(format!("{value} is outside {min}-{max}"));
+    }
+    Ok(value)
+}
+
+/// One field as a bit set; `*`, `5`, `1-5`, `*/15`, `10-40/10` and lists of them, plus
+/// month and weekday names. Weekday 7 is Sunday, like 0.
+fn field(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(|| format!("\"{step}\" isn't a step"))?),
+            None => (part, 1),
+        };
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (value(from, min, max, names)?, value(to, min, max, names)?),
+            None if part.contains('/') => (value(range, min, max, names)?, max),
+            None => {
+                let v = value(range, min, max, names)?;
+                (v, v)
+            }
+        };
+        if from > to {
+            return Err(format!("{range} runs backwards"));
+        }
+        for v in (from..=to).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse(expr: &str) -> Result<Cron, String> {
+    let expr = match expr.trim() {
+        "@hourly" => "0 * * * *",
+        "@daily" | "@midnight" => "0 0 * * *",
+        "@weekly" => "0 0 * * 0",
+        "@monthly" => "0 0 1 * *",
+        "@yearly" | "@annually" => "0 0 1 1 *",
+        other => other,
+    };
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let [minute, hour, day, month, weekday] = fields.as_slice() else {
+        return Err("A schedule has five parts: minute, hour, day of month, month and day of week".into());
+    };
+    let explain = |name: &'static str| move |e: String| format!("The {name} part is wrong: {e}");
+    let mut weekdays = field(weekday, 0, 7, WEEKDAYS).map_err(explain("day of week"))?;
+    if weekdays & (1 << 7) != 0 {
+        weekdays |= 1;
+    }
+    Ok(Cron {
+        minutes: field(minute, 0, 59, &[]).map_err(explain("minute"))?,
+        hours: field(hour, 0, 23, &[]).map_err(explain("hour"))?,
+        days: field(day, 1, 31, &[]).map_err(explain("day of month"))?,
+        months: field(month, 1, 12, MONTHS).map_err(explain("month"))?,
+        weekdays,
+        either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+    })
+}
+
+impl Cron {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        self.months & (1 << date.month()) != 0 && if self.either_day { day || weekday } else { day && weekday }
+    }
+
+    /// The first matching minute after `after`. Times a clock change skips are passed over.
+    fn next_after<Tz: TimeZone>(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        for date in start.date().iter_days().take(SEARCH_DAYS as usize).filter(|d| self.day_matches(*d)) {
+            let today = date == start.date();
+            for hour in (0..24).filter(|h| self.hours & (1 << h) != 0 && !(today && *h < start.hour())) {
+                for minute in (0..60).filter(|m| self.minutes & (1 << m) != 0 && !(today && hour == start.hour() && *m < start.minute())) {
+                    if let Some(time) = after.timezone().from_local_datetime(&date.and_hms_opt(hour, minute, 0)?).earliest() {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// When `expr` next comes due after `after`, as stored in `next_run`.
+fn next_run(expr: &str, after: DateTime<Local>) -> Result<String, String> {
+    parse(expr)?.next_after(after)
+        .map(|time| time.with_timezone(&Utc).to_rfc3339())
+        .ok_or_else(|| format!("The schedule \"{expr}\" never comes due"))
+}
+
+// ─── Scheduler ───
+
+fn find(conn: &Connection, id: &str) -> Result<Schedule, String> {
+    conn.query_row(&format!("SELECT {COLUMNS} FROM schedules WHERE id = ?1"), params![id], row_to_schedule)
+        .optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "That schedule no longer exists".to_string())
+}
+
+/// Drops a deleted agent's schedules.
+pub fn remove_agent(conn: &Connection, agent_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM schedules WHERE agent_id = ?1", params![agent_id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Enabled schedules whose time has come, each moved on to its next time. Schedules without
+/// a next time, such as ones just synced from another device, only get one. Returns the
+/// agents to start, leaving out runs missed while the app was closed when the agent's
+/// catch-up policy is `skip`.
+fn take_due(conn: &Connection, now: DateTime<Local>) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM schedules WHERE enabled = 1 AND agent_id IN (SELECT id FROM agents)"
+    )).map_err(|e| e.to_string())?;
+    let schedules: Vec<Schedule> = stmt.query_map([], row_to_schedule).map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+    let mut due = Vec::new();
+    for schedule in schedules {
+        let at = DateTime::parse_from_rfc3339(&schedule.next_run).ok().map(|t| t.with_timezone(&Local));
+        if at.is_some_and(|at| at > now) {
+            continue;
+        }
+        let next = next_run(&schedule.cron_expr, now).unwrap_or_default();
+        let Some(at) = at else {
+            conn.execute("UPDATE schedules SET next_run = ?1 WHERE id = ?2", params![next, schedule.id]).map_err(|e| e.to_string())?;
+            continue;
+        };
+        if now - at > MISSED_AFTER && blackout::catch_up(conn, &schedule.agent_id) == "skip" {
+            conn.execute("UPDATE schedules SET next_run = ?1 WHERE id = ?2", params![next, schedule.id]).map_err(|e| e.to_string())?;
+            continue;
+        }
+        conn.execute(
+            "UPDATE schedules SET last_run = ?1, next_run = ?2 WHERE id = ?3",
+            params![now.with_timezone(&Utc).to_rfc3339(), next, schedule.id],
+        ).map_err(|e| e.to_string())?;
+        if !due.contains(&schedule.agent_id) {
+            due.push(schedule.agent_id);
+        }
+    }
+    Ok(due)
+}
+
+/// Starts the thread that runs agents when their schedules come due. Each run gets a thread
+/// of its own, so a long one doesn't hold up the others.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("scheduler".into())
+        .spawn(move || loop {
+            if !shutdown::stopping(&app) {
+                let due = {
+                    let db = app.state::<DbState>();
+                    let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                    take_due(&conn, Local::now()).unwrap_or_else(|e| {
+                        eprintln!("Couldn't check schedules: {e}");
+                        Vec::new()
+                    })
+                };
+                for agent_id in due {
+                    let app = app.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = executor::execute_agent(&app, &agent_id, RunMode::Live, "schedule") {
+                            eprintln!("Scheduled run of {agent_id} didn't start: {e}");
+                        }
+                    });
+                }
+            }
+            std::thread::sleep(CHECK_EVERY);
+        })
+        .expect("Failed to start the scheduler");
+}
+
+// ─── Schedule Commands ───
+
+/// Every schedule, or one agent's, soonest first.
+#[tauri::command]
+pub fn list_schedules(db: State<DbState>, agent_id: Option<String>) -> Result<Vec<Schedule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM schedules WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY enabled DESC, next_run = '', next_run"
+    )).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id], row_to_schedule).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_schedule(db: State<DbState>, agent_id: String, cron_expr: String, description: Option<String>) -> Result<Schedule, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let exists: bool = conn.query_row("SELECT COUNT(*) > 0 FROM agents WHERE id = ?1", params![agent_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err("That agent no longer exists".into());
+    }
+    let cron_expr = cron_expr.split_whitespace().collect::<Vec<_>>().join(" ");
+    let next = next_run(&cron_expr, Local::now())?;
+    let id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO schedules (id, agent_id, cron_expr, description, enabled, last_run, next_run, updated_at) VALUES (?1, ?2, ?3, ?4, 1, '', ?5, ?6)",
+        params![id, agent_id, cron_expr, description.unwrap_or_default().trim(), next, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    find(&conn, &id)
+}
+
+/// Turns a schedule on or off. One turned back on starts from now rather than making up
+/// the runs it missed.
+#[tauri::command]
+pub fn toggle_schedule(db: State<DbState>, id: String, enabled: bool) -> Result<Schedule, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let schedule = find(&conn, &id)?;
+    let next = if enabled { next_run(&schedule.cron_expr, Local::now())? } else { String::new() };
+    conn.execute(
+        "UPDATE schedules SET enabled = ?1, next_run = ?2, updated_at = ?3 WHERE id = ?4",
+        params![enabled as i32, next, Utc::now().to_rfc3339(), id],
+    ).map_err(|e| e.to_string())?;
+    find(&conn, &id)
+}
+
+#[tauri::command]
+pub fn delete_schedule(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM schedules WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    sync::record_deletion(&conn, "schedule", &id)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, MappedLocalTime, NaiveDateTime};
+
+    fn bits(values: &[u32]) -> u64 {
+        values.iter().fold(0, |bits, v| bits | 1 << v)
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// A zone an hour behind UTC whose clocks go forward at 02:00 on 29 March 2026, so
+    /// 02:00-02:59 that day never happens.
+    #[derive(Clone, Copy, Debug)]
+    struct SpringForward;
+
+    impl SpringForward {
+        fn change() -> NaiveDateTime {
+            date(2026, 3, 29).and_hms_opt(2, 0, 0).unwrap()
+        }
+
+        fn winter() -> FixedOffset {
+            FixedOffset::west_opt(3600).unwrap()
+        }
+
+        fn summer() -> FixedOffset {
+            FixedOffset::east_opt(0).unwrap()
+        }
+    }
+
+    impl TimeZone for SpringForward {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            SpringForward
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> MappedLocalTime<FixedOffset> {
+            if *local < Self::change() {
+                MappedLocalTime::Single(Self::winter())
+            } else if *local < Self::change() + Duration::hours(1) {
+                MappedLocalTime::None
+            } else {
+                MappedLocalTime::Single(Self::summer())
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            if *utc < Self::change() + Duration::hours(1) { Self::winter() } else { Self::summer() }
+        }
+    }
+
+    fn at<Tz: TimeZone>(zone: Tz, y: i32, m: u32, d: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        zone.from_local_datetime(&date(y, m, d).and_hms_opt(hour, minute, 0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reads_ranges_steps_and_lists() {
+        assert_eq!(field("*", 0, 59, &[]).unwrap(), (1u64 << 60) - 1);
+        assert_eq!(field("5", 0, 59, &[]).unwrap(), bits(&[5]));
+        assert_eq!(field("1-5", 0, 23, &[]).unwrap(), bits(&[1, 2, 3, 4, 5]));
+        assert_eq!(field("*/15", 0, 59, &[]).unwrap(), bits(&[0, 15, 30, 45]));
+        assert_eq!(field("10-40/10", 0, 59, &[]).unwrap(), bits(&[10, 20, 30, 40]));
+        assert_eq!(field("50/5", 0, 59, &[]).unwrap(), bits(&[50, 55]));
+        assert_eq!(field("1,3,20-22", 0, 23, &[]).unwrap(), bits(&[1, 3, 20, 21, 22]));
+        assert_eq!(field("*/10", 1, 31, &[]).unwrap(), bits(&[1, 11, 21, 31]));
+    }
+
+    #[test]
+    fn reads_month_and_weekday_names() {
+        assert_eq!(field("jan-mar", 1, 12, MONTHS).unwrap(), bits(&[1, 2, 3]));
+        assert_eq!(field("DEC", 1, 12, MONTHS).unwrap(), bits(&[12]));
+        assert_eq!(field("mon-fri", 0, 7, WEEKDAYS).unwrap(), bits(&[1, 2, 3, 4, 5]));
+        assert_eq!(field("sat,Sun", 0, 7, WEEKDAYS).unwrap(), bits(&[0, 6]));
+    }
+
+    #[test]
+    fn treats_weekday_seven_as_sunday() {
+        let cron = parse("0 9 * * 7").unwrap();
+        assert_eq!(cron.weekdays & 1, 1);
+        assert!(cron.day_matches(date(2026, 10, 18)));
+        assert!(!cron.day_matches(date(2026, 10, 17)));
+        assert!(parse("0 9 * * 5-7").unwrap().day_matches(date(2026, 10, 18)));
+    }
+
+    #[test]
+    fn expands_shorthands() {
+        for (short, long) in [("@hourly", "0 * * * *"), ("@daily", "0 0 * * *"), ("@midnight", "0 0 * * *"),
+            ("@weekly", "0 0 * * 0"), ("@monthly", "0 0 1 * *"), ("@yearly", "0 0 1 1 *"), ("@annually", "0 0 1 1 *")] {
+            let (short, long) = (parse(short).unwrap(), parse(long).unwrap());
+            assert_eq!((short.minutes, short.hours, short.days, short.months, short.weekdays),
+                (long.minutes, long.hours, long.days, long.months, long.weekdays));
+        }
+    }
+
+    #[test]
+    fn matches_either_day_only_when_both_are_given() {
+        // The 13th, or any Friday.
+        let either = parse("0 0 13 * 5").unwrap();
+        assert!(either.day_matches(date(2026, 10, 13)));
+        assert!(either.day_matches(date(2026, 10, 16)));
+        assert!(!either.day_matches(date(2026, 10, 14)));
+
+        let fridays = parse("0 0 * * fri").unwrap();
+        assert!(fridays.day_matches(date(2026, 10, 16)));
+        assert!(!fridays.day_matches(date(2026, 10, 13)));
+
+        let thirteenths = parse("0 0 13 * *").unwrap();
+        assert!(thirteenths.day_matches(date(2026, 10, 13)));
+        assert!(!thirteenths.day_matches(date(2026, 10, 16)));
+
+        let june = parse("0 0 13 jun 5").unwrap();
+        assert!(!june.day_matches(date(2026, 10, 16)));
+        assert!(june.day_matches(date(2026, 6, 5)));
+    }
+
+    #[test]
+    fn explains_wrong_schedules() {
+        let wrong = |expr: &str| parse(expr).err().unwrap();
+        assert!(wrong("0 9 * *").starts_with("A schedule has five parts"));
+        assert!(wrong("0 9 * * * *").starts_with("A schedule has five parts"));
+        assert_eq!(wrong("60 9 * * *"), "The minute part is wrong: 60 is outside 0-59");
+        assert_eq!(wrong("0 24 * * *"), "The hour part is wrong: 24 is outside 0-23");
+        assert_eq!(wrong("0 9 0 * *"), "The day of month part is wrong: 0 is outside 1-31");
+        assert_eq!(wrong("0 9 * 13 *"), "The month part is wrong: 13 is outside 1-12");
+        assert_eq!(wrong("0 9 * * 8"), "The day of week part is wrong: 8 is outside 0-7");
+        assert_eq!(wrong("0 17-9 * * *"), "The hour part is wrong: 17-9 runs backwards");
+        assert_eq!(wrong("*/0 9 * * *"), "The minute part is wrong: \"0\" isn't a step");
+        assert_eq!(wrong("*/x 9 * * *"), "The minute part is wrong: \"x\" isn't a step");
+        assert_eq!(wrong("0 9 * smarch *"), "The month part is wrong: \"smarch\" isn't a number");
+        assert_eq!(wrong("0 9 * * mon-"), "The day of week part is wrong: \"\" isn't a number");
+    }
+
+    #[test]
+    fn finds_the_next_matching_minute() {
+        let zone = FixedOffset::east_opt(2 * 3600).unwrap();
+        let next = |expr: &str, after| parse(expr).unwrap().next_after(after).unwrap();
+        // Thursday 15 October 2026, 10:07.
+        let now = at(zone, 2026, 10, 15, 10, 7);
+        assert_eq!(next("*/15 * * * *", now), at(zone, 2026, 10, 15, 10, 15));
+        assert_eq!(next("0 9 * * *", now), at(zone, 2026, 10, 16, 9, 0));
+        assert_eq!(next("0 8 * * mon-fri", at(zone, 2026, 10, 16, 9, 0)), at(zone, 2026, 10, 19, 8, 0));
+        assert_eq!(next("7 10 * * *", now), at(zone, 2026, 10, 16, 10, 7));
+        assert_eq!(next("0 0 29 feb *", now), at(zone, 2028, 2, 29, 0, 0));
+        assert!(parse("0 0 31 feb *").unwrap().next_after(now).is_none());
+    }
+
+    #[test]
+    fn skips_times_lost_to_a_clock_change() {
+        let next = |expr: &str, after| parse(expr).unwrap().next_after(after).unwrap();
+        let night_before = at(SpringForward, 2026, 3, 28, 23, 0);
+        // 02:30 doesn't happen on the 29th, so the first run is the day after.
+        assert_eq!(next("30 2 * * *", night_before), at(SpringForward, 2026, 3, 30, 2, 30));
+        // An hourly schedule goes from 01:00 straight to 03:00.
+        assert_eq!(next("0 * * * *", at(SpringForward, 2026, 3, 29, 1, 0)), at(SpringForward, 2026, 3, 29, 3, 0));
+        assert_eq!(next("30 3 * * *", night_before), at(SpringForward, 2026, 3, 29, 3, 30));
+    }
+}
@@ -0,0 +1,243 @@
+//! Secure notes: passwords and other secrets the user keeps in the app, encrypted with a key
+//! of their own in the system keychain. Agents can generate passwords into notes, add notes
+//! and see their titles, but a note's secret is only ever shown to the user, in the main
+//! window, after the app lock PIN.
+
+use chrono::Utc;
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{audit, crypto, net, security};
+use crate::DbState;
+
+const KEYCHAIN_NOTES_ENTRY: &str = "secure-notes-key";
+const DEFAULT_PASSWORD_LENGTH: usize = 20;
+const PASSWORD_LENGTHS: std::ops::RangeInclusive<usize> = 8..=128;
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+/// Symbols nearly every site accepts; quotes, backslashes and spaces are left out.
+const SYMBOLS: &[u8] = b"!#$%&*+-=?@^_~";
+
+/// A note without its secret, which only [`reveal`] decrypts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecureNote {
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS secure_notes (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            username TEXT NOT NULL DEFAULT '',
+            url TEXT NOT NULL DEFAULT '',
+            secret BLOB NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize secure note tables");
+}
+
+const COLUMNS: &str = "id, title, username, url, created_at, updated_at";
+
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<SecureNote> {
+    Ok(SecureNote {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        username: row.get(2)?,
+        url: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+/// The notes key, made on first use. It lives in the system keychain, apart from the
+/// database, so a copied or synced database doesn't carry readable secrets with it.
+fn key() -> Result<[u8; 32], String> {
+    let entry = keyring::Entry::new(net::KEYCHAIN_SERVICE, KEYCHAIN_NOTES_ENTRY)
+        .map_err(|e| format!("The system keychain is unavailable: {e}"))?;
+    match entry.get_password() {
+        Ok(hex) => {
+            let bytes: Option<Vec<u8>> = (0..hex.len()).step_by(2)
+                .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                .collect();
+            bytes.and_then(|b| b.try_into().ok()).ok_or_else(|| "The secure notes key in the system keychain is damaged".into())
+        }
+        Err(keyring::Error::NoEntry) => {
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            let hex: String = key.iter().map(|b| format!("{b:02x}")).collect();
+            entry.set_password(&hex).map_err(|e| format!("Couldn't save the secure notes key to the system keychain: {e}"))?;
+            Ok(key)
+        }
+        Err(e) => Err(format!("Couldn't read the secure notes key from the system keychain: {e}")),
+    }
+}
+
+/// Removes the notes key from the system keychain, for a factory reset; the notes it
+/// sealed go with the rest of the database.
+pub(crate) fn forget_key() -> Result<(), String> {
+    let entry = keyring::Entry::new(net::KEYCHAIN_SERVICE, KEYCHAIN_NOTES_ENTRY)
+        .map_err(|e| format!("The system keychain is unavailable: {e}"))?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Couldn't remove the secure notes key from the system keychain: {e}")),
+    }
+}
+
+/// A random password of `length` characters from the operating system's secure random
+/// source, with at least one lowercase letter, capital and digit, and a symbol unless
+/// `symbols` is off.
+pub fn new_password(length: Option<usize>, symbols: bool) -> Result<String, String> {
+    let length = length.unwrap_or(DEFAULT_PASSWORD_LENGTH);
+    if !PASSWORD_LENGTHS.contains(&length) {
+        return Err(format!("Passwords can be {} to {} characters long", PASSWORD_LENGTHS.start(), PASSWORD_LENGTHS.end()));
+    }
+    let mut classes = vec![LOWERCASE, UPPERCASE, DIGITS];
+    if symbols {
+        classes.push(SYMBOLS);
+    }
+    let alphabet = classes.concat();
+    // Drawing again until every class turns up keeps each character uniformly random.
+    loop {
+        let password: Vec<u8> = (0..length).map(|_| *alphabet.choose(&mut OsRng).expect("alphabet is not empty")).collect();
+        if classes.iter().all(|class| password.iter().any(|c| class.contains(c))) {
+            return Ok(String::from_utf8(password).expect("alphabet is ASCII"));
+        }
+    }
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<SecureNote>, String> {
+    let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM secure_notes ORDER BY title COLLATE NOCASE"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_note).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Titles, usernames and sites of the notes, never their secrets.
+pub fn overview(notes: &[SecureNote]) -> String {
+    if notes.is_empty() {
+        return "There are no secure notes yet.".into();
+    }
+    notes.iter()
+        .map(|note| {
+            let mut line = format!("- {}", note.title);
+            if !note.username.is_empty() {
+                line.push_str(&format!(" (username: {})", note.username));
+            }
+            if !note.url.is_empty() {
+                line.push_str(&format!(" for {}", note.url));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds a note, or replaces the one with the same id. `secret` may be left out when
+/// replacing, to keep the one already saved. Titles are unique, since agents refer to notes
+/// by title.
+pub fn save(conn: &Connection, note: SecureNote, secret: Option<&str>) -> Result<SecureNote, String> {
+    let mut note = SecureNote {
+        title: note.title.trim().to_string(),
+        username: note.username.trim().to_string(),
+        url: note.url.trim().to_string(),
+        ..note
+    };
+    if note.title.is_empty() {
+        return Err("Give the secure note a title".into());
+    }
+    let taken: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM secure_notes WHERE title = ?1 COLLATE NOCASE AND id != ?2",
+        params![note.title, note.id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if taken {
+        return Err(format!("There is already a secure note called {}", note.title));
+    }
+    let sealed = match secret {
+        Some(secret) if !secret.is_empty() => crypto::encrypt(&key()?, secret.as_bytes())?,
+        Some(_) => return Err("There is nothing to keep in the secure note".into()),
+        None => conn.query_row("SELECT secret FROM secure_notes WHERE id = ?1", params![note.id], |row| row.get(0))
+            .optional().map_err(|e| e.to_string())?
+            .ok_or("There is nothing to keep in the secure note")?,
+    };
+    let now = Utc::now().to_rfc3339();
+    if note.id.is_empty() {
+        note.id = Uuid::new_v4().to_string();
+    }
+    if note.created_at.is_empty() {
+        note.created_at = now.clone();
+    }
+    note.updated_at = now;
+    conn.execute(
+        "INSERT OR REPLACE INTO secure_notes (id, title, username, url, secret, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![note.id, note.title, note.username, note.url, sealed, note.created_at, note.updated_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(note)
+}
+
+/// The note's secret in plain text, once `pin` has been checked against the app lock PIN.
+pub fn reveal(conn: &Connection, id: &str, pin: &str) -> Result<String, String> {
+    security::check_pin(conn, pin)?;
+    let (title, sealed): (String, Vec<u8>) = conn.query_row(
+        "SELECT title, secret FROM secure_notes WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or("That secure note no longer exists")?;
+    let secret = crypto::decrypt(&key()?, &sealed)
+        .map_err(|_| "This note was saved with a key that is no longer in the system keychain".to_string())?;
+    audit::record(conn, "secure_note_revealed", "", "", &format!("Showed the secure note {title}"), &json!({ "note_id": id }))?;
+    String::from_utf8(secret).map_err(|e| e.to_string())
+}
+
+// ─── Secure Note Commands ───
+
+#[tauri::command]
+pub fn list_secure_notes(db: State<DbState>) -> Result<Vec<SecureNote>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list(&conn)
+}
+
+/// Leave `secret` out to change only the title, username or site of an existing note.
+#[tauri::command]
+pub fn save_secure_note(db: State<DbState>, note: SecureNote, secret: Option<String>) -> Result<SecureNote, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save(&conn, note, secret.as_deref())
+}
+
+#[tauri::command]
+pub fn reveal_secure_note(db: State<DbState>, id: String, pin: String) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    reveal(&conn, &id, &pin)
+}
+
+#[tauri::command]
+pub fn delete_secure_note(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM secure_notes WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn generate_password(length: Option<usize>, symbols: Option<bool>) -> Result<String, String> {
+    new_password(length, symbols.unwrap_or(true))
+}
@@ -0,0 +1,324 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::ipc::{Invoke, InvokeMessage};
+use tauri::{Manager, Runtime, State};
+
+use crate::crypto;
+use crate::net;
+use crate::secure_notes;
+use crate::supervision;
+use crate::totp;
+use crate::{DbState, app_data_dir, read_setting, write_setting};
+
+/// Label Tauri gives the window declared in `tauri.conf.json`.
+const MAIN_WINDOW: &str = "main";
+const PIN_SETTING: &str = "app_lock_pin_hash";
+const FAILED_SETTING: &str = "app_lock_failed_attempts";
+const LOCKED_UNTIL_SETTING: &str = "app_lock_locked_until";
+/// Wrong PINs allowed in a row before the PIN stops being checked for a while.
+const FREE_ATTEMPTS: u32 = 5;
+/// The first wait; each wrong PIN after it doubles the wait, up to `MAX_LOCKOUT_SECONDS`.
+const FIRST_LOCKOUT_SECONDS: i64 = 30;
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+
+/// Who may call a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Callable while the app is locked.
+    Open,
+    /// The default: any app window, only while unlocked.
+    Unlocked,
+    /// Touches secrets or can wipe or exfiltrate data: the main window only, while unlocked.
+    MainWindow,
+}
+
+/// Commands that need something other than `Access::Unlocked`.
+const CAPABILITIES: &[(&str, Access)] = &[
+    ("get_lock_status", Access::Open),
+    ("unlock_app", Access::Open),
+    ("lock_app", Access::Open),
+    ("get_setting", Access::MainWindow),
+    ("set_setting", Access::MainWindow),
+    ("delete_setting", Access::MainWindow),
+    ("set_app_lock_pin", Access::MainWindow),
+    ("factory_reset", Access::MainWindow),
+    ("get_sync_config", Access::MainWindow),
+    ("set_sync_config", Access::MainWindow),
+    ("sync_now", Access::MainWindow),
+    ("share_agent", Access::MainWindow),
+    ("export_agents", Access::MainWindow),
+    ("import_agents", Access::MainWindow),
+    ("import_shared_agent", Access::MainWindow),
+    ("export_everything", Access::MainWindow),
+    ("import_everything", Access::MainWindow),
+    ("inspect_archive", Access::MainWindow),
+    ("set_data_directory", Access::MainWindow),
+    ("export_anonymized_diagnostics", Access::MainWindow),
+    ("upload_crash_report", Access::MainWindow),
+    ("set_crash_upload_settings", Access::MainWindow),
+    ("mint_api_token", Access::MainWindow),
+    ("list_api_tokens", Access::MainWindow),
+    ("revoke_api_token", Access::MainWindow),
+    ("start_companion_pairing", Access::MainWindow),
+    ("revoke_companion_device", Access::MainWindow),
+    ("set_discovery_settings", Access::MainWindow),
+    ("list_audit_events", Access::MainWindow),
+    ("export_audit_log", Access::MainWindow),
+    ("set_local_only_mode", Access::MainWindow),
+    ("get_proxy_settings", Access::MainWindow),
+    ("set_proxy_settings", Access::MainWindow),
+    ("set_activity_settings", Access::MainWindow),
+    ("start_recording", Access::MainWindow),
+    ("set_supervisor_pin", Access::MainWindow),
+    ("set_supervisor_policy", Access::MainWindow),
+    ("suspend_supervision", Access::MainWindow),
+    ("save_approval_route", Access::MainWindow),
+    ("list_secure_notes", Access::MainWindow),
+    ("save_secure_note", Access::MainWindow),
+    ("reveal_secure_note", Access::MainWindow),
+    ("delete_secure_note", Access::MainWindow),
+    ("generate_password", Access::MainWindow),
+    ("list_totp_accounts", Access::MainWindow),
+    ("add_totp_account", Access::MainWindow),
+    ("delete_totp_account", Access::MainWindow),
+    ("get_totp_code", Access::MainWindow),
+    ("disconnect_connection", Access::MainWindow),
+    ("save_data_source", Access::MainWindow),
+    ("query_data_source", Access::MainWindow),
+];
+
+/// Whether the UI is locked behind the PIN. Starts locked when a PIN is set.
+pub struct AppLock(pub AtomicBool);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LockStatus {
+    pub locked: bool,
+    pub has_pin: bool,
+}
+
+fn access(command: &str) -> Access {
+    CAPABILITIES.iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, access)| *access)
+        .unwrap_or(Access::Unlocked)
+}
+
+/// Only the app's own bundled pages (or the dev server) may call commands at all.
+fn trusted_origin(url: &tauri::Url) -> bool {
+    match url.scheme() {
+        "tauri" => true,
+        "http" | "https" => matches!(url.host_str(), Some("tauri.localhost" | "localhost" | "127.0.0.1")),
+        _ => false,
+    }
+}
+
+/// Runs before every app command; a rejection never reaches the command itself.
+pub fn check<R: Runtime>(message: &InvokeMessage<R>) -> Result<(), String> {
+    let webview = message.webview_ref();
+    let origin_ok = webview.url().map(|url| trusted_origin(&url)).unwrap_or(false);
+    if !origin_ok {
+        return Err("Commands are only accepted from the app's own pages".into());
+    }
+    let access = access(message.command());
+    if access == Access::Open {
+        return Ok(());
+    }
+    if webview.state::<AppLock>().0.load(Ordering::SeqCst) {
+        return Err("OpenClaw is locked".into());
+    }
+    if access == Access::MainWindow && webview.label() != MAIN_WINDOW {
+        return Err(format!("{} is only available from the main window", message.command()));
+    }
+    Ok(())
+}
+
+/// Wraps the generated command handler so `check` runs before every command.
+pub fn guard<R, F>(handler: F) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static
+where
+    R: Runtime,
+    F: Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        if let Err(e) = check(&invoke.message) {
+            invoke.resolver.reject(e);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+/// Settings only the lock and supervisor commands may change; `set_setting` would otherwise
+/// bypass the PIN.
+pub(crate) fn is_protected_setting(key: &str) -> bool {
+    key.starts_with("app_lock_") || key.starts_with("supervisor_")
+}
+
+/// Lock state for a freshly started app.
+pub fn initial_lock(conn: &Connection) -> AppLock {
+    AppLock(AtomicBool::new(has_pin(conn)))
+}
+
+pub(crate) fn has_pin(conn: &Connection) -> bool {
+    read_setting(conn, PIN_SETTING).ok().flatten().is_some_and(|h| !h.is_empty())
+}
+
+/// How long the PIN is locked after `failures` wrong ones in a row, if at all.
+fn lockout_after(failures: u32) -> Option<Duration> {
+    let over = failures.checked_sub(FREE_ATTEMPTS)?;
+    Some(Duration::seconds(FIRST_LOCKOUT_SECONDS.saturating_mul(1 << over.min(20)).min(MAX_LOCKOUT_SECONDS)))
+}
+
+/// `wait` as the user reads it, e.g. "30 seconds" or "4 minutes".
+fn wait_text(wait: Duration) -> String {
+    match wait.num_seconds().max(1) {
+        1 => "1 second".into(),
+        seconds if seconds < 90 => format!("{seconds} seconds"),
+        seconds => format!("{} minutes", (seconds + 59) / 60),
+    }
+}
+
+/// Fails with "Wrong PIN" unless `pin` is the app lock PIN; passes when no PIN is set.
+/// Wrong PINs are counted, and after `FREE_ATTEMPTS` in a row each one locks the PIN for
+/// longer, so a short numeric PIN can't simply be tried until it opens.
+pub(crate) fn check_pin(conn: &Connection, pin: &str) -> Result<(), String> {
+    let stored = read_setting(conn, PIN_SETTING)?.unwrap_or_default();
+    if stored.is_empty() {
+        return Ok(());
+    }
+    let now = Utc::now();
+    let locked_until = read_setting(conn, LOCKED_UNTIL_SETTING)?
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .filter(|t| *t > now);
+    if let Some(until) = locked_until {
+        return Err(format!("Too many wrong PINs; try again in {}", wait_text(until - now)));
+    }
+    let failures: u32 = read_setting(conn, FAILED_SETTING)?.and_then(|n| n.parse().ok()).unwrap_or(0);
+    if crypto::verify_password(pin, &stored) {
+        if failures > 0 {
+            write_setting(conn, FAILED_SETTING, "0")?;
+        }
+        return Ok(());
+    }
+    let failures = failures.saturating_add(1);
+    write_setting(conn, FAILED_SETTING, &failures.to_string())?;
+    match lockout_after(failures) {
+        Some(wait) => {
+            write_setting(conn, LOCKED_UNTIL_SETTING, &(now + wait).to_rfc3339())?;
+            Err(format!("Wrong PIN; try again in {}", wait_text(wait)))
+        }
+        None => Err("Wrong PIN".into()),
+    }
+}
+
+// ─── Lock Commands ───
+
+#[tauri::command]
+pub fn get_lock_status(db: State<DbState>, lock: State<AppLock>) -> Result<LockStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(LockStatus {
+        locked: lock.0.load(Ordering::SeqCst),
+        has_pin: read_setting(&conn, PIN_SETTING)?.is_some_and(|h| !h.is_empty()),
+    })
+}
+
+#[tauri::command]
+pub fn lock_app(db: State<DbState>, lock: State<AppLock>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if read_setting(&conn, PIN_SETTING)?.is_none_or(|h| h.is_empty()) {
+        return Err("Set a PIN before locking the app".into());
+    }
+    lock.0.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unlock_app(db: State<DbState>, lock: State<AppLock>, pin: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    check_pin(&conn, &pin)?;
+    lock.0.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Sets, changes or (with an empty `new_pin`) removes the PIN. The current PIN is required
+/// whenever one is set.
+#[tauri::command]
+pub fn set_app_lock_pin(db: State<DbState>, current_pin: String, new_pin: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    check_pin(&conn, &current_pin)?;
+    if new_pin.is_empty() {
+        return write_setting(&conn, PIN_SETTING, "");
+    }
+    if new_pin.chars().count() < 4 {
+        return Err("Use a PIN of at least 4 characters".into());
+    }
+    write_setting(&conn, PIN_SETTING, &crypto::hash_password(&new_pin))
+}
+
+/// Erases every agent, run, log and setting, plus the fixture folders. `confirm` must be `"RESET"`.
+#[tauri::command]
+pub fn factory_reset(db: State<DbState>, lock: State<AppLock>, confirm: String) -> Result<(), String> {
+    if confirm != "RESET" {
+        return Err("Type RESET to confirm".into());
+    }
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    if supervision::enabled(&conn) {
+        return Err("Turn supervised mode off before resetting".into());
+    }
+    let tables: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT name, COALESCE(sql, '') FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    // Full-text indexes keep their data in shadow tables that must only be emptied through
+    // the index itself.
+    let virtual_tables: Vec<&str> = tables.iter()
+        .filter(|(_, sql)| sql.starts_with("CREATE VIRTUAL TABLE"))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let tables: Vec<&str> = tables.iter()
+        .map(|(name, _)| name.as_str())
+        .filter(|name| !virtual_tables.iter().any(|v| name.strip_prefix(v).is_some_and(|rest| rest.starts_with('_'))))
+        .collect();
+    let totp_accounts = totp::account_ids(&conn)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for table in &tables {
+        tx.execute(&format!("DELETE FROM \"{table}\""), []).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    // Secrets in the system keychain go only once their rows have, so a reset that fails
+    // leaves both. Each is tried even when another can't be removed.
+    let forgotten = [totp::forget_secrets(&totp_accounts), secure_notes::forget_key(), net::forget_proxy_password()];
+    let fixtures = app_data_dir().join("fixtures");
+    if fixtures.exists() {
+        std::fs::remove_dir_all(fixtures).map_err(|e| e.to_string())?;
+    }
+    lock.0.store(false, Ordering::SeqCst);
+    forgotten.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_the_pin_for_longer_after_each_wrong_one() {
+        assert!((0..FREE_ATTEMPTS).all(|failures| lockout_after(failures).is_none()));
+        let waits: Vec<i64> = (FREE_ATTEMPTS..FREE_ATTEMPTS + 9)
+            .map(|failures| lockout_after(failures).unwrap().num_seconds())
+            .collect();
+        assert_eq!(waits, [30, 60, 120, 240, 480, 960, 1920, 3600, 3600]);
+        assert_eq!(lockout_after(u32::MAX).unwrap().num_seconds(), MAX_LOCKOUT_SECONDS);
+    }
+
+    #[test]
+    fn says_how_long_to_wait() {
+        assert_eq!(wait_text(Duration::seconds(30)), "30 seconds");
+        assert_eq!(wait_text(Duration::milliseconds(200)), "1 second");
+        assert_eq!(wait_text(Duration::seconds(90)), "2 minutes");
+        assert_eq!(wait_text(Duration::seconds(3600)), "60 minutes");
+    }
+}
@@ -0,0 +1,99 @@
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::audit;
+use crate::net;
+use crate::{DbState, update_agent_config};
+
+/// How sensitive the data an agent handles is, and so where it may be sent.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Sensitivity {
+    #[default]
+    Public,
+    /// A label for the user's own overview; handled like public data.
+    Internal,
+    /// Never leaves the machine: only the local model, only services on this computer.
+    Confidential,
+}
+
+impl Sensitivity {
+    fn parse(s: &str) -> Option<Sensitivity> {
+        match s {
+            "public" => Some(Sensitivity::Public),
+            "internal" => Some(Sensitivity::Internal),
+            "confidential" => Some(Sensitivity::Confidential),
+            _ => None,
+        }
+    }
+}
+
+fn from_config(config: &str) -> Sensitivity {
+    serde_json::from_str::<Value>(config).ok()
+        .and_then(|c| c.get("sensitivity").and_then(Value::as_str).and_then(Sensitivity::parse))
+        .unwrap_or_default()
+}
+
+/// The agent's label; agents without one, and runs outside an agent, count as public.
+pub fn of(conn: &Connection, agent_id: &str) -> Sensitivity {
+    let config: String = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).unwrap_or_default();
+    from_config(&config)
+}
+
+pub fn is_confidential(conn: &Connection, agent_id: &str) -> bool {
+    of(conn, agent_id) == Sensitivity::Confidential
+}
+
+/// Refuses to send a confidential agent's data anywhere but this machine and records the
+/// attempt in the audit trail. `url` is `None` for tools that always leave the machine.
+pub fn check_egress(conn: &Connection, what: &str, url: Option<&str>, agent_id: &str, run_id: &str) -> Result<(), String> {
+    if !is_confidential(conn, agent_id) || url.is_some_and(net::is_loopback) {
+        return Ok(());
+    }
+    let summary = format!("Blocked {what}: the agent handles confidential data, which stays on this computer");
+    audit::record(conn, "residency_blocked", agent_id, run_id, &summary, &json!({ "what": what, "url": url }))?;
+    Err(summary)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LabeledAgent {
+    pub id: String,
+    pub name: String,
+    pub sensitivity: Sensitivity,
+}
+
+// ─── Sensitivity Commands ───
+
+/// Labels an agent. Confidential agents are routed to the local model and can't reach
+/// anything outside this computer.
+#[tauri::command]
+pub fn set_agent_sensitivity(db: State<DbState>, agent_id: String, sensitivity: Sensitivity) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| match sensitivity {
+        Sensitivity::Public => {
+            config.remove("sensitivity");
+        }
+        level => {
+            config.insert("sensitivity".into(), json!(level));
+        }
+    })
+}
+
+/// Agents with their labels, optionally only those with `sensitivity`.
+#[tauri::command]
+pub fn list_agent_sensitivity(db: State<DbState>, sensitivity: Option<Sensitivity>) -> Result<Vec<LabeledAgent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT id, name, config_json FROM agents ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| {
+        Ok(LabeledAgent { id: row.get(0)?, name: row.get(1)?, sensitivity: from_config(&row.get::<_, String>(2)?) })
+    }).map_err(|e| e.to_string())?;
+    let agents: Vec<LabeledAgent> = rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+    Ok(agents.into_iter().filter(|a| sensitivity.is_none_or(|s| a.sensitivity == s)).collect())
+}
@@ -0,0 +1,213 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tauri::State;
+use chrono::Utc;
+
+use crate::builder::{self, AgentDraft};
+use crate::crypto::{self, Sealed};
+use crate::workflows::{self, WorkflowStep};
+use crate::{DbState, app_data_dir, read_setting, write_setting};
+
+const BUNDLE_FORMAT: &str = "openclaw-agent-bundle";
+const BUNDLE_EXTENSION: &str = "openclaw-agent";
+/// Setting holding this install's signing key, created the first time an agent is shared.
+const SIGNING_KEY_SETTING: &str = "share_signing_key";
+/// Param names whose values are never written into a bundle.
+const SECRET_KEYS: &[&str] = &["api_key", "apikey", "token", "password", "secret", "authorization", "auth"];
+
+/// The file written by `share_agent`. Only `salt`, `nonce` and `ciphertext` are covered by
+/// the signature; everything readable without the passphrase lives inside the ciphertext.
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    version: u32,
+    kdf_iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+    signer: String,
+    signature: String,
+}
+
+/// What is encrypted inside a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedAgent {
+    name: String,
+    role: String,
+    goal: String,
+    schedule: String,
+    tools: Vec<String>,
+    sandbox: bool,
+    /// Fully expanded, so the importer doesn't need the sharer's fragments.
+    workflow: Vec<WorkflowStep>,
+    shared_at: String,
+}
+
+/// Shown to the user before an imported agent is saved with `save_agent_draft`.
+#[derive(Debug, Serialize, Clone)]
+pub struct SharedAgentReview {
+    pub draft: AgentDraft,
+    /// Short fingerprint of the key that signed the bundle.
+    pub signer: String,
+    /// The bundle was shared from this install.
+    pub signed_by_you: bool,
+    pub shared_at: String,
+}
+
+pub(crate) fn fingerprint(key: &VerifyingKey) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    digest[..8].chunks(2).map(|c| format!("{:02x}{:02x}", c[0], c[1])).collect::<Vec<_>>().join(":")
+}
+
+pub(crate) fn stored_signing_key(conn: &Connection) -> Result<Option<SigningKey>, String> {
+    let Some(stored) = read_setting(conn, SIGNING_KEY_SETTING)? else {
+        return Ok(None);
+    };
+    let bytes: [u8; 32] = B64.decode(stored).ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Stored signing key is damaged")?;
+    Ok(Some(SigningKey::from_bytes(&bytes)))
+}
+
+pub(crate) fn signing_key(conn: &Connection) -> Result<SigningKey, String> {
+    if let Some(key) = stored_signing_key(conn)? {
+        return Ok(key);
+    }
+    let key = SigningKey::generate(&mut OsRng);
+    write_setting(conn, SIGNING_KEY_SETTING, &B64.encode(key.to_bytes()))?;
+    Ok(key)
+}
+
+/// Agents created from the UI store tools as a JSON array, the builder as a comma list.
+fn parse_tools(raw: &str) -> Vec<String> {
+    if let Ok(list) = serde_json::from_str::<Vec<String>>(raw) {
+        return list;
+    }
+    raw.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect()
+}
+
+/// Blanks values stored under secret-looking keys and returns how many were removed.
+fn scrub_secrets(value: &mut Value) -> usize {
+    match value {
+        Value::Object(map) => map.iter_mut()
+            .map(|(key, v)| {
+                let key = key.to_lowercase();
+                if v.is_string() && SECRET_KEYS.iter().any(|s| key.contains(s)) {
+                    *v = Value::String(String::new());
+                    1
+                } else {
+                    scrub_secrets(v)
+                }
+            })
+            .sum(),
+        Value::Array(items) => items.iter_mut().map(scrub_secrets).sum(),
+        _ => 0,
+    }
+}
+
+fn file_name(agent_name: &str) -> String {
+    let stem: String = agent_name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let stem = stem.trim_matches('-');
+    format!("{}.{BUNDLE_EXTENSION}", if stem.is_empty() { "agent" } else { stem })
+}
+
+// ─── Sharing Commands ───
+
+/// Writes an encrypted, signed copy of the agent to the Downloads folder and returns its path.
+/// Settings, API keys and secret-looking workflow params are never included.
+#[tauri::command]
+pub fn share_agent(db: State<DbState>, id: String, passphrase: String) -> Result<String, String> {
+    if passphrase.chars().count() < 8 {
+        return Err("Use a passphrase of at least 8 characters".into());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (name, role, goal, tools, schedule, sandbox): (String, String, String, String, String, i32) = conn.query_row(
+        "SELECT name, role, goal, tools, schedule, sandbox FROM agents WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+    ).optional().map_err(|e| e.to_string())?.ok_or("Agent not found")?;
+
+    let mut workflow = workflows::expand_workflow(&conn, &workflows::agent_workflow(&conn, &id)?)?;
+    for step in &mut workflow {
+        if let WorkflowStep::Tool { params, .. } = step {
+            scrub_secrets(params);
+        }
+    }
+    let agent = SharedAgent {
+        name,
+        role,
+        goal,
+        schedule,
+        tools: parse_tools(&tools),
+        sandbox: sandbox != 0,
+        workflow,
+        shared_at: Utc::now().to_rfc3339(),
+    };
+
+    let sealed = crypto::seal(&passphrase, &serde_json::to_vec(&agent).map_err(|e| e.to_string())?)?;
+    let signer = signing_key(&conn)?;
+    let signature = signer.sign(&[sealed.salt.as_slice(), &sealed.nonce, &sealed.ciphertext].concat());
+    let bundle = Bundle {
+        format: BUNDLE_FORMAT.into(),
+        version: 1,
+        kdf_iterations: sealed.kdf_iterations,
+        salt: B64.encode(&sealed.salt),
+        nonce: B64.encode(&sealed.nonce),
+        ciphertext: B64.encode(&sealed.ciphertext),
+        signer: B64.encode(signer.verifying_key().as_bytes()),
+        signature: B64.encode(signature.to_bytes()),
+    };
+
+    let dir = dirs_next::download_dir().unwrap_or_else(|| app_data_dir().join("shared"));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path: PathBuf = dir.join(file_name(&agent.name));
+    std::fs::write(&path, serde_json::to_vec_pretty(&bundle).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("Couldn't save the bundle: {e}"))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Checks the signature, decrypts the bundle and returns a draft for the user to review.
+/// Nothing is saved until the draft is passed to `save_agent_draft`.
+#[tauri::command]
+pub fn import_shared_agent(db: State<DbState>, path: String, passphrase: String) -> Result<SharedAgentReview, String> {
+    let raw = std::fs::read(&path).map_err(|e| format!("Couldn't read {path}: {e}"))?;
+    let bundle: Bundle = serde_json::from_slice(&raw).map_err(|_| "This isn't an agent bundle")?;
+    if bundle.format != BUNDLE_FORMAT || bundle.version != 1 {
+        return Err("This agent bundle was made by a newer version of the app".into());
+    }
+    let decode = |field: &str| B64.decode(field).map_err(|_| "The agent bundle is damaged".to_string());
+    let (salt, nonce, ciphertext) = (decode(&bundle.salt)?, decode(&bundle.nonce)?, decode(&bundle.ciphertext)?);
+    let signer_bytes: [u8; 32] = decode(&bundle.signer)?.try_into().map_err(|_| "The agent bundle is damaged")?;
+    let signature_bytes: [u8; 64] = decode(&bundle.signature)?.try_into().map_err(|_| "The agent bundle is damaged")?;
+    let signer = VerifyingKey::from_bytes(&signer_bytes).map_err(|_| "The agent bundle is damaged")?;
+    signer.verify(&[salt.as_slice(), &nonce, &ciphertext].concat(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "The agent bundle has been modified since it was shared")?;
+
+    let plaintext = crypto::open(&passphrase, &Sealed { kdf_iterations: bundle.kdf_iterations, salt, nonce, ciphertext })?;
+    let agent: SharedAgent = serde_json::from_slice(&plaintext).map_err(|_| "The agent bundle is damaged")?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let signed_by_you = stored_signing_key(&conn)?.is_some_and(|key| key.verifying_key() == signer);
+    let draft = builder::normalize_draft(AgentDraft {
+        name: agent.name,
+        role: agent.role,
+        goal: agent.goal,
+        schedule: agent.schedule,
+        tools: agent.tools,
+        workflow: agent.workflow,
+        permissions: Vec::new(),
+        sandbox: agent.sandbox,
+        notes: Vec::new(),
+        source: "shared".into(),
+    });
+    Ok(SharedAgentReview { draft, signer: fingerprint(&signer), signed_by_you, shared_at: agent.shared_at })
+}
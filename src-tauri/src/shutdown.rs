@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use rusqlite::{Connection, params};
+use tauri::{AppHandle, Manager};
+
+use crate::DbState;
+
+/// How long closing waits for runs to reach the end of their current step.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether the app is closing, and how many runs are still executing steps.
+#[derive(Default)]
+pub struct Shutdown {
+    stopping: AtomicBool,
+    in_flight: AtomicUsize,
+}
+
+/// Held while a run executes; closing waits until none are left.
+pub struct RunGuard<'a>(&'a Shutdown);
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn stopping(app: &AppHandle) -> bool {
+    app.state::<Shutdown>().stopping.load(Ordering::SeqCst)
+}
+
+/// Refuses to start or resume a run once the app is closing.
+pub fn begin_run(app: &AppHandle) -> Result<RunGuard<'_>, String> {
+    let shutdown = app.state::<Shutdown>().inner();
+    shutdown.in_flight.fetch_add(1, Ordering::SeqCst);
+    let guard = RunGuard(shutdown);
+    if shutdown.stopping.load(Ordering::SeqCst) {
+        return Err("OpenClaw is closing".into());
+    }
+    Ok(guard)
+}
+
+/// Runs left `running` by a session that was killed stopped partway through a step, so they
+/// can't safely pick up again.
+pub fn recover(conn: &Connection) {
+    let _ = conn.execute(
+        "UPDATE runs SET status = 'error', error = 'OpenClaw closed before this run finished', finished_at = ?1 WHERE status = 'running'",
+        params![Utc::now().to_rfc3339()],
+    );
+}
+
+/// Called as the app exits: stops new runs and the watchers from starting work, lets
+/// running ones stop at the end of their current step (live runs are kept as `interrupted`
+/// to resume later), then checkpoints the database so nothing is left in the write-ahead log.
+pub fn run(app: &AppHandle) {
+    let shutdown = app.state::<Shutdown>();
+    shutdown.stopping.store(true, Ordering::SeqCst);
+    let started = Instant::now();
+    while shutdown.in_flight.load(Ordering::SeqCst) > 0 && started.elapsed() < DRAIN_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let db = app.state::<DbState>();
+    let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+    let _ = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()));
+    let _ = conn.execute_batch("PRAGMA optimize;");
+}
@@ -0,0 +1,197 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::executor::{Run, RunMode, QUICK_TASK_AGENT};
+use crate::sync;
+use crate::{DbState, update_agent_config};
+
+/// Agents shown in the dashboard's "most time saved" list.
+const TOP_AGENTS: usize = 5;
+
+/// One device's successful live runs of one agent on one day. Rows are only ever written by
+/// the device they belong to and travel with sync, so a reinstall restored from sync keeps
+/// the history even though run logs stay local.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeSavedDay {
+    pub device_id: String,
+    /// Local date, `YYYY-MM-DD`.
+    pub day: String,
+    pub agent_id: String,
+    pub runs: i64,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AgentTimeSaved {
+    pub agent_id: String,
+    pub name: String,
+    pub runs: i64,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DashboardStats {
+    pub minutes_saved_this_month: f64,
+    pub minutes_saved_total: f64,
+    pub runs_this_month: i64,
+    /// Days in a row, up to today, with at least one successful run. Today not having a run
+    /// yet doesn't break the streak.
+    pub current_streak_days: i64,
+    pub longest_streak_days: i64,
+    /// Most time saved this month first.
+    pub top_agents: Vec<AgentTimeSaved>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS time_saved (
+            device_id TEXT NOT NULL,
+            day TEXT NOT NULL,
+            agent_id TEXT NOT NULL,
+            runs INTEGER NOT NULL DEFAULT 0,
+            minutes REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (device_id, day, agent_id)
+        );
+    ").expect("Failed to initialize stats tables");
+}
+
+/// Manual minutes one run of the agent saves, from `minutes_saved` in its config.
+fn minutes_per_run(conn: &Connection, agent_id: &str) -> f64 {
+    conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|c| serde_json::from_str::<Value>(&c).ok())
+        .and_then(|c| c.get("minutes_saved").and_then(Value::as_f64))
+        .unwrap_or(0.0)
+}
+
+/// Counts a finished run towards today's stats when it was a successful live run.
+pub fn record_run(conn: &Connection, run: &Run) -> Result<(), String> {
+    if run.mode != RunMode::Live || run.status != "success" {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO time_saved (device_id, day, agent_id, runs, minutes) VALUES (?1, ?2, ?3, 1, ?4)
+         ON CONFLICT(device_id, day, agent_id) DO UPDATE SET runs = runs + 1, minutes = minutes + excluded.minutes",
+        params![
+            sync::device_id(conn)?,
+            Local::now().date_naive().to_string(),
+            run.agent_id,
+            minutes_per_run(conn, &run.agent_id)
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn all_days(conn: &Connection) -> Result<Vec<TimeSavedDay>, String> {
+    let mut stmt = conn.prepare("SELECT device_id, day, agent_id, runs, minutes FROM time_saved")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok(TimeSavedDay {
+        device_id: row.get(0)?,
+        day: row.get(1)?,
+        agent_id: row.get(2)?,
+        runs: row.get(3)?,
+        minutes: row.get(4)?,
+    })).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Merges a row from another device's snapshot. Counts only grow, so the larger one is newer.
+pub fn merge_day(conn: &Connection, day: &TimeSavedDay) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT INTO time_saved (device_id, day, agent_id, runs, minutes) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(device_id, day, agent_id) DO UPDATE SET runs = MAX(runs, excluded.runs), minutes = MAX(minutes, excluded.minutes)",
+        params![day.device_id, day.day, day.agent_id, day.runs, day.minutes],
+    )
+}
+
+/// Current and longest streak of consecutive days in `days` (sorted, distinct).
+fn streaks(days: &[NaiveDate], today: NaiveDate) -> (i64, i64) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        run = match previous {
+            Some(p) if *day - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+    let current = match previous {
+        Some(last) if today - last <= Duration::days(1) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+// ─── Stats Commands ───
+
+/// Sets how many minutes of manual work one run of the agent saves; `None` clears it.
+/// Only runs from then on count with the new value.
+#[tauri::command]
+pub fn set_agent_time_saved(db: State<DbState>, agent_id: String, minutes: Option<f64>) -> Result<(), String> {
+    if minutes.is_some_and(|m| !m.is_finite() || m < 0.0) {
+        return Err("Minutes saved can't be negative".into());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| match minutes {
+        Some(m) => { config.insert("minutes_saved".into(), json!(m)); }
+        None => { config.remove("minutes_saved"); }
+    })
+}
+
+/// Time saved this month and overall, run streaks, and the agents saving the most time,
+/// across every device that syncs.
+#[tauri::command]
+pub fn get_dashboard_stats(db: State<DbState>) -> Result<DashboardStats, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let rows = all_days(&conn)?;
+    let today = Local::now().date_naive();
+    let month = format!("{:04}-{:02}", today.year(), today.month());
+
+    let mut days: Vec<NaiveDate> = rows.iter()
+        .filter(|r| r.runs > 0)
+        .filter_map(|r| r.day.parse().ok())
+        .collect();
+    days.sort();
+    days.dedup();
+    let (current_streak_days, longest_streak_days) = streaks(&days, today);
+
+    let this_month: Vec<&TimeSavedDay> = rows.iter().filter(|r| r.day.starts_with(&month)).collect();
+    let mut top_agents: Vec<AgentTimeSaved> = Vec::new();
+    for row in &this_month {
+        match top_agents.iter_mut().find(|a| a.agent_id == row.agent_id) {
+            Some(agent) => {
+                agent.runs += row.runs;
+                agent.minutes += row.minutes;
+            }
+            None => top_agents.push(AgentTimeSaved {
+                agent_id: row.agent_id.clone(),
+                name: String::new(),
+                runs: row.runs,
+                minutes: row.minutes,
+            }),
+        }
+    }
+    for agent in &mut top_agents {
+        agent.name = match agent.agent_id.as_str() {
+            QUICK_TASK_AGENT => "Quick tasks".into(),
+            id => conn.query_row("SELECT name FROM agents WHERE id = ?1", params![id], |row| row.get(0))
+                .unwrap_or_else(|_| "Deleted agent".into()),
+        };
+    }
+    top_agents.sort_by(|a, b| b.minutes.total_cmp(&a.minutes).then(b.runs.cmp(&a.runs)));
+    top_agents.truncate(TOP_AGENTS);
+
+    Ok(DashboardStats {
+        minutes_saved_this_month: this_month.iter().map(|r| r.minutes).sum(),
+        minutes_saved_total: rows.iter().map(|r| r.minutes).sum(),
+        runs_this_month: this_month.iter().map(|r| r.runs).sum(),
+        current_streak_days,
+        longest_streak_days,
+        top_agents,
+    })
+}
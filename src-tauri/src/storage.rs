@@ -0,0 +1,215 @@
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use crate::maintenance;
+use crate::recorder;
+use crate::{DbState, app_data_dir};
+
+/// Cleanup removes entries older than this unless told otherwise.
+const DEFAULT_KEEP_DAYS: i64 = 30;
+
+/// Space taken by one table, counting its indexes and full-text index data.
+#[derive(Debug, Serialize, Clone)]
+pub struct TableUsage {
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// A kind of data the user recognizes, and whether it can be cleaned up from here.
+#[derive(Debug, Serialize, Clone)]
+pub struct StorageCategory {
+    /// `cache`, `artifacts`, `logs`, `recordings` or `fixtures`; cleanable ones go to `clean_storage`.
+    pub id: String,
+    pub label: String,
+    pub bytes: u64,
+    pub items: i64,
+    pub cleanable: bool,
+}
+
+/// Where the app's disk space goes. Backups aren't listed: sync writes them to the user's own
+/// folder or WebDAV server, not to this device's app data.
+#[derive(Debug, Serialize, Clone)]
+pub struct StorageReport {
+    pub total_bytes: u64,
+    pub database_bytes: u64,
+    /// Largest first.
+    pub tables: Vec<TableUsage>,
+    pub categories: Vec<StorageCategory>,
+    /// Files in the app data folder outside the database and the listed categories.
+    pub other_files_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CleanupResult {
+    pub category: String,
+    pub removed: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else { return 0 };
+    entries.filter_map(|e| e.ok())
+        .map(|e| match e.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&e.path()),
+            _ => e.metadata().map_or(0, |m| m.len()),
+        })
+        .sum()
+}
+
+/// Bytes per table from SQLite's page statistics. Indexes count toward their table and the
+/// `<name>_*` shadow tables toward their full-text index.
+fn table_usage(conn: &Connection) -> Result<Vec<TableUsage>, String> {
+    let owners: HashMap<String, (String, String)> = {
+        let mut stmt = conn.prepare("SELECT name, tbl_name, COALESCE(sql, '') FROM sqlite_master")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?))))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let virtual_tables: Vec<&str> = owners.iter()
+        .filter(|(_, (_, sql))| sql.starts_with("CREATE VIRTUAL TABLE"))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let owner = |name: &str| -> String {
+        let table = owners.get(name).map_or(name, |(table, _)| table.as_str());
+        virtual_tables.iter()
+            .find(|v| table.strip_prefix(**v).is_some_and(|rest| rest.starts_with('_')))
+            .map_or(table, |v| v)
+            .to_string()
+    };
+
+    let mut stmt = conn.prepare("SELECT name, SUM(pgsize) FROM dbstat GROUP BY name").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?;
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    for row in rows {
+        let (name, bytes) = row.map_err(|e| e.to_string())?;
+        *totals.entry(owner(&name)).or_default() += bytes.max(0) as u64;
+    }
+    let mut tables: Vec<TableUsage> = totals.into_iter().map(|(name, bytes)| TableUsage { name, bytes }).collect();
+    tables.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    Ok(tables)
+}
+
+fn count(conn: &Connection, table: &str) -> Result<i64, String> {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).map_err(|e| e.to_string())
+}
+
+fn report(conn: &Connection) -> Result<StorageReport, String> {
+    let database_bytes = maintenance::database_size(conn)?;
+    let tables = table_usage(conn)?;
+    let table_bytes = |name: &str| tables.iter().find(|t| t.name == name).map_or(0, |t| t.bytes);
+
+    let fixtures_dir = app_data_dir().join("fixtures");
+    let fixtures_bytes = dir_size(&fixtures_dir);
+    let fixture_sets = std::fs::read_dir(&fixtures_dir).map_or(0, |d| d.count() as i64);
+    let recordings_dir = recorder::recordings_dir();
+    let recordings_bytes = dir_size(&recordings_dir);
+    let recordings = std::fs::read_dir(&recordings_dir).map_or(0, |d| d.count() as i64);
+    let categories = vec![
+        StorageCategory {
+            id: "cache".into(),
+            label: "Saved model responses".into(),
+            bytes: table_bytes("response_cache"),
+            items: count(conn, "response_cache")?,
+            cleanable: true,
+        },
+        StorageCategory {
+            id: "artifacts".into(),
+            label: "Intermediate run results".into(),
+            bytes: table_bytes("run_artifacts"),
+            items: count(conn, "run_artifacts")?,
+            cleanable: true,
+        },
+        StorageCategory {
+            id: "logs".into(),
+            label: "Activity log".into(),
+            bytes: table_bytes("execution_logs"),
+            items: count(conn, "execution_logs")?,
+            cleanable: true,
+        },
+        StorageCategory {
+            id: "recordings".into(),
+            label: "Audio recordings".into(),
+            bytes: recordings_bytes,
+            items: recordings,
+            cleanable: true,
+        },
+        StorageCategory {
+            id: "fixtures".into(),
+            label: "Test data sets".into(),
+            bytes: fixtures_bytes,
+            items: fixture_sets,
+            cleanable: false,
+        },
+    ];
+
+    let app_dir_bytes = dir_size(&app_data_dir());
+    let database_files = conn.path()
+        .map(|path| ["", "-wal", "-shm", "-journal"].iter()
+            .filter_map(|suffix| std::fs::metadata(format!("{path}{suffix}")).ok())
+            .map(|m| m.len())
+            .sum())
+        .unwrap_or(database_bytes);
+    Ok(StorageReport {
+        total_bytes: app_dir_bytes.max(database_files + fixtures_bytes + recordings_bytes),
+        database_bytes,
+        tables,
+        categories,
+        other_files_bytes: app_dir_bytes.saturating_sub(database_files + fixtures_bytes + recordings_bytes),
+    })
+}
+
+// ─── Storage Commands ───
+
+/// What the app stores and how much space each part takes.
+#[tauri::command]
+pub async fn get_storage_report(app: AppHandle) -> Result<StorageReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        report(&conn)
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// Deletes recording files made before `cutoff` along with their artifacts.
+fn remove_recordings(conn: &Connection, cutoff: &str) -> rusqlite::Result<usize> {
+    let mut stmt = conn.prepare("SELECT id, content FROM run_artifacts WHERE kind = 'recording' AND created_at < ?1")?;
+    let old: Vec<(String, String)> = stmt.query_map(params![cutoff], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    for (id, path) in &old {
+        // Only files in the recordings folder; an artifact's path is never trusted beyond it.
+        if Path::new(path).parent() == Some(recorder::recordings_dir().as_path()) {
+            let _ = std::fs::remove_file(path);
+        }
+        conn.execute("DELETE FROM run_artifacts WHERE id = ?1", params![id])?;
+    }
+    Ok(old.len())
+}
+
+/// Clears a cleanable category: the whole response cache, or artifacts, log entries and
+/// recordings older than `older_than_days` (default 30; `0` removes them all). The database is compacted
+/// afterwards so the space is actually given back.
+#[tauri::command]
+pub async fn clean_storage(app: AppHandle, category: String, older_than_days: Option<i64>) -> Result<CleanupResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let cutoff = (Utc::now() - Duration::days(older_than_days.unwrap_or(DEFAULT_KEEP_DAYS).max(0))).to_rfc3339();
+        let removed = match category.as_str() {
+            "cache" => conn.execute("DELETE FROM response_cache", []),
+            // Recordings have files behind them and are cleaned up as their own category.
+            "artifacts" => conn.execute("DELETE FROM run_artifacts WHERE created_at < ?1 AND kind != 'recording'", params![cutoff]),
+            "recordings" => remove_recordings(&conn, &cutoff),
+            "logs" => conn.execute("DELETE FROM execution_logs WHERE created_at < ?1", params![cutoff]),
+            other => return Err(format!("{other} can't be cleaned up here")),
+        }.map_err(|e| e.to_string())?;
+        let compacted = maintenance::run(&conn, false)?;
+        Ok(CleanupResult { category, removed, size_before: compacted.size_before, size_after: compacted.size_after })
+    }).await.map_err(|e| e.to_string())?
+}
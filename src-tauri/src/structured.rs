@@ -0,0 +1,176 @@
+use serde_json::Value;
+
+use crate::llm::{self, ChatMessage, LlmRequest, LlmResponse};
+use crate::DbState;
+
+/// The first reply plus this many corrections.
+const MAX_ATTEMPTS: usize = 3;
+
+fn type_matches(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    let at = if path.is_empty() { "the reply".to_string() } else { path.to_string() };
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| type_matches(value, t)) {
+        errors.push(format!("{at} should be {}", types.join(" or ")));
+        return;
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            errors.push(format!("{at} should be one of {}", Value::Array(options.clone())));
+        }
+    }
+    match value {
+        Value::Object(map) => {
+            for key in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                if !map.contains_key(key) {
+                    errors.push(format!("{at} is missing \"{key}\""));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, item) in map {
+                let child = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+                match properties.and_then(|p| p.get(key)) {
+                    Some(sub) => validate_at(item, sub, &child, errors),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{child} is not allowed"));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(sub) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item, sub, &format!("{at}[{i}]"), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks `value` against the parts of JSON Schema models are asked to follow: `type`,
+/// `enum`, `properties`, `required`, `additionalProperties: false` and `items`.
+/// Returns one readable message per problem.
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(value, schema, "", &mut errors);
+    errors
+}
+
+/// Fixes the usual ways model JSON is almost right: code fences or prose around it, trailing
+/// commas, curly quotes, and a reply cut off before its closing brackets.
+pub fn repair(text: &str) -> Result<Value, String> {
+    if let Ok(value) = llm::extract_json(text) {
+        return Ok(value);
+    }
+    let start = text.find(['{', '[']).ok_or("The reply contained no JSON")?;
+    let text = text[start..].replace(['\u{201c}', '\u{201d}'], "\"");
+
+    let mut out = String::with_capacity(text.len());
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => closers.push('}'),
+            '[' => closers.push(']'),
+            '}' | ']' => {
+                // Trailing comma before a closer.
+                if out.trim_end().ends_with(',') {
+                    out.truncate(out.trim_end().len() - 1);
+                }
+                closers.pop();
+            }
+            _ => {}
+        }
+        out.push(c);
+        if closers.is_empty() && (c == '}' || c == ']') {
+            break;
+        }
+    }
+    if in_string {
+        out.push('"');
+    }
+    while let Some(closer) = closers.pop() {
+        if out.trim_end().ends_with(',') {
+            out.truncate(out.trim_end().len() - 1);
+        }
+        out.push(closer);
+    }
+    serde_json::from_str(&out).map_err(|e| format!("The reply wasn't valid JSON: {e}"))
+}
+
+/// Asks for JSON matching `schema`, using the provider's JSON mode where it has one.
+/// Malformed replies are repaired locally when possible; otherwise the model is shown what
+/// was wrong and asked again.
+pub fn complete(db: &DbState, req: &LlmRequest, schema: &Value) -> Result<Value, String> {
+    complete_with(db, req, schema, llm::complete)
+}
+
+/// `complete`, but asking the local model whatever provider is configured.
+pub fn complete_locally(db: &DbState, req: &LlmRequest, schema: &Value) -> Result<Value, String> {
+    complete_with(db, req, schema, llm::complete_locally)
+}
+
+fn complete_with(
+    db: &DbState,
+    req: &LlmRequest,
+    schema: &Value,
+    send: fn(&DbState, &LlmRequest) -> Result<LlmResponse, String>,
+) -> Result<Value, String> {
+    let mut req = LlmRequest {
+        system: format!(
+            "{}\n\nReply with a single JSON value matching this JSON schema, and nothing else:\n{schema}",
+            req.system,
+        ),
+        json: true,
+        ..req.clone()
+    };
+    let mut problem = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let reply = send(db, &req)?.text;
+        problem = match repair(&reply) {
+            Ok(value) => {
+                let errors = validate(&value, schema);
+                if errors.is_empty() {
+                    return Ok(value);
+                }
+                errors.join("; ")
+            }
+            Err(e) => e,
+        };
+        req.messages.push(ChatMessage::assistant(reply));
+        req.messages.push(ChatMessage::user(format!(
+            "That reply can't be used: {problem}. Reply again with only the corrected JSON."
+        )));
+    }
+    Err(format!("The model's reply didn't match the expected format: {problem}"))
+}
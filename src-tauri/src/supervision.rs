@@ -0,0 +1,169 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::audit;
+use crate::crypto;
+use crate::{DbState, read_setting, write_setting};
+
+const PIN_SETTING: &str = "supervisor_pin_hash";
+const POLICY_SETTING: &str = "supervisor_policy";
+/// Enforcement is paused until this time, after the supervisor entered the PIN.
+const SUSPENDED_SETTING: &str = "supervisor_suspended_until";
+/// What a new policy allows: reading, thinking and notifying, nothing that changes or sends.
+const DEFAULT_TOOLS: &[&str] = &["read_file", "list_directory", "llm_prompt", "critique", "search_knowledge", "notify"];
+const MAX_SUSPEND_MINUTES: i64 = 24 * 60;
+
+/// What agents may do while supervised mode is on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SupervisorPolicy {
+    pub enabled: bool,
+    /// Tools agents may use; every other tool is blocked.
+    pub allowed_tools: Vec<String>,
+    /// Sites web tools may reach, e.g. `wikipedia.org`, subdomains included. Empty allows any
+    /// site, as long as the tool itself is allowed.
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for SupervisorPolicy {
+    fn default() -> Self {
+        SupervisorPolicy {
+            enabled: false,
+            allowed_tools: DEFAULT_TOOLS.iter().map(|t| t.to_string()).collect(),
+            allowed_domains: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SupervisorStatus {
+    pub has_pin: bool,
+    /// Empty unless enforcement is paused.
+    pub suspended_until: String,
+    pub policy: SupervisorPolicy,
+}
+
+fn policy(conn: &Connection) -> Result<SupervisorPolicy, String> {
+    Ok(read_setting(conn, POLICY_SETTING)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+pub fn enabled(conn: &Connection) -> bool {
+    policy(conn).is_ok_and(|p| p.enabled)
+}
+
+fn suspended_until(conn: &Connection) -> Result<Option<DateTime<Utc>>, String> {
+    Ok(read_setting(conn, SUSPENDED_SETTING)?
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .filter(|t| *t > Utc::now()))
+}
+
+/// Checks the supervisor PIN; a wrong one is recorded in the audit trail.
+fn verify_pin(conn: &Connection, pin: &str, attempt: &str) -> Result<(), String> {
+    let stored = read_setting(conn, PIN_SETTING)?.unwrap_or_default();
+    if stored.is_empty() || crypto::verify_password(pin, &stored) {
+        return Ok(());
+    }
+    let summary = format!("Wrong supervisor PIN when trying to {attempt}");
+    audit::record(conn, "supervisor_pin_failed", "", "", &summary, &json!({ "attempt": attempt }))?;
+    Err("Wrong supervisor PIN".into())
+}
+
+fn domain_allowed(policy: &SupervisorPolicy, url: &str) -> bool {
+    if policy.allowed_domains.is_empty() {
+        return true;
+    }
+    let Some(host) = tauri::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase)) else {
+        return false;
+    };
+    policy.allowed_domains.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+        host == domain || host.ends_with(&format!(".{domain}"))
+    })
+}
+
+/// Applies supervised mode to a step before it runs. A blocked tool or site is refused and
+/// recorded in the audit trail.
+pub fn check_step(conn: &Connection, agent_id: &str, run_id: &str, tool: &str, params: &Value) -> Result<(), String> {
+    let policy = policy(conn)?;
+    if !policy.enabled || suspended_until(conn)?.is_some() {
+        return Ok(());
+    }
+    let url = params.get("url").and_then(Value::as_str);
+    let reason = if !policy.allowed_tools.iter().any(|t| t == tool) {
+        format!("Supervised mode doesn't allow {tool}")
+    } else if let Some(url) = url.filter(|u| !domain_allowed(&policy, u)) {
+        format!("Supervised mode doesn't allow visiting {url}")
+    } else {
+        return Ok(());
+    };
+    audit::record(conn, "supervisor_blocked", agent_id, run_id, &reason, &json!({ "tool": tool, "url": url }))?;
+    Err(reason)
+}
+
+// ─── Supervisor Commands ───
+
+#[tauri::command]
+pub fn get_supervisor_status(db: State<DbState>) -> Result<SupervisorStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(SupervisorStatus {
+        has_pin: read_setting(&conn, PIN_SETTING)?.is_some_and(|h| !h.is_empty()),
+        suspended_until: suspended_until(&conn)?.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        policy: policy(&conn)?,
+    })
+}
+
+/// Sets, changes or (with an empty `new_pin`, once supervised mode is off) removes the
+/// supervisor PIN. The current PIN is required whenever one is set.
+#[tauri::command]
+pub fn set_supervisor_pin(db: State<DbState>, current_pin: String, new_pin: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    verify_pin(&conn, &current_pin, "change the supervisor PIN")?;
+    if new_pin.is_empty() {
+        if enabled(&conn) {
+            return Err("Turn supervised mode off before removing the PIN".into());
+        }
+        write_setting(&conn, PIN_SETTING, "")?;
+    } else {
+        if new_pin.chars().count() < 4 {
+            return Err("Use a PIN of at least 4 characters".into());
+        }
+        write_setting(&conn, PIN_SETTING, &crypto::hash_password(&new_pin))?;
+    }
+    audit::record(&conn, "supervisor_pin_changed", "", "", "The supervisor PIN was changed", &json!({}))
+}
+
+/// Replaces the policy, turning supervised mode on or off. Needs the supervisor PIN, which
+/// must be set before supervised mode can be turned on.
+#[tauri::command]
+pub fn set_supervisor_policy(db: State<DbState>, pin: String, policy: SupervisorPolicy) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    verify_pin(&conn, &pin, "change the supervised mode policy")?;
+    if policy.enabled && read_setting(&conn, PIN_SETTING)?.is_none_or(|h| h.is_empty()) {
+        return Err("Set a supervisor PIN before turning supervised mode on".into());
+    }
+    write_setting(&conn, POLICY_SETTING, &serde_json::to_string(&policy).map_err(|e| e.to_string())?)?;
+    let summary = if policy.enabled { "Supervised mode policy updated" } else { "Supervised mode turned off" };
+    audit::record(&conn, "supervisor_policy_changed", "", "", summary, &serde_json::to_value(&policy).map_err(|e| e.to_string())?)
+}
+
+/// Lets agents use everything for `minutes` (0 ends a pause early), e.g. while the
+/// supervisor is at the computer.
+#[tauri::command]
+pub fn suspend_supervision(db: State<DbState>, pin: String, minutes: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    verify_pin(&conn, &pin, "pause supervised mode")?;
+    let minutes = minutes.clamp(0, MAX_SUSPEND_MINUTES);
+    let until = if minutes == 0 { String::new() } else { (Utc::now() + Duration::minutes(minutes)).to_rfc3339() };
+    write_setting(&conn, SUSPENDED_SETTING, &until)?;
+    let summary = match minutes {
+        0 => "Supervised mode resumed".to_string(),
+        m => format!("Supervised mode paused for {m} minutes"),
+    };
+    audit::record(&conn, "supervisor_suspended", "", "", &summary, &json!({ "minutes": minutes }))
+}
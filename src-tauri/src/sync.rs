@@ -0,0 +1,659 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+use crate::artifacts;
+use crate::calendar;
+use crate::connections;
+use crate::crypto::{self, Sealed};
+use crate::llm;
+use crate::meetings;
+use crate::net::{self, Proxy};
+use crate::profiles;
+use crate::rates;
+use crate::receipts;
+use crate::recorder;
+use crate::routing;
+use crate::stats::{self, TimeSavedDay};
+use crate::transcribe;
+use crate::{DbState, read_setting, write_setting};
+
+const FILE_FORMAT: &str = "openclaw-sync";
+const FILE_EXTENSION: &str = ".ocsync";
+const WEBDAV_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_SYNC_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Settings that describe this machine rather than the user; they never leave it.
+const LOCAL_ONLY_SETTINGS: &[&str] = &["sync_", "active_fixture_set", "share_signing_key", "app_lock_", "local_only_mode", "proxy_", "knowledge_", "window_state_", "db_maintenance_last", "data_owner", "supervisor_", "focus_", "companion_", "local_api_", "crash_upload_", "activity_", "discovery_"];
+
+/// Device-only settings marked where they are defined: a module with a setting that holds
+/// a path, an address or state about this machine lists it as `LOCAL_ONLY_SETTINGS`.
+const MODULE_LOCAL_ONLY_SETTINGS: &[&[&str]] = &[
+    calendar::LOCAL_ONLY_SETTINGS,
+    llm::LOCAL_ONLY_SETTINGS,
+    meetings::LOCAL_ONLY_SETTINGS,
+    profiles::LOCAL_ONLY_SETTINGS,
+    rates::LOCAL_ONLY_SETTINGS,
+    receipts::LOCAL_ONLY_SETTINGS,
+    recorder::LOCAL_ONLY_SETTINGS,
+    routing::LOCAL_ONLY_SETTINGS,
+    transcribe::LOCAL_ONLY_SETTINGS,
+];
+
+/// How this device syncs. Secrets are write-only: `get_sync_config` returns them blank.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// `""` (off), `"folder"` or `"webdav"`.
+    pub target: String,
+    /// A folder kept in sync by Dropbox, Google Drive or similar.
+    pub folder: String,
+    pub webdav_url: String,
+    pub webdav_username: String,
+    pub webdav_password: String,
+    pub passphrase: String,
+    pub device_name: String,
+    pub has_passphrase: bool,
+    pub last_synced_at: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SyncReport {
+    /// Other devices whose changes were read.
+    pub devices: Vec<String>,
+    pub agents_updated: usize,
+    pub settings_updated: usize,
+    pub deleted: usize,
+    /// Names of agents saved as conflict copies because both sides changed them.
+    pub conflicts: Vec<String>,
+    pub synced_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct SyncedAgent {
+    id: String,
+    name: String,
+    role: String,
+    goal: String,
+    tools: String,
+    schedule: String,
+    config_json: String,
+    sandbox: bool,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct SyncedSetting {
+    key: String,
+    value: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct SyncedSchedule {
+    id: String,
+    agent_id: String,
+    cron_expr: String,
+    description: String,
+    enabled: bool,
+    updated_at: String,
+}
+
+/// Fragment versions never change once written, so they are simply unioned.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct SyncedFragment {
+    id: String,
+    version: i64,
+    name: String,
+    description: String,
+    params_json: String,
+    steps_json: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Tombstone {
+    kind: String,
+    key: String,
+    deleted_at: String,
+}
+
+/// Everything one device publishes; logs and run history stay local.
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    device_id: String,
+    device_name: String,
+    written_at: String,
+    agents: Vec<SyncedAgent>,
+    settings: Vec<SyncedSetting>,
+    schedules: Vec<SyncedSchedule>,
+    fragments: Vec<SyncedFragment>,
+    tombstones: Vec<Tombstone>,
+    /// Missing from snapshots written before time-saved stats existed.
+    #[serde(default)]
+    time_saved: Vec<TimeSavedDay>,
+}
+
+/// On-disk form of a snapshot: only the format marker is readable without the passphrase.
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncFile {
+    format: String,
+    version: u32,
+    kdf_iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS sync_tombstones (
+            kind TEXT NOT NULL,
+            key TEXT NOT NULL,
+            deleted_at TEXT NOT NULL,
+            PRIMARY KEY (kind, key)
+        );
+        CREATE TABLE IF NOT EXISTS sync_peers (
+            device_id TEXT PRIMARY KEY,
+            device_name TEXT DEFAULT '',
+            last_written_at TEXT DEFAULT ''
+        );
+    ").expect("Failed to initialize sync tables");
+}
+
+/// Remembers a deletion so other devices remove the item too instead of syncing it back.
+pub(crate) fn record_deletion(conn: &Connection, kind: &str, key: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO sync_tombstones (kind, key, deleted_at) VALUES (?1, ?2, ?3)",
+        params![kind, key, Utc::now().to_rfc3339()],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn is_local_only(key: &str) -> bool {
+    LOCAL_ONLY_SETTINGS.iter().chain(MODULE_LOCAL_ONLY_SETTINGS.iter().copied().flatten())
+        .any(|prefix| key.starts_with(prefix))
+}
+
+/// `a` is strictly newer than `b`; unstamped rows predate sync and count as oldest.
+fn newer(a: &str, b: &str) -> bool {
+    match (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a > b,
+        (Ok(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+// ─── Targets ───
+
+enum Target {
+    Folder(PathBuf),
+    WebDav { url: String, username: String, password: String, proxy: Proxy },
+}
+
+impl Target {
+    fn from_config(config: &SyncConfig, proxy: Proxy) -> Result<Target, String> {
+        match config.target.as_str() {
+            "folder" if !config.folder.trim().is_empty() => Ok(Target::Folder(PathBuf::from(config.folder.trim()))),
+            "folder" => Err("Choose a folder to sync through".into()),
+            "webdav" if config.webdav_url.starts_with("https://") => Ok(Target::WebDav {
+                url: format!("{}/", config.webdav_url.trim_end_matches('/')),
+                username: config.webdav_username.clone(),
+                password: config.webdav_password.clone(),
+                proxy,
+            }),
+            "webdav" => Err("The WebDAV address must start with https://".into()),
+            _ => Err("Sync is turned off".into()),
+        }
+    }
+
+    fn request(&self, method: &str, name: &str) -> Result<ureq::Request, String> {
+        let Target::WebDav { url, username, password, proxy } = self else {
+            unreachable!("only WebDAV targets make requests");
+        };
+        let address = format!("{url}{name}");
+        let request = proxy.agent(&address, WEBDAV_TIMEOUT)?.request(method, &address);
+        if username.is_empty() {
+            Ok(request)
+        } else {
+            Ok(request.set("Authorization", &format!("Basic {}", B64.encode(format!("{username}:{password}")))))
+        }
+    }
+
+    /// Names of every device file at the target.
+    fn list(&self) -> Result<Vec<String>, String> {
+        let names: Vec<String> = match self {
+            Target::Folder(dir) => {
+                std::fs::create_dir_all(dir).map_err(|e| format!("Can't open the sync folder: {e}"))?;
+                std::fs::read_dir(dir).map_err(|e| e.to_string())?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            }
+            Target::WebDav { .. } => {
+                let body = match self.request("PROPFIND", "")?.set("Depth", "1").call() {
+                    Ok(resp) => resp.into_string().map_err(|e| e.to_string())?,
+                    Err(ureq::Error::Status(code, _)) => return Err(format!("The WebDAV server answered with status {code}")),
+                    Err(e) => return Err(format!("Couldn't reach the WebDAV server: {e}")),
+                };
+                // Just the hrefs are needed, so the multistatus XML is scanned rather than parsed.
+                body.split("href>")
+                    .filter_map(|part| part.split('<').next())
+                    .filter_map(|href| href.trim_end_matches('/').rsplit('/').next().map(String::from))
+                    .collect()
+            }
+        };
+        Ok(names.into_iter().filter(|n| n.ends_with(FILE_EXTENSION)).collect())
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        match self {
+            Target::Folder(dir) => {
+                std::fs::File::open(dir.join(name)).map_err(|e| e.to_string())?
+                    .take(MAX_SYNC_FILE_BYTES)
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| e.to_string())?;
+            }
+            Target::WebDav { .. } => {
+                let resp = self.request("GET", name)?.call().map_err(|e| format!("Couldn't download {name}: {e}"))?;
+                resp.into_reader().take(MAX_SYNC_FILE_BYTES).read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<(), String> {
+        match self {
+            Target::Folder(dir) => {
+                // Written beside the real file and renamed, so sync clients never upload half a file.
+                let partial = dir.join(format!("{name}.partial"));
+                std::fs::write(&partial, bytes).map_err(|e| format!("Can't write to the sync folder: {e}"))?;
+                std::fs::rename(&partial, dir.join(name)).map_err(|e| e.to_string())
+            }
+            Target::WebDav { .. } => self.request("PUT", name)?
+                .set("Content-Type", "application/octet-stream")
+                .send_bytes(bytes)
+                .map(|_| ())
+                .map_err(|e| format!("Couldn't upload to the WebDAV server: {e}")),
+        }
+    }
+}
+
+/// Whether the sync target can be reached and listed with the saved settings.
+pub(crate) fn check(config: &SyncConfig, proxy: Proxy) -> Result<(), String> {
+    Target::from_config(config, proxy)?.list().map(|_| ())
+}
+
+// ─── Snapshots ───
+
+pub(crate) fn load_config(conn: &Connection) -> Result<SyncConfig, String> {
+    let get = |key: &str| -> Result<String, String> { Ok(read_setting(conn, key)?.unwrap_or_default()) };
+    let passphrase = get("sync_passphrase")?;
+    Ok(SyncConfig {
+        target: get("sync_target")?,
+        folder: get("sync_folder")?,
+        webdav_url: get("sync_webdav_url")?,
+        webdav_username: get("sync_webdav_username")?,
+        webdav_password: get("sync_webdav_password")?,
+        has_passphrase: !passphrase.is_empty(),
+        passphrase,
+        device_name: get("sync_device_name")?,
+        last_synced_at: get("sync_last_at")?,
+    })
+}
+
+pub(crate) fn device_id(conn: &Connection) -> Result<String, String> {
+    if let Some(id) = read_setting(conn, "sync_device_id")?.filter(|id| !id.is_empty()) {
+        return Ok(id);
+    }
+    let id = Uuid::new_v4().to_string();
+    write_setting(conn, "sync_device_id", &id)?;
+    Ok(id)
+}
+
+fn collect<T>(conn: &Connection, sql: &str, map: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>) -> Result<Vec<T>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], map).map_err(|e| e.to_string())?;
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+}
+
+const AGENT_COLUMNS: &str = "id, name, role, goal, tools, schedule, config_json, sandbox, created_at, updated_at";
+
+fn row_to_agent(row: &rusqlite::Row) -> rusqlite::Result<SyncedAgent> {
+    Ok(SyncedAgent {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        role: row.get(2)?,
+        goal: row.get(3)?,
+        tools: row.get(4)?,
+        schedule: row.get(5)?,
+        config_json: row.get(6)?,
+        sandbox: row.get::<_, i32>(7)? != 0,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn local_snapshot(conn: &Connection, device_id: &str, device_name: &str) -> Result<Snapshot, String> {
+    let settings = collect(conn, "SELECT key, value, updated_at FROM settings", |row| {
+        Ok(SyncedSetting { key: row.get(0)?, value: row.get(1)?, updated_at: row.get(2)? })
+    })?;
+    let overridden = profiles::overridden(conn);
+    Ok(Snapshot {
+        device_id: device_id.to_string(),
+        device_name: device_name.to_string(),
+        written_at: Utc::now().to_rfc3339(),
+        agents: collect(conn, &format!("SELECT {AGENT_COLUMNS} FROM agents"), row_to_agent)?,
+        settings: settings.into_iter().filter(|s| !is_local_only(&s.key)).filter_map(|mut s| match overridden.get(&s.key) {
+            // A network profile's value stays on this device; the user's own is shared.
+            None => Some(s),
+            Some(Value::String(own)) => {
+                s.value = own.clone();
+                Some(s)
+            }
+            Some(_) => None,
+        }).collect(),
+        schedules: collect(conn, "SELECT id, agent_id, cron_expr, description, enabled, updated_at FROM schedules", |row| {
+            Ok(SyncedSchedule {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                cron_expr: row.get(2)?,
+                description: row.get(3)?,
+                enabled: row.get::<_, i32>(4)? != 0,
+                updated_at: row.get(5)?,
+            })
+        })?,
+        fragments: collect(conn, "SELECT id, version, name, description, params_json, steps_json, created_at FROM workflow_fragments", |row| {
+            Ok(SyncedFragment {
+                id: row.get(0)?,
+                version: row.get(1)?,
+                name: row.get(2)?,
+                description: row.get(3)?,
+                params_json: row.get(4)?,
+                steps_json: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?,
+        tombstones: collect(conn, "SELECT kind, key, deleted_at FROM sync_tombstones", |row| {
+            Ok(Tombstone { kind: row.get(0)?, key: row.get(1)?, deleted_at: row.get(2)? })
+        })?,
+        time_saved: stats::all_days(conn)?,
+    })
+}
+
+fn encode(snapshot: &Snapshot, passphrase: &str) -> Result<Vec<u8>, String> {
+    let sealed = crypto::seal(passphrase, &serde_json::to_vec(snapshot).map_err(|e| e.to_string())?)?;
+    serde_json::to_vec(&SyncFile {
+        format: FILE_FORMAT.into(),
+        version: 1,
+        kdf_iterations: sealed.kdf_iterations,
+        salt: B64.encode(&sealed.salt),
+        nonce: B64.encode(&sealed.nonce),
+        ciphertext: B64.encode(&sealed.ciphertext),
+    }).map_err(|e| e.to_string())
+}
+
+fn decode(bytes: &[u8], passphrase: &str) -> Result<Snapshot, String> {
+    let file: SyncFile = serde_json::from_slice(bytes).map_err(|_| "not a sync file")?;
+    if file.format != FILE_FORMAT || file.version != 1 {
+        return Err("written by a newer version of the app".into());
+    }
+    let field = |s: &str| B64.decode(s).map_err(|_| "damaged".to_string());
+    let sealed = Sealed {
+        kdf_iterations: file.kdf_iterations,
+        salt: field(&file.salt)?,
+        nonce: field(&file.nonce)?,
+        ciphertext: field(&file.ciphertext)?,
+    };
+    serde_json::from_slice(&crypto::open(passphrase, &sealed)?).map_err(|_| "damaged".to_string())
+}
+
+// ─── Merge ───
+
+fn upsert_agent(conn: &Connection, agent: &SyncedAgent) -> rusqlite::Result<usize> {
+    conn.execute(
+        &format!("INSERT OR REPLACE INTO agents ({AGENT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"),
+        params![
+            agent.id, agent.name, agent.role, agent.goal, agent.tools, agent.schedule,
+            agent.config_json, agent.sandbox as i32, agent.created_at, agent.updated_at
+        ],
+    )
+}
+
+fn tombstone(conn: &Connection, kind: &str, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT deleted_at FROM sync_tombstones WHERE kind = ?1 AND key = ?2",
+        params![kind, key],
+        |row| row.get(0),
+    ).optional()
+}
+
+/// Applies one peer's snapshot: newest write wins, and an agent both sides edited since they
+/// last synced keeps the loser as a conflict copy. `seen` is the peer's previous `written_at`.
+fn merge(conn: &Connection, peer: &Snapshot, seen: &str, last_sync: &str, report: &mut SyncReport) -> rusqlite::Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let conflict_copy = |agent: &SyncedAgent, from: &str| SyncedAgent {
+        id: Uuid::new_v4().to_string(),
+        name: format!("{} (conflict copy from {from})", agent.name),
+        updated_at: now.clone(),
+        ..agent.clone()
+    };
+
+    for remote in &peer.agents {
+        if tombstone(conn, "agent", &remote.id)?.is_some_and(|deleted| !newer(&remote.updated_at, &deleted)) {
+            continue;
+        }
+        let local = conn.query_row(
+            &format!("SELECT {AGENT_COLUMNS} FROM agents WHERE id = ?1"),
+            params![remote.id],
+            row_to_agent,
+        ).optional()?;
+        let Some(local) = local else {
+            upsert_agent(conn, remote)?;
+            report.agents_updated += 1;
+            continue;
+        };
+        let same = SyncedAgent { updated_at: String::new(), ..local.clone() } == SyncedAgent { updated_at: String::new(), ..remote.clone() };
+        if same {
+            continue;
+        }
+        let local_changed = newer(&local.updated_at, last_sync);
+        let remote_changed = newer(&remote.updated_at, seen);
+        if newer(&remote.updated_at, &local.updated_at) {
+            if local_changed {
+                let copy = conflict_copy(&local, "this device");
+                upsert_agent(conn, &copy)?;
+                report.conflicts.push(copy.name);
+            }
+            upsert_agent(conn, remote)?;
+            report.agents_updated += 1;
+        } else if remote_changed {
+            let copy = conflict_copy(remote, &peer.device_name);
+            upsert_agent(conn, &copy)?;
+            report.conflicts.push(copy.name);
+        }
+    }
+
+    let overridden = profiles::overridden(conn);
+    for remote in peer.settings.iter().filter(|s| !is_local_only(&s.key)) {
+        if tombstone(conn, "setting", &remote.key)?.is_some_and(|deleted| !newer(&remote.updated_at, &deleted)) {
+            continue;
+        }
+        let local: Option<(String, String)> = conn.query_row(
+            "SELECT value, updated_at FROM settings WHERE key = ?1",
+            params![remote.key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+        let own = overridden.get(&remote.key);
+        let apply = match &local {
+            None => true,
+            Some((value, updated_at)) => {
+                let value = own.and_then(Value::as_str).unwrap_or(value);
+                value != remote.value && newer(&remote.updated_at, updated_at)
+            }
+        };
+        if apply && own.is_some() {
+            profiles::set_overridden(conn, &remote.key, &remote.value, &remote.updated_at)?;
+            report.settings_updated += 1;
+        } else if apply {
+            conn.execute(
+                "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                params![remote.key, remote.value, remote.updated_at],
+            )?;
+            report.settings_updated += 1;
+        }
+    }
+
+    for remote in &peer.schedules {
+        let local: Option<String> = conn.query_row(
+            "SELECT updated_at FROM schedules WHERE id = ?1",
+            params![remote.id],
+            |row| row.get(0),
+        ).optional()?;
+        if local.is_none_or(|updated_at| newer(&remote.updated_at, &updated_at)) {
+            conn.execute(
+                "INSERT OR REPLACE INTO schedules (id, agent_id, cron_expr, description, enabled, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![remote.id, remote.agent_id, remote.cron_expr, remote.description, remote.enabled as i32, remote.updated_at],
+            )?;
+        }
+    }
+
+    for fragment in &peer.fragments {
+        conn.execute(
+            "INSERT OR IGNORE INTO workflow_fragments (id, version, name, description, params_json, steps_json, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![fragment.id, fragment.version, fragment.name, fragment.description, fragment.params_json, fragment.steps_json, fragment.created_at],
+        )?;
+    }
+
+    for day in &peer.time_saved {
+        stats::merge_day(conn, day)?;
+    }
+
+    for stone in &peer.tombstones {
+        let (table, column) = match stone.kind.as_str() {
+            "agent" => ("agents", "id"),
+            "schedule" => ("schedules", "id"),
+            "setting" if !is_local_only(&stone.key) => ("settings", "key"),
+            _ => continue,
+        };
+        // An edit made after the deletion wins, so the item survives.
+        let removed = conn.execute(
+            &format!("DELETE FROM {table} WHERE {column} = ?1 AND (updated_at = '' OR updated_at <= ?2)"),
+            params![stone.key, stone.deleted_at],
+        )?;
+        report.deleted += removed;
+        conn.execute(
+            "INSERT INTO sync_tombstones (kind, key, deleted_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(kind, key) DO UPDATE SET deleted_at = MAX(deleted_at, excluded.deleted_at)",
+            params![stone.kind, stone.key, stone.deleted_at],
+        )?;
+    }
+    Ok(())
+}
+
+// ─── Sync Commands ───
+
+#[tauri::command]
+pub fn get_sync_config(db: State<DbState>) -> Result<SyncConfig, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let config = load_config(&conn)?;
+    Ok(SyncConfig { passphrase: String::new(), webdav_password: String::new(), ..config })
+}
+
+/// Saves the sync settings. Blank `passphrase` / `webdav_password` keep the stored ones.
+#[tauri::command]
+pub fn set_sync_config(db: State<DbState>, config: SyncConfig) -> Result<(), String> {
+    if !matches!(config.target.as_str(), "" | "folder" | "webdav") {
+        return Err(format!("Unknown sync target: {}", config.target));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    write_setting(&conn, "sync_target", &config.target)?;
+    write_setting(&conn, "sync_folder", &config.folder)?;
+    write_setting(&conn, "sync_webdav_url", &config.webdav_url)?;
+    write_setting(&conn, "sync_webdav_username", &config.webdav_username)?;
+    write_setting(&conn, "sync_device_name", &config.device_name)?;
+    if !config.webdav_password.is_empty() {
+        write_setting(&conn, "sync_webdav_password", &config.webdav_password)?;
+    }
+    if !config.passphrase.is_empty() {
+        if config.passphrase.chars().count() < 8 {
+            return Err("Use a passphrase of at least 8 characters".into());
+        }
+        write_setting(&conn, "sync_passphrase", &config.passphrase)?;
+    }
+    connections::setting_changed(&conn, "sync_target");
+    Ok(())
+}
+
+/// Pulls every other device's encrypted snapshot, merges it, then publishes this device's.
+#[tauri::command]
+pub async fn sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbState>();
+        let (config, device_id, proxy) = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            (load_config(&conn)?, device_id(&conn)?, net::proxy(&conn)?)
+        };
+        if config.passphrase.is_empty() {
+            return Err("Set a sync passphrase first; use the same one on every device".to_string());
+        }
+        let target = Target::from_config(&config, proxy)?;
+        if matches!(target, Target::WebDav { .. }) {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            net::check_egress(&conn, "WebDAV sync", None, "", "")?;
+        }
+        let own_file = format!("{device_id}{FILE_EXTENSION}");
+
+        let mut peers = Vec::new();
+        for name in target.list()?.into_iter().filter(|n| *n != own_file) {
+            let snapshot = decode(&target.read(&name)?, &config.passphrase)
+                .map_err(|e| format!("Couldn't read {name}: {e}"))?;
+            peers.push(snapshot);
+        }
+
+        let mut report = SyncReport::default();
+        let bytes = {
+            let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            for peer in &peers {
+                let seen: String = tx.query_row(
+                    "SELECT last_written_at FROM sync_peers WHERE device_id = ?1",
+                    params![peer.device_id],
+                    |row| row.get(0),
+                ).optional().map_err(|e| e.to_string())?.unwrap_or_default();
+                report.devices.push(if peer.device_name.is_empty() { peer.device_id.clone() } else { peer.device_name.clone() });
+                if !newer(&peer.written_at, &seen) {
+                    continue;
+                }
+                merge(&tx, peer, &seen, &config.last_synced_at, &mut report).map_err(|e| e.to_string())?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO sync_peers (device_id, device_name, last_written_at) VALUES (?1, ?2, ?3)",
+                    params![peer.device_id, peer.device_name, peer.written_at],
+                ).map_err(|e| e.to_string())?;
+            }
+            // Agents deleted on another device take their artifacts with them here too.
+            artifacts::remove_orphans(&tx)?;
+            let bytes = encode(&local_snapshot(&tx, &device_id, &config.device_name)?, &config.passphrase)?;
+            report.synced_at = Utc::now().to_rfc3339();
+            write_setting(&tx, "sync_last_at", &report.synced_at)?;
+            tx.commit().map_err(|e| e.to_string())?;
+            bytes
+        };
+        target.write(&own_file, &bytes)?;
+        Ok(report)
+    }).await.map_err(|e| e.to_string())?
+}
@@ -0,0 +1,244 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use chrono::Utc;
+
+use crate::builder::{self, AgentDraft};
+use crate::downloads;
+use crate::meetings;
+use crate::receipts;
+use crate::net;
+use crate::tools::web;
+use crate::workflows::WorkflowStep;
+use crate::{DbState, read_setting};
+
+/// Nothing is fetched unless this setting is `"true"`.
+const ENABLED_SETTING: &str = "template_catalog_enabled";
+/// HTTPS address of the index; `<url>.sig` holds its base64 ed25519 signature.
+const URL_SETTING: &str = "template_catalog_url";
+/// Base64 ed25519 public key the catalog maintainers sign the index with.
+const KEY_SETTING: &str = "template_catalog_public_key";
+
+/// One community template as published in the catalog index.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CatalogTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub goal: String,
+    #[serde(default)]
+    pub schedule: String,
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub workflow: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogIndex {
+    templates: Vec<CatalogTemplate>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CatalogStatus {
+    pub enabled: bool,
+    pub url: String,
+    pub template_count: usize,
+    /// Empty until the first successful sync.
+    pub fetched_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS template_catalog_cache (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            url TEXT NOT NULL,
+            body TEXT NOT NULL,
+            signature TEXT NOT NULL,
+            fetched_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize template catalog tables");
+}
+
+fn enabled(conn: &Connection) -> Result<bool, String> {
+    Ok(read_setting(conn, ENABLED_SETTING)?.as_deref() == Some("true"))
+}
+
+fn verify(conn: &Connection, body: &str, signature: &str) -> Result<(), String> {
+    let key = read_setting(conn, KEY_SETTING)?.filter(|k| !k.is_empty())
+        .ok_or("No catalog signing key is configured")?;
+    let key: [u8; 32] = B64.decode(key.trim()).ok()
+        .and_then(|k| k.try_into().ok())
+        .ok_or("The catalog signing key is not valid")?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|_| "The catalog signing key is not valid")?;
+    let signature: [u8; 64] = B64.decode(signature.trim()).ok()
+        .and_then(|s| s.try_into().ok())
+        .ok_or("The catalog signature is damaged")?;
+    key.verify(body.as_bytes(), &Signature::from_bytes(&signature))
+        .map_err(|_| "The catalog signature doesn't match; it was not installed".to_string())
+}
+
+/// The cached index, re-verified so a tampered database row is never trusted.
+fn cached_templates(conn: &Connection) -> Result<Vec<CatalogTemplate>, String> {
+    let cached: Option<(String, String)> = conn.query_row(
+        "SELECT body, signature FROM template_catalog_cache WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| e.to_string())?;
+    let Some((body, signature)) = cached else {
+        return Ok(Vec::new());
+    };
+    verify(conn, &body, &signature)?;
+    let index: CatalogIndex = serde_json::from_str(&body).map_err(|e| format!("The catalog index is malformed: {e}"))?;
+    Ok(index.templates)
+}
+
+/// Built-in template: a morning briefing from the weather and upcoming holidays, which needs
+/// nothing set up beyond the home location.
+fn morning_briefing() -> CatalogTemplate {
+    let step = |tool: &str, params: serde_json::Value, label: &str| WorkflowStep::Tool { tool: tool.into(), params, label: label.into() };
+    CatalogTemplate {
+        id: "builtin:morning-briefing".into(),
+        name: "Morning briefing".into(),
+        description: "Every morning, sums up the day's weather at your home location and any public \
+            holidays coming up this week in a short notification.".into(),
+        author: "OpenClaw".into(),
+        tags: vec!["briefing".into(), "weather".into(), "holidays".into(), "daily".into()],
+        role: "Morning briefer".into(),
+        goal: "Brief me on the day ahead every morning".into(),
+        schedule: "0 7 * * *".into(),
+        tools: vec!["get_weather".into(), "public_holidays".into(), "llm_prompt".into(), "notify".into()],
+        workflow: vec![
+            step("get_weather", serde_json::json!({ "days": 1 }), "Check the weather"),
+            step("public_holidays", serde_json::json!({ "upcoming_days": 7 }), "Check for holidays"),
+            step("llm_prompt", serde_json::json!({
+                "prompt": "Write a short, friendly briefing for {{weekday}}, {{today}}, in two or three sentences: \
+                    what the weather means for the day (a coat, an umbrella) and any public holiday this week.\n\n\
+                    {{step_1}}\n\n{{step_2}}",
+            }), "Write the briefing"),
+            step("notify", serde_json::json!({ "title": "Good morning", "message": "{{step_3}}" }), "Send it"),
+        ],
+    }
+}
+
+/// Templates that ship with the app; always available, catalog or not.
+fn builtin_templates() -> Vec<CatalogTemplate> {
+    vec![meetings::template(), downloads::template(), receipts::template(), morning_briefing()]
+}
+
+fn matches(template: &CatalogTemplate, query: &str) -> bool {
+    let query = query.to_lowercase();
+    query.split_whitespace().all(|word| {
+        template.name.to_lowercase().contains(word)
+            || template.description.to_lowercase().contains(word)
+            || template.tags.iter().any(|t| t.to_lowercase() == word)
+    })
+}
+
+// ─── Template Catalog Commands ───
+
+#[tauri::command]
+pub fn get_template_catalog_status(db: State<DbState>) -> Result<CatalogStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let fetched_at: Option<String> = conn.query_row(
+        "SELECT fetched_at FROM template_catalog_cache WHERE id = 1",
+        [],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    Ok(CatalogStatus {
+        enabled: enabled(&conn)?,
+        url: read_setting(&conn, URL_SETTING)?.unwrap_or_default(),
+        template_count: cached_templates(&conn).map(|t| t.len()).unwrap_or(0),
+        fetched_at: fetched_at.unwrap_or_default(),
+    })
+}
+
+/// Downloads the catalog index, checks its signature and replaces the local cache.
+/// Does nothing over the network unless the user has opted in.
+#[tauri::command]
+pub async fn sync_template_catalog(app: AppHandle) -> Result<CatalogStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (url, proxy) = {
+            let db = app.state::<DbState>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            if !enabled(&conn)? {
+                return Err("Turn on community templates in Settings first".to_string());
+            }
+            net::check_egress(&conn, "the template catalog", None, "", "")?;
+            let url = read_setting(&conn, URL_SETTING)?.filter(|u| !u.is_empty())
+                .ok_or("No template catalog address is configured")?;
+            (url, net::proxy(&conn)?)
+        };
+        if !url.starts_with("https://") {
+            return Err("The template catalog must be served over HTTPS".into());
+        }
+        let body = web::http_get(&proxy, &url)?;
+        let signature = web::http_get(&proxy, &format!("{url}.sig"))?;
+
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        verify(&conn, &body, &signature)?;
+        let index: CatalogIndex = serde_json::from_str(&body).map_err(|e| format!("The catalog index is malformed: {e}"))?;
+        let fetched_at = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT OR REPLACE INTO template_catalog_cache (id, url, body, signature, fetched_at) VALUES (1, ?1, ?2, ?3, ?4)",
+            params![url, body, signature, fetched_at],
+        ).map_err(|e| e.to_string())?;
+        Ok(CatalogStatus { enabled: true, url, template_count: index.templates.len(), fetched_at })
+    }).await.map_err(|e| e.to_string())?
+}
+
+/// Searches the built-in templates and the cached catalog by name, description and tags.
+/// Never touches the network.
+#[tauri::command]
+pub fn search_templates(db: State<DbState>, query: Option<String>) -> Result<Vec<CatalogTemplate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut templates = builtin_templates();
+    if enabled(&conn)? {
+        templates.extend(cached_templates(&conn)?);
+    }
+    let query = query.unwrap_or_default();
+    Ok(templates.into_iter().filter(|t| matches(t, &query)).collect())
+}
+
+/// Turns a built-in or catalog template into a draft for review; save it with `save_agent_draft`.
+/// Templates start in sandbox mode so they can be tried before touching anything real.
+#[tauri::command]
+pub fn install_template(db: State<DbState>, id: String) -> Result<AgentDraft, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let builtin = builtin_templates().into_iter().find(|t| t.id == id);
+    let is_builtin = builtin.is_some();
+    let template = match builtin {
+        Some(template) => template,
+        None if !enabled(&conn)? => return Err("Turn on community templates in Settings first".into()),
+        None => cached_templates(&conn)?.into_iter().find(|t| t.id == id)
+            .ok_or("Template not found; try syncing the catalog")?,
+    };
+    let note = match is_builtin {
+        true => "Built-in template; check the steps' paths before running it.".to_string(),
+        false => format!("Community template by {}.", if template.author.is_empty() { "an unknown author" } else { &template.author }),
+    };
+    Ok(builder::normalize_draft(AgentDraft {
+        name: template.name,
+        role: template.role,
+        goal: template.goal,
+        schedule: template.schedule,
+        tools: template.tools,
+        workflow: template.workflow,
+        permissions: Vec::new(),
+        sandbox: true,
+        notes: vec![note],
+        source: "template".into(),
+    }))
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::llm;
+use crate::DbState;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenCount {
+    pub tokens: usize,
+    /// False when the count comes from a characters-per-token estimate.
+    pub exact: bool,
+    /// e.g. `O200kBase` for OpenAI models, or `estimate`.
+    pub tokenizer: String,
+    pub model: String,
+    /// Set when counting for the active model.
+    pub context_window: Option<usize>,
+}
+
+/// OpenAI models are counted with their real BPE tokenizer. Other providers don't publish
+/// theirs, so they are estimated: Claude averages about 3.5 characters per token on English
+/// text, Llama-style local models about 4.
+fn measure(model: &str, text: &str) -> (usize, bool, String) {
+    if let Ok(bpe) = tiktoken_rs::bpe_for_model(model) {
+        let name = tiktoken_rs::tokenizer::get_tokenizer(model).map(|t| format!("{t:?}")).unwrap_or_default();
+        return (bpe.encode_with_special_tokens(text).len(), true, name);
+    }
+    let chars = text.chars().count();
+    let tokens = if model.starts_with("claude") { (chars * 2).div_ceil(7) } else { chars.div_ceil(4) };
+    (tokens, false, "estimate".into())
+}
+
+/// Tokens `text` takes up for `model`.
+pub fn count(model: &str, text: &str) -> usize {
+    measure(model, text).0
+}
+
+/// Counts tokens for `model`, or for the active model when none is given, so budgets can be
+/// shown and checked before anything is sent.
+#[tauri::command]
+pub fn count_tokens(db: State<DbState>, text: String, model: Option<String>) -> Result<TokenCount, String> {
+    let (model, context_window) = match model.filter(|m| !m.is_empty()) {
+        Some(model) => (model, None),
+        None => {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let cfg = llm::provider_config(&conn)?;
+            let window = llm::context_window(&conn, &cfg)?;
+            (cfg.model, Some(window))
+        }
+    };
+    let (tokens, exact, tokenizer) = measure(&model, &text);
+    Ok(TokenCount { tokens, exact, tokenizer, model, context_window })
+}
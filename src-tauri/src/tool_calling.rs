@@ -0,0 +1,137 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::executor::StepResult;
+use crate::injection;
+use crate::llm::{self, ChatMessage, LlmRequest, ToolCall, ToolDef};
+use crate::persona;
+use crate::policy;
+use crate::tools;
+use crate::vision;
+use crate::{DbState, update_agent_config};
+
+const SYSTEM: &str = "You are an automation agent working towards a goal on the user's computer. \
+Use the tools to do the work, one call at a time, and look at each result before deciding what to do next. \
+When the goal is done, or can't be done, reply with a short summary for the user instead of calling a tool.";
+const REPLY_TOKENS: u32 = 1024;
+/// Label of the steps the model chose, as opposed to ones from the agent's workflow.
+pub const CHOSEN_LABEL: &str = "Chosen by the model";
+/// Model-chosen steps in one run, so a model that never settles can't loop forever.
+pub const MAX_STEPS: usize = 12;
+/// Tool output handed back to the model is cut to this many characters.
+const RESULT_CHARS: usize = 8000;
+
+/// What the model wants to do after seeing the steps so far.
+pub enum Next {
+    Call { tool: String, params: Value },
+    Answer(String),
+}
+
+/// Whether the agent lets the model choose its own steps (`"tool_calling": true` in its config).
+pub fn enabled(config_json: &str) -> bool {
+    serde_json::from_str::<Value>(config_json).ok()
+        .and_then(|c| c.get("tool_calling").and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
+/// The tools listed on the agent, or `None` when it lists none of the registry's and so may
+/// use any of them.
+fn agent_tools(conn: &Connection, agent_id: &str) -> Result<Option<Vec<String>>, String> {
+    let listed: String = conn.query_row("SELECT tools FROM agents WHERE id = ?1", params![agent_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .unwrap_or_default();
+    // Agents store their tools as a JSON array or, from older forms, comma-separated.
+    let listed: Vec<String> = serde_json::from_str(&listed)
+        .unwrap_or_else(|_| listed.split(',').map(|t| t.trim().to_string()).collect());
+    Ok(tools::registry().iter().any(|t| listed.iter().any(|l| l == t.name)).then_some(listed))
+}
+
+/// The tool registry as provider tool definitions, limited to `allowed` when given. Model
+/// steps (`llm_prompt`, `critique`) are left out: the model doing the choosing is already one.
+pub fn definitions(allowed: Option<&[String]>) -> Vec<ToolDef> {
+    tools::registry().into_iter()
+        .filter(|t| t.permission != "llm")
+        .filter(|t| policy::tool_allowed(t.name))
+        .filter(|t| allowed.is_none_or(|allowed| allowed.iter().any(|a| a == t.name)))
+        .map(|t| ToolDef { name: t.name.into(), description: t.description.into(), parameters: t.params })
+        .collect()
+}
+
+/// What the model is told about a finished step.
+fn step_report(step: &StepResult) -> String {
+    let report = match step.status.as_str() {
+        "error" => format!("Error: {}", step.error),
+        "rejected" => "The user rejected this step.".to_string(),
+        "simulated" => format!("Simulated, nothing was changed: {}", step.output),
+        _ => step.output.clone(),
+    };
+    let report: String = report.chars().take(RESULT_CHARS).collect();
+    match injection::is_untrusted(&step.tool) {
+        true => injection::wrap(&step.tool, &report, step.injection.is_some()),
+        false => report,
+    }
+}
+
+/// Replays the run so far as a tool-calling conversation and asks the model for the next
+/// step. Ids are derived from step indexes so the conversation is the same on every replay.
+/// `allowed` overrides the agent's own tool list; `images` are attachments shown with the goal.
+pub fn next(
+    db: &DbState,
+    agent_id: &str,
+    run_id: &str,
+    goal: &str,
+    images: &[String],
+    allowed: Option<&[String]>,
+    done: &[StepResult],
+) -> Result<Next, String> {
+    let (tools, system, images) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let tools = match allowed {
+            Some(allowed) => definitions(Some(allowed)),
+            None => definitions(agent_tools(&conn, agent_id)?.as_deref()),
+        };
+        let system = persona::system_prompt(&conn, agent_id, &format!("{SYSTEM} {}", injection::SYSTEM_NOTE))?;
+        (tools, system, vision::load(&conn, images)?)
+    };
+    let mut messages = vec![ChatMessage::user_with_images(goal, images)];
+    for step in done {
+        let id = format!("call_{}", step.index);
+        messages.push(ChatMessage::tool_call("", ToolCall { id: id.clone(), name: step.tool.clone(), arguments: step.params.clone() }));
+        messages.push(ChatMessage::tool_result(id, step_report(step)));
+    }
+    let resp = llm::complete(db, &LlmRequest {
+        system,
+        messages,
+        max_tokens: REPLY_TOKENS,
+        tools: tools.clone(),
+        agent_id: agent_id.to_string(),
+        run_id: run_id.to_string(),
+        ..Default::default()
+    })?;
+    let Some(call) = resp.tool_calls.into_iter().next() else {
+        return Ok(Next::Answer(resp.text));
+    };
+    if !tools.iter().any(|t| t.name == call.name) {
+        return Err(format!("The model asked for a tool this agent can't use: {}", call.name));
+    }
+    let params = if call.arguments.is_object() { call.arguments } else { json!({}) };
+    Ok(Next::Call { tool: call.name, params })
+}
+
+// ─── Tool Calling Commands ───
+
+/// Lets the model choose an agent's steps from its tools. An agent with a workflow runs it
+/// first and the model carries on from its results.
+#[tauri::command]
+pub fn set_agent_tool_calling(db: State<DbState>, agent_id: String, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| {
+        if enabled {
+            config.insert("tool_calling".into(), json!(true));
+        } else {
+            config.remove("tool_calling");
+        }
+    })
+}
@@ -0,0 +1,499 @@
+//! Arithmetic and unit conversion done exactly, so the numbers in a run never depend on
+//! the model doing sums in its head.
+
+/// Digits after the point kept in results; trailing zeros are dropped.
+const MAX_DECIMALS: usize = 10;
+/// Nesting beyond this is refused rather than risking the stack.
+const MAX_DEPTH: usize = 64;
+
+/// `value` without float noise: rounded to `MAX_DECIMALS` places, trailing zeros dropped.
+pub fn format_number(value: f64) -> String {
+    if value.abs() >= 1e15 || (value != 0.0 && value.abs() < 1e-9) {
+        return format!("{value:e}");
+    }
+    let text = format!("{value:.MAX_DECIMALS$}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".into() } else { text.to_string() }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Word(String),
+    Op(char),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            // An exponent, as in 1.5e3 or 2E-4.
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                let sign = usize::from(chars.get(i + 1).is_some_and(|c| matches!(c, '+' | '-')));
+                if chars.get(i + 1 + sign).is_some_and(char::is_ascii_digit) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            let number = text.parse().map_err(|_| format!("\"{text}\" isn't a number"))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect::<String>().to_lowercase()));
+        } else {
+            let op = match c {
+                '×' | '·' => '*',
+                '÷' => '/',
+                '−' => '-',
+                '+' | '-' | '*' | '/' | '^' | '%' | '(' | ')' | ',' => c,
+                other => return Err(format!("\"{other}\" can't be used in a calculation")),
+            };
+            // `**` is a power, as in most programming languages.
+            if op == '*' && chars.get(i + 1) == Some(&'*') {
+                tokens.push(Token::Op('^'));
+                i += 2;
+                continue;
+            }
+            tokens.push(Token::Op(op));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the usual precedence: `+ -`, then `* / mod`, then unary signs,
+/// then `^` (right to left), then a postfix `%`.
+struct Evaluator {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Evaluator {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("The calculation is nested too deeply".into());
+        }
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                break;
+            }
+        }
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') || self.eat_word("of") || self.eat_word("x") {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".into());
+                }
+                value /= divisor;
+            } else if self.eat_word("mod") {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".into());
+                }
+                value = value.rem_euclid(divisor);
+            } else {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.percent()?;
+        if self.eat('^') {
+            let exponent = self.unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    fn percent(&mut self) -> Result<f64, String> {
+        let mut value = self.atom()?;
+        while self.eat('%') {
+            value /= 100.0;
+        }
+        Ok(value)
+    }
+
+    fn arguments(&mut self, name: &str) -> Result<Vec<f64>, String> {
+        if !self.eat('(') {
+            return Err(format!("{name} needs its arguments in parentheses, as in {name}(2)"));
+        }
+        let mut args = vec![self.sum()?];
+        while self.eat(',') {
+            args.push(self.sum()?);
+        }
+        if !self.eat(')') {
+            return Err(format!("A \")\" is missing after the arguments of {name}"));
+        }
+        Ok(args)
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.peek().cloned() {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(n)
+            }
+            Some(Token::Op('(')) => {
+                self.pos += 1;
+                let value = self.sum()?;
+                if !self.eat(')') {
+                    return Err("A \")\" is missing".into());
+                }
+                Ok(value)
+            }
+            Some(Token::Word(word)) => {
+                self.pos += 1;
+                match word.as_str() {
+                    "pi" | "π" => return Ok(std::f64::consts::PI),
+                    "e" => return Ok(std::f64::consts::E),
+                    _ => {}
+                }
+                let args = self.arguments(&word)?;
+                let one = |f: fn(f64) -> f64| match args.as_slice() {
+                    [x] => Ok(f(*x)),
+                    _ => Err(format!("{word} takes one number")),
+                };
+                match word.as_str() {
+                    "sqrt" if args.first().is_some_and(|x| *x < 0.0) => Err("The square root of a negative number isn't a real number".into()),
+                    "sqrt" => one(f64::sqrt),
+                    "abs" => one(f64::abs),
+                    "floor" => one(f64::floor),
+                    "ceil" => one(f64::ceil),
+                    "exp" => one(f64::exp),
+                    "ln" | "log" | "log10" if args.first().is_some_and(|x| *x <= 0.0) => Err("Logarithms are only defined above zero".into()),
+                    "ln" => one(f64::ln),
+                    "log" | "log10" => one(f64::log10),
+                    "sin" => one(f64::sin),
+                    "cos" => one(f64::cos),
+                    "tan" => one(f64::tan),
+                    "round" => match args.as_slice() {
+                        [x] => Ok(x.round()),
+                        [x, places] => {
+                            let scale = 10f64.powi(*places as i32);
+                            Ok((x * scale).round() / scale)
+                        }
+                        _ => Err("round takes a number and, optionally, how many decimals to keep".into()),
+                    },
+                    "min" => Ok(args.iter().copied().fold(f64::INFINITY, f64::min)),
+                    "max" => Ok(args.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+                    "sum" => Ok(args.iter().sum()),
+                    "avg" | "average" | "mean" => Ok(args.iter().sum::<f64>() / args.len() as f64),
+                    _ => Err(format!("\"{word}\" isn't a function the calculator knows")),
+                }
+            }
+            Some(Token::Op(op)) => Err(format!("\"{op}\" is out of place")),
+            None => Err("The calculation ends too early".into()),
+        }
+    }
+}
+
+/// Evaluates an arithmetic expression: `+ - * / ^`, parentheses, `mod`, `15%` for 0.15 (so
+/// `15% of 80` is 12), `pi`, `e`, and sqrt, abs, round(x, decimals), floor, ceil, min, max,
+/// sum, avg, exp, ln, log, sin, cos and tan.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("There is nothing to calculate".into());
+    }
+    let mut evaluator = Evaluator { tokens, pos: 0, depth: 0 };
+    let value = evaluator.sum()?;
+    if let Some(extra) = evaluator.peek() {
+        let extra = match extra {
+            Token::Number(n) => format_number(*n),
+            Token::Word(w) => w.clone(),
+            Token::Op(op) => op.to_string(),
+        };
+        return Err(format!("\"{extra}\" is out of place"));
+    }
+    if !value.is_finite() {
+        return Err("The result is too large to show".into());
+    }
+    Ok(value)
+}
+
+// ─── Units ───
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Length,
+    Mass,
+    Volume,
+    Area,
+    Speed,
+    Time,
+    Data,
+    Energy,
+    Temperature,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Length => "a length",
+            Kind::Mass => "a weight",
+            Kind::Volume => "a volume",
+            Kind::Area => "an area",
+            Kind::Speed => "a speed",
+            Kind::Time => "a time",
+            Kind::Data => "a data size",
+            Kind::Energy => "an energy",
+            Kind::Temperature => "a temperature",
+        }
+    }
+}
+
+/// Names a unit goes by, what it measures, and how many of the kind's base unit (metre,
+/// kilogram, litre, square metre, metre per second, second, byte, joule) one of it is.
+/// Temperatures are converted by formula; their factor is unused.
+const UNITS: &[(&[&str], Kind, f64)] = &[
+    (&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], Kind::Length, 0.001),
+    (&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], Kind::Length, 0.01),
+    (&["m", "meter", "meters", "metre", "metres"], Kind::Length, 1.0),
+    (&["km", "kilometer", "kilometers", "kilometre", "kilometres"], Kind::Length, 1000.0),
+    (&["in", "inch", "inches", "\""], Kind::Length, 0.0254),
+    (&["ft", "foot", "feet", "'"], Kind::Length, 0.3048),
+    (&["yd", "yard", "yards"], Kind::Length, 0.9144),
+    (&["mi", "mile", "miles"], Kind::Length, 1609.344),
+    (&["nmi", "nautical mile", "nautical miles"], Kind::Length, 1852.0),
+    (&["mg", "milligram", "milligrams"], Kind::Mass, 1e-6),
+    (&["g", "gram", "grams", "gramme", "grammes"], Kind::Mass, 0.001),
+    (&["kg", "kilo", "kilos", "kilogram", "kilograms"], Kind::Mass, 1.0),
+    (&["t", "tonne", "tonnes", "metric ton", "metric tons"], Kind::Mass, 1000.0),
+    (&["oz", "ounce", "ounces"], Kind::Mass, 0.028_349_523_125),
+    (&["lb", "lbs", "pound", "pounds"], Kind::Mass, 0.453_592_37),
+    (&["st", "stone", "stones"], Kind::Mass, 6.350_293_18),
+    (&["ml", "milliliter", "milliliters", "millilitre", "millilitres"], Kind::Volume, 0.001),
+    (&["cl", "centiliter", "centiliters", "centilitre", "centilitres"], Kind::Volume, 0.01),
+    (&["dl", "deciliter", "deciliters", "decilitre", "decilitres"], Kind::Volume, 0.1),
+    (&["l", "liter", "liters", "litre", "litres"], Kind::Volume, 1.0),
+    (&["m3", "m³", "cubic meter", "cubic meters", "cubic metre", "cubic metres"], Kind::Volume, 1000.0),
+    (&["tsp", "teaspoon", "teaspoons"], Kind::Volume, 0.004_928_921_593_75),
+    (&["tbsp", "tablespoon", "tablespoons"], Kind::Volume, 0.014_786_764_781_25),
+    (&["fl oz", "floz", "fluid ounce", "fluid ounces"], Kind::Volume, 0.029_573_529_562_5),
+    (&["cup", "cups"], Kind::Volume, 0.236_588_236_5),
+    (&["pt", "pint", "pints"], Kind::Volume, 0.473_176_473),
+    (&["qt", "quart", "quarts"], Kind::Volume, 0.946_352_946),
+    (&["gal", "gallon", "gallons", "us gallon", "us gallons"], Kind::Volume, 3.785_411_784),
+    (&["imperial gallon", "imperial gallons", "uk gallon", "uk gallons"], Kind::Volume, 4.546_09),
+    (&["mm2", "mm²", "square millimeter", "square millimeters", "square millimetre", "square millimetres"], Kind::Area, 1e-6),
+    (&["cm2", "cm²", "square centimeter", "square centimeters", "square centimetre", "square centimetres"], Kind::Area, 1e-4),
+    (&["m2", "m²", "sqm", "square meter", "square meters", "square metre", "square metres"], Kind::Area, 1.0),
+    (&["km2", "km²", "square kilometer", "square kilometers", "square kilometre", "square kilometres"], Kind::Area, 1e6),
+    (&["ha", "hectare", "hectares"], Kind::Area, 1e4),
+    (&["acre", "acres"], Kind::Area, 4_046.856_422_4),
+    (&["in2", "in²", "square inch", "square inches"], Kind::Area, 0.000_645_16),
+    (&["ft2", "ft²", "sqft", "square foot", "square feet"], Kind::Area, 0.092_903_04),
+    (&["mi2", "mi²", "square mile", "square miles"], Kind::Area, 2_589_988.110_336),
+    (&["m/s", "meters per second", "metres per second"], Kind::Speed, 1.0),
+    (&["km/h", "kmh", "kph", "kilometers per hour", "kilometres per hour"], Kind::Speed, 1.0 / 3.6),
+    (&["mph", "miles per hour"], Kind::Speed, 0.447_04),
+    (&["kn", "kt", "knot", "knots"], Kind::Speed, 1852.0 / 3600.0),
+    (&["ft/s", "feet per second"], Kind::Speed, 0.3048),
+    (&["ms", "millisecond", "milliseconds"], Kind::Time, 0.001),
+    (&["s", "sec", "secs", "second", "seconds"], Kind::Time, 1.0),
+    (&["min", "mins", "minute", "minutes"], Kind::Time, 60.0),
+    (&["h", "hr", "hrs", "hour", "hours"], Kind::Time, 3600.0),
+    (&["d", "day", "days"], Kind::Time, 86_400.0),
+    (&["wk", "week", "weeks"], Kind::Time, 604_800.0),
+    (&["bit", "bits"], Kind::Data, 0.125),
+    (&["b", "byte", "bytes"], Kind::Data, 1.0),
+    (&["kb", "kilobyte", "kilobytes"], Kind::Data, 1e3),
+    (&["mb", "megabyte", "megabytes"], Kind::Data, 1e6),
+    (&["gb", "gigabyte", "gigabytes"], Kind::Data, 1e9),
+    (&["tb", "terabyte", "terabytes"], Kind::Data, 1e12),
+    (&["kib", "kibibyte", "kibibytes"], Kind::Data, 1024.0),
+    (&["mib", "mebibyte", "mebibytes"], Kind::Data, 1_048_576.0),
+    (&["gib", "gibibyte", "gibibytes"], Kind::Data, 1_073_741_824.0),
+    (&["tib", "tebibyte", "tebibytes"], Kind::Data, 1_099_511_627_776.0),
+    (&["j", "joule", "joules"], Kind::Energy, 1.0),
+    (&["kj", "kilojoule", "kilojoules"], Kind::Energy, 1000.0),
+    (&["cal", "calorie", "calories"], Kind::Energy, 4.184),
+    (&["kcal", "kilocalorie", "kilocalories"], Kind::Energy, 4184.0),
+    (&["wh", "watt hour", "watt hours"], Kind::Energy, 3600.0),
+    (&["kwh", "kilowatt hour", "kilowatt hours"], Kind::Energy, 3.6e6),
+    (&["c", "°c", "celsius", "degrees celsius", "centigrade"], Kind::Temperature, 0.0),
+    (&["f", "°f", "fahrenheit", "degrees fahrenheit"], Kind::Temperature, 0.0),
+    (&["k", "kelvin", "kelvins"], Kind::Temperature, 0.0),
+];
+
+fn unit(name: &str) -> Option<(&'static str, Kind, f64)> {
+    let name = name.trim().to_lowercase();
+    UNITS.iter()
+        .find(|(names, ..)| names.contains(&name.as_str()))
+        .map(|(names, kind, factor)| (names[0], *kind, *factor))
+}
+
+fn to_kelvin(unit: &str, value: f64) -> f64 {
+    match unit {
+        "c" => value + 273.15,
+        "f" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    }
+}
+
+fn from_kelvin(unit: &str, kelvin: f64) -> f64 {
+    match unit {
+        "c" => kelvin - 273.15,
+        "f" => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => kelvin,
+    }
+}
+
+/// Whether `name` is a unit `convert_units` knows.
+pub fn is_unit(name: &str) -> bool {
+    unit(name).is_some()
+}
+
+/// `value` in `from` expressed in `to`, both units of the same kind.
+pub fn convert_units(value: f64, from: &str, to: &str) -> Result<f64, String> {
+    let (from_unit, from_kind, from_factor) = unit(from).ok_or_else(|| format!("\"{from}\" isn't a unit or currency the converter knows"))?;
+    let (to_unit, to_kind, to_factor) = unit(to).ok_or_else(|| format!("\"{to}\" isn't a unit or currency the converter knows"))?;
+    if from_kind != to_kind {
+        return Err(format!("{from} is {} and {to} is {}; they can't be converted", from_kind.name(), to_kind.name()));
+    }
+    if from_kind == Kind::Temperature {
+        let kelvin = to_kelvin(from_unit, value);
+        if kelvin < 0.0 {
+            return Err(format!("{} {from} is below absolute zero", format_number(value)));
+        }
+        return Ok(from_kelvin(to_unit, kelvin));
+    }
+    Ok(value * from_factor / to_factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(expression: &str) -> f64 {
+        evaluate(expression).unwrap_or_else(|e| panic!("{expression}: {e}"))
+    }
+
+    fn wrong(expression: &str) -> String {
+        evaluate(expression).err().unwrap_or_else(|| panic!("{expression} should fail"))
+    }
+
+    #[test]
+    fn follows_precedence() {
+        assert_eq!(calc("2 + 3 * 4"), 14.0);
+        assert_eq!(calc("2 * 3 + 4"), 10.0);
+        assert_eq!(calc("10 - 2 - 3"), 5.0);
+        assert_eq!(calc("48 / 4 / 2"), 6.0);
+        assert_eq!(calc("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(calc("2 ** 3 * 2"), 16.0);
+        assert_eq!(calc("1 + 7 mod 3"), 2.0);
+        assert_eq!(calc("15% of 80"), 12.0);
+        assert_eq!(calc("3 × 4 ÷ 2 − 1"), 5.0);
+    }
+
+    #[test]
+    fn applies_unary_signs() {
+        assert_eq!(calc("-3 + 5"), 2.0);
+        assert_eq!(calc("3 * -2"), -6.0);
+        assert_eq!(calc("--3"), 3.0);
+        assert_eq!(calc("+-3"), -3.0);
+        assert_eq!(calc("-2 ^ 2"), -4.0);
+        assert_eq!(calc("2 ^ -1"), 0.5);
+        assert_eq!(calc("-7 mod 3"), 2.0);
+    }
+
+    #[test]
+    fn groups_with_parentheses() {
+        assert_eq!(calc("(2 + 3) * 4"), 20.0);
+        assert_eq!(calc("-(2 + 3)"), -5.0);
+        assert_eq!(calc("((1 + 1) * (2 + 2)) ^ 2"), 64.0);
+        assert_eq!(calc("round(sqrt(2) * 100, 1)"), 141.4);
+        assert_eq!(calc("max(1, (2 + 5) / 7, 3)"), 3.0);
+        let deep = format!("{}1{}", "(".repeat(MAX_DEPTH + 1), ")".repeat(MAX_DEPTH + 1));
+        assert_eq!(wrong(&deep), "The calculation is nested too deeply");
+    }
+
+    #[test]
+    fn refuses_division_by_zero() {
+        assert_eq!(wrong("1 / 0"), "Division by zero");
+        assert_eq!(wrong("1 / (2 - 2)"), "Division by zero");
+        assert_eq!(wrong("5 mod 0"), "Division by zero");
+        assert_eq!(wrong("10 ^ 400"), "The result is too large to show");
+    }
+
+    #[test]
+    fn explains_malformed_input() {
+        assert_eq!(wrong(""), "There is nothing to calculate");
+        assert_eq!(wrong("   "), "There is nothing to calculate");
+        assert_eq!(wrong("1 +"), "The calculation ends too early");
+        assert_eq!(wrong("(1 + 2"), "A \")\" is missing");
+        assert_eq!(wrong("1 + 2)"), "\")\" is out of place");
+        assert_eq!(wrong("1 2"), "\"2\" is out of place");
+        assert_eq!(wrong("* 3"), "\"*\" is out of place");
+        assert_eq!(wrong("2 $ 3"), "\"$\" can't be used in a calculation");
+        assert_eq!(wrong("1..2"), "\"1..2\" isn't a number");
+        assert_eq!(wrong("sqrt 4"), "sqrt needs its arguments in parentheses, as in sqrt(2)");
+        assert_eq!(wrong("max(1, 2"), "A \")\" is missing after the arguments of max");
+        assert_eq!(wrong("abs(1, 2)"), "abs takes one number");
+        assert_eq!(wrong("foo(1)"), "\"foo\" isn't a function the calculator knows");
+    }
+}
@@ -0,0 +1,302 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Manifests and folders with more files than this are refused rather than read for hours.
+const MAX_FILES: usize = 20_000;
+/// Problems listed by name before "…and N more".
+const PREVIEW_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    /// Only for checking files against published MD5 sums; it is too weak to prove anything else.
+    Md5,
+}
+
+impl Algorithm {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().replace(['-', '_'], "").as_str() {
+            "sha256" | "" => Ok(Self::Sha256),
+            "sha1" => Ok(Self::Sha1),
+            "md5" => Ok(Self::Md5),
+            other => Err(format!("Unknown checksum type {other}; use sha256, sha1 or md5")),
+        }
+    }
+
+    /// Guessed from the length of a hex checksum.
+    fn for_hex(hex: &str) -> Option<Self> {
+        match hex.len() {
+            64 => Some(Self::Sha256),
+            40 => Some(Self::Sha1),
+            32 => Some(Self::Md5),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Sha256 => "SHA-256",
+            Self::Sha1 => "SHA-1",
+            Self::Md5 => "MD5",
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha1(Sha1),
+    Md5(Md5),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Sha256 => Self::Sha256(Sha256::new()),
+            Algorithm::Sha1 => Self::Sha1(Sha1::new()),
+            Algorithm::Md5 => Self::Md5(Md5::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(h) => h.update(data),
+            Self::Sha1(h) => h.update(data),
+            Self::Md5(h) => h.update(data),
+        }
+    }
+
+    fn finish(self) -> String {
+        let bytes: Vec<u8> = match self {
+            Self::Sha256(h) => h.finalize().to_vec(),
+            Self::Sha1(h) => h.finalize().to_vec(),
+            Self::Md5(h) => h.finish().to_vec(),
+        };
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const MD5_TABLE: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// MD5 (RFC 1321), which the hashing crates in use don't provide.
+struct Md5 {
+    state: [u32; 4],
+    pending: Vec<u8>,
+    length: u64,
+}
+
+impl Md5 {
+    fn new() -> Self {
+        Self { state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476], pending: Vec::with_capacity(64), length: 0 }
+    }
+
+    fn block(&mut self, block: &[u8]) {
+        let words: Vec<u32> = block.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a.wrapping_add(f).wrapping_add(MD5_TABLE[i]).wrapping_add(words[g]).rotate_left(MD5_SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        if !self.pending.is_empty() {
+            let take = (64 - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() < 64 {
+                return;
+            }
+            let block = std::mem::take(&mut self.pending);
+            self.block(&block);
+        }
+        let mut chunks = data.chunks_exact(64);
+        for block in &mut chunks {
+            self.block(block);
+        }
+        self.pending.extend_from_slice(chunks.remainder());
+    }
+
+    fn finish(mut self) -> [u8; 16] {
+        let bits = self.length.wrapping_mul(8);
+        let mut tail = std::mem::take(&mut self.pending);
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        tail.extend_from_slice(&bits.to_le_bytes());
+        for block in tail.chunks(64) {
+            self.block(block);
+        }
+        let mut out = [0u8; 16];
+        for (chunk, word) in out.chunks_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+}
+
+pub fn hash_file(path: &Path, algorithm: Algorithm) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Can't open folder {}: {e}", dir.display()))?;
+    for entry in entries.flatten() {
+        let Ok(kind) = entry.file_type() else { continue };
+        if kind.is_file() {
+            out.push(entry.path());
+        } else if kind.is_dir() {
+            walk(&entry.path(), out)?;
+        }
+        if out.len() > MAX_FILES {
+            return Err(format!("{} has more than {MAX_FILES} files", dir.display()));
+        }
+    }
+    Ok(())
+}
+
+/// `a1b2…` from `a1b2…`, `sha256:a1b2…` or `SHA256 = A1B2…`.
+fn normalize(expected: &str) -> String {
+    let hex = expected.rsplit([':', '=', ' ']).next().unwrap_or_default();
+    hex.trim().to_lowercase()
+}
+
+/// The checksum of a file, or one line per file of a folder in the `sha256sum` layout, which
+/// saved as a file is a manifest `verify_manifest` can check later. With `expected`, the file
+/// must match it. The type follows `expected`'s length when not given.
+pub fn checksum(path: &Path, algorithm: Option<Algorithm>, expected: Option<&str>) -> Result<String, String> {
+    let expected = expected.map(normalize).filter(|e| !e.is_empty());
+    let algorithm = algorithm.or_else(|| expected.as_deref().and_then(Algorithm::for_hex)).unwrap_or(Algorithm::Sha256);
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if path.is_dir() {
+        if expected.is_some() {
+            return Err(format!("{} is a folder; only a file can be checked against one checksum", path.display()));
+        }
+        let mut files = Vec::new();
+        walk(path, &mut files)?;
+        files.sort();
+        let mut lines = Vec::with_capacity(files.len());
+        for file in &files {
+            let relative = file.strip_prefix(path).unwrap_or(file).to_string_lossy().replace('\\', "/");
+            lines.push(format!("{}  {relative}", hash_file(file, algorithm)?));
+        }
+        return Ok(lines.join("\n"));
+    }
+    let actual = hash_file(path, algorithm)?;
+    match expected {
+        None => Ok(format!("{actual}  {name}")),
+        Some(expected) if expected == actual => Ok(format!("{name} matches the expected {} {actual}", algorithm.label())),
+        Some(expected) => Err(format!(
+            "{name} doesn't match: the {} should be {expected} but is {actual}. The file may be damaged or not the one expected",
+            algorithm.label(),
+        )),
+    }
+}
+
+/// One line of a manifest: `hash  path`, `hash *path`, or `SHA256 (path) = hash`.
+fn parse_line(line: &str) -> Option<(Option<Algorithm>, String, String)> {
+    if let Some((tag, rest)) = line.split_once(" (") {
+        let (path, hash) = rest.rsplit_once(") = ")?;
+        return Some((Algorithm::parse(tag).ok(), path.to_string(), hash.trim().to_lowercase()));
+    }
+    let (hash, path) = line.split_once(char::is_whitespace)?;
+    let path = path.trim_start().trim_start_matches('*');
+    (!path.is_empty()).then(|| (None, path.to_string(), hash.to_lowercase()))
+}
+
+/// Checks every file a checksum manifest lists, relative to `folder` (the manifest's own
+/// folder by default). Fails, listing what is wrong, when any file is missing or changed,
+/// so the run's log shows the problem.
+pub fn verify_manifest(manifest: &Path, folder: Option<&Path>, algorithm: Option<Algorithm>) -> Result<String, String> {
+    let text = fs::read_to_string(manifest).map_err(|e| format!("Can't read {}: {e}", manifest.display()))?;
+    let base = folder.map(Path::to_path_buf)
+        .or_else(|| manifest.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let entries: Vec<_> = text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| parse_line(line).ok_or_else(|| format!("{} has a line that isn't a checksum: {line}", manifest.display())))
+        .collect::<Result<_, _>>()?;
+    if entries.is_empty() {
+        return Err(format!("{} lists no files", manifest.display()));
+    }
+    if entries.len() > MAX_FILES {
+        return Err(format!("{} lists more than {MAX_FILES} files", manifest.display()));
+    }
+
+    let (mut changed, mut missing) = (Vec::new(), Vec::new());
+    let mut labels: Vec<&str> = Vec::new();
+    for (tagged, path, expected) in &entries {
+        let Some(algorithm) = algorithm.or(*tagged).or_else(|| Algorithm::for_hex(expected)) else {
+            return Err(format!("Can't tell what kind of checksum {expected} is; pass the algorithm"));
+        };
+        if !labels.contains(&algorithm.label()) {
+            labels.push(algorithm.label());
+        }
+        let file = base.join(path);
+        if !file.is_file() {
+            missing.push(path.as_str());
+        } else if hash_file(&file, algorithm)? != *expected {
+            changed.push(path.as_str());
+        }
+    }
+    let total = entries.len();
+    if changed.is_empty() && missing.is_empty() {
+        return Ok(format!("All {total} file(s) in {} are intact ({})", manifest.display(), labels.join(", ")));
+    }
+    let mut lines = vec![format!(
+        "{} of {total} file(s) in {} failed the check: {} changed, {} missing",
+        changed.len() + missing.len(), manifest.display(), changed.len(), missing.len(),
+    )];
+    for (kind, paths) in [("changed", &changed), ("missing", &missing)] {
+        lines.extend(paths.iter().take(PREVIEW_LINES).map(|p| format!("  {kind}: {p}")));
+        if paths.len() > PREVIEW_LINES {
+            lines.push(format!("  …and {} more {kind}", paths.len() - PREVIEW_LINES));
+        }
+    }
+    Err(lines.join("\n"))
+}
@@ -0,0 +1,1210 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aes::{Aes128, Aes192, Aes256};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use flate2::Compression;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use sha1::Sha1;
+
+use super::fileops;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// More entries than this is a mistake or a zip bomb, not a folder of invoices.
+const MAX_ENTRIES: usize = 10_000;
+/// Total size of the files going in or coming out. Also keeps zip offsets under 4 GB.
+const MAX_TOTAL_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// Fixed by the WinZip AES format, which 7-Zip, WinZip and most unzip tools read.
+const AES_ITERATIONS: u32 = 1000;
+const AES_MAC_LEN: usize = 10;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL: u32 = 0x0605_4b50;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+const METHOD_AES: u16 = 99;
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_STRONG: u16 = 1 << 6;
+const FLAG_UTF8: u16 = 1 << 11;
+const AES_EXTRA: u16 = 0x9901;
+
+enum Format {
+    Zip,
+    TarGz,
+}
+
+fn format(path: &Path) -> Result<Format, String> {
+    let name = fileops::name(path).to_lowercase();
+    if name.ends_with(".zip") {
+        Ok(Format::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(Format::TarGz)
+    } else {
+        Err(format!("{} isn't a .zip, .tar.gz or .tgz archive", path.display()))
+    }
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{b} bytes"),
+    }
+}
+
+fn io_error(path: &Path) -> impl Fn(io::Error) -> String + '_ {
+    move |e| format!("{}: {e}", path.display())
+}
+
+/// Copies at most `limit` bytes, failing rather than truncating when there are more.
+fn copy_limited(from: &mut dyn Read, to: &mut dyn Write, limit: u64, crc: &mut crc32fast::Hasher) -> Result<u64, String> {
+    let mut buffer = [0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let n = from.read(&mut buffer).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Ok(total);
+        }
+        total += n as u64;
+        if total > limit {
+            return Err(format!("Unpacking would take more than {}", size(MAX_TOTAL_BYTES)));
+        }
+        crc.update(&buffer[..n]);
+        to.write_all(&buffer[..n]).map_err(|e| e.to_string())?;
+    }
+}
+
+// ─── Packing ───
+
+/// A file or folder to pack and its name inside the archive.
+struct Input {
+    path: PathBuf,
+    name: String,
+    dir: bool,
+    size: u64,
+    modified: SystemTime,
+}
+
+fn collect(paths: &[PathBuf]) -> Result<Vec<Input>, String> {
+    fn add(path: &Path, name: String, out: &mut Vec<Input>, total: &mut u64) -> Result<(), String> {
+        let meta = fs::metadata(path).map_err(|e| format!("Can't find {}: {e}", path.display()))?;
+        let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+        if meta.is_dir() {
+            out.push(Input { path: path.to_path_buf(), name: name.clone(), dir: true, size: 0, modified });
+            let entries = fs::read_dir(path).map_err(|e| format!("Can't open folder {}: {e}", path.display()))?;
+            // Links are left out so a folder can't pull in files from elsewhere.
+            let mut children: Vec<PathBuf> = entries.flatten()
+                .filter(|e| e.file_type().is_ok_and(|t| !t.is_symlink()))
+                .map(|e| e.path())
+                .collect();
+            children.sort();
+            for child in children {
+                add(&child, format!("{name}/{}", fileops::name(&child)), out, total)?;
+            }
+        } else {
+            *total += meta.len();
+            out.push(Input { path: path.to_path_buf(), name, dir: false, size: meta.len(), modified });
+        }
+        if out.len() > MAX_ENTRIES {
+            return Err(format!("There are more than {MAX_ENTRIES} files to pack"));
+        }
+        if *total > MAX_TOTAL_BYTES {
+            return Err(format!("The files add up to more than {}", size(MAX_TOTAL_BYTES)));
+        }
+        Ok(())
+    }
+    let mut out = Vec::new();
+    let mut total = 0;
+    let mut names: Vec<String> = Vec::new();
+    for path in paths {
+        let name = fileops::name(path);
+        if name.is_empty() {
+            return Err(format!("Can't pack {}", path.display()));
+        }
+        if names.contains(&name) {
+            return Err(format!("Two of the items are called {name}"));
+        }
+        names.push(name.clone());
+        add(path, name, &mut out, &mut total)?;
+    }
+    if out.is_empty() {
+        return Err("Nothing to pack".into());
+    }
+    Ok(out)
+}
+
+fn dos_time(time: SystemTime) -> (u16, u16) {
+    let t: DateTime<Local> = time.into();
+    let year = t.year().clamp(1980, 2107) as u16;
+    let clock = (t.hour() << 11 | t.minute() << 5 | (t.second() / 2)) as u16;
+    let date = (year - 1980) << 9 | (t.month() as u16) << 5 | t.day() as u16;
+    (clock, date)
+}
+
+/// Encryption key, authentication key and password check for WinZip AES.
+fn aes_keys(password: &str, salt: &[u8], key_len: usize) -> Vec<u8> {
+    let mut keys = vec![0u8; key_len * 2 + 2];
+    pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, AES_ITERATIONS, &mut keys);
+    keys
+}
+
+enum AesCtr {
+    Aes128(ctr::Ctr128LE<Aes128>),
+    Aes192(ctr::Ctr128LE<Aes192>),
+    Aes256(ctr::Ctr128LE<Aes256>),
+}
+
+impl AesCtr {
+    /// WinZip AES counts blocks little-endian from 1.
+    fn new(key: &[u8]) -> Self {
+        let iv = 1u128.to_le_bytes();
+        match key.len() {
+            16 => Self::Aes128(ctr::Ctr128LE::new(key.into(), &iv.into())),
+            24 => Self::Aes192(ctr::Ctr128LE::new(key.into(), &iv.into())),
+            _ => Self::Aes256(ctr::Ctr128LE::new(key.into(), &iv.into())),
+        }
+    }
+
+    fn apply(&mut self, data: &mut [u8]) {
+        match self {
+            Self::Aes128(c) => c.apply_keystream(data),
+            Self::Aes192(c) => c.apply_keystream(data),
+            Self::Aes256(c) => c.apply_keystream(data),
+        }
+    }
+}
+
+/// Encrypts compressed data on its way into the archive and authenticates it.
+struct AesWriter<W: Write> {
+    inner: W,
+    cipher: AesCtr,
+    mac: HmacSha1,
+}
+
+impl<W: Write> Write for AesWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = buf.to_vec();
+        self.cipher.apply(&mut data);
+        self.mac.update(&data);
+        self.inner.write_all(&data)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct ZipEntry {
+    name: String,
+    flags: u16,
+    method: u16,
+    time: u16,
+    date: u16,
+    crc: u32,
+    compressed: u64,
+    size: u64,
+    offset: u64,
+    extra: Vec<u8>,
+    dir: bool,
+}
+
+fn put16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn version_needed(entry: &ZipEntry) -> u16 {
+    if entry.method == METHOD_AES { 51 } else { 20 }
+}
+
+fn local_header(entry: &ZipEntry) -> Vec<u8> {
+    let mut out = Vec::with_capacity(30 + entry.name.len() + entry.extra.len());
+    put32(&mut out, LOCAL_HEADER);
+    put16(&mut out, version_needed(entry));
+    put16(&mut out, entry.flags);
+    put16(&mut out, entry.method);
+    put16(&mut out, entry.time);
+    put16(&mut out, entry.date);
+    put32(&mut out, entry.crc);
+    put32(&mut out, entry.compressed as u32);
+    put32(&mut out, entry.size as u32);
+    put16(&mut out, entry.name.len() as u16);
+    put16(&mut out, entry.extra.len() as u16);
+    out.extend_from_slice(entry.name.as_bytes());
+    out.extend_from_slice(&entry.extra);
+    out
+}
+
+fn central_header(entry: &ZipEntry) -> Vec<u8> {
+    let mut out = Vec::with_capacity(46 + entry.name.len() + entry.extra.len());
+    put32(&mut out, CENTRAL_HEADER);
+    put16(&mut out, 20);
+    put16(&mut out, version_needed(entry));
+    put16(&mut out, entry.flags);
+    put16(&mut out, entry.method);
+    put16(&mut out, entry.time);
+    put16(&mut out, entry.date);
+    put32(&mut out, entry.crc);
+    put32(&mut out, entry.compressed as u32);
+    put32(&mut out, entry.size as u32);
+    put16(&mut out, entry.name.len() as u16);
+    put16(&mut out, entry.extra.len() as u16);
+    put16(&mut out, 0);
+    put16(&mut out, 0);
+    put16(&mut out, 0);
+    put32(&mut out, if entry.dir { 0x10 } else { 0 });
+    put32(&mut out, entry.offset as u32);
+    out.extend_from_slice(entry.name.as_bytes());
+    out.extend_from_slice(&entry.extra);
+    out
+}
+
+/// AE-2 with AES-256: no CRC is stored, the HMAC checks the data instead.
+fn aes_extra() -> Vec<u8> {
+    let mut out = Vec::with_capacity(11);
+    put16(&mut out, AES_EXTRA);
+    put16(&mut out, 7);
+    put16(&mut out, 2);
+    out.extend_from_slice(b"AE");
+    out.push(3);
+    put16(&mut out, METHOD_DEFLATE);
+    out
+}
+
+fn write_zip(inputs: &[Input], out: &mut BufWriter<File>, password: Option<&str>) -> Result<(), String> {
+    let err = |e: io::Error| e.to_string();
+    let mut central = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let (time, date) = dos_time(input.modified);
+        let mut entry = ZipEntry {
+            name: if input.dir { format!("{}/", input.name) } else { input.name.clone() },
+            flags: FLAG_UTF8,
+            method: METHOD_STORED,
+            time,
+            date,
+            crc: 0,
+            compressed: 0,
+            size: 0,
+            offset: out.stream_position().map_err(err)?,
+            extra: Vec::new(),
+            dir: input.dir,
+        };
+        if !input.dir {
+            entry.method = METHOD_DEFLATE;
+            if password.is_some() {
+                entry.flags |= FLAG_ENCRYPTED;
+                entry.method = METHOD_AES;
+                entry.extra = aes_extra();
+            }
+        }
+        out.write_all(&local_header(&entry)).map_err(err)?;
+        if !input.dir {
+            let start = out.stream_position().map_err(err)?;
+            let mut source = BufReader::new(File::open(&input.path).map_err(io_error(&input.path))?);
+            let mut crc = crc32fast::Hasher::new();
+            entry.size = match password {
+                None => {
+                    let mut encoder = DeflateEncoder::new(&mut *out, Compression::default());
+                    let n = copy_limited(&mut source, &mut encoder, MAX_TOTAL_BYTES, &mut crc)?;
+                    encoder.finish().map_err(err)?;
+                    n
+                }
+                Some(password) => {
+                    let mut salt = [0u8; 16];
+                    OsRng.fill_bytes(&mut salt);
+                    let keys = aes_keys(password, &salt, 32);
+                    out.write_all(&salt).map_err(err)?;
+                    out.write_all(&keys[64..]).map_err(err)?;
+                    let mut sink = AesWriter {
+                        inner: &mut *out,
+                        cipher: AesCtr::new(&keys[..32]),
+                        mac: HmacSha1::new_from_slice(&keys[32..64]).map_err(|e| e.to_string())?,
+                    };
+                    let mut encoder = DeflateEncoder::new(&mut sink, Compression::default());
+                    let n = copy_limited(&mut source, &mut encoder, MAX_TOTAL_BYTES, &mut crc)?;
+                    encoder.finish().map_err(err)?;
+                    let tag = sink.mac.finalize().into_bytes();
+                    out.write_all(&tag[..AES_MAC_LEN]).map_err(err)?;
+                    n
+                }
+            };
+            if password.is_none() {
+                entry.crc = crc.finalize();
+            }
+            let end = out.stream_position().map_err(err)?;
+            entry.compressed = end - start;
+            out.seek(SeekFrom::Start(entry.offset)).map_err(err)?;
+            out.write_all(&local_header(&entry)).map_err(err)?;
+            out.seek(SeekFrom::Start(end)).map_err(err)?;
+        }
+        central.push(entry);
+    }
+    let directory_start = out.stream_position().map_err(err)?;
+    for entry in &central {
+        out.write_all(&central_header(entry)).map_err(err)?;
+    }
+    let directory_end = out.stream_position().map_err(err)?;
+    let mut end = Vec::with_capacity(22);
+    put32(&mut end, END_OF_CENTRAL);
+    put16(&mut end, 0);
+    put16(&mut end, 0);
+    put16(&mut end, central.len() as u16);
+    put16(&mut end, central.len() as u16);
+    put32(&mut end, (directory_end - directory_start) as u32);
+    put32(&mut end, directory_start as u32);
+    put16(&mut end, 0);
+    out.write_all(&end).map_err(err)?;
+    out.flush().map_err(err)
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    let len = field.len();
+    field[..len - 1].copy_from_slice(&digits.as_bytes()[digits.len() - (len - 1)..]);
+    field[len - 1] = 0;
+}
+
+fn tar_header(name: &[u8], prefix: &[u8], size: u64, modified: SystemTime, kind: u8) -> [u8; 512] {
+    let mut header = [0u8; 512];
+    header[..name.len().min(100)].copy_from_slice(&name[..name.len().min(100)]);
+    octal(&mut header[100..108], if kind == b'5' { 0o755 } else { 0o644 });
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], modified.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+    header[148..156].fill(b' ');
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix);
+    let checksum: u64 = header.iter().map(|b| *b as u64).sum();
+    octal(&mut header[148..155], checksum);
+    header
+}
+
+/// The header blocks for one entry. Names over 100 bytes go in the 155-byte prefix when
+/// they split at a slash, and in a GNU long name entry before it otherwise.
+fn tar_entry(name: &str, size: u64, modified: SystemTime, dir: bool) -> Vec<u8> {
+    let kind = if dir { b'5' } else { b'0' };
+    if name.len() <= 100 {
+        return tar_header(name.as_bytes(), b"", size, modified, kind).to_vec();
+    }
+    let split = name.trim_end_matches('/').match_indices('/').map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, rest)| prefix.len() <= 155 && rest.len() <= 100);
+    if let Some((prefix, rest)) = split {
+        return tar_header(rest.as_bytes(), prefix.as_bytes(), size, modified, kind).to_vec();
+    }
+    let mut long = name.as_bytes().to_vec();
+    long.push(0);
+    let mut out = tar_header(b"././@LongLink", b"", long.len() as u64, modified, b'L').to_vec();
+    long.resize(long.len().div_ceil(512) * 512, 0);
+    out.extend_from_slice(&long);
+    out.extend_from_slice(&tar_header(name.as_bytes(), b"", size, modified, kind));
+    out
+}
+
+fn write_tar_gz(inputs: &[Input], out: &mut BufWriter<File>) -> Result<(), String> {
+    let err = |e: io::Error| e.to_string();
+    let mut gz = GzEncoder::new(out, Compression::default());
+    for input in inputs {
+        let name = if input.dir { format!("{}/", input.name) } else { input.name.clone() };
+        gz.write_all(&tar_entry(&name, input.size, input.modified, input.dir)).map_err(err)?;
+        if input.dir {
+            continue;
+        }
+        let mut source = File::open(&input.path).map_err(io_error(&input.path))?.take(input.size);
+        let written = io::copy(&mut source, &mut gz).map_err(err)?;
+        if written != input.size {
+            return Err(format!("{} changed while it was being packed", input.path.display()));
+        }
+        let padding = (512 - input.size % 512) % 512;
+        gz.write_all(&vec![0u8; padding as usize]).map_err(err)?;
+    }
+    gz.write_all(&[0u8; 1024]).map_err(err)?;
+    gz.finish().map_err(err)?.flush().map_err(err)
+}
+
+/// Packs files and folders into a .zip or .tar.gz. A password encrypts a zip with AES-256;
+/// tar.gz has no encryption of its own.
+pub fn compress(paths: &[PathBuf], to: &Path, password: Option<&str>) -> Result<String, String> {
+    let format = format(to)?;
+    if password.is_some() && matches!(format, Format::TarGz) {
+        return Err("Only .zip archives can have a password".into());
+    }
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    let inputs = collect(paths)?;
+    if let Some(parent) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Can't create {}: {e}", parent.display()))?;
+    }
+    // Written beside the target first, so a failure never leaves half an archive behind.
+    let partial = PathBuf::from(format!("{}.partial", to.display()));
+    let written = File::create(&partial).map_err(io_error(&partial)).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        match format {
+            Format::Zip => write_zip(&inputs, &mut out, password),
+            Format::TarGz => write_tar_gz(&inputs, &mut out),
+        }
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&partial, to).map_err(io_error(to))) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    let files = inputs.iter().filter(|i| !i.dir).count();
+    let before: u64 = inputs.iter().map(|i| i.size).sum();
+    let after = fs::metadata(to).map(|m| m.len()).unwrap_or_default();
+    Ok(format!(
+        "Packed {files} file(s) ({}) into {} ({}){}",
+        size(before), to.display(), size(after), if password.is_some() { ", protected with a password" } else { "" },
+    ))
+}
+
+/// A zip of the given names and contents, built in memory, for files such as .docx that
+/// are zips underneath.
+pub fn zip_bytes(entries: &[(&str, &[u8])]) -> Result<Vec<u8>, String> {
+    let (time, date) = dos_time(SystemTime::now());
+    let mut out = Vec::new();
+    let mut central = Vec::with_capacity(entries.len());
+    for (name, data) in entries {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).map_err(|e| e.to_string())?;
+        let compressed = encoder.finish().map_err(|e| e.to_string())?;
+        let entry = ZipEntry {
+            name: name.to_string(),
+            flags: FLAG_UTF8,
+            method: METHOD_DEFLATE,
+            time,
+            date,
+            crc: crc32fast::hash(data),
+            compressed: compressed.len() as u64,
+            size: data.len() as u64,
+            offset: out.len() as u64,
+            extra: Vec::new(),
+            dir: false,
+        };
+        out.extend_from_slice(&local_header(&entry));
+        out.extend_from_slice(&compressed);
+        central.push(entry);
+    }
+    let directory_start = out.len();
+    for entry in &central {
+        out.extend_from_slice(&central_header(entry));
+    }
+    let directory_len = out.len() - directory_start;
+    put32(&mut out, END_OF_CENTRAL);
+    put16(&mut out, 0);
+    put16(&mut out, 0);
+    put16(&mut out, central.len() as u16);
+    put16(&mut out, central.len() as u16);
+    put32(&mut out, directory_len as u32);
+    put32(&mut out, directory_start as u32);
+    put16(&mut out, 0);
+    Ok(out)
+}
+
+// ─── Unpacking ───
+
+/// The path an entry unpacks to under the destination, refusing names that would land
+/// anywhere else. `None` for names with nothing left, such as `./`.
+fn entry_path(name: &str) -> Result<Option<PathBuf>, String> {
+    let outside = || format!("The archive has an entry ({name}) that points outside the folder it unpacks to");
+    let name_slashed = name.replace('\\', "/");
+    if name_slashed.starts_with('/') {
+        return Err(outside());
+    }
+    let mut out = PathBuf::new();
+    for part in name_slashed.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err(outside()),
+            part if part.contains(':') || part.contains('\0') => return Err(outside()),
+            part => out.push(part),
+        }
+    }
+    Ok((!out.as_os_str().is_empty()).then_some(out))
+}
+
+/// What an archive holds, checked before anything is written.
+struct Listing {
+    name: String,
+    dir: bool,
+    size: u64,
+}
+
+fn check(dest: &Path, listing: &[Listing]) -> Result<(), String> {
+    if listing.len() > MAX_ENTRIES {
+        return Err(format!("The archive has more than {MAX_ENTRIES} entries"));
+    }
+    let total: u64 = listing.iter().map(|l| l.size).sum();
+    if total > MAX_TOTAL_BYTES {
+        return Err(format!("The archive would unpack to {}, more than the {} allowed", size(total), size(MAX_TOTAL_BYTES)));
+    }
+    for entry in listing {
+        if let Some(path) = entry_path(&entry.name)? {
+            if !entry.dir && dest.join(&path).exists() {
+                return Err(format!("{} already exists", dest.join(path).display()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes entries under `dest`, remembering what it created so a failure can be undone.
+struct Unpacker {
+    dest: PathBuf,
+    created: Vec<PathBuf>,
+    files: usize,
+    bytes: u64,
+}
+
+impl Unpacker {
+    fn new(dest: &Path) -> Self {
+        Self { dest: dest.to_path_buf(), created: Vec::new(), files: 0, bytes: 0 }
+    }
+
+    fn mkdirs(&mut self, dir: &Path) -> Result<(), String> {
+        if dir.as_os_str().is_empty() || dir.is_dir() {
+            return Ok(());
+        }
+        if let Some(parent) = dir.parent() {
+            self.mkdirs(parent)?;
+        }
+        fs::create_dir(dir).map_err(|e| format!("Can't create {}: {e}", dir.display()))?;
+        self.created.push(dir.to_path_buf());
+        Ok(())
+    }
+
+    fn dir(&mut self, name: &str) -> Result<(), String> {
+        match entry_path(name)? {
+            Some(path) => self.mkdirs(&self.dest.join(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// `crc` is checked when the archive stores one.
+    fn file(&mut self, name: &str, data: &mut dyn Read, crc: Option<u32>) -> Result<(), String> {
+        let Some(path) = entry_path(name)? else { return Ok(()) };
+        let path = self.dest.join(path);
+        if let Some(parent) = path.parent() {
+            self.mkdirs(parent)?;
+        }
+        let file = File::create_new(&path).map_err(|e| format!("Can't create {}: {e}", path.display()))?;
+        self.created.push(path.clone());
+        let mut out = BufWriter::new(file);
+        let mut hasher = crc32fast::Hasher::new();
+        let n = copy_limited(data, &mut out, MAX_TOTAL_BYTES - self.bytes, &mut hasher)
+            .map_err(|e| format!("{name}: {e}"))?;
+        out.flush().map_err(io_error(&path))?;
+        if crc.is_some_and(|crc| crc != hasher.finalize()) {
+            return Err(format!("{name} is damaged in the archive"));
+        }
+        self.files += 1;
+        self.bytes += n;
+        Ok(())
+    }
+
+    fn undo(&self) {
+        for path in self.created.iter().rev() {
+            let _ = if path.is_dir() { fs::remove_dir(path) } else { fs::remove_file(path) };
+        }
+    }
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, String> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or_else(|| "The zip file is damaged".to_string())
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32, String> {
+    data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| "The zip file is damaged".to_string())
+}
+
+fn zip_entries(file: &mut File) -> Result<Vec<ZipEntry>, String> {
+    let err = |e: io::Error| e.to_string();
+    let len = file.metadata().map_err(err)?.len();
+    let tail_len = len.min(22 + 65_535);
+    file.seek(SeekFrom::Start(len - tail_len)).map_err(err)?;
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail).map_err(err)?;
+    let end = tail.windows(4).rposition(|w| w == END_OF_CENTRAL.to_le_bytes())
+        .ok_or("This isn't a zip file, or it is damaged")?;
+    let count = u16_at(&tail, end + 10)? as usize;
+    let directory_len = u32_at(&tail, end + 12)? as u64;
+    let directory_start = u32_at(&tail, end + 16)? as u64;
+    if count == 0xFFFF || directory_start == 0xFFFF_FFFF {
+        return Err("Zip files over 4 GB aren't supported".into());
+    }
+    if count > MAX_ENTRIES {
+        return Err(format!("The archive has more than {MAX_ENTRIES} entries"));
+    }
+    if directory_start + directory_len > len {
+        return Err("The zip file is damaged".into());
+    }
+    file.seek(SeekFrom::Start(directory_start)).map_err(err)?;
+    let mut directory = vec![0u8; directory_len as usize];
+    file.read_exact(&mut directory).map_err(err)?;
+
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 0;
+    for _ in 0..count {
+        if u32_at(&directory, at)? != CENTRAL_HEADER {
+            return Err("The zip file is damaged".into());
+        }
+        let name_len = u16_at(&directory, at + 28)? as usize;
+        let extra_len = u16_at(&directory, at + 30)? as usize;
+        let comment_len = u16_at(&directory, at + 32)? as usize;
+        let name = directory.get(at + 46..at + 46 + name_len).ok_or("The zip file is damaged")?;
+        let extra = directory.get(at + 46 + name_len..at + 46 + name_len + extra_len).ok_or("The zip file is damaged")?;
+        let name = String::from_utf8_lossy(name).to_string();
+        entries.push(ZipEntry {
+            dir: name.ends_with('/') || name.ends_with('\\'),
+            name,
+            flags: u16_at(&directory, at + 8)?,
+            method: u16_at(&directory, at + 10)?,
+            time: u16_at(&directory, at + 12)?,
+            date: u16_at(&directory, at + 14)?,
+            crc: u32_at(&directory, at + 16)?,
+            compressed: u32_at(&directory, at + 20)? as u64,
+            size: u32_at(&directory, at + 24)? as u64,
+            offset: u32_at(&directory, at + 42)? as u64,
+            extra: extra.to_vec(),
+        });
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// The older "ZipCrypto" scheme, which many tools still write. Only ever read here.
+struct ZipCrypto {
+    keys: [u32; 3],
+}
+
+fn crc32_byte(crc: u32, byte: u8) -> u32 {
+    let mut c = (crc ^ byte as u32) & 0xff;
+    for _ in 0..8 {
+        c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+    }
+    c ^ (crc >> 8)
+}
+
+impl ZipCrypto {
+    fn new(password: &str) -> Self {
+        let mut crypto = Self { keys: [0x1234_5678, 0x2345_6789, 0x3456_7890] };
+        password.bytes().for_each(|b| crypto.update(b));
+        crypto
+    }
+
+    fn update(&mut self, byte: u8) {
+        self.keys[0] = crc32_byte(self.keys[0], byte);
+        self.keys[1] = self.keys[1].wrapping_add(self.keys[0] & 0xff).wrapping_mul(134_775_813).wrapping_add(1);
+        self.keys[2] = crc32_byte(self.keys[2], (self.keys[1] >> 24) as u8);
+    }
+
+    fn decrypt(&mut self, byte: u8) -> u8 {
+        let temp = (self.keys[2] | 2) & 0xffff;
+        let plain = byte ^ ((temp * (temp ^ 1)) >> 8) as u8;
+        self.update(plain);
+        plain
+    }
+}
+
+struct ZipCryptoReader<R: Read> {
+    inner: R,
+    crypto: ZipCrypto,
+}
+
+impl<R: Read> Read for ZipCryptoReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        buf[..n].iter_mut().for_each(|b| *b = self.crypto.decrypt(*b));
+        Ok(n)
+    }
+}
+
+/// Decrypts WinZip AES data and checks its authentication code once it has all been read.
+struct AesReader<R: Read> {
+    inner: io::Take<R>,
+    cipher: AesCtr,
+    mac: HmacSha1,
+    checked: bool,
+}
+
+impl<R: Read> Read for AesReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.mac.update(&buf[..n]);
+            self.cipher.apply(&mut buf[..n]);
+        } else if !self.checked && !buf.is_empty() {
+            self.checked = true;
+            let mut tag = [0u8; AES_MAC_LEN];
+            self.inner.get_mut().read_exact(&mut tag)?;
+            let expected = self.mac.clone().finalize().into_bytes();
+            if expected[..AES_MAC_LEN] != tag {
+                return Err(io::Error::other("the file was altered after it was encrypted"));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// The decrypted, decompressed contents of one entry, plus the CRC to check them against.
+fn open_entry<'a>(file: &'a File, entry: &ZipEntry, password: Option<&str>) -> Result<(Box<dyn Read + 'a>, Option<u32>), String> {
+    let err = |e: io::Error| e.to_string();
+    let mut reader = file;
+    reader.seek(SeekFrom::Start(entry.offset)).map_err(err)?;
+    let mut header = [0u8; 30];
+    reader.read_exact(&mut header).map_err(err)?;
+    if u32_at(&header, 0)? != LOCAL_HEADER {
+        return Err("The zip file is damaged".into());
+    }
+    let skip = u16_at(&header, 26)? as i64 + u16_at(&header, 28)? as i64;
+    reader.seek(SeekFrom::Current(skip)).map_err(err)?;
+    let raw = reader.take(entry.compressed);
+
+    let mut method = entry.method;
+    let mut crc = Some(entry.crc);
+    let data: Box<dyn Read + 'a> = if entry.flags & FLAG_ENCRYPTED == 0 {
+        Box::new(raw)
+    } else {
+        let password = password.ok_or_else(|| format!("{} is protected with a password", entry.name))?;
+        if entry.flags & FLAG_STRONG != 0 {
+            return Err(format!("{} uses a kind of encryption that isn't supported", entry.name));
+        }
+        if method == METHOD_AES {
+            let extra = aes_info(&entry.extra).ok_or_else(|| format!("{} is damaged in the archive", entry.name))?;
+            let (version, strength, actual) = extra;
+            let key_len = match strength {
+                1 => 16,
+                2 => 24,
+                3 => 32,
+                _ => return Err(format!("{} uses a kind of encryption that isn't supported", entry.name)),
+            };
+            let salt_len = key_len / 2;
+            let mut raw = raw;
+            let mut salt_check = vec![0u8; salt_len + 2];
+            raw.read_exact(&mut salt_check).map_err(err)?;
+            let keys = aes_keys(password, &salt_check[..salt_len], key_len);
+            if keys[key_len * 2..] != salt_check[salt_len..] {
+                return Err("Wrong password".into());
+            }
+            let body = entry.compressed.checked_sub((salt_len + 2 + AES_MAC_LEN) as u64)
+                .ok_or_else(|| format!("{} is damaged in the archive", entry.name))?;
+            method = actual;
+            // AE-2 leaves the CRC out; the authentication code covers the data instead.
+            if version == 2 {
+                crc = None;
+            }
+            Box::new(AesReader {
+                inner: raw.into_inner().take(body),
+                cipher: AesCtr::new(&keys[..key_len]),
+                mac: HmacSha1::new_from_slice(&keys[key_len..key_len * 2]).map_err(|e| e.to_string())?,
+                checked: false,
+            })
+        } else {
+            let mut crypto = ZipCrypto::new(password);
+            let mut raw = raw;
+            let mut head = [0u8; 12];
+            raw.read_exact(&mut head).map_err(err)?;
+            head.iter_mut().for_each(|b| *b = crypto.decrypt(*b));
+            let check = if entry.flags & FLAG_DESCRIPTOR != 0 { (entry.time >> 8) as u8 } else { (entry.crc >> 24) as u8 };
+            if head[11] != check {
+                return Err("Wrong password".into());
+            }
+            Box::new(ZipCryptoReader { inner: raw, crypto })
+        }
+    };
+    match method {
+        METHOD_STORED => Ok((data, crc)),
+        METHOD_DEFLATE => Ok((Box::new(DeflateDecoder::new(data)), crc)),
+        other => Err(format!("{} is compressed in a way that isn't supported (method {other})", entry.name)),
+    }
+}
+
+/// Vendor version, key strength and real compression method from the AES extra field.
+fn aes_info(extra: &[u8]) -> Option<(u16, u8, u16)> {
+    let mut at = 0;
+    while at + 4 <= extra.len() {
+        let id = u16_at(extra, at).ok()?;
+        let len = u16_at(extra, at + 2).ok()? as usize;
+        if id == AES_EXTRA && len >= 7 {
+            return Some((u16_at(extra, at + 4).ok()?, *extra.get(at + 8)?, u16_at(extra, at + 9).ok()?));
+        }
+        at += 4 + len;
+    }
+    None
+}
+
+fn extract_zip(archive: &Path, dest: &Path, password: Option<&str>, unpacker: &mut Unpacker) -> Result<(), String> {
+    let mut file = File::open(archive).map_err(io_error(archive))?;
+    let entries = zip_entries(&mut file)?;
+    let listing: Vec<Listing> = entries.iter().map(|e| Listing { name: e.name.clone(), dir: e.dir, size: e.size }).collect();
+    check(dest, &listing)?;
+    for entry in &entries {
+        if entry.dir {
+            unpacker.dir(&entry.name)?;
+            continue;
+        }
+        let (mut data, crc) = open_entry(&file, entry, password)?;
+        unpacker.file(&entry.name, &mut data, crc)?;
+    }
+    Ok(())
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64, String> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return Err("The archive has a file over 8 GB".into());
+    }
+    let text: String = field.iter().take_while(|b| **b != 0).map(|b| *b as char).collect();
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| "The tar archive is damaged".to_string())
+}
+
+enum TarKind {
+    File,
+    Dir,
+    /// Links, devices and the like, which are never unpacked.
+    Other,
+}
+
+/// Visits each entry of a gzipped tar archive in order, with its data.
+fn walk_tar(archive: &Path, mut visit: impl FnMut(&str, TarKind, u64, &mut dyn Read) -> Result<(), String>) -> Result<(), String> {
+    let damaged = |_| "The tar archive is damaged".to_string();
+    let mut reader = GzDecoder::new(BufReader::new(File::open(archive).map_err(io_error(archive))?));
+    let mut long_name: Option<String> = None;
+    loop {
+        let mut header = [0u8; 512];
+        reader.read_exact(&mut header).map_err(damaged)?;
+        if header.iter().all(|b| *b == 0) {
+            return Ok(());
+        }
+        let stored = parse_octal(&header[148..156])?;
+        let actual: u64 = header.iter().enumerate().map(|(i, b)| if (148..156).contains(&i) { b' ' as u64 } else { *b as u64 }).sum();
+        if stored != actual {
+            return Err("The tar archive is damaged".into());
+        }
+        let size = parse_octal(&header[124..136])?;
+        let field = |range: std::ops::Range<usize>| String::from_utf8_lossy(&header[range]).trim_end_matches('\0').to_string();
+        let name = long_name.take().unwrap_or_else(|| {
+            let (prefix, name) = (field(345..500), field(0..100));
+            if prefix.is_empty() { name } else { format!("{prefix}/{name}") }
+        });
+        let mut data = (&mut reader).take(size);
+        match header[156] {
+            // A GNU long name, or pax attributes that may carry one, for the next entry.
+            b'L' | b'x' if size <= 64 * 1024 => {
+                let mut text = Vec::new();
+                data.read_to_end(&mut text).map_err(damaged)?;
+                let text = String::from_utf8_lossy(&text).to_string();
+                long_name = if header[156] == b'L' {
+                    Some(text.trim_end_matches('\0').to_string())
+                } else {
+                    text.lines().find_map(|line| line.split_once(" path=").map(|(_, path)| path.to_string()))
+                };
+            }
+            b'0' | b'\0' | b'7' => visit(&name, TarKind::File, size, &mut data)?,
+            b'5' => visit(&name, TarKind::Dir, 0, &mut data)?,
+            b'g' | b'x' => {}
+            _ => visit(&name, TarKind::Other, size, &mut data)?,
+        }
+        io::copy(&mut data, &mut io::sink()).map_err(damaged)?;
+        let padding = (512 - size % 512) % 512;
+        io::copy(&mut (&mut reader).take(padding), &mut io::sink()).map_err(damaged)?;
+    }
+}
+
+fn extract_tar(archive: &Path, dest: &Path, unpacker: &mut Unpacker) -> Result<(), String> {
+    let mut listing = Vec::new();
+    walk_tar(archive, |name, kind, size, _| {
+        listing.push(Listing { name: name.to_string(), dir: matches!(kind, TarKind::Dir), size });
+        if listing.len() > MAX_ENTRIES {
+            return Err(format!("The archive has more than {MAX_ENTRIES} entries"));
+        }
+        // Checked here too, so hostile names are refused before anything is read further.
+        entry_path(name).map(|_| ())
+    })?;
+    check(dest, &listing)?;
+    walk_tar(archive, |name, kind, _, data| match kind {
+        TarKind::File => unpacker.file(name, data, None),
+        TarKind::Dir => unpacker.dir(name),
+        TarKind::Other => Ok(()),
+    })
+}
+
+/// Unpacks a .zip or .tar.gz into `dest`. Nothing outside `dest` is written and no existing
+/// file is replaced; if anything goes wrong, what was unpacked so far is removed again.
+pub fn extract(archive: &Path, dest: &Path, password: Option<&str>) -> Result<String, String> {
+    let format = format(archive)?;
+    if password.is_some() && matches!(format, Format::TarGz) {
+        return Err("Only .zip archives can have a password".into());
+    }
+    let mut unpacker = Unpacker::new(dest);
+    let result = unpacker.mkdirs(dest).and_then(|_| match format {
+        Format::Zip => extract_zip(archive, dest, password, &mut unpacker),
+        Format::TarGz => extract_tar(archive, dest, &mut unpacker),
+    });
+    if let Err(e) = result {
+        unpacker.undo();
+        return Err(e);
+    }
+    Ok(format!("Unpacked {} file(s) ({}) into {}", unpacker.files, size(unpacker.bytes), dest.display()))
+}
+
+/// The folder an archive unpacks to when none is given: beside it, named after it.
+pub fn default_destination(archive: &Path) -> PathBuf {
+    let name = fileops::name(archive);
+    let lower = name.to_lowercase();
+    let stem = [".tar.gz", ".tgz", ".zip"].iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(name.as_str(), |ext| &name[..name.len() - ext.len()]);
+    archive.with_file_name(stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Scratch;
+
+    /// A folder with a nested file, an empty folder and a name too long for a plain tar header.
+    fn sample(dir: &Path) -> PathBuf {
+        let root = dir.join("docs");
+        let deep = root.join("a".repeat(60)).join("b".repeat(60));
+        fs::create_dir_all(&deep).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(root.join("notes.txt"), "first notes").unwrap();
+        fs::write(deep.join("c".repeat(90) + ".txt"), vec![7u8; 200_000]).unwrap();
+        fs::write(root.join("d".repeat(120)), "long name").unwrap();
+        root
+    }
+
+    fn assert_same(original: &Path, unpacked: &Path) {
+        let mut names: Vec<PathBuf> = fs::read_dir(original).unwrap().flatten().map(|e| e.path()).collect();
+        names.sort();
+        for path in names {
+            let copy = unpacked.join(path.file_name().unwrap());
+            if path.is_dir() {
+                assert!(copy.is_dir(), "{} is missing", copy.display());
+                assert_same(&path, &copy);
+            } else {
+                assert_eq!(fs::read(&path).unwrap(), fs::read(&copy).unwrap(), "{} differs", copy.display());
+            }
+        }
+    }
+
+    fn tar_gz(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        for (name, data) in entries {
+            gz.write_all(&tar_entry(name, data.len() as u64, UNIX_EPOCH, false)).unwrap();
+            gz.write_all(data).unwrap();
+            gz.write_all(&vec![0u8; (512 - data.len() % 512) % 512]).unwrap();
+        }
+        gz.write_all(&[0u8; 1024]).unwrap();
+        fs::write(path, gz.finish().unwrap()).unwrap();
+    }
+
+    /// One stored entry encrypted with ZipCrypto, which the app reads but never writes.
+    fn zip_crypto(password: &str, name: &str, data: &[u8]) -> Vec<u8> {
+        let crc = crc32fast::hash(data);
+        let mut crypto = ZipCrypto::new(password);
+        let mut encrypt = |plain: u8| {
+            let temp = (crypto.keys[2] | 2) & 0xffff;
+            let sealed = plain ^ ((temp * (temp ^ 1)) >> 8) as u8;
+            crypto.update(plain);
+            sealed
+        };
+        let mut body: Vec<u8> = (0..11).map(&mut encrypt).collect();
+        body.push(encrypt((crc >> 24) as u8));
+        body.extend(data.iter().map(|b| encrypt(*b)));
+        let entry = ZipEntry {
+            name: name.into(),
+            flags: FLAG_ENCRYPTED,
+            method: METHOD_STORED,
+            time: 0,
+            date: 0,
+            crc,
+            compressed: body.len() as u64,
+            size: data.len() as u64,
+            offset: 0,
+            extra: Vec::new(),
+            dir: false,
+        };
+        let mut out = local_header(&entry);
+        out.extend_from_slice(&body);
+        let directory_start = out.len();
+        out.extend_from_slice(&central_header(&entry));
+        let directory_len = out.len() - directory_start;
+        for value in [END_OF_CENTRAL.to_le_bytes().to_vec(), vec![0; 4], 1u16.to_le_bytes().to_vec(), 1u16.to_le_bytes().to_vec()] {
+            out.extend_from_slice(&value);
+        }
+        put32(&mut out, directory_len as u32);
+        put32(&mut out, directory_start as u32);
+        put16(&mut out, 0);
+        out
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        let dir = Scratch::new("compress");
+        let root = sample(&dir);
+        let archive = dir.join("docs.zip");
+        compress(std::slice::from_ref(&root), &archive, None).unwrap();
+        assert!(compress(std::slice::from_ref(&root), &archive, None).unwrap_err().contains("already exists"));
+        let out = dir.join("out");
+        extract(&archive, &out, None).unwrap();
+        assert_same(&root, &out.join("docs"));
+        assert!(out.join("docs/empty").is_dir());
+    }
+
+    #[test]
+    fn tar_gz_round_trip() {
+        let dir = Scratch::new("compress");
+        let root = sample(&dir);
+        let archive = dir.join("docs.tar.gz");
+        compress(std::slice::from_ref(&root), &archive, None).unwrap();
+        let out = dir.join("out");
+        extract(&archive, &out, None).unwrap();
+        assert_same(&root, &out.join("docs"));
+        assert!(compress(&[root], &dir.join("locked.tgz"), Some("secret")).is_err());
+    }
+
+    #[test]
+    fn aes_zip_round_trip() {
+        let dir = Scratch::new("compress");
+        let root = sample(&dir);
+        let archive = dir.join("locked.zip");
+        compress(std::slice::from_ref(&root), &archive, Some("correct horse")).unwrap();
+        assert!(extract(&archive, &dir.join("none"), None).unwrap_err().contains("password"));
+        assert_eq!(extract(&archive, &dir.join("wrong"), Some("wrong horse")).unwrap_err(), "Wrong password");
+        assert!(!dir.join("wrong").join("docs").exists(), "a failed unpack is undone");
+        let out = dir.join("out");
+        extract(&archive, &out, Some("correct horse")).unwrap();
+        assert_same(&root, &out.join("docs"));
+    }
+
+    #[test]
+    fn refuses_altered_aes_data() {
+        let dir = Scratch::new("compress");
+        let file = dir.join("secret.txt");
+        fs::write(&file, "the combination is 1234").unwrap();
+        let archive = dir.join("secret.zip");
+        compress(&[file], &archive, Some("pw")).unwrap();
+        let mut data = fs::read(&archive).unwrap();
+        // The first byte after the header, salt and password check is encrypted data.
+        let body = 30 + "secret.txt".len() + aes_extra().len() + 16 + 2;
+        data[body] ^= 1;
+        fs::write(&archive, data).unwrap();
+        let out = dir.join("out");
+        assert!(extract(&archive, &out, Some("pw")).is_err());
+        assert!(!out.join("secret.txt").exists());
+    }
+
+    #[test]
+    fn reads_zip_crypto() {
+        let dir = Scratch::new("compress");
+        let archive = dir.join("old.zip");
+        fs::write(&archive, zip_crypto("hunter2", "old.txt", b"from an old tool")).unwrap();
+        let out = dir.join("out");
+        extract(&archive, &out, Some("hunter2")).unwrap();
+        assert_eq!(fs::read(out.join("old.txt")).unwrap(), b"from an old tool");
+        assert_eq!(extract(&archive, &dir.join("wrong"), Some("hunter3")).unwrap_err(), "Wrong password");
+    }
+
+    #[test]
+    fn refuses_entries_outside_the_destination() {
+        for name in ["../evil.txt", "/evil.txt", "a/../../evil.txt", "..\\evil.txt", "C:\\evil.txt", "a/b\0/evil.txt"] {
+            assert!(entry_path(name).is_err(), "{name} was allowed");
+        }
+        assert_eq!(entry_path("./a//b/").unwrap(), Some(PathBuf::from("a").join("b")));
+        assert_eq!(entry_path("./").unwrap(), None);
+
+        let dir = Scratch::new("compress");
+        let zip = dir.join("slip.zip");
+        fs::write(&zip, zip_bytes(&[("fine.txt", b"ok"), ("../evil.txt", b"gotcha")]).unwrap()).unwrap();
+        let out = dir.join("out");
+        assert!(extract(&zip, &out, None).unwrap_err().contains("outside"));
+        assert!(!dir.join("evil.txt").exists());
+        assert!(!out.join("fine.txt").exists(), "nothing is unpacked from a hostile archive");
+
+        let tar = dir.join("slip.tar.gz");
+        tar_gz(&tar, &[("../evil.txt", b"gotcha")]);
+        assert!(extract(&tar, &out, None).unwrap_err().contains("outside"));
+        assert!(!dir.join("evil.txt").exists());
+    }
+
+    #[test]
+    fn never_replaces_existing_files() {
+        let dir = Scratch::new("compress");
+        let zip = dir.join("notes.zip");
+        fs::write(&zip, zip_bytes(&[("new.txt", b"new"), ("notes.txt", b"from the archive")]).unwrap()).unwrap();
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("notes.txt"), "mine").unwrap();
+        assert!(extract(&zip, &out, None).unwrap_err().contains("already exists"));
+        assert_eq!(fs::read_to_string(out.join("notes.txt")).unwrap(), "mine");
+        assert!(!out.join("new.txt").exists());
+    }
+
+    #[test]
+    fn enforces_entry_and_size_limits() {
+        let dest = Scratch::new("compress");
+        let entry = |size| Listing { name: "f".into(), dir: false, size };
+        let many: Vec<Listing> = (0..=MAX_ENTRIES).map(|_| entry(0)).collect();
+        assert!(check(&dest, &many).unwrap_err().contains("entries"));
+        assert!(check(&dest, &[entry(MAX_TOTAL_BYTES), entry(1)]).is_err());
+        assert!(check(&dest, &[entry(MAX_TOTAL_BYTES)]).is_ok());
+
+        let mut copied = Vec::new();
+        let mut crc = crc32fast::Hasher::new();
+        assert!(copy_limited(&mut &[0u8; 100][..], &mut copied, 99, &mut crc).is_err());
+        assert_eq!(copy_limited(&mut &[0u8; 100][..], &mut copied, 100, &mut crc).unwrap(), 100);
+
+        // A zip whose directory claims more entries than allowed is refused before reading them.
+        let mut zip = END_OF_CENTRAL.to_le_bytes().to_vec();
+        zip.extend_from_slice(&[0; 6]);
+        put16(&mut zip, (MAX_ENTRIES + 1) as u16);
+        zip.extend_from_slice(&[0; 10]);
+        let path = dest.join("many.zip");
+        fs::write(&path, zip).unwrap();
+        assert!(extract(&path, &dest.join("out"), None).unwrap_err().contains("entries"));
+    }
+
+    #[test]
+    fn reports_damaged_archives() {
+        let dir = Scratch::new("compress");
+        let zip = dir.join("damaged.zip");
+        let whole = zip_bytes(&[("a.txt", b"some text to pack")]).unwrap();
+        // Only the comment length, never read, can go missing without harm.
+        for end in 0..whole.len() - 2 {
+            fs::write(&zip, &whole[..end]).unwrap();
+            assert!(extract(&zip, &dir.join("out"), None).is_err());
+        }
+        let tar = dir.join("damaged.tgz");
+        fs::write(&tar, b"\x1f\x8b not really gzip").unwrap();
+        assert!(extract(&tar, &dir.join("out"), None).is_err());
+        assert!(extract(&dir.join("notes.rar"), &dir.join("out"), None).unwrap_err().contains("isn't a .zip"));
+    }
+
+    #[test]
+    fn names_the_default_destination() {
+        assert_eq!(default_destination(Path::new("/tmp/Photos.ZIP")), PathBuf::from("/tmp/Photos"));
+        assert_eq!(default_destination(Path::new("/tmp/site.tar.gz")), PathBuf::from("/tmp/site"));
+    }
+}
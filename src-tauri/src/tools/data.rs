@@ -0,0 +1,224 @@
+//! Small public data sources that need no account or key: weather from Open-Meteo and
+//! public holidays from Nager.Date. Each service is rate limited here so a looping run
+//! can't get the user's address blocked; repeated lookups come from the response cache.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, Local, NaiveDate};
+use serde_json::Value;
+
+use super::calc;
+
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const HOLIDAYS_URL: &str = "https://date.nager.at/api/v3/PublicHolidays";
+/// Requests each service may get in `RATE_WINDOW`.
+const RATE_LIMIT: usize = 20;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const MAX_FORECAST_DAYS: u64 = 7;
+/// Where `get_weather` looks when a step doesn't name a place.
+pub const HOME_LOCATION_SETTING: &str = "home_location";
+
+static RECENT: LazyLock<Mutex<HashMap<&'static str, VecDeque<Instant>>>> = LazyLock::new(Default::default);
+
+/// Counts a request to `service`, refusing it when the service has had `RATE_LIMIT` in the
+/// last minute.
+pub fn throttle(service: &'static str) -> Result<(), String> {
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    let calls = recent.entry(service).or_default();
+    let now = Instant::now();
+    while calls.front().is_some_and(|at| now.duration_since(*at) > RATE_WINDOW) {
+        calls.pop_front();
+    }
+    if calls.len() >= RATE_LIMIT {
+        let wait = calls.front().map_or(RATE_WINDOW, |at| RATE_WINDOW.saturating_sub(now.duration_since(*at)));
+        return Err(format!("{service} has been asked too often; try again in {} seconds", wait.as_secs().max(1)));
+    }
+    calls.push_back(now);
+    Ok(())
+}
+
+/// `text` made safe to put in a URL's query string.
+fn query_value(text: &str) -> String {
+    text.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        other => format!("%{other:02X}"),
+    }).collect()
+}
+
+fn json(body: &str, service: &str) -> Result<Value, String> {
+    serde_json::from_str(body).map_err(|_| format!("{service} sent an answer that couldn't be read"))
+}
+
+/// A place found for a location, with its coordinates.
+pub struct Place {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// `52.52, 13.40` as coordinates, if that is what `location` is.
+pub fn coordinates(location: &str) -> Option<Place> {
+    let (lat, lon) = location.split_once(',')?;
+    let (latitude, longitude) = (lat.trim().parse::<f64>().ok()?, lon.trim().parse::<f64>().ok()?);
+    ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude))
+        .then(|| Place { name: format!("{latitude:.2}, {longitude:.2}"), latitude, longitude })
+}
+
+/// The search URL for `location`: the text before a comma is the place name; what follows,
+/// like a region or country, picks among places with that name.
+pub fn geocoding_url(location: &str) -> String {
+    let name = location.split(',').next().unwrap_or_default().trim();
+    format!("{GEOCODING_URL}?name={}&count=10&language=en&format=json", query_value(name))
+}
+
+/// The best match for `location` in a geocoding answer.
+pub fn pick_place(body: &str, location: &str) -> Result<Place, String> {
+    let answer = json(body, "The place search")?;
+    let results = answer.get("results").and_then(Value::as_array).cloned().unwrap_or_default();
+    let hints: Vec<String> = location.split(',').skip(1).map(|h| h.trim().to_lowercase()).filter(|h| !h.is_empty()).collect();
+    let field = |r: &Value, key: &str| r.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    let matches = |r: &Value| hints.iter().all(|hint| {
+        ["country", "country_code", "admin1", "admin2"].iter().any(|key| field(r, key).to_lowercase() == *hint || field(r, key).to_lowercase().starts_with(hint.as_str()))
+    });
+    let best = results.iter().find(|r| matches(r))
+        .ok_or_else(|| format!("No place called \"{}\" was found", location.trim()))?;
+    let name = [field(best, "name"), field(best, "admin1"), field(best, "country")].into_iter()
+        .filter(|part| !part.is_empty())
+        .fold(Vec::<String>::new(), |mut parts, part| {
+            if !parts.contains(&part) {
+                parts.push(part);
+            }
+            parts
+        })
+        .join(", ");
+    Ok(Place {
+        name,
+        latitude: best.get("latitude").and_then(Value::as_f64).ok_or("The place search gave no coordinates")?,
+        longitude: best.get("longitude").and_then(Value::as_f64).ok_or("The place search gave no coordinates")?,
+    })
+}
+
+/// The forecast URL for `days` days at `place`, in Fahrenheit and mph when `imperial`.
+pub fn forecast_url(place: &Place, days: u64, imperial: bool) -> String {
+    let units = if imperial { "&temperature_unit=fahrenheit&wind_speed_unit=mph" } else { "" };
+    format!(
+        "{FORECAST_URL}?latitude={:.4}&longitude={:.4}&current=temperature_2m,apparent_temperature,weather_code,wind_speed_10m\
+         &daily=weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max&timezone=auto&forecast_days={}{units}",
+        place.latitude, place.longitude, days.clamp(1, MAX_FORECAST_DAYS),
+    )
+}
+
+/// WMO weather interpretation codes, as Open-Meteo reports them.
+fn conditions(code: i64) -> &'static str {
+    match code {
+        0 => "clear sky",
+        1 => "mainly clear",
+        2 => "partly cloudy",
+        3 => "overcast",
+        45 | 48 => "fog",
+        51 | 53 | 55 => "drizzle",
+        56 | 57 => "freezing drizzle",
+        61 => "light rain",
+        63 => "rain",
+        65 => "heavy rain",
+        66 | 67 => "freezing rain",
+        71 => "light snow",
+        73 => "snow",
+        75 => "heavy snow",
+        77 => "snow grains",
+        80 | 81 => "rain showers",
+        82 => "violent rain showers",
+        85 | 86 => "snow showers",
+        95 => "thunderstorm",
+        96 | 99 => "thunderstorm with hail",
+        _ => "unknown conditions",
+    }
+}
+
+/// The forecast as a few plain sentences: the weather now, then one line per day.
+pub fn describe_forecast(body: &str, place: &Place, imperial: bool) -> Result<String, String> {
+    let answer = json(body, "The weather service")?;
+    if let Some(reason) = answer.get("reason").and_then(Value::as_str) {
+        return Err(format!("The weather service refused the request: {reason}"));
+    }
+    let (degrees, speed) = if imperial { ("°F", "mph") } else { ("°C", "km/h") };
+    let number = |v: Option<&Value>| v.and_then(Value::as_f64).map(|n| calc::format_number(n.round()));
+    let mut lines = vec![format!("Weather for {}", place.name)];
+    if let Some(current) = answer.get("current") {
+        let code = current.get("weather_code").and_then(Value::as_i64).unwrap_or(-1);
+        let mut now = format!(
+            "Now: {}{degrees}, {}",
+            number(current.get("temperature_2m")).unwrap_or_else(|| "?".into()),
+            conditions(code),
+        );
+        if let Some(feels) = number(current.get("apparent_temperature")) {
+            now.push_str(&format!(", feels like {feels}{degrees}"));
+        }
+        if let Some(wind) = number(current.get("wind_speed_10m")) {
+            now.push_str(&format!(", wind {wind} {speed}"));
+        }
+        lines.push(now);
+    }
+    let daily = answer.get("daily").ok_or("The weather service sent no forecast")?;
+    let series = |key: &str| daily.get(key).and_then(Value::as_array).cloned().unwrap_or_default();
+    let (dates, codes, highs, lows, rain) = (
+        series("time"), series("weather_code"), series("temperature_2m_max"), series("temperature_2m_min"), series("precipitation_probability_max"),
+    );
+    let today = Local::now().date_naive();
+    for (i, date) in dates.iter().enumerate() {
+        let Some(date) = date.as_str().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) else { continue };
+        let label = match (date - today).num_days() {
+            0 => "Today".to_string(),
+            1 => "Tomorrow".to_string(),
+            _ => date.format("%A %-d %B").to_string(),
+        };
+        let mut line = format!(
+            "{label}: {}, {}–{}{degrees}",
+            conditions(codes.get(i).and_then(Value::as_i64).unwrap_or(-1)),
+            number(lows.get(i)).unwrap_or_else(|| "?".into()),
+            number(highs.get(i)).unwrap_or_else(|| "?".into()),
+        );
+        if let Some(chance) = number(rain.get(i)) {
+            line.push_str(&format!(", {chance}% chance of rain"));
+        }
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+pub fn holidays_url(country: &str, year: i32) -> String {
+    format!("{HOLIDAYS_URL}/{year}/{}", country.to_uppercase())
+}
+
+/// A country's public holidays as `(date, name)`, nationwide ones only unless `regional`.
+pub fn parse_holidays(body: &str, country: &str, regional: bool) -> Result<Vec<(NaiveDate, String)>, String> {
+    if body.trim().is_empty() {
+        return Err(format!("No public holidays are known for \"{country}\"; use a two-letter country code such as US or DE"));
+    }
+    let answer = json(body, "The holiday service")?;
+    let list = answer.as_array().ok_or_else(|| format!("No public holidays are known for \"{country}\""))?;
+    let field = |h: &Value, key: &str| h.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    Ok(list.iter()
+        .filter(|h| regional || h.get("global").and_then(Value::as_bool).unwrap_or(true))
+        .filter_map(|h| {
+            let date = NaiveDate::parse_from_str(&field(h, "date"), "%Y-%m-%d").ok()?;
+            let (local, english) = (field(h, "localName"), field(h, "name"));
+            let name = if local.is_empty() || local == english { english } else { format!("{english} ({local})") };
+            Some((date, name))
+        })
+        .collect())
+}
+
+/// The years a holiday lookup from `from` over `days` days spans.
+pub fn years(from: NaiveDate, days: Option<u64>) -> Vec<i32> {
+    match days {
+        Some(days) => {
+            let to = from + chrono::Duration::days(days as i64);
+            (from.year()..=to.year()).collect()
+        }
+        None => vec![from.year()],
+    }
+}
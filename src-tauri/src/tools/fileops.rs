@@ -0,0 +1,288 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::files;
+
+/// Folders with more files than this are refused rather than walked for minutes.
+const MAX_FILES: usize = 20_000;
+/// Lines of a plan shown before "…and N more".
+const PREVIEW_LINES: usize = 20;
+
+/// Where `organize_files` puts files. The first rule that matches a file wins.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Rule {
+    /// Extensions without the dot, e.g. `["pdf", "docx"]`; empty matches any.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// A name pattern with `*` and `?`, e.g. `Invoice*`; empty matches any.
+    #[serde(default)]
+    pub pattern: String,
+    /// Destination folder, relative to the organized folder or absolute. `{year}`, `{month}`
+    /// and `{day}` are filled in from when the file was last changed, `{ext}` from its type.
+    pub to: String,
+}
+
+/// How `batch_rename` changes names. Extensions are kept unless `find` includes them.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RenameOptions {
+    /// Only files whose name matches, with `*` and `?`; empty for all.
+    #[serde(default)]
+    pub pattern: String,
+    #[serde(default)]
+    pub find: String,
+    #[serde(default)]
+    pub replace: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub suffix: String,
+    #[serde(default)]
+    pub lowercase: bool,
+    /// Numbers files in name order, replacing `{n}` in the new name.
+    #[serde(default)]
+    pub numbered: bool,
+}
+
+/// Case-insensitive match with `*` for any run of characters and `?` for one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.to_lowercase().chars().collect(), name.to_lowercase().chars().collect());
+    let (mut pi, mut ni, mut star, mut mark) = (0, 0, None, 0);
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            mark = ni;
+            pi += 1;
+        } else if let Some(s) = star {
+            pi = s + 1;
+            mark += 1;
+            ni = mark;
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// The files directly in `folder`, sorted by name.
+pub fn files_in(folder: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(folder).map_err(|e| format!("Can't open folder {}: {e}", folder.display()))?;
+    let mut files: Vec<PathBuf> = entries.flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .map(|e| e.path())
+        .collect();
+    if files.len() > MAX_FILES {
+        return Err(format!("{} has more than {MAX_FILES} files", folder.display()));
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn walk(folder: &Path, recursive: bool, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(folder).map_err(|e| format!("Can't open folder {}: {e}", folder.display()))?;
+    for entry in entries.flatten() {
+        let Ok(kind) = entry.file_type() else { continue };
+        if kind.is_file() {
+            out.push(entry.path());
+        } else if kind.is_dir() && recursive {
+            walk(&entry.path(), recursive, out)?;
+        }
+        if out.len() > MAX_FILES {
+            return Err(format!("{} has more than {MAX_FILES} files", folder.display()));
+        }
+    }
+    Ok(())
+}
+
+pub fn name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn hash(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Groups of files with identical contents. Only files sharing a size are read, and empty
+/// files are left out.
+pub fn find_duplicates(folder: &Path, recursive: bool) -> Result<String, String> {
+    let mut all = Vec::new();
+    walk(folder, recursive, &mut all)?;
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for path in all {
+        if let Ok(meta) = fs::metadata(&path) {
+            if meta.len() > 0 {
+                by_size.entry(meta.len()).or_default().push(path);
+            }
+        }
+    }
+    let mut groups: Vec<(u64, Vec<PathBuf>)> = Vec::new();
+    for (size, paths) in by_size.into_iter().filter(|(_, p)| p.len() > 1) {
+        let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            by_hash.entry(hash(&path)?).or_default().push(path);
+        }
+        groups.extend(by_hash.into_values().filter(|p| p.len() > 1).map(|mut p| { p.sort(); (size, p) }));
+    }
+    if groups.is_empty() {
+        return Ok(format!("No duplicate files in {}", folder.display()));
+    }
+    groups.sort_by_key(|(size, paths)| Reverse(size * paths.len() as u64));
+    let wasted: u64 = groups.iter().map(|(size, p)| size * (p.len() as u64 - 1)).sum();
+    let mut lines = vec![format!("{} groups of duplicates; removing the extra copies would free {wasted} bytes", groups.len())];
+    for (size, paths) in &groups {
+        lines.push(format!("{size} bytes each:"));
+        lines.extend(paths.iter().map(|p| format!("  {}", p.display())));
+    }
+    Ok(lines.join("\n"))
+}
+
+pub fn extension(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// Where each file in `folder` would go; files no rule matches, or that are already where
+/// their rule says, stay.
+pub fn organize_plan(folder: &Path, rules: &[Rule]) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut plan = Vec::new();
+    for path in files_in(folder)? {
+        let ext = extension(&path);
+        let Some(rule) = rules.iter().find(|r| {
+            (r.extensions.is_empty() || r.extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext)))
+                && (r.pattern.is_empty() || glob_match(&r.pattern, &name(&path)))
+        }) else {
+            continue;
+        };
+        let dest_dir = destination(folder, &rule.to, &path);
+        if dest_dir != folder {
+            plan.push((path.clone(), dest_dir.join(name(&path))));
+        }
+    }
+    Ok(plan)
+}
+
+/// The folder a `to` template names for `path`: `{year}`, `{month}` and `{day}` from when it
+/// was last changed, `{ext}` from its type, relative to `folder` unless absolute.
+pub fn destination(folder: &Path, to: &str, path: &Path) -> PathBuf {
+    let ext = extension(path);
+    let modified: DateTime<Local> = fs::metadata(path).and_then(|m| m.modified()).map(Into::into).unwrap_or_else(|_| Local::now());
+    let to = to
+        .replace("{year}", &modified.format("%Y").to_string())
+        .replace("{month}", &modified.format("%m").to_string())
+        .replace("{day}", &modified.format("%d").to_string())
+        .replace("{ext}", if ext.is_empty() { "other" } else { &ext });
+    match files::expand_path(&to) {
+        dir if dir.is_absolute() => dir,
+        dir => folder.join(dir),
+    }
+}
+
+/// New names for the files in `folder`; unchanged names are left out.
+pub fn rename_plan(folder: &Path, options: &RenameOptions) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut plan = Vec::new();
+    let files: Vec<PathBuf> = files_in(folder)?.into_iter()
+        .filter(|p| options.pattern.is_empty() || glob_match(&options.pattern, &name(p)))
+        .collect();
+    for (i, path) in files.iter().enumerate() {
+        let name = name(path);
+        let renamed = if options.find.is_empty() { name.clone() } else { name.replace(&options.find, &options.replace) };
+        let (stem, ext) = match renamed.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{ext}")),
+            _ => (renamed.clone(), String::new()),
+        };
+        let mut new_name = format!("{}{stem}{}{ext}", options.prefix, options.suffix);
+        if options.numbered {
+            let number = format!("{:0width$}", i + 1, width = files.len().to_string().len());
+            new_name = if new_name.contains("{n}") { new_name.replace("{n}", &number) } else { format!("{number} {new_name}") };
+        }
+        if options.lowercase {
+            new_name = new_name.to_lowercase();
+        }
+        if new_name != name && !new_name.is_empty() {
+            plan.push((path.clone(), folder.join(new_name)));
+        }
+    }
+    Ok(plan)
+}
+
+/// Folders under `folder` with nothing in them once their own empty folders are gone,
+/// deepest first. `folder` itself is kept.
+pub fn empty_folders(folder: &Path) -> Result<Vec<PathBuf>, String> {
+    fn visit(dir: &Path, out: &mut Vec<PathBuf>) -> bool {
+        let Ok(entries) = fs::read_dir(dir) else { return false };
+        let mut empty = true;
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() && !kind.is_symlink() => {
+                    if visit(&entry.path(), out) {
+                        out.push(entry.path());
+                    } else {
+                        empty = false;
+                    }
+                }
+                _ => empty = false,
+            }
+        }
+        empty
+    }
+    if !folder.is_dir() {
+        return Err(format!("{} isn't a folder", folder.display()));
+    }
+    let mut out = Vec::new();
+    visit(folder, &mut out);
+    Ok(out)
+}
+
+/// A plan under a heading, e.g. `a.pdf → ~/Documents/PDFs/a.pdf` per file, for the
+/// approval preview and the step's output.
+pub fn describe_plan(heading: &str, plan: &[(PathBuf, PathBuf)]) -> String {
+    if plan.is_empty() {
+        return "No files match, so nothing would change".into();
+    }
+    let mut lines = vec![format!("{heading} {} file(s):", plan.len())];
+    lines.extend(plan.iter().take(PREVIEW_LINES).map(|(from, to)| format!("  {} → {}", from.display(), to.display())));
+    if plan.len() > PREVIEW_LINES {
+        lines.push(format!("  …and {} more", plan.len() - PREVIEW_LINES));
+    }
+    lines.join("\n")
+}
+
+/// Carries out a move or rename plan, stopping at the first file that can't be moved and
+/// never overwriting. Targets that clash within the plan are refused up front.
+pub fn apply_plan(done: &str, plan: &[(PathBuf, PathBuf)]) -> Result<String, String> {
+    let mut targets: Vec<&PathBuf> = plan.iter().map(|(_, to)| to).collect();
+    targets.sort();
+    if let Some(clash) = targets.windows(2).find(|w| w[0] == w[1]) {
+        return Err(format!("More than one file would become {}", clash[0].display()));
+    }
+    for (i, (from, to)) in plan.iter().enumerate() {
+        files::move_file(from, to).map_err(|e| format!("{e} ({i} of {} done)", plan.len()))?;
+    }
+    Ok(describe_plan(done, plan))
+}
+
+pub fn remove_empty_folders(folder: &Path) -> Result<String, String> {
+    let empty = empty_folders(folder)?;
+    for dir in &empty {
+        fs::remove_dir(dir).map_err(|e| format!("Can't remove {}: {e}", dir.display()))?;
+    }
+    Ok(format!("Removed {} empty folder(s) under {}", empty.len(), folder.display()))
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are refused by `read_file` rather than loaded into a prompt.
+const MAX_READ_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Expands a leading `~` to the user's home folder.
+pub fn expand_path(path: &str) -> PathBuf {
+    let path = path.trim();
+    if path == "~" {
+        return dirs_next::home_dir().unwrap_or_default();
+    }
+    match path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+        Some(rest) => dirs_next::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<String, String> {
+    let meta = fs::metadata(path).map_err(|e| format!("Can't open {}: {e}", path.display()))?;
+    if meta.len() > MAX_READ_BYTES {
+        return Err(format!("{} is too large to read ({} bytes)", path.display(), meta.len()));
+    }
+    fs::read_to_string(path).map_err(|e| format!("Can't read {}: {e}", path.display()))
+}
+
+/// One line per entry: `name`, then `/` for folders or the size in bytes for files.
+pub fn list_directory(path: &Path) -> Result<String, String> {
+    let entries = fs::read_dir(path).map_err(|e| format!("Can't open folder {}: {e}", path.display()))?;
+    let mut lines = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        match entry.metadata() {
+            Ok(meta) if meta.is_dir() => lines.push(format!("{name}/")),
+            Ok(meta) => lines.push(format!("{name}\t{}", meta.len())),
+            Err(_) => lines.push(name),
+        }
+    }
+    lines.sort_by_key(|l| l.to_lowercase());
+    Ok(lines.join("\n"))
+}
+
+pub fn write_file(path: &Path, content: &str) -> Result<String, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Can't create {}: {e}", parent.display()))?;
+    }
+    fs::write(path, content).map_err(|e| format!("Can't write {}: {e}", path.display()))?;
+    Ok(format!("Wrote {} bytes to {}", content.len(), path.display()))
+}
+
+/// Writes a file that must not exist yet, creating its folder if needed.
+pub fn create_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Can't create {}: {e}", parent.display()))?;
+    }
+    let mut file = fs::File::create_new(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => format!("{} already exists", path.display()),
+        _ => format!("Can't create {}: {e}", path.display()),
+    })?;
+    file.write_all(bytes).map_err(|e| format!("Can't write {}: {e}", path.display()))
+}
+
+pub fn move_file(from: &Path, to: &Path) -> Result<String, String> {
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Can't create {}: {e}", parent.display()))?;
+    }
+    // rename fails across drives; fall back to copy + delete.
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to).map_err(|e| format!("Can't move {}: {e}", from.display()))?;
+        fs::remove_file(from).map_err(|e| format!("Copied but couldn't remove {}: {e}", from.display()))?;
+    }
+    Ok(format!("Moved {} to {}", from.display(), to.display()))
+}
+
+pub fn delete_file(path: &Path) -> Result<String, String> {
+    let meta = fs::metadata(path).map_err(|e| format!("Can't find {}: {e}", path.display()))?;
+    if meta.is_dir() {
+        return Err(format!("{} is a folder; only files can be deleted", path.display()));
+    }
+    fs::remove_file(path).map_err(|e| format!("Can't delete {}: {e}", path.display()))?;
+    Ok(format!("Deleted {}", path.display()))
+}
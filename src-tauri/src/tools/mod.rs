@@ -0,0 +1,1370 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::{DbState, read_setting};
+use crate::cache;
+use crate::fixtures::ActiveFixture;
+use crate::focus;
+use crate::format;
+use crate::grace;
+use crate::meetings;
+use crate::context;
+use crate::critique;
+use crate::datasources;
+use crate::documents;
+use crate::downloads;
+use crate::net::{self, Proxy};
+use crate::notifications::{self, Event};
+use crate::rag;
+use crate::rates;
+use crate::receipts;
+use crate::secure_notes::{self, SecureNote};
+use crate::transcribe;
+use crate::translate;
+
+pub mod calc;
+pub mod checksum;
+pub mod compress;
+pub mod data;
+pub mod fileops;
+pub mod files;
+pub mod pdf;
+pub mod text;
+pub mod transform;
+pub mod web;
+
+/// A fresh folder under the system temp folder for one test, deleted with everything in it
+/// when dropped, so failing tests don't leave files behind either.
+#[cfg(test)]
+pub(crate) struct Scratch(PathBuf);
+
+#[cfg(test)]
+impl Scratch {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("openclaw-{name}-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for Scratch {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// What a tool knows about the run it is executing in.
+pub struct ToolContext<'a> {
+    pub app: &'a AppHandle,
+    pub agent_id: &'a str,
+    pub run_id: &'a str,
+    /// Index of the step being run, for artifacts it produces.
+    pub step_index: usize,
+    /// When set, file paths resolve inside the fixture's scratch folder and the inbox is fake.
+    pub fixture: Option<&'a ActiveFixture>,
+}
+
+impl ToolContext<'_> {
+    fn proxy(&self) -> Result<Proxy, String> {
+        let db = self.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        net::proxy(&conn)
+    }
+
+    /// Answers a repeat of an identical request from the response cache unless the agent opted out.
+    fn cached(&self, kind: &str, request: &Value, fetch: impl FnOnce() -> Result<String, String>) -> Result<String, String> {
+        self.cached_within(kind, request, None, fetch)
+    }
+
+    /// [`Self::cached`] for answers that go stale sooner than the cache's TTL.
+    fn cached_within(
+        &self,
+        kind: &str,
+        request: &Value,
+        max_age: Option<chrono::Duration>,
+        fetch: impl FnOnce() -> Result<String, String>,
+    ) -> Result<String, String> {
+        let db = self.app.state::<DbState>();
+        let key = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let key = cache::enabled_for(&conn, self.agent_id).then(|| cache::key(kind, request));
+            if let Some(hit) = key.as_deref().and_then(|k| cache::get_within(&conn, k, max_age)) {
+                return Ok(hit);
+            }
+            key
+        };
+        let output = fetch()?;
+        if let Some(key) = key {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            cache::put(&conn, kind, &key, &output);
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn path(&self, raw: &str) -> PathBuf {
+        match self.fixture {
+            Some(fixture) => fixture.remap(raw),
+            None => files::expand_path(raw),
+        }
+    }
+}
+
+/// Describes one built-in tool an agent workflow can call.
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Coarse permission the tool needs, shown to the user before an agent is saved.
+    pub permission: &'static str,
+    /// Changes something outside the app (files, mailboxes, remote services).
+    pub side_effect: bool,
+    /// Cannot be undone once performed (sending, deleting).
+    pub irreversible: bool,
+    pub network: bool,
+    /// JSON schema of the `params` object.
+    pub params: Value,
+}
+
+const SEND_AT: &str = "When to send, in local time, e.g. \"tomorrow 08:00\" or \"2024-06-01 08:00\"; leave out to send right away";
+
+fn string_param(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn schema(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Every tool known to the executor, in the order they are presented to users and models.
+pub fn registry() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec {
+            name: "read_file",
+            description: "Read a text file from disk",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({ "path": string_param("File to read") }), &["path"]),
+        },
+        ToolSpec {
+            name: "list_directory",
+            description: "List the files in a folder",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({ "path": string_param("Folder to list") }), &["path"]),
+        },
+        ToolSpec {
+            name: "write_file",
+            description: "Create or overwrite a text file",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("File to write"),
+                "content": string_param("Text to write"),
+            }), &["path", "content"]),
+        },
+        ToolSpec {
+            name: "move_file",
+            description: "Move or rename a file",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "from": string_param("Current path"),
+                "to": string_param("New path"),
+            }), &["from", "to"]),
+        },
+        ToolSpec {
+            name: "delete_file",
+            description: "Delete a file",
+            permission: "files.delete",
+            side_effect: true,
+            irreversible: true,
+            network: false,
+            params: schema(json!({ "path": string_param("File to delete") }), &["path"]),
+        },
+        ToolSpec {
+            name: "find_duplicates",
+            description: "Find files with identical contents in a folder",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "folder": string_param("Folder to search"),
+                "recursive": { "type": "boolean", "description": "Also search the folders inside it" },
+            }), &["folder"]),
+        },
+        ToolSpec {
+            name: "organize_files",
+            description: "Move the files in a folder into other folders by type, name or date",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: true,
+            network: false,
+            params: schema(json!({
+                "folder": string_param("Folder to tidy"),
+                "rules": {
+                    "type": "array",
+                    "description": "First matching rule wins. `to` may use {year}, {month}, {day} and {ext}",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "extensions": { "type": "array", "items": { "type": "string" } },
+                            "pattern": string_param("Name pattern with * and ?"),
+                            "to": string_param("Destination folder, relative to the folder or absolute"),
+                        },
+                        "required": ["to"],
+                    },
+                },
+            }), &["folder", "rules"]),
+        },
+        ToolSpec {
+            name: "batch_rename",
+            description: "Rename many files in a folder at once",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: true,
+            network: false,
+            params: schema(json!({
+                "folder": string_param("Folder with the files"),
+                "pattern": string_param("Only names matching this, with * and ?"),
+                "find": string_param("Text to replace in each name"),
+                "replace": string_param("What to replace it with"),
+                "prefix": string_param("Text to add before each name"),
+                "suffix": string_param("Text to add after each name, before the extension"),
+                "lowercase": { "type": "boolean", "description": "Make names lowercase" },
+                "numbered": { "type": "boolean", "description": "Number the files in name order; {n} places the number" },
+            }), &["folder"]),
+        },
+        ToolSpec {
+            name: "remove_empty_folders",
+            description: "Delete the empty folders inside a folder",
+            permission: "files.delete",
+            side_effect: true,
+            irreversible: true,
+            network: false,
+            params: schema(json!({ "folder": string_param("Folder to clean up") }), &["folder"]),
+        },
+        ToolSpec {
+            name: "checksum",
+            description: "Compute the SHA-256, SHA-1 or MD5 checksum of a file, or of every file in a folder, optionally checking it against an expected value",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("File or folder"),
+                "algorithm": { "type": "string", "enum": ["sha256", "sha1", "md5"], "description": "sha256 unless given" },
+                "expected": string_param("Checksum the file should have, e.g. from the download page"),
+            }), &["path"]),
+        },
+        ToolSpec {
+            name: "verify_manifest",
+            description: "Check that every file listed in a checksum manifest (such as SHA256SUMS) is present and unchanged",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "manifest": string_param("Manifest file with one checksum and path per line"),
+                "folder": string_param("Folder the paths are relative to; the manifest's folder when empty"),
+                "algorithm": { "type": "string", "enum": ["sha256", "sha1", "md5"], "description": "Guessed from the checksums unless given" },
+            }), &["manifest"]),
+        },
+        ToolSpec {
+            name: "compress_files",
+            description: "Pack files and folders into a .zip or .tar.gz, optionally protected with a password (zip only)",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "Files and folders to pack" },
+                "to": string_param("Archive to create, ending in .zip, .tar.gz or .tgz"),
+                "password": string_param("Encrypts a zip with AES-256"),
+            }), &["paths", "to"]),
+        },
+        ToolSpec {
+            name: "extract_archive",
+            description: "Unpack a .zip or .tar.gz archive into a folder",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("Archive to unpack"),
+                "to": string_param("Folder to unpack into; beside the archive, named after it, when empty"),
+                "password": string_param("Password of a protected zip"),
+            }), &["path"]),
+        },
+        ToolSpec {
+            name: "merge_pdfs",
+            description: "Combine several PDFs into one, in the order given",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "PDFs to combine, first to last" },
+                "to": string_param("PDF to create"),
+            }), &["paths", "to"]),
+        },
+        ToolSpec {
+            name: "split_pdf",
+            description: "Split a PDF into separate files of one or more pages each",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("PDF to split"),
+                "every": { "type": "integer", "description": "Pages per file; 1 unless given" },
+                "to": string_param("Folder for the parts; the PDF's folder when empty"),
+            }), &["path"]),
+        },
+        ToolSpec {
+            name: "extract_pdf_pages",
+            description: "Save some pages of a PDF as a new PDF, in the order listed",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("PDF to take pages from"),
+                "pages": string_param("Pages like 1-3, 5, 8- (8- runs to the end)"),
+                "to": string_param("PDF to create; beside the original when empty"),
+            }), &["path", "pages"]),
+        },
+        ToolSpec {
+            name: "read_pdf_form",
+            description: "List the fillable fields of a PDF form with their types, current values and choices",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({ "path": string_param("PDF form") }), &["path"]),
+        },
+        ToolSpec {
+            name: "fill_pdf_form",
+            description: "Fill in a PDF form's fields and save the filled copy",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("PDF form"),
+                "values": { "type": "object", "description": "Field name to value; true or false for checkboxes, the option name for radio buttons" },
+                "to": string_param("PDF to create; beside the form when empty"),
+            }), &["path", "values"]),
+        },
+        ToolSpec {
+            name: "stamp_pdf",
+            description: "Stamp text such as \"Approved {date}\" or \"Page {page} of {pages}\" onto the pages of a PDF",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "path": string_param("PDF to stamp"),
+                "text": string_param("Text to stamp; {date} is today, {page} and {pages} the page numbers"),
+                "position": { "type": "string", "enum": ["top-left", "top", "top-right", "center", "bottom-left", "bottom", "bottom-right"], "description": "top-right unless given" },
+                "size": { "type": "number", "description": "Font size in points; 10 unless given" },
+                "color": { "type": "string", "enum": ["black", "gray", "red", "blue"] },
+                "pages": string_param("Pages to stamp like 1-3, 5; all when empty"),
+                "to": string_param("PDF to create; beside the original when empty"),
+            }), &["path", "text"]),
+        },
+        ToolSpec {
+            name: "generate_document",
+            description: "Create a PDF or Word document, such as an invoice or letter, by filling one of the user's document templates with data",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "template": string_param("Name of the document template"),
+                "data": { "type": "object", "description": "Values for the template's placeholders; lists fill repeated rows such as invoice items" },
+                "to": string_param("File to create, ending in .pdf or .docx; in Documents, named by the template, when empty"),
+                "review": { "type": "boolean", "description": "Show the filled-in document for approval before it is saved" },
+            }), &["template", "data"]),
+        },
+        ToolSpec {
+            name: "extract_receipt",
+            description: "Read the vendor, date, total, tax and currency from a receipt or invoice (a photo, scan or PDF), with how sure each one is",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({ "path": string_param("Receipt or invoice: PDF, PNG, JPEG, GIF or WebP") }), &["path"]),
+        },
+        ToolSpec {
+            name: "log_receipt",
+            description: "Add a receipt read by extract_receipt to the expenses spreadsheet (a CSV file); waits for approval when any field is uncertain",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "receipt": { "type": "object", "description": "What extract_receipt returned" },
+                "ledger": string_param("CSV file to add it to; the one in settings, or ~/Documents/Receipts.csv, when empty"),
+                "min_confidence": { "type": "number", "description": "Fields less certain than this (0 to 1, default 0.8) need approval" },
+            }), &["receipt"]),
+        },
+        ToolSpec {
+            name: "organize_downloads",
+            description: "Tidy the downloads folder by the user's download rules: move or delete files by type, name, age or source site",
+            permission: "files.delete",
+            side_effect: true,
+            irreversible: true,
+            network: false,
+            params: schema(json!({ "folder": string_param("Downloads folder; ~/Downloads when empty") }), &[]),
+        },
+        ToolSpec {
+            name: "transcribe_audio",
+            description: "Transcribe a recording, such as a meeting or voicemail, with timestamps",
+            permission: "files.read",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({ "path": string_param("Audio file (mp3, m4a, wav, ogg, webm or flac)") }), &["path"]),
+        },
+        ToolSpec {
+            name: "file_meeting_notes",
+            description: "File meeting notes into the notes folder and add the action items as tasks",
+            permission: "files.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "title": string_param("Meeting title"),
+                "summary": string_param("Summary of the meeting"),
+                "decisions": { "type": "array", "items": { "type": "string" }, "description": "Decisions made" },
+                "action_items": {
+                    "type": "array",
+                    "items": { "type": "object", "properties": { "task": { "type": "string" }, "owner": { "type": "string" }, "due": { "type": "string" } } },
+                    "description": "Action items, each with a task and optionally an owner and due date",
+                },
+                "transcript": string_param("Full transcript to keep with the notes"),
+            }), &["title", "summary"]),
+        },
+        ToolSpec {
+            name: "http_get",
+            description: "Fetch a web page or API response",
+            permission: "network",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({ "url": string_param("Address to fetch") }), &["url"]),
+        },
+        ToolSpec {
+            name: "http_post",
+            description: "Send data to a web address or webhook",
+            permission: "network",
+            side_effect: true,
+            irreversible: true,
+            network: true,
+            params: schema(json!({
+                "url": string_param("Address to post to"),
+                "body": string_param("Request body (JSON or text)"),
+                "send_at": string_param(SEND_AT),
+            }), &["url", "body"]),
+        },
+        ToolSpec {
+            name: "llm_prompt",
+            description: "Ask the language model to write, summarize or decide something",
+            permission: "llm",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({
+                "prompt": string_param("Instruction for the model"),
+                "schema": { "type": "object", "description": "Optional JSON schema the reply must match" },
+                "images": { "type": "array", "items": { "type": "string" }, "description": "Ids of attached images for the model to look at" },
+            }), &["prompt"]),
+        },
+        ToolSpec {
+            name: "critique",
+            description: "Have a second model check a draft against the goal and revise it until approved",
+            permission: "llm",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({
+                "draft": string_param("Text to review, usually {{previous_output}}"),
+                "goal": string_param("What the draft has to achieve"),
+                "model": string_param("Reviewer model; defaults to the llm_reviewer_model setting"),
+                "max_revisions": { "type": "integer", "description": "Revisions before giving up (default 2, at most 5)" },
+            }), &["draft", "goal"]),
+        },
+        ToolSpec {
+            name: "translate",
+            description: "Translate text into another language and say which language it was written in",
+            permission: "llm",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({
+                "text": string_param("Text to translate, e.g. an email body"),
+                "target": string_param("Language to translate into, by name or code (English, de, pt-BR, …)"),
+                "source": string_param("Language the text is in, if known; detected otherwise"),
+            }), &["text", "target"]),
+        },
+        ToolSpec {
+            name: "read_inbox",
+            description: "Read recent emails from the connected inbox",
+            permission: "email.read",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({ "limit": { "type": "integer", "description": "How many messages" } }), &[]),
+        },
+        ToolSpec {
+            name: "send_email",
+            description: "Send an email",
+            permission: "email.send",
+            side_effect: true,
+            irreversible: true,
+            network: true,
+            params: schema(json!({
+                "to": string_param("Recipient address"),
+                "subject": string_param("Subject line"),
+                "body": string_param("Message body"),
+                "send_at": string_param(SEND_AT),
+            }), &["to", "subject", "body"]),
+        },
+        ToolSpec {
+            name: "browser",
+            description: "Hand a browser task to the OpenClaw agent (search, post, comment)",
+            permission: "browser",
+            side_effect: true,
+            irreversible: true,
+            network: true,
+            params: schema(json!({ "instruction": string_param("What the browser agent should do") }), &["instruction"]),
+        },
+        ToolSpec {
+            name: "search_knowledge",
+            description: "Find passages in the user's indexed documents",
+            permission: "knowledge.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "query": string_param("What to look for"),
+                "limit": { "type": "integer", "description": "How many passages" },
+            }), &["query"]),
+        },
+        ToolSpec {
+            name: "start_focus",
+            description: "Start a focus session that blocks the user's distracting sites and apps for a while",
+            permission: "system",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({ "minutes": { "type": "integer", "description": "How long the session lasts" } }), &["minutes"]),
+        },
+        ToolSpec {
+            name: "get_weather",
+            description: "Get the current weather and the forecast for a place",
+            permission: "network",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({
+                "location": string_param("City, optionally with region or country (\"Portland, Oregon\"), or \"latitude, longitude\"; the home location in Settings when empty"),
+                "days": { "type": "integer", "description": "Days of forecast, 1 to 7 (default 3)" },
+            }), &[]),
+        },
+        ToolSpec {
+            name: "public_holidays",
+            description: "List a country's public holidays for a year or the coming days",
+            permission: "network",
+            side_effect: false,
+            irreversible: false,
+            network: true,
+            params: schema(json!({
+                "country": string_param("Two-letter country code such as US, GB or DE; the user's locale when empty"),
+                "year": { "type": "integer", "description": "Year to list; this year when empty" },
+                "upcoming_days": { "type": "integer", "description": "Only holidays from today through this many days ahead" },
+                "regional": { "type": "boolean", "description": "Include holidays kept only in some states or regions" },
+            }), &[]),
+        },
+        ToolSpec {
+            name: "calculate",
+            description: "Work out arithmetic exactly instead of estimating it: + - * / ^, parentheses, mod, percentages (15% of 80), sqrt, round(x, 2), min, max, sum, avg and more",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({ "expression": string_param("The calculation, e.g. (120 + 45.5) * 1.2") }), &["expression"]),
+        },
+        ToolSpec {
+            name: "convert",
+            description: "Convert an amount between currencies (from cached exchange rates) or units of length, weight, volume, area, speed, time, data size, energy or temperature",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "value": { "type": "number", "description": "The amount to convert" },
+                "from": string_param("Currency code (USD, EUR, …) or unit (km, lb, °F, GB, …) it is in"),
+                "to": string_param("Currency code or unit to express it in"),
+            }), &["value", "from", "to"]),
+        },
+        ToolSpec {
+            name: "list_data_sources",
+            description: "List the databases and spreadsheets the user has set up for querying, with their tables and columns",
+            permission: "data.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({}), &[]),
+        },
+        ToolSpec {
+            name: "query_data",
+            description: "Answer a question from one of the user's data sources with a read-only SQL SELECT query (SQLite dialect)",
+            permission: "data.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "source": string_param("Name of the data source, as list_data_sources gives it"),
+                "sql": string_param("A single SELECT query; each CSV file is a table named after the file"),
+                "limit": { "type": "integer", "description": "Rows to return at most; the source's limit when larger or not given" },
+            }), &["source", "sql"]),
+        },
+        ToolSpec {
+            name: "generate_password",
+            description: "Generate a strong random password and keep it in a new secure note; the password itself is never shown to the agent, only to the user",
+            permission: "secrets.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "title": string_param("Title of the new secure note, e.g. the account it is for"),
+                "username": string_param("Username or email address of the account"),
+                "url": string_param("Address of the site or service"),
+                "length": { "type": "integer", "description": "Number of characters, 8 to 128; 20 when not given" },
+                "symbols": { "type": "boolean", "description": "Include symbols such as ! and #; on unless turned off" },
+            }), &["title"]),
+        },
+        ToolSpec {
+            name: "save_secure_note",
+            description: "Keep a secret, such as a recovery code or PIN, in a new encrypted secure note instead of in the run's output",
+            permission: "secrets.write",
+            side_effect: true,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "title": string_param("Title of the new secure note"),
+                "secret": string_param("The text to keep secret"),
+                "username": string_param("Username or email address it belongs to"),
+                "url": string_param("Address of the site or service"),
+            }), &["title", "secret"]),
+        },
+        ToolSpec {
+            name: "list_secure_notes",
+            description: "List the titles, usernames and sites of the user's secure notes, without their secrets",
+            permission: "secrets.read",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({}), &[]),
+        },
+        ToolSpec {
+            name: "transform_data",
+            description: "Query and reshape JSON or CSV data with a jq-style query (e.g. map(select(.amount > 100)) | group_by(.region)) and convert between JSON and CSV",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "data": string_param("JSON or CSV text, e.g. {{step_1}}; CSV rows become objects keyed by the header"),
+                "query": string_param("jq-style query such as .items[] | {name, price}; the data as it is when empty"),
+                "input_format": { "type": "string", "enum": ["auto", "json", "csv"], "description": "auto unless given" },
+                "output_format": { "type": "string", "enum": ["json", "csv"], "description": "json unless given" },
+                "columns": { "type": "array", "items": { "type": "string" }, "description": "CSV output columns, in order" },
+                "delimiter": string_param("CSV input separator; guessed from the first line when empty"),
+            }), &["data"]),
+        },
+        ToolSpec {
+            name: "regex_extract",
+            description: "Pull every match of a regular expression out of text, with its capture groups",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "text": string_param("Text to search"),
+                "pattern": string_param("Regular expression; named groups like (?P<amount>\\d+) name the fields of each match"),
+                "all": { "type": "boolean", "description": "Every match rather than only the first (default true)" },
+                "ignore_case": { "type": "boolean", "description": "Match upper and lower case alike" },
+            }), &["text", "pattern"]),
+        },
+        ToolSpec {
+            name: "diff_text",
+            description: "Compare two versions of a text line by line and show the changes as a unified diff",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "old": string_param("The earlier version"),
+                "new": string_param("The later version"),
+                "context": { "type": "integer", "description": "Unchanged lines shown around each change (default 3)" },
+                "old_name": string_param("Label for the earlier version in the diff header"),
+                "new_name": string_param("Label for the later version in the diff header"),
+            }), &["old", "new"]),
+        },
+        ToolSpec {
+            name: "fill_template",
+            description: "Fill {{placeholders}} in a text with values, with {{#list}}…{{/list}} repeating for each entry",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "template": string_param("Text with {{name}} placeholders; dotted names like {{customer.name}} reach into objects"),
+                "data": { "type": "object", "description": "The values, or JSON text such as a structured prompt's reply" },
+                "strict": { "type": "boolean", "description": "Fail when a placeholder has no value instead of leaving it blank" },
+            }), &["template", "data"]),
+        },
+        ToolSpec {
+            name: "change_case",
+            description: "Rewrite text in another case or as a slug, e.g. Title Case, snake_case or url-friendly-slug",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "text": string_param("Text to rewrite"),
+                "case": { "type": "string", "enum": text::CASES, "description": "trim tidies whitespace and keeps the case" },
+            }), &["text", "case"]),
+        },
+        ToolSpec {
+            name: "format_date",
+            description: "Read a date written almost any way and write it in another format, optionally moved by some days",
+            permission: "compute",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "date": string_param("The date, e.g. 2026-10-15, 15.10.2026, Oct 15 2026 3pm, an email Date header or today"),
+                "format": string_param("iso (default), locale, long, rfc2822, unix, or a strftime pattern such as %d/%m/%Y"),
+                "add_days": { "type": "integer", "description": "Days to move the date by; negative goes back" },
+            }), &["date"]),
+        },
+        ToolSpec {
+            name: "notify",
+            description: "Show a desktop notification",
+            permission: "notifications",
+            side_effect: false,
+            irreversible: false,
+            network: false,
+            params: schema(json!({
+                "title": string_param("Notification title"),
+                "message": string_param("Notification text"),
+            }), &["title"]),
+        },
+    ]
+}
+
+pub fn find(name: &str) -> Option<ToolSpec> {
+    registry().into_iter().find(|t| t.name == name)
+}
+
+fn str_param<'a>(params: &'a Value, key: &str) -> Result<&'a str, String> {
+    params.get(key)
+        .and_then(Value::as_str)
+        .filter(|v| !v.trim().is_empty())
+        .ok_or_else(|| format!("Missing parameter '{key}'"))
+}
+
+/// An optional array-of-strings parameter; anything else counts as empty.
+fn string_list(params: &Value, key: &str) -> Vec<String> {
+    params.get(key)
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn rules(params: &Value) -> Result<Vec<fileops::Rule>, String> {
+    serde_json::from_value(params.get("rules").cloned().unwrap_or_default()).map_err(|e| format!("Invalid rules: {e}"))
+}
+
+/// Accepts a single path as well as a list, for steps fed by an earlier step's output.
+fn paths(params: &Value) -> Vec<String> {
+    match params.get("paths") {
+        Some(Value::String(path)) => vec![path.clone()],
+        _ => string_list(params, "paths"),
+    }
+}
+
+/// Adds a secure note from a step's `title`, `username` and `url`. Never replaces one, so a
+/// step can't overwrite a password the user still relies on.
+fn save_secure_note(ctx: &ToolContext, params: &Value, secret: &str) -> Result<SecureNote, String> {
+    let field = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    let note = SecureNote {
+        id: String::new(),
+        title: str_param(params, "title")?.to_string(),
+        username: field("username"),
+        url: field("url"),
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+    let db = ctx.app.state::<DbState>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    secure_notes::save(&conn, note, Some(secret))
+}
+
+fn password(params: &Value) -> Option<&str> {
+    params.get("password").and_then(Value::as_str).filter(|p| !p.is_empty())
+}
+
+fn unpack_to(ctx: &ToolContext, params: &Value, archive: &std::path::Path) -> PathBuf {
+    params.get("to").and_then(Value::as_str).filter(|p| !p.trim().is_empty())
+        .map_or_else(|| compress::default_destination(archive), |p| ctx.path(p))
+}
+
+fn algorithm(params: &Value) -> Result<Option<checksum::Algorithm>, String> {
+    params.get("algorithm").and_then(Value::as_str).filter(|a| !a.is_empty()).map(checksum::Algorithm::parse).transpose()
+}
+
+/// Where a PDF tool saves: `to`, or a copy named like `report stamped.pdf` beside the input.
+fn pdf_output(ctx: &ToolContext, params: &Value, input: &std::path::Path, suffix: &str) -> PathBuf {
+    params.get("to").and_then(Value::as_str).filter(|p| !p.trim().is_empty())
+        .map_or_else(|| pdf::beside(input, suffix), |p| ctx.path(p))
+}
+
+fn split_folder(ctx: &ToolContext, params: &Value, input: &std::path::Path) -> PathBuf {
+    params.get("to").and_then(Value::as_str).filter(|p| !p.trim().is_empty())
+        .map_or_else(|| input.parent().map(PathBuf::from).unwrap_or_default(), |p| ctx.path(p))
+}
+
+fn stamp_pdf(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let input = ctx.path(str_param(params, "path")?);
+    let mut text = str_param(params, "text")?.to_string();
+    if text.contains("{date}") {
+        let locale = format::locale(&ctx.app.state::<DbState>())?;
+        text = text.replace("{date}", &format::date(&locale, &chrono::Local::now()));
+    }
+    let option = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    let stamp = pdf::Stamp {
+        text,
+        position: option("position"),
+        size: params.get("size").and_then(Value::as_f64).unwrap_or(0.0),
+        color: option("color"),
+        pages: option("pages"),
+    };
+    pdf::stamp(&input, &stamp, &pdf_output(ctx, params, &input, "stamped"))
+}
+
+/// A public data service's answer for `url`, from the cache while younger than `max_age`.
+fn public_data(ctx: &ToolContext, service: &'static str, url: &str, max_age: chrono::Duration) -> Result<String, String> {
+    ctx.cached_within("data", &json!({ "url": url }), Some(max_age), || {
+        data::throttle(service)?;
+        web::http_get(&ctx.proxy()?, url)
+    })
+}
+
+/// The country part of the user's locale, e.g. `US` for `en-US`.
+fn locale_country(ctx: &ToolContext) -> Result<Option<String>, String> {
+    let locale = format::locale(&ctx.app.state::<DbState>())?;
+    Ok(locale.rsplit('-').next().filter(|c| c.len() == 2 && c.chars().all(|c| c.is_ascii_alphabetic())).map(str::to_uppercase))
+}
+
+fn weather(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let location = match params.get("location").and_then(Value::as_str).filter(|l| !l.trim().is_empty()) {
+        Some(location) => location.to_string(),
+        None => {
+            let db = ctx.app.state::<DbState>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            read_setting(&conn, data::HOME_LOCATION_SETTING)?.filter(|l| !l.trim().is_empty())
+                .ok_or("Say which place, or set your home location in Settings")?
+        }
+    };
+    // The US and the couple of countries that share its units get Fahrenheit and mph.
+    let imperial = matches!(locale_country(ctx)?.as_deref(), Some("US" | "LR" | "MM"));
+    let place = match data::coordinates(&location) {
+        Some(place) => place,
+        None => {
+            let found = public_data(ctx, "The place search", &data::geocoding_url(&location), chrono::Duration::days(30))?;
+            data::pick_place(&found, &location)?
+        }
+    };
+    let days = params.get("days").and_then(Value::as_u64).unwrap_or(3);
+    let forecast = public_data(ctx, "The weather service", &data::forecast_url(&place, days, imperial), chrono::Duration::minutes(30))?;
+    data::describe_forecast(&forecast, &place, imperial)
+}
+
+fn public_holidays(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let country = match params.get("country").and_then(Value::as_str).filter(|c| !c.trim().is_empty()) {
+        Some(country) => country.trim().to_uppercase(),
+        None => locale_country(ctx)?.ok_or("Say which country, as a two-letter code such as US or DE")?,
+    };
+    if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("\"{country}\" isn't a two-letter country code such as US or DE"));
+    }
+    let today = chrono::Local::now().date_naive();
+    let year = params.get("year").and_then(Value::as_i64).map(|y| y as i32);
+    let upcoming = params.get("upcoming_days").and_then(Value::as_u64).filter(|_| year.is_none());
+    let regional = params.get("regional").and_then(Value::as_bool).unwrap_or(false);
+    let mut holidays = Vec::new();
+    for year in year.map_or_else(|| data::years(today, upcoming), |y| vec![y]) {
+        let body = public_data(ctx, "The holiday service", &data::holidays_url(&country, year), chrono::Duration::days(7))
+            .map_err(|e| if e.contains("status 404") { format!("No public holidays are known for {country} in {year}") } else { e })?;
+        holidays.extend(data::parse_holidays(&body, &country, regional)?);
+    }
+    holidays.dedup();
+    let heading = match (upcoming, year) {
+        (Some(days), _) => {
+            holidays.retain(|(date, _)| *date >= today && *date <= today + chrono::Duration::days(days as i64));
+            format!("in the next {days} days")
+        }
+        (None, Some(year)) => format!("in {year}"),
+        (None, None) => format!("in {}", today.format("%Y")),
+    };
+    if holidays.is_empty() {
+        return Ok(format!("No public holidays in {country} {heading}"));
+    }
+    let lines: Vec<String> = holidays.iter().map(|(date, name)| format!("{} ({}): {name}", date, date.format("%A"))).collect();
+    Ok(format!("Public holidays in {country} {heading}:\n{}", lines.join("\n")))
+}
+
+/// A text parameter that may be empty, unlike `str_param`'s.
+fn text_param<'a>(params: &'a Value, key: &str) -> Result<&'a str, String> {
+    params.get(key).and_then(Value::as_str).ok_or_else(|| format!("Missing parameter '{key}'"))
+}
+
+fn transform_data(params: &Value) -> Result<String, String> {
+    let option = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or_default().trim().to_lowercase();
+    let delimiter = match params.get("delimiter").and_then(Value::as_str).unwrap_or_default() {
+        "" => None,
+        "\\t" | "tab" => Some('\t'),
+        text => Some(text.chars().next().unwrap_or(',')),
+    };
+    let data = params.get("data").ok_or("Missing parameter 'data'")?;
+    let (input, header) = transform::read_input(data, &option("input_format"), delimiter)?;
+    let output = match params.get("query").and_then(Value::as_str).map(str::trim) {
+        Some(query) if !query.is_empty() => transform::query(&input, query)?,
+        _ => input,
+    };
+    match option("output_format").as_str() {
+        "" | "json" => serde_json::to_string_pretty(&output).map_err(|e| e.to_string()),
+        "csv" => Ok(transform::to_csv(&output, &string_list(params, "columns"), &header)),
+        other => Err(format!("Unknown output format \"{other}\"; use json or csv")),
+    }
+}
+
+fn fill_template(params: &Value) -> Result<String, String> {
+    let data = match params.get("data") {
+        Some(Value::String(text)) => serde_json::from_str(text).map_err(|e| format!("The data isn't valid JSON: {e}"))?,
+        Some(value @ Value::Object(_)) => value.clone(),
+        _ => return Err("Missing parameter 'data'".into()),
+    };
+    let (text, missing) = documents::fill(text_param(params, "template")?, &data)?;
+    if !missing.is_empty() && params.get("strict").and_then(Value::as_bool).unwrap_or(false) {
+        return Err(format!("The data has no value for {}", missing.join(", ")));
+    }
+    Ok(text)
+}
+
+/// Numeric dates are read in the user's locale's order, so 03/04 is 3 April in Germany.
+fn format_date(ctx: &ToolContext, params: &Value) -> Result<String, String> {
+    let locale = format::locale(&ctx.app.state::<DbState>())?;
+    let (time, has_time) = text::parse_date(str_param(params, "date")?, format::day_before_month(&locale), chrono::Local::now().naive_local())?;
+    let days = params.get("add_days").and_then(Value::as_i64).unwrap_or(0);
+    let time = time.checked_add_signed(chrono::Duration::days(days)).ok_or("That date is out of range")?;
+    text::format_date(time, has_time, params.get("format").and_then(Value::as_str).unwrap_or_default(), &locale)
+}
+
+fn rename_options(params: &Value) -> fileops::RenameOptions {
+    serde_json::from_value(params.clone()).unwrap_or_default()
+}
+
+/// The dry run of a file organizing tool, listing each file it would touch, so approving
+/// one shows exactly what will happen.
+fn file_plan(ctx: &ToolContext, tool: &str, params: &Value) -> String {
+    let folder = ctx.path(params.get("folder").and_then(Value::as_str).unwrap_or_default());
+    let plan = match tool {
+        "organize_files" => rules(params).and_then(|rules| fileops::organize_plan(&folder, &rules))
+            .map(|plan| fileops::describe_plan("Would move", &plan)),
+        "batch_rename" => fileops::rename_plan(&folder, &rename_options(params))
+            .map(|plan| fileops::describe_plan("Would rename", &plan)),
+        _ => fileops::empty_folders(&folder).map(|empty| match empty.len() {
+            0 => format!("Would find no empty folders under {}", folder.display()),
+            n => format!("Would delete {n} empty folder(s):\n{}", empty.iter().map(|d| format!("  {}", d.display())).collect::<Vec<_>>().join("\n")),
+        }),
+    };
+    plan.unwrap_or_else(|e| format!("Would run {tool} on {}, but: {e}", folder.display()))
+}
+
+/// The folders a file step changes, snapshotted beforehand for the run's change report.
+pub fn touched_dirs(ctx: &ToolContext, tool: &str, params: &Value) -> Vec<PathBuf> {
+    let param = |key: &str| params.get(key).and_then(Value::as_str).map(|p| ctx.path(p));
+    let mut dirs: Vec<PathBuf> = ["path", "from", "to"].iter()
+        .filter_map(|key| param(key))
+        .filter_map(|p| p.parent().filter(|d| !d.as_os_str().is_empty()).map(PathBuf::from))
+        .collect();
+    if tool == "organize_downloads" {
+        dirs.extend(downloads::touched_dirs(ctx, params));
+    } else if tool == "generate_document" {
+        dirs.extend(documents::touched_dirs(ctx, params));
+    } else if tool == "log_receipt" {
+        dirs.extend(receipts::touched_dirs(ctx, params));
+    } else if tool == "extract_archive" {
+        if let Some(archive) = param("path") {
+            dirs.push(unpack_to(ctx, params, &archive));
+        }
+    } else if tool == "split_pdf" {
+        if let Some(input) = param("path") {
+            dirs.push(split_folder(ctx, params, &input));
+        }
+    } else if let Some(folder) = param("folder") {
+        if tool == "organize_files" {
+            let plan = rules(params).and_then(|rules| fileops::organize_plan(&folder, &rules)).unwrap_or_default();
+            dirs.extend(plan.iter().filter_map(|(_, to)| to.parent().map(PathBuf::from)));
+        }
+        dirs.push(folder);
+    }
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Params that hold a secret, blanked out wherever a step's params are kept or shown.
+const SECRET_PARAMS: &[(&str, &str)] = &[
+    ("compress_files", "password"),
+    ("extract_archive", "password"),
+    ("save_secure_note", "secret"),
+];
+
+/// `params` as they may be stored with the run, with any secret in them replaced.
+pub fn loggable_params(tool: &str, params: &Value) -> Value {
+    let mut params = params.clone();
+    for (_, key) in SECRET_PARAMS.iter().filter(|(t, _)| *t == tool) {
+        if let Some(value) = params.get_mut(*key).filter(|v| v.as_str().is_some_and(|s| !s.is_empty())) {
+            *value = Value::String("[hidden]".into());
+        }
+    }
+    params
+}
+
+/// Whether a call that could be undone still asked to wait for the user's approval, like a
+/// generated document sent for review before it is saved, or a receipt the model misread.
+pub fn wants_review(tool: &str, params: &Value) -> bool {
+    match tool {
+        "generate_document" => params.get("review").and_then(Value::as_bool).unwrap_or(false),
+        "log_receipt" => !receipts::unsure_fields(params).is_empty(),
+        _ => false,
+    }
+}
+
+/// Plain-language description of what a side-effecting call would do, used in simulate mode.
+/// Paths are resolved through `ctx`, so a loaded fixture set is what gets described.
+pub fn describe_effect(ctx: &ToolContext, conn: &Connection, tool: &str, params: &Value) -> String {
+    let p = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or("?").to_string();
+    let effect = match tool {
+        "write_file" => format!("Would write {} characters to {}", p("content").len(), p("path")),
+        "move_file" => format!("Would move {} to {}", p("from"), p("to")),
+        "delete_file" => format!("Would delete {}", p("path")),
+        "organize_files" | "batch_rename" | "remove_empty_folders" => file_plan(ctx, tool, params),
+        "organize_downloads" => downloads::preview(ctx, conn, params),
+        "generate_document" => documents::preview(conn, params),
+        "log_receipt" => receipts::preview(conn, params),
+        "compress_files" => format!(
+            "Would pack {} into {}{}",
+            paths(params).join(", "), p("to"), if password(params).is_some() { " with a password" } else { "" },
+        ),
+        "extract_archive" => match params.get("to").and_then(Value::as_str).filter(|t| !t.trim().is_empty()) {
+            Some(to) => format!("Would unpack {} into {to}", p("path")),
+            None => format!("Would unpack {} beside it", p("path")),
+        },
+        "merge_pdfs" => format!("Would combine {} into {}", paths(params).join(", "), p("to")),
+        "split_pdf" => format!(
+            "Would split {} into files of {} page(s)",
+            p("path"), params.get("every").and_then(Value::as_u64).unwrap_or(1),
+        ),
+        "extract_pdf_pages" => format!("Would save pages {} of {} as a new PDF", p("pages"), p("path")),
+        "fill_pdf_form" => {
+            let names: Vec<&String> = params.get("values").and_then(Value::as_object).map(|v| v.keys().collect()).unwrap_or_default();
+            format!("Would fill in {} of {} and save a copy", names.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", "), p("path"))
+        }
+        "generate_password" => format!("Would generate a password and save it in the secure note \"{}\"", p("title")),
+        "save_secure_note" => format!("Would save the secure note \"{}\"", p("title")),
+        "stamp_pdf" => format!("Would stamp \"{}\" on {} and save a copy", p("text"), p("path")),
+        "http_post" => format!("Would send data to {}", p("url")),
+        "send_email" => format!("Would email {} with subject \"{}\"", p("to"), p("subject")),
+        "browser" => format!("Would ask the browser agent to: {}", p("instruction")),
+        "start_focus" => format!(
+            "Would block distracting sites and apps for {} minutes",
+            params.get("minutes").and_then(Value::as_i64).map_or("?".into(), |m| m.to_string()),
+        ),
+        "file_meeting_notes" => {
+            let tasks = params.get("action_items").and_then(Value::as_array).map_or(0, Vec::len);
+            format!("Would file meeting notes \"{}\" in the notes folder and add {tasks} task(s)", p("title"))
+        }
+        other => format!("Would run {other} with {params}"),
+    };
+    match grace::send_at(params) {
+        Ok(Some(at)) => format!("{effect} at {}", at.format("%H:%M on %a %-d %b")),
+        _ => effect,
+    }
+}
+
+/// Runs a tool for real. Callers decide beforehand whether a call should be simulated or approved.
+pub fn execute(ctx: &ToolContext, tool: &str, params: &Value) -> Result<String, String> {
+    match tool {
+        "read_file" => files::read_file(&ctx.path(str_param(params, "path")?)),
+        "list_directory" => files::list_directory(&ctx.path(str_param(params, "path")?)),
+        "write_file" => files::write_file(
+            &ctx.path(str_param(params, "path")?),
+            params.get("content").and_then(Value::as_str).unwrap_or_default(),
+        ),
+        "move_file" => files::move_file(
+            &ctx.path(str_param(params, "from")?),
+            &ctx.path(str_param(params, "to")?),
+        ),
+        "delete_file" => files::delete_file(&ctx.path(str_param(params, "path")?)),
+        "find_duplicates" => fileops::find_duplicates(
+            &ctx.path(str_param(params, "folder")?),
+            params.get("recursive").and_then(Value::as_bool).unwrap_or(false),
+        ),
+        "organize_files" => {
+            let plan = fileops::organize_plan(&ctx.path(str_param(params, "folder")?), &rules(params)?)?;
+            fileops::apply_plan("Moved", &plan)
+        }
+        "batch_rename" => {
+            let plan = fileops::rename_plan(&ctx.path(str_param(params, "folder")?), &rename_options(params))?;
+            fileops::apply_plan("Renamed", &plan)
+        }
+        "remove_empty_folders" => fileops::remove_empty_folders(&ctx.path(str_param(params, "folder")?)),
+        "transcribe_audio" => transcribe::run_tool(ctx, &ctx.path(str_param(params, "path")?)),
+        "file_meeting_notes" => meetings::run_tool(ctx, params),
+        "organize_downloads" => downloads::run_tool(ctx, params),
+        "generate_document" => documents::run_tool(ctx, params),
+        "extract_receipt" => receipts::extract(ctx, &ctx.path(str_param(params, "path")?)),
+        "log_receipt" => receipts::run_tool(ctx, params),
+        "checksum" => checksum::checksum(
+            &ctx.path(str_param(params, "path")?),
+            algorithm(params)?,
+            params.get("expected").and_then(Value::as_str),
+        ),
+        "verify_manifest" => checksum::verify_manifest(
+            &ctx.path(str_param(params, "manifest")?),
+            params.get("folder").and_then(Value::as_str).filter(|f| !f.trim().is_empty()).map(|f| ctx.path(f)).as_deref(),
+            algorithm(params)?,
+        ),
+        "compress_files" => {
+            let sources: Vec<PathBuf> = paths(params).iter().map(|p| ctx.path(p)).collect();
+            compress::compress(&sources, &ctx.path(str_param(params, "to")?), password(params))
+        }
+        "extract_archive" => {
+            let archive = ctx.path(str_param(params, "path")?);
+            compress::extract(&archive, &unpack_to(ctx, params, &archive), password(params))
+        }
+        "merge_pdfs" => {
+            let inputs: Vec<PathBuf> = paths(params).iter().map(|p| ctx.path(p)).collect();
+            pdf::merge(&inputs, &ctx.path(str_param(params, "to")?))
+        }
+        "split_pdf" => {
+            let input = ctx.path(str_param(params, "path")?);
+            let every = params.get("every").and_then(Value::as_u64).unwrap_or(1) as usize;
+            pdf::split(&input, every, &split_folder(ctx, params, &input))
+        }
+        "extract_pdf_pages" => {
+            let input = ctx.path(str_param(params, "path")?);
+            pdf::extract_pages(&input, str_param(params, "pages")?, &pdf_output(ctx, params, &input, "extract"))
+        }
+        "read_pdf_form" => {
+            let fields = pdf::read_form(&ctx.path(str_param(params, "path")?))?;
+            if fields.is_empty() {
+                return Ok("The PDF has no form fields".into());
+            }
+            serde_json::to_string_pretty(&fields).map_err(|e| e.to_string())
+        }
+        "fill_pdf_form" => {
+            let input = ctx.path(str_param(params, "path")?);
+            let values = params.get("values").and_then(Value::as_object).ok_or("Missing parameter 'values'")?;
+            pdf::fill_form(&input, values, &pdf_output(ctx, params, &input, "filled"))
+        }
+        "stamp_pdf" => stamp_pdf(ctx, params),
+        "http_get" => {
+            let url = str_param(params, "url")?;
+            ctx.cached("http", &json!({ "method": "GET", "url": url }), || web::http_get(&ctx.proxy()?, url))
+        }
+        "http_post" => web::http_post(
+            &ctx.proxy()?,
+            str_param(params, "url")?,
+            params.get("body").and_then(Value::as_str).unwrap_or_default(),
+        ),
+        "llm_prompt" => context::run_prompt(
+            ctx,
+            str_param(params, "prompt")?,
+            params.get("schema").filter(|s| s.is_object()),
+            &string_list(params, "images"),
+        ),
+        "critique" => critique::run(
+            ctx,
+            str_param(params, "draft")?,
+            str_param(params, "goal")?,
+            params.get("model").and_then(Value::as_str),
+            params.get("max_revisions").and_then(Value::as_u64),
+        ),
+        "translate" => {
+            let db = ctx.app.state::<DbState>();
+            let translation = translate::translate(
+                &db,
+                str_param(params, "text")?,
+                str_param(params, "target")?,
+                params.get("source").and_then(Value::as_str),
+                ctx.agent_id,
+                ctx.run_id,
+            )?;
+            serde_json::to_string(&translation).map_err(|e| e.to_string())
+        }
+        "search_knowledge" => {
+            let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(5) as usize;
+            let hits = rag::search(&ctx.app.state::<DbState>(), str_param(params, "query")?, limit)?;
+            if hits.is_empty() {
+                return Ok("No matching passages in the indexed documents".into());
+            }
+            Ok(hits.iter()
+                .enumerate()
+                .map(|(i, hit)| format!("[{}] {}\n{}", i + 1, hit.path, hit.text))
+                .collect::<Vec<_>>()
+                .join("\n\n"))
+        }
+        "read_inbox" if ctx.fixture.is_some() => {
+            let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(10) as usize;
+            Ok(ctx.fixture.map(|f| f.inbox(limit)).unwrap_or_default())
+        }
+        "read_inbox" | "send_email" => Err("No email account is connected yet".into()),
+        "browser" => {
+            let output = std::process::Command::new("openclaw")
+                .args(["agent", "--message", str_param(params, "instruction")?])
+                .output()
+                .map_err(|e| format!("Couldn't start the OpenClaw agent: {e}"))?;
+            if output.status.success() {
+                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+            } else {
+                Err(String::from_utf8_lossy(&output.stderr).to_string())
+            }
+        }
+        "start_focus" => focus::run_tool(ctx.app, params, ctx.agent_id, ctx.run_id),
+        "get_weather" => weather(ctx, params),
+        "public_holidays" => public_holidays(ctx, params),
+        "calculate" => calc::evaluate(str_param(params, "expression")?).map(calc::format_number),
+        "convert" => {
+            let value = match params.get("value") {
+                Some(Value::Number(n)) => n.as_f64().unwrap_or_default(),
+                Some(Value::String(text)) => calc::evaluate(text)?,
+                _ => return Err("Missing parameter 'value'".into()),
+            };
+            let (from, to) = (str_param(params, "from")?, str_param(params, "to")?);
+            let currency = if calc::is_unit(from) && calc::is_unit(to) {
+                None
+            } else {
+                let db = ctx.app.state::<DbState>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                rates::convert(&conn, value, from, to)?
+            };
+            match currency {
+                Some(text) => Ok(text),
+                None => calc::convert_units(value, from, to)
+                    .map(|converted| format!("{} {from} = {} {to}", calc::format_number(value), calc::format_number(converted))),
+            }
+        }
+        "list_data_sources" => {
+            let sources = {
+                let db = ctx.app.state::<DbState>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                datasources::list(&conn)?
+            };
+            Ok(datasources::overview(&sources))
+        }
+        "query_data" => {
+            let source = {
+                let db = ctx.app.state::<DbState>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                datasources::find(&conn, str_param(params, "source")?)?
+            };
+            let result = datasources::query(&source, str_param(params, "sql")?, params.get("limit").and_then(Value::as_i64))?;
+            serde_json::to_string(&result).map_err(|e| e.to_string())
+        }
+        "generate_password" => {
+            let password = secure_notes::new_password(
+                params.get("length").and_then(Value::as_u64).map(|n| n as usize),
+                params.get("symbols").and_then(Value::as_bool).unwrap_or(true),
+            )?;
+            let note = save_secure_note(ctx, params, &password)?;
+            Ok(format!(
+                "Generated a {}-character password and saved it in the secure note \"{}\"; the user can see it under Secure notes",
+                password.chars().count(), note.title,
+            ))
+        }
+        "save_secure_note" => {
+            let note = save_secure_note(ctx, params, str_param(params, "secret")?)?;
+            Ok(format!("Saved the secure note \"{}\"", note.title))
+        }
+        "list_secure_notes" => {
+            let db = ctx.app.state::<DbState>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            Ok(secure_notes::overview(&secure_notes::list(&conn)?))
+        }
+        "transform_data" => transform_data(params),
+        "regex_extract" => {
+            let matches = text::regex_extract(
+                text_param(params, "text")?,
+                str_param(params, "pattern")?,
+                params.get("all").and_then(Value::as_bool).unwrap_or(true),
+                params.get("ignore_case").and_then(Value::as_bool).unwrap_or(false),
+            )?;
+            Ok(matches.to_string())
+        }
+        "diff_text" => {
+            let context = params.get("context").and_then(Value::as_u64).unwrap_or(3) as usize;
+            let label = |key: &str, default: &'static str| params.get(key).and_then(Value::as_str).filter(|l| !l.trim().is_empty()).unwrap_or(default);
+            Ok(text::unified_diff(text_param(params, "old")?, text_param(params, "new")?, label("old_name", "old"), label("new_name", "new"), context))
+        }
+        "fill_template" => fill_template(params),
+        "change_case" => text::change_case(text_param(params, "text")?, str_param(params, "case")?),
+        "format_date" => format_date(ctx, params),
+        "notify" => {
+            let title = str_param(params, "title")?;
+            let message = params.get("message").and_then(Value::as_str).unwrap_or_default();
+            let db = ctx.app.state::<DbState>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            if notifications::notify(ctx.app, &conn, ctx.agent_id, Event::Message, title, message) {
+                Ok("Notification sent".into())
+            } else {
+                Ok("Not sent: it's quiet hours".into())
+            }
+        }
+        other => Err(format!("Unknown tool: {other}")),
+    }
+}
+
+#[tauri::command]
+pub fn list_tools() -> Vec<ToolSpec> {
+    registry()
+}
@@ -0,0 +1,1900 @@
+//! Just enough of PDF to rearrange pages, fill forms, stamp text and read it back out:
+//! documents are read whole, changed as objects and written back out with a plain
+//! cross-reference table.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{fileops, files};
+
+/// PDFs bigger than this are refused rather than read into memory.
+const MAX_FILE_BYTES: u64 = 200 * 1024 * 1024;
+/// Streams that decompress to more than this are refused.
+const MAX_STREAM_BYTES: u64 = 256 * 1024 * 1024;
+/// Arrays and dictionaries nested deeper than this are treated as damage; real files stay
+/// in the single digits.
+const MAX_NESTING: usize = 256;
+/// Inherited from the page tree when a page doesn't set them itself.
+const INHERITED: &[&str] = &["Resources", "MediaBox", "CropBox", "Rotate"];
+/// Space left between stamped text and the edge of the page, in points.
+const STAMP_MARGIN: f64 = 24.0;
+const STAMP_FONT: &str = "OCStamp";
+
+type Dict = BTreeMap<String, Object>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Real(f64),
+    Name(String),
+    Str(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Stream(Dict, Vec<u8>),
+    Ref(u32),
+}
+
+impl Object {
+    fn dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(d) | Object::Stream(d, _) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn dict_mut(&mut self) -> Option<&mut Dict> {
+        match self {
+            Object::Dict(d) | Object::Stream(d, _) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Object::Int(i) => Some(*i),
+            Object::Real(r) => Some(*r as i64),
+            _ => None,
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Object::Int(i) => Some(*i as f64),
+            Object::Real(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Object::Name(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn array(&self) -> Option<&Vec<Object>> {
+        match self {
+            Object::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    fn name_obj(name: &str) -> Object {
+        Object::Name(name.into())
+    }
+}
+
+/// A PDF text string: PDFDocEncoding (close enough to Latin-1) or UTF-16 with a byte order mark.
+fn text_string(value: &str) -> Vec<u8> {
+    if value.chars().all(|c| (c as u32) < 0x80 || ((c as u32) >= 0xA0 && (c as u32) <= 0xFF)) {
+        return value.chars().map(|c| c as u8).collect();
+    }
+    let mut out = vec![0xFE, 0xFF];
+    value.encode_utf16().for_each(|u| out.extend_from_slice(&u.to_be_bytes()));
+    out
+}
+
+fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = bytes[2..].chunks(2).filter(|c| c.len() == 2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        return String::from_utf16_lossy(&units);
+    }
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+// ─── Reading ───
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Arrays and dictionaries currently open, up to `MAX_NESTING`.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Offsets past the end, as damaged files give, read as the end.
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos: pos.min(data.len()), depth: 0 }
+    }
+
+    fn damaged(&self) -> String {
+        format!("The PDF is damaged near byte {}", self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_space(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.peek().is_some_and(|b| b != b'\n' && b != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self) -> &'a [u8] {
+        self.skip_space();
+        let start = self.pos;
+        while self.peek().is_some_and(|b| !is_space(b) && !is_delimiter(b)) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn int_token(&mut self) -> Option<i64> {
+        std::str::from_utf8(self.token()).ok()?.parse().ok()
+    }
+
+    fn object(&mut self) -> Result<Object, String> {
+        self.skip_space();
+        match self.peek().ok_or_else(|| self.damaged())? {
+            b'/' => {
+                self.pos += 1;
+                let raw = self.token();
+                let mut name = Vec::with_capacity(raw.len());
+                let mut i = 0;
+                while i < raw.len() {
+                    let hex = raw.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+                    match (raw[i], hex) {
+                        (b'#', Some(byte)) => {
+                            name.push(byte);
+                            i += 3;
+                        }
+                        (b, _) => {
+                            name.push(b);
+                            i += 1;
+                        }
+                    }
+                }
+                Ok(Object::Name(String::from_utf8_lossy(&name).to_string()))
+            }
+            b'(' => self.literal(),
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => self.nested(),
+            b'<' => {
+                self.pos += 1;
+                let end = self.data[self.pos..].iter().position(|b| *b == b'>').ok_or_else(|| self.damaged())?;
+                let mut digits: Vec<u8> = self.data[self.pos..self.pos + end].iter().copied().filter(|b| b.is_ascii_hexdigit()).collect();
+                self.pos += end + 1;
+                if digits.len() % 2 == 1 {
+                    digits.push(b'0');
+                }
+                let bytes = digits.chunks(2)
+                    .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap_or("00"), 16).unwrap_or(0))
+                    .collect();
+                Ok(Object::Str(bytes))
+            }
+            b'[' => self.nested(),
+            _ => {
+                let token = self.token();
+                match token {
+                    b"true" => return Ok(Object::Bool(true)),
+                    b"false" => return Ok(Object::Bool(false)),
+                    b"null" => return Ok(Object::Null),
+                    b"" => return Err(self.damaged()),
+                    _ => {}
+                }
+                let text = std::str::from_utf8(token).map_err(|_| self.damaged())?;
+                if let Ok(int) = text.parse::<i64>() {
+                    // `12 0 R` is a reference; look ahead without consuming anything else.
+                    let mark = self.pos;
+                    if int >= 0 && self.int_token().is_some() && self.token() == b"R" {
+                        return Ok(Object::Ref(int as u32));
+                    }
+                    self.pos = mark;
+                    return Ok(Object::Int(int));
+                }
+                text.parse::<f64>().map(Object::Real).map_err(|_| self.damaged())
+            }
+        }
+    }
+
+    /// An array or dictionary. Nesting is limited so a crafted file can't exhaust the stack.
+    fn nested(&mut self) -> Result<Object, String> {
+        if self.depth >= MAX_NESTING {
+            return Err(self.damaged());
+        }
+        self.depth += 1;
+        let object = if self.peek() == Some(b'[') { self.array() } else { self.dict() };
+        self.depth -= 1;
+        object
+    }
+
+    fn array(&mut self) -> Result<Object, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Object::Array(items));
+                }
+                Some(_) => items.push(self.object()?),
+                None => return Err(self.damaged()),
+            }
+        }
+    }
+
+    fn dict(&mut self) -> Result<Object, String> {
+        self.pos += 2;
+        let mut dict = Dict::new();
+        loop {
+            self.skip_space();
+            if self.data.get(self.pos..self.pos + 2) == Some(b">>") {
+                self.pos += 2;
+                return Ok(Object::Dict(dict));
+            }
+            let Object::Name(key) = self.object()? else { return Err(self.damaged()) };
+            let value = self.object()?;
+            dict.insert(key, value);
+        }
+    }
+
+    fn literal(&mut self) -> Result<Object, String> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        let mut depth = 1;
+        loop {
+            let b = self.peek().ok_or_else(|| self.damaged())?;
+            self.pos += 1;
+            match b {
+                b'\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.damaged())?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(8),
+                        b'f' => out.push(12),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Object::Str(out));
+                    }
+                    out.push(b);
+                }
+                _ => out.push(b),
+            }
+        }
+    }
+}
+
+/// Undoes the PNG predictors cross-reference and object streams are usually saved with.
+fn unpredict(data: Vec<u8>, params: Option<&Dict>) -> Result<Vec<u8>, String> {
+    let predictor = params.and_then(|p| p.get("Predictor")).and_then(Object::int).unwrap_or(1);
+    if predictor < 10 {
+        return Ok(data);
+    }
+    let columns = params.and_then(|p| p.get("Columns")).and_then(Object::int).unwrap_or(1).max(1) as usize;
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous = vec![0u8; columns];
+    for row in data.chunks(columns + 1) {
+        let (kind, row) = (row[0], &row[1..]);
+        let mut current = vec![0u8; columns];
+        for i in 0..row.len() {
+            let left = if i > 0 { current[i - 1] } else { 0 };
+            let up = previous[i];
+            let up_left = if i > 0 { previous[i - 1] } else { 0 };
+            current[i] = match kind {
+                0 => row[i],
+                1 => row[i].wrapping_add(left),
+                2 => row[i].wrapping_add(up),
+                3 => row[i].wrapping_add(((left as u16 + up as u16) / 2) as u8),
+                4 => {
+                    let p = left as i16 + up as i16 - up_left as i16;
+                    let (pa, pb, pc) = ((p - left as i16).abs(), (p - up as i16).abs(), (p - up_left as i16).abs());
+                    let nearest = if pa <= pb && pa <= pc { left } else if pb <= pc { up } else { up_left };
+                    row[i].wrapping_add(nearest)
+                }
+                _ => return Err("The PDF uses an unknown predictor".into()),
+            };
+        }
+        out.extend_from_slice(&current[..row.len()]);
+        previous = current;
+    }
+    Ok(out)
+}
+
+/// Stream contents with their filters removed; only Flate, which is all the structure of
+/// a PDF ever uses, is understood.
+fn decode(dict: &Dict, data: &[u8]) -> Result<Vec<u8>, String> {
+    let filters: Vec<&str> = match dict.get("Filter") {
+        None => Vec::new(),
+        Some(Object::Name(n)) => vec![n.as_str()],
+        Some(Object::Array(items)) => items.iter().filter_map(Object::name).collect(),
+        Some(_) => return Err("The PDF has a stream with an unreadable filter".into()),
+    };
+    match filters.as_slice() {
+        [] => Ok(data.to_vec()),
+        ["FlateDecode"] => {
+            let mut out = Vec::new();
+            // Some writers leave junk after the compressed data; what decoded is kept.
+            if let Err(e) = ZlibDecoder::new(data).take(MAX_STREAM_BYTES + 1).read_to_end(&mut out) {
+                if out.is_empty() {
+                    return Err(format!("The PDF has a stream that can't be decompressed: {e}"));
+                }
+            }
+            if out.len() as u64 > MAX_STREAM_BYTES {
+                return Err(format!("The PDF has a stream larger than {} MB", MAX_STREAM_BYTES / (1024 * 1024)));
+            }
+            unpredict(out, dict.get("DecodeParms").and_then(Object::dict))
+        }
+        other => Err(format!("The PDF uses {} compression, which isn't supported", other.join("+"))),
+    }
+}
+
+/// Where each object lives: at a byte offset, or inside an object stream.
+#[derive(Clone, Copy)]
+enum Location {
+    Offset(usize),
+    InStream(u32),
+}
+
+struct Document {
+    objects: BTreeMap<u32, Object>,
+    trailer: Dict,
+}
+
+fn find_last(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).rposition(|w| w == needle)
+}
+
+impl Document {
+    fn open(path: &Path) -> Result<Self, String> {
+        let size = fs::metadata(path).map_err(|e| format!("Can't open {}: {e}", path.display()))?.len();
+        if size > MAX_FILE_BYTES {
+            return Err(format!("{} is larger than {} MB", path.display(), MAX_FILE_BYTES / (1024 * 1024)));
+        }
+        let data = fs::read(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+        if !data.starts_with(b"%PDF-") && !data[..data.len().min(1024)].windows(5).any(|w| w == b"%PDF-") {
+            return Err(format!("{} isn't a PDF", path.display()));
+        }
+        let document = Self::parse(&data).map_err(|e| format!("{}: {e}", path.display()))?;
+        if document.trailer.contains_key("Encrypt") {
+            return Err(format!("{} is password-protected; save an unprotected copy first", path.display()));
+        }
+        Ok(document)
+    }
+
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let (locations, trailer) = match Self::read_xref(data) {
+            Ok(found) => found,
+            Err(_) => Self::scan(data)?,
+        };
+        let mut document = Self { objects: BTreeMap::new(), trailer };
+        let mut streams: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for (id, location) in &locations {
+            match location {
+                Location::Offset(offset) => {
+                    if let Ok(object) = Self::indirect(data, *offset, &locations) {
+                        document.objects.insert(*id, object);
+                    }
+                }
+                Location::InStream(stream) => streams.entry(*stream).or_default().push(*id),
+            }
+        }
+        for (stream, wanted) in streams {
+            let Some(Object::Stream(dict, raw)) = document.objects.get(&stream) else { continue };
+            let decoded = decode(dict, raw)?;
+            let count = dict.get("N").and_then(Object::int).and_then(|n| usize::try_from(n).ok()).unwrap_or(0);
+            let first = dict.get("First").and_then(Object::int).and_then(|n| usize::try_from(n).ok()).unwrap_or(0);
+            let mut header = Parser::new(&decoded, 0);
+            let mut found = Vec::new();
+            for _ in 0..count {
+                let (Some(id), Some(offset)) = (header.int_token(), header.int_token()) else { break };
+                let (Ok(id), Some(offset)) = (u32::try_from(id), usize::try_from(offset).ok().and_then(|o| first.checked_add(o))) else { break };
+                found.push((id, offset));
+            }
+            for (id, offset) in found {
+                if wanted.contains(&id) && !document.objects.contains_key(&id) {
+                    if let Ok(object) = Parser::new(&decoded, offset).object() {
+                        document.objects.insert(id, object);
+                    }
+                }
+            }
+        }
+        if !document.trailer.contains_key("Root") {
+            return Err("The PDF has no catalog".into());
+        }
+        Ok(document)
+    }
+
+    /// Follows `startxref` and each `/Prev`, newest first, through tables and xref streams.
+    fn read_xref(data: &[u8]) -> Result<(HashMap<u32, Location>, Dict), String> {
+        let at = find_last(data, b"startxref").ok_or("no startxref")?;
+        let mut parser = Parser::new(data, at + 9);
+        let mut next = parser.int_token().map(|o| o as usize);
+        let mut locations = HashMap::new();
+        let mut trailer: Option<Dict> = None;
+        let mut seen = HashSet::new();
+        while let Some(offset) = next.take() {
+            if !seen.insert(offset) || offset >= data.len() {
+                break;
+            }
+            let mut parser = Parser::new(data, offset);
+            let section = if parser.token() == b"xref" {
+                loop {
+                    let mark = parser.pos;
+                    let (Some(start), Some(count)) = (parser.int_token(), parser.int_token()) else {
+                        parser.pos = mark;
+                        break;
+                    };
+                    for i in 0..count.max(0) {
+                        let (offset, _generation, kind) = (parser.int_token(), parser.int_token(), parser.token());
+                        if kind.is_empty() {
+                            break;
+                        }
+                        let id = start.checked_add(i).and_then(|id| u32::try_from(id).ok());
+                        if let (Some(id), b"n", Some(offset)) = (id, kind, offset.filter(|o| *o > 0)) {
+                            locations.entry(id).or_insert(Location::Offset(offset as usize));
+                        }
+                    }
+                }
+                if parser.token() != b"trailer" {
+                    return Err("no trailer".into());
+                }
+                let Object::Dict(section) = parser.object()? else { return Err("bad trailer".into()) };
+                if let Some(stream) = section.get("XRefStm").and_then(Object::int) {
+                    if let Ok(Object::Stream(dict, raw)) = Self::indirect(data, stream as usize, &HashMap::new()) {
+                        Self::xref_stream(&dict, &raw, &mut locations)?;
+                    }
+                }
+                section
+            } else {
+                let Object::Stream(dict, raw) = Self::indirect(data, offset, &locations)? else { return Err("bad xref".into()) };
+                Self::xref_stream(&dict, &raw, &mut locations)?;
+                dict
+            };
+            next = section.get("Prev").and_then(Object::int).map(|o| o as usize);
+            if trailer.is_none() {
+                trailer = Some(section);
+            }
+        }
+        let mut trailer = trailer.ok_or("no trailer")?;
+        for key in ["Prev", "XRefStm", "Type", "W", "Index", "Filter", "DecodeParms", "Length", "Size"] {
+            trailer.remove(key);
+        }
+        Ok((locations, trailer))
+    }
+
+    fn xref_stream(dict: &Dict, raw: &[u8], locations: &mut HashMap<u32, Location>) -> Result<(), String> {
+        let data = decode(dict, raw)?;
+        let widths: Vec<usize> = dict.get("W").and_then(Object::array).ok_or("bad xref stream")?
+            .iter().map(|w| w.int().unwrap_or(0).max(0) as usize).collect();
+        if widths.len() != 3 {
+            return Err("bad xref stream".into());
+        }
+        let size = dict.get("Size").and_then(Object::int).unwrap_or(0);
+        let index: Vec<i64> = dict.get("Index").and_then(Object::array)
+            .map(|i| i.iter().filter_map(Object::int).collect())
+            .unwrap_or_else(|| vec![0, size]);
+        let row = widths.iter().sum::<usize>();
+        let field = |bytes: &[u8]| bytes.iter().fold(0u64, |acc, b| acc << 8 | *b as u64);
+        let mut rows = data.chunks_exact(row.max(1));
+        for pair in index.chunks(2) {
+            let [start, count] = pair else { break };
+            for i in 0..*count {
+                let Some(entry) = rows.next() else { return Ok(()) };
+                let kind = if widths[0] == 0 { 1 } else { field(&entry[..widths[0]]) };
+                let second = field(&entry[widths[0]..widths[0] + widths[1]]);
+                let Some(id) = start.checked_add(i).and_then(|id| u32::try_from(id).ok()) else { continue };
+                match kind {
+                    1 => { locations.entry(id).or_insert(Location::Offset(second as usize)); }
+                    2 => { locations.entry(id).or_insert(Location::InStream(second as u32)); }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// For files whose cross-reference data is broken: every `N G obj` in the file.
+    fn scan(data: &[u8]) -> Result<(HashMap<u32, Location>, Dict), String> {
+        let pattern = regex::bytes::Regex::new(r"(?m)(?:^|[\r\n\s])(\d+)\s+\d+\s+obj\b").map_err(|e| e.to_string())?;
+        let mut locations = HashMap::new();
+        for found in pattern.captures_iter(data) {
+            let (Some(all), Some(id)) = (found.get(0), found.get(1)) else { continue };
+            let Some(id) = std::str::from_utf8(id.as_bytes()).ok().and_then(|s| s.parse::<u32>().ok()) else { continue };
+            let start = all.start() + all.as_bytes().iter().take_while(|b| is_space(**b)).count();
+            locations.insert(id, Location::Offset(start));
+        }
+        let mut trailer = Dict::new();
+        if let Some(at) = find_last(data, b"trailer") {
+            if let Ok(Object::Dict(dict)) = Parser::new(data, at + 7).object() {
+                trailer = dict;
+            }
+        }
+        if !trailer.contains_key("Root") {
+            let root = locations.iter().find(|(_, location)| match location {
+                Location::Offset(offset) => Self::indirect(data, *offset, &HashMap::new()).ok()
+                    .and_then(|o| o.dict().and_then(|d| d.get("Type")).and_then(Object::name).map(|t| t == "Catalog"))
+                    .unwrap_or(false),
+                Location::InStream(_) => false,
+            });
+            let (id, _) = root.ok_or("The PDF is too damaged to read")?;
+            trailer.insert("Root".into(), Object::Ref(*id));
+        }
+        Ok((locations, trailer))
+    }
+
+    /// `N G obj … endobj` at `offset`. A stream's `/Length` may itself be an indirect object.
+    fn indirect(data: &[u8], offset: usize, locations: &HashMap<u32, Location>) -> Result<Object, String> {
+        let mut parser = Parser::new(data, offset);
+        let (Some(_), Some(_)) = (parser.int_token(), parser.int_token()) else { return Err(parser.damaged()) };
+        if parser.token() != b"obj" {
+            return Err(parser.damaged());
+        }
+        let object = parser.object()?;
+        let Object::Dict(dict) = object else { return Ok(object) };
+        if parser.token() != b"stream" {
+            return Ok(Object::Dict(dict));
+        }
+        match parser.peek() {
+            Some(b'\r') if data.get(parser.pos + 1) == Some(&b'\n') => parser.pos += 2,
+            Some(b'\r') | Some(b'\n') => parser.pos += 1,
+            _ => {}
+        }
+        let start = parser.pos;
+        let length = match dict.get("Length") {
+            Some(Object::Int(n)) => usize::try_from(*n).ok(),
+            Some(Object::Ref(id)) => match locations.get(id) {
+                Some(Location::Offset(at)) => Self::indirect(data, *at, &HashMap::new()).ok().and_then(|o| o.int()).and_then(|n| usize::try_from(n).ok()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let fits = length.and_then(|n| start.checked_add(n)).filter(|end| {
+            *end <= data.len() && Parser::new(data, *end).token() == b"endstream"
+        });
+        let end = match fits {
+            Some(end) => end,
+            None => {
+                let found = data[start..].windows(9).position(|w| w == b"endstream").ok_or_else(|| parser.damaged())?;
+                let mut end = start + found;
+                while end > start && matches!(data[end - 1], b'\r' | b'\n') {
+                    end -= 1;
+                }
+                end
+            }
+        };
+        Ok(Object::Stream(dict, data[start..end].to_vec()))
+    }
+
+    fn get(&self, id: u32) -> &Object {
+        self.objects.get(&id).unwrap_or(&Object::Null)
+    }
+
+    fn resolve<'a>(&'a self, object: &'a Object) -> &'a Object {
+        let mut current = object;
+        for _ in 0..16 {
+            match current {
+                Object::Ref(id) => current = self.get(*id),
+                _ => return current,
+            }
+        }
+        &Object::Null
+    }
+
+    fn lookup<'a>(&'a self, dict: &'a Dict, key: &str) -> &'a Object {
+        dict.get(key).map_or(&Object::Null, |o| self.resolve(o))
+    }
+
+    fn catalog(&self) -> &Dict {
+        static EMPTY: Dict = Dict::new();
+        self.trailer.get("Root").map(|r| self.resolve(r)).and_then(Object::dict).unwrap_or(&EMPTY)
+    }
+
+    fn next_id(&self) -> u32 {
+        self.objects.keys().next_back().map_or(1, |id| id + 1)
+    }
+
+    fn add(&mut self, object: Object) -> u32 {
+        let id = self.next_id();
+        self.objects.insert(id, object);
+        id
+    }
+
+    /// Page object ids in reading order, and the ids of the page tree's inner nodes.
+    fn pages(&self) -> (Vec<u32>, HashSet<u32>) {
+        let mut pages = Vec::new();
+        let mut nodes = HashSet::new();
+        let mut stack: Vec<u32> = self.catalog().get("Pages").and_then(|p| match p { Object::Ref(id) => Some(*id), _ => None }).into_iter().collect();
+        while let Some(id) = stack.pop() {
+            if !nodes.insert(id) {
+                continue;
+            }
+            let Some(dict) = self.get(id).dict() else { continue };
+            if dict.get("Type").and_then(Object::name) == Some("Page") || !dict.contains_key("Kids") {
+                nodes.remove(&id);
+                pages.push(id);
+                continue;
+            }
+            let kids = self.lookup(dict, "Kids").array().cloned().unwrap_or_default();
+            stack.extend(kids.iter().rev().filter_map(|k| match k { Object::Ref(id) => Some(*id), _ => None }));
+        }
+        (pages, nodes)
+    }
+
+    /// A page's dictionary with what it inherits from the page tree filled in.
+    fn page_with_inherited(&self, id: u32) -> Dict {
+        let mut page = self.get(id).dict().cloned().unwrap_or_default();
+        let mut parent = page.get("Parent").cloned();
+        for _ in 0..64 {
+            let Some(Object::Ref(parent_id)) = parent else { break };
+            let Some(node) = self.get(parent_id).dict() else { break };
+            for key in INHERITED {
+                if !page.contains_key(*key) {
+                    if let Some(value) = node.get(*key) {
+                        page.insert(key.to_string(), value.clone());
+                    }
+                }
+            }
+            parent = node.get("Parent").cloned();
+        }
+        page
+    }
+
+    fn write(&self, path: &Path) -> Result<(), String> {
+        let mut out: Vec<u8> = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let size = self.next_id();
+        let mut offsets = vec![None; size as usize];
+        for (id, object) in &self.objects {
+            // Object and xref streams are unpacked on reading and not needed any more.
+            let kind = object.dict().and_then(|d| d.get("Type")).and_then(Object::name);
+            if matches!(object, Object::Stream(..)) && matches!(kind, Some("ObjStm") | Some("XRef")) {
+                continue;
+            }
+            offsets[*id as usize] = Some(out.len());
+            out.extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+            serialize(object, &mut out);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {size}\n").as_bytes());
+        for offset in &offsets {
+            match offset {
+                Some(offset) => out.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes()),
+                None => out.extend_from_slice(b"0000000000 65535 f \n"),
+            }
+        }
+        let mut trailer = Dict::new();
+        for key in ["Root", "Info", "ID"] {
+            if let Some(value) = self.trailer.get(key) {
+                trailer.insert(key.into(), value.clone());
+            }
+        }
+        trailer.insert("Size".into(), Object::Int(size as i64));
+        out.extend_from_slice(b"trailer\n");
+        serialize(&Object::Dict(trailer), &mut out);
+        out.extend_from_slice(format!("\nstartxref\n{xref}\n%%EOF\n").as_bytes());
+        files::create_file(path, &out)
+    }
+}
+
+// ─── Writing ───
+
+fn serialize(object: &Object, out: &mut Vec<u8>) {
+    match object {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Object::Int(i) => out.extend_from_slice(i.to_string().as_bytes()),
+        Object::Real(r) => {
+            let text = format!("{r:.4}");
+            out.extend_from_slice(text.trim_end_matches('0').trim_end_matches('.').as_bytes());
+        }
+        Object::Name(name) => {
+            out.push(b'/');
+            for b in name.bytes() {
+                if b <= b' ' || b > b'~' || b == b'#' || is_delimiter(b) {
+                    out.extend_from_slice(format!("#{b:02X}").as_bytes());
+                } else {
+                    out.push(b);
+                }
+            }
+        }
+        Object::Str(bytes) => {
+            out.push(b'<');
+            bytes.iter().for_each(|b| out.extend_from_slice(format!("{b:02X}").as_bytes()));
+            out.push(b'>');
+        }
+        Object::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                serialize(item, out);
+            }
+            out.push(b']');
+        }
+        Object::Dict(dict) => serialize_dict(dict, out),
+        Object::Stream(dict, data) => {
+            let mut dict = dict.clone();
+            dict.insert("Length".into(), Object::Int(data.len() as i64));
+            serialize_dict(&dict, out);
+            out.extend_from_slice(b"\nstream\n");
+            out.extend_from_slice(data);
+            out.extend_from_slice(b"\nendstream");
+        }
+        Object::Ref(id) => out.extend_from_slice(format!("{id} 0 R").as_bytes()),
+    }
+}
+
+fn serialize_dict(dict: &Dict, out: &mut Vec<u8>) {
+    out.extend_from_slice(b"<<");
+    for (key, value) in dict {
+        serialize(&Object::Name(key.clone()), out);
+        out.push(b' ');
+        serialize(value, out);
+        out.push(b' ');
+    }
+    out.extend_from_slice(b">>");
+}
+
+/// Copies pages and everything they use from other documents into a new one, numbering
+/// objects afresh. References to pages that aren't copied, and to the old page tree,
+/// become null, so a link or form field never drags the rest of its document along.
+struct Assembler {
+    objects: BTreeMap<u32, Object>,
+    next: u32,
+    pages_id: u32,
+    kids: Vec<Object>,
+    fields: Vec<Object>,
+    form: Dict,
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Self { objects: BTreeMap::new(), next: 2, pages_id: 1, kids: Vec::new(), fields: Vec::new(), form: Dict::new() }
+    }
+
+    fn reserve(&mut self) -> u32 {
+        self.next += 1;
+        self.next - 1
+    }
+
+    fn copy(&mut self, object: &Object, map: &mut HashMap<u32, u32>, skip: &HashSet<u32>, queue: &mut VecDeque<(u32, u32)>) -> Object {
+        match object {
+            Object::Ref(id) if skip.contains(id) => Object::Null,
+            Object::Ref(id) => Object::Ref(*map.entry(*id).or_insert_with(|| {
+                let new = self.next;
+                self.next += 1;
+                queue.push_back((*id, new));
+                new
+            })),
+            Object::Array(items) => Object::Array(items.iter().map(|i| self.copy(i, map, skip, queue)).collect()),
+            Object::Dict(dict) => Object::Dict(dict.iter().map(|(k, v)| (k.clone(), self.copy(v, map, skip, queue))).collect()),
+            Object::Stream(dict, data) => Object::Stream(
+                dict.iter().map(|(k, v)| (k.clone(), self.copy(v, map, skip, queue))).collect(),
+                data.clone(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// Adds `pages` (indexes into the document's page list) in the order given.
+    fn add(&mut self, document: &Document, pages: &[usize]) {
+        let (all, nodes) = document.pages();
+        let chosen: Vec<u32> = pages.iter().filter_map(|i| all.get(*i).copied()).collect();
+        let mut skip: HashSet<u32> = all.iter().filter(|id| !chosen.contains(id)).copied().collect();
+        skip.extend(nodes);
+        let mut map = HashMap::new();
+        let mut queue = VecDeque::new();
+        let reserved: Vec<u32> = chosen.iter().map(|id| {
+            let new = self.reserve();
+            map.insert(*id, new);
+            new
+        }).collect();
+        for (old, new) in chosen.iter().zip(&reserved) {
+            let mut page = document.page_with_inherited(*old);
+            page.remove("Parent");
+            let Object::Dict(mut page) = self.copy(&Object::Dict(page), &mut map, &skip, &mut queue) else { continue };
+            page.insert("Parent".into(), Object::Ref(self.pages_id));
+            self.objects.insert(*new, Object::Dict(page));
+            self.kids.push(Object::Ref(*new));
+        }
+        if let Some(form) = document.lookup(document.catalog(), "AcroForm").dict().cloned() {
+            if let Object::Dict(form) = self.copy(&Object::Dict(form), &mut map, &skip, &mut queue) {
+                if let Some(Object::Array(fields)) = form.get("Fields") {
+                    self.fields.extend(fields.iter().filter(|f| **f != Object::Null).cloned());
+                }
+                for (key, value) in form {
+                    if key != "Fields" {
+                        self.form.entry(key).or_insert(value);
+                    }
+                }
+            }
+        }
+        while let Some((old, new)) = queue.pop_front() {
+            let copied = self.copy(document.get(old), &mut map, &skip, &mut queue);
+            self.objects.insert(new, copied);
+        }
+    }
+
+    fn finish(mut self) -> Document {
+        let mut pages = Dict::new();
+        pages.insert("Type".into(), Object::name_obj("Pages"));
+        pages.insert("Count".into(), Object::Int(self.kids.len() as i64));
+        pages.insert("Kids".into(), Object::Array(self.kids));
+        self.objects.insert(self.pages_id, Object::Dict(pages));
+        let mut catalog = Dict::new();
+        catalog.insert("Type".into(), Object::name_obj("Catalog"));
+        catalog.insert("Pages".into(), Object::Ref(self.pages_id));
+        if !self.fields.is_empty() {
+            self.form.insert("Fields".into(), Object::Array(self.fields));
+            catalog.insert("AcroForm".into(), Object::Dict(self.form));
+        }
+        let catalog_id = self.next;
+        self.objects.insert(catalog_id, Object::Dict(catalog));
+        let mut trailer = Dict::new();
+        trailer.insert("Root".into(), Object::Ref(catalog_id));
+        Document { objects: self.objects, trailer }
+    }
+}
+
+// ─── Pages ───
+
+/// Zero-based page indexes from `1-3, 5, 8-` (1-based, open ranges run to the end).
+pub fn parse_pages(spec: &str, count: usize) -> Result<Vec<usize>, String> {
+    let spec = spec.trim();
+    if spec.is_empty() || spec.eq_ignore_ascii_case("all") {
+        return Ok((0..count).collect());
+    }
+    let number = |s: &str| s.trim().parse::<usize>().map_err(|_| format!("\"{spec}\" isn't a list of pages like 1-3, 5"));
+    let mut out = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((a, b)) => (if a.trim().is_empty() { 1 } else { number(a)? }, if b.trim().is_empty() { count } else { number(b)? }),
+            None => (number(part)?, number(part)?),
+        };
+        if first == 0 || last > count || first > last {
+            return Err(format!("{part} is outside this PDF, which has {count} page(s)"));
+        }
+        out.extend(first - 1..last);
+    }
+    Ok(out)
+}
+
+fn page_count(document: &Document) -> usize {
+    document.pages().0.len()
+}
+
+pub fn merge(inputs: &[PathBuf], to: &Path) -> Result<String, String> {
+    if inputs.len() < 2 {
+        return Err("Give at least two PDFs to merge".into());
+    }
+    let mut assembler = Assembler::new();
+    let mut total = 0;
+    for input in inputs {
+        let document = Document::open(input)?;
+        let count = page_count(&document);
+        assembler.add(&document, &(0..count).collect::<Vec<_>>());
+        total += count;
+    }
+    assembler.finish().write(to)?;
+    Ok(format!("Merged {} PDFs ({total} pages) into {}", inputs.len(), to.display()))
+}
+
+pub fn extract_pages(input: &Path, pages: &str, to: &Path) -> Result<String, String> {
+    let document = Document::open(input)?;
+    let chosen = parse_pages(pages, page_count(&document))?;
+    let mut seen = HashSet::new();
+    if let Some(twice) = chosen.iter().find(|p| !seen.insert(**p)) {
+        return Err(format!("Page {} is listed twice", twice + 1));
+    }
+    let mut assembler = Assembler::new();
+    assembler.add(&document, &chosen);
+    assembler.finish().write(to)?;
+    Ok(format!("Saved {} page(s) of {} as {}", chosen.len(), fileops::name(input), to.display()))
+}
+
+/// Splits into files of `every` pages each, named after the original, in `folder`.
+pub fn split(input: &Path, every: usize, folder: &Path) -> Result<String, String> {
+    let document = Document::open(input)?;
+    let count = page_count(&document);
+    let every = every.max(1);
+    let stem = input.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "document".into());
+    let parts: Vec<Vec<usize>> = (0..count).collect::<Vec<_>>().chunks(every).map(<[usize]>::to_vec).collect();
+    let targets: Vec<PathBuf> = parts.iter().map(|part| {
+        let (first, last) = (part[0] + 1, part[part.len() - 1] + 1);
+        let label = if first == last { format!("p{first}") } else { format!("p{first}-{last}") };
+        folder.join(format!("{stem} {label}.pdf"))
+    }).collect();
+    if let Some(existing) = targets.iter().find(|t| t.exists()) {
+        return Err(format!("{} already exists", existing.display()));
+    }
+    for (part, target) in parts.iter().zip(&targets) {
+        let mut assembler = Assembler::new();
+        assembler.add(&document, part);
+        assembler.finish().write(target)?;
+    }
+    Ok(format!("Split {} ({count} pages) into {} file(s) in {}", fileops::name(input), parts.len(), folder.display()))
+}
+
+// ─── Forms ───
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FormField {
+    /// Full name, parts joined with dots as in `address.city`.
+    pub name: String,
+    /// `text`, `checkbox`, `radio`, `choice`, `button` or `signature`.
+    pub kind: String,
+    pub value: String,
+    /// What a checkbox or radio button can be set to, or a choice field's options.
+    pub options: Vec<String>,
+}
+
+struct Field {
+    id: u32,
+    name: String,
+    kind: String,
+    flags: i64,
+    widgets: Vec<u32>,
+}
+
+const FLAG_RADIO: i64 = 1 << 15;
+const FLAG_PUSHBUTTON: i64 = 1 << 16;
+
+impl Document {
+    fn fields(&self) -> Vec<Field> {
+        let form = self.lookup(self.catalog(), "AcroForm").dict().cloned().unwrap_or_default();
+        let roots = self.lookup(&form, "Fields").array().cloned().unwrap_or_default();
+        let mut out = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack: Vec<(u32, String, String, i64)> = roots.iter().rev()
+            .filter_map(|f| match f { Object::Ref(id) => Some((*id, String::new(), String::new(), 0)), _ => None })
+            .collect();
+        while let Some((id, parent_name, parent_kind, parent_flags)) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(dict) = self.get(id).dict() else { continue };
+            let part = match self.lookup(dict, "T") { Object::Str(t) => Some(decode_text(t)), _ => None };
+            let name = match (&part, parent_name.is_empty()) {
+                (Some(part), true) => part.clone(),
+                (Some(part), false) => format!("{parent_name}.{part}"),
+                (None, _) => parent_name.clone(),
+            };
+            let kind = self.lookup(dict, "FT").name().map(str::to_string).unwrap_or(parent_kind);
+            let flags = self.lookup(dict, "Ff").int().unwrap_or(parent_flags);
+            let kids: Vec<u32> = self.lookup(dict, "Kids").array().into_iter().flatten()
+                .filter_map(|k| match k { Object::Ref(id) => Some(*id), _ => None })
+                .collect();
+            let (child_fields, widgets): (Vec<u32>, Vec<u32>) = kids.iter()
+                .partition(|k| self.get(**k).dict().is_some_and(|d| d.contains_key("T")));
+            stack.extend(child_fields.iter().rev().map(|k| (*k, name.clone(), kind.clone(), flags)));
+            if child_fields.is_empty() && !name.is_empty() {
+                let widgets = if widgets.is_empty() { vec![id] } else { widgets };
+                out.push(Field { id, name, kind, flags, widgets });
+            }
+        }
+        out
+    }
+
+    /// The "on" appearance names of a checkbox or radio button's widgets.
+    fn on_states(&self, widget: u32) -> Vec<String> {
+        let Some(dict) = self.get(widget).dict() else { return Vec::new() };
+        let appearance = self.lookup(dict, "AP").dict().cloned().unwrap_or_default();
+        self.lookup(&appearance, "N").dict().map(|n| n.keys().filter(|k| *k != "Off").cloned().collect()).unwrap_or_default()
+    }
+
+    fn field_info(&self, field: &Field) -> FormField {
+        let dict = self.get(field.id).dict().cloned().unwrap_or_default();
+        let value = match self.lookup(&dict, "V") {
+            Object::Str(s) => decode_text(s),
+            Object::Name(n) => n.clone(),
+            Object::Array(items) => items.iter().filter_map(|i| match i { Object::Str(s) => Some(decode_text(s)), _ => None }).collect::<Vec<_>>().join(", "),
+            _ => String::new(),
+        };
+        let (kind, options) = match field.kind.as_str() {
+            "Btn" if field.flags & FLAG_PUSHBUTTON != 0 => ("button", Vec::new()),
+            "Btn" => {
+                let mut states: Vec<String> = field.widgets.iter().flat_map(|w| self.on_states(*w)).collect();
+                states.dedup();
+                (if field.flags & FLAG_RADIO != 0 { "radio" } else { "checkbox" }, states)
+            }
+            "Ch" => {
+                let options = self.lookup(&dict, "Opt").array().into_iter().flatten().filter_map(|o| match self.resolve(o) {
+                    Object::Str(s) => Some(decode_text(s)),
+                    Object::Array(pair) => pair.get(1).and_then(|p| match p { Object::Str(s) => Some(decode_text(s)), _ => None }),
+                    _ => None,
+                }).collect();
+                ("choice", options)
+            }
+            "Sig" => ("signature", Vec::new()),
+            _ => ("text", Vec::new()),
+        };
+        FormField { name: field.name.clone(), kind: kind.into(), value, options }
+    }
+
+    fn helvetica(&mut self) -> u32 {
+        let mut font = Dict::new();
+        font.insert("Type".into(), Object::name_obj("Font"));
+        font.insert("Subtype".into(), Object::name_obj("Type1"));
+        font.insert("BaseFont".into(), Object::name_obj("Helvetica"));
+        font.insert("Encoding".into(), Object::name_obj("WinAnsiEncoding"));
+        self.add(Object::Dict(font))
+    }
+
+    /// A plain appearance for a filled-in text field, for viewers that don't redraw fields
+    /// themselves.
+    fn text_appearance(&mut self, widget: u32, value: &str, default_size: f64, font: u32) {
+        let Some(dict) = self.get(widget).dict() else { return };
+        let rect: Vec<f64> = self.lookup(dict, "Rect").array().into_iter().flatten().filter_map(Object::number).collect();
+        let [x1, y1, x2, y2] = rect[..] else { return };
+        let (width, height) = ((x2 - x1).abs(), (y2 - y1).abs());
+        let size = if default_size > 0.0 { default_size } else { (height * 0.7).clamp(4.0, 12.0) };
+        let lines: Vec<&str> = value.lines().collect();
+        // One line sits in the middle of the box, several start from the top.
+        let baseline = if lines.len() > 1 { height - size - 2.0 } else { (height - size) / 2.0 + size * 0.22 };
+        let mut content = format!("/Tx BMC q BT /Helv {size} Tf 0 g {} TL 2 {baseline} Td ", size * 1.15);
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                content.push_str("T* ");
+            }
+            content.push_str(&format!("{} Tj ", pdf_string(line)));
+        }
+        content.push_str("ET Q EMC");
+
+        let mut fonts = Dict::new();
+        fonts.insert("Helv".into(), Object::Ref(font));
+        let mut resources = Dict::new();
+        resources.insert("Font".into(), Object::Dict(fonts));
+        let mut form = Dict::new();
+        form.insert("Type".into(), Object::name_obj("XObject"));
+        form.insert("Subtype".into(), Object::name_obj("Form"));
+        form.insert("BBox".into(), Object::Array(vec![Object::Int(0), Object::Int(0), Object::Real(width), Object::Real(height)]));
+        form.insert("Resources".into(), Object::Dict(resources));
+        let stream = self.add(Object::Stream(form, content.into_bytes()));
+        let mut appearance = Dict::new();
+        appearance.insert("N".into(), Object::Ref(stream));
+        if let Some(widget) = self.objects.get_mut(&widget).and_then(Object::dict_mut) {
+            widget.insert("AP".into(), Object::Dict(appearance));
+        }
+    }
+}
+
+pub fn read_form(input: &Path) -> Result<Vec<FormField>, String> {
+    let document = Document::open(input)?;
+    Ok(document.fields().iter().map(|f| document.field_info(f)).collect())
+}
+
+fn truthy(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" | "x" | "checked" => Some(true),
+            "false" | "no" | "off" | "0" | "" | "unchecked" => Some(false),
+            _ => None,
+        },
+        Value::Number(n) => Some(n.as_f64() != Some(0.0)),
+        _ => None,
+    }
+}
+
+/// Fills AcroForm fields by name from `values`, e.g. `{"name": "Ada", "agree": true}`, and
+/// saves the result as `to`. Unknown field names are refused, listing the real ones.
+pub fn fill_form(input: &Path, values: &serde_json::Map<String, Value>, to: &Path) -> Result<String, String> {
+    let mut document = Document::open(input)?;
+    let fields = document.fields();
+    if fields.is_empty() {
+        return Err(format!("{} has no form fields", fileops::name(input)));
+    }
+    let unknown: Vec<&str> = values.keys().filter(|k| !fields.iter().any(|f| &f.name == *k)).map(String::as_str).collect();
+    if !unknown.is_empty() {
+        let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+        return Err(format!("No field called {} in the form; its fields are {}", unknown.join(", "), names.join(", ")));
+    }
+    let form_ref = document.catalog().get("AcroForm").cloned();
+    let form = document.resolve(form_ref.as_ref().unwrap_or(&Object::Null)).dict().cloned().unwrap_or_default();
+    let default_size = |da: &str| da.split_whitespace().collect::<Vec<_>>().windows(2)
+        .find(|w| w[1] == "Tf").and_then(|w| w[0].parse::<f64>().ok()).unwrap_or(0.0);
+    let form_da = match document.lookup(&form, "DA") { Object::Str(s) => decode_text(s), _ => String::new() };
+    let font = document.helvetica();
+
+    let mut filled = 0;
+    for field in fields.iter().filter(|f| values.contains_key(&f.name)) {
+        let value = &values[&field.name];
+        let text = match value {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        let (field_value, states): (Object, Vec<(u32, String)>) = match field.kind.as_str() {
+            "Btn" if field.flags & FLAG_PUSHBUTTON != 0 => return Err(format!("{} is a button, not something to fill in", field.name)),
+            "Btn" if field.flags & FLAG_RADIO != 0 => {
+                let available: Vec<String> = field.widgets.iter().flat_map(|w| document.on_states(*w)).collect();
+                let chosen = if truthy(value) == Some(false) { "Off".to_string() } else { text.clone() };
+                if chosen != "Off" && !available.contains(&chosen) {
+                    return Err(format!("{} can be {}", field.name, available.join(", ")));
+                }
+                let states = field.widgets.iter().map(|w| {
+                    (*w, if document.on_states(*w).contains(&chosen) { chosen.clone() } else { "Off".into() })
+                }).collect();
+                (Object::Name(chosen), states)
+            }
+            "Btn" => {
+                let on = field.widgets.iter().flat_map(|w| document.on_states(*w)).next().unwrap_or_else(|| "Yes".into());
+                let checked = truthy(value).or_else(|| (text == on).then_some(true))
+                    .ok_or_else(|| format!("{} is a checkbox; use true or false", field.name))?;
+                let state = if checked { on } else { "Off".into() };
+                (Object::Name(state.clone()), field.widgets.iter().map(|w| (*w, state.clone())).collect())
+            }
+            "Sig" => return Err(format!("{} is a signature field, which can't be filled in here", field.name)),
+            _ => (Object::Str(text_string(&text)), Vec::new()),
+        };
+        if let Some(dict) = document.objects.get_mut(&field.id).and_then(Object::dict_mut) {
+            dict.insert("V".into(), field_value);
+        }
+        for (widget, state) in states {
+            if let Some(dict) = document.objects.get_mut(&widget).and_then(Object::dict_mut) {
+                dict.insert("AS".into(), Object::Name(state));
+            }
+        }
+        if field.kind != "Btn" {
+            let da = match document.lookup(document.get(field.id).dict().unwrap_or(&Dict::new()), "DA") {
+                Object::Str(s) => decode_text(s),
+                _ => form_da.clone(),
+            };
+            for widget in &field.widgets {
+                document.text_appearance(*widget, &text, default_size(&da), font);
+            }
+        }
+        filled += 1;
+    }
+
+    // Viewers that can redraw fields in their own style are asked to.
+    let mut form = form;
+    form.insert("NeedAppearances".into(), Object::Bool(true));
+    match form_ref {
+        Some(Object::Ref(id)) => { document.objects.insert(id, Object::Dict(form)); }
+        _ => {
+            let root = document.trailer.get("Root").and_then(|r| match r { Object::Ref(id) => Some(*id), _ => None });
+            if let Some(catalog) = root.and_then(|id| document.objects.get_mut(&id)).and_then(Object::dict_mut) {
+                catalog.insert("AcroForm".into(), Object::Dict(form));
+            }
+        }
+    }
+    document.write(to)?;
+    Ok(format!("Filled {filled} field(s) of {} and saved it as {}", fileops::name(input), to.display()))
+}
+
+// ─── Stamps ───
+
+/// Widths of Helvetica's printable ASCII characters, in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Text as WinAnsi bytes, the encoding the stamp font uses; anything it lacks becomes `?`.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars().map(|c| match c {
+        '€' => 0x80,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        c if (c as u32) < 0x80 && !c.is_control() => c as u8,
+        c if (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+        _ => b'?',
+    }).collect()
+}
+
+/// `text` as a PDF string operand in WinAnsi, parentheses and backslashes escaped.
+pub fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for b in win_ansi(text) {
+        match b {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            32..=126 => out.push(b as char),
+            other => out.push_str(&format!("\\{other:03o}")),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// How wide `text` is in Helvetica at `size` points.
+pub fn text_width(text: &str, size: f64) -> f64 {
+    let units: u32 = win_ansi(text).iter().map(|b| match b {
+        32..=126 => HELVETICA_WIDTHS[(*b - 32) as usize] as u32,
+        _ => 556,
+    }).sum();
+    units as f64 * size / 1000.0
+}
+
+pub struct Stamp {
+    pub text: String,
+    /// `top-left`, `top-right`, `bottom-left`, `bottom-right`, `top`, `bottom` or `center`.
+    pub position: String,
+    pub size: f64,
+    /// `black`, `gray`, `red` or `blue`.
+    pub color: String,
+    pub pages: String,
+}
+
+/// Writes `stamp.text` on the chosen pages, replacing `{page}` and `{pages}` with page numbers,
+/// and saves the result as `to`.
+pub fn stamp(input: &Path, stamp: &Stamp, to: &Path) -> Result<String, String> {
+    let mut document = Document::open(input)?;
+    let (pages, _) = document.pages();
+    let chosen = parse_pages(&stamp.pages, pages.len())?;
+    let rgb = match stamp.color.as_str() {
+        "" | "black" => "0 0 0",
+        "gray" | "grey" => "0.5 0.5 0.5",
+        "red" => "0.8 0 0",
+        "blue" => "0 0 0.7",
+        other => return Err(format!("Unknown stamp color {other}; use black, gray, red or blue")),
+    };
+    let size = if stamp.size > 0.0 { stamp.size.clamp(4.0, 96.0) } else { 10.0 };
+    let font = document.helvetica();
+    let opening = document.add(Object::Stream(Dict::new(), b"q\n".to_vec()));
+    for index in &chosen {
+        let id = pages[*index];
+        let page = document.page_with_inherited(id);
+        let media: Vec<f64> = document.lookup(&page, "MediaBox").array().into_iter().flatten().filter_map(Object::number).collect();
+        let [x1, y1, x2, y2] = media[..] else { continue };
+        let text = stamp.text.replace("{page}", &(index + 1).to_string()).replace("{pages}", &pages.len().to_string());
+        let width = text_width(&text, size);
+        let left = x1.min(x2) + STAMP_MARGIN;
+        let right = x1.max(x2) - STAMP_MARGIN - width;
+        let centre = (x1 + x2 - width) / 2.0;
+        let top = y1.max(y2) - STAMP_MARGIN - size;
+        let bottom = y1.min(y2) + STAMP_MARGIN;
+        let (x, y) = match stamp.position.as_str() {
+            "top-left" => (left, top),
+            "" | "top-right" => (right, top),
+            "bottom-left" => (left, bottom),
+            "bottom-right" => (right, bottom),
+            "top" => (centre, top),
+            "bottom" => (centre, bottom),
+            "center" | "centre" => (centre, (y1 + y2 - size) / 2.0),
+            other => return Err(format!("Unknown stamp position {other}")),
+        };
+        let content = format!("Q\nq BT /{STAMP_FONT} {size} Tf {rgb} rg {x:.2} {y:.2} Td {} Tj ET Q\n", pdf_string(&text));
+        let stamp_stream = document.add(Object::Stream(Dict::new(), content.into_bytes()));
+
+        // The page's own drawing is wrapped in q/Q so nothing it leaves set moves the stamp.
+        let mut contents = vec![Object::Ref(opening)];
+        match page.get("Contents") {
+            Some(Object::Array(items)) => contents.extend(items.iter().cloned()),
+            Some(reference @ Object::Ref(_)) => match document.resolve(reference) {
+                Object::Array(items) => contents.extend(items.iter().cloned()),
+                _ => contents.push(reference.clone()),
+            },
+            _ => {}
+        }
+        contents.push(Object::Ref(stamp_stream));
+
+        let mut resources = document.lookup(&page, "Resources").dict().cloned().unwrap_or_default();
+        let mut fonts = document.lookup(&resources, "Font").dict().cloned().unwrap_or_default();
+        fonts.insert(STAMP_FONT.into(), Object::Ref(font));
+        resources.insert("Font".into(), Object::Dict(fonts));
+        if let Some(dict) = document.objects.get_mut(&id).and_then(Object::dict_mut) {
+            dict.insert("Contents".into(), Object::Array(contents));
+            dict.insert("Resources".into(), Object::Dict(resources));
+            if let Some(media) = page.get("MediaBox") {
+                dict.entry("MediaBox".into()).or_insert_with(|| media.clone());
+            }
+        }
+    }
+    document.write(to)?;
+    Ok(format!("Stamped \"{}\" on {} page(s) of {} and saved it as {}", stamp.text, chosen.len(), fileops::name(input), to.display()))
+}
+
+// ─── Text ───
+
+/// How one font's string bytes become text: its ToUnicode map, keyed by character code,
+/// and how many bytes each code takes.
+struct FontMap {
+    codes: HashMap<u32, String>,
+    width: usize,
+    /// Codes missing from the map are read as WinAnsi, which only makes sense for simple fonts.
+    fallback: bool,
+}
+
+/// Largest `bfrange` expanded; real maps stay well under this.
+const MAX_RANGE: u32 = 0x1_0000;
+/// Form XObjects nested deeper than this are not read.
+const MAX_FORM_DEPTH: usize = 8;
+/// A `TJ` gap wider than this many thousandths of the font size reads as a space.
+const WORD_GAP: f64 = 150.0;
+/// Text moved up or down less than this, in text space units, stays on the same line.
+const LINE_TOLERANCE: f64 = 1.0;
+
+/// Operands and operators of a content stream or CMap, in order. Inline images are skipped.
+fn operations(data: &[u8]) -> Vec<(Vec<Object>, String)> {
+    let mut parser = Parser::new(data, 0);
+    let mut operands = Vec::new();
+    let mut out = Vec::new();
+    loop {
+        parser.skip_space();
+        let Some(b) = parser.peek() else { break };
+        if matches!(b, b'/' | b'(' | b'<' | b'[' | b'+' | b'-' | b'.' | b'0'..=b'9') {
+            match parser.object() {
+                Ok(object) => operands.push(object),
+                Err(_) => break,
+            }
+            continue;
+        }
+        let token = parser.token();
+        if token.is_empty() {
+            parser.pos += 1;
+            continue;
+        }
+        if token == b"ID" {
+            // Inline image data runs up to `EI` on its own.
+            let rest = &data[parser.pos..];
+            match rest.windows(4).position(|w| is_space(w[0]) && &w[1..3] == b"EI" && (is_space(w[3]) || is_delimiter(w[3]))) {
+                Some(end) => parser.pos += end + 3,
+                None => break,
+            }
+            operands.clear();
+            continue;
+        }
+        out.push((std::mem::take(&mut operands), String::from_utf8_lossy(token).to_string()));
+    }
+    out
+}
+
+fn code_of(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |code, b| (code << 8) | *b as u32)
+}
+
+fn utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks(2).filter(|c| c.len() == 2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn font_map(document: &Document, font: &Dict) -> FontMap {
+    let composite = font.get("Subtype").and_then(Object::name) == Some("Type0");
+    let mut map = FontMap { codes: HashMap::new(), width: if composite { 2 } else { 1 }, fallback: !composite };
+    let Object::Stream(dict, raw) = document.lookup(font, "ToUnicode") else { return map };
+    let Ok(cmap) = decode(dict, raw) else { return map };
+    for (operands, operator) in operations(&cmap) {
+        match operator.as_str() {
+            "endcodespacerange" => {
+                if let Some(Object::Str(low)) = operands.first() {
+                    map.width = low.len().clamp(1, 4);
+                }
+            }
+            "endbfchar" => {
+                for pair in operands.chunks(2) {
+                    if let [Object::Str(code), Object::Str(text)] = pair {
+                        map.codes.insert(code_of(code), utf16(text));
+                    }
+                }
+            }
+            "endbfrange" => {
+                for range in operands.chunks(3) {
+                    let [Object::Str(low), Object::Str(high), target] = range else { continue };
+                    let (low, high) = (code_of(low), code_of(high));
+                    if high < low || high - low > MAX_RANGE {
+                        continue;
+                    }
+                    for (offset, code) in (low..=high).enumerate() {
+                        let text = match target {
+                            Object::Str(start) if start.len() >= 2 => {
+                                let mut units = start.clone();
+                                let at = units.len() - 2;
+                                let last = u16::from_be_bytes([units[at], units[at + 1]]).wrapping_add(offset as u16);
+                                units[at..].copy_from_slice(&last.to_be_bytes());
+                                utf16(&units)
+                            }
+                            Object::Array(items) => match items.get(offset) {
+                                Some(Object::Str(text)) => utf16(text),
+                                _ => continue,
+                            },
+                            _ => continue,
+                        };
+                        map.codes.insert(code, text);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    map
+}
+
+/// The reverse of `win_ansi`, for simple fonts without a ToUnicode map.
+fn from_win_ansi(b: u8) -> char {
+    match b {
+        0x80 => '€',
+        0x91 => '‘',
+        0x92 => '’',
+        0x93 => '“',
+        0x94 => '”',
+        0x95 => '•',
+        0x96 => '–',
+        0x97 => '—',
+        b => b as char,
+    }
+}
+
+fn show(bytes: &[u8], font: Option<&FontMap>, out: &mut String) {
+    let Some(font) = font else {
+        out.extend(bytes.iter().map(|b| from_win_ansi(*b)));
+        return;
+    };
+    for code in bytes.chunks(font.width) {
+        match font.codes.get(&code_of(code)) {
+            Some(text) => out.push_str(text),
+            None if font.fallback => out.extend(code.iter().map(|b| from_win_ansi(*b))),
+            None => {}
+        }
+    }
+}
+
+/// Starts a new line unless the text so far already ends one.
+fn break_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with([' ', '\n']) {
+        out.push(' ');
+    }
+}
+
+/// Appends the text a content stream draws, given the `resources` it runs with.
+fn content_text(document: &Document, data: &[u8], resources: &Dict, depth: usize, out: &mut String) {
+    let fonts: HashMap<String, FontMap> = document.lookup(resources, "Font").dict()
+        .map(|fonts| fonts.iter()
+            .filter_map(|(name, font)| Some((name.clone(), font_map(document, document.resolve(font).dict()?))))
+            .collect())
+        .unwrap_or_default();
+    let mut font: Option<&FontMap> = None;
+    // Only the vertical position matters: a change of it starts a new line.
+    let (mut y, mut leading) = (0.0, 0.0);
+    let mut line_y: Option<f64> = None;
+    let mut moved = |y: f64, out: &mut String| {
+        match line_y {
+            Some(last) if (last - y).abs() < LINE_TOLERANCE => space(out),
+            _ => break_line(out),
+        }
+        line_y = Some(y);
+    };
+    for (operands, operator) in operations(data) {
+        let number = |i: usize| operands.get(i).and_then(Object::number).unwrap_or(0.0);
+        match operator.as_str() {
+            "Tf" => font = operands.first().and_then(Object::name).and_then(|name| fonts.get(name)),
+            "TL" => leading = number(0),
+            "BT" => y = 0.0,
+            "Td" | "TD" => {
+                if operator == "TD" {
+                    leading = -number(1);
+                }
+                y += number(1);
+                moved(y, out);
+            }
+            "Tm" => {
+                y = number(5);
+                moved(y, out);
+            }
+            "T*" => {
+                y -= leading;
+                moved(y, out);
+            }
+            "Tj" => {
+                if let Some(Object::Str(bytes)) = operands.first() {
+                    show(bytes, font, out);
+                }
+            }
+            "'" | "\"" => {
+                y -= leading;
+                moved(y, out);
+                if let Some(Object::Str(bytes)) = operands.last() {
+                    show(bytes, font, out);
+                }
+            }
+            "TJ" => {
+                for item in operands.first().and_then(Object::array).into_iter().flatten() {
+                    match item {
+                        Object::Str(bytes) => show(bytes, font, out),
+                        gap if gap.number().is_some_and(|g| g < -WORD_GAP) => space(out),
+                        _ => {}
+                    }
+                }
+            }
+            "Do" if depth < MAX_FORM_DEPTH => {
+                let Some(name) = operands.first().and_then(Object::name) else { continue };
+                let xobject = document.lookup(resources, "XObject").dict().map(|x| document.lookup(x, name));
+                if let Some(Object::Stream(dict, raw)) = xobject {
+                    if dict.get("Subtype").and_then(Object::name) != Some("Form") {
+                        continue;
+                    }
+                    let Ok(inner) = decode(dict, raw) else { continue };
+                    let inner_resources = document.lookup(dict, "Resources").dict().unwrap_or(resources);
+                    content_text(document, &inner, inner_resources, depth + 1, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The page's content streams joined into one, as the spec says to read them.
+fn page_content(document: &Document, page: &Dict) -> Vec<u8> {
+    let parts = match page.get("Contents").map(|c| document.resolve(c)) {
+        Some(Object::Array(items)) => items.iter().map(|i| document.resolve(i)).collect(),
+        Some(stream) => vec![stream],
+        None => Vec::new(),
+    };
+    let mut out = Vec::new();
+    for part in parts {
+        if let Object::Stream(dict, raw) = part {
+            if let Ok(data) = decode(dict, raw) {
+                out.extend_from_slice(&data);
+                out.push(b'\n');
+            }
+        }
+    }
+    out
+}
+
+/// The text on every page, one line per line of text where the PDF makes that clear, pages
+/// separated by a blank line. Scans have none: their pages are only images.
+pub fn text(input: &Path) -> Result<String, String> {
+    let document = Document::open(input)?;
+    let mut pages = Vec::new();
+    for id in document.pages().0 {
+        let page = document.page_with_inherited(id);
+        let resources = document.lookup(&page, "Resources").dict().cloned().unwrap_or_default();
+        let mut out = String::new();
+        content_text(&document, &page_content(&document, &page), &resources, 0, &mut out);
+        let out: String = out.chars().filter(|c| *c == '\n' || !c.is_control()).collect();
+        let lines: Vec<&str> = out.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        pages.push(lines.join("\n"));
+    }
+    Ok(pages.join("\n\n").trim().to_string())
+}
+
+/// The JPEG images drawn on each page, in page order, as files of their own. Scanners and
+/// phone apps save scans this way; other image encodings are left out.
+pub fn jpeg_images(input: &Path) -> Result<Vec<Vec<u8>>, String> {
+    let document = Document::open(input)?;
+    let mut seen = HashSet::new();
+    let mut images = Vec::new();
+    for id in document.pages().0 {
+        let page = document.page_with_inherited(id);
+        let Some(xobjects) = document.lookup(&page, "Resources").dict().and_then(|r| document.lookup(r, "XObject").dict()) else { continue };
+        for reference in xobjects.values() {
+            if let Object::Ref(id) = reference {
+                if !seen.insert(*id) {
+                    continue;
+                }
+            }
+            let Object::Stream(dict, raw) = document.resolve(reference) else { continue };
+            let jpeg = match dict.get("Filter") {
+                Some(Object::Name(filter)) => filter == "DCTDecode",
+                Some(Object::Array(filters)) => filters.len() == 1 && filters[0].name() == Some("DCTDecode"),
+                _ => false,
+            };
+            if dict.get("Subtype").and_then(Object::name) == Some("Image") && jpeg {
+                images.push(raw.clone());
+            }
+        }
+    }
+    Ok(images)
+}
+
+// ─── New documents ───
+
+/// A4, in points, for documents the app lays out itself.
+pub const PAGE_SIZE: (f64, f64) = (595.0, 842.0);
+
+/// Writes a new PDF with one page per content stream in `pages`. The pages can use
+/// Helvetica as `/F1` and Helvetica-Bold as `/F2`.
+pub fn write_new(pages: &[String], to: &Path) -> Result<(), String> {
+    let mut document = Document { objects: BTreeMap::new(), trailer: Dict::new() };
+    let regular = document.helvetica();
+    let bold = document.helvetica();
+    if let Some(font) = document.objects.get_mut(&bold).and_then(Object::dict_mut) {
+        font.insert("BaseFont".into(), Object::name_obj("Helvetica-Bold"));
+    }
+    let mut fonts = Dict::new();
+    fonts.insert("F1".into(), Object::Ref(regular));
+    fonts.insert("F2".into(), Object::Ref(bold));
+    let mut resources = Dict::new();
+    resources.insert("Font".into(), Object::Dict(fonts));
+    let resources = document.add(Object::Dict(resources));
+    let pages_id = document.add(Object::Null);
+    let mut kids = Vec::with_capacity(pages.len());
+    for content in pages {
+        let stream = document.add(Object::Stream(Dict::new(), content.as_bytes().to_vec()));
+        let mut page = Dict::new();
+        page.insert("Type".into(), Object::name_obj("Page"));
+        page.insert("Parent".into(), Object::Ref(pages_id));
+        page.insert("Contents".into(), Object::Ref(stream));
+        kids.push(Object::Ref(document.add(Object::Dict(page))));
+    }
+    let mut tree = Dict::new();
+    tree.insert("Type".into(), Object::name_obj("Pages"));
+    tree.insert("Count".into(), Object::Int(kids.len() as i64));
+    tree.insert("Kids".into(), Object::Array(kids));
+    tree.insert("MediaBox".into(), Object::Array(vec![Object::Int(0), Object::Int(0), Object::Real(PAGE_SIZE.0), Object::Real(PAGE_SIZE.1)]));
+    tree.insert("Resources".into(), Object::Ref(resources));
+    document.objects.insert(pages_id, Object::Dict(tree));
+    let mut catalog = Dict::new();
+    catalog.insert("Type".into(), Object::name_obj("Catalog"));
+    catalog.insert("Pages".into(), Object::Ref(pages_id));
+    let root = document.add(Object::Dict(catalog));
+    document.trailer.insert("Root".into(), Object::Ref(root));
+    document.write(to)
+}
+
+/// `report.pdf` → `report stamped.pdf` beside it, when a tool isn't told where to save.
+pub fn beside(input: &Path, suffix: &str) -> PathBuf {
+    let stem = input.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "document".into());
+    input.with_file_name(format!("{stem} {suffix}.pdf"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Scratch;
+
+    fn page(text: &str) -> String {
+        format!("BT /F1 12 Tf 72 720 Td {} Tj ET", pdf_string(text))
+    }
+
+    fn new_pdf(dir: &Path, name: &str, pages: &[&str]) -> PathBuf {
+        let path = dir.join(name);
+        write_new(&pages.iter().map(|p| page(p)).collect::<Vec<_>>(), &path).unwrap();
+        path
+    }
+
+    /// A hand-written PDF with no cross-reference table, so it is read by scanning.
+    fn form_pdf(dir: &Path) -> PathBuf {
+        let path = dir.join("form.pdf");
+        fs::write(&path, b"%PDF-1.4
+1 0 obj << /Type /Catalog /Pages 2 0 R /AcroForm << /Fields [4 0 R 5 0 R] /DA (/Helv 0 Tf 0 g) >> >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 /MediaBox [0 0 200 200] >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /Annots [4 0 R 5 0 R] >> endobj
+4 0 obj << /Type /Annot /Subtype /Widget /FT /Tx /T (name) /Rect [10 10 110 30] >> endobj
+5 0 obj << /Type /Annot /Subtype /Widget /FT /Btn /T (agree) /Rect [10 40 20 50] /AP << /N << /Yes 6 0 R /Off 6 0 R >> >> >> endobj
+6 0 obj << /Length 0 >> stream
+endstream endobj
+%%EOF
+").unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_page_lists() {
+        assert_eq!(parse_pages("", 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(parse_pages("all", 2).unwrap(), vec![0, 1]);
+        assert_eq!(parse_pages("1-2, 4", 5).unwrap(), vec![0, 1, 3]);
+        assert_eq!(parse_pages("3-", 4).unwrap(), vec![2, 3]);
+        assert_eq!(parse_pages("-2", 4).unwrap(), vec![0, 1]);
+        assert!(parse_pages("0", 3).is_err());
+        assert!(parse_pages("4", 3).is_err());
+        assert!(parse_pages("3-1", 3).is_err());
+        assert!(parse_pages("one", 3).is_err());
+    }
+
+    #[test]
+    fn writes_and_reads_back_text() {
+        let dir = Scratch::new("pdf");
+        let path = new_pdf(&dir, "new.pdf", &["Hello (world)", "Second page"]);
+        assert_eq!(text(&path).unwrap(), "Hello (world)\n\nSecond page");
+    }
+
+    #[test]
+    fn merges_extracts_and_splits_pages() {
+        let dir = Scratch::new("pdf");
+        let a = new_pdf(&dir, "a.pdf", &["One", "Two"]);
+        let b = new_pdf(&dir, "b.pdf", &["Three"]);
+        let merged = dir.join("merged.pdf");
+        merge(&[a.clone(), b], &merged).unwrap();
+        assert_eq!(text(&merged).unwrap(), "One\n\nTwo\n\nThree");
+
+        let picked = dir.join("picked.pdf");
+        extract_pages(&merged, "3, 1", &picked).unwrap();
+        assert_eq!(text(&picked).unwrap(), "Three\n\nOne");
+        assert!(extract_pages(&merged, "1, 1", &dir.join("twice.pdf")).is_err());
+
+        let parts = dir.join("parts");
+        split(&merged, 2, &parts).unwrap();
+        assert_eq!(text(&parts.join("merged p1-2.pdf")).unwrap(), "One\n\nTwo");
+        assert_eq!(text(&parts.join("merged p3.pdf")).unwrap(), "Three");
+        assert!(split(&merged, 2, &parts).is_err(), "existing parts are never overwritten");
+        assert!(merge(&[a], &dir.join("one.pdf")).is_err());
+    }
+
+    #[test]
+    fn stamps_chosen_pages() {
+        let dir = Scratch::new("pdf");
+        let input = new_pdf(&dir, "in.pdf", &["One", "Two"]);
+        let to = dir.join("stamped.pdf");
+        let stamp_with = |color: &str| Stamp { text: "Page {page} of {pages}".into(), position: "bottom".into(), size: 0.0, color: color.into(), pages: "2".into() };
+        stamp(&input, &stamp_with("red"), &to).unwrap();
+        assert_eq!(text(&to).unwrap(), "One\n\nTwo\nPage 2 of 2");
+        assert!(stamp(&input, &stamp_with("green"), &dir.join("green.pdf")).is_err());
+    }
+
+    #[test]
+    fn reads_and_fills_forms() {
+        let dir = Scratch::new("pdf");
+        let input = form_pdf(&dir);
+        let fields = read_form(&input).unwrap();
+        let kinds: Vec<(&str, &str)> = fields.iter().map(|f| (f.name.as_str(), f.kind.as_str())).collect();
+        assert_eq!(kinds, vec![("name", "text"), ("agree", "checkbox")]);
+        assert_eq!(fields[1].options, vec!["Yes".to_string()]);
+
+        let to = dir.join("filled.pdf");
+        let values = serde_json::json!({ "name": "Ada Lovelace", "agree": true });
+        fill_form(&input, values.as_object().unwrap(), &to).unwrap();
+        let filled = read_form(&to).unwrap();
+        assert_eq!(filled[0].value, "Ada Lovelace");
+        assert_eq!(filled[1].value, "Yes");
+
+        let unknown = serde_json::json!({ "email": "x" });
+        assert!(fill_form(&input, unknown.as_object().unwrap(), &dir.join("unknown.pdf")).unwrap_err().contains("name, agree"));
+        let unclear = serde_json::json!({ "agree": "maybe" });
+        assert!(fill_form(&input, unclear.as_object().unwrap(), &dir.join("unclear.pdf")).is_err());
+    }
+
+    #[test]
+    fn refuses_files_that_are_not_pdfs() {
+        let dir = Scratch::new("pdf");
+        let path = dir.join("notes.pdf");
+        fs::write(&path, b"just some text").unwrap();
+        assert!(text(&path).unwrap_err().contains("isn't a PDF"));
+        fs::write(&path, b"%PDF-1.7\n").unwrap();
+        assert!(text(&path).is_err());
+    }
+
+    #[test]
+    fn reads_truncated_files_without_panicking() {
+        let dir = Scratch::new("pdf");
+        let path = new_pdf(&dir, "whole.pdf", &["Some text"]);
+        let data = fs::read(&path).unwrap();
+        for end in (0..data.len()).step_by(7) {
+            let _ = Document::parse(&data[..end]);
+        }
+        // Without its cross-reference table the file is still found by scanning.
+        let cut = find_last(&data, b"xref").unwrap();
+        let document = Document::parse(&data[..cut]).unwrap();
+        assert_eq!(page_count(&document), 1);
+    }
+
+    #[test]
+    fn survives_malicious_structure() {
+        let cases: &[&[u8]] = &[
+            // Cross-reference offsets past the end of the file.
+            b"%PDF-1.7\nxref\n0 2\n0000000000 65535 f \n0000999999 00000 n \ntrailer << /Root 1 0 R >>\nstartxref\n9\n%%EOF",
+            // A subsection whose numbers overflow.
+            b"%PDF-1.7\nxref\n4294967295 4294967295\ntrailer << /Root 1 0 R >>\nstartxref\n9\n%%EOF",
+            // /Prev pointing back at itself.
+            b"%PDF-1.7\nxref\n0 0\ntrailer << /Root 1 0 R /Prev 9 >>\nstartxref\n9\n%%EOF",
+            // A negative stream length.
+            b"%PDF-1.7\n1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n2 0 obj << /Length -5 >> stream\nabc\nendstream endobj\n",
+            // An object stream claiming more objects than fit in memory, at a negative offset.
+            b"%PDF-1.7\n1 0 obj << /Type /Catalog >> endobj\n2 0 obj << /Type /ObjStm /N 9223372036854775807 /First -100 /Length 3 >> stream\n3 0\nendstream endobj\n",
+            // Nesting deep enough to exhaust the stack of a recursive reader.
+            &[b"%PDF-1.7\n1 0 obj << /Type /Catalog /A ".as_slice(), &[b'['; 100_000]].concat(),
+        ];
+        for data in cases {
+            let _ = Document::parse(data);
+        }
+    }
+
+    #[test]
+    fn stops_at_cycles_in_the_page_tree() {
+        let data = b"%PDF-1.7
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [2 0 R 3 0 R] /Parent 2 0 R >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R /Contents 3 0 R >> endobj
+";
+        let document = Document::parse(data).unwrap();
+        let (pages, _) = document.pages();
+        assert_eq!(pages, vec![3]);
+        let page = document.page_with_inherited(3);
+        assert!(page_content(&document, &page).is_empty());
+    }
+
+    #[test]
+    fn decodes_flate_streams_and_predictors() {
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+        // Two rows of three bytes, the second saved with the "up" predictor.
+        let rows = [0u8, 1, 2, 3, 2, 1, 1, 1];
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&rows).unwrap();
+        let mut params = Dict::new();
+        params.insert("Predictor".into(), Object::Int(12));
+        params.insert("Columns".into(), Object::Int(3));
+        let mut dict = Dict::new();
+        dict.insert("Filter".into(), Object::name_obj("FlateDecode"));
+        dict.insert("DecodeParms".into(), Object::Dict(params));
+        assert_eq!(decode(&dict, &encoder.finish().unwrap()).unwrap(), vec![1, 2, 3, 2, 3, 4]);
+        assert!(decode(&dict, b"not zlib").is_err());
+        dict.insert("Filter".into(), Object::name_obj("LZWDecode"));
+        assert!(decode(&dict, b"").unwrap_err().contains("LZWDecode"));
+    }
+}
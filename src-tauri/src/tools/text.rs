@@ -0,0 +1,451 @@
+//! Mechanical text work done exactly: pulling matches out with a regular expression,
+//! comparing two versions, changing case and reading and writing dates. A workflow can do
+//! these without a model call, and gets the same answer every time.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono::format::{Item, StrftimeItems};
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Map, Value};
+use std::sync::LazyLock;
+
+use crate::format;
+
+/// Matches returned at most, so a pattern like `.` can't produce an enormous result.
+const MAX_MATCHES: usize = 1000;
+/// Compiled patterns larger than this are refused.
+const MAX_PATTERN_BYTES: usize = 1 << 20;
+/// Edits the diff looks for before showing the rest as replaced wholesale.
+const MAX_EDITS: usize = 2000;
+
+// ─── Regular expressions ───
+
+/// Every match of `pattern` in `text` (only the first unless `all`). Without capture groups
+/// each match is its text; with them it is an object of the groups, by name where they
+/// have one and by number otherwise, and `null` for a group that didn't take part.
+pub fn regex_extract(text: &str, pattern: &str, all: bool, ignore_case: bool) -> Result<Value, String> {
+    let re = RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .size_limit(MAX_PATTERN_BYTES)
+        .build()
+        .map_err(|e| format!("The pattern isn't a valid regular expression: {e}"))?;
+    let names: Vec<String> = re.capture_names().enumerate().skip(1)
+        .map(|(i, name)| name.map_or_else(|| i.to_string(), str::to_string))
+        .collect();
+    let limit = if all { MAX_MATCHES } else { 1 };
+    let matches: Vec<Value> = re.captures_iter(text).take(limit).map(|caps| {
+        if names.is_empty() {
+            return json!(&caps[0]);
+        }
+        let groups: Map<String, Value> = names.iter().enumerate()
+            .map(|(i, name)| (name.clone(), caps.get(i + 1).map_or(Value::Null, |m| json!(m.as_str()))))
+            .collect();
+        Value::Object(groups)
+    }).collect();
+    Ok(Value::Array(matches))
+}
+
+// ─── Diff ───
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same,
+    Delete,
+    Insert,
+}
+
+/// The shortest edit script turning `a` into `b` (Myers' algorithm). Past `MAX_EDITS` the
+/// differing middle is given as deleted and inserted whole.
+fn edits(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let mut script = vec![Edit::Same; prefix];
+    script.extend(middle_edits(a_mid, b_mid));
+    script.extend(std::iter::repeat_n(Edit::Same, suffix));
+    script
+}
+
+fn middle_edits(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // The frontier before each round, kept for diagonals -d-1..=d+1 only.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+    'search: for d in 0..=(n + m).min(MAX_EDITS as isize) {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[at - 1] < v[at + 1]) { v[at + 1] } else { v[at - 1] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        let mut script = vec![Edit::Delete; a.len()];
+        script.extend(vec![Edit::Insert; b.len()]);
+        return script;
+    }
+    // Walk back through the saved frontiers from the end to the start.
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| (k + d + 1) as usize;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) { k + 1 } else { k - 1 };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            script.push(Edit::Same);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            script.push(if x == prev_x { Edit::Insert } else { Edit::Delete });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    script.reverse();
+    script
+}
+
+/// `start,count` for a hunk header; an empty side names the line before it.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{count}", start + 1),
+    }
+}
+
+/// A unified diff from `old` to `new` with `context` unchanged lines around each change,
+/// as `diff -u` writes it. Empty when the texts have the same lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let script = edits(&a, &b);
+    // Where each edit starts in the old and new texts.
+    let mut positions = Vec::with_capacity(script.len());
+    let (mut i, mut j) = (0, 0);
+    for edit in &script {
+        positions.push((i, j));
+        match edit {
+            Edit::Same => (i, j) = (i + 1, j + 1),
+            Edit::Delete => i += 1,
+            Edit::Insert => j += 1,
+        }
+    }
+    let changes: Vec<usize> = (0..script.len()).filter(|&e| script[e] != Edit::Same).collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+    // Changes closer together than twice the context share a hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &e in &changes {
+        match hunks.last_mut() {
+            Some((_, end)) if e <= *end + 2 * context + 1 => *end = e,
+            _ => hunks.push((e, e)),
+        }
+    }
+    let mut out = vec![format!("--- {old_name}"), format!("+++ {new_name}")];
+    for (first, last) in hunks {
+        let from = first.saturating_sub(context);
+        let to = (last + context + 1).min(script.len());
+        let (old_start, new_start) = positions[from];
+        let old_count = script[from..to].iter().filter(|e| **e != Edit::Insert).count();
+        let new_count = script[from..to].iter().filter(|e| **e != Edit::Delete).count();
+        out.push(format!("@@ -{} +{} @@", range(old_start, old_count), range(new_start, new_count)));
+        for e in from..to {
+            let (i, j) = positions[e];
+            out.push(match script[e] {
+                Edit::Same => format!(" {}", a[i]),
+                Edit::Delete => format!("-{}", a[i]),
+                Edit::Insert => format!("+{}", b[j]),
+            });
+        }
+    }
+    out.join("\n")
+}
+
+// ─── Case ───
+
+/// The words of `text` for re-casing: split at spaces, punctuation, and where camelCase
+/// turns from lower to upper.
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+/// The ASCII letter an accented Latin letter is written with in a URL slug, e.g. `é` → `e`.
+fn plain_letters(c: char) -> &'static str {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'å' | 'ā' | 'ą' => "a",
+        'ä' | 'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ō' | 'ø' => "o",
+        'ö' | 'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'š' => "s",
+        'ť' => "t",
+        'ù' | 'ú' | 'û' | 'ū' | 'ů' => "u",
+        'ü' => "ue",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => "",
+    }
+}
+
+/// `text` as a URL or file name slug: lower-case ASCII words joined by hyphens.
+pub fn slugify(text: &str) -> String {
+    let ascii: String = text.to_lowercase().chars()
+        .map(|c| match c.is_ascii() {
+            true => c.to_string(),
+            false => match plain_letters(c) {
+                "" if c.is_alphanumeric() => c.to_string(),
+                "" => " ".to_string(),
+                plain => plain.to_string(),
+            },
+        })
+        .collect();
+    ascii.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+pub const CASES: &[&str] = &["upper", "lower", "title", "sentence", "snake", "kebab", "camel", "pascal", "slug", "trim"];
+
+/// `text` written in `case`, one of `CASES`. `trim` keeps the case and tidies whitespace:
+/// runs of spaces become one and each line is trimmed.
+pub fn change_case(text: &str, case: &str) -> Result<String, String> {
+    let lower = || words(text).iter().map(|w| w.to_lowercase()).collect::<Vec<_>>();
+    Ok(match case.trim().to_lowercase().as_str() {
+        "upper" => text.to_uppercase(),
+        "lower" => text.to_lowercase(),
+        "title" => text.split(' ').map(capitalized).collect::<Vec<_>>().join(" "),
+        "sentence" => {
+            let lower = text.to_lowercase();
+            match lower.char_indices().find(|(_, c)| c.is_alphabetic()) {
+                Some((at, c)) => format!("{}{}{}", &lower[..at], c.to_uppercase(), &lower[at + c.len_utf8()..]),
+                None => lower,
+            }
+        }
+        "snake" => lower().join("_"),
+        "kebab" => lower().join("-"),
+        "camel" => lower().iter().enumerate().map(|(i, w)| if i == 0 { w.clone() } else { capitalized(w) }).collect(),
+        "pascal" => lower().iter().map(|w| capitalized(w)).collect(),
+        "slug" => slugify(text),
+        "trim" => text.lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string(),
+        other => return Err(format!("Unknown case \"{other}\"; use one of {}", CASES.join(", "))),
+    })
+}
+
+// ─── Dates ───
+
+const MONTHS: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: &[&str] = &["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+static TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(\d{1,2})(?::(\d{2}))?(?::(\d{2}))?\s*(am|pm|a\.m\.|p\.m\.)?(?:\s|,|$)").unwrap()
+});
+static NUMERIC_DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{1,4})[./-](\d{1,2})[./-](\d{1,4})\.?$").unwrap()
+});
+
+/// A time of day at the end of `text` (`14:30`, `2:30 pm`, `9am`), and what comes before it.
+fn split_time(text: &str) -> (String, Option<NaiveTime>) {
+    for caps in TIME.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        let (minute, meridiem) = (caps.get(2), caps.get(4));
+        // A bare number is a day or year, not a time.
+        if minute.is_none() && meridiem.is_none() {
+            continue;
+        }
+        let number = |i: usize| caps.get(i).map_or(0, |m| m.as_str().parse::<u32>().unwrap_or(99));
+        let mut hour = number(1);
+        match meridiem.map(|m| m.as_str().to_lowercase().starts_with('p')) {
+            Some(true) if hour < 12 => hour += 12,
+            Some(false) if hour == 12 => hour = 0,
+            _ => {}
+        }
+        if let Some(time) = NaiveTime::from_hms_opt(hour, number(2), number(3)) {
+            let rest = format!("{} {}", &text[..whole.start()], &text[whole.end()..]);
+            return (rest.replace(" at ", " "), Some(time));
+        }
+    }
+    (text.to_string(), None)
+}
+
+fn year(number: u32) -> i32 {
+    match number {
+        0..=68 => 2000 + number as i32,
+        69..=99 => 1900 + number as i32,
+        _ => number as i32,
+    }
+}
+
+/// `15.10.2026`, `10/15/26` or `2026-10-15`. Which of the first two numbers is the day
+/// follows the locale unless one of them can only be a day.
+fn numeric_date(text: &str, day_first: bool) -> Option<NaiveDate> {
+    let caps = NUMERIC_DATE.captures(text)?;
+    let part = |i: usize| caps[i].parse::<u32>().ok();
+    let (first, second, third) = (part(1)?, part(2)?, part(3)?);
+    if caps[1].len() == 4 {
+        return NaiveDate::from_ymd_opt(first as i32, second, third);
+    }
+    let day_first = match (first > 12, second > 12) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => day_first,
+    };
+    let (day, month) = if day_first { (first, second) } else { (second, first) };
+    NaiveDate::from_ymd_opt(year(third), month, day)
+}
+
+/// `October 15, 2026`, `15 Oct 2026`, `Thursday 15th October`; without a year, the current one.
+fn named_month_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let mut month = None;
+    let (mut day, mut year_number) = (None, None);
+    for token in text.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()) {
+        let lower = token.to_lowercase();
+        if let Some(m) = MONTHS.iter().position(|m| lower.starts_with(m) && lower.len() >= 3) {
+            month = Some(m as u32 + 1);
+        } else if WEEKDAYS.iter().any(|w| lower.starts_with(w)) || lower == "of" {
+            continue;
+        } else {
+            let digits = lower.trim_end_matches(|c: char| c.is_alphabetic());
+            if !["", "st", "nd", "rd", "th"].contains(&&lower[digits.len()..]) {
+                return None;
+            }
+            let number: u32 = digits.parse().ok()?;
+            match (digits.len(), day) {
+                (4, _) => year_number = Some(number as i32),
+                (_, None) if number <= 31 => day = Some(number),
+                _ => year_number = Some(year(number)),
+            }
+        }
+    }
+    NaiveDate::from_ymd_opt(year_number.unwrap_or(today.year()), month?, day?)
+}
+
+/// Reads a date, with its time of day if it has one: ISO 8601 and RFC 3339, email
+/// (RFC 2822) dates, Unix timestamps, numeric dates in the locale's order, dates with the
+/// month named in English, and `today`, `tomorrow`, `yesterday` and `now`. Dates with a time
+/// zone are turned into local time.
+pub fn parse_date(text: &str, day_first: bool, now: NaiveDateTime) -> Result<(NaiveDateTime, bool), String> {
+    let trimmed = text.trim();
+    let lower = trimmed.to_lowercase();
+    let at_midnight = |date: NaiveDate| (date.and_time(NaiveTime::MIN), false);
+    match lower.as_str() {
+        "now" => return Ok((now, true)),
+        "today" => return Ok(at_midnight(now.date())),
+        "tomorrow" => return Ok(at_midnight(now.date() + Duration::days(1))),
+        "yesterday" => return Ok(at_midnight(now.date() - Duration::days(1))),
+        _ => {}
+    }
+    let local = |time: DateTime<chrono::FixedOffset>| (time.with_timezone(&Local).naive_local(), true);
+    if trimmed.chars().all(|c| c.is_ascii_digit()) {
+        let stamp: i64 = trimmed.parse().map_err(|_| format!("\"{trimmed}\" is too large to be a date"))?;
+        let from_stamp = |stamp: i64, millis: bool| {
+            let time = if millis { Local.timestamp_millis_opt(stamp) } else { Local.timestamp_opt(stamp, 0) };
+            time.single().map(|t| (t.naive_local(), true))
+        };
+        let parsed = match trimmed.len() {
+            8 => NaiveDate::parse_from_str(trimmed, "%Y%m%d").ok().map(at_midnight),
+            9..=11 => from_stamp(stamp, false),
+            12..=14 => from_stamp(stamp, true),
+            _ => None,
+        };
+        return parsed.ok_or_else(|| format!("\"{trimmed}\" isn't a date"));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(trimmed).or_else(|_| DateTime::parse_from_rfc2822(trimmed)) {
+        return Ok(local(time));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(trimmed, format) {
+            return Ok((time, true));
+        }
+    }
+    let (rest, time) = split_time(trimmed);
+    let rest = rest.trim().trim_end_matches(',').trim();
+    let date = match rest.to_lowercase().as_str() {
+        "" if time.is_some() => Some(now.date()),
+        "today" => Some(now.date()),
+        "tomorrow" => Some(now.date() + Duration::days(1)),
+        "yesterday" => Some(now.date() - Duration::days(1)),
+        rest => numeric_date(rest, day_first).or_else(|| named_month_date(rest, now.date())),
+    };
+    let date = date.ok_or_else(|| format!("\"{trimmed}\" isn't a date that can be read"))?;
+    Ok(match time {
+        Some(time) => (date.and_time(time), true),
+        None => at_midnight(date),
+    })
+}
+
+/// Names for common date formats; anything else is taken as a strftime pattern like `%d %B %Y`.
+pub const DATE_FORMATS: &[&str] = &["iso", "locale", "long", "rfc2822", "unix"];
+
+/// `time` written as `format`; `locale` writes it the way the user's locale does.
+pub fn format_date(time: NaiveDateTime, has_time: bool, format: &str, locale: &str) -> Result<String, String> {
+    let local = || Local.from_local_datetime(&time).earliest().ok_or("That time doesn't exist in the local time zone");
+    Ok(match format.trim() {
+        "" | "iso" if has_time => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "" | "iso" => time.format("%Y-%m-%d").to_string(),
+        "locale" if has_time => format::date_time(locale, &local()?),
+        "locale" => format::date(locale, &local()?),
+        "long" if has_time => time.format("%A %-d %B %Y, %H:%M").to_string(),
+        "long" => time.format("%A %-d %B %Y").to_string(),
+        "rfc2822" => local()?.to_rfc2822(),
+        "unix" => local()?.timestamp().to_string(),
+        pattern => {
+            if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("\"{pattern}\" isn't a date format; use one of {} or a strftime pattern such as %d.%m.%Y", DATE_FORMATS.join(", ")));
+            }
+            time.format(pattern).to_string()
+        }
+    })
+}
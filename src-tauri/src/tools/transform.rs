@@ -0,0 +1,1405 @@
+//! Reshaping data without a model: CSV read into JSON records and written back, and queries
+//! in a subset of jq (paths, `select`, `map`, `group_by`, `add`, object construction and
+//! so on), so a workflow can pick fields out of an API response or total a column exactly.
+
+use std::cmp::Ordering;
+
+use regex::RegexBuilder;
+use serde_json::{json, Map, Number, Value};
+
+use crate::usage::csv_field;
+
+/// Evaluation steps a query may take, so a runaway query fails instead of hanging the run.
+const MAX_STEPS: usize = 5_000_000;
+/// Separators tried when a CSV's delimiter isn't given.
+const DELIMITERS: &[char] = &[',', ';', '\t', '|'];
+
+// ─── CSV ───
+
+/// The rows of a CSV text, following RFC 4180: quoted fields may hold the delimiter, line
+/// breaks and doubled quotes.
+pub fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (c, false) if c == delimiter => row.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n' | '\r', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| !(row.len() == 1 && row[0].trim().is_empty()));
+    rows
+}
+
+/// The delimiter a CSV's first line uses most, outside quotes.
+pub fn guess_delimiter(text: &str) -> char {
+    let mut counts = [0usize; 4];
+    let mut quoted = false;
+    for c in text.chars() {
+        if c == '\n' && !quoted {
+            break;
+        }
+        if c == '"' {
+            quoted = !quoted;
+        } else if let Some(i) = DELIMITERS.iter().position(|d| *d == c).filter(|_| !quoted) {
+            counts[i] += 1;
+        }
+    }
+    let best = (0..DELIMITERS.len()).max_by_key(|i| (counts[*i], std::cmp::Reverse(*i))).unwrap_or(0);
+    DELIMITERS[best]
+}
+
+/// A cell as JSON: empty is null, `true`/`false` are booleans and plain decimal numbers are
+/// numbers. Anything with a leading zero, like a postcode, stays text.
+fn cell_value(cell: &str) -> Value {
+    let trimmed = cell.trim();
+    let digits = trimmed.strip_prefix('-').unwrap_or(trimmed);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    let plain_number = !whole.is_empty() && whole.chars().all(|c| c.is_ascii_digit())
+        && (whole == "0" || !whole.starts_with('0'))
+        && !fraction.is_empty() && fraction.chars().all(|c| c.is_ascii_digit());
+    match trimmed {
+        "" => Value::Null,
+        "true" | "TRUE" | "True" => Value::Bool(true),
+        "false" | "FALSE" | "False" => Value::Bool(false),
+        _ if plain_number => trimmed.parse::<i64>().map(Value::from)
+            .or_else(|_| trimmed.parse::<f64>().map(Value::from))
+            .unwrap_or_else(|_| Value::String(cell.to_string())),
+        _ => Value::String(cell.to_string()),
+    }
+}
+
+/// CSV rows as one object per row, keyed by the header row, with the header in order.
+pub fn csv_records(text: &str, delimiter: Option<char>) -> Result<(Value, Vec<String>), String> {
+    let rows = parse_csv(text, delimiter.unwrap_or_else(|| guess_delimiter(text)));
+    let Some((header, body)) = rows.split_first() else {
+        return Ok((json!([]), Vec::new()));
+    };
+    let mut columns: Vec<String> = Vec::new();
+    for (i, name) in header.iter().enumerate() {
+        let name = match name.trim() {
+            "" => format!("column_{}", i + 1),
+            name => name.to_string(),
+        };
+        // A repeated heading gets a number so neither column is lost.
+        let mut unique = name.clone();
+        let mut n = 2;
+        while columns.contains(&unique) {
+            unique = format!("{name}_{n}");
+            n += 1;
+        }
+        columns.push(unique);
+    }
+    let records = body.iter().map(|row| {
+        let mut record = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            record.insert(column.clone(), row.get(i).map_or(Value::Null, |cell| cell_value(cell)));
+        }
+        for (i, extra) in row.iter().enumerate().skip(columns.len()) {
+            record.insert(format!("column_{}", i + 1), cell_value(extra));
+        }
+        Value::Object(record)
+    }).collect();
+    Ok((Value::Array(records), columns))
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => csv_field(s),
+        Value::Number(_) | Value::Bool(_) => value.to_string(),
+        other => csv_field(&other.to_string()),
+    }
+}
+
+/// `value` as CSV. Records become rows under a header: `columns` when given, otherwise the
+/// columns of `preferred` they have (the input's header order) followed by any others in
+/// the order they appear. Arrays of arrays are written as rows; anything else is one column.
+pub fn to_csv(value: &Value, columns: &[String], preferred: &[String]) -> String {
+    let rows: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(_) => vec![value],
+        _ => return csv_cell(value),
+    };
+    let line = |cells: Vec<String>| cells.join(",");
+    if rows.iter().all(|r| r.is_array()) {
+        return rows.iter()
+            .map(|r| line(r.as_array().into_iter().flatten().map(csv_cell).collect()))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    if !rows.iter().all(|r| r.is_object()) {
+        let mut lines = vec!["value".to_string()];
+        lines.extend(rows.iter().map(|r| csv_cell(r)));
+        return lines.join("\n");
+    }
+    let header: Vec<String> = if columns.is_empty() {
+        let mut header: Vec<String> = preferred.iter()
+            .filter(|c| rows.iter().any(|r| r.get(c.as_str()).is_some()))
+            .cloned()
+            .collect();
+        for row in &rows {
+            for key in row.as_object().into_iter().flat_map(Map::keys) {
+                if !header.contains(key) {
+                    header.push(key.clone());
+                }
+            }
+        }
+        header
+    } else {
+        columns.to_vec()
+    };
+    let mut lines = vec![line(header.iter().map(|h| csv_field(h)).collect())];
+    for row in rows {
+        lines.push(line(header.iter().map(|h| row.get(h.as_str()).map(csv_cell).unwrap_or_default()).collect()));
+    }
+    lines.join("\n")
+}
+
+// ─── Query language ───
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &[
+    "//", "==", "!=", "<=", ">=", "|", ",", "(", ")", "[", "]", "{", "}", ":", ";", "?", "<", ">", "+", "-", "*", "/", "%",
+];
+
+fn string_literal(chars: &[char], i: &mut usize) -> Result<String, String> {
+    let mut out = String::new();
+    *i += 1;
+    loop {
+        let c = *chars.get(*i).ok_or("A string in the query is never closed")?;
+        *i += 1;
+        match c {
+            '"' => return Ok(out),
+            '\\' => {
+                let escaped = *chars.get(*i).ok_or("A string in the query is never closed")?;
+                *i += 1;
+                match escaped {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    'u' => {
+                        let hex: String = chars.get(*i..*i + 4).ok_or("Bad \\u escape in the query")?.iter().collect();
+                        *i += 4;
+                        let code = u32::from_str_radix(&hex, 16).map_err(|_| "Bad \\u escape in the query")?;
+                        out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    '(' => return Err("String interpolation isn't supported in queries".into()),
+                    other => out.push(other),
+                }
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = query.chars().collect();
+    let ident_start = |c: char| c.is_alphabetic() || c == '_';
+    let ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '.' && chars.get(i + 1) == Some(&'.') {
+            tokens.push(Token::DotDot);
+            i += 2;
+        } else if c == '.' && chars.get(i + 1).is_some_and(|c| ident_start(*c)) {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Field(chars[start..i].iter().collect()));
+        } else if c == '.' && !chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+            tokens.push(Token::Dot);
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                i += 1;
+                if i < chars.len() && matches!(chars[i], '+' | '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(text.parse().map_err(|_| format!("\"{text}\" isn't a number"))?));
+        } else if c == '"' {
+            tokens.push(Token::Str(string_literal(&chars, &mut i)?));
+        } else if ident_start(c) || c == '$' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && ident_char(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if word.starts_with('$') {
+                return Err(format!("Variables like {word} aren't supported in queries"));
+            }
+            tokens.push(Token::Ident(word));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let punct = PUNCTUATION.iter().find(|p| rest.starts_with(**p)).ok_or_else(|| format!("Unexpected \"{c}\" in the query"))?;
+            tokens.push(Token::Punct(punct));
+            i += punct.chars().count();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Identity,
+    Recurse,
+    Literal(Value),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Iterate(Box<Expr>),
+    Try(Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(Expr, Expr)>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Alternative(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    If(Vec<(Expr, Expr)>, Option<Box<Expr>>),
+    Call(String, Vec<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(q)) if *q == p)
+    }
+
+    fn is_word(&self, w: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word == w)
+    }
+
+    fn expect(&mut self, p: &str) -> Result<(), String> {
+        if self.is_punct(p) {
+            self.at += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected \"{p}\" in the query{}", self.near()))
+        }
+    }
+
+    fn expect_word(&mut self, w: &str) -> Result<(), String> {
+        if self.is_word(w) {
+            self.at += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected \"{w}\" in the query{}", self.near()))
+        }
+    }
+
+    fn near(&self) -> String {
+        match self.peek() {
+            Some(Token::Punct(p)) => format!(" before \"{p}\""),
+            Some(Token::Ident(w) | Token::Field(w)) => format!(" before \"{w}\""),
+            Some(_) => String::new(),
+            None => " at its end".into(),
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, String> {
+        let mut left = self.comma()?;
+        while self.is_punct("|") {
+            self.at += 1;
+            left = Expr::Pipe(Box::new(left), Box::new(self.comma()?));
+        }
+        Ok(left)
+    }
+
+    fn comma(&mut self) -> Result<Expr, String> {
+        let mut left = self.alternative()?;
+        while self.is_punct(",") {
+            self.at += 1;
+            left = Expr::Comma(Box::new(left), Box::new(self.alternative()?));
+        }
+        Ok(left)
+    }
+
+    fn alternative(&mut self) -> Result<Expr, String> {
+        let left = self.or()?;
+        if self.is_punct("//") {
+            self.at += 1;
+            return Ok(Expr::Alternative(Box::new(left), Box::new(self.alternative()?)));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.is_word("or") {
+            self.at += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison()?;
+        while self.is_word("and") {
+            self.at += 1;
+            left = Expr::And(Box::new(left), Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.additive()?;
+        for op in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.is_punct(op) {
+                self.at += 1;
+                return Ok(Expr::Binary(op, Box::new(left), Box::new(self.additive()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.multiplicative()?;
+        while let Some(op) = ["+", "-"].into_iter().find(|op| self.is_punct(op)) {
+            self.at += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(op) = ["*", "/", "%"].into_iter().find(|op| self.is_punct(op)) {
+            self.at += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.is_punct("-") {
+            self.at += 1;
+            return Ok(Expr::Negate(Box::new(self.postfix()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            match self.peek().cloned() {
+                Some(Token::Field(name)) => {
+                    self.at += 1;
+                    expr = Expr::Index(Box::new(expr), Box::new(Expr::Literal(Value::String(name))));
+                }
+                Some(Token::Dot) if matches!(self.tokens.get(self.at + 1), Some(Token::Str(_))) => {
+                    self.at += 1;
+                    let Some(Token::Str(name)) = self.peek().cloned() else { unreachable!() };
+                    self.at += 1;
+                    expr = Expr::Index(Box::new(expr), Box::new(Expr::Literal(Value::String(name))));
+                }
+                Some(Token::Dot) if matches!(self.tokens.get(self.at + 1), Some(Token::Punct("["))) => {
+                    self.at += 1;
+                }
+                Some(Token::Punct("[")) => {
+                    self.at += 1;
+                    expr = self.bracket(expr)?;
+                }
+                Some(Token::Punct("?")) => {
+                    self.at += 1;
+                    expr = Expr::Try(Box::new(expr));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// What follows `[` after an expression: `]`, an index, or a slice.
+    fn bracket(&mut self, target: Expr) -> Result<Expr, String> {
+        if self.is_punct("]") {
+            self.at += 1;
+            return Ok(Expr::Iterate(Box::new(target)));
+        }
+        let from = if self.is_punct(":") { None } else { Some(Box::new(self.pipe()?)) };
+        if self.is_punct(":") {
+            self.at += 1;
+            let to = if self.is_punct("]") { None } else { Some(Box::new(self.pipe()?)) };
+            self.expect("]")?;
+            return Ok(Expr::Slice(Box::new(target), from, to));
+        }
+        self.expect("]")?;
+        Ok(Expr::Index(Box::new(target), from.ok_or("Empty index in the query")?))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.peek().cloned().ok_or("The query ends too early")?;
+        self.at += 1;
+        Ok(match token {
+            Token::Dot => match self.peek().cloned() {
+                Some(Token::Str(name)) => {
+                    self.at += 1;
+                    Expr::Index(Box::new(Expr::Identity), Box::new(Expr::Literal(Value::String(name))))
+                }
+                _ => Expr::Identity,
+            },
+            Token::DotDot => Expr::Recurse,
+            Token::Field(name) => Expr::Index(Box::new(Expr::Identity), Box::new(Expr::Literal(Value::String(name)))),
+            Token::Num(n) => Expr::Literal(number(n)),
+            Token::Str(s) => Expr::Literal(Value::String(s)),
+            Token::Punct("(") => {
+                let inner = self.pipe()?;
+                self.expect(")")?;
+                inner
+            }
+            Token::Punct("[") => {
+                if self.is_punct("]") {
+                    self.at += 1;
+                    Expr::Array(None)
+                } else {
+                    let inner = self.pipe()?;
+                    self.expect("]")?;
+                    Expr::Array(Some(Box::new(inner)))
+                }
+            }
+            Token::Punct("{") => self.object()?,
+            Token::Ident(word) => match word.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                "if" => self.conditional()?,
+                _ => {
+                    let mut args = Vec::new();
+                    if self.is_punct("(") {
+                        self.at += 1;
+                        args.push(self.pipe()?);
+                        while self.is_punct(";") {
+                            self.at += 1;
+                            args.push(self.pipe()?);
+                        }
+                        self.expect(")")?;
+                    }
+                    Expr::Call(word, args)
+                }
+            },
+            Token::Punct(p) => return Err(format!("Unexpected \"{p}\" in the query")),
+        })
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.pipe()?;
+            self.expect_word("then")?;
+            branches.push((condition, self.pipe()?));
+            if self.is_word("elif") {
+                self.at += 1;
+                continue;
+            }
+            let otherwise = if self.is_word("else") {
+                self.at += 1;
+                Some(Box::new(self.pipe()?))
+            } else {
+                None
+            };
+            self.expect_word("end")?;
+            return Ok(Expr::If(branches, otherwise));
+        }
+    }
+
+    /// `{name: .x, "other name": .y, id, (.key): .value}`
+    fn object(&mut self) -> Result<Expr, String> {
+        let mut entries = Vec::new();
+        while !self.is_punct("}") {
+            let token = self.peek().cloned().ok_or("An object in the query is never closed")?;
+            self.at += 1;
+            let key = match token {
+                Token::Ident(name) | Token::Str(name) => Expr::Literal(Value::String(name)),
+                Token::Punct("(") => {
+                    let key = self.pipe()?;
+                    self.expect(")")?;
+                    key
+                }
+                _ => return Err(format!("Expected a key in an object in the query{}", self.near())),
+            };
+            let value = if self.is_punct(":") {
+                self.at += 1;
+                self.alternative()?
+            } else {
+                match &key {
+                    Expr::Literal(name) => Expr::Index(Box::new(Expr::Identity), Box::new(Expr::Literal(name.clone()))),
+                    _ => return Err("A computed key needs a value".into()),
+                }
+            };
+            entries.push((key, value));
+            if !self.is_punct("}") {
+                self.expect(",")?;
+            }
+        }
+        self.at += 1;
+        Ok(Expr::Object(entries))
+    }
+}
+
+fn parse(query: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(query)?, at: 0 };
+    if parser.tokens.is_empty() {
+        return Ok(Expr::Identity);
+    }
+    let expr = parser.pipe()?;
+    if parser.at < parser.tokens.len() {
+        return Err(format!("Unexpected text in the query{}", parser.near()));
+    }
+    Ok(expr)
+}
+
+// ─── Evaluation ───
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// jq's order: null, false, true, numbers, strings, arrays, then objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64().unwrap_or(0.0).total_cmp(&y.as_f64().unwrap_or(0.0)),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x.iter().zip(y).map(|(a, b)| compare(a, b)).find(|o| o.is_ne()).unwrap_or(x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            let keys = |m: &Map<String, Value>| m.keys().cloned().collect::<Vec<_>>();
+            keys(x).cmp(&keys(y)).then_with(|| {
+                x.values().zip(y.values()).map(|(a, b)| compare(a, b)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// A number, written as an integer when it is one so `2` doesn't come out as `2.0`.
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9e15 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn add(a: &Value, b: &Value) -> Result<Value, String> {
+    Ok(match (a, b) {
+        (Value::Null, other) | (other, Value::Null) => other.clone(),
+        (Value::Number(_), Value::Number(_)) => number(a.as_f64().unwrap_or(0.0) + b.as_f64().unwrap_or(0.0)),
+        (Value::String(x), Value::String(y)) => Value::String(format!("{x}{y}")),
+        (Value::Array(x), Value::Array(y)) => Value::Array(x.iter().chain(y).cloned().collect()),
+        (Value::Object(x), Value::Object(y)) => {
+            let mut merged = x.clone();
+            merged.extend(y.iter().map(|(k, v)| (k.clone(), v.clone())));
+            Value::Object(merged)
+        }
+        _ => return Err(format!("Can't add {} and {}", type_name(a), type_name(b))),
+    })
+}
+
+fn arithmetic(op: &str, a: &Value, b: &Value) -> Result<Value, String> {
+    if op == "+" {
+        return add(a, b);
+    }
+    let cannot = || format!("Can't use {op} on {} and {}", type_name(a), type_name(b));
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => {
+            let (x, y) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+            Ok(number(match op {
+                "-" => x - y,
+                "*" => x * y,
+                "/" if y == 0.0 => return Err("Division by zero in the query".into()),
+                "/" => x / y,
+                "%" if y.trunc() == 0.0 => return Err("Division by zero in the query".into()),
+                _ => (x.trunc() % y.trunc()).trunc(),
+            }))
+        }
+        (Value::Array(x), Value::Array(y)) if op == "-" => Ok(Value::Array(x.iter().filter(|v| !y.contains(v)).cloned().collect())),
+        (Value::String(x), Value::String(y)) if op == "/" => Ok(Value::Array(x.split(y.as_str()).map(|s| json!(s)).collect())),
+        _ => Err(cannot()),
+    }
+}
+
+fn index(target: &Value, key: &Value) -> Result<Value, String> {
+    match (target, key) {
+        (Value::Object(map), Value::String(k)) => Ok(map.get(k).cloned().unwrap_or(Value::Null)),
+        (Value::Array(items), Value::Number(n)) => {
+            let n = n.as_f64().unwrap_or(0.0).floor() as i64;
+            let at = if n < 0 { items.len() as i64 + n } else { n };
+            Ok(usize::try_from(at).ok().and_then(|i| items.get(i)).cloned().unwrap_or(Value::Null))
+        }
+        (Value::Null, Value::String(_) | Value::Number(_)) => Ok(Value::Null),
+        _ => Err(format!("Can't index {} with {}", type_name(target), match key {
+            Value::String(k) => format!("\"{k}\""),
+            other => type_name(other).to_string(),
+        })),
+    }
+}
+
+fn slice(target: &Value, from: Option<&Value>, to: Option<&Value>) -> Result<Value, String> {
+    let bound = |v: Option<&Value>, len: usize, default: usize| -> Result<usize, String> {
+        match v {
+            None | Some(Value::Null) => Ok(default),
+            Some(Value::Number(n)) => {
+                let n = n.as_f64().unwrap_or(0.0).floor() as i64;
+                Ok(if n < 0 { (len as i64 + n).max(0) as usize } else { (n as usize).min(len) })
+            }
+            Some(other) => Err(format!("Can't slice with {}", type_name(other))),
+        }
+    };
+    match target {
+        Value::Array(items) => {
+            let (a, b) = (bound(from, items.len(), 0)?, bound(to, items.len(), items.len())?);
+            Ok(Value::Array(items[a.min(b)..b].to_vec()))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let (a, b) = (bound(from, chars.len(), 0)?, bound(to, chars.len(), chars.len())?);
+            Ok(Value::String(chars[a.min(b)..b].iter().collect()))
+        }
+        Value::Null => Ok(Value::Null),
+        other => Err(format!("Can't slice {}", type_name(other))),
+    }
+}
+
+fn iterate(value: &Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Array(items) => Ok(items.clone()),
+        Value::Object(map) => Ok(map.values().cloned().collect()),
+        other => Err(format!("Can't iterate over {}", type_name(other))),
+    }
+}
+
+fn recurse(value: &Value, out: &mut Vec<Value>) {
+    out.push(value.clone());
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| recurse(v, out)),
+        Value::Object(map) => map.values().for_each(|v| recurse(v, out)),
+        _ => {}
+    }
+}
+
+fn contains(a: &Value, b: &Value) -> Result<bool, String> {
+    Ok(match (a, b) {
+        (Value::String(x), Value::String(y)) => x.contains(y.as_str()),
+        (Value::Array(x), Value::Array(y)) => y.iter().all(|want| x.iter().any(|have| contains(have, want).unwrap_or(false))),
+        (Value::Object(x), Value::Object(y)) => y.iter().all(|(k, want)| x.get(k).is_some_and(|have| contains(have, want).unwrap_or(false))),
+        _ if type_name(a) == type_name(b) => a == b,
+        _ => return Err(format!("Can't check whether {} contains {}", type_name(a), type_name(b))),
+    })
+}
+
+fn array<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, String> {
+    value.as_array().ok_or_else(|| format!("{name} needs an array, not {}", type_name(value)))
+}
+
+fn string<'a>(value: &'a Value, name: &str) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("{name} needs a string, not {}", type_name(value)))
+}
+
+fn flatten(items: &[Value], out: &mut Vec<Value>) {
+    for item in items {
+        match item {
+            Value::Array(inner) => flatten(inner, out),
+            other => out.push(other.clone()),
+        }
+    }
+}
+
+struct Machine {
+    steps: usize,
+}
+
+impl Machine {
+    /// Counts `steps` of work, failing once the query has done too much.
+    fn charge(&mut self, steps: usize) -> Result<(), String> {
+        self.steps += steps;
+        match self.steps > MAX_STEPS {
+            true => Err("The query did too much work; narrow it down".into()),
+            false => Ok(()),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, input: &Value) -> Result<Vec<Value>, String> {
+        self.charge(1)?;
+        Ok(match expr {
+            Expr::Identity => vec![input.clone()],
+            Expr::Recurse => {
+                let mut out = Vec::new();
+                recurse(input, &mut out);
+                self.charge(out.len())?;
+                out
+            }
+            Expr::Literal(value) => vec![value.clone()],
+            Expr::Index(target, key) => {
+                let mut out = Vec::new();
+                for t in self.eval(target, input)? {
+                    for k in self.eval(key, input)? {
+                        out.push(index(&t, &k)?);
+                    }
+                }
+                out
+            }
+            Expr::Slice(target, from, to) => {
+                let bound = |m: &mut Self, e: &Option<Box<Expr>>| -> Result<Option<Value>, String> {
+                    match e {
+                        Some(e) => Ok(m.eval(e, input)?.into_iter().next()),
+                        None => Ok(None),
+                    }
+                };
+                let (from, to) = (bound(self, from)?, bound(self, to)?);
+                self.eval(target, input)?.iter()
+                    .map(|t| slice(t, from.as_ref(), to.as_ref()))
+                    .collect::<Result<_, _>>()?
+            }
+            Expr::Iterate(target) => {
+                let mut out = Vec::new();
+                for t in self.eval(target, input)? {
+                    out.extend(iterate(&t)?);
+                }
+                self.charge(out.len())?;
+                out
+            }
+            Expr::Try(inner) => self.eval(inner, input).unwrap_or_default(),
+            Expr::Array(inner) => match inner {
+                Some(inner) => vec![Value::Array(self.eval(inner, input)?)],
+                None => vec![json!([])],
+            },
+            Expr::Object(entries) => {
+                let mut objects = vec![Map::new()];
+                for (key, value) in entries {
+                    let keys = self.eval(key, input)?;
+                    let values = self.eval(value, input)?;
+                    let mut next = Vec::new();
+                    for object in &objects {
+                        for k in &keys {
+                            let k = string(k, "An object key")?;
+                            for v in &values {
+                                let mut object = object.clone();
+                                object.insert(k.to_string(), v.clone());
+                                next.push(object);
+                            }
+                        }
+                    }
+                    objects = next;
+                }
+                objects.into_iter().map(Value::Object).collect()
+            }
+            Expr::Pipe(left, right) => {
+                let mut out = Vec::new();
+                for value in self.eval(left, input)? {
+                    out.extend(self.eval(right, &value)?);
+                }
+                out
+            }
+            Expr::Comma(left, right) => {
+                let mut out = self.eval(left, input)?;
+                out.extend(self.eval(right, input)?);
+                out
+            }
+            Expr::Alternative(left, right) => {
+                let found: Vec<Value> = self.eval(left, input).unwrap_or_default().into_iter().filter(truthy).collect();
+                if found.is_empty() { self.eval(right, input)? } else { found }
+            }
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let is_and = matches!(expr, Expr::And(..));
+                let mut out = Vec::new();
+                for l in self.eval(left, input)? {
+                    if truthy(&l) != is_and {
+                        out.push(Value::Bool(!is_and));
+                        continue;
+                    }
+                    for r in self.eval(right, input)? {
+                        out.push(Value::Bool(truthy(&r)));
+                    }
+                }
+                out
+            }
+            Expr::Binary(op, left, right) => {
+                let rights = self.eval(right, input)?;
+                let mut out = Vec::new();
+                for l in self.eval(left, input)? {
+                    for r in &rights {
+                        let ordering = compare(&l, r);
+                        out.push(match *op {
+                            "==" => Value::Bool(ordering.is_eq()),
+                            "!=" => Value::Bool(ordering.is_ne()),
+                            "<" => Value::Bool(ordering.is_lt()),
+                            "<=" => Value::Bool(ordering.is_le()),
+                            ">" => Value::Bool(ordering.is_gt()),
+                            ">=" => Value::Bool(ordering.is_ge()),
+                            op => arithmetic(op, &l, r)?,
+                        });
+                    }
+                }
+                out
+            }
+            Expr::Negate(inner) => self.eval(inner, input)?.iter()
+                .map(|v| v.as_f64().map(|n| number(-n)).ok_or_else(|| format!("Can't negate {}", type_name(v))))
+                .collect::<Result<_, _>>()?,
+            Expr::If(branches, otherwise) => {
+                let Some(((condition, then), rest)) = branches.split_first() else {
+                    return Ok(vec![input.clone()]);
+                };
+                let mut out = Vec::new();
+                for c in self.eval(condition, input)? {
+                    if truthy(&c) {
+                        out.extend(self.eval(then, input)?);
+                    } else if rest.is_empty() {
+                        match otherwise {
+                            Some(otherwise) => out.extend(self.eval(otherwise, input)?),
+                            None => out.push(input.clone()),
+                        }
+                    } else {
+                        out.extend(self.eval(&Expr::If(rest.to_vec(), otherwise.clone()), input)?);
+                    }
+                }
+                out
+            }
+            Expr::Call(name, args) => self.call(name, args, input)?,
+        })
+    }
+
+    /// The first output of `expr`, or null when it has none.
+    fn first(&mut self, expr: &Expr, input: &Value) -> Result<Value, String> {
+        Ok(self.eval(expr, input)?.into_iter().next().unwrap_or(Value::Null))
+    }
+
+    /// Each item of `items` with its key under `f`, all of `f`'s outputs as an array.
+    fn keyed(&mut self, items: &[Value], f: &Expr) -> Result<Vec<(Value, Value)>, String> {
+        items.iter().map(|item| Ok((Value::Array(self.eval(f, item)?), item.clone()))).collect()
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], input: &Value) -> Result<Vec<Value>, String> {
+        let one = |v: Value| Ok(vec![v]);
+        match (name, args) {
+            ("empty", []) => Ok(Vec::new()),
+            ("not", []) => one(Value::Bool(!truthy(input))),
+            ("length", []) => one(match input {
+                Value::Null => json!(0),
+                Value::Number(n) => number(n.as_f64().unwrap_or(0.0).abs()),
+                Value::String(s) => json!(s.chars().count()),
+                Value::Array(items) => json!(items.len()),
+                Value::Object(map) => json!(map.len()),
+                Value::Bool(_) => return Err("A boolean has no length".into()),
+            }),
+            ("type", []) => one(json!(type_name(input))),
+            ("numbers" | "strings" | "booleans" | "nulls" | "arrays" | "objects" | "iterables" | "scalars" | "values", []) => {
+                let keep = match name {
+                    "iterables" => input.is_array() || input.is_object(),
+                    "scalars" => !(input.is_array() || input.is_object()),
+                    "values" => !input.is_null(),
+                    plural => plural.strip_suffix('s') == Some(type_name(input)),
+                };
+                Ok(if keep { vec![input.clone()] } else { Vec::new() })
+            }
+            ("keys", []) => one(match input {
+                Value::Object(map) => json!(map.keys().collect::<Vec<_>>()),
+                Value::Array(items) => json!((0..items.len()).collect::<Vec<_>>()),
+                other => return Err(format!("{} has no keys", type_name(other))),
+            }),
+            ("has", [key]) => self.eval(key, input)?.iter().map(|k| Ok(Value::Bool(match (input, k) {
+                (Value::Object(map), Value::String(k)) => map.contains_key(k),
+                (Value::Array(items), Value::Number(n)) => n.as_f64().is_some_and(|n| n >= 0.0 && (n as usize) < items.len()),
+                _ => return Err(format!("Can't check whether {} has a {} key", type_name(input), type_name(k))),
+            }))).collect(),
+            ("add", []) => one(iterate(input)?.iter().try_fold(Value::Null, |sum, v| add(&sum, v))?),
+            ("any", []) => one(Value::Bool(iterate(input)?.iter().any(truthy))),
+            ("all", []) => one(Value::Bool(iterate(input)?.iter().all(truthy))),
+            ("any" | "all", [f]) => {
+                let mut results = Vec::new();
+                for item in iterate(input)? {
+                    results.push(self.eval(f, &item)?.iter().any(truthy));
+                }
+                one(Value::Bool(if name == "any" { results.iter().any(|r| *r) } else { results.iter().all(|r| *r) }))
+            }
+            ("first", []) => one(index(input, &json!(0))?),
+            ("last", []) => one(index(input, &json!(-1))?),
+            ("first", [f]) => Ok(self.eval(f, input)?.into_iter().take(1).collect()),
+            ("last", [f]) => Ok(self.eval(f, input)?.into_iter().last().into_iter().collect()),
+            ("limit", [n, f]) => {
+                let n = self.first(n, input)?.as_f64().ok_or("limit needs a number")?.max(0.0) as usize;
+                Ok(self.eval(f, input)?.into_iter().take(n).collect())
+            }
+            ("range", [to]) => {
+                let to = self.first(to, input)?.as_f64().ok_or("range needs a number")?;
+                self.charge(to.clamp(0.0, MAX_STEPS as f64) as usize)?;
+                Ok((0..to.ceil().max(0.0) as i64).map(Value::from).collect())
+            }
+            ("range", [from, to]) => {
+                let from = self.first(from, input)?.as_f64().ok_or("range needs numbers")?;
+                let to = self.first(to, input)?.as_f64().ok_or("range needs numbers")?;
+                self.charge((to - from).clamp(0.0, MAX_STEPS as f64) as usize)?;
+                Ok((from.ceil() as i64..to.ceil() as i64).map(Value::from).collect())
+            }
+            ("reverse", []) => one(match input {
+                Value::Array(items) => Value::Array(items.iter().rev().cloned().collect()),
+                Value::String(s) => Value::String(s.chars().rev().collect()),
+                Value::Null => json!([]),
+                other => return Err(format!("Can't reverse {}", type_name(other))),
+            }),
+            ("sort", []) => {
+                let mut items = array(input, "sort")?.clone();
+                items.sort_by(compare);
+                one(Value::Array(items))
+            }
+            ("unique", []) => {
+                let mut items = array(input, "unique")?.clone();
+                items.sort_by(compare);
+                items.dedup_by(|a, b| compare(a, b).is_eq());
+                one(Value::Array(items))
+            }
+            ("min" | "max", []) => {
+                let items = array(input, name)?;
+                let pick = if name == "min" { items.iter().min_by(|a, b| compare(a, b)) } else { items.iter().max_by(|a, b| compare(a, b)) };
+                one(pick.cloned().unwrap_or(Value::Null))
+            }
+            ("flatten", []) => {
+                let mut out = Vec::new();
+                flatten(array(input, "flatten")?, &mut out);
+                one(Value::Array(out))
+            }
+            ("map", [f]) => {
+                let mut out = Vec::new();
+                for item in iterate(input)? {
+                    out.extend(self.eval(f, &item)?);
+                }
+                one(Value::Array(out))
+            }
+            ("map_values", [f]) => one(match input {
+                Value::Object(map) => {
+                    let mut out = Map::new();
+                    for (k, v) in map {
+                        if let Some(v) = self.eval(f, v)?.into_iter().next() {
+                            out.insert(k.clone(), v);
+                        }
+                    }
+                    Value::Object(out)
+                }
+                Value::Array(items) => {
+                    let mut out = Vec::new();
+                    for item in items {
+                        out.extend(self.eval(f, item)?.into_iter().take(1));
+                    }
+                    Value::Array(out)
+                }
+                other => return Err(format!("Can't map over {}", type_name(other))),
+            }),
+            ("select", [f]) => Ok(match self.eval(f, input)?.iter().any(truthy) {
+                true => vec![input.clone()],
+                false => Vec::new(),
+            }),
+            ("sort_by" | "group_by" | "unique_by" | "min_by" | "max_by", [f]) => {
+                let mut keyed = self.keyed(array(input, name)?, f)?;
+                keyed.sort_by(|a, b| compare(&a.0, &b.0));
+                one(match name {
+                    "sort_by" => Value::Array(keyed.into_iter().map(|(_, v)| v).collect()),
+                    "min_by" => keyed.into_iter().next().map(|(_, v)| v).unwrap_or(Value::Null),
+                    "max_by" => keyed.into_iter().last().map(|(_, v)| v).unwrap_or(Value::Null),
+                    _ => {
+                        let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+                        for (key, item) in keyed {
+                            match groups.last_mut() {
+                                Some((last, group)) if compare(last, &key).is_eq() => group.push(item),
+                                _ => groups.push((key, vec![item])),
+                            }
+                        }
+                        match name {
+                            "group_by" => Value::Array(groups.into_iter().map(|(_, g)| Value::Array(g)).collect()),
+                            _ => Value::Array(groups.into_iter().filter_map(|(_, g)| g.into_iter().next()).collect()),
+                        }
+                    }
+                })
+            }
+            ("to_entries", []) => one(Value::Array(match input {
+                Value::Object(map) => map.iter().map(|(k, v)| json!({ "key": k, "value": v })).collect(),
+                other => return Err(format!("to_entries needs an object, not {}", type_name(other))),
+            })),
+            ("from_entries", []) => {
+                let mut map = Map::new();
+                for entry in array(input, "from_entries")? {
+                    let key = ["key", "k", "name", "Key", "Name"].iter().find_map(|k| entry.get(*k)).cloned().unwrap_or(Value::Null);
+                    let key = match key {
+                        Value::String(s) => s,
+                        Value::Null => return Err("An entry has no key".into()),
+                        other => other.to_string(),
+                    };
+                    let value = ["value", "v", "Value"].iter().find_map(|k| entry.get(*k)).cloned().unwrap_or(Value::Null);
+                    map.insert(key, value);
+                }
+                one(Value::Object(map))
+            }
+            ("with_entries", [f]) => {
+                let entries = self.call("to_entries", &[], input)?;
+                let mapped = self.call("map", std::slice::from_ref(f), &entries[0])?;
+                self.call("from_entries", &[], &mapped[0])
+            }
+            ("tostring", []) => one(match input {
+                Value::String(_) => input.clone(),
+                other => Value::String(other.to_string()),
+            }),
+            ("tonumber", []) => one(match input {
+                Value::Number(_) => input.clone(),
+                Value::String(s) => s.trim().replace(',', "").parse::<f64>().map(number)
+                    .map_err(|_| format!("\"{s}\" isn't a number"))?,
+                other => return Err(format!("Can't make {} a number", type_name(other))),
+            }),
+            ("tojson", []) => one(Value::String(input.to_string())),
+            ("fromjson", []) => one(serde_json::from_str(string(input, "fromjson")?).map_err(|e| format!("fromjson: {e}"))?),
+            ("ascii_downcase", []) => one(Value::String(string(input, name)?.to_lowercase())),
+            ("ascii_upcase", []) => one(Value::String(string(input, name)?.to_uppercase())),
+            ("floor" | "ceil" | "round" | "fabs" | "sqrt", []) => {
+                let n = input.as_f64().ok_or_else(|| format!("{name} needs a number, not {}", type_name(input)))?;
+                one(number(match name {
+                    "floor" => n.floor(),
+                    "ceil" => n.ceil(),
+                    "round" => n.round(),
+                    "fabs" => n.abs(),
+                    _ => n.sqrt(),
+                }))
+            }
+            ("contains" | "startswith" | "endswith" | "ltrimstr" | "rtrimstr" | "split" | "join" | "test", [arg, rest @ ..]) if rest.len() <= 1 => {
+                let flags = match rest {
+                    [flags] => self.first(flags, input)?.as_str().unwrap_or_default().to_string(),
+                    _ => String::new(),
+                };
+                let mut out = Vec::new();
+                for a in self.eval(arg, input)? {
+                    out.push(match name {
+                        "contains" => Value::Bool(contains(input, &a)?),
+                        "startswith" => Value::Bool(string(input, name)?.starts_with(string(&a, name)?)),
+                        "endswith" => Value::Bool(string(input, name)?.ends_with(string(&a, name)?)),
+                        "ltrimstr" => match (input, &a) {
+                            (Value::String(s), Value::String(p)) => json!(s.strip_prefix(p.as_str()).unwrap_or(s)),
+                            _ => input.clone(),
+                        },
+                        "rtrimstr" => match (input, &a) {
+                            (Value::String(s), Value::String(p)) => json!(s.strip_suffix(p.as_str()).unwrap_or(s)),
+                            _ => input.clone(),
+                        },
+                        "split" => json!(string(input, name)?.split(string(&a, name)?).collect::<Vec<_>>()),
+                        "join" => {
+                            let separator = string(&a, name)?;
+                            let parts: Vec<String> = array(input, name)?.iter().map(|v| match v {
+                                Value::Null => Ok(String::new()),
+                                Value::String(s) => Ok(s.clone()),
+                                Value::Number(_) | Value::Bool(_) => Ok(v.to_string()),
+                                other => Err(format!("Can't join {}", type_name(other))),
+                            }).collect::<Result<_, String>>()?;
+                            json!(parts.join(separator))
+                        }
+                        _ => {
+                            let re = RegexBuilder::new(string(&a, name)?)
+                                .case_insensitive(flags.contains('i'))
+                                .size_limit(1 << 20)
+                                .build()
+                                .map_err(|e| format!("test: the pattern isn't valid: {e}"))?;
+                            Value::Bool(re.is_match(string(input, name)?))
+                        }
+                    });
+                }
+                Ok(out)
+            }
+            (name, args) => Err(format!("Unknown function {name}/{} in the query", args.len())),
+        }
+    }
+}
+
+/// Runs a jq-style `query` on `input`. A query with one result gives that result; one with
+/// several (like `.[] | .name`) gives them as an array.
+pub fn query(input: &Value, query: &str) -> Result<Value, String> {
+    let expr = parse(query)?;
+    let mut results = Machine { steps: 0 }.eval(&expr, input)?;
+    Ok(match results.len() {
+        1 => results.remove(0),
+        _ => Value::Array(results),
+    })
+}
+
+/// The records of `data`: JSON as it is, or CSV as one object per row. The second value is
+/// the CSV header, kept so written CSV has the same column order.
+pub fn read_input(data: &Value, format: &str, delimiter: Option<char>) -> Result<(Value, Vec<String>), String> {
+    let Value::String(text) = data else {
+        return Ok((data.clone(), Vec::new()));
+    };
+    let looks_json = text.trim_start().starts_with(['{', '[']);
+    match format {
+        "json" => serde_json::from_str(text).map(|v| (v, Vec::new())).map_err(|e| format!("The data isn't valid JSON: {e}")),
+        "csv" => csv_records(text, delimiter),
+        "" | "auto" if looks_json => match serde_json::from_str(text) {
+            Ok(value) => Ok((value, Vec::new())),
+            Err(_) => csv_records(text, delimiter),
+        },
+        "" | "auto" => csv_records(text, delimiter),
+        other => Err(format!("Unknown input format \"{other}\"; use json, csv or auto")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: Value, q: &str) -> Value {
+        query(&input, q).unwrap_or_else(|e| panic!("{q}: {e}"))
+    }
+
+    #[test]
+    fn reads_quoted_csv() {
+        let rows = parse_csv("\u{feff}name,note\r\n\"Smith, Jo\",\"said \"\"hi\"\"\nthen left\"\r\n\n", ',');
+        assert_eq!(rows, vec![
+            vec!["name".to_string(), "note".to_string()],
+            vec!["Smith, Jo".to_string(), "said \"hi\"\nthen left".to_string()],
+        ]);
+        assert_eq!(parse_csv("", ','), Vec::<Vec<String>>::new());
+    }
+
+    #[test]
+    fn guesses_the_delimiter_from_the_first_line() {
+        assert_eq!(guess_delimiter("a;b;c\n1,2,3,4,5"), ';');
+        assert_eq!(guess_delimiter("a\tb\n"), '\t');
+        assert_eq!(guess_delimiter("\"x,y,z\"|b\n"), '|');
+        assert_eq!(guess_delimiter("single"), ',');
+    }
+
+    #[test]
+    fn types_cells_and_names_columns() {
+        let (records, columns) = csv_records("id,zip,,id,ok,price\n1,02134,x,2,true,-3.5,extra\n", None).unwrap();
+        assert_eq!(columns, ["id", "zip", "column_3", "id_2", "ok", "price"]);
+        assert_eq!(records, json!([{
+            "id": 1, "zip": "02134", "column_3": "x", "id_2": 2, "ok": true, "price": -3.5, "column_7": "extra",
+        }]));
+        let (records, _) = csv_records("a,b\n1\n", None).unwrap();
+        assert_eq!(records, json!([{ "a": 1, "b": null }]));
+        assert_eq!(csv_records("", None).unwrap(), (json!([]), Vec::new()));
+    }
+
+    #[test]
+    fn writes_csv_in_header_order() {
+        let text = "name,age\nAda,36\n\"Lee, B\",\n";
+        let (records, columns) = csv_records(text, None).unwrap();
+        assert_eq!(to_csv(&records, &[], &columns), "name,age\nAda,36\n\"Lee, B\",");
+        assert_eq!(to_csv(&json!([{ "b": 1 }, { "a": "x\"y" }]), &[], &[]), "b,a\n1,\n,\"x\"\"y\"");
+        assert_eq!(to_csv(&json!([{ "a": 1, "b": 2 }]), &["b".to_string()], &[]), "b\n2");
+        assert_eq!(to_csv(&json!([[1, "a"], [2, null]]), &[], &[]), "1,a\n2,");
+        assert_eq!(to_csv(&json!([1, "x"]), &[], &[]), "value\n1\nx");
+        assert_eq!(to_csv(&json!("plain"), &[], &[]), "plain");
+    }
+
+    #[test]
+    fn reads_input_by_format() {
+        assert_eq!(read_input(&json!({ "a": 1 }), "csv", None).unwrap().0, json!({ "a": 1 }));
+        assert_eq!(read_input(&json!("[1,2]"), "auto", None).unwrap().0, json!([1, 2]));
+        assert_eq!(read_input(&json!("[x],y\n1,2"), "auto", None).unwrap().0, json!([{ "[x]": 1, "y": 2 }]));
+        assert_eq!(read_input(&json!("a|b\n1|2"), "", None).unwrap().1, ["a", "b"]);
+        assert!(read_input(&json!("nope"), "json", None).unwrap_err().contains("isn't valid JSON"));
+        assert!(read_input(&json!("a"), "xml", None).unwrap_err().contains("Unknown input format"));
+    }
+
+    #[test]
+    fn follows_paths() {
+        let data = json!({ "items": [{ "name": "a", "n": 1 }, { "name": "b", "n": 2 }], "odd key": true });
+        assert_eq!(run(data.clone(), ""), data);
+        assert_eq!(run(data.clone(), ".items[0].name"), json!("a"));
+        assert_eq!(run(data.clone(), ".items[-1].n"), json!(2));
+        assert_eq!(run(data.clone(), ".items[].name"), json!(["a", "b"]));
+        assert_eq!(run(data.clone(), ".\"odd key\""), json!(true));
+        assert_eq!(run(data.clone(), ".[\"odd key\"]"), json!(true));
+        assert_eq!(run(data.clone(), ".missing.deeper"), Value::Null);
+        assert_eq!(run(data.clone(), ".items[5]"), Value::Null);
+        assert_eq!(run(data.clone(), ".items[].nope?"), json!([null, null]));
+        assert_eq!(run(json!([1, 2, 3, 4]), ".[1:3]"), json!([2, 3]));
+        assert_eq!(run(json!([1, 2, 3, 4]), ".[-2:]"), json!([3, 4]));
+        assert_eq!(run(json!("héllo"), ".[:2]"), json!("hé"));
+        assert_eq!(run(json!([[1], 2]), "[..] | length"), json!(4));
+        assert_eq!(run(json!([]), ".[]"), json!([]));
+    }
+
+    #[test]
+    fn evaluates_operators() {
+        assert_eq!(run(json!(null), "1 + 2 * 3 - 4 / 2"), json!(5));
+        assert_eq!(run(json!(null), "7 % 3, -(2.5)"), json!([1, -2.5]));
+        assert_eq!(run(json!(null), "\"a\" + \"b\", [1,2] - [2], {a:1} + {b:2}"), json!(["ab", [1], { "a": 1, "b": 2 }]));
+        assert_eq!(run(json!(null), "\"a,b\" / \",\""), json!(["a", "b"]));
+        assert_eq!(run(json!(null), "null + 1"), json!(1));
+        assert_eq!(run(json!({ "a": null }), ".a // \"fallback\""), json!("fallback"));
+        assert_eq!(run(json!(null), "1 < 2 and (null or false | not)"), json!(true));
+        assert_eq!(run(json!(null), "[null, false, 0, \"\", [], {}] | map(if . then 1 else 0 end)"), json!([0, 0, 1, 1, 1, 1]));
+        assert_eq!(run(json!(3), "if . > 5 then \"big\" elif . > 1 then \"mid\" end"), json!("mid"));
+        assert_eq!(run(json!(0), "if . > 5 then \"big\" end"), json!(0));
+        assert_eq!(run(json!(null), "[1, \"a\", null, true, [0]] | sort"), json!([null, true, 1, "a", [0]]));
+    }
+
+    #[test]
+    fn builds_objects_and_arrays() {
+        let data = json!({ "id": 7, "user": { "name": "Ada" }, "tags": ["x", "y"] });
+        assert_eq!(run(data.clone(), "{id, who: .user.name, (.user.name): 1}"), json!({ "id": 7, "who": "Ada", "Ada": 1 }));
+        assert_eq!(run(data.clone(), "{tag: .tags[]}"), json!([{ "tag": "x" }, { "tag": "y" }]));
+        assert_eq!(run(data.clone(), "[.tags[] | ascii_upcase]"), json!(["X", "Y"]));
+        assert_eq!(run(data, "[]"), json!([]));
+    }
+
+    #[test]
+    fn runs_builtins() {
+        let rows = json!([
+            { "team": "b", "score": 3 },
+            { "team": "a", "score": 5 },
+            { "team": "b", "score": 4 },
+        ]);
+        assert_eq!(run(rows.clone(), "map(.score) | add"), json!(12));
+        assert_eq!(run(rows.clone(), "map(select(.score > 3) | .score)"), json!([5, 4]));
+        assert_eq!(run(rows.clone(), "group_by(.team) | map({team: .[0].team, total: (map(.score) | add)})"),
+            json!([{ "team": "a", "total": 5 }, { "team": "b", "total": 7 }]));
+        assert_eq!(run(rows.clone(), "sort_by(.score) | map(.score)"), json!([3, 4, 5]));
+        assert_eq!(run(rows.clone(), "unique_by(.team) | length"), json!(2));
+        assert_eq!(run(rows.clone(), "max_by(.score).team, min_by(.score).score"), json!(["a", 3]));
+        assert_eq!(run(rows, "first.team, last.score, (map(.score) | min, max)"), json!(["b", 4, 3, 5]));
+        assert_eq!(run(json!({ "a": 1, "b": 2 }), "to_entries | map(.key)"), json!(["a", "b"]));
+        assert_eq!(run(json!({ "a": 1 }), "with_entries({key: (.key | ascii_upcase), value: (.value + 1)})"), json!({ "A": 2 }));
+        assert_eq!(run(json!([{ "name": "a", "value": 1 }, { "k": 2, "v": 3 }]), "from_entries"), json!({ "a": 1, "2": 3 }));
+        assert_eq!(run(json!({ "b": 1, "a": 2 }), "keys, has(\"a\"), (map_values(. * 10) | .b)"), json!([["a", "b"], true, 10]));
+        assert_eq!(run(json!([[1, [2]], 3]), "flatten, (flatten | reverse), ([3, 1, 3] | unique)"), json!([[1, 2, 3], [3, 2, 1], [1, 3]]));
+        assert_eq!(run(json!(null), "[range(3)], [range(2; 4)], [limit(2; range(10))], [first(range(5)), last(range(5))]"),
+            json!([[0, 1, 2], [2, 3], [0, 1], [0, 4]]));
+        assert_eq!(run(json!(["x", 1, null]), "[.[] | strings], [.[] | scalars | type], [.[] | values]"), json!([["x"], ["string", "number", "null"], ["x", 1]]));
+        assert_eq!(run(json!("1,234.5"), "tonumber, (tostring | length), (tojson | fromjson)"), json!([1234.5, 7, "1,234.5"]));
+        assert_eq!(run(json!(-2.5), "floor, ceil, round, fabs, length, (16 | sqrt)"), json!([-3, -2, -3, 2.5, 2.5, 4]));
+        assert_eq!(run(json!("Hello World"), "contains(\"lo W\"), startswith(\"He\"), endswith(\"d\"), ltrimstr(\"Hello \"), rtrimstr(\"World\")"),
+            json!([true, true, true, "World", "Hello "]));
+        assert_eq!(run(json!("a-b-c"), "split(\"-\") | join(\"+\")"), json!("a+b+c"));
+        assert_eq!(run(json!("Invoice 42"), "test(\"invoice \\\\d+\"; \"i\"), test(\"^\\\\d\")"), json!([true, false]));
+        assert_eq!(run(json!({ "a": { "b": [1, 2] } }), "contains({a: {b: [2]}}), ([1, 2] | any(. > 1), all(. > 1))"), json!([true, true, false]));
+        assert_eq!(run(json!([1, null]), "map(. // 0) | add, (.[] | empty)"), json!(1));
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        for (q, wanted) in [
+            (".a |", "ends too early"),
+            ("(.a", "Expected \")\""),
+            (".a 1", "Unexpected text"),
+            ("\"open", "never closed"),
+            ("\"\\u12\"", "\\u escape"),
+            ("\"\\(.a)\"", "interpolation"),
+            ("$x", "Variables"),
+            ("@csv", "Unexpected \"@\""),
+            ("{a: 1,", "never closed"),
+            ("{(.a)}", "computed key"),
+            ("{1: 2}", "Expected a key"),
+            ("if . then 1", "Expected \"end\""),
+            (".[]]", "Unexpected text"),
+            ("1.2.3", "isn't a number"),
+            (")", "Unexpected \")\""),
+        ] {
+            let error = query(&json!({}), q).expect_err(q);
+            assert!(error.contains(wanted), "{q}: {error}");
+        }
+    }
+
+    #[test]
+    fn reports_evaluation_errors() {
+        for (input, q, wanted) in [
+            (json!(1), ".a", "Can't index number with \"a\""),
+            (json!({}), ".[0]", "Can't index object with number"),
+            (json!(true), ".[]", "Can't iterate over boolean"),
+            (json!(5), ".[1:]", "Can't slice number"),
+            (json!(null), "1 / 0", "Division by zero"),
+            (json!(null), "5 % 0.5", "Division by zero"),
+            (json!(null), "1 + \"a\"", "Can't add number and string"),
+            (json!(null), "\"a\" * 2", "Can't use * on string and number"),
+            (json!("x"), "-.", "Can't negate string"),
+            (json!(true), "length", "no length"),
+            (json!(1), "sort", "sort needs an array"),
+            (json!("x"), "tonumber", "isn't a number"),
+            (json!(null), "frobnicate(1)", "Unknown function frobnicate/1"),
+            (json!("x"), "test(\"(\")", "pattern isn't valid"),
+            (json!([{ "value": 1 }]), "from_entries", "no key"),
+            (json!({}), "{(1): 2}", "An object key needs a string"),
+        ] {
+            let error = query(&input, q).expect_err(q);
+            assert!(error.contains(wanted), "{q}: {error}");
+        }
+        assert_eq!(query(&json!(1), ".a?").unwrap(), json!([]));
+        assert_eq!(query(&json!(1), ".a // 2").unwrap(), json!(2));
+    }
+
+    #[test]
+    fn stops_runaway_queries() {
+        let error = query(&json!(null), "[range(10000) | [range(1000)]] | length").unwrap_err();
+        assert!(error.contains("too much work"), "{error}");
+        assert!(query(&json!(null), "range(1e12)").unwrap_err().contains("too much work"));
+        assert!(query(&json!(null), "[range(1000)] | length").is_ok());
+    }
+}
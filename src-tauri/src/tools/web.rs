@@ -0,0 +1,52 @@
+use std::io::Read;
+use std::time::Duration;
+
+use crate::net::Proxy;
+
+/// Responses are cut off here so one page can't flood a prompt or the run report.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn read_body(resp: ureq::Response) -> Result<String, String> {
+    let mut body = String::new();
+    resp.into_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(format!("Not a web address: {url}"))
+    }
+}
+
+pub fn http_get(proxy: &Proxy, url: &str) -> Result<String, String> {
+    check_url(url)?;
+    match proxy.agent(url, TIMEOUT)?.get(url).call() {
+        Ok(resp) => read_body(resp),
+        Err(ureq::Error::Status(code, _)) => Err(format!("{url} answered with status {code}")),
+        Err(e) => Err(format!("Couldn't reach {url}: {e}")),
+    }
+}
+
+/// Posts `body` as JSON when it parses as JSON, otherwise as plain text.
+pub fn http_post(proxy: &Proxy, url: &str, body: &str) -> Result<String, String> {
+    check_url(url)?;
+    let content_type = if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    match proxy.agent(url, TIMEOUT)?.post(url).set("Content-Type", content_type).send_string(body) {
+        Ok(resp) => {
+            let status = resp.status();
+            Ok(format!("{status} {}", read_body(resp)?))
+        }
+        Err(ureq::Error::Status(code, _)) => Err(format!("{url} answered with status {code}")),
+        Err(e) => Err(format!("Couldn't reach {url}: {e}")),
+    }
+}
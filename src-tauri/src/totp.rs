@@ -0,0 +1,319 @@
+//! Two-factor codes (TOTP, RFC 6238) for accounts the user adds, so a login they are
+//! supervising isn't stuck at the 2FA prompt. Account details sit in the database; each
+//! secret is kept only in the system keychain, and a code is only given out against the app
+//! lock PIN, every time.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{audit, net, security};
+use crate::DbState;
+
+const ALGORITHMS: &[&str] = &["SHA1", "SHA256", "SHA512"];
+const DIGITS: std::ops::RangeInclusive<u32> = 6..=8;
+const PERIODS: std::ops::RangeInclusive<u32> = 15..=120;
+/// Shorter secrets than this (80 bits) are typos, not real keys.
+const MIN_SECRET_BYTES: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TotpAccount {
+    #[serde(default)]
+    pub id: String,
+    /// What the user calls the account, e.g. `Work email`.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub issuer: String,
+    /// `SHA1` (what nearly every site uses), `SHA256` or `SHA512`.
+    #[serde(default = "default_algorithm")]
+    pub algorithm: String,
+    #[serde(default = "default_digits")]
+    pub digits: u32,
+    /// Seconds each code is valid for.
+    #[serde(default = "default_period")]
+    pub period: u32,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+fn default_algorithm() -> String {
+    "SHA1".into()
+}
+
+fn default_digits() -> u32 {
+    6
+}
+
+fn default_period() -> u32 {
+    30
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TotpCode {
+    pub code: String,
+    /// Seconds until the code stops working.
+    pub expires_in: u64,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS totp_accounts (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            issuer TEXT NOT NULL DEFAULT '',
+            algorithm TEXT NOT NULL DEFAULT 'SHA1',
+            digits INTEGER NOT NULL DEFAULT 6,
+            period INTEGER NOT NULL DEFAULT 30,
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize TOTP tables");
+}
+
+const COLUMNS: &str = "id, name, issuer, algorithm, digits, period, created_at";
+
+fn row_to_account(row: &rusqlite::Row) -> rusqlite::Result<TotpAccount> {
+    Ok(TotpAccount {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        issuer: row.get(2)?,
+        algorithm: row.get(3)?,
+        digits: row.get(4)?,
+        period: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+fn keychain_entry(id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(net::KEYCHAIN_SERVICE, &format!("totp-{id}"))
+        .map_err(|e| format!("The system keychain is unavailable: {e}"))
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<TotpAccount>, String> {
+    let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM totp_accounts ORDER BY name COLLATE NOCASE"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_account).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// The account with this id, or else with this name, ignoring case.
+pub fn find(conn: &Connection, id_or_name: &str) -> Result<TotpAccount, String> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM totp_accounts WHERE id = ?1 OR name = ?1 COLLATE NOCASE ORDER BY id = ?1 DESC LIMIT 1"),
+        params![id_or_name.trim()],
+        row_to_account,
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No two-factor account called \"{id_or_name}\""))
+}
+
+/// RFC 4648 base32, as sites show setup keys: any case, spaces, dashes and padding ignored.
+fn base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u64, 0u32);
+    for c in text.chars().filter(|c| !matches!(c, ' ' | '-' | '=')) {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u64 - 'A' as u64,
+            c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%').then(|| text.get(i + 1..i + 3)).flatten().and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Reads an `otpauth://totp/Issuer:name?secret=…` link from a setup QR code into `account`,
+/// keeping what the user already filled in, and returns its secret.
+fn read_uri(uri: &str, account: &mut TotpAccount) -> Result<String, String> {
+    let url = tauri::Url::parse(uri).map_err(|_| "That isn't a valid otpauth:// link")?;
+    if url.host_str().is_some_and(|kind| !kind.eq_ignore_ascii_case("totp")) {
+        return Err("Only time-based (TOTP) codes are supported, not counter-based ones".into());
+    }
+    let label = percent_decode(url.path().trim_start_matches('/'));
+    let (label_issuer, label_name) = label.split_once(':').map_or(("", label.as_str()), |(i, n)| (i, n));
+    let mut secret = String::new();
+    let mut issuer = label_issuer.trim().to_string();
+    for (key, value) in url.query_pairs() {
+        match key.to_ascii_lowercase().as_str() {
+            "secret" => secret = value.into_owned(),
+            "issuer" => issuer = value.trim().to_string(),
+            "algorithm" => account.algorithm = value.to_uppercase(),
+            "digits" => account.digits = value.parse().map_err(|_| format!("The link's digits ({value}) isn't a number"))?,
+            "period" => account.period = value.parse().map_err(|_| format!("The link's period ({value}) isn't a number"))?,
+            _ => {}
+        }
+    }
+    if account.issuer.trim().is_empty() {
+        account.issuer = issuer;
+    }
+    if account.name.trim().is_empty() {
+        account.name = match (account.issuer.trim(), label_name.trim()) {
+            ("", name) => name.to_string(),
+            (issuer, "") => issuer.to_string(),
+            (issuer, name) => format!("{issuer} ({name})"),
+        };
+    }
+    Ok(secret)
+}
+
+fn validate(account: &TotpAccount, key: &[u8]) -> Result<(), String> {
+    if account.name.trim().is_empty() {
+        return Err("Give the account a name".into());
+    }
+    if !ALGORITHMS.contains(&account.algorithm.as_str()) {
+        return Err(format!("Algorithm must be one of {}", ALGORITHMS.join(", ")));
+    }
+    if !DIGITS.contains(&account.digits) {
+        return Err(format!("Codes can have {} to {} digits", DIGITS.start(), DIGITS.end()));
+    }
+    if !PERIODS.contains(&account.period) {
+        return Err(format!("Codes can last {} to {} seconds", PERIODS.start(), PERIODS.end()));
+    }
+    if key.len() < MIN_SECRET_BYTES {
+        return Err("That setup key is too short; check it was copied in full".into());
+    }
+    Ok(())
+}
+
+/// The code for `key` at Unix time `now`.
+fn code_at(account: &TotpAccount, key: &[u8], now: u64) -> String {
+    let counter = (now / u64::from(account.period)).to_be_bytes();
+    let hash = match account.algorithm.as_str() {
+        "SHA256" => Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length").chain_update(counter).finalize().into_bytes().to_vec(),
+        "SHA512" => Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any length").chain_update(counter).finalize().into_bytes().to_vec(),
+        _ => Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes keys of any length").chain_update(counter).finalize().into_bytes().to_vec(),
+    };
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let value = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+    format!("{:0width$}", value % 10u32.pow(account.digits), width = account.digits as usize)
+}
+
+/// Adds an account from its setup key or `otpauth://` link. The secret goes straight to the
+/// system keychain; the database only learns the account's name and settings.
+pub fn add(conn: &Connection, account: TotpAccount, secret: &str) -> Result<TotpAccount, String> {
+    let mut account = account;
+    let secret = secret.trim();
+    let secret = if secret.to_ascii_lowercase().starts_with("otpauth://") { read_uri(secret, &mut account)? } else { secret.to_string() };
+    let key = base32(&secret).ok_or("The setup key should only have the letters A-Z and digits 2-7")?;
+    account.name = account.name.trim().to_string();
+    account.issuer = account.issuer.trim().to_string();
+    account.algorithm = account.algorithm.trim().to_uppercase();
+    validate(&account, &key)?;
+    let taken: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM totp_accounts WHERE name = ?1 COLLATE NOCASE",
+        params![account.name],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if taken {
+        return Err(format!("There is already a two-factor account called {}", account.name));
+    }
+    account.id = Uuid::new_v4().to_string();
+    account.created_at = Utc::now().to_rfc3339();
+    keychain_entry(&account.id)?.set_password(&secret.replace([' ', '-', '='], "").to_uppercase())
+        .map_err(|e| format!("Couldn't save the setup key to the system keychain: {e}"))?;
+    conn.execute(
+        &format!("INSERT INTO totp_accounts ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"),
+        params![account.id, account.name, account.issuer, account.algorithm, account.digits, account.period, account.created_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(account)
+}
+
+/// The current code for an account, by id or name. Always needs the app lock PIN, so a PIN
+/// must be set at all.
+pub fn code(conn: &Connection, id_or_name: &str, pin: &str) -> Result<TotpCode, String> {
+    if !security::has_pin(conn) {
+        return Err("Set an app lock PIN before using two-factor codes".into());
+    }
+    security::check_pin(conn, pin)?;
+    let account = find(conn, id_or_name)?;
+    let secret = match keychain_entry(&account.id)?.get_password() {
+        Ok(secret) => secret,
+        Err(keyring::Error::NoEntry) => return Err(format!("The setup key for {} is no longer in the system keychain; add the account again", account.name)),
+        Err(e) => return Err(format!("Couldn't read the setup key from the system keychain: {e}")),
+    };
+    let key = base32(&secret).ok_or("The setup key in the system keychain is damaged")?;
+    let now = Utc::now().timestamp().max(0) as u64;
+    audit::record(conn, "totp_code_shown", "", "", &format!("Showed a two-factor code for {}", account.name), &json!({ "account_id": account.id }))?;
+    Ok(TotpCode {
+        code: code_at(&account, &key, now),
+        expires_in: u64::from(account.period) - now % u64::from(account.period),
+    })
+}
+
+fn forget_secret(id: &str) -> Result<(), String> {
+    match keychain_entry(id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Couldn't remove the setup key from the system keychain: {e}")),
+    }
+}
+
+/// Every account's id, read before a factory reset empties the database.
+pub(crate) fn account_ids(conn: &Connection) -> Result<Vec<String>, String> {
+    Ok(list(conn)?.into_iter().map(|account| account.id).collect())
+}
+
+/// Removes the setup keys of `ids` from the system keychain, once a factory reset has
+/// deleted the accounts.
+pub(crate) fn forget_secrets(ids: &[String]) -> Result<(), String> {
+    ids.iter().try_for_each(|id| forget_secret(id))
+}
+
+// ─── TOTP Commands ───
+
+#[tauri::command]
+pub fn list_totp_accounts(db: State<DbState>) -> Result<Vec<TotpAccount>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list(&conn)
+}
+
+/// `secret` is the setup key the site shows, or the `otpauth://` link in its QR code.
+#[tauri::command]
+pub fn add_totp_account(db: State<DbState>, account: TotpAccount, secret: String) -> Result<TotpAccount, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add(&conn, account, &secret)
+}
+
+#[tauri::command]
+pub fn delete_totp_account(db: State<DbState>, id: String) -> Result<(), String> {
+    forget_secret(&id)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM totp_accounts WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_totp_code(db: State<DbState>, account: String, pin: String) -> Result<TotpCode, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    code(&conn, &account, &pin)
+}
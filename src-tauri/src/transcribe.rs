@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use rusqlite::Connection;
+
+use crate::artifacts;
+use crate::context;
+use crate::llm::{self, ProviderConfig};
+use crate::net;
+use crate::policy;
+use crate::tools::ToolContext;
+use crate::{DbState, read_setting};
+
+/// A local Whisper server with the OpenAI-compatible API, e.g. faster-whisper-server.
+const DEFAULT_LOCAL_URL: &str = "http://127.0.0.1:8000/v1";
+const DEFAULT_LOCAL_MODEL: &str = "Systran/faster-whisper-small";
+const LOCAL_URL_SETTING: &str = "transcription_local_url";
+/// The local server runs on this computer, or one only it can reach.
+pub(crate) const LOCAL_ONLY_SETTINGS: &[&str] = &[LOCAL_URL_SETTING];
+/// OpenAI's upload limit; local servers take more but are slow on hour-long files anyway.
+const MAX_BYTES: u64 = 25 * 1024 * 1024;
+/// An hour of audio can take a while on a local model.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(900);
+const FORMATS: &[&str] = &["mp3", "mp4", "mpeg", "mpga", "m4a", "wav", "webm", "ogg", "oga", "flac"];
+
+/// A stretch of speech and when it was said, in seconds from the start.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Transcript {
+    pub path: String,
+    pub provider: String,
+    pub language: String,
+    pub duration_seconds: f64,
+    pub segments: Vec<Segment>,
+    /// The segments as `[mm:ss] text` lines, as stored in the artifact.
+    pub text: String,
+}
+
+/// Where recordings go: OpenAI's Whisper when it is the agent's provider, otherwise the local
+/// Whisper server (`transcription_local_url`), since Anthropic and Ollama can't transcribe.
+/// `transcription_model` overrides the model.
+pub fn config(conn: &Connection, agent_id: &str) -> Result<ProviderConfig, String> {
+    let active = llm::agent_provider_config(conn, agent_id)?;
+    let mut cfg = if active.provider == "openai" {
+        active
+    } else {
+        policy::check_provider("local")?;
+        let base_url = read_setting(conn, LOCAL_URL_SETTING)?.unwrap_or_else(|| DEFAULT_LOCAL_URL.into());
+        ProviderConfig { provider: "local".into(), api_key: String::new(), model: String::new(), base_url, proxy: net::proxy(conn)? }
+    };
+    cfg.model = read_setting(conn, "transcription_model")?
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| if cfg.provider == "openai" { "whisper-1" } else { DEFAULT_LOCAL_MODEL }.into());
+    Ok(cfg)
+}
+
+fn timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    match total / 3600 {
+        0 => format!("{:02}:{:02}", total / 60, total % 60),
+        hours => format!("{hours}:{:02}:{:02}", total / 60 % 60, total % 60),
+    }
+}
+
+fn render(segments: &[Segment]) -> String {
+    segments.iter()
+        .map(|s| format!("[{}] {}", timestamp(s.start), s.text.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The recording as a multipart form, which is how the transcription API takes uploads.
+fn form(path: &Path, bytes: &[u8], model: &str, boundary: &str) -> Vec<u8> {
+    let name = path.file_name().map(|n| n.to_string_lossy().replace('"', "")).unwrap_or_else(|| "audio".into());
+    let mut body = Vec::with_capacity(bytes.len() + 512);
+    body.extend(format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{name}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+    ).as_bytes());
+    body.extend(bytes);
+    for (field, value) in [("model", model), ("response_format", "verbose_json"), ("timestamp_granularities[]", "segment")] {
+        body.extend(format!("\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"{field}\"\r\n\r\n{value}").as_bytes());
+    }
+    body.extend(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+/// Sends a recording to `cfg` and returns its timestamped transcript.
+pub fn transcribe(cfg: &ProviderConfig, path: &Path) -> Result<Transcript, String> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if !FORMATS.contains(&extension.as_str()) {
+        return Err(format!("Can't transcribe .{extension} files; use one of: {}", FORMATS.join(", ")));
+    }
+    let size = std::fs::metadata(path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?.len();
+    if size > MAX_BYTES {
+        return Err(format!(
+            "{} is {} MB; recordings can be at most {} MB. Split it or save it as a compressed format such as MP3.",
+            path.display(), size / 1_048_576, MAX_BYTES / 1_048_576,
+        ));
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+    let boundary = format!("openclaw-{}", uuid::Uuid::new_v4().simple());
+    let url = format!("{}/audio/transcriptions", cfg.base_url.trim_end_matches('/'));
+    let mut request = cfg.proxy.agent(&url, REQUEST_TIMEOUT)?
+        .post(&url)
+        .set("Content-Type", &format!("multipart/form-data; boundary={boundary}"));
+    if !cfg.api_key.is_empty() {
+        request = request.set("Authorization", &format!("Bearer {}", cfg.api_key));
+    }
+    let data: Value = match request.send_bytes(&form(path, &bytes, &cfg.model, &boundary)) {
+        Ok(resp) => resp.into_json().map_err(|e| e.to_string())?,
+        Err(ureq::Error::Status(code, resp)) => {
+            return Err(format!("Transcription API error: {code} {}", resp.into_string().unwrap_or_default()));
+        }
+        Err(_) if cfg.provider == "local" => {
+            return Err(format!("No transcription server is running at {}. Start a local Whisper server or switch to OpenAI.", cfg.base_url));
+        }
+        Err(e) => return Err(format!("Transcription request failed: {e}")),
+    };
+    let mut segments: Vec<Segment> = serde_json::from_value(data["segments"].clone()).unwrap_or_default();
+    if segments.is_empty() {
+        // Servers without segment timestamps still return the whole text.
+        let text = data["text"].as_str().unwrap_or_default().trim().to_string();
+        if !text.is_empty() {
+            segments.push(Segment { start: 0.0, end: data["duration"].as_f64().unwrap_or(0.0), text });
+        }
+    }
+    Ok(Transcript {
+        path: path.display().to_string(),
+        provider: cfg.provider.clone(),
+        language: data["language"].as_str().unwrap_or_default().to_string(),
+        duration_seconds: data["duration"].as_f64().or_else(|| segments.last().map(|s| s.end)).unwrap_or(0.0),
+        text: render(&segments),
+        segments,
+    })
+}
+
+fn artifact_name(transcript: &Transcript) -> String {
+    let file = Path::new(&transcript.path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    format!("Transcript of {file} ({})", timestamp(transcript.duration_seconds))
+}
+
+/// Runs a `transcribe_audio` step. The transcript is kept as a run artifact and returned as
+/// the step's output for later steps to summarize.
+pub fn run_tool(ctx: &ToolContext, path: &Path) -> Result<String, String> {
+    let cfg = {
+        let db = ctx.app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        config(&conn, ctx.agent_id)?
+    };
+    let transcript = transcribe(&cfg, path)?;
+    if transcript.segments.is_empty() {
+        return Ok("The recording has no speech in it".into());
+    }
+    context::record(ctx, "transcript", &artifact_name(&transcript), &transcript.text)?;
+    Ok(transcript.text)
+}
+
+/// Transcribes a recording outside any run, e.g. one dropped onto the app. The transcript is
+/// also kept as an artifact with no run, so it appears with the other artifacts.
+pub fn standalone(app: &AppHandle, path: &Path) -> Result<Transcript, String> {
+    let db = app.state::<DbState>();
+    let cfg = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let cfg = config(&conn, "")?;
+        net::check_egress(&conn, "audio transcription", Some(&cfg.base_url), "", "")?;
+        cfg
+    };
+    let transcript = transcribe(&cfg, path)?;
+    if !transcript.segments.is_empty() {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        artifacts::record(&conn, "", "", 0, "transcript", &artifact_name(&transcript), &transcript.text)?;
+    }
+    Ok(transcript)
+}
+
+// ─── Transcription Commands ───
+
+#[tauri::command]
+pub async fn transcribe_audio(app: AppHandle, path: String) -> Result<Transcript, String> {
+    tauri::async_runtime::spawn_blocking(move || standalone(&app, Path::new(&path)))
+        .await.map_err(|e| e.to_string())?
+}
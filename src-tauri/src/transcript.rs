@@ -0,0 +1,183 @@
+use chrono::DateTime;
+use rusqlite::{Connection, params};
+use serde_json::Value;
+use tauri::State;
+
+use crate::executor::{self, Run, StepResult};
+use crate::pii;
+use crate::sanitize;
+use crate::DbState;
+
+/// Outputs and artifacts longer than this are cut in the transcript.
+const MAX_SECTION_CHARS: usize = 4000;
+/// Param names whose values never leave the app, in any casing, e.g. `api_key`, `Authorization`.
+const SECRET_KEYS: &[&str] = &["password", "passphrase", "secret", "token", "api_key", "apikey", "authorization", "cookie"];
+
+/// A transcript section before it's rendered: a heading, `label: value` facts and text blocks.
+struct Section {
+    heading: String,
+    facts: Vec<(&'static str, String)>,
+    blocks: Vec<(&'static str, String)>,
+}
+
+/// Hides values of secret-looking keys and masks personal data in everything else.
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| {
+            let key = k.to_ascii_lowercase();
+            let v = if SECRET_KEYS.iter().any(|s| key.contains(s)) { Value::String("[redacted]".into()) } else { redact(v) };
+            (k.clone(), v)
+        }).collect()),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => pii::mask_value(other),
+    }
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_SECTION_CHARS) {
+        Some((cut, _)) => format!("{}\n… ({} more characters)", &text[..cut], text[cut..].chars().count()),
+        None => text.to_string(),
+    }
+}
+
+fn duration(ms: i64) -> String {
+    match ms {
+        ms if ms < 1000 => format!("{ms} ms"),
+        ms if ms < 60_000 => format!("{:.1} s", ms as f64 / 1000.0),
+        ms => format!("{}m {}s", ms / 60_000, ms % 60_000 / 1000),
+    }
+}
+
+fn run_duration(run: &Run) -> Option<i64> {
+    let start = DateTime::parse_from_rfc3339(&run.started_at).ok()?;
+    let end = DateTime::parse_from_rfc3339(&run.finished_at).ok()?;
+    Some((end - start).num_milliseconds())
+}
+
+fn step_section(step: &StepResult) -> Section {
+    let title = if step.label.is_empty() { step.tool.clone() } else { format!("{} ({})", step.label, step.tool) };
+    let mut facts = vec![("Status", step.status.clone()), ("Took", duration(step.duration_ms))];
+    if !step.served_by.is_empty() {
+        facts.push(("Model", step.served_by.join(", ")));
+    }
+    if let Some(reason) = &step.injection {
+        facts.push(("Warning", reason.clone()));
+    }
+    let mut blocks = Vec::new();
+    if !step.params.is_null() && step.params != Value::Object(Default::default()) {
+        blocks.push(("Input", serde_json::to_string_pretty(&redact(&step.params)).unwrap_or_default()));
+    }
+    if !step.output.is_empty() {
+        blocks.push(("Output", truncate(&step.output)));
+    }
+    if !step.error.is_empty() {
+        blocks.push(("Error", step.error.clone()));
+    }
+    Section { heading: format!("Step {}: {title}", step.index + 1), facts, blocks }
+}
+
+fn sections(conn: &Connection, run: &Run) -> Result<(String, Vec<Section>), String> {
+    let agent: String = match run.agent_id.as_str() {
+        executor::QUICK_TASK_AGENT => "Quick task".into(),
+        id => conn.query_row("SELECT name FROM agents WHERE id = ?1", params![id], |row| row.get(0))
+            .unwrap_or_else(|_| "Deleted agent".into()),
+    };
+    let mut summary = vec![
+        ("Agent", agent.clone()),
+        ("Mode", run.mode.as_str().to_string()),
+        ("Status", run.status.clone()),
+        ("Started by", run.trigger.clone()),
+        ("Started", run.started_at.clone()),
+    ];
+    if !run.finished_at.is_empty() {
+        summary.push(("Finished", run.finished_at.clone()));
+    }
+    if let Some(ms) = run_duration(run) {
+        summary.push(("Took", duration(ms)));
+    }
+    let mut blocks = Vec::new();
+    if !run.note.is_empty() {
+        blocks.push(("Note", run.note.clone()));
+    }
+    if !run.error.is_empty() {
+        blocks.push(("Error", run.error.clone()));
+    }
+    if !run.side_effects.is_empty() {
+        blocks.push(("Changes made", run.side_effects.iter().map(|s| format!("- {s}")).collect::<Vec<_>>().join("\n")));
+    }
+    let mut all = vec![Section { heading: "Summary".into(), facts: summary, blocks }];
+    all.extend(run.steps.iter().map(step_section));
+
+    let mut stmt = conn.prepare(
+        "SELECT step_index, kind, name, content FROM run_artifacts WHERE run_id = ?1 ORDER BY step_index, created_at"
+    ).map_err(|e| e.to_string())?;
+    let artifacts = stmt.query_map(params![run.id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+    }).map_err(|e| e.to_string())?;
+    for artifact in artifacts {
+        let (step, kind, name, content) = artifact.map_err(|e| e.to_string())?;
+        all.push(Section {
+            heading: format!("Artifact: {name}"),
+            facts: vec![("Kind", kind.replace('_', " ")), ("From step", (step + 1).to_string())],
+            blocks: vec![("Content", truncate(&content))],
+        });
+    }
+    Ok((format!("Run of {agent}"), all))
+}
+
+fn markdown(title: &str, sections: &[Section]) -> String {
+    let mut out = format!("# {title}\n");
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n", section.heading));
+        for (label, value) in &section.facts {
+            out.push_str(&format!("- **{label}:** {value}\n"));
+        }
+        for (label, text) in &section.blocks {
+            // A fence longer than any backtick run in the text keeps it from closing early.
+            let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest.max(2) + 1);
+            out.push_str(&format!("\n**{label}**\n\n{fence}\n{text}\n{fence}\n"));
+        }
+    }
+    out
+}
+
+fn html(title: &str, sections: &[Section]) -> String {
+    let e = sanitize::escape;
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title>\n<style>\
+         body{{font-family:system-ui,sans-serif;max-width:52rem;margin:2rem auto;padding:0 1rem;color:#222}}\
+         pre{{background:#f5f5f5;padding:.75rem;overflow-x:auto;white-space:pre-wrap}}\
+         h2{{border-bottom:1px solid #ddd;padding-bottom:.25rem}}</style></head><body>\n<h1>{0}</h1>\n",
+        e(title),
+    );
+    for section in sections {
+        out.push_str(&format!("<h2>{}</h2>\n<ul>\n", e(&section.heading)));
+        for (label, value) in &section.facts {
+            out.push_str(&format!("<li><strong>{label}:</strong> {}</li>\n", e(value)));
+        }
+        out.push_str("</ul>\n");
+        for (label, text) in &section.blocks {
+            out.push_str(&format!("<h3>{label}</h3>\n<pre>{}</pre>\n", e(text)));
+        }
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+// ─── Transcript Commands ───
+
+/// A readable document of one run, as `markdown` (default) or `html`, to send to a colleague
+/// or support: the summary, each step's redacted input, output and timing, and the artifacts.
+/// Secret-looking params are hidden and personal data in inputs is masked.
+#[tauri::command]
+pub fn export_run_transcript(db: State<DbState>, run_id: String, format: Option<String>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let run = executor::load_run(&conn, &run_id)?;
+    let (title, sections) = sections(&conn, &run)?;
+    match format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => Ok(markdown(&title, &sections)),
+        "html" => Ok(html(&title, &sections)),
+        other => Err(format!("Unknown transcript format: {other}")),
+    }
+}
@@ -0,0 +1,239 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use tauri::State;
+use chrono::Utc;
+
+use crate::{Agent, AGENT_COLUMNS, DbState, insert_agent, row_to_agent};
+
+const EXPORT_FORMAT: &str = "openclaw-agents";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AgentExport {
+    format: String,
+    version: u32,
+    agents: Vec<Agent>,
+}
+
+/// `Add` imports every agent as new; `Merge` matches incoming agents to existing ones.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    #[default]
+    Add,
+    Merge,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    KeepMine,
+    TakeTheirs,
+    Duplicate,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FieldDiff {
+    pub field: String,
+    pub mine: String,
+    pub theirs: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportItem {
+    pub incoming_id: String,
+    pub name: String,
+    /// The local agent it matched, if any.
+    pub existing_id: Option<String>,
+    /// `id`, `name` or empty when nothing matched.
+    pub matched_by: String,
+    /// `new`, `unchanged` or `conflict`.
+    pub status: String,
+    pub diff: Vec<FieldDiff>,
+    pub resolution: Option<Resolution>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportResult {
+    /// False when nothing was written: a dry run, or conflicts still need a resolution.
+    pub applied: bool,
+    pub items: Vec<ImportItem>,
+    /// Ids of agents created or overwritten.
+    pub imported: Vec<String>,
+}
+
+fn config_of(config_json: &str) -> Map<String, Value> {
+    match serde_json::from_str(config_json) {
+        Ok(Value::Object(config)) => config,
+        _ => Map::new(),
+    }
+}
+
+fn shown(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+/// Every difference `TakeTheirs` would apply: the agent's columns, then each key of its
+/// config (the workflow, sensitivity label, PII policy, persona and so on) by name.
+fn diff_agents(mine: &Agent, theirs: &Agent) -> Vec<FieldDiff> {
+    let fields = [
+        ("name", mine.name.clone(), theirs.name.clone()),
+        ("role", mine.role.clone(), theirs.role.clone()),
+        ("goal", mine.goal.clone(), theirs.goal.clone()),
+        ("tools", mine.tools.clone(), theirs.tools.clone()),
+        ("schedule", mine.schedule.clone(), theirs.schedule.clone()),
+        ("sandbox", mine.sandbox.to_string(), theirs.sandbox.to_string()),
+    ];
+    let (my_config, their_config) = (config_of(&mine.config_json), config_of(&theirs.config_json));
+    let keys: BTreeSet<&String> = my_config.keys().chain(their_config.keys()).collect();
+    let config = keys.into_iter()
+        .filter(|key| my_config.get(*key) != their_config.get(*key))
+        .map(|key| (key.clone(), shown(my_config.get(key)), shown(their_config.get(key))));
+    fields.into_iter()
+        .map(|(field, mine, theirs)| (field.to_string(), mine, theirs))
+        .filter(|(_, a, b)| a != b)
+        .chain(config)
+        .map(|(field, mine, theirs)| FieldDiff { field, mine, theirs })
+        .collect()
+}
+
+fn find_match(conn: &Connection, incoming: &Agent) -> Result<Option<(Agent, &'static str)>, String> {
+    let by_id = conn.query_row(
+        &format!("SELECT {AGENT_COLUMNS} FROM agents WHERE id = ?1"),
+        params![incoming.id],
+        row_to_agent,
+    ).optional().map_err(|e| e.to_string())?;
+    if let Some(agent) = by_id {
+        return Ok(Some((agent, "id")));
+    }
+    let by_name = conn.query_row(
+        &format!("SELECT {AGENT_COLUMNS} FROM agents WHERE lower(name) = lower(?1) ORDER BY created_at LIMIT 1"),
+        params![incoming.name],
+        row_to_agent,
+    ).optional().map_err(|e| e.to_string())?;
+    Ok(by_name.map(|agent| (agent, "name")))
+}
+
+/// Inserts `agent` under a fresh id, keeping its workflow and other config.
+fn insert_copy(conn: &Connection, agent: &Agent, name: String) -> Result<String, String> {
+    let created = insert_agent(
+        conn,
+        name,
+        agent.role.clone(),
+        agent.goal.clone(),
+        agent.tools.clone(),
+        agent.schedule.clone(),
+        agent.sandbox,
+    )?;
+    if serde_json::from_str::<Value>(&agent.config_json).is_ok_and(|c| c.is_object()) {
+        conn.execute("UPDATE agents SET config_json = ?1 WHERE id = ?2", params![agent.config_json, created.id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(created.id)
+}
+
+// ─── Import / Export Commands ───
+
+/// Serializes the given agents (all when `ids` is empty) for `import_agents`.
+#[tauri::command]
+pub fn export_agents(db: State<DbState>, ids: Option<Vec<String>>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!("SELECT {AGENT_COLUMNS} FROM agents ORDER BY created_at"))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_agent).map_err(|e| e.to_string())?;
+    let ids = ids.unwrap_or_default();
+
+    let mut agents = Vec::new();
+    for row in rows {
+        let agent = row.map_err(|e| e.to_string())?;
+        if ids.is_empty() || ids.contains(&agent.id) {
+            agents.push(agent);
+        }
+    }
+    serde_json::to_string_pretty(&AgentExport { format: EXPORT_FORMAT.into(), version: 1, agents })
+        .map_err(|e| e.to_string())
+}
+
+/// Imports agents exported by `export_agents`.
+///
+/// In merge mode each incoming agent is matched by id, then by name. Agents that differ from
+/// their match need an entry in `resolutions` (keyed by incoming id); until every conflict has
+/// one, or when `dry_run` is set, nothing is written and the computed diffs are returned.
+/// Everything is applied in a single transaction.
+#[tauri::command]
+pub fn import_agents(
+    db: State<DbState>,
+    data: String,
+    mode: Option<ImportMode>,
+    resolutions: Option<HashMap<String, Resolution>>,
+    dry_run: Option<bool>,
+) -> Result<ImportResult, String> {
+    let export: AgentExport = serde_json::from_str(&data).map_err(|_| "This isn't an agent export file")?;
+    if export.format != EXPORT_FORMAT || export.version != 1 {
+        return Err("This export was made by a newer version of the app".into());
+    }
+    let mode = mode.unwrap_or_default();
+    let resolutions = resolutions.unwrap_or_default();
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut plan = Vec::new();
+    for incoming in &export.agents {
+        let matched = match mode {
+            ImportMode::Merge => find_match(&conn, incoming)?,
+            ImportMode::Add => None,
+        };
+        let (existing_id, matched_by, diff) = match &matched {
+            Some((existing, by)) => (Some(existing.id.clone()), by.to_string(), diff_agents(existing, incoming)),
+            None => (None, String::new(), Vec::new()),
+        };
+        let status = match (&existing_id, diff.is_empty()) {
+            (None, _) => "new",
+            (Some(_), true) => "unchanged",
+            (Some(_), false) => "conflict",
+        };
+        plan.push(ImportItem {
+            incoming_id: incoming.id.clone(),
+            name: incoming.name.clone(),
+            existing_id,
+            matched_by,
+            status: status.into(),
+            diff,
+            resolution: resolutions.get(&incoming.id).copied(),
+        });
+    }
+
+    let unresolved = plan.iter().any(|item| item.status == "conflict" && item.resolution.is_none());
+    if unresolved || dry_run.unwrap_or(false) {
+        return Ok(ImportResult { applied: false, items: plan, imported: Vec::new() });
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut imported = Vec::new();
+    for (item, incoming) in plan.iter().zip(&export.agents) {
+        match (item.status.as_str(), item.resolution) {
+            ("new", _) => imported.push(insert_copy(&tx, incoming, incoming.name.clone())?),
+            ("conflict", Some(Resolution::TakeTheirs)) => {
+                let id = item.existing_id.clone().unwrap_or_default();
+                tx.execute(
+                    "UPDATE agents SET name = ?1, role = ?2, goal = ?3, tools = ?4, schedule = ?5, config_json = ?6, sandbox = ?7, updated_at = ?8 WHERE id = ?9",
+                    params![
+                        incoming.name, incoming.role, incoming.goal, incoming.tools, incoming.schedule,
+                        incoming.config_json, incoming.sandbox as i32, Utc::now().to_rfc3339(), id
+                    ],
+                ).map_err(|e| e.to_string())?;
+                imported.push(id);
+            }
+            ("conflict", Some(Resolution::Duplicate)) => {
+                imported.push(insert_copy(&tx, incoming, format!("{} (imported)", incoming.name))?);
+            }
+            _ => {}
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(ImportResult { applied: true, items: plan, imported })
+}
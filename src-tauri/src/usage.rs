@@ -0,0 +1,502 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audit;
+use crate::executor;
+use crate::llm;
+use crate::power;
+use crate::tokens;
+use crate::workflows::WorkflowStep;
+use crate::{DbState, read_setting};
+
+/// JSON object of `{"model-prefix": [input, output]}` in USD per million tokens, for models
+/// missing from the built-in table or priced differently by a gateway.
+const PRICE_OVERRIDES_SETTING: &str = "llm_price_overrides";
+/// Recent finished runs an estimate is based on.
+const HISTORY_RUNS: i64 = 20;
+/// Planning assumptions for agents that haven't run yet.
+const PROMPT_OVERHEAD_TOKENS: usize = 300;
+const REPLY_TOKENS: usize = 500;
+const TOOL_CALLING_CALLS: usize = 4;
+const DEFAULT_CALL_MS: i64 = 5_000;
+const DEFAULT_TOOL_MS: i64 = 1_000;
+/// Share of a monthly budget at which an alert goes out, once per month each.
+const ALERT_THRESHOLDS: &[i64] = &[50, 80, 100];
+const BUDGET_CHECK_EVERY: Duration = Duration::from_secs(60);
+
+/// USD per million tokens, most specific prefix first.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-4", 30.00, 60.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o3-mini", 1.10, 4.40),
+    ("o4-mini", 1.10, 4.40),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-haiku-4", 1.00, 5.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-3-opus", 15.00, 75.00),
+    ("claude-opus-4", 15.00, 75.00),
+];
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct Price {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl Price {
+    pub fn cost(&self, input_tokens: i64, output_tokens: i64) -> f64 {
+        (input_tokens as f64 * self.input_per_million + output_tokens as f64 * self.output_per_million) / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RunEstimate {
+    pub provider: String,
+    pub model: String,
+    /// `None` when the model's price is unknown.
+    pub cost_usd: Option<f64>,
+    pub duration_ms: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub model_calls: usize,
+    /// `history` when based on the agent's recent runs, `plan` when worked out from its steps.
+    pub basis: &'static str,
+    pub sample_runs: usize,
+    /// Ready to show, e.g. "~$0.12, ~40s".
+    pub summary: String,
+}
+
+/// Spend with one provider over a month.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ProviderSpend {
+    pub provider: String,
+    pub cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub calls: i64,
+    /// Calls to models with no known price, left out of `cost_usd`.
+    pub unpriced_calls: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Budget {
+    pub provider: String,
+    pub monthly_limit_usd: f64,
+    pub spent_usd: f64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BudgetAlert {
+    pub provider: String,
+    /// The threshold crossed: 50, 80 or 100.
+    pub threshold: i64,
+    pub spent_usd: f64,
+    pub monthly_limit_usd: f64,
+    pub month: String,
+    pub title: String,
+    pub message: String,
+}
+
+/// One line of the usage report: a day's calls to one model for one agent.
+#[derive(Debug, Serialize, Clone)]
+pub struct UsageLine {
+    pub date: String,
+    pub provider: String,
+    pub model: String,
+    pub agent: String,
+    pub calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// `None` when the model's price is unknown.
+    pub cost_usd: Option<f64>,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS usage_budgets (
+            provider TEXT PRIMARY KEY,
+            monthly_limit_usd REAL NOT NULL,
+            alerted_month TEXT DEFAULT '',
+            alerted_threshold INTEGER DEFAULT 0
+        );
+    ").expect("Failed to initialize usage tables");
+}
+
+/// What `model` costs; local models are free. `None` for an unknown cloud model.
+pub fn price(conn: &Connection, provider: &str, model: &str) -> Option<Price> {
+    if provider == "local" {
+        return Some(Price { input_per_million: 0.0, output_per_million: 0.0 });
+    }
+    let overrides = read_setting(conn, PRICE_OVERRIDES_SETTING).ok().flatten()
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+    let overridden = overrides.as_ref().and_then(Value::as_object).and_then(|map| {
+        map.iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .and_then(|(_, v)| Some(Price { input_per_million: v.get(0)?.as_f64()?, output_per_million: v.get(1)?.as_f64()? }))
+    });
+    overridden.or_else(|| {
+        PRICES.iter()
+            .find(|(prefix, _, _)| model.starts_with(prefix))
+            .map(|&(_, input, output)| Price { input_per_million: input, output_per_million: output })
+    })
+}
+
+fn format_summary(cost: Option<f64>, duration_ms: i64) -> String {
+    let cost = match cost {
+        Some(0.0) => "free".to_string(),
+        Some(c) if c < 0.01 => "<$0.01".to_string(),
+        Some(c) => format!("~${c:.2}"),
+        None => "cost unknown".to_string(),
+    };
+    let secs = (duration_ms as f64 / 1000.0).round() as i64;
+    let duration = if secs < 60 { format!("~{}s", secs.max(1)) } else { format!("~{}m", (secs + 30) / 60) };
+    format!("{cost}, {duration}")
+}
+
+struct History {
+    runs: usize,
+    input_tokens: i64,
+    output_tokens: i64,
+    model_calls: usize,
+    duration_ms: i64,
+}
+
+/// Averages over the agent's recent finished runs; `None` if it has none.
+fn history(conn: &Connection, agent_id: &str) -> Result<Option<History>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT r.started_at, r.finished_at,
+                COALESCE(SUM(u.input_tokens), 0), COALESCE(SUM(u.output_tokens), 0), COUNT(u.id)
+         FROM runs r LEFT JOIN llm_usage u ON u.run_id = r.id
+         WHERE r.agent_id = ?1 AND r.status IN ('success', 'error') AND r.finished_at != ''
+         GROUP BY r.id ORDER BY r.started_at DESC LIMIT ?2"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![agent_id, HISTORY_RUNS], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?, row.get::<_, i64>(4)?))
+    }).map_err(|e| e.to_string())?;
+
+    let mut total = History { runs: 0, input_tokens: 0, output_tokens: 0, model_calls: 0, duration_ms: 0 };
+    for row in rows {
+        let (started, finished, input, output, calls) = row.map_err(|e| e.to_string())?;
+        let (Ok(started), Ok(finished)) = (DateTime::parse_from_rfc3339(&started), DateTime::parse_from_rfc3339(&finished)) else {
+            continue;
+        };
+        total.runs += 1;
+        total.input_tokens += input;
+        total.output_tokens += output;
+        total.model_calls += calls as usize;
+        let (started, finished) = (started.with_timezone(&Utc), finished.with_timezone(&Utc));
+        let asleep = power::asleep_ms(conn, started, finished)?;
+        total.duration_ms += ((finished - started).num_milliseconds() - asleep).max(0);
+    }
+    if total.runs == 0 {
+        return Ok(None);
+    }
+    let n = total.runs as i64;
+    Ok(Some(History {
+        runs: total.runs,
+        input_tokens: total.input_tokens / n,
+        output_tokens: total.output_tokens / n,
+        model_calls: total.model_calls.div_ceil(total.runs),
+        duration_ms: total.duration_ms / n,
+    }))
+}
+
+/// Average time one call to `model` has taken here.
+fn call_ms(conn: &Connection, model: &str) -> i64 {
+    conn.query_row(
+        "SELECT CAST(AVG(duration_ms) AS INTEGER) FROM llm_usage WHERE model = ?1",
+        params![model],
+        |row| row.get::<_, Option<i64>>(0),
+    ).ok().flatten().unwrap_or(DEFAULT_CALL_MS)
+}
+
+/// Checks `YYYY-MM`.
+fn parse_month(month: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d")
+        .map(|d| d.format("%Y-%m").to_string())
+        .map_err(|_| format!("Invalid month '{month}', expected YYYY-MM"))
+}
+
+fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// Usage in `month` by day, provider, model and agent, priced with today's prices.
+fn usage_lines(conn: &Connection, month: &str) -> Result<Vec<UsageLine>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT substr(u.created_at, 1, 10), u.provider, u.model, COALESCE(a.name, ''),
+                COUNT(*), SUM(u.input_tokens), SUM(u.output_tokens)
+         FROM llm_usage u LEFT JOIN agents a ON a.id = u.agent_id
+         WHERE substr(u.created_at, 1, 7) = ?1
+         GROUP BY 1, 2, 3, 4 ORDER BY 1, 2, 3, 4"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![month], |row| {
+        Ok(UsageLine {
+            date: row.get(0)?,
+            provider: row.get(1)?,
+            model: row.get(2)?,
+            agent: row.get(3)?,
+            calls: row.get(4)?,
+            input_tokens: row.get(5)?,
+            output_tokens: row.get(6)?,
+            cost_usd: None,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut lines = Vec::new();
+    for row in rows {
+        let mut line = row.map_err(|e| e.to_string())?;
+        line.cost_usd = price(conn, &line.provider, &line.model).map(|p| p.cost(line.input_tokens, line.output_tokens));
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn provider_spend(conn: &Connection, month: &str) -> Result<Vec<ProviderSpend>, String> {
+    let mut by_provider: BTreeMap<String, ProviderSpend> = BTreeMap::new();
+    for line in usage_lines(conn, month)? {
+        let spend = by_provider.entry(line.provider.clone()).or_insert_with(|| ProviderSpend {
+            provider: line.provider.clone(),
+            ..Default::default()
+        });
+        spend.input_tokens += line.input_tokens;
+        spend.output_tokens += line.output_tokens;
+        spend.calls += line.calls;
+        match line.cost_usd {
+            Some(cost) => spend.cost_usd += cost,
+            None => spend.unpriced_calls += line.calls,
+        }
+    }
+    Ok(by_provider.into_values().collect())
+}
+
+fn budgets(conn: &Connection) -> Result<Vec<Budget>, String> {
+    let spend = provider_spend(conn, &current_month())?;
+    let mut stmt = conn.prepare("SELECT provider, monthly_limit_usd FROM usage_budgets ORDER BY provider")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))
+        .map_err(|e| e.to_string())?;
+
+    let mut budgets = Vec::new();
+    for row in rows {
+        let (provider, limit) = row.map_err(|e| e.to_string())?;
+        let spent = spend.iter().find(|s| s.provider == provider).map(|s| s.cost_usd).unwrap_or(0.0);
+        let percent = if limit > 0.0 { spent / limit * 100.0 } else { 100.0 };
+        budgets.push(Budget { provider, monthly_limit_usd: limit, spent_usd: spent, percent });
+    }
+    Ok(budgets)
+}
+
+/// Budgets that crossed a threshold not yet alerted this month. Each is marked as alerted
+/// and recorded as a `budget_alert` audit event.
+fn check_budgets(conn: &Connection) -> Result<Vec<BudgetAlert>, String> {
+    let month = current_month();
+    let mut alerts = Vec::new();
+    for budget in budgets(conn)? {
+        let Some(&threshold) = ALERT_THRESHOLDS.iter().rev().find(|&&t| budget.percent >= t as f64) else { continue };
+        let alerted: i64 = conn.query_row(
+            "SELECT CASE WHEN alerted_month = ?1 THEN alerted_threshold ELSE 0 END FROM usage_budgets WHERE provider = ?2",
+            params![month, budget.provider],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+        if threshold <= alerted {
+            continue;
+        }
+        conn.execute(
+            "UPDATE usage_budgets SET alerted_month = ?1, alerted_threshold = ?2 WHERE provider = ?3",
+            params![month, threshold, budget.provider],
+        ).map_err(|e| e.to_string())?;
+        let title = match threshold {
+            100 => format!("{} budget used up", budget.provider),
+            _ => format!("{threshold}% of the {} budget used", budget.provider),
+        };
+        let message = format!(
+            "${:.2} of ${:.2} spent in {month}.",
+            budget.spent_usd, budget.monthly_limit_usd,
+        );
+        audit::record(conn, "budget_alert", "", "", &format!("{title}: {message}"), &json!({
+            "provider": budget.provider,
+            "threshold": threshold,
+            "spent_usd": budget.spent_usd,
+            "monthly_limit_usd": budget.monthly_limit_usd,
+        }))?;
+        alerts.push(BudgetAlert {
+            provider: budget.provider,
+            threshold,
+            spent_usd: budget.spent_usd,
+            monthly_limit_usd: budget.monthly_limit_usd,
+            month: month.clone(),
+            title,
+            message,
+        });
+    }
+    Ok(alerts)
+}
+
+/// Starts the budget watcher, which emits `budget-alert` when a provider's spend this month
+/// crosses 50, 80 or 100% of its budget.
+pub fn start(app: AppHandle) {
+    std::thread::Builder::new()
+        .name("budget-watcher".into())
+        .spawn(move || loop {
+            let alerts = {
+                let db = app.state::<DbState>();
+                let conn = db.0.lock().unwrap_or_else(|e| e.into_inner());
+                check_budgets(&conn).unwrap_or_default()
+            };
+            for alert in alerts {
+                let _ = app.emit("budget-alert", alert);
+            }
+            std::thread::sleep(BUDGET_CHECK_EVERY);
+        })
+        .expect("Failed to start the budget watcher");
+}
+
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// ─── Usage Commands ───
+
+/// Estimates what one run of the agent will cost and how long it will take with the model
+/// configured now. Agents with finished runs are estimated from them; new agents from their
+/// steps, counting the prompts' tokens and assuming a typical reply length.
+#[tauri::command]
+pub fn estimate_run_cost(db: State<DbState>, agent_id: String) -> Result<RunEstimate, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let cfg = llm::provider_config(&conn)?;
+    let price = price(&conn, &cfg.provider, &cfg.model);
+
+    let (basis, sample_runs, input_tokens, output_tokens, model_calls, duration_ms) = match history(&conn, &agent_id)? {
+        Some(h) => ("history", h.runs, h.input_tokens, h.output_tokens, h.model_calls, h.duration_ms),
+        None => {
+            let (plan, tool_calling) = executor::preview_plan(&conn, &agent_id)?;
+            let (mut input, mut calls, mut other_steps) = (0, 0, 0);
+            for step in &plan {
+                let WorkflowStep::Tool { tool, params, .. } = step else { continue };
+                // A critique is a review plus, typically, one revision and a second review.
+                let step_calls = match tool.as_str() {
+                    "llm_prompt" => 1,
+                    "critique" => 3,
+                    _ => {
+                        other_steps += 1;
+                        continue;
+                    }
+                };
+                let text = params.as_object().into_iter().flatten()
+                    .filter_map(|(_, v)| v.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                input += step_calls * (tokens::count(&cfg.model, &text) + PROMPT_OVERHEAD_TOKENS);
+                calls += step_calls;
+            }
+            if tool_calling {
+                // Each choice re-sends the conversation so far, so input grows with every call.
+                input += (1..=TOOL_CALLING_CALLS).map(|n| n * (PROMPT_OVERHEAD_TOKENS + REPLY_TOKENS)).sum::<usize>();
+                calls += TOOL_CALLING_CALLS;
+                other_steps += TOOL_CALLING_CALLS - 1;
+            }
+            let duration = calls as i64 * call_ms(&conn, &cfg.model) + other_steps as i64 * DEFAULT_TOOL_MS;
+            ("plan", 0, input as i64, (calls * REPLY_TOKENS) as i64, calls, duration)
+        }
+    };
+    let cost_usd = price.map(|p| p.cost(input_tokens, output_tokens));
+    Ok(RunEstimate {
+        provider: cfg.provider,
+        model: cfg.model,
+        cost_usd,
+        duration_ms,
+        input_tokens,
+        output_tokens,
+        model_calls,
+        basis,
+        sample_runs,
+        summary: format_summary(cost_usd, duration_ms),
+    })
+}
+
+/// This month's budgets with what has been spent against them.
+#[tauri::command]
+pub fn list_budgets(db: State<DbState>) -> Result<Vec<Budget>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    budgets(&conn)
+}
+
+/// Sets a provider's monthly budget in USD; `None` removes it.
+#[tauri::command]
+pub fn set_budget(db: State<DbState>, provider: String, monthly_limit_usd: Option<f64>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match monthly_limit_usd {
+        Some(limit) if limit < 0.0 => return Err("A budget can't be negative".into()),
+        Some(limit) => conn.execute(
+            "INSERT INTO usage_budgets (provider, monthly_limit_usd) VALUES (?1, ?2)
+             ON CONFLICT(provider) DO UPDATE SET monthly_limit_usd = excluded.monthly_limit_usd",
+            params![provider, limit],
+        ),
+        None => conn.execute("DELETE FROM usage_budgets WHERE provider = ?1", params![provider]),
+    }.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Spend per provider in `month` (`YYYY-MM`, default this month).
+#[tauri::command]
+pub fn get_monthly_usage(db: State<DbState>, month: Option<String>) -> Result<Vec<ProviderSpend>, String> {
+    let month = match month {
+        Some(month) => parse_month(&month)?,
+        None => current_month(),
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    provider_spend(&conn, &month)
+}
+
+/// The month's usage as `csv` (default) or `json`: one line per day, provider, model and
+/// agent, ending with a total, for expense reports.
+#[tauri::command]
+pub fn export_usage_report(db: State<DbState>, month: String, format: Option<String>) -> Result<String, String> {
+    let month = parse_month(&month)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let lines = usage_lines(&conn, &month)?;
+    let total: f64 = lines.iter().filter_map(|l| l.cost_usd).sum();
+    match format.as_deref().unwrap_or("csv") {
+        "json" => serde_json::to_string_pretty(&json!({ "month": month, "total_usd": total, "lines": lines }))
+            .map_err(|e| e.to_string()),
+        "csv" => {
+            let mut out = String::from("date,provider,model,agent,calls,input_tokens,output_tokens,cost_usd\n");
+            for l in &lines {
+                out.push_str(&format!(
+                    "{},{},{},{},{},{},{},{}\n",
+                    l.date, csv_field(&l.provider), csv_field(&l.model), csv_field(&l.agent),
+                    l.calls, l.input_tokens, l.output_tokens,
+                    l.cost_usd.map(|c| format!("{c:.4}")).unwrap_or_default(),
+                ));
+            }
+            out.push_str(&format!("{month},,,Total,{},{},{},{total:.4}\n",
+                lines.iter().map(|l| l.calls).sum::<i64>(),
+                lines.iter().map(|l| l.input_tokens).sum::<i64>(),
+                lines.iter().map(|l| l.output_tokens).sum::<i64>(),
+            ));
+            Ok(out)
+        }
+        other => Err(format!("Unknown report format: {other}")),
+    }
+}
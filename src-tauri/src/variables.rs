@@ -0,0 +1,159 @@
+use chrono::Local;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tauri::State;
+
+use crate::activity;
+use crate::audit;
+use crate::format;
+use crate::{DbState, read_setting, update_agent_config};
+
+/// The user's preferred name for `{{user_name}}`; the OS account name when unset.
+const USER_NAME_SETTING: &str = "user_name";
+/// Sensitive values are cut to this many characters.
+const MAX_CHARS: usize = 20_000;
+
+/// A live value step params and prompts can use as `{{name}}`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ContextProvider {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Reads something the user may not mean to share; agents must be allowed it first.
+    pub sensitive: bool,
+}
+
+const PROVIDERS: &[ContextProvider] = &[
+    ContextProvider { name: "today", description: "Today's date, in your date format", sensitive: false },
+    ContextProvider { name: "now", description: "The date and time right now", sensitive: false },
+    ContextProvider { name: "weekday", description: "Today's day of the week, e.g. Monday", sensitive: false },
+    ContextProvider { name: "user_name", description: "Your name", sensitive: false },
+    ContextProvider { name: "clipboard", description: "The text on the clipboard", sensitive: true },
+    ContextProvider { name: "selection", description: "The text selected in the active app", sensitive: true },
+    ContextProvider { name: "active_window_title", description: "The title of the window in front", sensitive: true },
+];
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    std::process::Command::new(program).args(args).output().ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim_end().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn read_os(name: &str) -> Option<String> {
+    // Wayland tools first, then X11.
+    match name {
+        "clipboard" => run("wl-paste", &["--no-newline"]).or_else(|| run("xclip", &["-o", "-selection", "clipboard"])),
+        "selection" => run("wl-paste", &["--primary", "--no-newline"]).or_else(|| run("xclip", &["-o", "-selection", "primary"])),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_os(name: &str) -> Option<String> {
+    match name {
+        "clipboard" => run("pbpaste", &[]),
+        // Selected text isn't readable without accessibility access; asking the app to copy
+        // would overwrite the clipboard.
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn read_os(name: &str) -> Option<String> {
+    match name {
+        "clipboard" => run("powershell", &["-NoProfile", "-Command", "Get-Clipboard -Raw"]),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_os(_name: &str) -> Option<String> {
+    None
+}
+
+/// Sensitive providers the agent may read, from `context_providers` in its config.
+fn allowed(conn: &Connection, agent_id: &str) -> Vec<String> {
+    conn.query_row("SELECT config_json FROM agents WHERE id = ?1", params![agent_id], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        .and_then(|config| config.get("context_providers").and_then(|v| serde_json::from_value(v.clone()).ok()))
+        .unwrap_or_default()
+}
+
+/// Names of providers `value` uses as placeholders.
+fn used(value: &Value, found: &mut Vec<&'static str>) {
+    match value {
+        Value::String(s) => {
+            for provider in PROVIDERS {
+                if !found.contains(&provider.name) && s.contains(&format!("{{{{{}}}}}", provider.name)) {
+                    found.push(provider.name);
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| used(v, found)),
+        Value::Object(map) => map.values().for_each(|v| used(v, found)),
+        _ => {}
+    }
+}
+
+/// Adds the live values `params` uses to `args`, read just now. Only the providers a step
+/// mentions are read; a sensitive one the agent isn't allowed fails the step, and each
+/// sensitive read is audited. Arguments already set, e.g. by a workflow, win.
+pub fn provide(db: &DbState, agent_id: &str, run_id: &str, params: &Value, args: &mut Map<String, Value>) -> Result<(), String> {
+    let mut names = Vec::new();
+    used(params, &mut names);
+    names.retain(|name| !args.contains_key(*name));
+    if names.is_empty() {
+        return Ok(());
+    }
+    let locale = format::locale(db)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let now = Local::now();
+    for name in names {
+        let value = match name {
+            "today" => format::date(&locale, &now),
+            "now" => format::date_time(&locale, &now),
+            "weekday" => now.format("%A").to_string(),
+            "user_name" => read_setting(&conn, USER_NAME_SETTING)?
+                .filter(|n| !n.trim().is_empty())
+                .or_else(|| ["USER", "USERNAME", "LOGNAME"].iter().find_map(|var| std::env::var(var).ok()))
+                .unwrap_or_default(),
+            sensitive => {
+                let label = PROVIDERS.iter().find(|p| p.name == sensitive).map_or(sensitive, |p| p.description);
+                if !allowed(&conn, agent_id).iter().any(|a| a == sensitive) {
+                    return Err(format!("This agent isn't allowed to read {} ({{{{{sensitive}}}}})", label.to_lowercase()));
+                }
+                let text = match sensitive {
+                    "active_window_title" => activity::active_window().map(|w| w.title),
+                    other => read_os(other),
+                };
+                let text: String = text.unwrap_or_default().chars().take(MAX_CHARS).collect();
+                audit::record(&conn, "context_read", agent_id, run_id, &format!("Read {}", label.to_lowercase()), &json!({ "provider": sensitive, "chars": text.chars().count() }))?;
+                text
+            }
+        };
+        args.insert(name.into(), Value::String(value));
+    }
+    Ok(())
+}
+
+// ─── Context Variable Commands ───
+
+#[tauri::command]
+pub fn list_context_providers() -> Vec<ContextProvider> {
+    PROVIDERS.to_vec()
+}
+
+/// The sensitive providers (clipboard, selection, active window title) an agent may read.
+#[tauri::command]
+pub fn set_agent_context_providers(db: State<DbState>, agent_id: String, allowed: Vec<String>) -> Result<(), String> {
+    if let Some(name) = allowed.iter().find(|a| !PROVIDERS.iter().any(|p| p.sensitive && p.name == a.as_str())) {
+        return Err(format!("{name} isn't a context value that needs permission"));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_agent_config(&conn, &agent_id, |config| {
+        config.insert("context_providers".into(), json!(allowed));
+    })
+}
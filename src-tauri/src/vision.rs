@@ -0,0 +1,310 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::llm;
+use crate::{DbState, read_setting};
+
+/// Images larger than this on their long edge are scaled down; providers do the same on their
+/// side, so sending more only costs upload time.
+const MAX_EDGE: u32 = 1568;
+const MAX_PIXELS: u64 = 1_150_000;
+/// Largest file accepted, before scaling.
+const MAX_INPUT_BYTES: usize = 20 * 1024 * 1024;
+/// Largest image sent to a provider, after scaling; the providers' own limit.
+const MAX_SEND_BYTES: usize = 5 * 1024 * 1024;
+/// Images one model call may carry, unless `vision_max_images` says otherwise.
+const DEFAULT_MAX_IMAGES: usize = 4;
+
+/// An image in a model request, base64 encoded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Image {
+    /// `image/png`, `image/jpeg`, `image/gif` or `image/webp`.
+    pub media_type: String,
+    pub data: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Image {
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+
+    /// What the image costs in prompt tokens with `provider`, following each one's published
+    /// formula. Local models vary; Anthropic's is a fair middle.
+    pub fn tokens(&self, provider: &str) -> usize {
+        let (w, h) = (self.width.max(1) as f64, self.height.max(1) as f64);
+        match provider {
+            "openai" => {
+                let fit = (2048.0 / w.max(h)).min(1.0);
+                let short = (768.0 / (w.min(h) * fit)).min(1.0) * fit;
+                let tiles = ((w * short) / 512.0).ceil() * ((h * short) / 512.0).ceil();
+                85 + 170 * tiles as usize
+            }
+            _ => (w * h / 750.0).ceil() as usize,
+        }
+    }
+}
+
+/// An image the user attached, ready to go into chat or a workflow step by its id.
+#[derive(Debug, Serialize, Clone)]
+pub struct ImageAttachment {
+    pub id: String,
+    pub name: String,
+    pub media_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+    /// Whether it was scaled down to what providers accept.
+    pub downscaled: bool,
+    /// Prompt tokens it adds with the active provider.
+    pub estimated_tokens: usize,
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS image_attachments (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            media_type TEXT NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            downscaled INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+    ").expect("Failed to initialize image tables");
+}
+
+fn media_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn u16_be(b: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes([*b.get(at)?, *b.get(at + 1)?]) as u32)
+}
+
+fn u16_le(b: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes([*b.get(at)?, *b.get(at + 1)?]) as u32)
+}
+
+/// Width and height from the file header, for formats that aren't decoded here.
+fn dimensions(media_type: &str, b: &[u8]) -> Option<(u32, u32)> {
+    match media_type {
+        "image/gif" => Some((u16_le(b, 6)?, u16_le(b, 8)?)),
+        "image/webp" => match b.get(12..16)? {
+            b"VP8 " => Some((u16_le(b, 26)? & 0x3FFF, u16_le(b, 28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(b.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => {
+                let w = u32::from_le_bytes([*b.get(24)?, *b.get(25)?, *b.get(26)?, 0]) + 1;
+                let h = u32::from_le_bytes([*b.get(27)?, *b.get(28)?, *b.get(29)?, 0]) + 1;
+                Some((w, h))
+            }
+            _ => None,
+        },
+        "image/jpeg" => {
+            // Walk the segments to the start-of-frame marker, which holds the size.
+            let mut at = 2;
+            while at + 9 < b.len() {
+                if b[at] != 0xFF {
+                    return None;
+                }
+                let marker = b[at + 1];
+                if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                    return Some((u16_be(b, at + 7)?, u16_be(b, at + 5)?));
+                }
+                at += 2 + u16_be(b, at + 2)? as usize;
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// The size after scaling to what providers accept.
+fn target_size(width: u32, height: u32) -> (u32, u32) {
+    let by_edge = MAX_EDGE as f64 / width.max(height) as f64;
+    let by_area = (MAX_PIXELS as f64 / (width as f64 * height as f64)).sqrt();
+    let scale = by_edge.min(by_area).min(1.0);
+    (((width as f64 * scale).round() as u32).max(1), ((height as f64 * scale).round() as u32).max(1))
+}
+
+/// Box filter: each target pixel averages the source pixels it covers.
+fn downscale(rgba: &[u8], width: u32, height: u32, to_w: u32, to_h: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity((to_w * to_h * 4) as usize);
+    for ty in 0..to_h {
+        let (y0, y1) = (ty * height / to_h, ((ty + 1) * height / to_h).max(ty * height / to_h + 1));
+        for tx in 0..to_w {
+            let (x0, x1) = (tx * width / to_w, ((tx + 1) * width / to_w).max(tx * width / to_w + 1));
+            let mut sum = [0u64; 4];
+            for y in y0..y1.min(height) {
+                for x in x0..x1.min(width) {
+                    let i = ((y * width + x) * 4) as usize;
+                    for (c, total) in sum.iter_mut().enumerate() {
+                        *total += rgba[i + c] as u64;
+                    }
+                }
+            }
+            let n = ((y1.min(height) - y0) * (x1.min(width) - x0)).max(1) as u64;
+            out.extend(sum.iter().map(|total| (total / n) as u8));
+        }
+    }
+    out
+}
+
+/// Decodes a PNG, scales it down when it is larger than providers accept and encodes it again.
+/// Returns the new file and size, or `None` when it was already small enough.
+fn shrink_png(bytes: &[u8]) -> Result<Option<(Vec<u8>, u32, u32)>, String> {
+    let invalid = |e: png::DecodingError| format!("Couldn't read the image: {e}");
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(invalid)?;
+    let (width, height) = (info.width, info.height);
+    let (to_w, to_h) = target_size(width, height);
+    if (to_w, to_h) == (width, height) {
+        return Ok(None);
+    }
+    let pixels = &buf[..info.buffer_size()];
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        _ => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+    };
+    let scaled = downscale(&rgba, width, height, to_w, to_h);
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, to_w, to_h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&scaled).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(Some((out, to_w, to_h)))
+}
+
+/// Checks and prepares an image file for a model: PNGs, which is what screenshots are, are
+/// scaled down to what providers accept; other formats are sent as they are if small enough.
+pub fn prepare(bytes: &[u8]) -> Result<(Image, bool), String> {
+    if bytes.len() > MAX_INPUT_BYTES {
+        return Err(format!("The image is {} MB; the limit is {} MB", bytes.len() / 1_048_576, MAX_INPUT_BYTES / 1_048_576));
+    }
+    let media_type = media_type(bytes).ok_or("Only PNG, JPEG, GIF and WebP images can be attached")?;
+    let (bytes, width, height, downscaled) = match media_type {
+        "image/png" => match shrink_png(bytes)? {
+            Some((scaled, w, h)) => (scaled, w, h, true),
+            None => {
+                let (w, h) = (u32::from_be_bytes(bytes[16..20].try_into().unwrap_or_default()),
+                    u32::from_be_bytes(bytes[20..24].try_into().unwrap_or_default()));
+                (bytes.to_vec(), w, h, false)
+            }
+        },
+        _ => {
+            let (w, h) = dimensions(media_type, bytes).ok_or("Couldn't read the image's size; is the file complete?")?;
+            (bytes.to_vec(), w, h, false)
+        }
+    };
+    if bytes.len() > MAX_SEND_BYTES {
+        return Err(format!(
+            "The image is still {} MB after preparing it; models accept up to {} MB. Save it as a smaller JPEG or a PNG.",
+            bytes.len() / 1_048_576, MAX_SEND_BYTES / 1_048_576,
+        ));
+    }
+    // Formats sent as they are get scaled by the provider; count them at that size.
+    let (width, height) = target_size(width, height);
+    Ok((Image { media_type: media_type.into(), data: BASE64.encode(&bytes), width, height }, downscaled))
+}
+
+fn max_images(conn: &Connection) -> usize {
+    read_setting(conn, "vision_max_images").ok().flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_IMAGES)
+}
+
+/// Stored attachments by id, in order, for a model call. Fails on an unknown id or more
+/// images than one call may carry.
+pub fn load(conn: &Connection, ids: &[String]) -> Result<Vec<Image>, String> {
+    let limit = max_images(conn);
+    if ids.len() > limit {
+        return Err(format!("{} images were attached; a model call takes at most {limit}", ids.len()));
+    }
+    ids.iter().map(|id| {
+        conn.query_row(
+            "SELECT media_type, data, width, height FROM image_attachments WHERE id = ?1",
+            params![id],
+            |row| {
+                let data: Vec<u8> = row.get(1)?;
+                Ok(Image { media_type: row.get(0)?, data: BASE64.encode(data), width: row.get(2)?, height: row.get(3)? })
+            },
+        ).optional().map_err(|e| e.to_string())?.ok_or_else(|| format!("Image attachment not found: {id}"))
+    }).collect()
+}
+
+// ─── Image Commands ───
+
+/// Attaches an image for chat or a workflow step: a dropped file by `path`, or a pasted
+/// screenshot as base64 `data`. Returns its id with what it will cost to send.
+#[tauri::command]
+pub fn attach_image(db: State<DbState>, name: Option<String>, path: Option<String>, data: Option<String>) -> Result<ImageAttachment, String> {
+    let (bytes, name) = match (path, data) {
+        (Some(path), _) => {
+            let path = std::path::PathBuf::from(path);
+            let name = name.unwrap_or_else(|| path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+            (std::fs::read(&path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?, name)
+        }
+        (None, Some(data)) => {
+            // Accept a data URL as the clipboard gives it, or bare base64.
+            let data = data.split_once(";base64,").map(|(_, d)| d).unwrap_or(&data);
+            (BASE64.decode(data.trim()).map_err(|_| "The image data isn't valid base64")?, name.unwrap_or_else(|| "Pasted image".into()))
+        }
+        (None, None) => return Err("Choose an image to attach".into()),
+    };
+    let (image, downscaled) = prepare(&bytes)?;
+    let stored = BASE64.decode(&image.data).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let attachment = ImageAttachment {
+        id: Uuid::new_v4().to_string(),
+        name,
+        media_type: image.media_type.clone(),
+        width: image.width,
+        height: image.height,
+        bytes: stored.len(),
+        downscaled,
+        estimated_tokens: image.tokens(&llm::provider_config(&conn)?.provider),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    conn.execute(
+        "INSERT INTO image_attachments (id, name, media_type, width, height, data, downscaled, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![attachment.id, attachment.name, attachment.media_type, attachment.width, attachment.height, stored, downscaled as i32, attachment.created_at],
+    ).map_err(|e| e.to_string())?;
+    Ok(attachment)
+}
+
+/// The attached image as a data URL, for a preview.
+#[tauri::command]
+pub fn get_image_attachment(db: State<DbState>, id: String) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load(&conn, std::slice::from_ref(&id))?.remove(0).data_url())
+}
+
+#[tauri::command]
+pub fn delete_image_attachment(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM image_attachments WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
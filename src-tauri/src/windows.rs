@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventTarget, LogicalPosition, LogicalSize, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::{DbState, read_setting, write_setting};
+
+const MAIN_WINDOW: &str = "main";
+/// Window geometry is kept per window under this prefix plus the window label. It describes
+/// this machine's screens, so it doesn't sync.
+pub const STATE_SETTING_PREFIX: &str = "window_state_";
+
+/// Windows that can be opened next to the main one, as (label, title, width, height). Each
+/// loads the app at `index.html#/<label>`.
+const SECONDARY: &[(&str, &str, f64, f64)] = &[
+    ("approvals", "Approvals", 420.0, 640.0),
+    ("logs", "Activity log", 720.0, 520.0),
+];
+
+/// Events a secondary window can subscribe to. The main window always gets every event.
+const EVENTS: &[&str] = &["approvals-changed", "logs-changed", "run-updated"];
+
+/// Events each secondary window asked for, by window label.
+#[derive(Default)]
+pub struct Subscriptions(Mutex<HashMap<String, HashSet<String>>>);
+
+/// How much of a window's top edge has to be on a screen for it to be restored where it was;
+/// enough to grab the title bar.
+const VISIBLE_GRIP: f64 = 48.0;
+
+/// Where a window was and how big it was, in logical pixels. For a maximized window these are
+/// its size and position from before it was maximized.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WindowState {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub maximized: bool,
+    /// The view the main window last showed, e.g. `agents` or `settings/model`.
+    pub view: String,
+}
+
+fn load_state(conn: &rusqlite::Connection, label: &str) -> Option<WindowState> {
+    serde_json::from_str(&read_setting(conn, &format!("{STATE_SETTING_PREFIX}{label}")).ok()??).ok()
+}
+
+fn store_state(conn: &rusqlite::Connection, label: &str, state: &WindowState) -> Result<(), String> {
+    let json = serde_json::to_string(state).map_err(|e| e.to_string())?;
+    write_setting(conn, &format!("{STATE_SETTING_PREFIX}{label}"), &json)
+}
+
+/// Remembers the window's position and size for the next time it opens.
+pub fn save_state(window: &tauri::Window) {
+    let db = window.state::<DbState>();
+    let Ok(conn) = db.0.lock() else { return };
+    let mut state = load_state(&conn, window.label()).unwrap_or_default();
+    state.maximized = window.is_maximized().unwrap_or(false);
+    // A maximized window's geometry is the screen's; keep what it returns to instead.
+    if !state.maximized || state.width <= 0.0 {
+        let (Ok(position), Ok(size), Ok(scale)) = (window.outer_position(), window.inner_size(), window.scale_factor()) else {
+            return;
+        };
+        let position = position.to_logical::<f64>(scale);
+        let size = size.to_logical::<f64>(scale);
+        (state.x, state.y, state.width, state.height) = (position.x, position.y, size.width, size.height);
+    }
+    let _ = store_state(&conn, window.label(), &state);
+}
+
+/// Screens as (x, y, width, height) in logical pixels.
+fn screens(window: &WebviewWindow) -> Vec<(f64, f64, f64, f64)> {
+    window.available_monitors().unwrap_or_default().iter().map(|m| {
+        let position = m.position().to_logical::<f64>(m.scale_factor());
+        let size = m.size().to_logical::<f64>(m.scale_factor());
+        (position.x, position.y, size.width, size.height)
+    }).collect()
+}
+
+/// Moves a saved window onto a screen when the one it was on is gone, e.g. an unplugged
+/// external monitor, and shrinks it to fit. `None` when no screen is known.
+fn clamp(mut state: WindowState, screens: &[(f64, f64, f64, f64)]) -> Option<WindowState> {
+    let grip_visible = |&(x, y, w, h): &(f64, f64, f64, f64)| {
+        state.x + state.width.min(VISIBLE_GRIP) > x && state.x < x + w - VISIBLE_GRIP
+            && state.y >= y && state.y < y + h - VISIBLE_GRIP
+    };
+    let (x, y, w, h) = match screens.iter().find(|s| grip_visible(s)) {
+        Some(screen) => *screen,
+        None => {
+            let screen = *screens.first()?;
+            state.x = screen.0 + ((screen.2 - state.width) / 2.0).max(0.0);
+            state.y = screen.1 + ((screen.3 - state.height) / 2.0).max(0.0);
+            screen
+        }
+    };
+    state.width = state.width.min(w);
+    state.height = state.height.min(h);
+    state.x = state.x.min(x + w - VISIBLE_GRIP).max(x - state.width + VISIBLE_GRIP);
+    state.y = state.y.min(y + h - VISIBLE_GRIP).max(y);
+    Some(state)
+}
+
+/// Puts a window back where it was last closed, or onto a screen that's still connected.
+pub fn restore_state(window: &WebviewWindow) {
+    let state = {
+        let db = window.state::<DbState>();
+        let Ok(conn) = db.0.lock() else { return };
+        load_state(&conn, window.label())
+    };
+    let Some(state) = state.filter(|s| s.width > 0.0 && s.height > 0.0) else { return };
+    let Some(state) = clamp(state, &screens(window)) else { return };
+    let _ = window.set_size(LogicalSize::new(state.width, state.height));
+    let _ = window.set_position(LogicalPosition::new(state.x, state.y));
+    if state.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// Emits `event` to the main window and every secondary window subscribed to it.
+pub fn broadcast<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    let subscribed: HashSet<String> = app.state::<Subscriptions>().0.lock()
+        .map(|subs| subs.iter().filter(|(_, events)| events.contains(event)).map(|(label, _)| label.clone()).collect())
+        .unwrap_or_default();
+    let _ = app.emit_filter(event, payload, |target| match target {
+        EventTarget::WebviewWindow { label } | EventTarget::Webview { label } | EventTarget::Window { label } => {
+            label == MAIN_WINDOW || subscribed.contains(label)
+        }
+        _ => false,
+    });
+}
+
+/// Drops a closed window's subscriptions.
+pub fn forget(window: &tauri::Window) {
+    if let Ok(mut subs) = window.state::<Subscriptions>().0.lock() {
+        subs.remove(window.label());
+    }
+}
+
+// ─── Window Commands ───
+
+/// Opens the approvals panel or the live log (`kind` is `approvals` or `logs`) in its own
+/// window, where it was last closed. An already open one is brought to the front.
+#[tauri::command]
+pub fn open_window(app: AppHandle, kind: String) -> Result<(), String> {
+    let &(label, title, width, height) = SECONDARY.iter().find(|(label, ..)| *label == kind)
+        .ok_or_else(|| format!("Unknown window: {kind}"))?;
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    let window = WebviewWindowBuilder::new(&app, label, WebviewUrl::App(format!("index.html#/{label}").into()))
+        .title(title)
+        .inner_size(width, height)
+        .min_inner_size(320.0, 240.0)
+        .build()
+        .map_err(|e| e.to_string())?;
+    restore_state(&window);
+    Ok(())
+}
+
+/// Sets which events the calling window receives, from `approvals-changed`, `logs-changed`
+/// and `run-updated`. Replaces its earlier subscriptions.
+#[tauri::command]
+pub fn subscribe_window_events(window: WebviewWindow, subs: tauri::State<Subscriptions>, events: Vec<String>) -> Result<(), String> {
+    if let Some(unknown) = events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+        return Err(format!("Unknown event: {unknown}"));
+    }
+    let mut subs = subs.0.lock().map_err(|e| e.to_string())?;
+    subs.insert(window.label().to_string(), events.into_iter().collect());
+    Ok(())
+}
+
+/// Remembers the view the main window shows, to reopen it there on the next start.
+#[tauri::command]
+pub fn set_last_view(db: tauri::State<DbState>, view: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut state = load_state(&conn, MAIN_WINDOW).unwrap_or_default();
+    state.view = view;
+    store_state(&conn, MAIN_WINDOW, &state)
+}
+
+/// The main window's saved state; `view` is empty on first start.
+#[tauri::command]
+pub fn get_main_window_state(db: tauri::State<DbState>) -> Result<WindowState, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_state(&conn, MAIN_WINDOW).unwrap_or_default())
+}
@@ -0,0 +1,380 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::DbState;
+
+/// Fragments may call other fragments; this bounds how deep that can go.
+const MAX_FRAGMENT_DEPTH: usize = 8;
+
+/// A single step in an agent workflow, stored under `workflow` in `config_json`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkflowStep {
+    Tool {
+        tool: String,
+        #[serde(default)]
+        params: Value,
+        #[serde(default)]
+        label: String,
+    },
+    Fragment {
+        fragment_id: String,
+        /// Pinned version; `None` always resolves to the latest one.
+        #[serde(default)]
+        version: Option<i64>,
+        #[serde(default)]
+        args: Map<String, Value>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkflowFragment {
+    pub id: String,
+    pub version: i64,
+    pub name: String,
+    pub description: String,
+    pub params: Vec<String>,
+    pub steps: Vec<WorkflowStep>,
+    pub created_at: String,
+}
+
+pub fn init_tables(conn: &Connection) {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS workflow_fragments (
+            id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT DEFAULT '',
+            params_json TEXT DEFAULT '[]',
+            steps_json TEXT DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (id, version)
+        );
+    ").expect("Failed to initialize workflow tables");
+}
+
+fn row_to_fragment(row: &rusqlite::Row) -> rusqlite::Result<(WorkflowFragment, String, String)> {
+    Ok((
+        WorkflowFragment {
+            id: row.get(0)?,
+            version: row.get(1)?,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            params: Vec::new(),
+            steps: Vec::new(),
+            created_at: row.get(6)?,
+        },
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn decode_fragment(raw: (WorkflowFragment, String, String)) -> Result<WorkflowFragment, String> {
+    let (mut fragment, params_json, steps_json) = raw;
+    fragment.params = serde_json::from_str(&params_json).map_err(|e| e.to_string())?;
+    fragment.steps = serde_json::from_str(&steps_json).map_err(|e| e.to_string())?;
+    Ok(fragment)
+}
+
+/// Loads a fragment at the given version, or its latest version when `version` is `None`.
+pub fn load_fragment(conn: &Connection, id: &str, version: Option<i64>) -> Result<WorkflowFragment, String> {
+    let cols = "id, version, name, description, params_json, steps_json, created_at";
+    let raw = match version {
+        Some(v) => conn.query_row(
+            &format!("SELECT {cols} FROM workflow_fragments WHERE id = ?1 AND version = ?2"),
+            params![id, v],
+            row_to_fragment,
+        ),
+        None => conn.query_row(
+            &format!("SELECT {cols} FROM workflow_fragments WHERE id = ?1 ORDER BY version DESC LIMIT 1"),
+            params![id],
+            row_to_fragment,
+        ),
+    }
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| match version {
+        Some(v) => format!("Fragment {id} v{v} not found"),
+        None => format!("Fragment {id} not found"),
+    })?;
+    decode_fragment(raw)
+}
+
+/// Replaces `{{name}}` placeholders in every string of `value` with the matching argument.
+pub fn bind_args(value: &Value, args: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(s) => {
+            // A string that is exactly one placeholder takes the argument's JSON type.
+            if let Some(name) = s.strip_prefix("{{").and_then(|r| r.strip_suffix("}}")) {
+                if let Some(arg) = args.get(name.trim()) {
+                    return arg.clone();
+                }
+            }
+            let mut out = s.clone();
+            for (name, arg) in args {
+                let text = match arg {
+                    Value::String(t) => t.clone(),
+                    other => other.to_string(),
+                };
+                out = out.replace(&format!("{{{{{name}}}}}"), &text);
+            }
+            Value::String(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| bind_args(v, args)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter().map(|(k, v)| (k.clone(), bind_args(v, args))).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn expand_into(
+    conn: &Connection,
+    steps: &[WorkflowStep],
+    args: &Map<String, Value>,
+    stack: &mut Vec<String>,
+    out: &mut Vec<WorkflowStep>,
+) -> Result<(), String> {
+    for step in steps {
+        match step {
+            WorkflowStep::Tool { tool, params, label } => out.push(WorkflowStep::Tool {
+                tool: tool.clone(),
+                params: bind_args(params, args),
+                label: label.clone(),
+            }),
+            WorkflowStep::Fragment { fragment_id, version, args: call_args } => {
+                if stack.contains(fragment_id) {
+                    return Err(format!("Fragment {fragment_id} calls itself"));
+                }
+                if stack.len() >= MAX_FRAGMENT_DEPTH {
+                    return Err(format!("Fragments nested deeper than {MAX_FRAGMENT_DEPTH} levels"));
+                }
+                let fragment = load_fragment(conn, fragment_id, *version)?;
+                let bound = match bind_args(&Value::Object(call_args.clone()), args) {
+                    Value::Object(map) => map,
+                    _ => Map::new(),
+                };
+                if let Some(missing) = fragment.params.iter().find(|p| !bound.contains_key(*p)) {
+                    return Err(format!("Fragment '{}' is missing argument '{missing}'", fragment.name));
+                }
+                stack.push(fragment_id.clone());
+                expand_into(conn, &fragment.steps, &bound, stack, out)?;
+                stack.pop();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Flattens a workflow so that every fragment call is replaced by its tool steps.
+pub fn expand_workflow(conn: &Connection, steps: &[WorkflowStep]) -> Result<Vec<WorkflowStep>, String> {
+    let mut out = Vec::new();
+    expand_into(conn, steps, &Map::new(), &mut Vec::new(), &mut out)?;
+    Ok(out)
+}
+
+/// Reads the workflow steps stored in an agent's `config_json`.
+pub fn agent_workflow(conn: &Connection, agent_id: &str) -> Result<Vec<WorkflowStep>, String> {
+    let config: String = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    let config: Value = serde_json::from_str(&config).unwrap_or(Value::Null);
+    match config.get("workflow") {
+        Some(steps) => serde_json::from_value(steps.clone()).map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Checks that a fragment body expands cleanly, with its own params bound to placeholders.
+fn validate_fragment(conn: &Connection, id: &str, params: &[String], steps: &[WorkflowStep]) -> Result<(), String> {
+    let args = params.iter().map(|p| (p.clone(), Value::Null)).collect();
+    expand_into(conn, steps, &args, &mut vec![id.to_string()], &mut Vec::new())
+}
+
+// ─── Fragment Commands ───
+
+#[tauri::command]
+pub fn create_fragment(
+    db: State<DbState>,
+    name: String,
+    description: String,
+    params: Vec<String>,
+    steps: Vec<WorkflowStep>,
+) -> Result<WorkflowFragment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    validate_fragment(&conn, &id, &params, &steps)?;
+    conn.execute(
+        "INSERT INTO workflow_fragments (id, version, name, description, params_json, steps_json, created_at) VALUES (?1, 1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id, name, description,
+            serde_json::to_string(&params).map_err(|e| e.to_string())?,
+            serde_json::to_string(&steps).map_err(|e| e.to_string())?,
+            now
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(WorkflowFragment { id, version: 1, name, description, params, steps, created_at: now })
+}
+
+/// Saves a new version of an existing fragment; older versions stay available for pinned callers.
+#[tauri::command]
+pub fn update_fragment(
+    db: State<DbState>,
+    id: String,
+    name: String,
+    description: String,
+    params: Vec<String>,
+    steps: Vec<WorkflowStep>,
+) -> Result<WorkflowFragment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let current = load_fragment(&conn, &id, None)?;
+    validate_fragment(&conn, &id, &params, &steps)?;
+    let version = current.version + 1;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO workflow_fragments (id, version, name, description, params_json, steps_json, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            id, version, name, description,
+            serde_json::to_string(&params).map_err(|e| e.to_string())?,
+            serde_json::to_string(&steps).map_err(|e| e.to_string())?,
+            now
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(WorkflowFragment { id, version, name, description, params, steps, created_at: now })
+}
+
+/// Lists the latest version of every fragment.
+#[tauri::command]
+pub fn list_fragments(db: State<DbState>) -> Result<Vec<WorkflowFragment>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT f.id, f.version, f.name, f.description, f.params_json, f.steps_json, f.created_at
+         FROM workflow_fragments f
+         WHERE f.version = (SELECT MAX(version) FROM workflow_fragments WHERE id = f.id)
+         ORDER BY f.name COLLATE NOCASE"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], row_to_fragment).map_err(|e| e.to_string())?;
+
+    let mut fragments = Vec::new();
+    for row in rows {
+        fragments.push(decode_fragment(row.map_err(|e| e.to_string())?)?);
+    }
+    Ok(fragments)
+}
+
+#[tauri::command]
+pub fn get_fragment(db: State<DbState>, id: String, version: Option<i64>) -> Result<WorkflowFragment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_fragment(&conn, &id, version)
+}
+
+#[tauri::command]
+pub fn list_fragment_versions(db: State<DbState>, id: String) -> Result<Vec<WorkflowFragment>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, version, name, description, params_json, steps_json, created_at FROM workflow_fragments WHERE id = ?1 ORDER BY version DESC"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![id], row_to_fragment).map_err(|e| e.to_string())?;
+
+    let mut versions = Vec::new();
+    for row in rows {
+        versions.push(decode_fragment(row.map_err(|e| e.to_string())?)?);
+    }
+    Ok(versions)
+}
+
+/// Returns the ids of agents whose workflow calls the fragment, directly or through another fragment.
+fn fragment_users(conn: &Connection, fragment_id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare("SELECT id FROM agents").map_err(|e| e.to_string())?;
+    let ids = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+    let mut users = Vec::new();
+    for id in ids {
+        let id = id.map_err(|e| e.to_string())?;
+        let steps = agent_workflow(conn, &id).unwrap_or_default();
+        if calls_fragment(conn, &steps, fragment_id, 0) {
+            users.push(id);
+        }
+    }
+    Ok(users)
+}
+
+fn calls_fragment(conn: &Connection, steps: &[WorkflowStep], fragment_id: &str, depth: usize) -> bool {
+    if depth > MAX_FRAGMENT_DEPTH {
+        return false;
+    }
+    steps.iter().any(|step| match step {
+        WorkflowStep::Fragment { fragment_id: id, version, .. } => {
+            id == fragment_id
+                || load_fragment(conn, id, *version)
+                    .map(|f| calls_fragment(conn, &f.steps, fragment_id, depth + 1))
+                    .unwrap_or(false)
+        }
+        WorkflowStep::Tool { .. } => false,
+    })
+}
+
+#[tauri::command]
+pub fn get_fragment_usages(db: State<DbState>, id: String) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    fragment_users(&conn, &id)
+}
+
+/// Deletes every version of a fragment; refuses while an agent still calls it.
+#[tauri::command]
+pub fn delete_fragment(db: State<DbState>, id: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let users = fragment_users(&conn, &id)?;
+    if !users.is_empty() {
+        return Err(format!("Fragment is still used by {} agent(s)", users.len()));
+    }
+    conn.execute("DELETE FROM workflow_fragments WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ─── Agent Workflows ───
+
+/// Validates and stores the workflow steps for an agent inside its `config_json`.
+pub fn store_agent_workflow(conn: &Connection, agent_id: &str, steps: &[WorkflowStep]) -> Result<(), String> {
+    expand_workflow(conn, steps)?;
+    let config: String = conn.query_row(
+        "SELECT config_json FROM agents WHERE id = ?1",
+        params![agent_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    let mut config: Value = serde_json::from_str(&config).unwrap_or_else(|_| serde_json::json!({}));
+    if !config.is_object() {
+        config = serde_json::json!({});
+    }
+    config["workflow"] = serde_json::to_value(steps).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agents SET config_json = ?1, updated_at = ?2 WHERE id = ?3",
+        params![config.to_string(), Utc::now().to_rfc3339(), agent_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_agent_workflow(db: State<DbState>, agent_id: String, steps: Vec<WorkflowStep>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store_agent_workflow(&conn, &agent_id, &steps)
+}
+
+/// Returns an agent's workflow, optionally with fragment calls expanded into tool steps.
+#[tauri::command]
+pub fn get_agent_workflow(db: State<DbState>, agent_id: String, expand: Option<bool>) -> Result<Vec<WorkflowStep>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let steps = agent_workflow(&conn, &agent_id)?;
+    if expand.unwrap_or(false) {
+        expand_workflow(&conn, &steps)
+    } else {
+        Ok(steps)
+    }
+}
@@ -118,8 +118,23 @@ export const createAgent = (data) =>
         sandbox: data.sandbox || false,
     });
 
-export const listAgents = () => invoke("list_agents");
+/** Replaces an agent's fields, keeping its id and history; takes the same fields as createAgent. */
+export const updateAgent = (id, data) =>
+    invoke("update_agent", {
+        id,
+        name: data.name,
+        role: data.role || "",
+        goal: data.goal || "",
+        tools: data.tools || "[]",
+        schedule: data.schedule || "",
+        sandbox: data.sandbox || false,
+    });
+
+/** @param {"created"|"recent"|"most_used"|"pinned"|null} [sort] - Newest first by default. */
+export const listAgents = (sort = null) => invoke("list_agents", { sort });
 export const deleteAgent = (id) => invoke("delete_agent", { id });
+export const pinAgent = (id) => invoke("pin_agent", { id });
+export const unpinAgent = (id) => invoke("unpin_agent", { id });
 
 // ── Logs ──
 export const addLog = (agentId, action, status, output, error) =>
@@ -139,4 +154,905 @@ export const addApproval = (agentId, actionType, contentPreview) =>
 export const updateApproval = (id, status) =>
     invoke("update_approval", { id, status });
 
-export const getApprovals = () => invoke("get_approvals");
+/**
+ * The approval queue. `order` is "priority" (default: pending first, most urgent first),
+ * "newest" or "oldest"; `groupBy` ("agent", "action" or "priority") keeps groups together.
+ */
+export const getApprovals = (order = null, groupBy = null) => invoke("get_approvals", { order, groupBy });
+
+/** Priority is "low", "normal", "high" or "urgent". */
+export const setApprovalPriority = (id, priority) => invoke("set_approval_priority", { id, priority });
+/** Fixes an agent's approval priority; null goes back to judging each step by its risk. */
+export const setAgentApprovalPriority = (agentId, priority = null) =>
+    invoke("set_agent_approval_priority", { agentId, priority });
+
+/** Minutes a high-priority item may wait before it is raised and shown again (`approval-escalated`); 0 turns it off. */
+export const getApprovalEscalation = () => invoke("get_approval_escalation");
+export const setApprovalEscalation = (minutes) => invoke("set_approval_escalation", { minutes });
+
+// ── Workflow Fragments ──
+export const createFragment = (name, description, params, steps) =>
+    invoke("create_fragment", { name, description, params, steps });
+
+export const updateFragment = (id, name, description, params, steps) =>
+    invoke("update_fragment", { id, name, description, params, steps });
+
+export const listFragments = () => invoke("list_fragments");
+export const getFragment = (id, version = null) => invoke("get_fragment", { id, version });
+export const listFragmentVersions = (id) => invoke("list_fragment_versions", { id });
+export const getFragmentUsages = (id) => invoke("get_fragment_usages", { id });
+export const deleteFragment = (id) => invoke("delete_fragment", { id });
+
+export const setAgentWorkflow = (agentId, steps) =>
+    invoke("set_agent_workflow", { agentId, steps });
+
+export const getAgentWorkflow = (agentId, expand = false) =>
+    invoke("get_agent_workflow", { agentId, expand });
+
+// ── Tools & Agent Builder ──
+export const listTools = () => invoke("list_tools");
+export const draftAgentFromDescription = (text) =>
+    invoke("draft_agent_from_description", { text });
+export const saveAgentDraft = (draft, sessionId = null) =>
+    invoke("save_agent_draft", { draft, sessionId });
+
+export const startAgentDraft = (text) => invoke("start_agent_draft", { text });
+export const answerDraftQuestions = (sessionId, answers) =>
+    invoke("answer_draft_questions", { sessionId, answers });
+export const getDraftSession = (id) => invoke("get_draft_session", { id });
+export const listDraftSessions = () => invoke("list_draft_sessions");
+export const discardDraftSession = (id) => invoke("discard_draft_session", { id });
+/** Drafts an agent that repeats a successful run, e.g. a quick task; save it with `saveAgentDraft`. */
+export const promoteRunToAgent = (runId) => invoke("promote_run_to_agent", { runId });
+
+// ── Runs ──
+/**
+ * Runs carry `changes`: what their file steps created, modified, moved or deleted, as
+ * [{ kind, path, to?, description }], compared with the folders before the first write.
+ */
+/** @param {"live"|"simulate"} [mode="live"] - Simulate mocks every side-effecting tool. */
+export const runAgent = (agentId, mode = "live") => invoke("run_agent", { agentId, mode });
+export const simulateAgent = (agentId) => invoke("run_agent", { agentId, mode: "simulate" });
+/** Also continues runs left `interrupted` when the app closed, from the step they stopped at. */
+export const resumeRun = (runId) => invoke("resume_run", { runId });
+/**
+ * Runs one instruction once without creating an agent; the run's `agent_id` is "quick-task".
+ * @param {string[]} allowedTools - tools the model may use; empty means it can only answer.
+ * @param {string[]} images - ids from `attachImage` for the model to look at.
+ */
+export const runQuickTask = (instruction, allowedTools = [], mode = "live", images = []) =>
+    invoke("run_quick_task", { instruction, allowedTools, images, mode });
+export const getRun = (id) => invoke("get_run", { id });
+export const listRuns = (agentId = null, limit = 50) => invoke("list_runs", { agentId, limit });
+
+// ── Regression Testing ──
+export const setGoldenRun = (runId, golden = true) => invoke("set_golden_run", { runId, golden });
+export const testAgent = (id) => invoke("test_agent", { id });
+
+// ── Scenario Fixtures ──
+/**
+ * @param {{path:string, content:string}[]} files - Sample files, e.g. `~/Downloads/invoice.txt`.
+ * @param {{from:string, to?:string, subject:string, body:string, date?:string}[]} emails - Fake inbox.
+ */
+export const createFixtureSet = (name, description, files = [], emails = []) =>
+    invoke("create_fixture_set", { name, description, files, emails });
+
+export const listFixtureSets = () => invoke("list_fixture_sets");
+/** Simulated runs operate on this set's scratch folder and inbox until it is unloaded. */
+export const loadFixtureSet = (id) => invoke("load_fixture_set", { id });
+export const unloadFixtureSet = () => invoke("unload_fixture_set");
+export const deleteFixtureSet = (id) => invoke("delete_fixture_set", { id });
+
+// ── Agent Sharing ──
+/** @returns {Promise<string>} Path of the encrypted bundle written to Downloads. */
+export const shareAgent = (id, passphrase) => invoke("share_agent", { id, passphrase });
+/** Returns `{draft, signer, signed_by_you, shared_at}`; save the reviewed draft with `saveAgentDraft`. */
+export const importSharedAgent = (path, passphrase) =>
+    invoke("import_shared_agent", { path, passphrase });
+
+// ── Community Templates ──
+// Opt-in: set `template_catalog_enabled` to "true" plus `template_catalog_url` and
+// `template_catalog_public_key` before syncing. Search and install only read the local cache.
+// Built-in templates, such as the meeting summarizer, are listed whether or not it is on.
+export const getTemplateCatalogStatus = () => invoke("get_template_catalog_status");
+export const syncTemplateCatalog = () => invoke("sync_template_catalog");
+export const searchTemplates = (query = "") => invoke("search_templates", { query });
+/** Returns a sandboxed draft; save it with `saveAgentDraft` after review. */
+export const installTemplate = (id) => invoke("install_template", { id });
+
+// ── Device Sync ──
+/**
+ * @param {Object} config
+ * @param {""|"folder"|"webdav"} config.target - `""` turns sync off.
+ * @param {string} [config.folder] - Folder kept in sync by Dropbox, Google Drive, etc.
+ * @param {string} [config.passphrase] - Same on every device; leave blank to keep the saved one.
+ */
+export const setSyncConfig = (config) => invoke("set_sync_config", { config });
+export const getSyncConfig = () => invoke("get_sync_config");
+/** Syncs agents, schedules and settings (never logs); returns counts and any conflict copies. */
+export const syncNow = () => invoke("sync_now");
+
+// ── Agent Import / Export ──
+export const exportAgents = (ids = null) => invoke("export_agents", { ids });
+/**
+ * @param {string} data - Text produced by `exportAgents`.
+ * @param {"add"|"merge"} [mode="add"]
+ * @param {Object<string, "keep_mine"|"take_theirs"|"duplicate">} [resolutions] - Keyed by incoming agent id.
+ * Returns `{applied:false}` with per-agent diffs until every conflict has a resolution.
+ */
+export const importAgents = (data, mode = "add", resolutions = null, dryRun = false) =>
+    invoke("import_agents", { data, mode, resolutions, dryRun });
+
+// ── Local API Tokens ──
+// The local HTTP API listens on 127.0.0.1 when the `local_api_enabled` setting is "true".
+/** @param {"admin"|"observer"} scope - Observers can only read status, runs and logs. */
+export const mintApiToken = (name, scope = "observer", expiresInDays = null) =>
+    invoke("mint_api_token", { name, scope, expiresInDays });
+export const listApiTokens = () => invoke("list_api_tokens");
+export const revokeApiToken = (id) => invoke("revoke_api_token", { id });
+
+// ── App Lock ──
+export const getLockStatus = () => invoke("get_lock_status");
+export const lockApp = () => invoke("lock_app");
+export const unlockApp = (pin) => invoke("unlock_app", { pin });
+/** Pass an empty `newPin` to remove the lock. */
+export const setAppLockPin = (currentPin, newPin) => invoke("set_app_lock_pin", { currentPin, newPin });
+/** Erases all agents, runs, logs and settings. `confirm` must be "RESET". */
+export const factoryReset = (confirm) => invoke("factory_reset", { confirm });
+
+// ── Privacy & Audit ──
+// Personal data in what network steps send is checked against the agent's `pii_policy`,
+// falling back to the `pii_default_policy` setting. Scanning is off when neither is set.
+/** @param {"allow"|"mask"|"block"|null} policy - `null` uses the default. */
+export const setAgentPiiPolicy = (agentId, policy) => invoke("set_agent_pii_policy", { agentId, policy });
+export const scanPii = (text) => invoke("scan_pii", { text });
+// Content read from files, the web or email is screened for prompt injection; flagged steps
+// carry an `injection` reason and are logged as `injection_suspected`. Setting
+// `injection_classifier` to "true" also asks the model about content the patterns pass.
+export const listAuditEvents = (kind = null, agentId = null, limit = 200) =>
+    invoke("list_audit_events", { kind, agentId, limit });
+
+// ── Network ──
+export const getNetworkMode = () => invoke("get_network_mode");
+/** While on, network tools, cloud models, template downloads and WebDAV sync are refused. */
+export const setLocalOnlyMode = (enabled) => invoke("set_local_only_mode", { enabled });
+/**
+ * Proxies apply to model providers, web tools, template downloads and WebDAV sync.
+ * Send `password` only to change it (an empty string removes it); it is kept in the OS keychain.
+ * Self-hosted gateways are set with the `llm_openai_base_url` / `llm_anthropic_base_url` settings.
+ */
+export const getProxySettings = () => invoke("get_proxy_settings");
+export const setProxySettings = (settings) => invoke("set_proxy_settings", { settings });
+
+// ── Response Cache ──
+// Identical model prompts and web fetches are answered from a local cache for
+// `response_cache_ttl_hours` (default 24), up to `response_cache_max_mb` (default 50).
+export const getCacheStats = () => invoke("get_cache_stats");
+/** @param {"llm"|"http"|null} [kind] - Omit to clear everything. */
+export const clearCache = (kind = null) => invoke("clear_cache", { kind });
+export const setAgentCacheEnabled = (agentId, enabled) => invoke("set_agent_cache_enabled", { agentId, enabled });
+
+// ── Knowledge (document search) ──
+// Text documents in knowledge folders are chunked and embedded; only new or changed
+// files are re-embedded on each index run.
+export const listKnowledgeSources = () => invoke("list_knowledge_sources");
+export const addKnowledgeSource = (path) => invoke("add_knowledge_source", { path });
+export const removeKnowledgeSource = (id) => invoke("remove_knowledge_source", { id });
+/**
+ * Starts a background index pass. Listen for `knowledge-index-progress`
+ * (`{ state, done, total, file }`) and `knowledge-index-finished` (the report).
+ * Indexing slows down while the app is focused or the machine is on battery.
+ */
+export const indexKnowledge = () => invoke("index_knowledge");
+export const pauseIndexing = () => invoke("pause_indexing");
+export const resumeIndexing = () => invoke("resume_indexing");
+/** Returns `{ indexed_files, pending_files, removed_files, chunk_count, last_indexed_at, ... }`. */
+export const getIndexStatus = () => invoke("get_index_status");
+export const searchKnowledge = (query, limit = 5) => invoke("search_knowledge", { query, limit });
+
+// ── Run Artifacts ──
+// Intermediate results, e.g. the per-chunk summaries made when a step's input
+// was too long for the model. Listings omit `content`; fetch one to read it.
+export const listRunArtifacts = (runId) => invoke("list_run_artifacts", { runId });
+export const getArtifact = (id) => invoke("get_artifact", { id });
+
+// ── Tokens ──
+/**
+ * Counts tokens for `model`, or for the active model (also returning its `context_window`).
+ * OpenAI models are counted exactly; others are estimated (`exact: false`).
+ */
+export const countTokens = (text, model = null) => invoke("count_tokens", { text, model });
+
+// ── Tool Calling ──
+// Lets the model pick an agent's steps from its tools, one at a time (up to 12 per run).
+// Each chosen step still goes through the usual network, privacy and approval checks;
+// the model's closing reply is recorded as a final `answer` step.
+export const setAgentToolCalling = (agentId, enabled) => invoke("set_agent_tool_calling", { agentId, enabled });
+
+// ── Personas ──
+// Reusable system prompts with a tone ("neutral" | "formal" | "friendly" | "concise").
+// Built-in personas have ids starting with "builtin:" and are read-only.
+export const listPersonas = () => invoke("list_personas");
+/** Pass `id` to update an existing persona; omit it to create one. */
+export const savePersona = ({ id = null, name, description = "", systemPrompt, tone = null }) =>
+    invoke("save_persona", { id, name, description, systemPrompt, tone });
+export const deletePersona = (id) => invoke("delete_persona", { id });
+/** Returns `{ persona_id, system_prompt, tone }`; `tone` overrides the library persona's. */
+export const getAgentPersona = (agentId) => invoke("get_agent_persona", { agentId });
+export const setAgentPersona = (agentId, persona) => invoke("set_agent_persona", { agentId, persona });
+/** The full system prompt the agent's model steps will receive. */
+export const previewAgentPrompt = (agentId) => invoke("preview_agent_prompt", { agentId });
+
+// ── Usage & Cost ──
+/**
+ * Estimated cost and duration of one run with the current model, e.g. `summary: "~$0.12, ~40s"`.
+ * `basis` is "history" (the agent's recent runs) or "plan" (its steps, for new agents).
+ * Prices can be overridden with the `llm_price_overrides` setting: `{"model-prefix": [in, out]}` USD per 1M tokens.
+ */
+export const estimateRunCost = (agentId) => invoke("estimate_run_cost", { agentId });
+/** This month's per-provider budgets: `{ provider, monthly_limit_usd, spent_usd, percent }`. */
+export const listBudgets = () => invoke("list_budgets");
+/**
+ * Sets a provider's monthly budget in USD; `null` removes it. A `budget-alert` event
+ * (`{ provider, threshold, title, message, ... }`) fires at 50, 80 and 100%.
+ */
+export const setBudget = (provider, monthlyLimitUsd) => invoke("set_budget", { provider, monthlyLimitUsd });
+/** @param {string|null} [month] - "YYYY-MM", default this month. */
+export const getMonthlyUsage = (month = null) => invoke("get_monthly_usage", { month });
+/** @param {"csv"|"json"} [format] */
+export const exportUsageReport = (month, format = "csv") => invoke("export_usage_report", { month, format });
+
+// ── Command Palette ──
+/**
+ * Ranked actions for the palette: `{ id, kind, title, subtitle, target, score }`, where `kind`
+ * is "run_agent" | "simulate_agent" | "open" (page or "settings/<section>") | "artifact".
+ */
+export const searchActions = (query, limit = 20) => invoke("search_actions", { query, limit });
+/** Call when the user picks an action so it ranks higher next time. */
+export const recordPaletteAction = (id) => invoke("record_palette_action", { id });
+
+// ── Folders ──
+/** All folders, flat: `{ id, name, parent_id, created_at }`. */
+export const listFolders = () => invoke("list_folders");
+/** @param {string|null} [parentId] - null for a top-level folder. */
+export const createFolder = (name, parentId = null) => invoke("create_folder", { name, parentId });
+export const renameFolder = (id, name) => invoke("rename_folder", { id, name });
+/** Moves a folder and its contents; refuses to move a folder into its own subfolder. */
+export const moveFolder = (id, parentId = null) => invoke("move_folder", { id, parentId });
+/** Deletes a folder; its agents and subfolders move up to its parent. */
+export const deleteFolder = (id) => invoke("delete_folder", { id });
+/** @param {string|null} folderId - null takes the agent out of any folder. */
+export const setAgentFolder = (agentId, folderId) => invoke("set_agent_folder", { agentId, folderId });
+/**
+ * Agents as a folder tree: `{ folder, agents, children }`, where the root has `folder: null`
+ * and holds unfiled agents. `sort` is as for `listAgents`.
+ */
+export const listAgentsByFolder = (sort = null) => invoke("list_agents_by_folder", { sort });
+
+// ── Bulk Operations ──
+/**
+ * Applies one operation to many agents at once, all or nothing. `op` is one of
+ * `{ op: "enable" }`, `{ op: "disable" }`, `{ op: "delete" }`,
+ * `{ op: "tag", add: [...], remove: [...] }` or `{ op: "move_to_folder", folder_id }`.
+ * Returns `{ applied, items: [{ id, ok, error }] }`; when `applied` is false nothing changed.
+ * Disabled agents still run by hand but not from automatic triggers; tags live under
+ * `tags` in the agent's config.
+ */
+export const bulkAgentOperation = (ids, op) => invoke("bulk_agent_operation", { ids, op });
+
+// ── Run Notes ──
+/** Attaches a note to a run (shown as `run.note`); an empty note removes it. */
+export const annotateRun = (runId, note) => invoke("annotate_run", { runId, note });
+/** Attaches a note to a log entry (shown as `log.note`); an empty note removes it. */
+export const annotateLog = (logId, note) => invoke("annotate_log", { logId, note });
+/**
+ * Full-text search over notes: `{ id, kind: "run"|"log", target, agent_id, agent_name, note,
+ * snippet, updated_at }`, best match first. `snippet` marks matches with `[` and `]`.
+ */
+export const searchNotes = (query, limit = 20) => invoke("search_notes", { query, limit });
+
+// ── Transcripts ──
+/**
+ * A shareable document of one run: summary, steps with redacted inputs, outputs and timings,
+ * and artifacts. Secret-looking params and personal data in inputs are masked.
+ * @param {"markdown"|"html"} [format]
+ * @returns {Promise<string>}
+ */
+export const exportRunTranscript = (runId, format = "markdown") => invoke("export_run_transcript", { runId, format });
+
+// ── Feedback ──
+/**
+ * Thumbs up (`positive: true`) or down on a "run", "artifact" or builder "draft" (session id).
+ * Rating the same thing again replaces the earlier rating.
+ */
+export const submitFeedback = (kind, targetId, positive, comment = null) =>
+    invoke("submit_feedback", { kind, targetId, positive, comment });
+export const clearFeedback = (kind, targetId) => invoke("clear_feedback", { kind, targetId });
+/** The current rating `{ rating: 1|-1, comment, ... }`, or null. */
+export const getFeedback = (kind, targetId) => invoke("get_feedback", { kind, targetId });
+/** Per-agent `{ agent_id, agent_name, up, down, satisfaction, recent_comments }`, least satisfied first. */
+export const listAgentFeedback = () => invoke("list_agent_feedback");
+export const getAgentFeedback = (agentId) => invoke("get_agent_feedback", { agentId });
+
+// ── Agent Improvements ──
+/**
+ * Asks the model for changes based on the agent's feedback and failed runs:
+ * `{ id, summary, changes: [{ field, current, proposed, reason, diff }], status }`.
+ * `field` is "goal", "role", "system_prompt", "tone" or "step:<n>:prompt". Nothing changes
+ * until the suggestion is applied.
+ */
+export const suggestAgentImprovements = (agentId) => invoke("suggest_agent_improvements", { agentId });
+export const listAgentSuggestions = (agentId) => invoke("list_agent_suggestions", { agentId });
+/** @param {string[]|null} [fields] - apply only these changes; null applies all of them. */
+export const applyAgentSuggestion = (id, fields = null) => invoke("apply_agent_suggestion", { id, fields });
+export const dismissAgentSuggestion = (id) => invoke("dismiss_agent_suggestion", { id });
+
+// ── Dashboard Stats ──
+/** Minutes of manual work one run of the agent saves; `null` clears it. */
+export const setAgentTimeSaved = (agentId, minutes) => invoke("set_agent_time_saved", { agentId, minutes });
+/**
+ * `{ minutes_saved_this_month, minutes_saved_total, runs_this_month, current_streak_days,
+ * longest_streak_days, top_agents: [{ agent_id, name, runs, minutes }] }`. Counts successful
+ * live runs on every synced device; the history travels with sync.
+ */
+export const getDashboardStats = () => invoke("get_dashboard_stats");
+
+// ── Windows ──
+/**
+ * Opens the approvals panel or live log in its own window (`kind` "approvals" | "logs"),
+ * loading `index.html#/<kind>`. Position and size are remembered per window.
+ */
+export const openWindow = (kind) => invoke("open_window", { kind });
+/**
+ * Called from a secondary window to choose which events it receives:
+ * "approvals-changed", "logs-changed" and/or "run-updated" (`{ run_id, agent_id, status }`).
+ */
+export const subscribeWindowEvents = (events) => invoke("subscribe_window_events", { events });
+/** Remembers the main window's current view (e.g. "agents", "settings/model") for the next start. */
+export const setLastView = (view) => invoke("set_last_view", { view });
+/** `{ x, y, width, height, maximized, view }`; open `view` on startup when it isn't empty. */
+export const getMainWindowState = () => invoke("get_main_window_state");
+
+// ── Appearance ──
+/**
+ * `{ theme, accent_color, font_scale, effective_theme }`. `theme` is "light" | "dark" | "system";
+ * `effective_theme` is what to render, with "system" resolved from the OS.
+ */
+export const getAppearance = () => invoke("get_appearance");
+/**
+ * Saves `{ theme, accent_color ("#rrggbb" or ""), font_scale (0.8–1.6) }`. Every window gets an
+ * "appearance-changed" event with the new appearance, also sent when the OS theme flips.
+ */
+export const setAppearance = (settings) => invoke("set_appearance", { settings });
+
+// ── Accessibility ──
+/**
+ * OS accessibility preferences: `{ reduced_motion, high_contrast, screen_reader }`. All false
+ * until the first reading; "accessibility-changed" carries the new values whenever one flips.
+ */
+export const getAccessibility = () => invoke("get_accessibility");
+
+// ── Formatting ──
+/** Locale used for dates and numbers: the "locale" setting (e.g. "de-DE"), else the OS locale. */
+export const getLocale = () => invoke("get_locale");
+/**
+ * Formats stored timestamps for display, in local time:
+ * `[{ timestamp, relative ("3 minutes ago"), date, date_time }]`, in the order given.
+ */
+export const formatTimes = (timestamps) => invoke("format_times", { timestamps });
+/** Formats numbers with the locale's separators, e.g. "1,234.5" or "1.234,5". */
+export const formatNumbers = (values, decimals = 0) => invoke("format_numbers", { values, decimals });
+
+// ── Database Maintenance ──
+/**
+ * Compacts and tunes the database now. Resolves to
+ * `{ ran_at, automatic, size_before, size_after, duration_ms, fts_tables }` (sizes in bytes).
+ */
+export const runDbMaintenance = () => invoke("run_db_maintenance");
+/**
+ * The last pass, or `null`. Passes also run by themselves every "db_maintenance_interval_days"
+ * (default 7, 0 = off) while the app is idle, each followed by a "db-maintenance-finished" event.
+ */
+export const getDbMaintenanceStatus = () => invoke("get_db_maintenance_status");
+
+// ── Storage ──
+/**
+ * `{ total_bytes, database_bytes, tables: [{ name, bytes }], other_files_bytes,
+ * categories: [{ id, label, bytes, items, cleanable }] }`.
+ */
+export const getStorageReport = () => invoke("get_storage_report");
+/**
+ * Cleans a cleanable category ("cache", "artifacts", "logs" or "recordings"), keeping entries newer than
+ * `olderThanDays` (default 30). Resolves to `{ category, removed, size_before, size_after }`.
+ */
+export const cleanStorage = (category, olderThanDays = null) =>
+    invoke("clean_storage", { category, olderThanDays });
+
+// ── Artifact Cleanup ──
+/**
+ * Artifacts left behind by deleted runs or agents: `{ artifacts, bytes, deleted }`.
+ * Only a dry run by default; pass `false` to delete them.
+ */
+export const findOrphanedArtifacts = (dryRun = true) => invoke("find_orphaned_artifacts", { dryRun });
+
+// ── Data Directory ──
+/** Folder holding the database and other app data. */
+export const getDataDirectory = () => invoke("get_data_directory");
+/**
+ * Moves the app data to an empty folder, e.g. on another drive, and switches to it without a
+ * restart. Resolves to `{ old_path, new_path, bytes_copied }`; the old folder is kept until
+ * the user deletes it.
+ */
+export const setDataDirectory = (path) => invoke("set_data_directory", { path });
+/**
+ * True when launched with `--portable` or with a file named `portable` beside the app: all data
+ * then stays in the `data` folder next to it and can't be moved elsewhere.
+ */
+export const getPortableMode = () => invoke("get_portable_mode");
+
+// ── Diagnostics ──
+/**
+ * Startup audit of whether this account's data is kept from other OS accounts:
+ * `[{ check, status ("ok" | "repaired" | "warning"), detail }]`.
+ */
+export const getIsolationReport = () => invoke("get_isolation_report");
+
+// ── Supervised Mode ──
+/** `{ has_pin, suspended_until, policy: { enabled, allowed_tools, allowed_domains } }`. */
+export const getSupervisorStatus = () => invoke("get_supervisor_status");
+/** Sets or changes the supervisor PIN; an empty `newPin` removes it once supervised mode is off. */
+export const setSupervisorPin = (currentPin, newPin) =>
+    invoke("set_supervisor_pin", { currentPin, newPin });
+/**
+ * Replaces the policy with the supervisor PIN. While enabled, steps using other tools or
+ * sites fail and are recorded in the audit trail as "supervisor_blocked".
+ */
+export const setSupervisorPolicy = (pin, policy) => invoke("set_supervisor_policy", { pin, policy });
+/** Lifts the restrictions for `minutes` (0 resumes them now). */
+export const suspendSupervision = (pin, minutes) => invoke("suspend_supervision", { pin, minutes });
+
+// ── Managed Policy ──
+/**
+ * The administrator's policy file, if any: `{ path, managed, error, allowed_providers,
+ * disabled_tools, local_only, log_retention_days, locked_settings }`. Show locked settings as
+ * read-only; changing them fails with "managed by your organization".
+ */
+export const getEffectivePolicy = () => invoke("get_effective_policy");
+
+// ── Audit Export ──
+/**
+ * Writes audit events in `range` (`{ from, to }`, RFC 3339, either optional) to `path` as a
+ * hash-chained JSONL file plus a detached signature at `<path>.sig`. Resolves to
+ * `{ path, signature_path, events, head_hash, signer }`.
+ */
+export const exportAuditLog = (path, range = null) => invoke("export_audit_log", { range, path });
+/** Checks an export against its `.sig` file: `{ valid, problem, events, signer, signed_by_you }`. */
+export const verifyAuditExport = (path) => invoke("verify_audit_export", { path });
+
+// ── Data Sensitivity ──
+/**
+ * Labels an agent "public", "internal" or "confidential". Confidential agents only use the
+ * local model and can't send data outside this computer.
+ */
+export const setAgentSensitivity = (agentId, sensitivity) =>
+    invoke("set_agent_sensitivity", { agentId, sensitivity });
+/** `[{ id, name, sensitivity }]`, optionally only agents with the given label. */
+export const listAgentSensitivity = (sensitivity = null) => invoke("list_agent_sensitivity", { sensitivity });
+
+// ── Model Capabilities ──
+/**
+ * What a model supports: `{ provider, model, streaming, tools, vision, json_mode,
+ * context_window, overridden }`. Defaults to the active provider and model. Features a model
+ * lacks are worked around in the prompt rather than failing the run.
+ */
+export const getModelCapabilities = (provider = null, model = null) =>
+    invoke("get_model_capabilities", { provider, model });
+/**
+ * Corrects the assumptions for a model, e.g. `{ tools: true, context_window: 32768 }` for a
+ * local model. Pass `null` to go back to the built-in values.
+ */
+export const setModelCapabilities = (provider, model, overrides) =>
+    invoke("set_model_capabilities", { provider, model, overrides });
+
+// ── Image Attachments ──
+/**
+ * Attaches an image for chat or an `llm_prompt` step's `images`: a dropped file by `path`, or
+ * a pasted screenshot as base64 or a data URL in `data`. Large screenshots are scaled down.
+ * Resolves to `{ id, name, media_type, width, height, bytes, downscaled, estimated_tokens }`.
+ */
+export const attachImage = ({ path = null, data = null, name = null }) =>
+    invoke("attach_image", { name, path, data });
+/** The attachment as a data URL, for a preview. */
+export const getImageAttachment = (id) => invoke("get_image_attachment", { id });
+export const deleteImageAttachment = (id) => invoke("delete_image_attachment", { id });
+
+// ── Audio Transcription ──
+/**
+ * Transcribes a recording (mp3, m4a, wav, ogg, webm, flac; up to 25 MB) with OpenAI's Whisper
+ * or a local Whisper server at the `transcription_local_url` setting. Resolves to `{ path,
+ * provider, language, duration_seconds, segments: [{ start, end, text }], text }`; the
+ * timestamped text is also kept as a "transcript" artifact with no run (run id "").
+ */
+export const transcribeAudio = (path) => invoke("transcribe_audio", { path });
+
+// ── Meeting Notes ──
+/**
+ * Transcribes a meeting recording, summarizes it and files the notes under `Meetings/` in the
+ * notes folder (`notes_vault_dir` setting), adding the action items to its `Tasks.md`.
+ * Resolves to `{ title, summary, decisions, action_items: [{ task, owner, due }], note_path,
+ * tasks_path, duration_seconds }`. The same pipeline is the "Meeting summarizer" template.
+ */
+export const summarizeMeeting = (path, title = null) => invoke("summarize_meeting", { path, title });
+
+// ── Audio Recording ──
+// Needs ffmpeg (or the `ffmpeg_path` setting). Listen for "recording-state" to show the
+// recording indicator and "recording-level" (`{ id, level_db }`) for the level meter.
+/**
+ * Starts recording "microphone" or "system" audio. Stops by itself at `recording_max_minutes`
+ * (default 120) or `recording_max_mb` (default 200). Resolves to the recording status.
+ */
+export const startRecording = (source = "microphone") => invoke("start_recording", { source });
+/**
+ * Stops and files the recording: `{ id, source, path, started_at, duration_seconds, bytes,
+ * stopped_by, error }`. Pass `path` to `transcribeAudio` or `summarizeMeeting`.
+ */
+export const stopRecording = () => invoke("stop_recording");
+/** `{ recording, id, source, started_at, elapsed_seconds, bytes, max_seconds, max_bytes, level_db }`. */
+export const getRecordingStatus = () => invoke("get_recording_status");
+
+// ── Blackout Windows ──
+/**
+ * Windows when agents must not run, for one agent plus the global ones (or all without an
+ * id): `[{ id, agent_id, label, days, date, start, end }]`; `agent_id` "" means every agent.
+ */
+export const listBlackoutWindows = (agentId = null) => invoke("list_blackout_windows", { agentId });
+/**
+ * Adds a window, e.g. `{ label: "Standup", days: ["mon", "tue", "wed", "thu", "fri"],
+ * start: "09:00", end: "10:00" }`, or a one-off with `date: "2024-05-31"`. Leave out
+ * `agent_id` for all agents. Only automatic runs are held; starting one by hand still works.
+ */
+export const addBlackoutWindow = (window) => invoke("add_blackout_window", { window });
+export const deleteBlackoutWindow = (id) => invoke("delete_blackout_window", { id });
+/** "defer" (run once the window ends, the default) or "skip". */
+export const setAgentCatchUp = (agentId, policy) => invoke("set_agent_catch_up", { agentId, policy });
+/** `{ blocked, window, event, until, catch_up }`; `event` is the meeting being sat out. */
+export const getBlackoutStatus = (agentId) => invoke("get_blackout_status", { agentId });
+export const listDeferredRuns = () => invoke("list_deferred_runs");
+export const cancelDeferredRun = (id) => invoke("cancel_deferred_run", { id });
+
+// ── Calendar ──
+
+/** `{ sources, events, fetched_at, error }`. */
+export const getCalendarStatus = () => invoke("get_calendar_status");
+/**
+ * Sets the calendar feeds (ICS addresses or .ics files) and reads them right away.
+ * @param {string[]} sources
+ */
+export const setCalendarSources = (sources) => invoke("set_calendar_sources", { sources });
+export const refreshCalendar = () => invoke("refresh_calendar");
+/** Events over the next `hours` (24 by default). */
+export const listCalendarEvents = (hours = null) => invoke("list_calendar_events", { hours });
+/**
+ * Keeps an agent's automatic runs out of meetings whose title contains `filter`
+ * (`*` for any meeting, `|` between alternatives); `null` clears it.
+ */
+export const setAgentEventFilter = (agentId, filter) => invoke("set_agent_event_filter", { agentId, filter });
+
+// ── Network profiles ──
+
+/** `{ ssid, domains, profile_id, profile_name }`; also sent as the `profile-changed` event. */
+export const getCurrentProfile = () => invoke("get_current_profile");
+export const listNetworkProfiles = () => invoke("list_network_profiles");
+/**
+ * Creates or updates a profile.
+ * @param {{ id?: string, name: string, ssids?: string[], domains?: string[], settings?: object }} profile
+ */
+export const saveNetworkProfile = (profile) => invoke("save_network_profile", { profile });
+export const deleteNetworkProfile = (id) => invoke("delete_network_profile", { id });
+/** Profile ids in which the agent doesn't run automatically. */
+export const setAgentProfiles = (agentId, offIn) => invoke("set_agent_profiles", { agentId, offIn });
+
+// ── Pending actions ──
+
+/**
+ * Approved irreversible steps counting down before they run (`action_grace_seconds`,
+ * 10 by default) or scheduled for later (`send_at` is set). Refresh on the
+ * `pending-actions-changed` event.
+ */
+export const listPendingActions = () => invoke("list_pending_actions");
+/** Stops a step before its countdown ends, like "undo send". */
+export const cancelPendingAction = (id) => invoke("cancel_pending_action", { id });
+/**
+ * Moves a scheduled send (a step with `send_at`) to another local time.
+ * @param {string} sendAt e.g. "tomorrow 08:00" or "2024-06-01 08:00"
+ */
+export const reschedulePendingAction = (id, sendAt) => invoke("reschedule_pending_action", { id, sendAt });
+
+// ── Context variables ──
+
+/**
+ * Live values usable as `{{name}}` in step params and prompts: today, now, weekday,
+ * user_name, and the sensitive clipboard, selection and active_window_title.
+ */
+export const listContextProviders = () => invoke("list_context_providers");
+/** Sensitive providers an agent may read, e.g. `["clipboard"]`. */
+export const setAgentContextProviders = (agentId, allowed) => invoke("set_agent_context_providers", { agentId, allowed });
+
+// ── App activity ──
+
+/** `{ enabled, retention_days }`. Tracking is off until the user turns it on. */
+export const getActivitySettings = () => invoke("get_activity_settings");
+export const setActivitySettings = (settings) => invoke("set_activity_settings", { settings });
+/** Time per app over the last `days`, most used first. */
+export const getAppUsage = (days = null) => invoke("get_app_usage", { days });
+export const clearAppUsage = () => invoke("clear_app_usage");
+/**
+ * Apps that bring up an agent when they come to the front. Without `auto_run` the
+ * `app-trigger-offer` event is sent so the agent can be offered instead.
+ * @param {{ apps: string[], auto_run?: boolean }} trigger
+ */
+export const setAgentAppTrigger = (agentId, trigger) => invoke("set_agent_app_trigger", { agentId, trigger });
+
+// ── Focus mode ──
+
+/** `{ domains, apps }` blocked during focus sessions. */
+export const getFocusBlocklist = () => invoke("get_focus_blocklist");
+export const setFocusBlocklist = (blocklist) => invoke("set_focus_blocklist", { blocklist });
+/** `{ active, ends_at, remaining_seconds, strict, blocklist, hosts_applied }`; also the `focus-changed` event. */
+export const getFocusStatus = () => invoke("get_focus_status");
+/** Strict sessions can only be ended early with the emergency unlock. */
+export const startFocusSession = (minutes, strict = false) => invoke("start_focus_session", { minutes, strict });
+export const stopFocusSession = () => invoke("stop_focus_session");
+export const emergencyUnlockFocus = () => invoke("emergency_unlock_focus");
+
+// ── Weekly review ──
+
+/**
+ * Resumes this week's unfinished review or compiles a new one: the week's runs, waiting
+ * approvals, stale drafts and suggested changes. Offered on `week_review_day` with the
+ * `week-review-due` event.
+ */
+export const startWeekReview = () => invoke("start_week_review");
+export const getWeekReview = (id) => invoke("get_week_review", { id });
+export const listWeekReviews = () => invoke("list_week_reviews");
+/** `step` is runs, approvals, drafts, suggestions, or done to finish. */
+export const setWeekReviewStep = (id, step) => invoke("set_week_review_step", { id, step });
+export const decideReviewSuggestion = (id, suggestionId, apply) => invoke("decide_review_suggestion", { id, suggestionId, apply });
+
+// ── Approval routing ──
+
+/**
+ * Routes send approvals of some action types to another person over email, Telegram or
+ * Slack, with one-time approve/reject links answered through the local API (or the
+ * `approval_link_base_url` setting). Route: { id?, approver, actionTypes, channel, target, expiresHours? }.
+ */
+export const listApprovalRoutes = () => invoke("list_approval_routes");
+export const saveApprovalRoute = ({ id = "", approver, actionTypes, channel, target, expiresHours = null }) =>
+    invoke("save_approval_route", {
+        route: { id, approver, action_types: actionTypes, channel, target, expires_hours: expiresHours },
+    });
+export const deleteApprovalRoute = (id) => invoke("delete_approval_route", { id });
+
+// ── Phone companion ──
+
+/**
+ * Serves a small approvals and runs page to phones on the same network over HTTPS with a
+ * self-signed certificate. Returns { url, qrSvg, fingerprint, expiresAt } (snake_case from
+ * Rust); the code works once, for ten minutes.
+ */
+export const startCompanionPairing = () => invoke("start_companion_pairing");
+export const getCompanionStatus = () => invoke("get_companion_status");
+export const revokeCompanionDevice = (id) => invoke("revoke_companion_device", { id });
+export const stopCompanion = () => invoke("stop_companion");
+
+// ── Discovery ──
+
+/**
+ * Advertises the phone view on the local network over mDNS (`_openclaw._tcp`) while it is
+ * running, so phones and the CLI find it without an address. On by default.
+ */
+export const getDiscoverySettings = () => invoke("get_discovery_settings");
+/** `name` is what phones list this computer as; an empty string goes back to the default. */
+export const setDiscoverySettings = (enabled, name = null) => invoke("set_discovery_settings", { enabled, name });
+
+// ── Full backup ──
+
+/**
+ * Writes everything the app keeps (database, data folder files, settings and the list of
+ * installed plugins) to one archive encrypted with `passphrase`. Keychain passwords are not
+ * included and need entering again after a restore.
+ */
+export const exportEverything = (path, passphrase) => invoke("export_everything", { path, passphrase });
+/** What an archive holds, without restoring it. */
+export const inspectArchive = (path, passphrase) => invoke("inspect_archive", { path, passphrase });
+/**
+ * Replaces this machine's data with the archive's; the old database is kept beside it. Returns
+ * { summary, previous_database, files_restored, missing_plugins }. Restart the app afterwards.
+ */
+export const importEverything = (path, passphrase) => invoke("import_everything", { path, passphrase });
+
+// ── Diagnostics ──
+
+/**
+ * Writes a JSON report to attach to bug reports: logs, database layout and recent runs, with
+ * emails, paths, links and the user's own text replaced by placeholders like `<email-1>`.
+ * Returns { path, tables, logs, runs, replaced }.
+ */
+export const exportAnonymizedDiagnostics = (path) => invoke("export_anonymized_diagnostics", { path });
+
+// ── Crash reports ──
+
+/**
+ * Panics, and sessions that ended without shutting down, are saved on this computer only.
+ * Newest first: [{ id, kind, created_at, message, location, backtrace, uploaded_at }].
+ */
+export const listCrashReports = () => invoke("list_crash_reports");
+export const deleteCrashReport = (id) => invoke("delete_crash_report", { id });
+/** Uploading is off until turned on with an https:// endpoint. */
+export const getCrashUploadSettings = () => invoke("get_crash_upload_settings");
+export const setCrashUploadSettings = (enabled, endpoint) => invoke("set_crash_upload_settings", { enabled, endpoint });
+/** Shows exactly what would be sent: { endpoint, payload, digest }. */
+export const previewCrashUpload = (id) => invoke("preview_crash_upload", { id });
+/** Pass the `digest` from the preview the user looked at. */
+export const uploadCrashReport = (id, digest) => invoke("upload_crash_report", { id, digest });
+
+// ── Sleep and wake ──
+
+/**
+ * Times the computer was asleep, newest first: [{ id, suspended_at, resumed_at, seconds }].
+ * Countdowns before irreversible steps are moved on by the time asleep, run durations leave
+ * it out, and a `system-resumed` event fires on waking.
+ */
+export const listSuspensions = (limit = 50) => invoke("list_suspensions", { limit });
+
+// ── Notifications ──
+
+/**
+ * What live runs notify about and where: { on_success, on_failure, on_approval, channel
+ * ("desktop" | "telegram" | "slack"), target, quiet_start, quiet_end ("HH:MM" or "") }.
+ */
+export const getNotificationSettings = () => invoke("get_notification_settings");
+export const setNotificationSettings = (settings) => invoke("set_notification_settings", { settings });
+/**
+ * One agent's overrides; unset fields follow the global settings. Prefs: { on_success?,
+ * on_failure?, on_approval?, channel?, target?, ignore_quiet_hours }, or null to clear them.
+ */
+export const getAgentNotifications = (agentId) => invoke("get_agent_notifications", { agentId });
+export const setAgentNotifications = (agentId, prefs) => invoke("set_agent_notifications", { agentId, prefs });
+
+// ── Download rules ──
+
+/**
+ * Rules the "Organize my downloads" template tidies by, in order; the first enabled rule
+ * that matches a file wins: [{ id, name, enabled, extensions, domain, pattern,
+ * min_age_days, action ("move" | "delete" | "keep"), destination, position }].
+ */
+export const listDownloadRules = () => invoke("list_download_rules");
+/** Adds the rule at the end, or replaces the one with the same id. */
+export const saveDownloadRule = (rule) => invoke("save_download_rule", { rule });
+export const deleteDownloadRule = (id) => invoke("delete_download_rule", { id });
+export const reorderDownloadRules = (ids) => invoke("reorder_download_rules", { ids });
+/**
+ * Dry run over a folder (Downloads by default) without touching anything: [{ path, rule_id,
+ * rule_name, action, to, source }]. Pass `rule` to try one rule before saving it.
+ */
+export const testDownloadRules = (folder = null, rule = null) => invoke("test_download_rules", { folder, rule });
+
+// ── Document templates ──
+
+/**
+ * Templates the generate_document tool fills: [{ id, name, description, format ("pdf" |
+ * "docx"), file_name, body, created_at, updated_at }]. Bodies use {{placeholders}},
+ * {{#list}}…{{/list}} for repeated rows, and "# ", "## ", "---" and "| a | b |" lines for
+ * a title, headings, rules and tables.
+ */
+export const listDocumentTemplates = () => invoke("list_document_templates");
+/** Adds the template, or replaces the one with the same id; names must be unique. */
+export const saveDocumentTemplate = (template) => invoke("save_document_template", { template });
+export const deleteDocumentTemplate = (id) => invoke("delete_document_template", { id });
+/**
+ * Fills a template, saved or not, with sample data without writing anything:
+ * { text, file_name, missing } where `missing` lists placeholders the data lacked.
+ */
+export const previewDocument = (template, data) => invoke("preview_document", { template, data });
+
+// ── Receipts ──
+
+/**
+ * Reads a receipt or invoice (PDF or photo) and adds its vendor, date, total, tax and
+ * currency to the ledger CSV (setting "receipt_ledger_path", ~/Documents/Receipts.csv by
+ * default). Returns the run; when the model is unsure of a field the entry waits for
+ * approval, showing which fields to check.
+ */
+export const processReceipt = (path) => invoke("process_receipt", { path });
+
+// ── Currency rates ──
+
+/**
+ * Exchange rates the convert tool uses, cached and refreshed twice a day from the ECB (or
+ * the "currency_rates_url" setting): { url, as_of, source, fetched_at, error, rates: [{
+ * code, per_euro }] }. Until a fetch succeeds `source` is "built-in" and the rates are an
+ * approximate snapshot shipped with the app.
+ */
+export const getCurrencyRates = () => invoke("get_currency_rates");
+export const refreshCurrencyRates = () => invoke("refresh_currency_rates");
+
+// ── Translation ──
+
+/**
+ * Translates text into `target` (a language name or code such as "English" or "de"):
+ * { translation, source_language, source_code, target_language, local_fallback }. The
+ * source language is detected; `local_fallback` is set when the configured provider failed
+ * and the local model translated instead.
+ */
+export const translateText = (text, target) => invoke("translate_text", { text, target });
+
+// ── Data sources ──
+
+/**
+ * SQLite files and CSV files or folders agents can query with read-only SQL:
+ * [{ id, name, kind: "sqlite" | "csv", path, masked_columns, row_limit, created_at, updated_at }].
+ * Masked columns, given as "column" or "table.column", always read as null.
+ */
+export const listDataSources = () => invoke("list_data_sources");
+export const saveDataSource = (source) => invoke("save_data_source", { source });
+export const deleteDataSource = (id) => invoke("delete_data_source", { id });
+/** The source's tables: [{ name, columns, masked, rows }]; `rows` is -1 for views. */
+export const describeDataSource = (id) => invoke("describe_data_source", { id });
+/** Runs a query as an agent would: { columns, rows, truncated, masked_columns }. */
+export const queryDataSource = (id, sql) => invoke("query_data_source", { id, sql });
+
+// ── Secure notes ──
+
+/**
+ * Passwords and other secrets kept encrypted, with a key in the system keychain:
+ * [{ id, title, username, url, created_at, updated_at }]. Secrets are never listed.
+ */
+export const listSecureNotes = () => invoke("list_secure_notes");
+/** Leave `secret` out to change only the title, username or url of an existing note. */
+export const saveSecureNote = (note, secret) => invoke("save_secure_note", { note, secret });
+/** The note's secret; needs the app lock PIN when one is set. */
+export const revealSecureNote = (id, pin) => invoke("reveal_secure_note", { id, pin });
+export const deleteSecureNote = (id) => invoke("delete_secure_note", { id });
+/** A random password, 20 characters with symbols unless told otherwise. */
+export const generatePassword = (length, symbols) => invoke("generate_password", { length, symbols });
+
+// ── Two-factor codes ──
+
+/**
+ * Accounts with time-based (TOTP) two-factor codes:
+ * [{ id, name, issuer, algorithm, digits, period, created_at }]. Setup keys stay in the system keychain.
+ */
+export const listTotpAccounts = () => invoke("list_totp_accounts");
+/** `secret` is the site's setup key or the otpauth:// link from its QR code; details in the link fill in blanks in `account`. */
+export const addTotpAccount = (account, secret) => invoke("add_totp_account", { account, secret });
+export const deleteTotpAccount = (id) => invoke("delete_totp_account", { id });
+/** The account's current code, { code, expires_in }; always needs the app lock PIN. */
+export const getTotpCode = (account, pin) => invoke("get_totp_code", { account, pin });
+
+// ── Connections ──
+
+/**
+ * Outside services the app is connected to, with the result of their last check:
+ * [{ id, kind: "ai" | "calendar" | "sync", name, detail, status, error, checked_at }].
+ * `status` is "ok", "error", "needs_sign_in" (the saved key or password was refused) or "untested".
+ */
+export const listConnections = () => invoke("list_connections");
+/** Checks a connection with its saved settings; resolves to its updated entry. */
+export const testConnection = (id) => invoke("test_connection", { id });
+/** Disconnects a service and deletes the key or password saved for it. */
+export const disconnectConnection = (id) => invoke("disconnect_connection", { id });
+
+// ── Schedules ──
+
+/**
+ * Schedules that start agents, soonest first:
+ * [{ id, agent_id, cron_expr, description, enabled, last_run, next_run, updated_at }].
+ * Pass an agent id for just its schedules.
+ */
+export const listSchedules = (agentId) => invoke("list_schedules", { agentId });
+/** `cronExpr` is a five-field cron expression in local time, e.g. "0 8 * * 1-5", or @daily style. */
+export const createSchedule = (agentId, cronExpr, description) => invoke("create_schedule", { agentId, cronExpr, description });
+/** Turning a schedule back on starts from now; runs it missed aren't made up. */
+export const toggleSchedule = (id, enabled) => invoke("toggle_schedule", { id, enabled });
+export const deleteSchedule = (id) => invoke("delete_schedule", { id });